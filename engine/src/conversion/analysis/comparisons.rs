@@ -0,0 +1,134 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, References},
+        apivec::ApiVec,
+    },
+    minisyn::{FnArg, ReturnType},
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// The name of the method synthesized for each type listed in `ord!`.
+/// The Rust `Ord` implementation for the type calls it.
+pub(crate) const COMPARE_METHOD_NAME: &str = "cpp_compare";
+
+/// The name of the method synthesized for each type listed in
+/// `partial_ord!`. The Rust `PartialOrd` implementation for the type
+/// calls it.
+pub(crate) const PARTIAL_COMPARE_METHOD_NAME: &str = "cpp_partial_compare";
+
+/// The name of the method synthesized for each type listed in
+/// `partial_eq!`. The Rust `PartialEq` implementation for the type calls it.
+pub(crate) const EQ_METHOD_NAME: &str = "cpp_eq";
+
+/// bindgen tells us nothing about comparison operators, so for each type
+/// listed in `ord!` we synthesize a method which performs a three-way
+/// comparison using its `operator<=>`, if it has one, or else its
+/// `operator<`. Rust's `Ord` (and therefore `PartialOrd`, `Eq` and
+/// `PartialEq`) is then implemented entirely in terms of that method, so
+/// that equality is always consistent with the ordering. Types listed in
+/// `partial_ord!` instead get a method which may also find two objects
+/// unordered, and only `PartialOrd`. Similarly, for each type listed in
+/// `partial_eq!` or `partial_ord!` we synthesize a method which uses its
+/// `operator==`, whether that's a member or a free function, and Rust's
+/// `PartialEq` is implemented using that.
+pub(crate) fn add_comparisons(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            let cpp_name = name.name.to_cpp_name();
+            if config.is_ordered(&cpp_name) {
+                results.push(create_comparison(
+                    &name.name,
+                    "compare",
+                    COMPARE_METHOD_NAME,
+                    parse_quote! { -> ::std::os::raw::c_int },
+                    CppFunctionBody::SpaceshipCall,
+                    "Compares using the C++ `operator<=>`, or `operator<`, returning -1, \
+                    0 or 1 as `self` is less than, equivalent to or greater than \
+                    `other`. This is what the `Ord` implementation uses.",
+                ));
+            }
+            if config.is_partial_ord(&cpp_name) {
+                results.push(create_comparison(
+                    &name.name,
+                    "partial_compare",
+                    PARTIAL_COMPARE_METHOD_NAME,
+                    parse_quote! { -> ::std::os::raw::c_int },
+                    CppFunctionBody::PartialSpaceshipCall,
+                    "Compares using the C++ `operator<=>`, or `operator<` and \
+                    `operator==`, returning -1, 0 or 1 as `self` is less than, equal to \
+                    or greater than `other`, or 2 if they're unordered. This is what the \
+                    `PartialOrd` implementation uses.",
+                ));
+            }
+            if config.is_partial_eq(&cpp_name) {
+                results.push(create_comparison(
+                    &name.name,
+                    "eq",
+                    EQ_METHOD_NAME,
+                    parse_quote! { -> bool },
+                    CppFunctionBody::EqualityCall,
+                    "Compares using the C++ `operator==`. This is what the `PartialEq` \
+                    implementation uses.",
+                ));
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_comparison(
+    ty: &QualifiedName,
+    suffix: &str,
+    method_name: &str,
+    output: ReturnType,
+    payload: CppFunctionBody,
+    doc: &str,
+) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_{suffix}", ty.get_final_item())),
+    );
+    let typ = ty.to_type_path();
+    let this: FnArg = parse_quote! {
+        this: *const #typ
+    };
+    let other: FnArg = parse_quote! {
+        other: *const #typ
+    };
+    let mut references = References::new_with_this_as_reference();
+    references.ref_params.insert(make_ident("other"));
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            references,
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(method_name),
+                [this, other].into_iter().collect(),
+                output,
+                payload,
+            )
+        }),
+        analysis: (),
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{CompoundAssign, IncludeCppConfig};
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, References},
+        apivec::ApiVec,
+    },
+    known_types::known_types,
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// The name of the method synthesized for a `compound_assign!` operator,
+/// for example `cpp_add_assign` for `operator+=`. The Rust trait
+/// implementation calls it.
+pub(crate) fn compound_assign_method_name(ca: &CompoundAssign) -> String {
+    format!("cpp_{}", ca.rust_trait().1)
+}
+
+/// Whether the right hand side is passed by value rather than by `const`
+/// reference. That's the case for numbers and other built-in types which
+/// Rust can hold, but not for strings or types from C++ headers.
+pub(crate) fn compound_assign_rhs_by_value(ca: &CompoundAssign) -> bool {
+    let rhs = QualifiedName::new_from_cpp_name(&ca.rhs);
+    known_types().is_known_type(&rhs) && !known_types().permissible_within_unique_ptr(&rhs)
+}
+
+/// bindgen tells us nothing about operators, so for each compound
+/// assignment operator listed in `compound_assign!` we synthesize a method
+/// which applies it to `self`. The Rust trait for the operator, such as
+/// `AddAssign`, is then implemented in terms of that method. The C++
+/// operator's return value (usually a reference to `*this`) is discarded.
+pub(crate) fn add_compound_assigns(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            let cpp_name = name.name.to_cpp_name();
+            results.extend(
+                config
+                    .compound_assigns
+                    .iter()
+                    .filter(|ca| ca.ty == cpp_name)
+                    .map(|ca| create_compound_assign(&name.name, ca)),
+            );
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_compound_assign(ty: &QualifiedName, ca: &CompoundAssign) -> Api<PodPhase> {
+    let method = compound_assign_method_name(ca);
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_{method}", ty.get_final_item())),
+    );
+    let typ = ty.to_type_path();
+    let rhs_typ = QualifiedName::new_from_cpp_name(&ca.rhs).to_type_path();
+    let this: FnArg = parse_quote! {
+        this: *mut #typ
+    };
+    let mut references = References::new_with_this_as_reference();
+    let rhs: FnArg = if compound_assign_rhs_by_value(ca) {
+        parse_quote! {
+            rhs: #rhs_typ
+        }
+    } else {
+        references.ref_params.insert(make_ident("rhs"));
+        parse_quote! {
+            rhs: *const #rhs_typ
+        }
+    };
+    let doc = format!(
+        "Applies the C++ `operator{}` to `self`. This is what the `{}` implementation uses.",
+        ca.operator,
+        ca.rust_trait().0
+    );
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            references,
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(method),
+                [this, rhs].into_iter().collect(),
+                parse_quote! {},
+                CppFunctionBody::CompoundAssign(ca.operator.clone()),
+            )
+        }),
+        analysis: (),
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{DefaultArgNaming, IncludeCppConfig};
+use syn::{FnArg, Pat, Type, TypePtr};
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, Provenance},
+        apivec::ApiVec,
+    },
+    types::{make_ident, QualifiedName},
+};
+
+use super::pod::PodPhase;
+
+/// bindgen tells us nothing about default arguments, so for each function
+/// listed in `default_args!` we add overloads which omit one or more of its
+/// trailing parameters. Their C++ wrappers simply call the function without
+/// those arguments, so it's the C++ compiler which fills in the defaults.
+/// That means a default can be any expression, not just a literal, and
+/// it's evaluated on each call, with names looked up in the scope of the
+/// function's declaration, exactly as if C++ had called it.
+pub(crate) fn add_default_arg_overloads(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        let overloads = match api {
+            Api::Function {
+                ref name, ref fun, ..
+            } if matches!(fun.provenance, Provenance::Bindgen) => {
+                create_default_arg_overloads(config, name, fun)
+            }
+            _ => Vec::new(),
+        };
+        results.push(api);
+        results.extend(overloads.into_iter());
+    }
+    results
+}
+
+fn create_default_arg_overloads(
+    config: &IncludeCppConfig,
+    name: &ApiName,
+    fun: &FuncToConvert,
+) -> Vec<Api<PodPhase>> {
+    let (cpp_name, this_type, qualified_cpp_name) = default_args_names(name, fun);
+    let param_count = fun.inputs.len() - usize::from(this_type.is_some());
+    let omittable = config
+        .get_default_arg_count(&qualified_cpp_name)
+        .min(param_count);
+    (1..=omittable)
+        .map(|omitted| {
+            let mut overload = fun.clone();
+            overload.inputs = fun
+                .inputs
+                .iter()
+                .take(fun.inputs.len() - omitted)
+                .cloned()
+                .collect();
+            overload.provenance = Provenance::SynthesizedWithDefaultArgs(omitted);
+            Api::Function {
+                name: ApiName::new_with_cpp_name(
+                    name.name.get_namespace(),
+                    make_ident(format!(
+                        "{}_autocxx_default_args{omitted}",
+                        name.name.get_final_item()
+                    )),
+                    Some(cpp_name.clone()),
+                ),
+                fun: Box::new(overload),
+                analysis: (),
+            }
+        })
+        .collect()
+}
+
+/// For a function listed in `default_args!` with `with_params` naming,
+/// the Rust name of this form of it. That's `None` for the shortest form,
+/// which keeps the function's own name, and for constructors, which are
+/// named like any others. Each longer form is named after the last
+/// parameter it takes, so `refresh(bool force = false)` becomes `refresh()`
+/// and `refresh_with_force(force)`.
+pub(crate) fn default_arg_form_rust_name(
+    config: &IncludeCppConfig,
+    name: &ApiName,
+    fun: &FuncToConvert,
+    ideal_rust_name: &str,
+) -> Option<String> {
+    let omitted = match fun.provenance {
+        Provenance::Bindgen => 0,
+        Provenance::SynthesizedWithDefaultArgs(omitted) => omitted,
+        _ => return None,
+    };
+    let (cpp_name, this_type, qualified_cpp_name) = default_args_names(name, fun);
+    if config.get_default_arg_naming(&qualified_cpp_name) != DefaultArgNaming::WithParams
+        || this_type
+            .as_ref()
+            .is_some_and(|ty| ty.get_final_item() == cpp_name)
+    {
+        return None;
+    }
+    let param_count = fun.inputs.len() - usize::from(this_type.is_some()) + omitted;
+    let omittable = config
+        .get_default_arg_count(&qualified_cpp_name)
+        .min(param_count);
+    if omitted == omittable {
+        return None;
+    }
+    let last_param = match &**fun.inputs.last()? {
+        FnArg::Typed(pt) => match pt.pat.as_ref() {
+            Pat::Ident(pp) => pp.ident.to_string(),
+            _ => return None,
+        },
+        FnArg::Receiver(_) => return None,
+    };
+    Some(format!(
+        "{}_with_{}",
+        ideal_rust_name.trim_end_matches('_'),
+        last_param.trim_end_matches('_')
+    ))
+}
+
+/// The C++ name of a function, the type of its `this` parameter if it's a
+/// method, and the name by which `default_args!` refers to it.
+fn default_args_names(
+    name: &ApiName,
+    fun: &FuncToConvert,
+) -> (String, Option<QualifiedName>, String) {
+    let cpp_name = name
+        .cpp_name_if_present()
+        .cloned()
+        .unwrap_or_else(|| fun.ident.to_string());
+    let this_type = fun.inputs.first().and_then(|arg| match &**arg {
+        FnArg::Typed(pt) => match (pt.pat.as_ref(), pt.ty.as_ref()) {
+            (Pat::Ident(pp), Type::Ptr(TypePtr { elem, .. })) if pp.ident == "this" => {
+                match elem.as_ref() {
+                    Type::Path(tp) => Some(QualifiedName::from_type_path(tp)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        FnArg::Receiver(_) => None,
+    });
+    let qualified_cpp_name = match (&this_type, &fun.self_ty) {
+        (Some(ty), _) | (None, Some(ty)) => format!("{}::{cpp_name}", ty.to_cpp_name()),
+        (None, None) => {
+            QualifiedName::new(name.name.get_namespace(), make_ident(&cpp_name)).to_cpp_name()
+        }
+    };
+    (cpp_name, this_type, qualified_cpp_name)
+}
@@ -42,6 +42,10 @@ impl HasDependencies for Api<FnPrePhase1> {
             } => Box::new(std::iter::once(superclass)),
             Api::RustSubclassFn { details, .. } => Box::new(details.dependencies.iter()),
             Api::RustFn { deps, .. } => Box::new(deps.iter()),
+            Api::ContainerIterator {
+                container, item, ..
+            } => Box::new([container, item].into_iter()),
+            Api::ConstantAccessor { enum_type, .. } => Box::new(std::iter::once(enum_type)),
             _ => Box::new(std::iter::empty()),
         }
     }
@@ -91,6 +95,10 @@ impl HasDependencies for Api<FnPhase> {
             } => Box::new(std::iter::once(superclass)),
             Api::RustSubclassFn { details, .. } => Box::new(details.dependencies.iter()),
             Api::RustFn { deps, .. } => Box::new(deps.iter()),
+            Api::ContainerIterator {
+                container, item, ..
+            } => Box::new([container, item].into_iter()),
+            Api::ConstantAccessor { enum_type, .. } => Box::new(std::iter::once(enum_type)),
             _ => Box::new(std::iter::empty()),
         }
     }
@@ -0,0 +1,97 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{DerefTarget, IncludeCppConfig};
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, References},
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// The name of the method synthesized for each type listed in `deref!`.
+/// The Rust `Deref` implementation calls it.
+pub(crate) const DEREF_METHOD_NAME: &str = "cpp_deref";
+
+/// The name of the method synthesized for each type listed in `deref!`
+/// with `mut`. The Rust `DerefMut` implementation, if any, calls it.
+pub(crate) const DEREF_MUT_METHOD_NAME: &str = "cpp_deref_mut";
+
+/// bindgen tells us nothing about operators, so for each type listed in
+/// `deref!` we synthesize a method which calls its `operator*` on a `const`
+/// reference, returning a `const` reference to the target, and, if asked,
+/// another which does the same with non-`const` references. Rust's `Deref`
+/// and `DerefMut` are then implemented using those methods. Rust's method
+/// calls look through `Deref`, so there's no need for `operator->`.
+pub(crate) fn add_derefs(config: &IncludeCppConfig, apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            let cpp_name = name.name.to_cpp_name();
+            for deref in config.deref_targets.iter().filter(|d| d.ty == cpp_name) {
+                results.push(create_deref(&name.name, deref, false));
+                if deref.mutable {
+                    results.push(create_deref(&name.name, deref, true));
+                }
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_deref(ty: &QualifiedName, deref: &DerefTarget, mutable: bool) -> Api<PodPhase> {
+    let method = if mutable {
+        DEREF_MUT_METHOD_NAME
+    } else {
+        DEREF_METHOD_NAME
+    };
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_{method}", ty.get_final_item())),
+    );
+    let typ = ty.to_type_path();
+    let target_typ = QualifiedName::new_from_cpp_name(&deref.target).to_type_path();
+    let (this, output, doc): (FnArg, _, _) = if mutable {
+        (
+            parse_quote! { this: *mut #typ },
+            parse_quote! { -> *mut #target_typ },
+            "Returns the target of the C++ `operator*`. This is what the `DerefMut` \
+                implementation, if any, uses.",
+        )
+    } else {
+        (
+            parse_quote! { this: *const #typ },
+            parse_quote! { -> *const #target_typ },
+            "Returns the target of the C++ `const` `operator*`. This is what the \
+                `Deref` implementation uses.",
+        )
+    };
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            references: References::new_with_this_and_return_as_reference(),
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(method),
+                [this].into_iter().collect(),
+                output,
+                CppFunctionBody::Dereference { mutable },
+            )
+        }),
+        analysis: (),
+    }
+}
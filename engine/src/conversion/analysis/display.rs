@@ -0,0 +1,78 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, References},
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// The name of the method synthesized for each type listed in `display!`.
+/// The Rust `Display` implementation for the type calls it.
+pub(crate) const TO_STRING_METHOD_NAME: &str = "cpp_to_string";
+
+/// bindgen tells us nothing about `operator<<`, so for each type listed in
+/// `display!` we synthesize a method which streams the object into a
+/// `std::ostringstream` and returns the resulting string. The operator is
+/// found by C++ overload resolution, including argument-dependent lookup,
+/// so it may be a friend, or a free function in the type's own namespace.
+/// Rust's `Display` is then implemented using that method.
+pub(crate) fn add_displays(config: &IncludeCppConfig, apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            if config.is_display(&name.name.to_cpp_name()) {
+                results.push(create_to_string(&name.name));
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_to_string(ty: &QualifiedName) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_to_string", ty.get_final_item())),
+    );
+    let typ = ty.to_type_path();
+    let fnarg: FnArg = parse_quote! {
+        this: *const #typ
+    };
+    let doc = "Formats this object using its C++ `operator<<`. This is what the \
+        `Display` implementation uses.";
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            references: References::new_with_this_as_reference(),
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(TO_STRING_METHOD_NAME),
+                [fnarg].into_iter().collect(),
+                // Rather than a `std::string`, whose conversion depends upon
+                // `rust_string_returns!`, return a `std::unique_ptr` so that
+                // the `Display` implementation always knows what it's getting.
+                parse_quote! {
+                    -> root::std::unique_ptr<root::std::string>
+                },
+                CppFunctionBody::StreamInsertion,
+            )
+        }),
+        analysis: (),
+    }
+}
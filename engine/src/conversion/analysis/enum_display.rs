@@ -0,0 +1,83 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert},
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// The name of the static method synthesized for each enum listed in
+/// `enum_display!`. The Rust `Display` implementation calls it.
+pub(crate) const DISPLAY_NAME_METHOD_NAME: &str = "cpp_display_name";
+
+/// For each enum listed in `enum_display!` we synthesize a static method
+/// which passes a value to the C++ function which names it, typically
+/// `const char* toString(Enum)`. Rust's `Display` is then implemented
+/// using that method. The function is called through a wrapper, so it
+/// doesn't matter if it's overloaded for other enums.
+pub(crate) fn add_enum_displays(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Enum { ref name, .. } = api {
+            if let Some(function) = config.get_enum_display_function(&name.name.to_cpp_name()) {
+                results.push(create_display_name(&name.name, function));
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_display_name(ty: &QualifiedName, function: &str) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_display_name", ty.get_final_item())),
+    );
+    let function = QualifiedName::new_from_cpp_name(function);
+    let typ = ty.to_type_path();
+    let value: FnArg = parse_quote! {
+        value: #typ
+    };
+    let doc = format!(
+        "The name which the C++ `{}` gives this value, or null if it has none. \
+        This is what the `Display` implementation uses.",
+        function.to_cpp_name()
+    );
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(DISPLAY_NAME_METHOD_NAME),
+                [value].into_iter().collect(),
+                parse_quote! {
+                    -> *const ::std::os::raw::c_char
+                },
+                CppFunctionBody::FunctionCall(
+                    function.get_namespace().clone(),
+                    function.get_final_ident(),
+                ),
+            )
+        }),
+        analysis: (),
+    }
+}
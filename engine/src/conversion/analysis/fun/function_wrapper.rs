@@ -11,6 +11,7 @@ use crate::{
     conversion::{api::SubclassName, type_helpers::extract_pinned_mutable_reference_type},
     types::{Namespace, QualifiedName},
 };
+use autocxx_parser::{NullVectorPolicy, SecondsCountPolicy, Utf16Policy, Utf8Policy};
 use quote::ToTokens;
 use syn::{parse_quote, Type, TypeReference};
 
@@ -27,6 +28,174 @@ pub(crate) enum CppConversionType {
     FromReturnValueToPlacementPtr,
     FromPointerToReference, // unwrapped_type is always Type::Ptr
     FromReferenceToPointer, // unwrapped_type is always Type::Ptr
+    FromOptionalBoolToI8,   // unwrapped_type is always i8
+    /// A returned `std::optional<std::reference_wrapper<T>>` which becomes
+    /// null if empty. unwrapped_type is always `*const T`.
+    FromOptionalReferenceWrapperToPtr,
+    /// A returned `std::shared_ptr<const T>` whose object the user has
+    /// promised isn't const. unwrapped_type is always cxx::SharedPtr.
+    FromSharedPtrDroppingConst,
+    /// A returned `std::wstring` transcoded to UTF-8. unwrapped_type is
+    /// always `String`.
+    FromWStringToString,
+    /// A returned `std::u16string` whose UTF-16 code units are copied into
+    /// a `rust::Vec<uint16_t>`, to be decoded in Rust. unwrapped_type is
+    /// always `Vec<u16>`.
+    FromU16StringToVec,
+    /// A returned `std::chrono::duration` which becomes its count of
+    /// nanoseconds. unwrapped_type is always `i64`.
+    FromDurationToNanosecondsCount,
+    /// A returned `std::chrono::duration` which becomes its count of
+    /// seconds, including any fraction. unwrapped_type is always `f64`.
+    FromDurationToSecondsCount,
+    /// An integer parameter which the wrapper casts to the given enum
+    /// before passing it on. unwrapped_type is always the enum's
+    /// underlying integer type.
+    FromIntegerToEnum(Box<crate::minisyn::Type>),
+    /// A `cstr_returns!` `const std::string&` return value which becomes a
+    /// pointer to its null-terminated characters. unwrapped_type is always
+    /// `*const c_char`.
+    FromStringToCharPointer,
+    /// A `cstr_string_returns!` `const char*` return value whose characters
+    /// are copied into a new `std::string`, which is empty if the pointer
+    /// is null. unwrapped_type is always `CxxString`.
+    FromCharPointerToString,
+    /// A `bitmask_params!` parameter which the wrapper casts to the given
+    /// integer type before passing it on. unwrapped_type is always the
+    /// bitmask enum.
+    FromBitmaskToInteger(Box<crate::minisyn::Type>),
+    /// A `&str` parameter transcoded into a `std::wstring`. unwrapped_type
+    /// is always `&str`.
+    FromStrToWString,
+    /// A slice of UTF-16 code units, encoded in Rust from a `&str`, from
+    /// which the wrapper makes a `std::u16string`. unwrapped_type is always
+    /// `&[u16]`.
+    FromSliceToU16String,
+    /// A returned `std::string_view` whose characters are borrowed by Rust
+    /// as bytes, which aren't checked to be UTF-8 until they reach Rust.
+    /// unwrapped_type is always `&[u8]`.
+    FromStringViewToStr,
+    /// A returned `std::string_view` copied into a Rust `String`, because
+    /// it might not outlive the call. Any invalid UTF-8 is replaced with
+    /// U+FFFD. unwrapped_type is always `String`.
+    FromStringViewToString,
+    /// A `&str` parameter viewed by C++ as a `std::string_view`, pointing
+    /// at the Rust characters rather than a heap `std::string`. The Rust
+    /// borrow lasts for the whole call, so the view is valid until the
+    /// callee returns, but no longer. unwrapped_type is always `&str`.
+    FromStrToStringView,
+    /// An `Option<&str>` parameter, received by the wrapper as a slice of
+    /// no or one `&str`, which becomes a `std::optional<std::string_view>`.
+    /// unwrapped_type is always `&[&str]`.
+    FromStrSliceToOptionalStringView,
+    /// A `str_params!` `&str` parameter copied into a `std::string`, so
+    /// that C++ can be given a pointer to its null-terminated characters.
+    /// The string is destroyed once the call is over. unwrapped_type is
+    /// always `&str`.
+    FromStrToCharPointer,
+    /// A `const T&` received by the wrapper, which passes its address to a
+    /// C++ function taking `const T*`. unwrapped_type is always `&T`.
+    FromConstRefToConstPtr,
+    /// A slice received by the wrapper, whose bytes a C++ function taking
+    /// a reference to `uint8_t[N]` then uses in place. unwrapped_type is
+    /// always `&[u8]` or `&mut [u8]`.
+    FromSliceToByteArray(usize),
+    /// A slice received by the wrapper, which passes the C++ function a
+    /// `const void*` or `void*` to its bytes followed by its length.
+    /// unwrapped_type is always `&[u8]` or `&mut [u8]`.
+    FromSliceToVoidPtrAndSize,
+    /// A slice received by the wrapper, which passes the C++ function a
+    /// `std::span` of its elements, so of the same length.
+    /// unwrapped_type is always `&[T]` or `&mut [T]`.
+    FromSliceToSpan,
+    /// A slice received by the wrapper, which passes the C++ function a
+    /// `const T*` or `T*` to its elements followed by the given sort of
+    /// bound. unwrapped_type is always `&[T]` or `&mut [T]`.
+    FromSliceToPtr(SliceBound),
+    /// A `std::unique_ptr<std::string>&` received by the wrapper, which
+    /// passes the C++ function a callback that stores its string there.
+    FromUniquePtrToStringCallback,
+    /// A `T*` to uninitialized storage received by the wrapper, which
+    /// instead passes the C++ function a `std::optional<T>&` to fill in.
+    /// It's followed by a [`CppConversionType::IgnoredOptionalOutParamFlag`].
+    /// unwrapped_type is always `*mut T`.
+    FromPtrToOptionalOutParam,
+    /// The `bool&` which records whether the preceding
+    /// [`CppConversionType::FromPtrToOptionalOutParam`] was filled in.
+    /// It's not passed to the C++ function.
+    IgnoredOptionalOutParamFlag,
+    /// A returned `std::unique_ptr<std::vector<T>>` which, if null, is
+    /// replaced by a pointer to an empty vector. unwrapped_type is always
+    /// `cxx::UniquePtr<cxx::CxxVector<T>>`.
+    FromNullVectorToEmpty,
+    /// A returned `std::optional<T>` for a number or POD type `T`, whose
+    /// value (or a value-initialized `T`, if empty) is returned and whose
+    /// presence is recorded in the
+    /// [`CppConversionType::IgnoredOptionalReturnFlag`] parameter.
+    /// unwrapped_type is always `T`.
+    FromOptionalToValue,
+    /// A returned `std::optional<std::vector<T>>` whose vector, if any, is
+    /// moved into a new heap allocation. An empty optional becomes null.
+    /// unwrapped_type is always `cxx::UniquePtr<cxx::CxxVector<T>>`.
+    FromOptionalVectorToUniquePtr,
+    /// The `bool&` which records whether a returned
+    /// [`CppConversionType::FromOptionalToValue`], or optional
+    /// [`CppConversionType::FromPairToElements`], had a value. It's not
+    /// passed to the C++ function.
+    IgnoredOptionalReturnFlag,
+    /// A Rust function pointer received by the wrapper, which passes the
+    /// C++ function a lambda calling it. It's followed by a
+    /// [`CppConversionType::IgnoredCallbackContext`] to pass back to it.
+    /// unwrapped_type is always `fn(usize, Args...) -> R`: see
+    /// [`CallbackKind`] for the arguments of each kind of callback.
+    FromFnToCallback(CallbackKind),
+    /// The context for the preceding [`CppConversionType::FromFnToCallback`].
+    /// It's not passed to the C++ function.
+    IgnoredCallbackContext,
+    /// A returned `std::variant` of two types, one of them an error. The
+    /// other is returned in a `std::unique_ptr`, which is null if there
+    /// was an error, in which case the error is moved into the
+    /// [`CppConversionType::IgnoredResultVariantError`] parameter instead.
+    /// The `usize` is the index of the error within the variant.
+    /// unwrapped_type is always `cxx::UniquePtr<T>`.
+    FromResultVariantToUniquePtr(usize),
+    /// The `std::unique_ptr<E>&` into which a
+    /// [`CppConversionType::FromResultVariantToUniquePtr`] moves any error.
+    /// It's not passed to the C++ function.
+    IgnoredResultVariantError,
+    /// A returned `std::tuple` whose elements, which are all the same
+    /// built-in type, are copied into the array pointed to by the
+    /// [`CppConversionType::IgnoredTupleArray`] parameter. unwrapped_type
+    /// is always the element type.
+    FromTupleToArray,
+    /// The `T*` to the array which a [`CppConversionType::FromTupleToArray`]
+    /// fills in. It's not passed to the C++ function.
+    IgnoredTupleArray,
+    /// A pointer to an object which keeps its own reference count, which
+    /// the wrapper passes on in the given intrusive pointer template, such
+    /// as `boost::intrusive_ptr`. That adopts the reference which Rust gave
+    /// up rather than adding one. unwrapped_type is always `*mut T`.
+    FromPtrToIntrusivePtr(String),
+    /// A returned intrusive pointer, which the wrapper `detach`es so that
+    /// its reference goes to Rust along with the raw pointer.
+    /// unwrapped_type is always `*mut T`.
+    FromIntrusivePtrToPtr,
+    /// A returned `std::pair`, or `std::optional` of one, each of whose
+    /// elements, however deeply nested in further pairs, is moved into one
+    /// of the [`CppConversionType::IgnoredPairElement`] or
+    /// [`CppConversionType::IgnoredPairStringElement`] parameters. For an
+    /// optional, a [`CppConversionType::IgnoredOptionalReturnFlag`] says
+    /// whether it had a value.
+    FromPairToElements,
+    /// The `T*`, or `std::unique_ptr<T>&`, into which a
+    /// [`CppConversionType::FromPairToElements`] moves the element at this
+    /// member path, such as `first.second`. It's not passed to the C++
+    /// function.
+    IgnoredPairElement(String),
+    /// The `rust::String*` to uninitialized storage into which a
+    /// [`CppConversionType::FromPairToElements`] converts the `std::string`
+    /// element at this member path. It's not passed to the C++ function.
+    IgnoredPairStringElement(String),
 }
 
 impl CppConversionType {
@@ -47,11 +216,62 @@ impl CppConversionType {
     }
 }
 
+/// The shape of a `std::pair`, which becomes a Rust tuple of the same
+/// shape. Each element is either another pair, a built-in type or `String`
+/// which the C++ copies into the out parameter with this name, or a
+/// `UniquePtr` out parameter into which the C++ moves a `std::unique_ptr`.
+#[derive(Clone, Debug)]
+pub(crate) enum PairShape {
+    Element(Ident, Box<crate::minisyn::Type>),
+    UniquePtr(Ident, Box<crate::minisyn::Type>),
+    Pair(Box<PairShape>, Box<PairShape>),
+    /// A `std::optional` of a pair, which becomes an `Option` of the
+    /// tuple, according to the flag with this name.
+    Optional(Ident, Box<PairShape>),
+}
+
+/// How a C++ function taking a pointer to the elements of a slice is told
+/// where they end.
+#[derive(Clone, Debug)]
+pub(crate) enum SliceBound {
+    /// A following `size_t` count of elements.
+    Size,
+    /// A following pointer to one past the last element, as for a range
+    /// of iterators.
+    EndPtr,
+}
+
+/// A callback parameter to which Rust passes a closure. The bridge takes a
+/// function pointer and a `usize` context, which is really a pointer to
+/// the closure, and the C++ wrapper passes the original function a lambda
+/// which calls the function pointer with the context.
+#[derive(Clone, Debug)]
+pub(crate) enum CallbackKind {
+    /// A `comparator!` comparator, called as `fn(usize, &T, &T) -> bool`,
+    /// whose closure returns a `core::cmp::Ordering`.
+    Comparator,
+    /// An `allocator_callback!` callback, called with a size and alignment
+    /// as `fn(usize, usize, usize) -> usize`, whose closure takes a
+    /// `core::alloc::Layout` and returns a pointer.
+    Allocator,
+    /// A `closure_callback!` callback, called with the same arguments as
+    /// the closure. If it's `fallible`, the closure returns a `Result`, and
+    /// the function pointer takes two more parameters, `&mut bool` and
+    /// `&mut String`, which it sets to say that the closure failed and why.
+    /// The lambda then throws a `std::runtime_error`.
+    Closure { fallible: bool },
+}
+
 #[derive(Clone, Debug)]
 pub(crate) enum RustConversionType {
     None,
     FromStr,
-    ToBoxedUpHolder(SubclassName),
+    /// Boxes up the peer holder for a subclass. `is_dyn` is set for
+    /// subclasses from `dyn_subclass!`, whose Rust struct we generate.
+    ToBoxedUpHolder {
+        subclass: SubclassName,
+        is_dyn: bool,
+    },
     FromPinMaybeUninitToPtr,
     FromPinMoveRefToPtr,
     FromTypeToPtr,
@@ -60,6 +280,137 @@ pub(crate) enum RustConversionType {
     FromRValueParamToPtr,
     FromReferenceWrapperToPointer, // unwrapped_type is always Type::Ptr
     FromPointerToReferenceWrapper, // unwrapped_type is always Type::Ptr
+    /// A `UniquePtr<CxxString>` return value which is turned into a Rust `String`.
+    FromCxxStringToString(Utf8Policy),
+    FromI8ToOptionalBool, // unwrapped_type is always i8
+    /// An `f64` count of seconds returned by a `seconds_count!` function
+    /// which becomes a `Result<Duration, TryFromFloatSecsError>`.
+    FromSecondsToDuration,
+    /// A possibly-null `*const T` return value which becomes an
+    /// `Option<&T>`.
+    FromPtrToOptionalReference,
+    /// A `&mut UniquePtr<T>` parameter which the wrapper creates itself
+    /// and then returns.
+    FromUniquePtrOutParamToReturnValue,
+    /// A `&mut UniquePtr<CxxString>` which the wrapper creates, for the C++
+    /// to fill in via a callback, then returns. If there's a string return
+    /// policy, the string is converted just as a returned one would be.
+    FromStringCallbackToReturnValue(Option<Utf8Policy>),
+    /// Storage for a `std::optional<T>&` out parameter which the wrapper
+    /// creates, then returns as an `Option<T>`, depending on the flag in
+    /// the parameter named `has_value`.
+    FromOptionalOutParamToReturnValue {
+        has_value: Ident,
+    },
+    /// The flag saying whether a `std::optional<T>&` out parameter was
+    /// filled in.
+    OptionalOutParamFlag,
+    /// A return value which becomes an `Option`, depending on the flag in
+    /// the parameter named `has_value`.
+    FromCppOptionalToOption {
+        has_value: Ident,
+    },
+    /// A closure passed to C++ as a function pointer, which calls it with
+    /// a context that comes first. See [`CallbackKind`].
+    FromClosureToCallbackFn(CallbackKind),
+    /// The context the C++ passes back to the function pointer from a
+    /// [`RustConversionType::FromClosureToCallbackFn`] in the parameter
+    /// named `callback`: really a pointer to the closure.
+    CallbackContext {
+        callback: Ident,
+    },
+    /// A `UniquePtr` return value which becomes a `Result`. The error is
+    /// the `UniquePtr` of type `error_ty` in the parameter named `error`,
+    /// if that isn't null.
+    FromUniquePtrToResult {
+        error: Ident,
+        error_ty: Box<crate::minisyn::Type>,
+    },
+    /// An `Option<&str>` passed to cxx as a slice of no or one `&str`,
+    /// since cxx doesn't support `Option`.
+    FromOptionToSlice,
+    /// The place for the error from a
+    /// [`RustConversionType::FromUniquePtrToResult`].
+    ResultErrorOutParam,
+    /// A pointer to uninitialized storage for an array of this many
+    /// elements, which the C++ fills in, and which is then returned.
+    FromTupleArrayOutParamToReturnValue(usize),
+    /// A pointer to uninitialized storage for one element of a `std::pair`,
+    /// or a `UniquePtr` for a `std::unique_ptr` element, which the C++
+    /// fills in. The storage for the first element also returns the whole
+    /// of the pair as a tuple of this shape.
+    FromPairElementOutParamToReturnValue(Option<PairShape>),
+    /// A possibly-null `UniquePtr` return value which becomes an
+    /// `Option<UniquePtr<T>>`.
+    FromNullableUniquePtrToOption,
+    /// An integer return value which becomes `None` if it's equal to this
+    /// sentinel, and otherwise `Some`.
+    FromSentinelToOption(i128),
+    /// A floating point parameter which is checked not to be NaN in debug
+    /// builds, then passed on unchanged.
+    FromFloatRejectingNan,
+    /// An `integer_enum_params!` parameter: an integer which is checked,
+    /// in debug builds, to be the value of one of the `variants` of
+    /// `enum_ty`, then passed on unchanged.
+    FromIntegerRejectingUnknownEnumValues {
+        enum_ty: Box<crate::minisyn::Type>,
+        variants: Vec<Ident>,
+    },
+    /// A `bitmask_enum!` return value which is checked to contain only
+    /// bits matching the enum's flags.
+    FromBitmaskRejectingUnknownBits,
+    /// A `vector_slices!` return value: a `&CxxVector<T>` which becomes a
+    /// `&[T]` of the same storage.
+    FromCppVectorToSlice,
+    /// A returned `&[u8]` of the characters of a `std::string_view`, which
+    /// becomes a `Result<&str, Utf8Error>` viewing the same characters.
+    FromBytesToStr,
+    /// A `cstr_returns!` return value: a pointer to null-terminated
+    /// characters, which becomes a `&CStr` borrowing them.
+    FromCharPointerToCStr,
+    /// A `bitmask_enum!` parameter which is checked, before it's passed to
+    /// C++, to contain only bits matching the enum's flags.
+    FromBitmaskParamRejectingUnknownBits,
+    /// An `output_buffers!` return value: the number of bytes written to
+    /// the `buffer` parameter. A count bigger than the buffer is taken to
+    /// be the size the buffer would have needed, and becomes an error.
+    FromBytesWrittenToResult {
+        buffer: Ident,
+    },
+    /// A `&[u8; N]` or `&mut [u8; N]` passed to the bridge as a slice.
+    FromByteArrayToSlice(usize),
+    /// A `UniquePtr` to a `std::map<std::string, T>` which is copied into a
+    /// Rust `HashMap` by calling `snapshot`.
+    FromStringMapToHashMap {
+        snapshot: Ident,
+        value: Box<crate::minisyn::Type>,
+    },
+    /// A `UniquePtr` to a `std::vector<std::string>` which is copied into a
+    /// Rust `Vec`, converting each element under the given policy.
+    FromStringVectorToVec(Utf8Policy),
+    /// A `Vec` of UTF-16 code units from a `std::u16string`, which is
+    /// decoded into a Rust `String` under the given policy.
+    FromUtf16ToString(Utf16Policy),
+    /// A `&str` which is encoded as UTF-16 and passed on as a slice.
+    FromStrToUtf16,
+    /// A `const std::map<K, V>&` parameter which the wrapper instead
+    /// accepts as an iterator of `(K, V)` pairs. It makes the map by
+    /// calling `new` then `insert`s each pair in turn.
+    FromIteratorToMap {
+        new: Ident,
+        insert: Ident,
+        key: Box<crate::minisyn::Type>,
+        value: Box<crate::minisyn::Type>,
+    },
+    /// An `autocxx::IntrusivePtr` parameter, whose reference is handed to
+    /// C++ along with the raw pointer.
+    FromIntrusivePtrToPtr,
+    /// A raw pointer returned along with a reference, which becomes an
+    /// `autocxx::IntrusivePtr` owning that reference.
+    FromPtrToIntrusivePtr,
+    /// A `cstr_params!` `&CStr` parameter, whose pointer is passed to C++.
+    /// unwrapped_type is always `*const c_char`.
+    FromCStrToCharPointer,
 }
 
 impl RustConversionType {
@@ -148,6 +499,795 @@ impl TypeConversionPolicy {
         }
     }
 
+    /// A `const T*` parameter which Rust passes as a `&T`, because the
+    /// function promises never to be given null. `ty` is the `&T`.
+    pub(crate) fn new_const_ref_to_const_ptr(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromConstRefToConstPtr,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A reference to a `uint8_t[len]` parameter, which Rust passes as a
+    /// reference to a `[u8; len]`.
+    pub(crate) fn new_for_byte_array_reference(len: usize, is_mut: bool) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: if is_mut {
+                parse_quote! { &mut [u8] }
+            } else {
+                parse_quote! { &[u8] }
+            },
+            cpp_conversion: CppConversionType::FromSliceToByteArray(len),
+            rust_conversion: RustConversionType::FromByteArrayToSlice(len),
+        }
+    }
+
+    /// A `const void*` or `void*` parameter followed by its length, both
+    /// passed from Rust as a single slice.
+    pub(crate) fn new_for_byte_slice(is_mut: bool) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: if is_mut {
+                parse_quote! { &mut [u8] }
+            } else {
+                parse_quote! { &[u8] }
+            },
+            cpp_conversion: CppConversionType::FromSliceToVoidPtrAndSize,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `std::span<T>` parameter, passed from Rust as a slice of `elem`,
+    /// which is mutable unless the span's elements are `const`.
+    pub(crate) fn new_for_span(elem: &Type, is_const: bool) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: if is_const {
+                parse_quote! { &[#elem] }
+            } else {
+                parse_quote! { &mut [#elem] }
+            },
+            cpp_conversion: CppConversionType::FromSliceToSpan,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `const T*` or `T*` parameter followed by its length, or by a
+    /// pointer to its end, both passed from Rust as a single slice of
+    /// `elem`s.
+    pub(crate) fn new_for_slice(elem: &Type, is_mut: bool, bound: SliceBound) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: if is_mut {
+                parse_quote! { &mut [#elem] }
+            } else {
+                parse_quote! { &[#elem] }
+            },
+            cpp_conversion: CppConversionType::FromSliceToPtr(bound),
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A returned `std::map<std::string, T>`, which reaches Rust as a
+    /// `UniquePtr` then gets copied into a `HashMap` with values of type
+    /// `value`.
+    pub(crate) fn new_for_map_snapshot(ty: Type, snapshot: Ident, value: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromValueToUniquePtr,
+            rust_conversion: RustConversionType::FromStringMapToHashMap {
+                snapshot,
+                value: Box::new(value.into()),
+            },
+        }
+    }
+
+    /// A returned `std::vector<std::string>`, which reaches Rust as a
+    /// `UniquePtr` then gets copied into a `Vec` of strings.
+    pub(crate) fn new_for_string_vec(ty: Type, policy: Utf8Policy) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromValueToUniquePtr,
+            rust_conversion: RustConversionType::FromStringVectorToVec(policy),
+        }
+    }
+
+    /// A `const std::map<K, V>&` parameter, which reaches the bridge as a
+    /// reference `ty` but is built from an iterator of pairs of `key` and
+    /// `value`.
+    pub(crate) fn new_for_map_from_iter(
+        ty: Type,
+        new: Ident,
+        insert: Ident,
+        key: Type,
+        value: Type,
+    ) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::None,
+            rust_conversion: RustConversionType::FromIteratorToMap {
+                new,
+                insert,
+                key: Box::new(key.into()),
+                value: Box::new(value.into()),
+            },
+        }
+    }
+
+    /// A parameter taking the intrusive pointer template `pointer` to a
+    /// `pointee` listed in `intrusive_ptr!`, by value or by `const`
+    /// reference. Rust passes an `IntrusivePtr`.
+    pub(crate) fn new_for_intrusive_ptr_param(pointee: Type, pointer: String) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *mut #pointee },
+            cpp_conversion: CppConversionType::FromPtrToIntrusivePtr(pointer),
+            rust_conversion: RustConversionType::FromIntrusivePtrToPtr,
+        }
+    }
+
+    /// A returned intrusive pointer to a `pointee` listed in
+    /// `intrusive_ptr!`, which Rust receives as an `IntrusivePtr`.
+    pub(crate) fn new_for_intrusive_ptr_return(pointee: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *mut #pointee },
+            cpp_conversion: CppConversionType::FromIntrusivePtrToPtr,
+            rust_conversion: RustConversionType::FromPtrToIntrusivePtr,
+        }
+    }
+
+    /// A returned integer from a `sentinel_none!` function, which uses
+    /// `sentinel` to mean that there's no value.
+    pub(crate) fn new_for_sentinel(ty: Type, sentinel: i128) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::None,
+            rust_conversion: RustConversionType::FromSentinelToOption(sentinel),
+        }
+    }
+
+    /// A returned `std::unique_ptr<std::vector<T>>` which may be null, to be
+    /// handled according to `policy`.
+    pub(crate) fn new_for_null_vector(ty: Type, policy: NullVectorPolicy) -> Self {
+        let (cpp_conversion, rust_conversion) = match policy {
+            NullVectorPolicy::AsNone => (
+                CppConversionType::None,
+                RustConversionType::FromNullableUniquePtrToOption,
+            ),
+            NullVectorPolicy::AsEmpty => (
+                CppConversionType::FromNullVectorToEmpty,
+                RustConversionType::None,
+            ),
+        };
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion,
+            rust_conversion,
+        }
+    }
+
+    pub(crate) fn new_to_rust_string(ty: Type, policy: Utf8Policy) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromValueToUniquePtr,
+            rust_conversion: RustConversionType::FromCxxStringToString(policy),
+        }
+    }
+
+    /// A `std::optional<bool>` return value crosses the boundary as an
+    /// `i8`: -1 for `None`, 0 for `Some(false)` and 1 for `Some(true)`.
+    pub(crate) fn new_from_optional_bool() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { i8 },
+            cpp_conversion: CppConversionType::FromOptionalBoolToI8,
+            rust_conversion: RustConversionType::FromI8ToOptionalBool,
+        }
+    }
+
+    /// A `std::optional<std::reference_wrapper<T>>` return value crosses
+    /// the boundary as a pointer, null for an empty optional. `ty` is the
+    /// `*const T`.
+    pub(crate) fn new_from_optional_reference_wrapper(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromOptionalReferenceWrapperToPtr,
+            rust_conversion: RustConversionType::FromPtrToOptionalReference,
+        }
+    }
+
+    /// A `nullable_returns!` return value: a `*const T` which becomes an
+    /// `Option<&T>`, `None` if it's null.
+    pub(crate) fn new_to_optional_reference(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::None,
+            rust_conversion: RustConversionType::FromPtrToOptionalReference,
+        }
+    }
+
+    /// A `std::unique_ptr<T>&` parameter filled in by the C++, which
+    /// Rust returns rather than accepts.
+    pub(crate) fn new_for_unique_ptr_out_param(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &mut #ty },
+            cpp_conversion: CppConversionType::None,
+            rust_conversion: RustConversionType::FromUniquePtrOutParamToReturnValue,
+        }
+    }
+
+    /// Stands in for the callback parameter of a `string_callback!`
+    /// function.
+    pub(crate) fn new_for_string_callback(policy: Option<Utf8Policy>) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &mut cxx::UniquePtr<cxx::CxxString> },
+            cpp_conversion: CppConversionType::FromUniquePtrToStringCallback,
+            rust_conversion: RustConversionType::FromStringCallbackToReturnValue(policy),
+        }
+    }
+
+    /// Storage for a `std::optional<T>&` out parameter. `ty` is the
+    /// `*mut T`.
+    pub(crate) fn new_for_optional_out_param(ty: Type, has_value: Ident) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromPtrToOptionalOutParam,
+            rust_conversion: RustConversionType::FromOptionalOutParamToReturnValue { has_value },
+        }
+    }
+
+    pub(crate) fn new_for_optional_out_param_flag() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &mut bool },
+            cpp_conversion: CppConversionType::IgnoredOptionalOutParamFlag,
+            rust_conversion: RustConversionType::OptionalOutParamFlag,
+        }
+    }
+
+    /// A `std::optional<T>` return value crosses the boundary as the plain
+    /// `T`, alongside a flag parameter named `has_value` saying whether
+    /// there was one. `T` must be a number or a POD type, so that it can
+    /// simply be copied. The value is copied as it is, so a NaN stays a NaN.
+    pub(crate) fn new_from_optional_value(ty: Type, has_value: Ident) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromOptionalToValue,
+            rust_conversion: RustConversionType::FromCppOptionalToOption { has_value },
+        }
+    }
+
+    /// A returned `std::unique_ptr<T>` from a `nullable_returns!` function,
+    /// which is `None` if it's null. `ty` is the `cxx::UniquePtr<T>`.
+    pub(crate) fn new_for_nullable_unique_ptr(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::None,
+            rust_conversion: RustConversionType::FromNullableUniquePtrToOption,
+        }
+    }
+
+    /// A `std::optional<std::vector<T>>` return value crosses the boundary
+    /// as a `UniquePtr`, null for an empty optional, which Rust turns into
+    /// an `Option`. A present but empty vector is still `Some`. `ty` is the
+    /// `cxx::UniquePtr<cxx::CxxVector<T>>`.
+    pub(crate) fn new_from_optional_vector(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromOptionalVectorToUniquePtr,
+            rust_conversion: RustConversionType::FromNullableUniquePtrToOption,
+        }
+    }
+
+    pub(crate) fn new_for_optional_return_flag() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &mut bool },
+            cpp_conversion: CppConversionType::IgnoredOptionalReturnFlag,
+            rust_conversion: RustConversionType::OptionalOutParamFlag,
+        }
+    }
+
+    /// A `std::variant` return value from a `result_variant!` function,
+    /// whose error alternative is at `error_index`. `ty` is the
+    /// `cxx::UniquePtr<T>` for the other alternative, and `error_ty` is
+    /// the `cxx::UniquePtr<E>` for the error, which goes in the parameter
+    /// named `error`.
+    pub(crate) fn new_from_result_variant(
+        ty: Type,
+        error_index: usize,
+        error: Ident,
+        error_ty: Type,
+    ) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromResultVariantToUniquePtr(error_index),
+            rust_conversion: RustConversionType::FromUniquePtrToResult {
+                error,
+                error_ty: Box::new(error_ty.into()),
+            },
+        }
+    }
+
+    /// The parameter which receives the error from a `result_variant!`
+    /// function. `ty` is the `cxx::UniquePtr<E>`.
+    pub(crate) fn new_for_result_error(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &mut #ty },
+            cpp_conversion: CppConversionType::IgnoredResultVariantError,
+            rust_conversion: RustConversionType::ResultErrorOutParam,
+        }
+    }
+
+    /// A `std::tuple` return value from a `tuple_array!` function, whose
+    /// elements are all of type `ty`.
+    pub(crate) fn new_from_tuple_to_array(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromTupleToArray,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// The parameter into which a `tuple_array!` function's `len` tuple
+    /// elements are copied. `ty` is the `*mut T` to the first.
+    pub(crate) fn new_for_tuple_array(ty: Type, len: usize) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::IgnoredTupleArray,
+            rust_conversion: RustConversionType::FromTupleArrayOutParamToReturnValue(len),
+        }
+    }
+
+    /// A `std::pair` return value from a `pair_tuple!` function. `ty` is
+    /// the pair itself, which Rust never sees.
+    pub(crate) fn new_from_pair_to_elements(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromPairToElements,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// The parameter into which a `pair_tuple!` function's pair element at
+    /// `member` is moved. `ty` is the `*mut T` to it, or the
+    /// `&mut cxx::UniquePtr<T>` for a `std::unique_ptr`. The first such
+    /// parameter has the `shape` of the whole pair.
+    pub(crate) fn new_for_pair_element(ty: Type, member: String, shape: Option<PairShape>) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::IgnoredPairElement(member),
+            rust_conversion: RustConversionType::FromPairElementOutParamToReturnValue(shape),
+        }
+    }
+
+    /// The parameter into which a `pair_tuple!` function's `std::string`
+    /// pair element at `member` is converted, as a `*mut String`.
+    pub(crate) fn new_for_pair_string_element(member: String, shape: Option<PairShape>) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *mut String },
+            cpp_conversion: CppConversionType::IgnoredPairStringElement(member),
+            rust_conversion: RustConversionType::FromPairElementOutParamToReturnValue(shape),
+        }
+    }
+
+    /// A callback parameter to which Rust passes a closure. `ty` is the
+    /// function pointer which the closure becomes, described under
+    /// [`CallbackKind`].
+    pub(crate) fn new_for_callback(ty: Type, kind: CallbackKind) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromFnToCallback(kind.clone()),
+            rust_conversion: RustConversionType::FromClosureToCallbackFn(kind),
+        }
+    }
+
+    pub(crate) fn new_for_callback_context(callback: Ident) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { usize },
+            cpp_conversion: CppConversionType::IgnoredCallbackContext,
+            rust_conversion: RustConversionType::CallbackContext { callback },
+        }
+    }
+
+    /// An `integer_enum_params!` parameter of type `enum_ty`, which Rust
+    /// passes as its `underlying_type`. In debug builds the Rust wrapper
+    /// asserts that the value is one of `variants` before calling C++.
+    pub(crate) fn new_for_integer_enum(
+        enum_ty: Type,
+        underlying_type: &Ident,
+        variants: Vec<Ident>,
+    ) -> Self {
+        let enum_ty = Box::new(crate::minisyn::Type::from(enum_ty));
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { #underlying_type },
+            cpp_conversion: CppConversionType::FromIntegerToEnum(enum_ty.clone()),
+            rust_conversion: RustConversionType::FromIntegerRejectingUnknownEnumValues {
+                enum_ty,
+                variants,
+            },
+        }
+    }
+
+    /// A returned `bitmask_enum!` which must not contain any bits other
+    /// than its flags.
+    pub(crate) fn new_for_checked_bitmask(ty: Type) -> Self {
+        Self::new(
+            ty,
+            CppConversionType::None,
+            RustConversionType::FromBitmaskRejectingUnknownBits,
+        )
+    }
+
+    /// A `vector_slices!` return value of type `&CxxVector<T>`, which Rust
+    /// views as a `&[T]`.
+    pub(crate) fn new_from_vector_to_slice(ty: Type) -> Self {
+        Self::new(
+            ty,
+            CppConversionType::None,
+            RustConversionType::FromCppVectorToSlice,
+        )
+    }
+
+    /// A `cstr_returns!` return value, from either a `const char*` or, if
+    /// `from_string`, a `const std::string&`.
+    pub(crate) fn new_to_cstr(from_string: bool) -> Self {
+        Self::new(
+            parse_quote! { *const ::std::os::raw::c_char },
+            if from_string {
+                CppConversionType::FromStringToCharPointer
+            } else {
+                CppConversionType::None
+            },
+            RustConversionType::FromCharPointerToCStr,
+        )
+    }
+
+    /// A `cstr_string_returns!` return value, copied from a `const char*`
+    /// and then converted into a Rust `String` as `rust_string_returns!`
+    /// says.
+    pub(crate) fn new_from_char_pointer_to_string(policy: Utf8Policy) -> Self {
+        Self::new(
+            parse_quote! { cxx::CxxString },
+            CppConversionType::FromCharPointerToString,
+            RustConversionType::FromCxxStringToString(policy),
+        )
+    }
+
+    /// A `bitmask_params!` parameter: C++ takes an `integer_ty`, but Rust
+    /// passes the `bitmask_ty` which has the same layout.
+    pub(crate) fn new_for_bitmask_param(
+        bitmask_ty: Type,
+        integer_ty: Type,
+        reject_unknown_bits: bool,
+    ) -> Self {
+        Self::new(
+            bitmask_ty,
+            CppConversionType::FromBitmaskToInteger(Box::new(integer_ty.into())),
+            if reject_unknown_bits {
+                RustConversionType::FromBitmaskParamRejectingUnknownBits
+            } else {
+                RustConversionType::None
+            },
+        )
+    }
+
+    /// A `bitmask_enum!` parameter which must not contain any bits other
+    /// than its flags.
+    pub(crate) fn new_for_checked_bitmask_param(ty: Type) -> Self {
+        Self::new(
+            ty,
+            CppConversionType::None,
+            RustConversionType::FromBitmaskParamRejectingUnknownBits,
+        )
+    }
+
+    /// A returned `size_t` from an `output_buffers!` function, checked
+    /// against the length of the slice passed as `buffer`.
+    pub(crate) fn new_for_bytes_written(buffer: Ident) -> Self {
+        Self::new(
+            parse_quote! { usize },
+            CppConversionType::None,
+            RustConversionType::FromBytesWrittenToResult { buffer },
+        )
+    }
+
+    /// A returned `std::shared_ptr`. The C++ wrapper casts it to the
+    /// declared pointee type, so a factory returning `shared_ptr<Base>`
+    /// keeps `Base` as the static type whatever subclass it created.
+    ///
+    /// A returned `std::shared_ptr<const T>` from a function listed in
+    /// `mutable_shared_ptr_returns!`. cxx's `SharedPtr` can't express the
+    /// constness, so we cast it away on the C++ side, relying on the user's
+    /// promise that the object wasn't created `const`.
+    pub(crate) fn new_from_shared_ptr(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromSharedPtrDroppingConst,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `std::wstring` return value. `wchar_t` is 16 bits on Windows
+    /// (so the string is UTF-16) and 32 bits elsewhere (UTF-32); the C++
+    /// wrapper handles either, and replaces any invalid sequence such as
+    /// an unpaired surrogate with U+FFFD before handing Rust a `String`.
+    pub(crate) fn new_from_wstring() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { String },
+            cpp_conversion: CppConversionType::FromWStringToString,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `std::u16string` return value. This is always UTF-16, whatever the
+    /// platform, so the C++ wrapper just copies out the code units and the
+    /// Rust wrapper decodes them, dealing with any unpaired surrogates
+    /// according to `policy`.
+    pub(crate) fn new_from_u16string(policy: Utf16Policy) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { Vec<u16> },
+            cpp_conversion: CppConversionType::FromU16StringToVec,
+            rust_conversion: RustConversionType::FromUtf16ToString(policy),
+        }
+    }
+
+    /// A `std::u16string` parameter, passed from Rust as a `&str` which
+    /// the Rust wrapper encodes as UTF-16.
+    pub(crate) fn new_to_u16string() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &[u16] },
+            cpp_conversion: CppConversionType::FromSliceToU16String,
+            rust_conversion: RustConversionType::FromStrToUtf16,
+        }
+    }
+
+    /// The return value of a `nanoseconds_count!` function.
+    pub(crate) fn new_from_duration_to_nanoseconds_count() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { i64 },
+            cpp_conversion: CppConversionType::FromDurationToNanosecondsCount,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// The return value of a `seconds_count!` function.
+    pub(crate) fn new_from_duration_to_seconds_count(policy: SecondsCountPolicy) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { f64 },
+            cpp_conversion: CppConversionType::FromDurationToSecondsCount,
+            rust_conversion: match policy {
+                SecondsCountPolicy::AsF64 => RustConversionType::None,
+                SecondsCountPolicy::AsDuration => RustConversionType::FromSecondsToDuration,
+            },
+        }
+    }
+
+    /// A `std::wstring` parameter, passed from Rust as a `&str` and
+    /// transcoded into UTF-16 or UTF-32 depending on the platform.
+    pub(crate) fn new_to_wstring() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &str },
+            cpp_conversion: CppConversionType::FromStrToWString,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `std::string_view` return value. The `&str` borrows the same
+    /// characters, so the function must have exactly one reference input
+    /// from which the view could have come, in order that Rust ties the
+    /// lifetime of the return value to it. cxx's `rust::Str` would throw
+    /// if the view weren't UTF-8, so instead Rust is given the bytes and
+    /// checks them itself.
+    pub(crate) fn new_from_string_view() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &[u8] },
+            cpp_conversion: CppConversionType::FromStringViewToStr,
+            rust_conversion: RustConversionType::FromBytesToStr,
+        }
+    }
+
+    /// A `std::string_view` return value which may view a temporary, so is
+    /// copied before the C++ wrapper returns.
+    pub(crate) fn new_from_string_view_copy() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { String },
+            cpp_conversion: CppConversionType::FromStringViewToString,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `std::string_view` parameter, passed from Rust as a `&str`
+    /// without copying.
+    pub(crate) fn new_to_string_view() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &str },
+            cpp_conversion: CppConversionType::FromStrToStringView,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `const char*` parameter of a function listed in `str_params!`,
+    /// passed from Rust as a `&str`.
+    pub(crate) fn new_to_char_pointer() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &str },
+            cpp_conversion: CppConversionType::FromStrToCharPointer,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `const char*` parameter of a function listed in `cstr_params!`,
+    /// passed from Rust as a `&CStr` without copying.
+    pub(crate) fn new_from_cstr_to_char_pointer() -> Self {
+        Self::new(
+            parse_quote! { *const ::std::os::raw::c_char },
+            CppConversionType::None,
+            RustConversionType::FromCStrToCharPointer,
+        )
+    }
+
+    /// A `std::optional<std::string_view>` parameter, passed from Rust as
+    /// an `Option<&str>` without copying.
+    pub(crate) fn new_to_optional_string_view() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &[&str] },
+            cpp_conversion: CppConversionType::FromStrSliceToOptionalStringView,
+            rust_conversion: RustConversionType::FromOptionToSlice,
+        }
+    }
+
+    pub(crate) fn is_string_view_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromStringViewToStr
+                | CppConversionType::FromStringViewToString
+                | CppConversionType::FromStrToStringView
+                | CppConversionType::FromStrSliceToOptionalStringView
+        )
+    }
+
+    /// Whether the C++ wrapper views a Rust `&str` as a `std::string_view`,
+    /// using `autocxx_make_string_view`.
+    pub(crate) fn makes_string_view(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromStrToStringView
+                | CppConversionType::FromStrSliceToOptionalStringView
+        )
+    }
+
+    pub(crate) fn is_char_pointer_param_conversion(&self) -> bool {
+        matches!(self.cpp_conversion, CppConversionType::FromStrToCharPointer)
+    }
+
+    pub(crate) fn is_optional_string_view_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromStrSliceToOptionalStringView
+        )
+    }
+
+    pub(crate) fn is_borrowed_string_view_return(&self) -> bool {
+        matches!(self.cpp_conversion, CppConversionType::FromStringViewToStr)
+    }
+
+    /// Whether the C++ wrapper makes the C++ function's argument from a
+    /// value of its own, which is destroyed once the call is over.
+    pub(crate) fn passes_temporary(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromUniquePtrToValue
+                | CppConversionType::FromPtrToValue
+                | CppConversionType::FromPtrToMove
+                | CppConversionType::FromStrToWString
+                | CppConversionType::FromSliceToU16String
+                | CppConversionType::FromStrToCharPointer
+        )
+    }
+
+    pub(crate) fn is_slice_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromSliceToByteArray(_)
+                | CppConversionType::FromSliceToVoidPtrAndSize
+                | CppConversionType::FromSliceToPtr(_)
+        )
+    }
+
+    pub(crate) fn is_span_conversion(&self) -> bool {
+        matches!(self.cpp_conversion, CppConversionType::FromSliceToSpan)
+    }
+
+    pub(crate) fn is_optional_out_param_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromPtrToOptionalOutParam
+        )
+    }
+
+    pub(crate) fn is_callback_conversion(&self) -> bool {
+        matches!(self.cpp_conversion, CppConversionType::FromFnToCallback(_))
+    }
+
+    pub(crate) fn is_fallible_callback_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromFnToCallback(CallbackKind::Closure { fallible: true })
+        )
+    }
+
+    pub(crate) fn is_optional_return_flag(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::IgnoredOptionalReturnFlag
+        )
+    }
+
+    pub(crate) fn is_result_variant_error(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::IgnoredResultVariantError
+        )
+    }
+
+    pub(crate) fn is_result_variant_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromResultVariantToUniquePtr(_)
+        )
+    }
+
+    pub(crate) fn is_tuple_array(&self) -> bool {
+        matches!(self.cpp_conversion, CppConversionType::IgnoredTupleArray)
+    }
+
+    pub(crate) fn is_tuple_array_conversion(&self) -> bool {
+        matches!(self.cpp_conversion, CppConversionType::FromTupleToArray)
+    }
+
+    pub(crate) fn is_pair_element(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::IgnoredPairElement(_)
+                | CppConversionType::IgnoredPairStringElement(_)
+        )
+    }
+
+    pub(crate) fn is_pair_string_element(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::IgnoredPairStringElement(_)
+        )
+    }
+
+    pub(crate) fn is_pair_tuple_conversion(&self) -> bool {
+        matches!(self.cpp_conversion, CppConversionType::FromPairToElements)
+    }
+
+    pub(crate) fn is_duration_count_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromDurationToNanosecondsCount
+                | CppConversionType::FromDurationToSecondsCount
+        )
+    }
+
+    pub(crate) fn is_string_callback_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromUniquePtrToStringCallback
+        )
+    }
+
+    pub(crate) fn is_wstring_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromWStringToString | CppConversionType::FromStrToWString
+        )
+    }
+
+    pub(crate) fn is_u16string_conversion(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromU16StringToVec | CppConversionType::FromSliceToU16String
+        )
+    }
+
     pub(crate) fn new_for_placement_return(ty: Type) -> Self {
         TypeConversionPolicy {
             unwrapped_type: ty.into(),
@@ -159,13 +1299,27 @@ impl TypeConversionPolicy {
         }
     }
 
+    /// The type of the object this return value conversion gets out of
+    /// C++ by moving or copying it, if any.
+    pub(crate) fn returned_by_value_type(&self) -> Option<QualifiedName> {
+        match (&self.cpp_conversion, &*self.unwrapped_type) {
+            (
+                CppConversionType::FromReturnValueToPlacementPtr
+                | CppConversionType::FromValueToUniquePtr,
+                Type::Path(typ),
+            ) => Some(QualifiedName::from_type_path(typ)),
+            _ => None,
+        }
+    }
+
     pub(crate) fn cpp_work_needed(&self) -> bool {
         !matches!(self.cpp_conversion, CppConversionType::None)
     }
 
     pub(crate) fn unconverted_rust_type(&self) -> Type {
         match self.cpp_conversion {
-            CppConversionType::FromValueToUniquePtr => self.make_unique_ptr_type(),
+            CppConversionType::FromValueToUniquePtr
+            | CppConversionType::FromCharPointerToString => self.make_unique_ptr_type(),
             _ => self.unwrapped_type.clone().into(),
         }
     }
@@ -190,6 +1344,14 @@ impl TypeConversionPolicy {
         }
     }
 
+    /// Whether this is an `f32` or `f64` passed as-is to C++.
+    pub(crate) fn is_unconverted_float(&self) -> bool {
+        matches!(&*self.unwrapped_type, Type::Path(tp)
+            if tp.path.is_ident("f32") || tp.path.is_ident("f64"))
+            && !self.rust_work_needed()
+            && !self.cpp_work_needed()
+    }
+
     pub(crate) fn rust_work_needed(&self) -> bool {
         !matches!(self.rust_conversion, RustConversionType::None)
     }
@@ -227,6 +1389,8 @@ impl TypeConversionPolicy {
         !matches!(
             self.cpp_conversion,
             CppConversionType::FromReturnValueToPlacementPtr
+                | CppConversionType::FromTupleToArray
+                | CppConversionType::FromPairToElements
         )
     }
 }
@@ -234,10 +1398,51 @@ impl TypeConversionPolicy {
 #[derive(Clone, Debug)]
 pub(crate) enum CppFunctionBody {
     FunctionCall(Namespace, Ident),
+    /// Calls the function template, instantiated with the given C++ type.
+    TemplateCall(QualifiedName, String),
     StaticMethodCall(Namespace, Ident, Ident),
+    /// Returns the value of the given static data member of the given class.
+    StaticMemberAccess(Namespace, Ident, Ident),
     PlacementNew(Namespace, Ident),
     ConstructSuperclass(String),
     Cast,
+    /// Explicitly converts the sole argument to the return type, using
+    /// a C++ conversion operator such as `operator bool()`.
+    ConversionOperator,
+    /// Applies `operator!` to the sole argument, returning the result as a
+    /// `bool`.
+    LogicalNot,
+    /// Performs a three-way comparison of the two arguments, using their
+    /// `operator<=>` if they have one, or else `operator<`.
+    SpaceshipCall,
+    /// Likewise, but allowing for the arguments being unordered, and so
+    /// falling back to `operator<` and `operator==`.
+    PartialSpaceshipCall,
+    /// Compares the two arguments for equality using `operator==`.
+    EqualityCall,
+    /// Streams the sole argument into a `std::ostringstream` using
+    /// `operator<<`, returning the result in a new `std::unique_ptr`.
+    StreamInsertion,
+    /// Applies a compound assignment operator such as `+=` to the first
+    /// argument, with the second on the right hand side.
+    CompoundAssign(String),
+    /// Returns the reference which the `const` `operator[]` of the first
+    /// argument returns for the second.
+    SubscriptCall,
+    /// Returns the reference which the `operator*` of the sole argument
+    /// returns, calling the `const` one unless `mutable`.
+    Dereference {
+        mutable: bool,
+    },
+    /// Moves an element out of the vector given as the first argument, at
+    /// the index given as the second, into a new `std::unique_ptr`.
+    MoveOutOfVector,
+    /// Returns the `sizeof` the type, first asserting that it's the given
+    /// size, if any.
+    SizeOf(QualifiedName, Option<usize>),
+    /// Returns the `alignof` the type, first asserting that it's the given
+    /// alignment, if any.
+    AlignOf(QualifiedName, Option<usize>),
     Destructor(Namespace, Ident),
     AllocUninitialized(QualifiedName),
     FreeUninitialized(QualifiedName),
@@ -249,6 +1454,8 @@ pub(crate) enum CppFunctionKind {
     Method,
     Constructor,
     ConstMethod,
+    /// A method with a `volatile` (or `const volatile`) receiver.
+    VolatileMethod,
     SynthesizedConstructor,
 }
 
@@ -262,4 +1469,25 @@ pub(crate) struct CppFunction {
     pub(crate) kind: CppFunctionKind,
     pub(crate) pass_obs_field: bool,
     pub(crate) qualification: Option<QualifiedName>,
+    /// Whether the wrapper reports any C++ exception to Rust. Those
+    /// derived from `std::exception` pass straight through to cxx, which
+    /// turns them into an `Err`; anything else is first replaced by a
+    /// `std::runtime_error`.
+    pub(crate) catches_exceptions: bool,
+}
+
+impl CppFunction {
+    /// The namespace in which we generate the wrapper, if it's not the
+    /// global namespace. A wrapper which calls a free function goes in
+    /// the same namespace as that function, so that it can call it by
+    /// its unqualified name, and can't collide with a wrapper for a
+    /// function of the same name in another namespace.
+    pub(crate) fn wrapper_namespace(&self) -> Option<&Namespace> {
+        match (&self.payload, &self.kind) {
+            (CppFunctionBody::FunctionCall(ns, _), CppFunctionKind::Function) if !ns.is_empty() => {
+                Some(ns)
+            }
+            _ => None,
+        }
+    }
 }
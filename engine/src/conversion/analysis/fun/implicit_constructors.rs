@@ -567,6 +567,40 @@ pub(super) fn find_constructors_present(
     all_items_found
 }
 
+/// Finds the types which can be neither moved nor copied, so which no
+/// function can return by value. We only conclude that for types which
+/// declare a move or copy constructor themselves, deleted or not public:
+/// for other types, we may not know enough about their members.
+pub(super) fn find_immovable_types(
+    apis: &ApiVec<FnPrePhase1>,
+    all_items_found: &HashMap<QualifiedName, ItemsFound>,
+) -> HashSet<QualifiedName> {
+    let (explicits, _) = find_explicit_items(apis);
+    all_items_found
+        .iter()
+        .filter(|(ty, items_found)| {
+            !items_found.move_constructor.callable_any()
+                && !items_found.const_copy_constructor.callable_any()
+                && !items_found.non_const_copy_constructor.callable_any()
+                && [
+                    ExplicitKind::MoveConstructor,
+                    ExplicitKind::ConstCopyConstructor,
+                ]
+                .into_iter()
+                .any(|kind| {
+                    matches!(
+                        explicits.get(&ExplicitType {
+                            ty: (*ty).clone(),
+                            kind,
+                        }),
+                        Some(ExplicitFound::Deleted | ExplicitFound::UserDefined(_))
+                    )
+                })
+        })
+        .map(|(ty, _)| ty.clone())
+        .collect()
+}
+
 fn find_explicit_items(
     apis: &ApiVec<FnPrePhase1>,
 ) -> (HashMap<ExplicitType, ExplicitFound>, HashSet<QualifiedName>) {
@@ -27,6 +27,18 @@ use crate::{
         convert_error::ErrorContext,
         convert_error::{ConvertErrorWithContext, ErrorContextType},
         error_reporter::{convert_apis, report_any_error},
+        type_helpers::{
+            extract_byte_array_len, extract_cxx_vector_reference_item,
+            extract_intrusive_ptr_pointee, extract_map_key_and_value, extract_pair_elements,
+            extract_pinned_mutable_reference_type, extract_reference_wrapper_type,
+            extract_shared_ptr_type, extract_std_optional_type, extract_string_map_value,
+            extract_tuple_elements, extract_unordered_map_key_and_value,
+            extract_variant_alternatives, integer_range, is_char_pointer, is_const_char_pointer,
+            is_cxx_string_reference, is_cxx_string_vector, is_std_chrono_duration,
+            is_std_optional_string_view, is_std_string, is_std_string_view, is_std_u16string,
+            is_std_vector, is_std_wstring, is_unique_ptr, is_unique_ptr_vector,
+            map_snapshot_value_type,
+        },
     },
     known_types::known_types,
     minisyn::minisynize_punctuated,
@@ -35,11 +47,16 @@ use crate::{
 use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 
-use autocxx_parser::{ExternCppType, IncludeCppConfig, UnsafePolicy};
-use function_wrapper::{CppFunction, CppFunctionBody, TypeConversionPolicy};
+use autocxx_parser::{
+    ClosureCallback, ExternCppType, IncludeCppConfig, SecondsCountPolicy, StdArray, StdSpan,
+    UnsafePolicy,
+};
+use function_wrapper::{
+    CallbackKind, CppFunction, CppFunctionBody, PairShape, SliceBound, TypeConversionPolicy,
+};
 use itertools::Itertools;
 use proc_macro2::Span;
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::{
     parse_quote, punctuated::Punctuated, token::Comma, FnArg, Ident, Pat, PatType, ReturnType,
     Type, TypePath, TypePtr, TypeReference, Visibility,
@@ -56,7 +73,7 @@ use crate::{
 use self::{
     bridge_name_tracker::BridgeNameTracker,
     function_wrapper::RustConversionType,
-    implicit_constructors::{find_constructors_present, ItemsFound},
+    implicit_constructors::{find_constructors_present, find_immovable_types, ItemsFound},
     overload_tracker::OverloadTracker,
     subclass::{
         create_subclass_constructor, create_subclass_fn_wrapper, create_subclass_function,
@@ -65,10 +82,12 @@ use self::{
 };
 
 use super::{
+    default_args::default_arg_form_rust_name,
     depth_first::HasFieldsAndBases,
     doc_label::make_doc_attrs,
     pod::{PodAnalysis, PodPhase},
     tdef::TypedefAnalysis,
+    trait_probes::TraitProbeResults,
     type_converter::{Annotated, PointerTreatment},
 };
 
@@ -172,6 +191,12 @@ pub(crate) struct FnAnalysis {
     pub(crate) externally_callable: bool,
     /// Whether we need to generate a Rust-side calling function
     pub(crate) rust_wrapper_needed: bool,
+    /// Whether this is a `catch_exceptions!` function, which returns a
+    /// `Result<T, cxx::Exception>`.
+    pub(crate) catches_exceptions: bool,
+    /// Whether this is a `borrow_from_receiver!` method, whose returned
+    /// reference borrows from the receiver alone.
+    pub(crate) borrows_from_receiver: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -193,6 +218,8 @@ pub(crate) struct ReturnTypeAnalysis {
     was_mutable_reference: bool,
     deps: HashSet<QualifiedName>,
     placement_param_needed: Option<(FnArg, ArgumentAnalysis)>,
+    /// Out parameters for the elements of a returned `std::pair`.
+    pair_element_params_needed: Vec<(FnArg, ArgumentAnalysis)>,
 }
 
 impl Default for ReturnTypeAnalysis {
@@ -204,6 +231,7 @@ impl Default for ReturnTypeAnalysis {
             was_mutable_reference: false,
             deps: Default::default(),
             placement_param_needed: None,
+            pair_element_params_needed: Vec::new(),
         }
     }
 }
@@ -270,6 +298,32 @@ impl AnalysisPhase for FnPhase {
     type FunAnalysis = FnAnalysis;
 }
 
+/// The names of the helper functions, generated for concrete map types,
+/// which functions listed in `map_snapshot!` or `map_from_iter!` call to
+/// copy a `std::map` out or build one. Others aren't generated.
+pub(crate) fn find_map_helpers(apis: &ApiVec<FnPhase>) -> HashSet<String> {
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Function { analysis, .. } if analysis.ignore_reason.is_ok() => Some(analysis),
+            _ => None,
+        })
+        .flat_map(|analysis| {
+            analysis
+                .param_details
+                .iter()
+                .map(|pd| &pd.conversion)
+                .chain(analysis.ret_conversion.as_ref())
+        })
+        .filter_map(|conversion| match &conversion.rust_conversion {
+            RustConversionType::FromStringMapToHashMap { snapshot, .. } => {
+                Some(snapshot.to_string())
+            }
+            RustConversionType::FromIteratorToMap { new, .. } => Some(new.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
 /// Whether to allow highly optimized calls because this is a simple Rust->C++ call,
 /// or to use a simpler set of policies because this is a subclass call where
 /// we may have C++->Rust->C++ etc.
@@ -293,6 +347,10 @@ pub(crate) struct FnAnalyzer<'a> {
     generic_types: HashSet<QualifiedName>,
     types_in_anonymous_namespace: HashSet<QualifiedName>,
     existing_superclass_trait_api_names: HashSet<QualifiedName>,
+    trait_probes: &'a TraitProbeResults,
+    /// The underlying type and variants of each enum which isn't a
+    /// `bitmask_enum!`, for `integer_enum_params!`.
+    integer_enums: HashMap<QualifiedName, (crate::minisyn::Ident, Vec<crate::minisyn::Ident>)>,
     force_wrapper_generation: bool,
 }
 
@@ -301,6 +359,7 @@ impl<'a> FnAnalyzer<'a> {
         apis: ApiVec<PodPhase>,
         unsafe_policy: &'a UnsafePolicy,
         config: &'a IncludeCppConfig,
+        trait_probes: &'a TraitProbeResults,
         force_wrapper_generation: bool,
     ) -> ApiVec<FnPrePhase2> {
         let mut me = Self {
@@ -317,6 +376,8 @@ impl<'a> FnAnalyzer<'a> {
             generic_types: Self::build_generic_type_set(&apis),
             existing_superclass_trait_api_names: HashSet::new(),
             types_in_anonymous_namespace: Self::build_types_in_anonymous_namespace(&apis),
+            trait_probes,
+            integer_enums: Self::build_integer_enum_map(&apis, config),
             force_wrapper_generation,
         };
         let mut results = ApiVec::new();
@@ -400,6 +461,30 @@ impl<'a> FnAnalyzer<'a> {
             .collect()
     }
 
+    fn build_integer_enum_map(
+        apis: &ApiVec<PodPhase>,
+        config: &IncludeCppConfig,
+    ) -> HashMap<QualifiedName, (crate::minisyn::Ident, Vec<crate::minisyn::Ident>)> {
+        apis.iter()
+            .filter_map(|api| match api {
+                Api::Enum { name, item } if !config.is_bitmask_enum(&name.name.to_cpp_name()) => {
+                    let underlying_type = item
+                        .attrs
+                        .iter()
+                        .filter(|attr| attr.path().is_ident("repr"))
+                        .find_map(|attr| attr.parse_args::<Ident>().ok())?;
+                    let variants = item
+                        .variants
+                        .iter()
+                        .map(|variant| variant.ident.clone().into())
+                        .collect();
+                    Some((name.name.clone(), (underlying_type.into(), variants)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     fn build_types_in_anonymous_namespace(apis: &ApiVec<PodPhase>) -> HashSet<QualifiedName> {
         apis.iter()
             .filter_map(|api| match api {
@@ -737,12 +822,75 @@ impl<'a> FnAnalyzer<'a> {
         let initial_rust_name = fun.ident.to_string();
         let diagnostic_display_name = cpp_name.as_ref().unwrap_or(&initial_rust_name);
 
+        // Functions listed in `string_callback!` deliver their result by
+        // calling their final parameter with a string. We don't bind that
+        // parameter at all: the C++ wrapper passes its own callback, which
+        // stores the string for Rust to return. We need to know this before
+        // analyzing parameters, because the callback's type typically can't
+        // be represented in Rust.
+        let free_function_name = (fun.self_ty.is_none()
+            && !fun.inputs.iter().any(|i| is_this_param(i)))
+        .then(|| QualifiedName::new(ns, make_ident(name.cpp_name())).to_cpp_name());
+        let void_free_function_name = free_function_name
+            .clone()
+            .filter(|_| matches!(*fun.output, ReturnType::Default));
+        let string_callback_param = void_free_function_name
+            .as_ref()
+            .filter(|name| self.config.is_string_callback_function(name))
+            .and_then(|_| fun.inputs.last());
+        // Similarly, the final parameter of a function listed in
+        // `comparator!` is replaced by a Rust closure.
+        let comparator_param = free_function_name
+            .as_ref()
+            .filter(|_| string_callback_param.is_none())
+            .and_then(|name| self.config.get_comparator_element_type(name))
+            .map(str::to_owned)
+            .zip(fun.inputs.last());
+        // And that of a function listed in `allocator_callback!`.
+        let allocator_param = free_function_name
+            .as_ref()
+            .filter(|_| string_callback_param.is_none() && comparator_param.is_none())
+            .filter(|name| self.config.is_allocator_callback_function(name))
+            .and_then(|_| fun.inputs.last());
+        // And that of a function listed in `closure_callback!`.
+        let closure_callback_param = free_function_name
+            .as_ref()
+            .filter(|_| {
+                string_callback_param.is_none()
+                    && comparator_param.is_none()
+                    && allocator_param.is_none()
+            })
+            .and_then(|name| self.config.get_closure_callback(name))
+            .cloned()
+            .zip(fun.inputs.last());
+        let bound_input_count = fun.inputs.len()
+            - usize::from(
+                string_callback_param.is_some()
+                    || comparator_param.is_some()
+                    || allocator_param.is_some()
+                    || closure_callback_param.is_some(),
+            );
+        // Likewise, the `std::optional<T>&` parameters of functions listed in
+        // `out_params!` can't be represented in Rust, so need special
+        // treatment before normal parameter analysis.
+        let has_out_params = void_free_function_name
+            .as_ref()
+            .is_some_and(|name| self.config.is_out_param_function(name));
+
         // Now let's analyze all the parameters.
         // See if any have annotations which our fork of bindgen has craftily inserted...
         let (param_details, bads): (Vec<_>, Vec<_>) = fun
             .inputs
             .iter()
+            .take(bound_input_count)
             .map(|i| {
+                if has_out_params {
+                    if let Some(optional_out_params) =
+                        self.convert_optional_out_param(i, ns, &fun.references)
+                    {
+                        return Ok(optional_out_params);
+                    }
+                }
                 self.convert_fn_arg(
                     i,
                     ns,
@@ -755,6 +903,7 @@ impl<'a> FnAnalyzer<'a> {
                     sophistication,
                     false,
                 )
+                .map(|param| vec![param])
                 .map_err(|err| ConvertErrorFromCpp::Argument {
                     arg: describe_arg(i),
                     err: Box::new(err),
@@ -762,7 +911,7 @@ impl<'a> FnAnalyzer<'a> {
             })
             .partition(Result::is_ok);
         let (mut params, mut param_details): (Punctuated<_, Comma>, Vec<_>) =
-            param_details.into_iter().map(Result::unwrap).unzip();
+            param_details.into_iter().flat_map(Result::unwrap).unzip();
 
         let params_deps: HashSet<_> = param_details
             .iter()
@@ -799,6 +948,10 @@ impl<'a> FnAnalyzer<'a> {
                 }
             }
         };
+        // `default_args!` may instead name each form of a function after
+        // the parameters it takes.
+        let ideal_rust_name = default_arg_form_rust_name(self.config, &name, fun, &ideal_rust_name)
+            .unwrap_or(ideal_rust_name);
 
         // Let's spend some time figuring out the kind of this function (i.e. method,
         // virtual function, etc.)
@@ -1048,8 +1201,14 @@ impl<'a> FnAnalyzer<'a> {
             }
             FnKind::TraitMethod {
                 kind: TraitMethodKind::CopyConstructor,
+                ref impl_for,
                 ..
             } => {
+                if !self.trait_probes.is_copy_constructible(impl_for) {
+                    set_ignore_reason(ConvertErrorFromCpp::FailedTraitProbe(
+                        "std::is_copy_constructible",
+                    ));
+                }
                 if param_details.len() < 2 {
                     set_ignore_reason(ConvertErrorFromCpp::ConstructorWithOnlyOneParam);
                 }
@@ -1073,8 +1232,14 @@ impl<'a> FnAnalyzer<'a> {
 
             FnKind::TraitMethod {
                 kind: TraitMethodKind::MoveConstructor,
+                ref impl_for,
                 ..
             } => {
+                if !self.trait_probes.is_move_constructible(impl_for) {
+                    set_ignore_reason(ConvertErrorFromCpp::FailedTraitProbe(
+                        "std::is_move_constructible",
+                    ));
+                }
                 if param_details.len() < 2 {
                     set_ignore_reason(ConvertErrorFromCpp::ConstructorWithOnlyOneParam);
                 }
@@ -1123,8 +1288,10 @@ impl<'a> FnAnalyzer<'a> {
             CppVisibility::Protected => false,
             CppVisibility::Public => true,
         };
+        // We could in theory bind a variadic function's named parameters
+        // alone, but for now we don't bind it at all.
         if fun.variadic {
-            set_ignore_reason(ConvertErrorFromCpp::Variadic);
+            set_ignore_reason(ConvertErrorFromCpp::Variadic(rust_name.clone()));
         }
         if let Some(problem) = bads.into_iter().next() {
             match problem {
@@ -1196,14 +1363,64 @@ impl<'a> FnAnalyzer<'a> {
         }
         let mut cxxbridge_name = make_ident(&cxxbridge_name);
 
+        // The name by which directives such as `out_params!` refer to this
+        // function.
+        let qualified_cpp_name = match &kind {
+            FnKind::Method { impl_for, .. } | FnKind::TraitMethod { impl_for, .. } => {
+                format!("{}::{}", impl_for.to_cpp_name(), name.cpp_name())
+            }
+            FnKind::Function => QualifiedName::new(ns, make_ident(name.cpp_name())).to_cpp_name(),
+        };
+
         // Analyze the return type, just as we previously did for the
-        // parameters.
-        let mut return_analysis = self
-            .convert_return_type(&fun.output, ns, &fun.references, sophistication)
-            .unwrap_or_else(|err| {
-                set_ignore_reason(err);
-                ReturnTypeAnalysis::default()
-            });
+        // parameters. Functions listed in `result_variant!` return a
+        // `std::variant` which becomes a `Result`, those in
+        // `tuple_array!` a `std::tuple` which becomes an array, those in
+        // `pair_tuple!` a `std::pair` which becomes a tuple, those in
+        // `std_array!` a `std::array` which becomes an array, those in
+        // `nanoseconds_count!` a `std::chrono::duration` which becomes an
+        // `i64`, those in `seconds_count!` one which becomes an `f64` or a
+        // `Duration`, and those in `vector_slices!` a `const std::vector<T>&`
+        // which becomes a `&[T]`.
+        let result_variant_error_type = self
+            .config
+            .get_result_variant_error_type(&qualified_cpp_name)
+            .map(str::to_owned);
+        let std_array_return = self.config.get_std_array(&qualified_cpp_name, "return");
+        let seconds_count_policy = self.config.get_seconds_count_policy(&qualified_cpp_name);
+        let mut return_analysis = match result_variant_error_type {
+            Some(error_type) => {
+                self.convert_result_variant_return_type(&fun.output, &error_type, ns)
+            }
+            None if self.config.returns_tuple_as_array(&qualified_cpp_name) => {
+                self.convert_tuple_array_return_type(&fun.output, ns)
+            }
+            None if self.config.returns_pair_as_tuple(&qualified_cpp_name) => {
+                self.convert_pair_tuple_return_type(&fun.output, ns)
+            }
+            None if std_array_return.is_some() => {
+                self.convert_std_array_return_type(&fun.output, std_array_return.unwrap(), ns)
+            }
+            None if self.config.returns_nanoseconds_count(&qualified_cpp_name) => {
+                Self::convert_nanoseconds_count_return_type(&fun.output)
+            }
+            None if seconds_count_policy.is_some() => {
+                Self::convert_seconds_count_return_type(&fun.output, seconds_count_policy.unwrap())
+            }
+            None if self.config.returns_vector_slice(&qualified_cpp_name) => self
+                .convert_vector_slice_return_type(
+                    &fun.output,
+                    ns,
+                    &fun.references,
+                    sophistication,
+                    &qualified_cpp_name,
+                ),
+            None => self.convert_return_type(&fun.output, ns, &fun.references, sophistication),
+        }
+        .unwrap_or_else(|err| {
+            set_ignore_reason(err);
+            ReturnTypeAnalysis::default()
+        });
         let mut deps = params_deps;
         deps.extend(return_analysis.deps.drain(..));
 
@@ -1214,13 +1431,483 @@ impl<'a> FnAnalyzer<'a> {
             param_details.push(extra_param_details);
             params.push(extra_param);
         }
+        for (extra_param, extra_param_details) in return_analysis.pair_element_params_needed {
+            param_details.push(extra_param_details);
+            params.push(extra_param);
+        }
+
+        // For functions listed in `out_params!`, any `std::unique_ptr<T>&`
+        // parameters which the C++ fills in are returned to Rust instead.
+        if matches!(kind, FnKind::Function)
+            && matches!(return_analysis.rt, ReturnType::Default)
+            && self.config.is_out_param_function(&qualified_cpp_name)
+        {
+            Self::convert_unique_ptr_out_params(&mut params, &mut param_details);
+        }
+
+        if let Some(FnArg::Typed(pt)) = string_callback_param.map(|i| &**i) {
+            let (param, pd) = self.string_callback_out_param(pt.pat.as_ref().clone());
+            params.push(param);
+            param_details.push(pd);
+        }
+
+        if let Some((element_type, FnArg::Typed(pt))) = comparator_param.map(|(e, i)| (e, &**i)) {
+            match self.comparator_params(pt.pat.as_ref(), &element_type, ns) {
+                Ok(comparator_params) => {
+                    for (param, pd) in comparator_params {
+                        deps.extend(pd.deps.iter().cloned());
+                        params.push(param);
+                        param_details.push(pd);
+                    }
+                }
+                Err(err) => set_ignore_reason(err),
+            }
+        }
+
+        if let Some(FnArg::Typed(pt)) = allocator_param.map(|i| &**i) {
+            for (param, pd) in Self::allocator_params(pt.pat.as_ref()) {
+                params.push(param);
+                param_details.push(pd);
+            }
+        }
+
+        if let Some((callback, FnArg::Typed(pt))) = closure_callback_param.map(|(c, i)| (c, &**i)) {
+            match self.closure_callback_params(pt.pat.as_ref(), &callback, ns) {
+                Ok(closure_callback_params) => {
+                    for (param, pd) in closure_callback_params {
+                        deps.extend(pd.deps.iter().cloned());
+                        params.push(param);
+                        param_details.push(pd);
+                    }
+                }
+                Err(err) => set_ignore_reason(err),
+            }
+        }
+
+        // For functions listed in `map_snapshot!`, a returned
+        // `std::map<std::string, T>` is copied into a Rust `HashMap`, so long
+        // as `T` can be copied. Otherwise it stays an opaque type.
+        if self.config.is_map_snapshot_function(&qualified_cpp_name) {
+            if let Some(conversion) = self.map_snapshot_conversion(&fun.output, &return_analysis.rt)
+            {
+                return_analysis.conversion = Some(conversion);
+            }
+        }
+
+        // For functions listed in `null_vector!`, a returned
+        // `std::unique_ptr<std::vector<T>>` which may be null becomes either
+        // an `Option` or, if null, an empty vector. Either way the vector
+        // itself reaches Rust as a `UniquePtr<CxxVector<T>>`, just as it
+        // would without the directive.
+        if let Some(policy) = self.config.get_null_vector_policy(&qualified_cpp_name) {
+            if let ReturnType::Type(_, ty) = &return_analysis.rt {
+                if is_unique_ptr_vector(ty) && !return_analysis.was_reference {
+                    return_analysis.conversion = Some(TypeConversionPolicy::new_for_null_vector(
+                        ty.as_ref().clone(),
+                        policy,
+                    ));
+                }
+            }
+        }
+
+        // For functions listed in `sentinel_none!`, a returned integer which
+        // is equal to the sentinel becomes `None`, and any other `Some`. The
+        // C++ is none the wiser.
+        if let Some(sentinel) = self.config.get_none_sentinel(&qualified_cpp_name) {
+            if let ReturnType::Type(_, ty) = &return_analysis.rt {
+                match integer_range(ty) {
+                    Some((min, max))
+                        if !return_analysis.was_reference
+                            && return_analysis.conversion.is_none() =>
+                    {
+                        if (min..=max).contains(&sentinel) {
+                            return_analysis.conversion =
+                                Some(TypeConversionPolicy::new_for_sentinel(
+                                    ty.as_ref().clone(),
+                                    sentinel,
+                                ));
+                        } else {
+                            set_ignore_reason(ConvertErrorFromCpp::SentinelOutOfRange(
+                                sentinel,
+                                ty.to_token_stream().to_string(),
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // For functions listed in `string_vec!`, a returned
+        // `std::vector<std::string>` is copied into a Rust `Vec`, each element
+        // becoming a string just as a returned `std::string` would under
+        // `rust_string_returns!`.
+        if let Some(policy) = self.config.get_string_vec_policy(&qualified_cpp_name) {
+            if let ReturnType::Type(_, ty) = &return_analysis.rt {
+                if is_cxx_string_vector(ty) && !return_analysis.was_reference {
+                    return_analysis.conversion = Some(TypeConversionPolicy::new_for_string_vec(
+                        ty.as_ref().clone(),
+                        policy,
+                    ));
+                }
+            }
+        }
+
+        // For functions listed in `map_from_iter!`, a `const std::map<K, V>&`
+        // or `const std::unordered_map<K, V>&` parameter instead accepts an
+        // iterator of `(K, V)` pairs, so long as `K` and `V` can be passed by
+        // value. Otherwise it stays a reference to an opaque type. The map
+        // only lives as long as the call, so nothing returned may borrow
+        // from it.
+        if self.config.is_map_from_iter_function(&qualified_cpp_name)
+            && !return_analysis.was_reference
+        {
+            for pd in param_details.iter_mut() {
+                if let Some(conversion) = self.map_from_iter_conversion(&fun.inputs, pd) {
+                    pd.conversion = conversion;
+                }
+            }
+        }
+
+        // A parameter which is an intrusive pointer, such as a
+        // `boost::intrusive_ptr`, to a type listed in `intrusive_ptr!` takes
+        // an `autocxx::IntrusivePtr` instead. C++ adopts its reference.
+        if matches!(sophistication, TypeConversionSophistication::Regular) {
+            for pd in param_details.iter_mut() {
+                if let Some(conversion) = self.intrusive_ptr_param_conversion(&fun.inputs, pd) {
+                    pd.conversion = conversion;
+                    pd.has_lifetime = false;
+                    pd.requires_unsafe = UnsafetyNeeded::JustBridge;
+                }
+            }
+        }
+
+        // Functions listed in `byte_slices!` take a `&[u8]` in place of each
+        // `const void*` parameter followed by a `size_t` length. This must
+        // happen before the other pointer conversions below.
+        if matches!(
+            kind,
+            FnKind::Function
+                | FnKind::Method {
+                    method_kind: MethodKind::Normal | MethodKind::Static,
+                    ..
+                }
+        ) && self.config.takes_byte_slices(&qualified_cpp_name)
+        {
+            Self::convert_pointers_to_slices(
+                &mut params,
+                &mut param_details,
+                is_slice_length,
+                |ty| {
+                    void_pointer_mutability(ty)
+                        .map(|is_mut| (is_mut, TypeConversionPolicy::new_for_byte_slice(is_mut)))
+                },
+            );
+        }
+
+        // Those listed in `slices!` similarly take a `&[T]` in place of
+        // each `const T*` followed by a `size_t`, if `T` is a number or POD
+        // type which Rust can simply view in place.
+        if matches!(
+            kind,
+            FnKind::Function
+                | FnKind::Method {
+                    method_kind: MethodKind::Normal | MethodKind::Static,
+                    ..
+                }
+        ) && self.config.takes_slices(&qualified_cpp_name)
+        {
+            Self::convert_pointers_to_slices(
+                &mut params,
+                &mut param_details,
+                is_slice_length,
+                |ty| self.pod_slice_conversion(ty, SliceBound::Size),
+            );
+        }
+
+        // Functions listed in `iterator_ranges!` take a `&[T]` or `&mut [T]`
+        // in place of each named pair of pointers giving the beginning and
+        // end of a range.
+        let iterator_ranges: Vec<_> = self
+            .config
+            .get_iterator_ranges(&qualified_cpp_name)
+            .collect();
+        if !iterator_ranges.is_empty() {
+            let is_listed_range = |begin: &PatType, end: &PatType| {
+                begin.ty == end.ty
+                    && iterator_ranges
+                        .iter()
+                        .any(|ir| pat_is(&begin.pat, &ir.begin) && pat_is(&end.pat, &ir.end))
+            };
+            let ranges = if matches!(
+                kind,
+                FnKind::Function
+                    | FnKind::Method {
+                        method_kind: MethodKind::Normal | MethodKind::Static,
+                        ..
+                    }
+            ) {
+                Self::convert_pointers_to_slices(
+                    &mut params,
+                    &mut param_details,
+                    is_listed_range,
+                    |ty| self.pod_slice_conversion(ty, SliceBound::EndPtr),
+                )
+            } else {
+                Vec::new()
+            };
+            if let Some(missing) = iterator_ranges
+                .iter()
+                .find(|ir| !ranges.iter().any(|begin| *begin == ir.begin))
+            {
+                set_ignore_reason(ConvertErrorFromCpp::NotAnIteratorRange(
+                    missing.begin.clone(),
+                    missing.end.clone(),
+                ));
+            }
+        }
+
+        // Functions listed in `output_buffers!` instead take a `&mut [u8]`
+        // in place of each `uint8_t*` or `void*` followed by its capacity.
+        // If there's exactly one such buffer, a returned `size_t` is the
+        // number of bytes written to it, which we check fits.
+        if matches!(
+            kind,
+            FnKind::Function
+                | FnKind::Method {
+                    method_kind: MethodKind::Normal | MethodKind::Static,
+                    ..
+                }
+        ) && self.config.writes_output_buffers(&qualified_cpp_name)
+        {
+            let buffers = Self::convert_pointers_to_slices(
+                &mut params,
+                &mut param_details,
+                is_slice_length,
+                |ty| {
+                    output_buffer_pointer(ty)
+                        .map(|is_mut| (is_mut, TypeConversionPolicy::new_for_byte_slice(is_mut)))
+                },
+            );
+            let returns_usize = match &return_analysis.rt {
+                ReturnType::Type(_, ty) => {
+                    matches!(ty.as_ref(), Type::Path(tp) if tp.path.is_ident("usize"))
+                }
+                ReturnType::Default => false,
+            };
+            if let [buffer] = buffers.as_slice() {
+                if returns_usize && return_analysis.conversion.is_none() {
+                    return_analysis.conversion = Some(TypeConversionPolicy::new_for_bytes_written(
+                        buffer.clone().into(),
+                    ));
+                }
+            }
+        }
+
+        // Functions listed in `cstr_returns!` return a `&CStr` borrowing
+        // the characters of a returned `const char*` or `const std::string&`.
+        // That's a reference, so it must borrow from a reference parameter,
+        // which the checks below insist on.
+        if self.config.returns_cstr(&qualified_cpp_name)
+            && !return_analysis
+                .conversion
+                .as_ref()
+                .is_some_and(|conv| conv.cpp_work_needed() || conv.rust_work_needed())
+        {
+            let from_string = match &return_analysis.rt {
+                ReturnType::Type(_, ty) if is_const_char_pointer(ty) => Some(false),
+                ReturnType::Type(_, ty) if is_cxx_string_reference(ty) => Some(true),
+                _ => None,
+            };
+            if let Some(from_string) = from_string {
+                return_analysis.rt = parse_quote! { -> *const ::std::os::raw::c_char };
+                return_analysis.conversion = Some(TypeConversionPolicy::new_to_cstr(from_string));
+                return_analysis.was_reference = true;
+            }
+        }
+
+        // bindgen doesn't tell us whether a returned `std::shared_ptr` points
+        // to a const object, and cxx's `SharedPtr` can't express it either,
+        // so a returned `std::shared_ptr<const T>` doesn't compile. Functions
+        // listed in `mutable_shared_ptr_returns!` promise the object isn't
+        // really const, so we may cast the constness away.
+        if self.config.returns_mutable_shared_ptr(&qualified_cpp_name)
+            && !return_analysis
+                .conversion
+                .as_ref()
+                .is_some_and(|conv| conv.cpp_work_needed() || conv.rust_work_needed())
+        {
+            if let ReturnType::Type(_, ty) = &return_analysis.rt {
+                if extract_shared_ptr_type(ty).is_some() && !return_analysis.was_reference {
+                    return_analysis.conversion = Some(TypeConversionPolicy::new_from_shared_ptr(
+                        ty.as_ref().clone(),
+                    ));
+                }
+            }
+        }
+
+        // Functions listed in `cstr_string_returns!` instead copy the
+        // characters of a returned `const char*` into a Rust `String`, so
+        // nothing is borrowed. A null pointer gives an empty string.
+        if self.config.returns_cstr_as_string(&qualified_cpp_name)
+            && !return_analysis
+                .conversion
+                .as_ref()
+                .is_some_and(|conv| conv.cpp_work_needed() || conv.rust_work_needed())
+            && matches!(&return_analysis.rt, ReturnType::Type(_, ty) if is_char_pointer(ty))
+        {
+            return_analysis.conversion =
+                Some(TypeConversionPolicy::new_from_char_pointer_to_string(
+                    self.config.string_return_policy.unwrap_or_default(),
+                ));
+        }
+
+        // Functions listed in `nullable_returns!` return an `Option<&T>` in
+        // place of a `const T*`, which is `None` if the pointer is null. Like
+        // a returned reference, it must borrow from a reference parameter.
+        // A returned `std::unique_ptr<T>` likewise becomes an
+        // `Option<UniquePtr<T>>`, which owns the object as before. So does
+        // the pointer returned by a `subscript!` method.
+        if (self.config.returns_nullable(&qualified_cpp_name)
+            || matches!(fun.synthetic_cpp, Some((CppFunctionBody::SubscriptCall, _))))
+            && !return_analysis
+                .conversion
+                .as_ref()
+                .is_some_and(|conv| conv.cpp_work_needed() || conv.rust_work_needed())
+        {
+            if let ReturnType::Type(_, ty) = &return_analysis.rt {
+                if is_unique_ptr(ty) && !return_analysis.was_reference {
+                    return_analysis.conversion = Some(
+                        TypeConversionPolicy::new_for_nullable_unique_ptr(ty.as_ref().clone()),
+                    );
+                } else if matches!(
+                    ty.as_ref(),
+                    Type::Ptr(TypePtr {
+                        mutability: None,
+                        ..
+                    })
+                ) && !matches!(
+                    self.config.unsafe_policy,
+                    UnsafePolicy::ReferencesWrappedAllFunctionsSafe
+                ) {
+                    return_analysis.conversion = Some(
+                        TypeConversionPolicy::new_to_optional_reference(ty.as_ref().clone()),
+                    );
+                    return_analysis.was_reference = true;
+                }
+            }
+        }
+
+        // Functions listed in `str_params!` take a `&str` in place of each
+        // `const char*` parameter, and those in `cstr_params!` a `&CStr`.
+        if matches!(
+            kind,
+            FnKind::Function
+                | FnKind::Method {
+                    method_kind: MethodKind::Normal | MethodKind::Static,
+                    ..
+                }
+        ) {
+            if self.config.takes_str_params(&qualified_cpp_name) {
+                Self::convert_char_pointers_to_strs(&mut params, &mut param_details);
+            } else if self.config.takes_cstr_params(&qualified_cpp_name) {
+                Self::convert_char_pointers_to_cstrs(&mut params, &mut param_details);
+            }
+        }
+
+        // C++ doesn't consider the outer `const` of a `const T* const`
+        // parameter part of the function's type, so we can't see it.
+        // Functions listed in `const_pointers_as_references!` promise that
+        // their `const T*` parameters are never null, though, so take `&T`.
+        if matches!(
+            kind,
+            FnKind::Function
+                | FnKind::Method {
+                    method_kind: MethodKind::Normal | MethodKind::Static,
+                    ..
+                }
+        ) && !matches!(
+            self.config.unsafe_policy,
+            UnsafePolicy::ReferencesWrappedAllFunctionsSafe
+        ) && self
+            .config
+            .takes_const_pointers_as_references(&qualified_cpp_name)
+        {
+            Self::convert_const_pointers_to_references(&mut params, &mut param_details);
+        }
+
+        // Functions listed in `integer_enum_params!` take the underlying
+        // integer of each enum parameter instead, for the sake of Rust code
+        // which only has the integer. It's checked in debug builds, but C++
+        // is given whatever it's passed, which is why this isn't the default.
+        if self.config.takes_enums_as_integers(&qualified_cpp_name) {
+            self.convert_enum_params_to_integers(&mut params, &mut param_details);
+        }
+
+        // Functions listed in `bitmask_params!` take a `bitmask_enum!` in
+        // place of each named integer parameter, so Rust callers pass flags
+        // rather than a bare number. The C++ wrapper casts it back. Bitmask
+        // parameters in `reject_unknown_bits` mode are also checked to hold
+        // only known flags, wherever they appear.
+        self.convert_bitmask_params(&qualified_cpp_name, ns, &mut params, &mut param_details)
+            .unwrap_or_else(&mut set_ignore_reason);
+
+        // bindgen can't tell us about `std::array`s, so functions listed in
+        // `std_array!` tell us which parameters are really `[T; N]`s.
+        self.convert_std_array_params(&qualified_cpp_name, ns, &mut params, &mut param_details)
+            .unwrap_or_else(&mut set_ignore_reason);
+
+        // Nor can it tell us about `std::span`s, so functions listed in
+        // `std_span!` say which parameters are spans, and of what. Rust
+        // passes each as a slice, whose length the span then shares.
+        self.convert_std_span_params(&qualified_cpp_name, ns, &mut params, &mut param_details)
+            .unwrap_or_else(&mut set_ignore_reason);
+
+        // Floating point parameters, including NaN and infinities, are
+        // normally passed straight through to C++. Functions listed in
+        // `reject_nan!` instead check for NaN in debug builds.
+        if self.config.rejects_nan(&qualified_cpp_name) {
+            Self::add_nan_checks(&mut param_details);
+        }
 
         let requires_unsafe = self.should_be_unsafe(&param_details, &kind);
 
+        // A returned `std::string_view` can only be borrowed by Rust if it
+        // views the one reference the function takes. Otherwise it may well
+        // view a temporary, such as a parameter which the C++ wrapper made,
+        // which is gone by the time Rust could look at it. So copy it while
+        // it's still valid.
+        if return_analysis
+            .conversion
+            .as_ref()
+            .is_some_and(|conv| conv.is_borrowed_string_view_return())
+            && (param_details.iter().filter(|pd| pd.has_lifetime).count() != 1
+                || param_details
+                    .iter()
+                    .any(|pd| pd.conversion.passes_temporary()))
+        {
+            return_analysis.rt = parse_quote! { -> String };
+            return_analysis.conversion = Some(TypeConversionPolicy::new_from_string_view_copy());
+            return_analysis.was_reference = false;
+        }
+
+        // A `borrow_from_receiver!` method is promised to return part of
+        // `this`, so its return value borrows from the receiver alone,
+        // whatever other references it takes.
+        let reference_receiver = param_details
+            .iter()
+            .find(|pd| pd.self_type.is_some() && pd.has_lifetime)
+            .filter(|_| self.config.borrows_from_receiver(&qualified_cpp_name));
         // The following sections reject some types of function because of the arrangement
         // of Rust references. We could lift these restrictions when/if we switch to using
         // CppRef to represent C++ references.
-        if return_analysis.was_reference {
+        if let Some(receiver) = reference_receiver {
+            if return_analysis.was_mutable_reference && !receiver.is_mutable_reference {
+                set_ignore_reason(ConvertErrorFromCpp::NoMutableInputReference(
+                    rust_name.clone(),
+                ));
+            }
+        } else if return_analysis.was_reference {
             // cxx only allows functions to return a reference if they take exactly
             // one reference as a parameter. Let's see.
             let num_input_references = param_details.iter().filter(|pd| pd.has_lifetime).count();
@@ -1233,7 +1920,7 @@ impl<'a> FnAnalyzer<'a> {
                 ));
             }
         }
-        if return_analysis.was_mutable_reference {
+        if return_analysis.was_mutable_reference && reference_receiver.is_none() {
             // This one's a bit more subtle. We can't have:
             //    fn foo(thing: &Thing) -> &mut OtherThing
             // because Rust doesn't allow it.
@@ -1256,10 +1943,29 @@ impl<'a> FnAnalyzer<'a> {
                 ));
             }
         }
+        let borrows_from_receiver = reference_receiver.is_some();
 
         let mut ret_type = return_analysis.rt;
         let ret_type_conversion = return_analysis.conversion;
 
+        // Functions listed in `catch_exceptions!` return a `Result`, which
+        // cxx fills in if the C++ throws. That can't be done for a
+        // function which makes its return value in place.
+        let catches_exceptions = self.config.catches_exceptions(&qualified_cpp_name);
+        if catches_exceptions
+            && (matches!(
+                kind,
+                FnKind::Method {
+                    method_kind: MethodKind::Constructor { .. },
+                    ..
+                }
+            ) || param_details
+                .iter()
+                .any(|pd| pd.is_placement_return_destination))
+        {
+            set_ignore_reason(ConvertErrorFromCpp::CatchExceptionsWithPlacementReturn);
+        }
+
         // Do we need to convert either parameters or return type?
         let param_conversion_needed = param_details.iter().any(|b| b.conversion.cpp_work_needed());
         let ret_type_conversion_needed = ret_type_conversion
@@ -1274,6 +1980,10 @@ impl<'a> FnAnalyzer<'a> {
         let effective_cpp_name = cpp_name.as_ref().unwrap_or(&rust_name);
         let cpp_name_incompatible_with_cxx =
             validate_ident_ok_for_rust(effective_cpp_name).is_err();
+        // cxx can't express a volatile receiver, so for methods listed in
+        // volatile_methods! we add the qualifier ourselves in a wrapper.
+        let volatile_receiver = matches!(kind, FnKind::Method { .. })
+            && self.config.has_volatile_receiver(&qualified_cpp_name);
         // If possible, we'll put knowledge of the C++ API directly into the cxx::bridge
         // mod. However, there are various circumstances where cxx can't work with the existing
         // C++ API and we need to create a C++ wrapper function which is more cxx-compliant.
@@ -1296,10 +2006,17 @@ impl<'a> FnAnalyzer<'a> {
                 ..
             } => true,
             FnKind::Method { .. } if cxxbridge_name != rust_name => true,
+            FnKind::Method { .. } if volatile_receiver => true,
             _ if param_conversion_needed => true,
             _ if ret_type_conversion_needed => true,
             _ if cpp_name_incompatible_with_cxx => true,
             _ if fun.synthetic_cpp.is_some() => true,
+            // The wrapper turns exceptions of other types into ones which
+            // cxx can report.
+            _ if catches_exceptions => true,
+            // cxx would insist on binding to a C++ function which takes
+            // exactly these parameters.
+            _ if matches!(fun.provenance, Provenance::SynthesizedWithDefaultArgs(_)) => true,
             _ if self.force_wrapper_generation => true,
             _ => false,
         };
@@ -1355,7 +2072,11 @@ impl<'a> FnAnalyzer<'a> {
                     ),
                     FnKind::Method { .. } => (
                         CppFunctionBody::FunctionCall(ns.clone(), cpp_construction_ident),
-                        CppFunctionKind::Method,
+                        if volatile_receiver {
+                            CppFunctionKind::VolatileMethod
+                        } else {
+                            CppFunctionKind::Method
+                        },
                     ),
                     _ => (
                         CppFunctionBody::FunctionCall(ns.clone(), cpp_construction_ident),
@@ -1399,6 +2120,7 @@ impl<'a> FnAnalyzer<'a> {
                 kind: cpp_function_kind,
                 pass_obs_field: false,
                 qualification: None,
+                catches_exceptions,
             })
         } else {
             None
@@ -1448,6 +2170,8 @@ impl<'a> FnAnalyzer<'a> {
             ignore_reason,
             externally_callable,
             rust_wrapper_needed,
+            catches_exceptions,
+            borrows_from_receiver,
         };
         let name = ApiName::new_with_cpp_name(ns, cxxbridge_name, cpp_name);
         (analysis, name)
@@ -1712,6 +2436,80 @@ impl<'a> FnAnalyzer<'a> {
                     _ => old_pat,
                 };
 
+                let str_conversion =
+                    if !matches!(sophistication, TypeConversionSophistication::Regular)
+                        || self_type.is_some()
+                    {
+                        None
+                    } else if Self::is_const_param_of(&pt.ty, &pointer_treatment, is_std_wstring) {
+                        Some(TypeConversionPolicy::new_to_wstring())
+                    } else if Self::is_const_param_of(&pt.ty, &pointer_treatment, is_std_u16string)
+                    {
+                        Some(TypeConversionPolicy::new_to_u16string())
+                    } else if Self::is_const_param_of(
+                        &pt.ty,
+                        &pointer_treatment,
+                        is_std_string_view,
+                    ) {
+                        Some(TypeConversionPolicy::new_to_string_view())
+                    } else if Self::is_const_param_of(
+                        &pt.ty,
+                        &pointer_treatment,
+                        is_std_optional_string_view,
+                    ) {
+                        Some(TypeConversionPolicy::new_to_optional_string_view())
+                    } else {
+                        None
+                    };
+                if let Some(conversion) = str_conversion {
+                    pt.pat = Box::new(new_pat.clone());
+                    pt.ty = Box::new(conversion.cxxbridge_type().clone());
+                    return Ok((
+                        FnArg::Typed(pt),
+                        ArgumentAnalysis {
+                            self_type,
+                            name: new_pat.into(),
+                            conversion,
+                            has_lifetime: true,
+                            is_mutable_reference: false,
+                            deps: HashSet::new(),
+                            requires_unsafe: UnsafetyNeeded::None,
+                            is_placement_return_destination: false,
+                        },
+                    ));
+                }
+                // A reference to a C array of bytes can't be expressed in
+                // cxx, so it's passed across as a slice.
+                let byte_array = match (pt.ty.as_ref(), &pointer_treatment) {
+                    (
+                        Type::Ptr(TypePtr {
+                            mutability, elem, ..
+                        }),
+                        PointerTreatment::Reference,
+                    ) if matches!(sophistication, TypeConversionSophistication::Regular) => {
+                        extract_byte_array_len(elem).map(|len| (len, mutability.is_some()))
+                    }
+                    _ => None,
+                };
+                if let Some((len, is_mut)) = byte_array {
+                    let conversion =
+                        TypeConversionPolicy::new_for_byte_array_reference(len, is_mut);
+                    pt.pat = Box::new(new_pat.clone());
+                    pt.ty = Box::new(conversion.cxxbridge_type().clone());
+                    return Ok((
+                        FnArg::Typed(pt),
+                        ArgumentAnalysis {
+                            self_type,
+                            name: new_pat.into(),
+                            conversion,
+                            has_lifetime: true,
+                            is_mutable_reference: is_mut,
+                            deps: HashSet::new(),
+                            requires_unsafe: UnsafetyNeeded::None,
+                            is_placement_return_destination: false,
+                        },
+                    ));
+                }
                 let is_placement_return_destination = is_placement_return_destination
                     || matches!(
                         force_rust_conversion,
@@ -1764,37 +2562,941 @@ impl<'a> FnAnalyzer<'a> {
         })
     }
 
-    fn argument_conversion_details(
-        &self,
-        annotated_type: &Annotated<Box<Type>>,
-        is_move_constructor: bool,
-        force_rust_conversion: Option<RustConversionType>,
-        sophistication: TypeConversionSophistication,
-        is_self: bool,
-        is_placement_return_destination: bool,
-    ) -> TypeConversionPolicy {
-        let is_subclass_holder = match &annotated_type.kind {
-            type_converter::TypeKind::SubclassHolder(holder) => Some(holder),
-            _ => None,
-        };
-        let is_rvalue_ref = matches!(
-            annotated_type.kind,
-            type_converter::TypeKind::RValueReference
-        );
-        let is_reference =
-            matches!(annotated_type.kind, type_converter::TypeKind::Reference) || is_self;
-        let rust_conversion_forced = force_rust_conversion.is_some();
-        let ty = &*annotated_type.ty;
-        if let Some(holder_id) = is_subclass_holder {
-            let subclass = SubclassName::from_holder_name(holder_id);
-            return {
-                let ty = parse_quote! {
-                    rust::Box<#holder_id>
+    /// Turns each `Pin<&mut UniquePtr<T>>` parameter into a plain
+    /// `&mut UniquePtr<T>` in the bridge, which the Rust wrapper then
+    /// creates and returns rather than taking it from the caller.
+    fn convert_unique_ptr_out_params(
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut [ArgumentAnalysis],
+    ) {
+        for (param, pd) in params.iter_mut().zip(param_details.iter_mut()) {
+            let FnArg::Typed(pt) = param else {
+                continue;
+            };
+            let unique_ptr_ty = match pt.ty.as_ref() {
+                Type::Path(tp) => match extract_pinned_mutable_reference_type(tp) {
+                    Some(inner) if is_unique_ptr(inner) => inner.clone(),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            pt.ty = parse_quote! { &mut #unique_ptr_ty };
+            pd.conversion = TypeConversionPolicy::new_for_unique_ptr_out_param(unique_ptr_ty);
+            pd.has_lifetime = false;
+            pd.is_mutable_reference = false;
+        }
+    }
+
+    /// Whether `ty` is a number or a POD type which can simply be copied
+    /// into Rust.
+    fn is_copyable_pod(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Path(tp) => {
+                let tn = QualifiedName::from_type_path(tp);
+                self.pod_safe_types.contains(&tn) && !known_types().lacks_copy_constructor(&tn)
+            }
+            _ => false,
+        }
+    }
+
+    /// Why a `std::optional<T>` can't be returned, given that `T` isn't a
+    /// number or POD type which we can copy.
+    fn unsupported_optional_return(&self, ty: &Type) -> ConvertErrorFromCpp {
+        match ty {
+            Type::Ptr(_) => ConvertErrorFromCpp::OptionalOfPointer,
+            Type::Path(tp) => {
+                let tn = QualifiedName::from_type_path(tp);
+                if self.pod_safe_types.contains(&tn) {
+                    ConvertErrorFromCpp::OptionalOfUncopyable(tn)
+                } else if known_types().is_known_type(&tn) {
+                    ConvertErrorFromCpp::NonTrivialOptional(tn.to_cpp_name())
+                } else {
+                    ConvertErrorFromCpp::OptionalOfNonPod(tn)
+                }
+            }
+            _ => ConvertErrorFromCpp::NonTrivialOptional(ty.to_token_stream().to_string()),
+        }
+    }
+
+    /// A `std::optional<T>&` parameter of an `out_params!` function
+    /// becomes two parameters in the bridge: a `*mut T` pointing to
+    /// uninitialized storage, into which the C++ wrapper moves any value,
+    /// and a `&mut bool` saying whether it did. Returns `None` if this isn't
+    /// such a parameter, or if `T` can't simply be copied into Rust.
+    fn convert_optional_out_param(
+        &mut self,
+        arg: &FnArg,
+        ns: &Namespace,
+        references: &References,
+    ) -> Option<Vec<(FnArg, ArgumentAnalysis)>> {
+        let FnArg::Typed(PatType { pat, ty, .. }) = arg else {
+            return None;
+        };
+        let Pat::Ident(pp) = pat.as_ref() else {
+            return None;
+        };
+        if !matches!(
+            references.param_treatment(&pp.ident.clone().into()),
+            PointerTreatment::Reference
+        ) {
+            return None;
+        }
+        let inner = match ty.as_ref() {
+            Type::Ptr(TypePtr {
+                mutability: Some(_),
+                elem,
+                ..
+            }) => extract_std_optional_type(elem)?,
+            _ => return None,
+        };
+        let annotated_type = self
+            .convert_boxed_type(parse_quote! { *mut #inner }, ns, PointerTreatment::Pointer)
+            .ok()?;
+        let copyable = match annotated_type.ty.as_ref() {
+            Type::Ptr(TypePtr { elem, .. }) => self.is_copyable_pod(elem),
+            _ => false,
+        };
+        if !copyable {
+            return None;
+        }
+        let name = &pp.ident;
+        let has_value = make_ident(format!("{name}_has_value"));
+        let value_conversion = TypeConversionPolicy::new_for_optional_out_param(
+            (*annotated_type.ty).clone(),
+            has_value.clone(),
+        );
+        let value_ty = value_conversion.cxxbridge_type().clone();
+        let has_value_conversion = TypeConversionPolicy::new_for_optional_out_param_flag();
+        let has_value_ty = has_value_conversion.cxxbridge_type().clone();
+        let value_pat: Pat = parse_quote! { #name };
+        let has_value_pat: Pat = parse_quote! { #has_value };
+        Some(vec![
+            (
+                parse_quote! { #value_pat: #value_ty },
+                ArgumentAnalysis {
+                    conversion: value_conversion,
+                    name: value_pat.into(),
+                    self_type: None,
+                    has_lifetime: false,
+                    is_mutable_reference: false,
+                    deps: annotated_type.types_encountered,
+                    requires_unsafe: UnsafetyNeeded::JustBridge,
+                    is_placement_return_destination: false,
+                },
+            ),
+            (
+                parse_quote! { #has_value_pat: #has_value_ty },
+                ArgumentAnalysis {
+                    conversion: has_value_conversion,
+                    name: has_value_pat.into(),
+                    self_type: None,
+                    has_lifetime: false,
+                    is_mutable_reference: false,
+                    deps: HashSet::new(),
+                    requires_unsafe: UnsafetyNeeded::None,
+                    is_placement_return_destination: false,
+                },
+            ),
+        ])
+    }
+
+    /// The comparator parameter of a `comparator!` function becomes a
+    /// callback saying whether one `element_type` is less than another.
+    fn comparator_params(
+        &mut self,
+        name: &Pat,
+        element_type: &str,
+        ns: &Namespace,
+    ) -> Result<Vec<(FnArg, ArgumentAnalysis)>, ConvertErrorFromCpp> {
+        let elem = QualifiedName::new_from_cpp_name(element_type).to_type_path();
+        let annotated_type =
+            self.convert_boxed_type(parse_quote! { *const #elem }, ns, PointerTreatment::Pointer)?;
+        let elem = match annotated_type.ty.as_ref() {
+            Type::Ptr(TypePtr { elem, .. }) => elem.as_ref(),
+            _ => panic!("Not a pointer"),
+        };
+        Ok(Self::callback_params(
+            name,
+            "compare",
+            parse_quote! { fn(usize, &#elem, &#elem) -> bool },
+            CallbackKind::Comparator,
+            annotated_type.types_encountered,
+        ))
+    }
+
+    /// The allocation callback parameter of an `allocator_callback!`
+    /// function becomes a callback taking a size and an alignment and
+    /// returning the address of the memory it allocated.
+    fn allocator_params(name: &Pat) -> Vec<(FnArg, ArgumentAnalysis)> {
+        Self::callback_params(
+            name,
+            "allocate",
+            parse_quote! { fn(usize, usize, usize) -> usize },
+            CallbackKind::Allocator,
+            HashSet::new(),
+        )
+    }
+
+    /// The callback parameter of a `closure_callback!` function becomes a
+    /// callback with the same signature. For now, the callback may only
+    /// take and return numbers and `bool`s, which Rust and C++ pass in just
+    /// the same way.
+    fn closure_callback_params(
+        &mut self,
+        name: &Pat,
+        callback: &ClosureCallback,
+        ns: &Namespace,
+    ) -> Result<Vec<(FnArg, ArgumentAnalysis)>, ConvertErrorFromCpp> {
+        let mut deps = HashSet::new();
+        let mut convert = |cpp_type: &str| -> Result<Type, ConvertErrorFromCpp> {
+            let tn = QualifiedName::new_from_cpp_name(cpp_type);
+            let path = known_types()
+                .known_type_type_path(&tn)
+                .filter(|_| known_types().is_by_value_built_in(&tn))
+                .ok_or_else(|| {
+                    ConvertErrorFromCpp::UnsupportedClosureCallbackType(cpp_type.into())
+                })?;
+            let annotated_type =
+                self.convert_boxed_type(parse_quote! { #path }, ns, PointerTreatment::Pointer)?;
+            deps.extend(annotated_type.types_encountered);
+            Ok(*annotated_type.ty)
+        };
+        let inputs = callback
+            .param_types
+            .iter()
+            .map(|param| convert(param))
+            .collect::<Result<Vec<_>, _>>()?;
+        let output: ReturnType = match callback.return_type.as_str() {
+            "void" => ReturnType::Default,
+            return_type => {
+                let ty = convert(return_type)?;
+                parse_quote! { -> #ty }
+            }
+        };
+        // A fallible callback reports any error through two more parameters:
+        // whether there was one, and what it was.
+        let callback_fn: Type = if callback.fallible {
+            parse_quote! { fn(usize #(, #inputs)*, &mut bool, &mut String) #output }
+        } else {
+            parse_quote! { fn(usize #(, #inputs)*) #output }
+        };
+        let kind = CallbackKind::Closure {
+            fallible: callback.fallible,
+        };
+        Ok(Self::callback_params(
+            name,
+            "callback",
+            callback_fn,
+            kind,
+            deps,
+        ))
+    }
+
+    /// A callback parameter to which Rust passes a closure becomes two
+    /// parameters in the bridge: a function pointer, which takes an extra
+    /// `usize` context first, and the context itself, which is really a
+    /// pointer to the closure. The C++ wrapper passes the original function
+    /// a lambda which calls the two. The closure is on the caller's stack,
+    /// so the function is always unsafe: nothing stops C++ keeping the
+    /// callback and calling it after the closure has gone.
+    fn callback_params(
+        name: &Pat,
+        default_name: &str,
+        callback_fn: Type,
+        kind: CallbackKind,
+        deps: HashSet<QualifiedName>,
+    ) -> Vec<(FnArg, ArgumentAnalysis)> {
+        let callback_conversion = TypeConversionPolicy::new_for_callback(callback_fn, kind);
+        let callback_ty = callback_conversion.cxxbridge_type().clone();
+        let callback_ident = match name {
+            Pat::Ident(pp) => pp.ident.clone().into(),
+            _ => make_ident(default_name),
+        };
+        let callback_pat: Pat = parse_quote! { #callback_ident };
+        let context = make_ident(format!("{callback_ident}_context"));
+        let context_conversion = TypeConversionPolicy::new_for_callback_context(callback_ident);
+        let context_ty = context_conversion.cxxbridge_type().clone();
+        let context_pat: Pat = parse_quote! { #context };
+        vec![
+            (
+                parse_quote! { #callback_pat: #callback_ty },
+                ArgumentAnalysis {
+                    conversion: callback_conversion,
+                    name: callback_pat.into(),
+                    self_type: None,
+                    has_lifetime: false,
+                    is_mutable_reference: false,
+                    deps,
+                    requires_unsafe: UnsafetyNeeded::Always,
+                    is_placement_return_destination: false,
+                },
+            ),
+            (
+                parse_quote! { #context_pat: #context_ty },
+                ArgumentAnalysis {
+                    conversion: context_conversion,
+                    name: context_pat.into(),
+                    self_type: None,
+                    has_lifetime: false,
+                    is_mutable_reference: false,
+                    deps: HashSet::new(),
+                    requires_unsafe: UnsafetyNeeded::None,
+                    is_placement_return_destination: false,
+                },
+            ),
+        ]
+    }
+
+    /// The parameter which replaces a `string_callback!` function's
+    /// callback: a `std::unique_ptr<std::string>&` which the C++ wrapper's
+    /// own callback fills in. That callback refers to the Rust caller's
+    /// local `UniquePtr`, so, as for a `comparator!` function, the function
+    /// is unsafe: nothing stops C++ keeping the callback and calling it
+    /// after the function has returned.
+    fn string_callback_out_param(&self, name: Pat) -> (FnArg, ArgumentAnalysis) {
+        let conversion =
+            TypeConversionPolicy::new_for_string_callback(self.config.string_return_policy);
+        let ty = conversion.cxxbridge_type().clone();
+        (
+            parse_quote! { #name: #ty },
+            ArgumentAnalysis {
+                conversion,
+                name: name.into(),
+                self_type: None,
+                has_lifetime: false,
+                is_mutable_reference: false,
+                deps: HashSet::new(),
+                requires_unsafe: UnsafetyNeeded::Always,
+                is_placement_return_destination: false,
+            },
+        )
+    }
+
+    /// Turns each pointer parameter which `is_bound` says is followed by
+    /// where its elements end, such as a `usize` length, into a single
+    /// slice, if `slice_conversion` gives a conversion for it, along with
+    /// whether the slice is mutable. The C++ wrapper passes on the slice's
+    /// data and bound as the two arguments. Returns the names of the slice
+    /// parameters.
+    fn convert_pointers_to_slices(
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut Vec<ArgumentAnalysis>,
+        is_bound: impl Fn(&PatType, &PatType) -> bool,
+        slice_conversion: impl Fn(&Type) -> Option<(bool, TypeConversionPolicy)>,
+    ) -> Vec<Ident> {
+        let is_plain = |pd: &ArgumentAnalysis| {
+            pd.self_type.is_none()
+                && !pd.conversion.cpp_work_needed()
+                && !pd.conversion.rust_work_needed()
+        };
+        let mut args: Vec<_> = std::mem::take(params)
+            .into_iter()
+            .zip(std::mem::take(param_details))
+            .collect();
+        let mut slices = Vec::new();
+        let mut i = 0;
+        while i + 1 < args.len() {
+            let slice = match (&args[i], &args[i + 1]) {
+                ((FnArg::Typed(pt), pd), (FnArg::Typed(bound), bound_pd))
+                    if is_plain(pd) && is_plain(bound_pd) && is_bound(pt, bound) =>
+                {
+                    slice_conversion(&pt.ty)
+                }
+                _ => None,
+            };
+            let Some((is_mut, conversion)) = slice else {
+                i += 1;
+                continue;
+            };
+            args.remove(i + 1);
+            let (FnArg::Typed(pt), pd) = &mut args[i] else {
+                unreachable!()
+            };
+            pd.conversion = conversion;
+            *pt.ty = pd.conversion.cxxbridge_type().clone();
+            pd.has_lifetime = true;
+            pd.is_mutable_reference = is_mut;
+            pd.requires_unsafe = UnsafetyNeeded::None;
+            if let Pat::Ident(name) = &*pd.name {
+                slices.push(name.ident.clone());
+            }
+            i += 1;
+        }
+        let (new_params, new_param_details): (Punctuated<_, _>, Vec<_>) = args.into_iter().unzip();
+        *params = new_params;
+        *param_details = new_param_details;
+        slices
+    }
+
+    /// The conversion of a pointer to a number or POD type, which Rust can
+    /// simply view in place, into a slice with the given sort of bound.
+    fn pod_slice_conversion(
+        &self,
+        ty: &Type,
+        bound: SliceBound,
+    ) -> Option<(bool, TypeConversionPolicy)> {
+        match ty {
+            Type::Ptr(TypePtr {
+                mutability, elem, ..
+            }) if self.is_copyable_pod(elem) => Some((
+                mutability.is_some(),
+                TypeConversionPolicy::new_for_slice(elem, mutability.is_some(), bound),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Turns each enum parameter passed by value into its underlying
+    /// integer. The C++ wrapper casts it back to the enum. This never
+    /// creates a Rust enum from the integer, which would be undefined
+    /// behavior if it isn't one of the variants.
+    fn convert_enum_params_to_integers(
+        &self,
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut [ArgumentAnalysis],
+    ) {
+        for (param, pd) in params.iter_mut().zip(param_details.iter_mut()) {
+            let FnArg::Typed(pt) = param else {
+                continue;
+            };
+            if pd.self_type.is_some()
+                || pd.conversion.cpp_work_needed()
+                || pd.conversion.rust_work_needed()
+            {
+                continue;
+            }
+            let enum_ty = pd.conversion.cxxbridge_type().clone();
+            let Some((underlying_type, variants)) = (match &enum_ty {
+                Type::Path(tp) => self.integer_enums.get(&QualifiedName::from_type_path(tp)),
+                _ => None,
+            }) else {
+                continue;
+            };
+            pd.conversion = TypeConversionPolicy::new_for_integer_enum(
+                enum_ty,
+                underlying_type,
+                variants.clone(),
+            );
+            *pt.ty = pd.conversion.cxxbridge_type().clone();
+        }
+    }
+
+    /// Turns each integer parameter passed by value which `bitmask_params!`
+    /// names for this function into the bitmask enum it lists, which has
+    /// the same layout. Also adds checks to any bitmask enum parameter whose
+    /// unknown bits are to be rejected.
+    fn convert_bitmask_params(
+        &mut self,
+        qualified_cpp_name: &str,
+        ns: &Namespace,
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut [ArgumentAnalysis],
+    ) -> Result<(), ConvertErrorFromCpp> {
+        for (param, pd) in params.iter_mut().zip(param_details.iter_mut()) {
+            let FnArg::Typed(pt) = param else {
+                continue;
+            };
+            if pd.self_type.is_some()
+                || pd.conversion.cpp_work_needed()
+                || pd.conversion.rust_work_needed()
+            {
+                continue;
+            }
+            let Type::Path(tp) = pd.conversion.cxxbridge_type().clone() else {
+                continue;
+            };
+            let param_name = match &pd.name.0 {
+                Pat::Ident(pp) => pp.ident.to_string(),
+                _ => continue,
+            };
+            match self
+                .config
+                .get_bitmask_param_type(qualified_cpp_name, &param_name)
+            {
+                Some(bitmask_ty)
+                    if known_types().is_by_value_built_in(&QualifiedName::from_type_path(&tp)) =>
+                {
+                    let bitmask_name = QualifiedName::new_from_cpp_name(bitmask_ty);
+                    let reject_unknown_bits = self
+                        .config
+                        .rejects_unknown_bits(&bitmask_name.to_cpp_name());
+                    let annotated = self.convert_boxed_type(
+                        Box::new(Type::Path(bitmask_name.to_type_path())),
+                        ns,
+                        PointerTreatment::Pointer,
+                    )?;
+                    pd.conversion = TypeConversionPolicy::new_for_bitmask_param(
+                        *annotated.ty,
+                        Type::Path(tp),
+                        reject_unknown_bits,
+                    );
+                    pd.deps.extend(annotated.types_encountered);
+                    *pt.ty = pd.conversion.cxxbridge_type().clone();
+                }
+                _ if self
+                    .config
+                    .rejects_unknown_bits(&QualifiedName::from_type_path(&tp).to_cpp_name()) =>
+                {
+                    pd.conversion =
+                        TypeConversionPolicy::new_for_checked_bitmask_param(Type::Path(tp));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns each parameter passed by value which `std_array!` names for
+    /// this function into the `[T; N]` it lists.
+    fn convert_std_array_params(
+        &mut self,
+        qualified_cpp_name: &str,
+        ns: &Namespace,
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut [ArgumentAnalysis],
+    ) -> Result<(), ConvertErrorFromCpp> {
+        let config = self.config;
+        for (param, pd) in params.iter_mut().zip(param_details.iter_mut()) {
+            let FnArg::Typed(pt) = param else {
+                continue;
+            };
+            let std_array = match &pd.name.0 {
+                Pat::Ident(pp) => config.get_std_array(qualified_cpp_name, &pp.ident.to_string()),
+                _ => None,
+            };
+            let Some(std_array) = std_array else {
+                continue;
+            };
+            if pd.self_type.is_some()
+                || pd.conversion.cpp_work_needed()
+                || pd.conversion.rust_work_needed()
+            {
+                return Err(ConvertErrorFromCpp::NotAStdArray);
+            }
+            let (array_ty, deps) =
+                self.std_array_type(std_array, pd.conversion.cxxbridge_type(), ns)?;
+            pd.conversion = TypeConversionPolicy::new_unconverted(array_ty);
+            pd.deps.extend(deps);
+            *pt.ty = pd.conversion.cxxbridge_type().clone();
+        }
+        Ok(())
+    }
+
+    /// The `[T; N]` which Rust uses in place of a `std::array<T, N>` listed
+    /// in `std_array!`. The array's non-type template parameter means
+    /// bindgen tells us only its size, as an opaque blob standing in for
+    /// `ty`, so the element type and length come from the directive. cxx
+    /// itself passes arrays to and from C++ as `std::array`s, copying
+    /// them, so no more glue is needed, however long they are.
+    fn std_array_type(
+        &mut self,
+        std_array: &StdArray,
+        ty: &Type,
+        ns: &Namespace,
+    ) -> Result<(Type, HashSet<QualifiedName>), ConvertErrorFromCpp> {
+        if !matches!(ty, Type::Array(_) | Type::Path(_)) {
+            return Err(ConvertErrorFromCpp::NotAStdArray);
+        }
+        if std_array.len == 0 {
+            return Err(ConvertErrorFromCpp::ZeroLengthStdArray);
+        }
+        let elem = QualifiedName::new_from_cpp_name(&std_array.element_type).to_type_path();
+        let pointer =
+            self.convert_boxed_type(parse_quote! { *mut #elem }, ns, PointerTreatment::Pointer)?;
+        let elem = match pointer.ty.as_ref() {
+            Type::Ptr(TypePtr { elem, .. }) => match elem.as_ref() {
+                Type::Path(tp)
+                    if known_types().is_by_value_built_in(&QualifiedName::from_type_path(tp)) =>
+                {
+                    elem.as_ref()
+                }
+                _ => return Err(ConvertErrorFromCpp::NotAStdArray),
+            },
+            _ => return Err(ConvertErrorFromCpp::NotAStdArray),
+        };
+        let len = syn::LitInt::new(&std_array.len.to_string(), Span::call_site());
+        Ok((parse_quote! { [#elem; #len] }, pointer.types_encountered))
+    }
+
+    /// Turns each parameter passed by value which `std_span!` names for
+    /// this function into a slice of the elements it lists.
+    fn convert_std_span_params(
+        &mut self,
+        qualified_cpp_name: &str,
+        ns: &Namespace,
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut [ArgumentAnalysis],
+    ) -> Result<(), ConvertErrorFromCpp> {
+        let config = self.config;
+        // Catch a typo in the directive, rather than silently leaving the
+        // function taking an opaque span which can't be passed from Rust.
+        if let Some(missing) = config
+            .get_std_span_params(qualified_cpp_name)
+            .find(|param| {
+                !param_details
+                    .iter()
+                    .any(|pd| matches!(&pd.name.0, Pat::Ident(pp) if pp.ident == param))
+            })
+        {
+            return Err(ConvertErrorFromCpp::NoSuchStdSpanParam(missing.to_string()));
+        }
+        for (param, pd) in params.iter_mut().zip(param_details.iter_mut()) {
+            let FnArg::Typed(pt) = param else {
+                continue;
+            };
+            let std_span = match &pd.name.0 {
+                Pat::Ident(pp) => config.get_std_span(qualified_cpp_name, &pp.ident.to_string()),
+                _ => None,
+            };
+            let Some(std_span) = std_span else {
+                continue;
+            };
+            if pd.self_type.is_some()
+                || pd.conversion.cpp_work_needed()
+                || pd.conversion.rust_work_needed()
+                || !matches!(
+                    pd.conversion.cxxbridge_type(),
+                    Type::Array(_) | Type::Path(_)
+                )
+            {
+                return Err(ConvertErrorFromCpp::NotAStdSpan);
+            }
+            let (elem, deps) = self.std_span_element_type(std_span, ns)?;
+            pd.conversion = TypeConversionPolicy::new_for_span(&elem, std_span.is_const);
+            // The span itself is opaque to bindgen, and we no longer need it.
+            pd.deps = deps;
+            pd.has_lifetime = true;
+            pd.is_mutable_reference = !std_span.is_const;
+            *pt.ty = pd.conversion.cxxbridge_type().clone();
+        }
+        Ok(())
+    }
+
+    /// The Rust type of the elements of a `std::span` listed in
+    /// `std_span!`, which must be a number or a POD type, so that the
+    /// slice's elements can be used by C++ as they are.
+    fn std_span_element_type(
+        &mut self,
+        std_span: &StdSpan,
+        ns: &Namespace,
+    ) -> Result<(Type, HashSet<QualifiedName>), ConvertErrorFromCpp> {
+        let elem = QualifiedName::new_from_cpp_name(&std_span.element_type).to_type_path();
+        let pointer =
+            self.convert_boxed_type(parse_quote! { *mut #elem }, ns, PointerTreatment::Pointer)?;
+        match pointer.ty.as_ref() {
+            Type::Ptr(TypePtr { elem, .. }) if self.is_copyable_pod(elem) => {
+                Ok((elem.as_ref().clone(), pointer.types_encountered))
+            }
+            _ => Err(ConvertErrorFromCpp::NotAStdSpan),
+        }
+    }
+
+    /// Turns each `const char*` parameter into a `&str`, whose characters
+    /// the C++ wrapper copies into a null-terminated `std::string`.
+    fn convert_char_pointers_to_strs(
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut [ArgumentAnalysis],
+    ) {
+        for (param, pd) in params.iter_mut().zip(param_details.iter_mut()) {
+            let FnArg::Typed(pt) = param else {
+                continue;
+            };
+            if pd.self_type.is_some()
+                || pd.conversion.cpp_work_needed()
+                || pd.conversion.rust_work_needed()
+                || !is_const_char_pointer(&pt.ty)
+            {
+                continue;
+            }
+            pd.conversion = TypeConversionPolicy::new_to_char_pointer();
+            *pt.ty = pd.conversion.cxxbridge_type().clone();
+            pd.has_lifetime = true;
+            pd.requires_unsafe = UnsafetyNeeded::None;
+        }
+    }
+
+    /// Turns each `const char*` parameter into a `&CStr`. The bridge still
+    /// takes the pointer, which the Rust wrapper gets from the `&CStr`, so
+    /// nothing is copied.
+    fn convert_char_pointers_to_cstrs(
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut [ArgumentAnalysis],
+    ) {
+        for (param, pd) in params.iter_mut().zip(param_details.iter_mut()) {
+            let FnArg::Typed(pt) = param else {
+                continue;
+            };
+            if pd.self_type.is_some()
+                || pd.conversion.cpp_work_needed()
+                || pd.conversion.rust_work_needed()
+                || !is_const_char_pointer(&pt.ty)
+            {
+                continue;
+            }
+            pd.conversion = TypeConversionPolicy::new_from_cstr_to_char_pointer();
+            pd.requires_unsafe = UnsafetyNeeded::JustBridge;
+        }
+    }
+
+    /// Turns each `*const T` parameter into a `&T`. The C++ wrapper takes a
+    /// `const T&` and passes its address on to the original function. A
+    /// `const char*` is left alone: it's almost certainly a string, which
+    /// `str_params!` or `cstr_params!` can pass, not a single character.
+    fn convert_const_pointers_to_references(
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut [ArgumentAnalysis],
+    ) {
+        for (param, pd) in params.iter_mut().zip(param_details.iter_mut()) {
+            let FnArg::Typed(pt) = param else {
+                continue;
+            };
+            if pd.self_type.is_some()
+                || pd.conversion.cpp_work_needed()
+                || pd.conversion.rust_work_needed()
+                || is_const_char_pointer(&pt.ty)
+            {
+                continue;
+            }
+            let elem = match pt.ty.as_ref() {
+                Type::Ptr(TypePtr {
+                    mutability: None,
+                    elem,
+                    ..
+                }) => match elem.as_ref() {
+                    Type::Path(tp)
+                        if tp
+                            .path
+                            .segments
+                            .last()
+                            .is_some_and(|seg| seg.ident != "c_void") =>
+                    {
+                        elem.clone()
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            pt.ty = parse_quote! { &#elem };
+            pd.conversion = TypeConversionPolicy::new_const_ref_to_const_ptr(*pt.ty.clone());
+            pd.has_lifetime = true;
+            pd.requires_unsafe = UnsafetyNeeded::None;
+        }
+    }
+
+    /// Works out how to copy a returned `std::map<std::string, T>` into a
+    /// `HashMap`. `original` is the return type as it came from bindgen, and
+    /// `converted` is what we'd otherwise return: the concrete type which
+    /// type conversion made for the map.
+    fn map_snapshot_conversion(
+        &self,
+        original: &ReturnType,
+        converted: &ReturnType,
+    ) -> Option<TypeConversionPolicy> {
+        let value = match original {
+            ReturnType::Type(_, ty) => match ty.as_ref() {
+                Type::Path(tp) => map_snapshot_value_type(extract_string_map_value(tp)?)?,
+                _ => return None,
+            },
+            ReturnType::Default => return None,
+        };
+        match converted {
+            ReturnType::Type(_, ty) => match ty.as_ref() {
+                Type::Path(tp) => {
+                    let snapshot =
+                        make_ident(self.config.get_map_snapshot_name(
+                            QualifiedName::from_type_path(tp).get_final_item(),
+                        ));
+                    Some(TypeConversionPolicy::new_for_map_snapshot(
+                        ty.as_ref().clone(),
+                        snapshot,
+                        value,
+                    ))
+                }
+                _ => None,
+            },
+            ReturnType::Default => None,
+        }
+    }
+
+    /// If `ty`, as it came from bindgen, is an intrusive pointer to a type
+    /// listed in `intrusive_ptr!`, returns that type and the C++ name of
+    /// the pointer template.
+    fn intrusive_ptr_pointee(&self, ty: &Type) -> Option<(Type, &str)> {
+        let Type::Path(tp) = ty else {
+            return None;
+        };
+        self.config.intrusive_ptrs.iter().find_map(|ip| {
+            let pointee = match extract_intrusive_ptr_pointee(tp, &ip.pointer)? {
+                Type::Path(pointee) => QualifiedName::from_type_path(pointee),
+                _ => return None,
+            };
+            (pointee.to_cpp_name() == ip.ty)
+                .then(|| (Type::Path(pointee.to_type_path()), ip.pointer.as_str()))
+        })
+    }
+
+    /// Works out how to pass an `IntrusivePtr` for a parameter which takes
+    /// an intrusive pointer by value or by `const` reference. `inputs` are
+    /// the parameters as they came from bindgen, and `pd` is our existing
+    /// analysis of one of them.
+    fn intrusive_ptr_param_conversion(
+        &self,
+        inputs: &Punctuated<crate::minisyn::FnArg, Comma>,
+        pd: &ArgumentAnalysis,
+    ) -> Option<TypeConversionPolicy> {
+        if pd.self_type.is_some() || pd.is_placement_return_destination {
+            return None;
+        }
+        let Pat::Ident(name) = &*pd.name else {
+            return None;
+        };
+        let original = inputs.iter().find_map(|i| match &**i {
+            FnArg::Typed(pt) => match pt.pat.as_ref() {
+                Pat::Ident(pp) if pp.ident == name.ident => Some(pt.ty.as_ref()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })?;
+        let original = match original {
+            Type::Ptr(TypePtr {
+                mutability: None,
+                elem,
+                ..
+            }) if matches!(pd.conversion.cxxbridge_type(), Type::Reference(_)) => elem.as_ref(),
+            Type::Path(_) => original,
+            _ => return None,
+        };
+        let (pointee, pointer) = self.intrusive_ptr_pointee(original)?;
+        Some(TypeConversionPolicy::new_for_intrusive_ptr_param(
+            pointee,
+            pointer.to_string(),
+        ))
+    }
+
+    /// Works out how to build a `const std::map<K, V>&` or
+    /// `const std::unordered_map<K, V>&` parameter from an iterator.
+    /// `inputs` are the parameters as they came from bindgen, and `pd` is
+    /// our existing analysis of one of them, which passes a reference to
+    /// the concrete type which type conversion made for the map.
+    fn map_from_iter_conversion(
+        &self,
+        inputs: &Punctuated<crate::minisyn::FnArg, Comma>,
+        pd: &ArgumentAnalysis,
+    ) -> Option<TypeConversionPolicy> {
+        if !matches!(pd.conversion.rust_conversion, RustConversionType::None) {
+            return None;
+        }
+        let Pat::Ident(name) = &*pd.name else {
+            return None;
+        };
+        let original = inputs.iter().find_map(|i| match &**i {
+            FnArg::Typed(pt) => match pt.pat.as_ref() {
+                Pat::Ident(pp) if pp.ident == name.ident => Some(pt.ty.as_ref()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })?;
+        let (key, value) = match original {
+            Type::Ptr(TypePtr {
+                mutability: None,
+                elem,
+                ..
+            }) => match elem.as_ref() {
+                Type::Path(tp) => extract_map_key_and_value(tp)
+                    .or_else(|| extract_unordered_map_key_and_value(tp))?,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let key = map_snapshot_value_type(key)?;
+        let value = map_snapshot_value_type(value)?;
+        let converted = pd.conversion.cxxbridge_type();
+        let map = match converted {
+            Type::Reference(TypeReference {
+                mutability: None,
+                elem,
+                ..
+            }) => match elem.as_ref() {
+                Type::Path(tp) => QualifiedName::from_type_path(tp)
+                    .get_final_item()
+                    .to_string(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let [new, insert] = ["new", "insert"]
+            .map(|builder| make_ident(self.config.get_map_builder_name(&map, builder)));
+        Some(TypeConversionPolicy::new_for_map_from_iter(
+            converted.clone(),
+            new,
+            insert,
+            key,
+            value,
+        ))
+    }
+
+    fn add_nan_checks(param_details: &mut [ArgumentAnalysis]) {
+        for pd in param_details {
+            if pd.conversion.is_unconverted_float() {
+                pd.conversion.rust_conversion = RustConversionType::FromFloatRejectingNan;
+            }
+        }
+    }
+
+    /// Whether this parameter is of a type matching `is_type`, passed by
+    /// value or by const reference, either of which we can construct from
+    /// a `&str`.
+    fn is_const_param_of(
+        ty: &Type,
+        pointer_treatment: &PointerTreatment,
+        is_type: fn(&Type) -> bool,
+    ) -> bool {
+        match (ty, pointer_treatment) {
+            (
+                Type::Ptr(TypePtr {
+                    mutability: None,
+                    elem,
+                    ..
+                }),
+                PointerTreatment::Reference,
+            ) => is_type(elem),
+            (_, PointerTreatment::Reference | PointerTreatment::RValueReference) => false,
+            (ty, _) => is_type(ty),
+        }
+    }
+
+    fn argument_conversion_details(
+        &self,
+        annotated_type: &Annotated<Box<Type>>,
+        is_move_constructor: bool,
+        force_rust_conversion: Option<RustConversionType>,
+        sophistication: TypeConversionSophistication,
+        is_self: bool,
+        is_placement_return_destination: bool,
+    ) -> TypeConversionPolicy {
+        let is_subclass_holder = match &annotated_type.kind {
+            type_converter::TypeKind::SubclassHolder(holder) => Some(holder),
+            _ => None,
+        };
+        let is_rvalue_ref = matches!(
+            annotated_type.kind,
+            type_converter::TypeKind::RValueReference
+        );
+        let is_reference =
+            matches!(annotated_type.kind, type_converter::TypeKind::Reference) || is_self;
+        let rust_conversion_forced = force_rust_conversion.is_some();
+        let ty = &*annotated_type.ty;
+        if let Some(holder_id) = is_subclass_holder {
+            let subclass = SubclassName::from_holder_name(holder_id);
+            let is_dyn = self.config.is_dyn_subclass(&subclass.id().to_string());
+            return {
+                let ty = parse_quote! {
+                    rust::Box<#holder_id>
                 };
                 TypeConversionPolicy::new(
                     ty,
                     CppConversionType::Move,
-                    RustConversionType::ToBoxedUpHolder(subclass),
+                    RustConversionType::ToBoxedUpHolder { subclass, is_dyn },
                 )
             };
         } else if matches!(
@@ -1826,7 +3528,12 @@ impl<'a> FnAnalyzer<'a> {
                         RustConversionType::FromReferenceWrapperToPointer,
                     )
                 } else if self.pod_safe_types.contains(&tn) {
-                    if known_types().lacks_copy_constructor(&tn) {
+                    // A std::shared_ptr<const T> parameter can't be called
+                    // directly by cxx, which only knows std::shared_ptr<T>,
+                    // but a wrapper can convert implicitly.
+                    let shared_ptr_needs_wrapper = extract_shared_ptr_type(&ty).is_some()
+                        && matches!(sophistication, TypeConversionSophistication::Regular);
+                    if known_types().lacks_copy_constructor(&tn) || shared_ptr_needs_wrapper {
                         TypeConversionPolicy::new(
                             ty,
                             CppConversionType::Move,
@@ -1916,6 +3623,375 @@ impl<'a> FnAnalyzer<'a> {
         }
     }
 
+    /// The return type of a `result_variant!` function: a `std::variant`
+    /// of a value and an error, named `error_type`. Rust gets each of them
+    /// in a `UniquePtr`, so they must be types which can go in one. The
+    /// C++ wrapper returns the value, and gives any error to Rust through
+    /// an extra parameter.
+    fn convert_result_variant_return_type(
+        &mut self,
+        rt: &ReturnType,
+        error_type: &str,
+        ns: &Namespace,
+    ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
+        let not_a_result_variant = || ConvertErrorFromCpp::NotAResultVariant(error_type.into());
+        let (first, second) = match rt {
+            ReturnType::Type(_, ty) => extract_variant_alternatives(ty),
+            ReturnType::Default => None,
+        }
+        .ok_or_else(not_a_result_variant)?;
+        let is_error = |ty: &Type| matches!(ty, Type::Path(tp) if QualifiedName::from_type_path(tp).to_cpp_name() == error_type);
+        let (error_index, value, error) = if is_error(first) {
+            (0, second, first)
+        } else if is_error(second) {
+            (1, first, second)
+        } else {
+            return Err(not_a_result_variant());
+        };
+        let value = self.convert_boxed_type(
+            parse_quote! { root::std::unique_ptr<#value> },
+            ns,
+            PointerTreatment::Pointer,
+        )?;
+        let error = self.convert_boxed_type(
+            parse_quote! { root::std::unique_ptr<#error> },
+            ns,
+            PointerTreatment::Pointer,
+        )?;
+        let mut deps = value.types_encountered;
+        deps.extend(error.types_encountered);
+        let value_ty = *value.ty;
+        let error_ty = *error.ty;
+        let error_ident = make_ident("return_error");
+        let error_conversion = TypeConversionPolicy::new_for_result_error(error_ty.clone());
+        let error_param_ty = error_conversion.cxxbridge_type().clone();
+        let error_pat: Pat = parse_quote! { #error_ident };
+        Ok(ReturnTypeAnalysis {
+            rt: parse_quote! { -> #value_ty },
+            conversion: Some(TypeConversionPolicy::new_from_result_variant(
+                value_ty,
+                error_index,
+                error_ident,
+                error_ty,
+            )),
+            deps,
+            placement_param_needed: Some((
+                parse_quote! { #error_pat: #error_param_ty },
+                ArgumentAnalysis {
+                    conversion: error_conversion,
+                    name: error_pat.into(),
+                    self_type: None,
+                    has_lifetime: false,
+                    is_mutable_reference: false,
+                    deps: HashSet::new(),
+                    requires_unsafe: UnsafetyNeeded::None,
+                    is_placement_return_destination: false,
+                },
+            )),
+            ..Default::default()
+        })
+    }
+
+    /// The return type of a `tuple_array!` function: a `std::tuple` whose
+    /// elements are all the same built-in type, such as `int32_t`. The C++
+    /// wrapper copies them into an array supplied by Rust through an extra
+    /// parameter, and Rust returns that array.
+    fn convert_tuple_array_return_type(
+        &mut self,
+        rt: &ReturnType,
+        ns: &Namespace,
+    ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
+        let elements = match rt {
+            ReturnType::Type(_, ty) => extract_tuple_elements(ty),
+            ReturnType::Default => None,
+        }
+        .ok_or(ConvertErrorFromCpp::NotAHomogeneousTuple)?;
+        // syn can't compare types, so compare their tokens.
+        let first = match elements.split_first() {
+            Some((first, rest))
+                if rest.iter().all(|ty| {
+                    ty.to_token_stream().to_string() == first.to_token_stream().to_string()
+                }) =>
+            {
+                *first
+            }
+            _ => return Err(ConvertErrorFromCpp::NotAHomogeneousTuple),
+        };
+        let pointer =
+            self.convert_boxed_type(parse_quote! { *mut #first }, ns, PointerTreatment::Pointer)?;
+        let element_ty = match pointer.ty.as_ref() {
+            Type::Ptr(TypePtr { elem, .. }) => match elem.as_ref() {
+                Type::Path(tp)
+                    if known_types().is_by_value_built_in(&QualifiedName::from_type_path(tp)) =>
+                {
+                    elem.as_ref().clone()
+                }
+                _ => return Err(ConvertErrorFromCpp::NotAHomogeneousTuple),
+            },
+            _ => return Err(ConvertErrorFromCpp::NotAHomogeneousTuple),
+        };
+        let array_ident = make_ident("return_elements");
+        let array_conversion =
+            TypeConversionPolicy::new_for_tuple_array(*pointer.ty, elements.len());
+        let array_param_ty = array_conversion.cxxbridge_type().clone();
+        let array_pat: Pat = parse_quote! { #array_ident };
+        Ok(ReturnTypeAnalysis {
+            rt: ReturnType::Default,
+            conversion: Some(TypeConversionPolicy::new_from_tuple_to_array(element_ty)),
+            deps: pointer.types_encountered,
+            placement_param_needed: Some((
+                parse_quote! { #array_pat: #array_param_ty },
+                ArgumentAnalysis {
+                    conversion: array_conversion,
+                    name: array_pat.into(),
+                    self_type: None,
+                    has_lifetime: false,
+                    is_mutable_reference: false,
+                    deps: HashSet::new(),
+                    requires_unsafe: UnsafetyNeeded::None,
+                    is_placement_return_destination: false,
+                },
+            )),
+            ..Default::default()
+        })
+    }
+
+    /// The return type of a function whose return value is listed in
+    /// `std_array!`: a `std::array` of a built-in type, which Rust gets as
+    /// a `[T; N]`.
+    fn convert_std_array_return_type(
+        &mut self,
+        rt: &ReturnType,
+        std_array: &StdArray,
+        ns: &Namespace,
+    ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
+        let (ty, deps) = match rt {
+            ReturnType::Type(_, ty) => self.std_array_type(std_array, ty, ns)?,
+            ReturnType::Default => return Err(ConvertErrorFromCpp::NotAStdArray),
+        };
+        Ok(ReturnTypeAnalysis {
+            rt: parse_quote! { -> #ty },
+            conversion: Some(TypeConversionPolicy::new_unconverted(ty)),
+            deps,
+            ..Default::default()
+        })
+    }
+
+    /// The return type of a `pair_tuple!` function: a `std::pair`, or a
+    /// `std::optional` of one, whose elements are built-in types, such as
+    /// `int32_t`, `std::string`s, `std::unique_ptr`s or further such pairs.
+    /// The C++ wrapper moves each element into storage supplied by Rust
+    /// through an extra parameter, and Rust returns a tuple of the same
+    /// shape as the pair.
+    fn convert_pair_tuple_return_type(
+        &mut self,
+        rt: &ReturnType,
+        ns: &Namespace,
+    ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
+        let (pair, optional) = match rt {
+            ReturnType::Type(_, ty) if extract_pair_elements(ty).is_some() => (ty.as_ref(), false),
+            ReturnType::Type(_, ty) => match extract_std_optional_type(ty) {
+                Some(inner) if extract_pair_elements(inner).is_some() => (inner, true),
+                _ => return Err(ConvertErrorFromCpp::NotATrivialPair),
+            },
+            _ => return Err(ConvertErrorFromCpp::NotATrivialPair),
+        };
+        let mut elements = Vec::new();
+        let mut deps = HashSet::new();
+        let mut shape = self.pair_shape(pair, "", ns, &mut elements, &mut deps)?;
+        // As for any other returned optional, the C++ wrapper also fills in a
+        // flag saying whether it had a value. If not, the elements are left
+        // alone.
+        let placement_param_needed = optional.then(|| {
+            let has_value = make_ident("return_has_value");
+            let flag_param = Self::optional_return_flag_param(&has_value);
+            shape = PairShape::Optional(has_value, Box::new(shape.clone()));
+            flag_param
+        });
+        let mut shape = Some(shape);
+        let pair_element_params_needed = elements
+            .into_iter()
+            .map(|(member, ident, ty)| {
+                let conversion = match ty {
+                    Some(ty) => {
+                        TypeConversionPolicy::new_for_pair_element(ty, member, shape.take())
+                    }
+                    None => TypeConversionPolicy::new_for_pair_string_element(member, shape.take()),
+                };
+                let param_ty = conversion.cxxbridge_type().clone();
+                let pat: Pat = parse_quote! { #ident };
+                (
+                    parse_quote! { #pat: #param_ty },
+                    ArgumentAnalysis {
+                        conversion,
+                        name: pat.into(),
+                        self_type: None,
+                        has_lifetime: false,
+                        is_mutable_reference: false,
+                        deps: HashSet::new(),
+                        requires_unsafe: UnsafetyNeeded::None,
+                        is_placement_return_destination: false,
+                    },
+                )
+            })
+            .collect();
+        Ok(ReturnTypeAnalysis {
+            rt: ReturnType::Default,
+            conversion: Some(TypeConversionPolicy::new_from_pair_to_elements(
+                pair.clone(),
+            )),
+            deps,
+            placement_param_needed,
+            pair_element_params_needed,
+            ..Default::default()
+        })
+    }
+
+    /// The parameter into which the C++ wrapper puts whether a returned
+    /// `std::optional` had a value.
+    fn optional_return_flag_param(has_value: &crate::minisyn::Ident) -> (FnArg, ArgumentAnalysis) {
+        let flag_conversion = TypeConversionPolicy::new_for_optional_return_flag();
+        let flag_ty = flag_conversion.cxxbridge_type().clone();
+        let flag_pat: Pat = parse_quote! { #has_value };
+        (
+            parse_quote! { #flag_pat: #flag_ty },
+            ArgumentAnalysis {
+                conversion: flag_conversion,
+                name: flag_pat.into(),
+                self_type: None,
+                has_lifetime: false,
+                is_mutable_reference: false,
+                deps: HashSet::new(),
+                requires_unsafe: UnsafetyNeeded::None,
+                is_placement_return_destination: false,
+            },
+        )
+    }
+
+    /// The shape of the pair, or pair element, `ty` at the member path
+    /// `member`, adding the member path, out parameter name and type of
+    /// each element to `elements`. That's a `*mut T` for a built-in
+    /// element, a `&mut cxx::UniquePtr<T>` for a `std::unique_ptr`, which
+    /// can only be moved out of the pair, or `None` for a `std::string`,
+    /// which becomes a `String`.
+    fn pair_shape(
+        &mut self,
+        ty: &Type,
+        member: &str,
+        ns: &Namespace,
+        elements: &mut Vec<(String, Ident, Option<Type>)>,
+        deps: &mut HashSet<QualifiedName>,
+    ) -> Result<PairShape, ConvertErrorFromCpp> {
+        if let Some((first, second)) = extract_pair_elements(ty) {
+            let child_member = |child: &str| match member {
+                "" => child.to_string(),
+                _ => format!("{member}.{child}"),
+            };
+            let first = self.pair_shape(first, &child_member("first"), ns, elements, deps)?;
+            let second = self.pair_shape(second, &child_member("second"), ns, elements, deps)?;
+            return Ok(PairShape::Pair(Box::new(first), Box::new(second)));
+        }
+        let ident = make_ident(format!("return_{}", member.replace('.', "_")));
+        if is_std_string(ty) {
+            elements.push((member.to_string(), ident.clone().into(), None));
+            return Ok(PairShape::Element(
+                ident,
+                Box::new(parse_quote! { ::std::string::String }),
+            ));
+        }
+        let pointer =
+            self.convert_boxed_type(parse_quote! { *mut #ty }, ns, PointerTreatment::Pointer)?;
+        let (param_ty, shape) = match pointer.ty.as_ref() {
+            Type::Ptr(TypePtr { elem, .. }) => match elem.as_ref() {
+                Type::Path(tp)
+                    if known_types().is_by_value_built_in(&QualifiedName::from_type_path(tp)) =>
+                {
+                    (
+                        pointer.ty.as_ref().clone(),
+                        PairShape::Element(ident.clone(), Box::new(elem.as_ref().clone().into())),
+                    )
+                }
+                elem if is_unique_ptr(elem) => (
+                    parse_quote! { &mut #elem },
+                    PairShape::UniquePtr(ident.clone(), Box::new(elem.clone().into())),
+                ),
+                _ => return Err(ConvertErrorFromCpp::NotATrivialPair),
+            },
+            _ => return Err(ConvertErrorFromCpp::NotATrivialPair),
+        };
+        deps.extend(pointer.types_encountered);
+        elements.push((member.to_string(), ident.into(), Some(param_ty)));
+        Ok(shape)
+    }
+
+    /// The return type of a `vector_slices!` function: a reference to a
+    /// `std::vector` of numbers or POD types, which Rust views as a slice.
+    /// It must be a reference, so that the vector outlives the call.
+    fn convert_vector_slice_return_type(
+        &mut self,
+        rt: &ReturnType,
+        ns: &Namespace,
+        references: &References,
+        sophistication: TypeConversionSophistication,
+        cpp_name: &str,
+    ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
+        let not_a_vector_reference = || ConvertErrorFromCpp::NotAVectorReference(cpp_name.into());
+        let mut return_analysis = self.convert_return_type(rt, ns, references, sophistication)?;
+        let ty = match &return_analysis.rt {
+            ReturnType::Type(_, ty)
+                if !return_analysis
+                    .conversion
+                    .as_ref()
+                    .is_some_and(|conv| conv.cpp_work_needed() || conv.rust_work_needed()) =>
+            {
+                ty.as_ref().clone()
+            }
+            _ => return Err(not_a_vector_reference()),
+        };
+        let elem = extract_cxx_vector_reference_item(&ty).ok_or_else(not_a_vector_reference)?;
+        if !self.is_copyable_pod(elem) {
+            return Err(ConvertErrorFromCpp::NonTrivialVectorSlice);
+        }
+        return_analysis.conversion = Some(TypeConversionPolicy::new_from_vector_to_slice(ty));
+        Ok(return_analysis)
+    }
+
+    /// The return type of a `nanoseconds_count!` function: a
+    /// `std::chrono::duration`, which the C++ wrapper converts to
+    /// nanoseconds and returns the count of. There's no need for Rust to
+    /// know about the duration type itself.
+    fn convert_nanoseconds_count_return_type(
+        rt: &ReturnType,
+    ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
+        match rt {
+            ReturnType::Type(_, ty) if is_std_chrono_duration(ty) => Ok(ReturnTypeAnalysis {
+                rt: parse_quote! { -> i64 },
+                conversion: Some(TypeConversionPolicy::new_from_duration_to_nanoseconds_count()),
+                ..Default::default()
+            }),
+            _ => Err(ConvertErrorFromCpp::NotADuration),
+        }
+    }
+
+    /// The return type of a `seconds_count!` function: a
+    /// `std::chrono::duration`, which the C++ wrapper converts to a count of
+    /// seconds in a `double`. Rust may then make a `Duration` of that.
+    fn convert_seconds_count_return_type(
+        rt: &ReturnType,
+        policy: SecondsCountPolicy,
+    ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
+        match rt {
+            ReturnType::Type(_, ty) if is_std_chrono_duration(ty) => Ok(ReturnTypeAnalysis {
+                rt: parse_quote! { -> f64 },
+                conversion: Some(TypeConversionPolicy::new_from_duration_to_seconds_count(
+                    policy,
+                )),
+                ..Default::default()
+            }),
+            _ => Err(ConvertErrorFromCpp::NotADuration),
+        }
+    }
+
     fn convert_return_type(
         &mut self,
         rt: &ReturnType,
@@ -1923,8 +3999,160 @@ impl<'a> FnAnalyzer<'a> {
         references: &References,
         sophistication: TypeConversionSophistication,
     ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
+        // An intrusive pointer to a type listed in `intrusive_ptr!` is
+        // returned as a raw pointer, along with the reference it held, which
+        // Rust wraps up in an `autocxx::IntrusivePtr`.
+        if let ReturnType::Type(_, ty) = rt {
+            if let Some((pointee, _)) = self
+                .intrusive_ptr_pointee(ty)
+                .filter(|_| matches!(sophistication, TypeConversionSophistication::Regular))
+            {
+                let deps = match &pointee {
+                    Type::Path(tp) => std::iter::once(QualifiedName::from_type_path(tp)).collect(),
+                    _ => HashSet::new(),
+                };
+                return Ok(ReturnTypeAnalysis {
+                    rt: parse_quote! { -> *mut #pointee },
+                    conversion: Some(TypeConversionPolicy::new_for_intrusive_ptr_return(pointee)),
+                    deps,
+                    ..Default::default()
+                });
+            }
+        }
         Ok(match rt {
             ReturnType::Default => ReturnTypeAnalysis::default(),
+            ReturnType::Type(_, boxed_type)
+                if matches!(sophistication, TypeConversionSophistication::Regular)
+                    && matches!(
+                        extract_std_optional_type(boxed_type),
+                        Some(Type::Path(inner)) if inner.path.is_ident("bool")
+                    ) =>
+            {
+                ReturnTypeAnalysis {
+                    rt: parse_quote! { -> i8 },
+                    conversion: Some(TypeConversionPolicy::new_from_optional_bool()),
+                    ..Default::default()
+                }
+            }
+            ReturnType::Type(_, boxed_type)
+                if matches!(sophistication, TypeConversionSophistication::Regular)
+                    && extract_std_optional_type(boxed_type)
+                        .and_then(extract_reference_wrapper_type)
+                        .is_some() =>
+            {
+                // This returns a pointer which is null if the optional is
+                // empty. The `Option<&T>` borrows from elsewhere, so treat
+                // it like a returned reference: the function must have
+                // exactly one reference input to lend it a lifetime.
+                let referent = extract_std_optional_type(boxed_type)
+                    .and_then(extract_reference_wrapper_type)
+                    .unwrap();
+                let annotated_type = self.convert_boxed_type(
+                    parse_quote! { *const #referent },
+                    ns,
+                    PointerTreatment::Pointer,
+                )?;
+                let ty = annotated_type.ty;
+                ReturnTypeAnalysis {
+                    rt: parse_quote! { -> #ty },
+                    conversion: Some(TypeConversionPolicy::new_from_optional_reference_wrapper(
+                        *ty,
+                    )),
+                    was_reference: true,
+                    deps: annotated_type.types_encountered,
+                    ..Default::default()
+                }
+            }
+            ReturnType::Type(_, boxed_type)
+                if matches!(sophistication, TypeConversionSophistication::Regular)
+                    && extract_std_optional_type(boxed_type).is_some_and(is_std_vector) =>
+            {
+                // A vector can't be copied out like a POD value, so it's
+                // moved into a `UniquePtr` which is null if the optional was
+                // empty. That keeps an empty optional distinct from an
+                // empty vector.
+                let inner = extract_std_optional_type(boxed_type).unwrap();
+                let annotated_type = self.convert_boxed_type(
+                    parse_quote! { root::std::unique_ptr<#inner> },
+                    ns,
+                    PointerTreatment::Pointer,
+                )?;
+                let ty = annotated_type.ty;
+                ReturnTypeAnalysis {
+                    rt: parse_quote! { -> #ty },
+                    conversion: Some(TypeConversionPolicy::new_from_optional_vector(*ty)),
+                    deps: annotated_type.types_encountered,
+                    ..Default::default()
+                }
+            }
+            ReturnType::Type(_, boxed_type)
+                if matches!(sophistication, TypeConversionSophistication::Regular)
+                    && extract_std_optional_type(boxed_type).is_some() =>
+            {
+                // There's no value we could use to say the optional was
+                // empty (zero, or a NaN, might be a real value) so the C++
+                // wrapper also fills in a flag parameter saying whether it
+                // had one. That means copying the value, so it has to be a
+                // number or a POD type.
+                let inner = extract_std_optional_type(boxed_type).unwrap();
+                let annotated_type = self.convert_boxed_type(
+                    parse_quote! { *mut #inner },
+                    ns,
+                    PointerTreatment::Pointer,
+                )?;
+                let ty = match *annotated_type.ty {
+                    Type::Ptr(TypePtr { elem, .. }) if self.is_copyable_pod(&elem) => *elem,
+                    Type::Ptr(TypePtr { elem, .. }) => {
+                        return Err(self.unsupported_optional_return(&elem))
+                    }
+                    _ => unreachable!("converting a pointer type should give a pointer type"),
+                };
+                let has_value = make_ident("return_has_value");
+                ReturnTypeAnalysis {
+                    rt: parse_quote! { -> #ty },
+                    placement_param_needed: Some(Self::optional_return_flag_param(&has_value)),
+                    conversion: Some(TypeConversionPolicy::new_from_optional_value(ty, has_value)),
+                    deps: annotated_type.types_encountered,
+                    ..Default::default()
+                }
+            }
+            ReturnType::Type(_, boxed_type)
+                if matches!(sophistication, TypeConversionSophistication::Regular)
+                    && is_std_wstring(boxed_type) =>
+            {
+                ReturnTypeAnalysis {
+                    rt: parse_quote! { -> String },
+                    conversion: Some(TypeConversionPolicy::new_from_wstring()),
+                    ..Default::default()
+                }
+            }
+            ReturnType::Type(_, boxed_type)
+                if matches!(sophistication, TypeConversionSophistication::Regular)
+                    && is_std_u16string(boxed_type) =>
+            {
+                ReturnTypeAnalysis {
+                    rt: parse_quote! { -> Vec<u16> },
+                    conversion: Some(TypeConversionPolicy::new_from_u16string(
+                        self.config.u16string_return_policy,
+                    )),
+                    ..Default::default()
+                }
+            }
+            ReturnType::Type(_, boxed_type)
+                if matches!(sophistication, TypeConversionSophistication::Regular)
+                    && is_std_string_view(boxed_type) =>
+            {
+                // The returned `&str` borrows from whatever is viewed, so
+                // treat it like a returned reference: that way the
+                // function must have exactly one reference input from
+                // which the return value takes its lifetime.
+                ReturnTypeAnalysis {
+                    rt: parse_quote! { -> &[u8] },
+                    conversion: Some(TypeConversionPolicy::new_from_string_view()),
+                    was_reference: true,
+                    ..Default::default()
+                }
+            }
             ReturnType::Type(rarrow, boxed_type) => {
                 let annotated_type =
                     self.convert_boxed_type(boxed_type.clone(), ns, references.return_treatment())?;
@@ -1975,8 +4203,18 @@ impl<'a> FnAnalyzer<'a> {
                             // (CxxString) or because they're a concrete type where we haven't attempted to do
                             // the analysis to work out the type's size. For these, we always return a plain old
                             // UniquePtr<T>. These restrictions may be fixed in future.
-                            let conversion =
-                                Some(TypeConversionPolicy::new_to_unique_ptr(ty.clone()));
+                            let conversion = Some(match self.config.string_return_policy {
+                                Some(policy)
+                                    if known_types().convertible_from_strs(&tn)
+                                        && matches!(
+                                            sophistication,
+                                            TypeConversionSophistication::Regular
+                                        ) =>
+                                {
+                                    TypeConversionPolicy::new_to_rust_string(ty.clone(), policy)
+                                }
+                                _ => TypeConversionPolicy::new_to_unique_ptr(ty.clone()),
+                            });
                             ReturnTypeAnalysis {
                                 rt: ReturnType::Type(*rarrow, boxed_type),
                                 conversion,
@@ -2000,6 +4238,12 @@ impl<'a> FnAnalyzer<'a> {
                                 )
                             {
                                 TypeConversionPolicy::return_reference_into_wrapper(ty.clone())
+                            } else if !was_reference
+                                && matches!(ty, Type::Path(p) if self.config.rejects_unknown_bits(
+                                    &QualifiedName::from_type_path(p).to_cpp_name()
+                                ))
+                            {
+                                TypeConversionPolicy::new_for_checked_bitmask(ty.clone())
                             } else {
                                 TypeConversionPolicy::new_unconverted(ty.clone())
                             },
@@ -2011,6 +4255,7 @@ impl<'a> FnAnalyzer<'a> {
                             was_mutable_reference,
                             deps: annotated_type.types_encountered,
                             placement_param_needed: None,
+                            pair_element_params_needed: Vec::new(),
                         }
                     }
                 }
@@ -2032,6 +4277,7 @@ impl<'a> FnAnalyzer<'a> {
     /// for further analysis phases.
     fn add_constructors_present(&mut self, mut apis: ApiVec<FnPrePhase1>) -> ApiVec<FnPrePhase2> {
         let all_items_found = find_constructors_present(&apis);
+        let immovable_types = find_immovable_types(&apis, &all_items_found);
         for (self_ty, items_found) in all_items_found.iter() {
             if self.config.exclude_impls {
                 // Remember that `find_constructors_present` mutates `apis`, so we always have to
@@ -2094,12 +4340,34 @@ impl<'a> FnAnalyzer<'a> {
             }
         }
 
-        // Also, annotate each type with the constructors we found.
+        // Also, annotate each type with the constructors we found, and stop
+        // trying to return types by value if they can't get out of C++.
         let mut results = ApiVec::new();
         convert_apis(
             apis,
             &mut results,
-            Api::fun_unchanged,
+            |name, fun, mut analysis| {
+                if analysis.ignore_reason.is_ok() {
+                    if let Some(ty) = analysis
+                        .ret_conversion
+                        .as_ref()
+                        .and_then(|conv| conv.returned_by_value_type())
+                        .filter(|ty| immovable_types.contains(ty))
+                    {
+                        let error_context = match &analysis.kind {
+                            FnKind::Method { impl_for, .. } => {
+                                self.error_context_for_method(impl_for, &analysis.rust_name)
+                            }
+                            _ => ErrorContext::new_for_item(make_ident(&analysis.rust_name)),
+                        };
+                        analysis.ignore_reason = Err(ConvertErrorWithContext(
+                            ConvertErrorFromCpp::ReturnsImmovableType(ty),
+                            Some(error_context),
+                        ));
+                    }
+                }
+                Api::fun_unchanged(name, fun, analysis)
+            },
             |name, details, analysis| {
                 let items_found = all_items_found.get(&name.name);
                 Ok(Box::new(std::iter::once(Api::Struct {
@@ -2188,6 +4456,32 @@ impl<'a> FnAnalyzer<'a> {
     }
 }
 
+/// Functions listed in `vector_slices!` which don't return a reference to a
+/// `std::vector` were asked for explicitly, so rather than quietly ignoring
+/// them as we would other unsupported functions, fail the whole build.
+pub(crate) fn confirm_vector_slices_are_references(
+    apis: &ApiVec<FnPrePhase2>,
+) -> Result<(), ConvertErrorFromCpp> {
+    for api in apis.iter() {
+        if let Api::Function {
+            analysis:
+                FnAnalysis {
+                    ignore_reason:
+                        Err(ConvertErrorWithContext(
+                            err @ ConvertErrorFromCpp::NotAVectorReference(_),
+                            _,
+                        )),
+                    ..
+                },
+            ..
+        } = api
+        {
+            return Err(err.clone());
+        }
+    }
+    Ok(())
+}
+
 /// Attempts to determine whether this function name is a constructor, and if so,
 /// returns the suffix.
 fn constructor_with_suffix<'a>(rust_name: &'a str, nested_type_ident: &str) -> Option<&'a str> {
@@ -2212,6 +4506,7 @@ impl Api<FnPhase> {
                 }
             },
             Api::RustSubclassFn { subclass, .. } => subclass.0.name.clone(),
+            Api::ContainerIterator { container, .. } => container.clone(),
             Api::IgnoredItem {
                 name,
                 ctx: Some(ctx),
@@ -2246,6 +4541,9 @@ impl Api<FnPhase> {
                 },
                 ..
             } | Api::StringConstructor { .. }
+                | Api::WStringConstructor { .. }
+                | Api::ContainerIterator { .. }
+                | Api::ConstantAccessor { .. }
                 | Api::ConcreteType { .. }
                 | Api::CType { .. }
                 | Api::RustSubclassFn { .. }
@@ -2267,6 +4565,7 @@ impl Api<FnPhase> {
         match self {
             Api::Function { ref analysis, .. } => Some(analysis.cxxbridge_name.clone()),
             Api::StringConstructor { .. }
+            | Api::WStringConstructor { .. }
             | Api::Const { .. }
             | Api::IgnoredItem { .. }
             | Api::RustSubclassFn { .. } => None,
@@ -2352,3 +4651,66 @@ fn describe_arg(arg: &FnArg) -> String {
         },
     }
 }
+
+/// Whether this is the `this` parameter bindgen gives to methods.
+fn is_this_param(arg: &FnArg) -> bool {
+    matches!(arg, FnArg::Typed(PatType { pat, .. }) if matches!(pat.as_ref(), Pat::Ident(pti) if pti.ident == "this"))
+}
+
+/// Whether this parameter, following a pointer, is the `usize` number of
+/// elements to which the pointer points.
+fn is_slice_length(_ptr: &PatType, len: &PatType) -> bool {
+    matches!(len.ty.as_ref(), Type::Path(tp) if tp.path.is_ident("usize"))
+}
+
+/// Whether this is the pattern of a parameter with this name.
+fn pat_is(pat: &Pat, name: &str) -> bool {
+    matches!(pat, Pat::Ident(pp) if pp.ident == name)
+}
+
+/// An `output_buffers!` buffer is a `uint8_t*` or a `void*`, and is
+/// always mutable, so this returns `Some(true)` for those and `None` for
+/// anything else.
+fn output_buffer_pointer(ty: &Type) -> Option<bool> {
+    match ty {
+        Type::Ptr(TypePtr {
+            mutability: Some(_),
+            elem,
+            ..
+        }) => match elem.as_ref() {
+            Type::Path(tp)
+                if tp
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|seg| seg.ident == "u8" || seg.ident == "c_void") =>
+            {
+                Some(true)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// For a `*const c_void` returns `Some(false)`, and for a `*mut c_void`
+/// returns `Some(true)`.
+fn void_pointer_mutability(ty: &Type) -> Option<bool> {
+    match ty {
+        Type::Ptr(TypePtr {
+            mutability, elem, ..
+        }) => match elem.as_ref() {
+            Type::Path(tp)
+                if tp
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|seg| seg.ident == "c_void") =>
+            {
+                Some(mutability.is_some())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
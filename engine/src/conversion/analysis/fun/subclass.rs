@@ -162,6 +162,7 @@ pub(super) fn create_subclass_function(
                 argument_conversion,
                 kind,
                 pass_obs_field: true,
+                catches_exceptions: false,
                 qualification: Some(cpp),
             },
             superclass: superclass.clone(),
@@ -205,6 +206,7 @@ pub(super) fn create_subclass_constructor(
         argument_conversion: args.collect(),
         kind: CppFunctionKind::SynthesizedConstructor,
         pass_obs_field: false,
+        catches_exceptions: false,
         qualification: Some(cpp.clone()),
         original_cpp_name: cpp.to_cpp_name(),
     };
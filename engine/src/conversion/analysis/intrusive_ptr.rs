@@ -0,0 +1,101 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert},
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// The name of the static method synthesized for each type listed in
+/// `intrusive_ptr!` which adds a reference to an object.
+pub(crate) const ADD_REF_METHOD_NAME: &str = "cpp_intrusive_add_ref";
+
+/// Likewise, the static method which removes a reference.
+pub(crate) const RELEASE_METHOD_NAME: &str = "cpp_intrusive_release";
+
+/// For each type listed in `intrusive_ptr!` we synthesize a pair of static
+/// methods which pass a pointer to one of its objects to the C++ functions
+/// adjusting its reference count. Those are usually found by
+/// argument-dependent lookup, as `boost::intrusive_ptr` itself does.
+/// The `IntrusivePtrTarget` implementation, which lets Rust clone and drop
+/// `IntrusivePtr`s, calls them.
+pub(crate) fn add_intrusive_ptrs(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            if let Some(ip) = config.get_intrusive_ptr(&name.name.to_cpp_name()) {
+                results.push(create_ref_count_function(
+                    &name.name,
+                    ADD_REF_METHOD_NAME,
+                    "add_ref",
+                    &ip.add_ref,
+                ));
+                results.push(create_ref_count_function(
+                    &name.name,
+                    RELEASE_METHOD_NAME,
+                    "release",
+                    &ip.release,
+                ));
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_ref_count_function(
+    ty: &QualifiedName,
+    method_name: &str,
+    suffix: &str,
+    function: &str,
+) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_intrusive_{suffix}", ty.get_final_item())),
+    );
+    let function = QualifiedName::new_from_cpp_name(function);
+    let typ = ty.to_type_path();
+    let ptr: FnArg = parse_quote! {
+        ptr: *mut #typ
+    };
+    let doc = format!(
+        "Calls the C++ `{}` on the non-null `ptr`. This is what `IntrusivePtr` uses \
+        to keep count of its references.",
+        function.to_cpp_name()
+    );
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(method_name),
+                [ptr].into_iter().collect(),
+                parse_quote! {},
+                CppFunctionBody::FunctionCall(
+                    function.get_namespace().clone(),
+                    function.get_final_ident(),
+                ),
+            )
+        }),
+        analysis: (),
+    }
+}
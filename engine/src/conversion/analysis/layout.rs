@@ -0,0 +1,87 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, Layout},
+        apivec::ApiVec,
+    },
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// For each type listed in `expose_layout!` we synthesize static methods
+/// which return its C++ `sizeof` and `alignof`. Where bindgen told us the
+/// layout, the C++ also asserts that it agrees, because Rust code generation
+/// then bakes the same numbers into constants.
+pub(crate) fn add_layout_accessors(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct {
+            ref name,
+            ref details,
+            ..
+        } = api
+        {
+            if config.is_layout_type(&name.name.to_cpp_name()) {
+                results.extend(
+                    create_layout_accessors(&name.name, details.layout.as_ref()).into_iter(),
+                );
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_layout_accessors(ty: &QualifiedName, layout: Option<&Layout>) -> Vec<Api<PodPhase>> {
+    [
+        (
+            "cpp_size",
+            "The size in bytes of this type, according to C++ `sizeof`.",
+            CppFunctionBody::SizeOf(ty.clone(), layout.map(|l| l.size)),
+        ),
+        (
+            "cpp_align",
+            "The alignment in bytes of this type, according to C++ `alignof`.",
+            CppFunctionBody::AlignOf(ty.clone(), layout.map(|l| l.align)),
+        ),
+    ]
+    .into_iter()
+    .map(|(method, doc, payload)| {
+        let name = QualifiedName::new(
+            ty.get_namespace(),
+            make_ident(format!("{}_{method}", ty.get_final_item())),
+        );
+        let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+        Api::Function {
+            name: ApiName::new_from_qualified_name(name),
+            fun: Box::new(FuncToConvert {
+                doc_attrs: vec![doc_attr.into()],
+                self_ty: Some(ty.clone()),
+                ..FuncToConvert::new_synthesized(
+                    make_ident(method),
+                    Default::default(),
+                    parse_quote! {
+                        -> usize
+                    },
+                    payload,
+                )
+            }),
+            analysis: (),
+        }
+    })
+    .collect()
+}
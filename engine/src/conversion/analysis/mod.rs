@@ -9,18 +9,32 @@
 pub(crate) mod abstract_types;
 pub(crate) mod allocators;
 pub(crate) mod casts;
+pub(crate) mod comparisons;
+pub(crate) mod compound_assign;
 pub(crate) mod constructor_deps;
 pub(crate) mod ctypes;
+pub(crate) mod default_args;
 pub(crate) mod deps;
 mod depth_first;
+pub(crate) mod deref;
+pub(crate) mod display;
 mod doc_label;
+pub(crate) mod enum_display;
 pub(crate) mod fun;
 pub(crate) mod gc;
+pub(crate) mod intrusive_ptr;
+pub(crate) mod layout;
+pub(crate) mod move_out_of_vector;
 mod name_check;
+pub(crate) mod operator_bool;
+pub(crate) mod operator_not;
 pub(crate) mod pod; // hey, that rhymes
 pub(crate) mod remove_ignored;
 mod replace_hopeless_typedef_targets;
+pub(crate) mod subscript;
 pub(crate) mod tdef;
+pub(crate) mod template_returns;
+pub(crate) mod trait_probes;
 mod type_converter;
 
 pub(crate) use name_check::check_names;
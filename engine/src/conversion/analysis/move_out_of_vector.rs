@@ -0,0 +1,83 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, References},
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// cxx's `CxxVector` only hands out references to its elements, so a
+/// move-only type can't be got out of one. For each type listed in
+/// `move_out_of_vector!` we synthesize a static method which moves an
+/// element out into a new heap allocation, returning null if the index
+/// is out of bounds. The element left behind is in whatever valid but
+/// moved-from state the type's move constructor leaves it.
+pub(crate) fn add_moves_out_of_vectors(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            if config.is_move_out_of_vector_type(&name.name.to_cpp_name()) {
+                results.push(create_move_out_of_vector(&name.name));
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_move_out_of_vector(ty: &QualifiedName) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_take_from_vector", ty.get_final_item())),
+    );
+    let typ = ty.to_type_path();
+    let vector: FnArg = parse_quote! {
+        vector: *mut root::std::vector<#typ>
+    };
+    let index: FnArg = parse_quote! {
+        index: usize
+    };
+    let references = References {
+        ref_params: [make_ident("vector")].into_iter().collect(),
+        ..Default::default()
+    };
+    let doc = "Moves the element at `index` out of `vector`, using its C++ move \
+        constructor, and returns it in a new `UniquePtr`. The element left in \
+        the vector is in a valid but moved-from state. Returns a null \
+        `UniquePtr` if `index` is out of bounds.";
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            references,
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident("take_from_vector"),
+                [vector, index].into_iter().collect(),
+                parse_quote! {
+                    -> root::std::unique_ptr<#typ>
+                },
+                CppFunctionBody::MoveOutOfVector,
+            )
+        }),
+        analysis: (),
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, References},
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// bindgen tells us nothing about conversion operators, so for each type
+/// listed in `operator_bool!` we synthesize a method which calls its
+/// `operator bool()`. In Rust, an implicit conversion to `bool` isn't
+/// idiomatic, so this becomes a normal method with the requested name.
+pub(crate) fn add_operator_bools(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            let cpp_name = name.name.to_cpp_name();
+            results.extend(
+                config
+                    .operator_bools
+                    .iter()
+                    .filter(|ob| ob.ty == cpp_name)
+                    .map(|ob| create_operator_bool(&name.name, &ob.method.to_string())),
+            );
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_operator_bool(ty: &QualifiedName, method: &str) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_operator_bool", ty.get_final_item())),
+    );
+    let typ = ty.to_type_path();
+    let fnarg: FnArg = parse_quote! {
+        this: *const #typ
+    };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            references: References::new_with_this_as_reference(),
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(method),
+                [fnarg].into_iter().collect(),
+                parse_quote! {
+                    -> bool
+                },
+                CppFunctionBody::ConversionOperator,
+            )
+        }),
+        analysis: (),
+    }
+}
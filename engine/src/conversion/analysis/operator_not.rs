@@ -0,0 +1,79 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, References},
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// bindgen tells us nothing about operators, so for each type listed in
+/// `operator_not!` we synthesize a method which calls its `operator!()`.
+/// Rust's `Not` trait is for bitwise negation too, and takes its operand
+/// by value, so this becomes a normal method with the requested name.
+pub(crate) fn add_operator_nots(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            let cpp_name = name.name.to_cpp_name();
+            results.extend(
+                config
+                    .operator_nots
+                    .iter()
+                    .filter(|on| on.ty == cpp_name)
+                    .map(|on| create_operator_not(&name.name, &on.method.to_string())),
+            );
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_operator_not(ty: &QualifiedName, method: &str) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_operator_not", ty.get_final_item())),
+    );
+    let typ = ty.to_type_path();
+    let fnarg: FnArg = parse_quote! {
+        this: *const #typ
+    };
+    let doc = format!(
+        "Returns the result of the C++ `operator!` applied to this `{}`.",
+        ty.get_final_item()
+    );
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            references: References::new_with_this_as_reference(),
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(method),
+                [fnarg].into_iter().collect(),
+                parse_quote! {
+                    -> bool
+                },
+                CppFunctionBody::LogicalNot,
+            )
+        }),
+        analysis: (),
+    }
+}
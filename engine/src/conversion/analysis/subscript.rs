@@ -0,0 +1,92 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{IncludeCppConfig, Subscript};
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert, References},
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// The name of the method synthesized for each type listed in
+/// `subscript!`. The Rust `Index` implementation calls it.
+pub(crate) const INDEX_METHOD_NAME: &str = "cpp_index";
+
+/// bindgen tells us nothing about operators, so for each type listed in
+/// `subscript!` we synthesize a method which calls its `operator[]` on a
+/// `const` reference, returning a `const` reference to the element, or
+/// `None` if the index isn't less than the container's `size()`. Rust's
+/// `Index` is then implemented using that method, panicking instead of
+/// returning `None`. There's never an `IndexMut`: the non-`const`
+/// `operator[]`, if any, isn't used, so the elements can't be changed this
+/// way.
+pub(crate) fn add_subscripts(
+    config: &IncludeCppConfig,
+    apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { ref name, .. } = api {
+            let cpp_name = name.name.to_cpp_name();
+            results.extend(
+                config
+                    .subscripts
+                    .iter()
+                    .filter(|s| s.ty == cpp_name)
+                    .map(|s| create_subscript(&name.name, s)),
+            );
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_subscript(ty: &QualifiedName, subscript: &Subscript) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty.get_namespace(),
+        make_ident(format!("{}_{INDEX_METHOD_NAME}", ty.get_final_item())),
+    );
+    let typ = ty.to_type_path();
+    let index_typ = QualifiedName::new_from_cpp_name(&subscript.index).to_type_path();
+    let output_typ = QualifiedName::new_from_cpp_name(&subscript.output).to_type_path();
+    let this: FnArg = parse_quote! {
+        this: *const #typ
+    };
+    let index: FnArg = parse_quote! {
+        index: #index_typ
+    };
+    let doc = "Returns the element at `index` using the C++ `const` `operator[]`, \
+        or `None` if `index` is out of bounds. This is what the `Index` \
+        implementation uses.";
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            // The returned pointer becomes an `Option<&T>`.
+            references: References::new_with_this_as_reference(),
+            self_ty: Some(ty.clone()),
+            ..FuncToConvert::new_synthesized(
+                make_ident(INDEX_METHOD_NAME),
+                [this, index].into_iter().collect(),
+                parse_quote! {
+                    -> *const #output_typ
+                },
+                CppFunctionBody::SubscriptCall,
+            )
+        }),
+        analysis: (),
+    }
+}
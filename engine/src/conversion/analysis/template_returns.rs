@@ -0,0 +1,85 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{IncludeCppConfig, TemplateReturn};
+use syn::{parse_quote, punctuated::Punctuated};
+
+use crate::{
+    conversion::{
+        api::{Api, ApiName, FuncToConvert},
+        apivec::ApiVec,
+        convert_error::{ConvertErrorFromCpp, ErrorContext},
+    },
+    known_types::known_types,
+    types::QualifiedName,
+};
+
+use super::{fun::function_wrapper::CppFunctionBody, pod::PodPhase};
+
+/// bindgen tells us nothing about function templates, so for each type
+/// listed for a template in `template_returns!` we synthesize a function
+/// which calls it, instantiated with that type, and returns the result.
+/// Its return type is then analyzed just as if bindgen had found a
+/// function returning that type, so the usual conversions apply.
+pub(crate) fn add_template_return_instances(
+    config: &IncludeCppConfig,
+    mut apis: ApiVec<PodPhase>,
+) -> ApiVec<PodPhase> {
+    for template_return in &config.template_returns {
+        apis.extend(
+            template_return
+                .instances()
+                .map(|(ty, instance_name)| create_instance(template_return, ty, &instance_name)),
+        );
+    }
+    apis
+}
+
+fn create_instance(
+    template_return: &TemplateReturn,
+    ty: &str,
+    instance_name: &str,
+) -> Api<PodPhase> {
+    let name = QualifiedName::new_from_cpp_name(instance_name);
+    let ident = name.get_final_ident();
+    let ret_type = QualifiedName::new_from_cpp_name(ty);
+    // Unless it's a built-in type we'll refer to the type just as bindgen
+    // would, which is only possible if it's named by identifiers.
+    let nameable = known_types().is_known_type(&ret_type)
+        || ret_type
+            .segment_iter()
+            .all(|seg| syn::parse_str::<syn::Ident>(&seg).is_ok());
+    if !nameable {
+        return Api::IgnoredItem {
+            name: ApiName::new_from_qualified_name(name),
+            err: ConvertErrorFromCpp::UnnameableTemplateReturnType(ty.to_string()),
+            ctx: Some(ErrorContext::new_for_item(ident)),
+        };
+    }
+    let ret_type = ret_type.to_type_path();
+    let doc = format!("Calls `{}<{ty}>()`.", template_return.function);
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            doc_attrs: vec![doc_attr.into()],
+            ..FuncToConvert::new_synthesized(
+                ident,
+                Punctuated::new(),
+                parse_quote! {
+                    -> #ret_type
+                },
+                CppFunctionBody::TemplateCall(
+                    QualifiedName::new_from_cpp_name(&template_return.function),
+                    ty.to_string(),
+                ),
+            )
+        }),
+        analysis: (),
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for `probe_traits!`. Our own analysis of which special members
+//! a type has can be wrong, for instance if a `= default` copy constructor
+//! is in fact deleted because of a member. So for each type listed, we
+//! ask the C++ compiler itself: we add a `constexpr bool` to the header we
+//! give to bindgen for each type trait, and bindgen tells us its value.
+
+use autocxx_parser::IncludeCppConfig;
+use indexmap::set::IndexSet as HashSet;
+use syn::{Expr, ExprLit, Lit};
+
+use crate::{
+    conversion::{
+        api::{Api, NullPhase},
+        apivec::ApiVec,
+    },
+    types::QualifiedName,
+};
+
+/// The namespace of the probes in the header given to bindgen.
+pub(crate) const TRAIT_PROBE_NAMESPACE: &str = "autocxx_probes";
+
+/// The traits we probe, and the prefix of the name of each probe.
+const PROBED_TRAITS: [(&str, &str); 2] = [
+    ("std::is_copy_constructible", "copy_constructible_"),
+    ("std::is_move_constructible", "move_constructible_"),
+];
+
+/// C++ to append to the header given to bindgen, declaring the probes for
+/// each type listed in `probe_traits!`.
+pub(crate) fn trait_probe_header(config: &IncludeCppConfig) -> String {
+    let types = config.trait_probe_types();
+    if types.is_empty() {
+        return String::new();
+    }
+    let probes: String = types
+        .iter()
+        .enumerate()
+        .flat_map(|(index, ty)| {
+            PROBED_TRAITS.iter().map(move |(trt, prefix)| {
+                format!("constexpr bool {prefix}{index} = {trt}<{ty}>::value;\n")
+            })
+        })
+        .collect();
+    format!("#include <type_traits>\nnamespace {TRAIT_PROBE_NAMESPACE} {{\n{probes}}}\n")
+}
+
+/// Which probed types turned out not to have each trait. Types we didn't
+/// probe are assumed to have them all.
+#[derive(Default)]
+pub(crate) struct TraitProbeResults {
+    not_copy_constructible: HashSet<QualifiedName>,
+    not_move_constructible: HashSet<QualifiedName>,
+}
+
+impl TraitProbeResults {
+    pub(crate) fn is_copy_constructible(&self, ty: &QualifiedName) -> bool {
+        !self.not_copy_constructible.contains(ty)
+    }
+
+    pub(crate) fn is_move_constructible(&self, ty: &QualifiedName) -> bool {
+        !self.not_move_constructible.contains(ty)
+    }
+}
+
+/// Removes the probes from the `apis` found by bindgen, returning what
+/// they told us.
+pub(crate) fn take_trait_probe_results(
+    config: &IncludeCppConfig,
+    apis: ApiVec<NullPhase>,
+) -> (ApiVec<NullPhase>, TraitProbeResults) {
+    let mut results = TraitProbeResults::default();
+    let mut remaining = ApiVec::new();
+    for api in apis.into_iter() {
+        match api {
+            Api::Const {
+                ref name,
+                ref const_item,
+            } if name.name.get_namespace().iter().eq([TRAIT_PROBE_NAMESPACE]) => {
+                let holds = matches!(
+                    &*const_item.expr,
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Bool(b),
+                        ..
+                    }) if b.value
+                );
+                if holds {
+                    continue;
+                }
+                let probe = name.name.get_final_item();
+                let failure = PROBED_TRAITS
+                    .iter()
+                    .zip([
+                        &mut results.not_copy_constructible,
+                        &mut results.not_move_constructible,
+                    ])
+                    .find_map(|((_, prefix), failures)| {
+                        let index = probe.strip_prefix(prefix)?.parse::<usize>().ok()?;
+                        Some((failures, config.trait_probe_types().get(index)?))
+                    });
+                if let Some((failures, ty)) = failure {
+                    failures.insert(QualifiedName::new_from_cpp_name(ty));
+                }
+            }
+            _ => remaining.push(api),
+        }
+    }
+    (remaining, results)
+}
@@ -11,6 +11,7 @@ use crate::{
         api::{AnalysisPhase, Api, ApiName, NullPhase, TypedefKind, UnanalyzedApi},
         apivec::ApiVec,
         codegen_cpp::type_to_cpp::CppNameMap,
+        type_helpers::extract_unique_ptr_vector_item,
         ConvertErrorFromCpp,
     },
     known_types::{known_types, CxxGenericType},
@@ -313,8 +314,35 @@ impl<'a> TypeConverter<'a> {
         let mut kind = TypeKind::Regular;
 
         // Finally let's see if it's generic.
+        let unique_ptr_vector_item = match extract_unique_ptr_vector_item(&typ) {
+            Some(Type::Path(item)) => Some(QualifiedName::from_type_path(item)),
+            _ => None,
+        };
         if let Some(last_seg) = Self::get_generic_args(&mut typ) {
-            let generic_behavior = known_types().cxx_generic_behavior(&tn);
+            let mut generic_behavior = known_types().cxx_generic_behavior(&tn);
+            if generic_behavior == CxxGenericType::CppPtr {
+                if Self::has_custom_deleter(last_seg) {
+                    // cxx's UniquePtr always uses the default deleter, so it
+                    // can't represent this. Treat it like any other template
+                    // which cxx doesn't understand: the resulting concrete
+                    // type keeps the std::unique_ptr intact on the C++ side,
+                    // so its own deleter runs when it's destroyed. Like other
+                    // such types, it's opaque to Rust.
+                    generic_behavior = CxxGenericType::Not;
+                } else {
+                    Self::strip_default_deleter(last_seg);
+                }
+            }
+            if generic_behavior == CxxGenericType::CppVector {
+                if let Some(item) = unique_ptr_vector_item {
+                    // cxx's CxxVector can't hold UniquePtrs, so this too becomes
+                    // a concrete type. Codegen gives it accessors which move
+                    // each element out of the vector, so it needs the element
+                    // type too.
+                    deps.insert(item);
+                    generic_behavior = CxxGenericType::Not;
+                }
+            }
             let forward_declarations_ok = generic_behavior == CxxGenericType::Rust;
             if generic_behavior != CxxGenericType::Not {
                 // this is a type of generic understood by cxx (e.g. CxxVector)
@@ -380,6 +408,38 @@ impl<'a> TypeConverter<'a> {
         Ok(Annotated::new(Type::Path(typ), deps, extra_apis, kind))
     }
 
+    /// Whether this is a `std::unique_ptr<T, D>` with some deleter `D`
+    /// other than `std::default_delete<T>`.
+    fn has_custom_deleter(last_seg: &PathSegment) -> bool {
+        match &last_seg.arguments {
+            // Allow for inline namespaces such as std::__1.
+            PathArguments::AngleBracketed(ab) => match ab.args.iter().nth(1) {
+                Some(GenericArgument::Type(Type::Path(deleter))) => {
+                    !Self::is_std_default_delete(&QualifiedName::from_type_path(deleter))
+                }
+                Some(_) => true,
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Removes an explicit `std::default_delete<T>` from a
+    /// `std::unique_ptr<T, std::default_delete<T>>`, since cxx can cope
+    /// perfectly well with that.
+    fn strip_default_deleter(last_seg: &mut PathSegment) {
+        if let PathArguments::AngleBracketed(ref mut ab) = last_seg.arguments {
+            if ab.args.len() > 1 {
+                ab.args = ab.args.iter().take(1).cloned().collect();
+            }
+        }
+    }
+
+    fn is_std_default_delete(qn: &QualifiedName) -> bool {
+        qn.get_final_item() == "default_delete"
+            && qn.get_namespace().iter().next().map(String::as_str) == Some("std")
+    }
+
     fn get_generic_args(typ: &mut TypePath) -> Option<&mut PathSegment> {
         match typ.path.segments.last_mut() {
             Some(s) if !s.arguments.is_empty() => Some(s),
@@ -737,6 +797,9 @@ pub(crate) fn find_types<A: AnalysisPhase>(apis: &ApiVec<A>) -> HashSet<Qualifie
             | Api::ExternCppType { .. }
             | Api::RustType { .. } => Some(api.name()),
             Api::StringConstructor { .. }
+            | Api::WStringConstructor { .. }
+            | Api::ContainerIterator { .. }
+            | Api::ConstantAccessor { .. }
             | Api::Function { .. }
             | Api::Const { .. }
             | Api::CType { .. }
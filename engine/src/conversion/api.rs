@@ -11,6 +11,7 @@ use std::fmt::Display;
 
 use syn::{
     parse::Parse,
+    parse_quote,
     punctuated::Punctuated,
     token::{Comma, Unsafe},
 };
@@ -155,6 +156,11 @@ impl References {
     pub(crate) fn new_with_this_and_return_as_reference() -> Self {
         Self {
             ref_return: true,
+            ..Self::new_with_this_as_reference()
+        }
+    }
+    pub(crate) fn new_with_this_as_reference() -> Self {
+        Self {
             ref_params: [make_ident("this")].into_iter().collect(),
             ..Default::default()
         }
@@ -248,6 +254,10 @@ pub(crate) enum Provenance {
     Bindgen,
     SynthesizedOther,
     SynthesizedSubclassConstructor(Box<SubclassConstructorDetails>),
+    /// A function from bindgen, minus some trailing parameters for which
+    /// C++ should supply the default arguments. Records how many were
+    /// omitted.
+    SynthesizedWithDefaultArgs(usize),
 }
 
 /// Whether a function has =delete or =default
@@ -299,6 +309,40 @@ pub(crate) struct FuncToConvert {
     pub(crate) is_deleted: DeletedOrDefaulted,
 }
 
+impl FuncToConvert {
+    /// A public function which doesn't exist in the C++, and which we
+    /// synthesize from the given body. Anything else, such as its
+    /// `self_ty`, `references` or `doc_attrs`, can be filled in using
+    /// struct update syntax.
+    pub(crate) fn new_synthesized(
+        ident: Ident,
+        inputs: Punctuated<FnArg, Comma>,
+        output: ReturnType,
+        body: CppFunctionBody,
+    ) -> Self {
+        Self {
+            provenance: Provenance::SynthesizedOther,
+            ident,
+            doc_attrs: Vec::new(),
+            inputs,
+            variadic: false,
+            output,
+            vis: parse_quote! { pub },
+            virtualness: Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: None,
+            self_ty: None,
+            synthesized_this_type: None,
+            add_to_trait: None,
+            synthetic_cpp: Some((body, CppFunctionKind::Function)),
+            is_deleted: DeletedOrDefaulted::Neither,
+        }
+    }
+}
+
 /// Layers of analysis which may be applied to decorate each API.
 /// See description of the purpose of this trait within `Api`.
 pub(crate) trait AnalysisPhase: std::fmt::Debug {
@@ -417,6 +461,17 @@ impl SubclassName {
     pub(crate) fn remove_ownership(&self) -> Ident {
         self.with_suffix("_remove_ownership")
     }
+    /// The Rust struct, as seen from the root bindgen mod. Normally the user
+    /// declares it alongside `include_cpp!`, but for `dyn_subclass!` we
+    /// generate it ourselves, within that root mod.
+    pub(crate) fn rust_type_path(&self, is_dyn: bool) -> syn::TypePath {
+        let id = self.id();
+        if is_dyn {
+            parse_quote! { #id }
+        } else {
+            parse_quote! { super::super::super::#id }
+        }
+    }
     fn with_suffix(&self, suffix: &str) -> Ident {
         make_ident(format!("{}{}", self.0.name.get_final_item(), suffix))
     }
@@ -494,6 +549,27 @@ pub(crate) enum Api<T: AnalysisPhase> {
     /// A simple note that we want to make a constructor for
     /// a `std::string` on the heap.
     StringConstructor { name: ApiName },
+    /// The same for a `std::wstring`, transcoded from UTF-8.
+    WStringConstructor { name: ApiName },
+    /// A Rust iterator over a C++ container, driven by the container's
+    /// `begin()` and `end()` iterators. Unless `mutable`, these are the
+    /// `const` overloads, and the iterator yields shared references.
+    ContainerIterator {
+        name: ApiName,
+        container: QualifiedName,
+        item: QualifiedName,
+        mutable: bool,
+    },
+    /// A C++ global constant of enum type. bindgen can only give us
+    /// these as static data, so we read them through a C++ accessor
+    /// which returns the enum's underlying integer type, then check it's
+    /// one of the enum's variants before turning it back into the Rust enum.
+    ConstantAccessor {
+        name: ApiName,
+        enum_type: QualifiedName,
+        underlying_type: crate::minisyn::Ident,
+        variants: Vec<crate::minisyn::Ident>,
+    },
     /// A function. May include some analysis.
     Function {
         name: ApiName,
@@ -598,6 +674,9 @@ impl<T: AnalysisPhase> Api<T> {
             Api::OpaqueTypedef { name, .. } => name,
             Api::ConcreteType { name, .. } => name,
             Api::StringConstructor { name } => name,
+            Api::WStringConstructor { name } => name,
+            Api::ContainerIterator { name, .. } => name,
+            Api::ConstantAccessor { name, .. } => name,
             Api::Function { name, .. } => name,
             Api::Const { name, .. } => name,
             Api::Typedef { name, .. } => name,
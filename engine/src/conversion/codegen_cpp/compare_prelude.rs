@@ -0,0 +1,45 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Support for `ord!` and `partial_ord!`. These compare two objects using
+/// their `operator<=>` if the compiler supports that and the type has
+/// one, or else `operator<` (and for `partial_ord!`, `operator==`). Either
+/// way, the result is -1, 0 or 1 as the first is less than, equivalent
+/// to or greater than the second, or 2 if they're unordered.
+pub(super) static COMPARE_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_COMPARE_PRELUDE
+    #define AUTOCXX_COMPARE_PRELUDE
+    // Mechanics to compare objects for ord! and partial_ord!
+    template <typename T, typename = void> struct autocxx_has_spaceship : std::false_type {};
+    #if defined(__cpp_impl_three_way_comparison)
+    template <typename T> struct autocxx_has_spaceship<T, decltype(void(std::declval<const T&>() <=> std::declval<const T&>()))> : std::true_type {};
+    template <typename T> int autocxx_compare(const T& a, const T& b, std::true_type) {
+      auto result = a <=> b;
+      return (result < 0) ? -1 : ((result > 0) ? 1 : 0);
+    }
+    template <typename T> int autocxx_partial_compare(const T& a, const T& b, std::true_type) {
+      auto result = a <=> b;
+      return (result < 0) ? -1 : ((result > 0) ? 1 : ((result == 0) ? 0 : 2));
+    }
+    #endif
+    template <typename T> int autocxx_compare(const T& a, const T& b, std::false_type) {
+      return (a < b) ? -1 : ((b < a) ? 1 : 0);
+    }
+    template <typename T> int autocxx_partial_compare(const T& a, const T& b, std::false_type) {
+      return (a < b) ? -1 : ((b < a) ? 1 : ((a == b) ? 0 : 2));
+    }
+    template <typename T> int autocxx_compare(const T& a, const T& b) {
+      return autocxx_compare(a, b, autocxx_has_spaceship<T>());
+    }
+    template <typename T> int autocxx_partial_compare(const T& a, const T& b) {
+      return autocxx_partial_compare(a, b, autocxx_has_spaceship<T>());
+    }
+    #endif // AUTOCXX_COMPARE_PRELUDE
+"};
@@ -6,11 +6,15 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use syn::{Type, TypePtr};
+use itertools::Itertools;
+use syn::{ReturnType, Type, TypeBareFn, TypePtr, TypeReference, TypeSlice};
 
 use crate::conversion::{
-    analysis::fun::function_wrapper::{CppConversionType, TypeConversionPolicy},
+    analysis::fun::function_wrapper::{
+        CallbackKind, CppConversionType, SliceBound, TypeConversionPolicy,
+    },
     api::Pointerness,
+    type_helpers::{extract_shared_ptr_type, extract_unique_ptr_type},
     ConvertErrorFromCpp,
 };
 
@@ -26,6 +30,63 @@ impl TypeConversionPolicy {
             CppConversionType::FromPtrToValue => {
                 Ok(format!("{}*", self.unwrapped_type_as_string(cpp_name_map)?))
             }
+            CppConversionType::FromOptionalBoolToI8 => Ok("std::optional<bool>".into()),
+            CppConversionType::FromOptionalToValue => Ok(format!(
+                "std::optional<{}>",
+                self.unwrapped_type_as_string(cpp_name_map)?
+            )),
+            CppConversionType::FromOptionalVectorToUniquePtr => Ok(format!(
+                "std::optional<{}>",
+                cpp_name_map.type_to_cpp(
+                    extract_unique_ptr_type(self.cxxbridge_type()).expect("Not a unique_ptr")
+                )?
+            )),
+            CppConversionType::FromOptionalReferenceWrapperToPtr => match self.cxxbridge_type() {
+                Type::Ptr(TypePtr { elem, .. }) => Ok(format!(
+                    "std::optional<std::reference_wrapper<const {}>>",
+                    cpp_name_map.type_to_cpp(elem)?
+                )),
+                _ => panic!("Not a pointer"),
+            },
+            CppConversionType::FromFnToCallback(_) => {
+                let (output, inputs) = self.callback_signature(cpp_name_map)?;
+                Ok(format!(
+                    "rust::Fn<{output}(std::size_t{}{})>",
+                    inputs.iter().map(|ty| format!(", {ty}")).join(""),
+                    if self.is_fallible_callback_conversion() {
+                        ", bool&, rust::String&"
+                    } else {
+                        ""
+                    }
+                ))
+            }
+            CppConversionType::FromWStringToString => Ok("std::wstring".into()),
+            CppConversionType::FromU16StringToVec => Ok("std::u16string".into()),
+            CppConversionType::FromSliceToU16String => Ok("rust::Slice<const uint16_t>".into()),
+            CppConversionType::FromStringToCharPointer => Ok("const std::string&".into()),
+            CppConversionType::FromCharPointerToString => Ok("const char*".into()),
+            CppConversionType::FromDurationToNanosecondsCount => {
+                Ok("std::chrono::nanoseconds".into())
+            }
+            CppConversionType::FromDurationToSecondsCount => {
+                Ok("std::chrono::duration<double>".into())
+            }
+            CppConversionType::FromStringViewToStr | CppConversionType::FromStringViewToString => {
+                Ok("std::string_view".into())
+            }
+            CppConversionType::FromSliceToByteArray(_)
+            | CppConversionType::FromSliceToVoidPtrAndSize => Ok(format!(
+                "rust::Slice<{}uint8_t>",
+                self.byte_array_const_string()
+            )),
+            CppConversionType::FromStrSliceToOptionalStringView => {
+                Ok("rust::Slice<const rust::Str>".into())
+            }
+            CppConversionType::FromSliceToSpan => Ok(format!(
+                "rust::Slice<{}{}>",
+                self.byte_array_const_string(),
+                self.slice_element_type(cpp_name_map)?
+            )),
             _ => self.unwrapped_type_as_string(cpp_name_map),
         }
     }
@@ -35,7 +96,10 @@ impl TypeConversionPolicy {
         cpp_name_map: &CppNameMap,
     ) -> Result<String, ConvertErrorFromCpp> {
         match self.cpp_conversion {
-            CppConversionType::FromValueToUniquePtr => self.unique_ptr_wrapped_type(cpp_name_map),
+            CppConversionType::FromValueToUniquePtr
+            | CppConversionType::FromCharPointerToString => {
+                self.unique_ptr_wrapped_type(cpp_name_map)
+            }
             CppConversionType::FromReferenceToPointer => {
                 let (const_string, ty) = match self.cxxbridge_type() {
                     Type::Ptr(TypePtr {
@@ -52,10 +116,237 @@ impl TypeConversionPolicy {
                     cpp_name_map.type_to_cpp(ty)?
                 ))
             }
+            CppConversionType::FromConstRefToConstPtr => match self.cxxbridge_type() {
+                Type::Reference(TypeReference { elem, .. }) => {
+                    Ok(format!("const {}*", cpp_name_map.type_to_cpp(elem)?))
+                }
+                _ => panic!("Not a reference"),
+            },
+            CppConversionType::FromSliceToByteArray(len) => Ok(format!(
+                "std::add_lvalue_reference_t<{}uint8_t[{len}]>",
+                self.byte_array_const_string()
+            )),
+            CppConversionType::FromU16StringToVec => Ok("rust::Vec<uint16_t>".into()),
+            CppConversionType::FromSliceToU16String => Ok("std::u16string".into()),
             _ => self.unwrapped_type_as_string(cpp_name_map),
         }
     }
 
+    /// The argument for a `std::optional<T>&` out parameter. `value_var`
+    /// points to storage for the `T`, and `has_value_var` is the flag saying
+    /// whether it's been filled in.
+    pub(super) fn optional_out_param_conversion(
+        &self,
+        value_var: &str,
+        has_value_var: &str,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        let ty = match self.cxxbridge_type() {
+            Type::Ptr(TypePtr { elem, .. }) => cpp_name_map.type_to_cpp(elem)?,
+            _ => panic!("Not a pointer"),
+        };
+        Ok(format!(
+            "autocxx_optional_out<{ty}>({value_var}, {has_value_var}).get()"
+        ))
+    }
+
+    /// The return value for a `std::optional<T>`, which is `T`'s
+    /// value-initialized state if empty. `has_value_var` is the flag saying
+    /// whether there was a value.
+    pub(super) fn optional_return_conversion(
+        &self,
+        var_name: &str,
+        has_value_var: &str,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        let ty = self.unwrapped_type_as_string(cpp_name_map)?;
+        Ok(format!(
+            "[](const std::optional<{ty}>& v, bool& has_value) -> {ty} {{ has_value = v.has_value(); if (has_value) {{ return *v; }} return {{}}; }}({var_name}, {has_value_var})"
+        ))
+    }
+
+    /// The return value for a `std::variant` from a `result_variant!`
+    /// function. Any error is moved into `error_var`, and otherwise the
+    /// value is moved into a new heap allocation. A variant which is
+    /// valueless by exception throws `std::bad_variant_access`.
+    pub(super) fn result_variant_return_conversion(
+        &self,
+        var_name: &str,
+        error_var: &str,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        let error_index = match self.cpp_conversion {
+            CppConversionType::FromResultVariantToUniquePtr(error_index) => error_index,
+            _ => panic!("Not a result variant"),
+        };
+        let value_index = 1 - error_index;
+        let ty = self.unwrapped_type_as_string(cpp_name_map)?;
+        Ok(format!(
+            "[](auto v, auto& error) -> {ty} {{ if (v.index() == {error_index}) {{ error = std::make_unique<std::variant_alternative_t<{error_index}, decltype(v)>>(std::get<{error_index}>(std::move(v))); return nullptr; }} return std::make_unique<std::variant_alternative_t<{value_index}, decltype(v)>>(std::get<{value_index}>(std::move(v))); }}({var_name}, {error_var})"
+        ))
+    }
+
+    /// The call to a `tuple_array!` function, copying each element of the
+    /// returned `std::tuple` into the array at `array_var`.
+    pub(super) fn tuple_array_return_conversion(&self, var_name: &str, array_var: &str) -> String {
+        format!(
+            "[](const auto& t, auto* out) {{ std::apply([out](const auto&... v) {{ std::size_t i = 0; ((out[i++] = v), ...); }}, t); }}({var_name}, {array_var})"
+        )
+    }
+
+    /// The call to a `pair_tuple!` function, moving each element of the
+    /// returned `std::pair` into its out parameter. `elements` are the
+    /// conversion for each out parameter and its name. If the pair is in
+    /// a `std::optional`, `has_value_var` is set to whether it had a value,
+    /// and only then are the elements filled in.
+    pub(super) fn pair_tuple_return_conversion(
+        &self,
+        var_name: &str,
+        elements: &[(&TypeConversionPolicy, String)],
+        has_value_var: Option<&str>,
+    ) -> String {
+        let params = elements
+            .iter()
+            .map(|(_, var)| format!(", auto* {var}"))
+            .join("");
+        let moves = elements
+            .iter()
+            .map(|(conv, var)| format!("{} ", conv.pair_element_move(var)))
+            .join("");
+        // Built-in elements and strings go into the uninitialized storage to
+        // which the parameter points, whereas a `std::unique_ptr` is moved
+        // into the null one which Rust made.
+        let args = elements
+            .iter()
+            .map(|(conv, var)| match conv.cxxbridge_type() {
+                Type::Reference(_) => format!(", &{var}"),
+                _ => format!(", {var}"),
+            })
+            .join("");
+        match has_value_var {
+            None => format!("[](auto&& p{params}) {{ {moves}}}({var_name}{args})"),
+            Some(has_value) => format!(
+                "[](auto&& o, bool& has_value{params}) {{ has_value = o.has_value(); if (has_value) {{ auto& p = *o; {moves}}} }}({var_name}, {has_value}{args})"
+            ),
+        }
+    }
+
+    /// The statement which moves the element of the pair `p` returned by
+    /// a `pair_tuple!` function into its out parameter `var`. A string is
+    /// converted straight into the `rust::String` which Rust will own, so
+    /// its characters are copied just once, replacing any invalid UTF-8.
+    fn pair_element_move(&self, var: &str) -> String {
+        match &self.cpp_conversion {
+            CppConversionType::IgnoredPairElement(member) => {
+                format!("*{var} = std::move(p.{member});")
+            }
+            CppConversionType::IgnoredPairStringElement(member) => {
+                format!("new ({var}) rust::String(rust::String::lossy(p.{member}));")
+            }
+            _ => panic!("Not a pair element"),
+        }
+    }
+
+    /// The argument for a callback parameter: a lambda which passes
+    /// `context_var` back to the Rust function `fn_var` on each call,
+    /// followed by the callback's own arguments.
+    pub(super) fn callback_conversion(
+        &self,
+        fn_var: &str,
+        context_var: &str,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        let (output, inputs) = self.callback_signature(cpp_name_map)?;
+        let params = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| format!("{ty} arg{i}"))
+            .join(", ");
+        let args = (0..inputs.len()).map(|i| format!(", arg{i}")).join("");
+        let (params, output, body) = match self.cpp_conversion {
+            // The Rust function returns the address of the memory. C++
+            // callbacks which aren't told the alignment get memory aligned
+            // as `operator new` would align it.
+            CppConversionType::FromFnToCallback(CallbackKind::Allocator) => (
+                "std::size_t arg0, std::size_t arg1 = alignof(std::max_align_t)".into(),
+                "void*".into(),
+                format!("return reinterpret_cast<void*>({fn_var}({context_var}{args}));"),
+            ),
+            // If the closure is fallible, the Rust function also tells us
+            // whether it failed and why, and we throw.
+            CppConversionType::FromFnToCallback(CallbackKind::Closure { fallible: true }) => {
+                let call = format!("{fn_var}({context_var}{args}, failed, message)");
+                let (call, result) = if output == "void" {
+                    (format!("{call};"), "")
+                } else {
+                    (format!("auto result = {call};"), " return result;")
+                };
+                let body = format!(
+                    "bool failed = false; rust::String message; {call} \
+                    if (failed) {{ throw std::runtime_error(std::string(message)); }}{result}"
+                );
+                (params, output, body)
+            }
+            _ => (
+                params,
+                output,
+                format!("return {fn_var}({context_var}{args});"),
+            ),
+        };
+        Ok(format!(
+            "[{fn_var}, {context_var}]({params}) -> {output} {{ {body} }}"
+        ))
+    }
+
+    /// The C++ return type and parameter types of the Rust function pointer
+    /// for a callback, leaving out the context and any error reporting.
+    fn callback_signature(
+        &self,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<(String, Vec<String>), ConvertErrorFromCpp> {
+        let error_params = if self.is_fallible_callback_conversion() {
+            2
+        } else {
+            0
+        };
+        match self.cxxbridge_type() {
+            Type::BareFn(TypeBareFn { inputs, output, .. }) => Ok((
+                match output {
+                    ReturnType::Default => "void".to_string(),
+                    ReturnType::Type(_, ty) => cpp_name_map.type_to_cpp(ty)?,
+                },
+                inputs
+                    .iter()
+                    .skip(1)
+                    .take(inputs.len() - 1 - error_params)
+                    .map(|arg| cpp_name_map.type_to_cpp(&arg.ty))
+                    .collect::<Result<_, _>>()?,
+            )),
+            _ => panic!("Not a function pointer"),
+        }
+    }
+
+    fn byte_array_const_string(&self) -> &'static str {
+        match self.cxxbridge_type() {
+            Type::Reference(TypeReference {
+                mutability: Some(_),
+                ..
+            }) => "",
+            _ => "const ",
+        }
+    }
+
+    /// The C++ name of the elements of a slice received by the wrapper.
+    fn slice_element_type(&self, cpp_name_map: &CppNameMap) -> Result<String, ConvertErrorFromCpp> {
+        match self.cxxbridge_type() {
+            Type::Reference(TypeReference { elem, .. }) => match elem.as_ref() {
+                Type::Slice(TypeSlice { elem, .. }) => cpp_name_map.type_to_cpp(elem),
+                _ => panic!("Not a slice"),
+            },
+            _ => panic!("Not a reference"),
+        }
+    }
+
     fn unwrapped_type_as_string(
         &self,
         cpp_name_map: &CppNameMap,
@@ -114,8 +405,144 @@ impl TypeConversionPolicy {
                     format!("std::move({dereference})")
                 })
             }
-            CppConversionType::IgnoredPlacementPtrParameter => None,
-            CppConversionType::FromReferenceToPointer { .. } => Some(format!("&{var_name}")),
+            CppConversionType::IgnoredPlacementPtrParameter
+            | CppConversionType::IgnoredOptionalOutParamFlag
+            | CppConversionType::IgnoredOptionalReturnFlag
+            | CppConversionType::IgnoredCallbackContext
+            | CppConversionType::IgnoredResultVariantError
+            | CppConversionType::IgnoredTupleArray
+            | CppConversionType::IgnoredPairElement(_)
+            | CppConversionType::IgnoredPairStringElement(_) => None,
+            CppConversionType::FromPtrToOptionalOutParam => {
+                panic!("Use optional_out_param_conversion, which also needs the flag")
+            }
+            CppConversionType::FromFnToCallback(_) => {
+                panic!("Use callback_conversion, which also needs the context")
+            }
+            CppConversionType::FromOptionalToValue => {
+                panic!("Use optional_return_conversion, which also needs the flag")
+            }
+            CppConversionType::FromResultVariantToUniquePtr(_) => {
+                panic!("Use result_variant_return_conversion, which also needs the error")
+            }
+            CppConversionType::FromTupleToArray => {
+                panic!("Use tuple_array_return_conversion, which also needs the array")
+            }
+            CppConversionType::FromPairToElements => {
+                panic!("Use pair_tuple_return_conversion, which also needs the elements")
+            }
+            CppConversionType::FromReferenceToPointer { .. }
+            | CppConversionType::FromConstRefToConstPtr => Some(format!("&{var_name}")),
+            CppConversionType::FromOptionalBoolToI8 => Some(format!(
+                "[](const std::optional<bool>& v) -> int8_t {{ return v ? (*v ? 1 : 0) : -1; }}({var_name})"
+            )),
+            CppConversionType::FromOptionalReferenceWrapperToPtr => Some(format!(
+                "[](const auto& v) -> {} {{ return v ? &v->get() : nullptr; }}({var_name})",
+                self.unwrapped_type_as_string(cpp_name_map)?
+            )),
+            CppConversionType::FromOptionalVectorToUniquePtr => Some(format!(
+                "[](auto v) -> {} {{ if (!v) {{ return nullptr; }} return std::make_unique<typename decltype(v)::value_type>(std::move(*v)); }}({var_name})",
+                self.unwrapped_type_as_string(cpp_name_map)?
+            )),
+            CppConversionType::FromSharedPtrDroppingConst => {
+                let pointee = extract_shared_ptr_type(self.cxxbridge_type())
+                    .expect("Not a shared_ptr");
+                Some(format!(
+                    "std::const_pointer_cast<{}>({})",
+                    cpp_name_map.type_to_cpp(pointee)?,
+                    var_name
+                ))
+            }
+            CppConversionType::FromNullVectorToEmpty => Some(format!(
+                "[](auto v) {{ if (!v) {{ v = std::make_unique<typename decltype(v)::element_type>(); }} return v; }}({var_name})"
+            )),
+            CppConversionType::FromWStringToString => {
+                Some(format!("rust::String(autocxx_wstring_to_utf8({var_name}))"))
+            }
+            CppConversionType::FromU16StringToVec => Some(format!(
+                "[](const std::u16string& s) {{ rust::Vec<uint16_t> v; v.reserve(s.size()); for (char16_t c : s) {{ v.push_back(static_cast<uint16_t>(c)); }} return v; }}({var_name})"
+            )),
+            CppConversionType::FromIntegerToEnum(ref enum_ty) => Some(format!(
+                "static_cast<{}>({var_name})",
+                cpp_name_map.type_to_cpp(enum_ty)?
+            )),
+            CppConversionType::FromStringToCharPointer => Some(format!("({var_name}).c_str()")),
+            CppConversionType::FromPtrToIntrusivePtr(ref pointer) => match self.cxxbridge_type() {
+                Type::Ptr(TypePtr { elem, .. }) => Some(format!(
+                    "{pointer}<{}>({var_name}, false)",
+                    cpp_name_map.type_to_cpp(elem)?
+                )),
+                _ => panic!("Not a pointer"),
+            },
+            CppConversionType::FromIntrusivePtrToPtr => Some(format!("({var_name}).detach()")),
+            CppConversionType::FromCharPointerToString => Some(format!(
+                "[](const char* s) {{ return std::make_unique<std::string>(s ? s : \"\"); }}({var_name})"
+            )),
+            CppConversionType::FromBitmaskToInteger(ref integer_ty) => Some(format!(
+                "static_cast<{}>({var_name})",
+                cpp_name_map.type_to_cpp(integer_ty)?
+            )),
+            CppConversionType::FromDurationToNanosecondsCount => Some(format!(
+                "std::chrono::duration_cast<std::chrono::nanoseconds>({var_name}).count()"
+            )),
+            // Counting seconds in a `double` keeps any fraction of a second,
+            // whatever the duration's own representation and period.
+            CppConversionType::FromDurationToSecondsCount => Some(format!(
+                "std::chrono::duration_cast<std::chrono::duration<double>>({var_name}).count()"
+            )),
+            CppConversionType::FromStrToWString => Some(format!(
+                "autocxx_utf8_to_wstring({var_name}.data(), {var_name}.size())"
+            )),
+            CppConversionType::FromSliceToU16String => Some(format!(
+                "std::u16string(reinterpret_cast<const char16_t*>({var_name}.data()), {var_name}.size())"
+            )),
+            CppConversionType::FromStringViewToStr => Some(format!(
+                "[](std::string_view v) {{ return rust::Slice<const uint8_t>(reinterpret_cast<const uint8_t*>(v.data()), v.size()); }}({var_name})"
+            )),
+            CppConversionType::FromStringViewToString => Some(format!(
+                "[](std::string_view v) {{ return rust::String::lossy(v.data(), v.size()); }}({var_name})"
+            )),
+            CppConversionType::FromStrToStringView => {
+                Some(format!("autocxx_make_string_view({var_name})"))
+            }
+            CppConversionType::FromStrToCharPointer => {
+                Some(format!("std::string({var_name}).c_str()"))
+            }
+            CppConversionType::FromStrSliceToOptionalStringView => Some(format!(
+                "[](rust::Slice<const rust::Str> v) -> std::optional<std::string_view> {{ if (v.empty()) {{ return std::nullopt; }} return autocxx_make_string_view(v[0]); }}({var_name})"
+            )),
+            // The Rust array is used in place, so its bytes reach C++
+            // exactly as they were, in the same order.
+            CppConversionType::FromSliceToByteArray(len) => Some(format!(
+                "*reinterpret_cast<{}uint8_t(*)[{len}]>({var_name}.data())",
+                self.byte_array_const_string()
+            )),
+            // Both the pointer and the length come from the slice, so they
+            // can't disagree. An empty Rust slice has a dangling pointer,
+            // which C++ gets as null instead. Converting to `void*` needs no
+            // cast at all, so nothing is reinterpreted. Otherwise the pointer
+            // is already of the right type, and as Rust guarantees, aligned.
+            CppConversionType::FromSliceToVoidPtrAndSize
+            | CppConversionType::FromSliceToPtr(SliceBound::Size) => Some(format!(
+                "({var_name}.empty() ? nullptr : {var_name}.data()), {var_name}.size()"
+            )),
+            // The end pointer is one past the last element, which is fine
+            // even for an empty slice: neither is ever dereferenced.
+            CppConversionType::FromSliceToPtr(SliceBound::EndPtr) => Some(format!(
+                "{var_name}.data(), {var_name}.data() + {var_name}.size()"
+            )),
+            CppConversionType::FromSliceToSpan => Some(format!(
+                "autocxx_dynamic_span<{}{}>({var_name}.data(), {var_name}.size())",
+                self.byte_array_const_string(),
+                self.slice_element_type(cpp_name_map)?
+            )),
+            // If the callback is called more than once, the last string
+            // wins. If it's never called, Rust gets a null pointer. The
+            // capture refers to the Rust caller's `UniquePtr`, which is why
+            // the function is unsafe.
+            CppConversionType::FromUniquePtrToStringCallback => Some(format!(
+                "[&{var_name}](const auto& value) {{ {var_name} = std::make_unique<std::string>(value); }}"
+            )),
         })
     }
 }
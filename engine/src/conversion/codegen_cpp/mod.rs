@@ -6,9 +6,14 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod compare_prelude;
 mod function_wrapper_cpp;
 mod new_and_delete_prelude;
+mod optional_out_prelude;
+mod span_prelude;
+mod string_view_prelude;
 pub(crate) mod type_to_cpp;
+mod wstring_prelude;
 
 use crate::{
     conversion::analysis::fun::{function_wrapper::CppFunctionKind, FnAnalysis},
@@ -20,11 +25,14 @@ use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 use itertools::Itertools;
 use std::borrow::Cow;
+use std::collections::BTreeSet;
+use syn::{parse_quote, Type};
 use type_to_cpp::CppNameMap;
 
 use super::{
     analysis::{
         fun::{
+            find_map_helpers,
             function_wrapper::{CppFunction, CppFunctionBody},
             FnPhase, PodAndDepAnalysis,
         },
@@ -32,15 +40,28 @@ use super::{
     },
     api::{Api, Provenance, SubclassName, TypeKind},
     apivec::ApiVec,
+    type_helpers::{
+        extract_map_key_and_value, extract_string_map_value, extract_unique_ptr_vector_item,
+        extract_unordered_map_key_and_value, is_std_string, map_snapshot_value_type,
+    },
     ConvertErrorFromCpp,
 };
 
+/// A header needed by some of our generated C++. The order of the
+/// variants is the order in which they're `#include`d: first system
+/// headers, in alphabetical order, then `cxx.h`, then our own cxxgen header,
+/// then the preludes, which may themselves rely on any of those.
 #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
 enum Header {
     System(&'static str),
     CxxH,
     CxxgenH,
     NewDeletePrelude,
+    WStringPrelude,
+    OptionalOutPrelude,
+    ComparePrelude,
+    StringViewPrelude,
+    SpanPrelude,
 }
 
 impl Header {
@@ -64,6 +85,11 @@ impl Header {
                 format!("#include \"{prefix}{cxxgen_header_name}\"")
             }
             Header::NewDeletePrelude => new_and_delete_prelude::NEW_AND_DELETE_PRELUDE.to_string(),
+            Header::WStringPrelude => wstring_prelude::WSTRING_PRELUDE.to_string(),
+            Header::OptionalOutPrelude => optional_out_prelude::OPTIONAL_OUT_PRELUDE.to_string(),
+            Header::ComparePrelude => compare_prelude::COMPARE_PRELUDE.to_string(),
+            Header::StringViewPrelude => string_view_prelude::STRING_VIEW_PRELUDE.to_string(),
+            Header::SpanPrelude => span_prelude::SPAN_PRELUDE.to_string(),
         }
     }
 
@@ -102,6 +128,7 @@ pub(crate) struct CppCodeGenerator<'a> {
     config: &'a IncludeCppConfig,
     cpp_codegen_options: &'a CppCodegenOptions<'a>,
     cxxgen_header_name: &'a str,
+    map_helpers: HashSet<String>,
 }
 
 struct SubclassFunction<'a> {
@@ -124,6 +151,7 @@ impl<'a> CppCodeGenerator<'a> {
             config,
             cpp_codegen_options,
             cxxgen_header_name,
+            map_helpers: find_map_helpers(apis),
         };
         // The 'filter' on the following line is designed to ensure we don't accidentally
         // end up out of sync with needs_cpp_codegen
@@ -142,6 +170,23 @@ impl<'a> CppCodeGenerator<'a> {
         for api in apis {
             match &api {
                 Api::StringConstructor { .. } => self.generate_string_constructor(),
+                Api::WStringConstructor { .. } => self.generate_wstring_constructor(),
+                Api::ContainerIterator {
+                    name,
+                    container,
+                    item,
+                    mutable,
+                } => self.generate_container_iterator(
+                    name.name.get_final_item(),
+                    container,
+                    item,
+                    *mutable,
+                ),
+                Api::ConstantAccessor {
+                    name,
+                    underlying_type,
+                    ..
+                } => self.generate_constant_accessor(&name.name, underlying_type)?,
                 Api::Function {
                     analysis:
                         FnAnalysis {
@@ -173,7 +218,32 @@ impl<'a> CppCodeGenerator<'a> {
                         None => Cow::Borrowed(cpp_definition),
                     };
 
-                    self.generate_typedef(api.name(), &effective_cpp_definition)
+                    self.generate_typedef(api.name(), &effective_cpp_definition);
+                    if let Some(item) = rs_definition.as_ref().and_then(|ty| match &***ty {
+                        Type::Path(tp) => extract_unique_ptr_vector_item(tp),
+                        _ => None,
+                    }) {
+                        self.generate_unique_ptr_vector_accessors(api.name(), item)?
+                    }
+                    if let Some(value) = rs_definition.as_ref().and_then(|ty| match &***ty {
+                        Type::Path(tp) => extract_string_map_value(tp),
+                        _ => None,
+                    }) {
+                        self.generate_map_snapshot(api.name(), value)?
+                    }
+                    if let Some((key, value)) = rs_definition.as_ref().and_then(|ty| match &***ty {
+                        Type::Path(tp) => extract_map_key_and_value(tp),
+                        _ => None,
+                    }) {
+                        self.generate_map_builder(api.name(), key, value, "map")?
+                    }
+                    if let Some((key, value)) = rs_definition.as_ref().and_then(|ty| match &***ty {
+                        Type::Path(tp) => extract_unordered_map_key_and_value(tp),
+                        _ => None,
+                    }) {
+                        self.generate_map_builder(api.name(), key, value, "unordered_map")?;
+                        self.generate_unordered_map_accessors(api.name(), key, value)?
+                    }
                 }
                 Api::CType { typename, .. } => self.generate_ctype_typedef(typename),
                 Api::Subclass { .. } => deferred_apis.push(api),
@@ -229,9 +299,18 @@ impl<'a> CppCodeGenerator<'a> {
             let cpp_headers = self.collect_headers(|additional_need| &additional_need.cpp_headers);
             let type_definitions = self.concat_additional_items(|x| x.type_definition.as_ref());
             let declarations = self.concat_additional_items(|x| x.declaration.as_ref());
+            // Snippets from `cpp_prelude!` go verbatim after the user's own
+            // #includes, so they can use anything those declare and all our
+            // glue can use them.
+            let preludes: String = self
+                .config
+                .cpp_preludes()
+                .iter()
+                .map(|prelude| format!("{prelude}\n"))
+                .collect();
             let declarations = format!(
-                "#ifndef __AUTOCXXGEN_H__\n#define __AUTOCXXGEN_H__\n\n{}\n{}\n{}\n{}#endif // __AUTOCXXGEN_H__\n",
-                headers, self.inclusions, type_definitions, declarations
+                "#ifndef __AUTOCXXGEN_H__\n#define __AUTOCXXGEN_H__\n\n{}\n{}\n{}{}\n{}#endif // __AUTOCXXGEN_H__\n",
+                headers, self.inclusions, preludes, type_definitions, declarations
             );
             log::info!("Additional C++ decls:\n{}", declarations);
             let header_name = self
@@ -263,18 +342,24 @@ impl<'a> CppCodeGenerator<'a> {
     where
         F: Fn(&ExtraCpp) -> &[Header],
     {
-        let cpp_headers: HashSet<_> = self
+        // Sorted, so that the same APIs always give exactly the same
+        // output, whatever order we came across them in.
+        let cpp_headers: BTreeSet<_> = self
             .additional_functions
             .iter()
             .flat_map(|x| filter(x).iter())
             .filter(|x| !self.cpp_codegen_options.suppress_system_headers || !x.is_system())
-            .collect(); // uniqify
+            .collect();
         cpp_headers
             .iter()
             .map(|x| x.include_stmt(self.cpp_codegen_options, self.cxxgen_header_name))
             .join("\n")
     }
 
+    /// Joins up one sort of snippet from all the [ExtraCpp]s. The same
+    /// snippet may have been asked for more than once, and emitting it
+    /// twice would define the same function twice, so only the first
+    /// of each is kept.
     fn concat_additional_items<F>(&self, field_access: F) -> String
     where
         F: FnMut(&ExtraCpp) -> Option<&String>,
@@ -283,6 +368,7 @@ impl<'a> CppCodeGenerator<'a> {
             .additional_functions
             .iter()
             .flat_map(field_access)
+            .unique()
             .join("\n");
         s.push('\n');
         s
@@ -318,15 +404,300 @@ impl<'a> CppCodeGenerator<'a> {
         })
     }
 
+    /// cxx knows nothing of `std::wstring`, so the bridge declares it as an
+    /// opaque type under an alias of our own.
+    fn generate_wstring_constructor(&mut self) {
+        let makewstring_name = self.config.get_makewstring_name();
+        let to_string_name = self.config.get_wstring_to_string_name();
+        let alias = self.config.get_wstring_alias_name();
+        let type_definition = Some(format!("using {alias} = std::wstring;"));
+        let declaration = Some(format!("inline std::unique_ptr<{alias}> {makewstring_name}(::rust::Str str) {{ return std::make_unique<{alias}>(autocxx_utf8_to_wstring(str.data(), str.size())); }}\ninline ::rust::String {to_string_name}(const {alias}& str) {{ return ::rust::String(autocxx_wstring_to_utf8(str)); }}"));
+        self.additional_functions.push(ExtraCpp {
+            type_definition,
+            declaration,
+            headers: vec![
+                Header::System("memory"),
+                Header::System("string"),
+                Header::CxxH,
+                Header::WStringPrelude,
+            ],
+            ..Default::default()
+        })
+    }
+
+    /// cxx's `CxxVector` can't hold `UniquePtr`s, so a `std::vector` of
+    /// `std::unique_ptr`s gets its own functions to reach the elements.
+    /// Taking an element moves it out, leaving a null `std::unique_ptr`
+    /// behind in the vector. Bounds are checked on the Rust side.
+    fn generate_unique_ptr_vector_accessors(
+        &mut self,
+        vector: &QualifiedName,
+        item: &Type,
+    ) -> Result<(), ConvertErrorFromCpp> {
+        let vector = vector.get_final_item();
+        let len = self
+            .config
+            .get_unique_ptr_vector_accessor_name(vector, "len");
+        let get = self
+            .config
+            .get_unique_ptr_vector_accessor_name(vector, "get");
+        let take = self
+            .config
+            .get_unique_ptr_vector_accessor_name(vector, "take");
+        let item = self.original_name_map.type_to_cpp(item)?;
+        let declaration = Some(format!(
+            "inline size_t {len}(const {vector}& v) {{ return v.size(); }}
+inline const {item}* {get}(const {vector}& v, size_t i) {{ return v[i].get(); }}
+inline std::unique_ptr<{item}> {take}({vector}& v, size_t i) {{ return std::move(v[i]); }}"
+        ));
+        self.additional_functions.push(ExtraCpp {
+            declaration,
+            headers: vec![
+                Header::System("cstddef"),
+                Header::System("memory"),
+                Header::System("utility"),
+            ],
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Generates a function which copies the contents of a
+    /// `std::map<std::string, T>` into a pair of Rust `Vec`s, if `T` is
+    /// simple enough that we can put it in a `Vec` and a function listed in
+    /// `map_snapshot!` returns the map.
+    fn generate_map_snapshot(
+        &mut self,
+        map: &QualifiedName,
+        value: &Type,
+    ) -> Result<(), ConvertErrorFromCpp> {
+        let map = map.get_final_item();
+        let snapshot = self.config.get_map_snapshot_name(map);
+        if map_snapshot_value_type(value).is_none() || !self.map_helpers.contains(&snapshot) {
+            return Ok(());
+        }
+        // Keys and string values needn't be UTF-8 in C++, so any invalid
+        // sequences are replaced.
+        let (value, copy_value) = if is_std_string(value) {
+            (
+                "rust::String".to_string(),
+                "rust::String::lossy(entry.second)",
+            )
+        } else {
+            (self.original_name_map.type_to_cpp(value)?, "entry.second")
+        };
+        let declaration = Some(format!(
+            "inline void {snapshot}(const {map}& m, rust::Vec<rust::String>& keys, rust::Vec<{value}>& values) {{
+  keys.reserve(m.size());
+  values.reserve(m.size());
+  for (const auto& entry : m) {{
+    keys.push_back(rust::String::lossy(entry.first));
+    values.push_back({copy_value});
+  }}
+}}"
+        ));
+        self.additional_functions.push(ExtraCpp {
+            declaration,
+            headers: vec![Header::CxxH],
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Generates functions to find out the size of a
+    /// `std::unordered_map<K, V>`, look up a key, and copy out the entries
+    /// of each bucket, if `K` and `V` are simple enough that Rust can hold
+    /// them by value. String keys to look up come from Rust as `rust::Str`s,
+    /// and string keys and values are copied out as `rust::String`s with
+    /// any invalid UTF-8 replaced.
+    fn generate_unordered_map_accessors(
+        &mut self,
+        map: &QualifiedName,
+        key: &Type,
+        value: &Type,
+    ) -> Result<(), ConvertErrorFromCpp> {
+        if map_snapshot_value_type(key).is_none() || map_snapshot_value_type(value).is_none() {
+            return Ok(());
+        }
+        let map = map.get_final_item();
+        let [len, get, bucket_count, bucket] = ["len", "get", "bucket_count", "bucket"]
+            .map(|accessor| self.config.get_unordered_map_accessor_name(map, accessor));
+        let element = |ty: &Type, var: &str| -> Result<_, ConvertErrorFromCpp> {
+            Ok(if is_std_string(ty) {
+                (
+                    "rust::String".to_string(),
+                    format!("rust::String::lossy({var})"),
+                )
+            } else {
+                (self.original_name_map.type_to_cpp(ty)?, var.to_string())
+            })
+        };
+        let (key_param, find_key) = if is_std_string(key) {
+            ("rust::Str key".to_string(), "std::string(key)")
+        } else {
+            (
+                format!("{} key", self.original_name_map.type_to_cpp(key)?),
+                "key",
+            )
+        };
+        let (key, copy_key) = element(key, "it->first")?;
+        let (value, copy_value) = element(value, "it->second")?;
+        let declaration = Some(format!(
+            "inline size_t {len}(const {map}& m) {{ return m.size(); }}
+inline bool {get}(const {map}& m, {key_param}, {value}& out) {{
+  auto it = m.find({find_key});
+  if (it == m.end()) {{
+    return false;
+  }}
+  out = {copy_value};
+  return true;
+}}
+inline size_t {bucket_count}(const {map}& m) {{ return m.bucket_count(); }}
+inline void {bucket}(const {map}& m, size_t n, rust::Vec<{key}>& keys, rust::Vec<{value}>& values) {{
+  for (auto it = m.begin(n); it != m.end(n); ++it) {{
+    keys.push_back({copy_key});
+    values.push_back({copy_value});
+  }}
+}}"
+        ));
+        self.additional_functions.push(ExtraCpp {
+            declaration,
+            headers: vec![
+                Header::System("cstddef"),
+                Header::System("string"),
+                Header::System("unordered_map"),
+                Header::CxxH,
+            ],
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Generates functions to make an empty `std::map<K, V>` or
+    /// `std::unordered_map<K, V>` and insert entries into it, if `K` and
+    /// `V` are simple enough that Rust can pass them by value and a
+    /// function listed in `map_from_iter!` takes the map. Strings come
+    /// from Rust as `rust::String`s. As ever with `insert`, an entry whose
+    /// key is already present is ignored, so the first value for any key
+    /// wins. `header` is the standard header declaring the map.
+    fn generate_map_builder(
+        &mut self,
+        map: &QualifiedName,
+        key: &Type,
+        value: &Type,
+        header: &'static str,
+    ) -> Result<(), ConvertErrorFromCpp> {
+        let map = map.get_final_item();
+        let new = self.config.get_map_builder_name(map, "new");
+        let insert = self.config.get_map_builder_name(map, "insert");
+        if map_snapshot_value_type(key).is_none()
+            || map_snapshot_value_type(value).is_none()
+            || !self.map_helpers.contains(&new)
+        {
+            return Ok(());
+        }
+        let element = |ty: &Type, var: &str| -> Result<_, ConvertErrorFromCpp> {
+            Ok(if is_std_string(ty) {
+                (format!("rust::String {var}"), format!("std::string({var})"))
+            } else {
+                (
+                    format!("{} {var}", self.original_name_map.type_to_cpp(ty)?),
+                    var.to_string(),
+                )
+            })
+        };
+        let (key_param, key) = element(key, "key")?;
+        let (value_param, value) = element(value, "value")?;
+        let declaration = Some(format!(
+            "inline std::unique_ptr<{map}> {new}() {{ return std::make_unique<{map}>(); }}
+inline void {insert}({map}& m, {key_param}, {value_param}) {{ m.insert({{{key}, {value}}}); }}"
+        ));
+        self.additional_functions.push(ExtraCpp {
+            declaration,
+            headers: vec![
+                Header::System("memory"),
+                Header::System(header),
+                Header::CxxH,
+            ],
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Generates a type holding a pair of iterators into a container,
+    /// plus functions to advance either end. Both ends stop once they
+    /// meet, so callers get `nullptr` rather than ever dereferencing
+    /// `end()` or stepping before `begin()`. Unless `mutable`, we use
+    /// the `const` overloads of `begin()` and `end()`.
+    fn generate_container_iterator(
+        &mut self,
+        iterator_name: &str,
+        container: &QualifiedName,
+        item: &QualifiedName,
+        mutable: bool,
+    ) {
+        let state = self.config.get_iterator_state_name(iterator_name);
+        let container = self.original_name_map.map(container);
+        let item = self.original_name_map.map(item);
+        let const_string = if mutable { "" } else { "const " };
+        let declaration = Some(format!(
+            "struct {state} {{
+    using iterator = decltype(std::declval<{const_string}{container}&>().begin());
+    iterator front;
+    iterator back;
+}};
+inline std::unique_ptr<{state}> {state}_new({const_string}{container}& container) {{ return std::make_unique<{state}>({state}{{container.begin(), container.end()}}); }}
+inline {const_string}{item}* {state}_next({state}& state) {{ if (state.front == state.back) {{ return nullptr; }} return &*(state.front++); }}
+inline {const_string}{item}* {state}_next_back({state}& state) {{ if (state.front == state.back) {{ return nullptr; }} return &*(--state.back); }}"
+        ));
+        self.additional_functions.push(ExtraCpp {
+            declaration,
+            headers: vec![Header::System("memory"), Header::System("utility")],
+            ..Default::default()
+        })
+    }
+
+    fn generate_constant_accessor(
+        &mut self,
+        name: &QualifiedName,
+        underlying_type: &crate::minisyn::Ident,
+    ) -> Result<(), ConvertErrorFromCpp> {
+        let accessor = self
+            .config
+            .get_constant_accessor_name(&name.to_cpp_name().replace("::", "_"));
+        let constant = self.original_name_map.map(name);
+        let underlying_type = self
+            .original_name_map
+            .type_to_cpp(&parse_quote! { #underlying_type })?;
+        let declaration = Some(format!(
+            "inline {underlying_type} {accessor}() {{ return static_cast<{underlying_type}>({constant}); }}"
+        ));
+        self.additional_functions.push(ExtraCpp {
+            declaration,
+            headers: vec![Header::System("cstdint")],
+            ..Default::default()
+        });
+        Ok(())
+    }
+
     fn generate_cpp_function(&mut self, details: &CppFunction) -> Result<(), ConvertErrorFromCpp> {
-        self.additional_functions
-            .push(self.generate_cpp_function_inner(
-                details,
-                false,
-                ConversionDirection::RustCallsCpp,
-                false,
-                None,
-            )?);
+        let mut extra_cpp = self.generate_cpp_function_inner(
+            details,
+            false,
+            ConversionDirection::RustCallsCpp,
+            false,
+            None,
+        )?;
+        if let Some(ns) = details.wrapper_namespace() {
+            let (opening, closing): (String, String) = ns
+                .iter()
+                .map(|segment| (format!("namespace {segment} {{ "), "} ".to_string()))
+                .unzip();
+            extra_cpp.declaration = extra_cpp
+                .declaration
+                .map(|declaration| format!("{opening}{declaration} {}", closing.trim_end()));
+        }
+        self.additional_functions.push(extra_cpp);
         Ok(())
     }
 
@@ -338,18 +709,19 @@ impl<'a> CppCodeGenerator<'a> {
         requires_rust_declarations: bool,
         force_name: Option<&str>,
     ) -> Result<ExtraCpp, ConvertErrorFromCpp> {
-        // Even if the original function call is in a namespace,
-        // we generate this wrapper in the global namespace.
-        // We could easily do this the other way round, and when
-        // cxx::bridge comes to support nested namespace mods then
-        // we wil wish to do that to avoid name conflicts. However,
-        // at the moment this is simpler because it avoids us having
-        // to generate namespace blocks in the generated C++.
+        // Wrappers for free functions in a namespace go in that namespace
+        // (see `CppFunction::wrapper_namespace`), so they call the
+        // function by its unqualified name. Everything else goes in the
+        // global namespace.
+        let in_wrapper_namespace =
+            matches!(conversion_direction, ConversionDirection::RustCallsCpp)
+                && details.wrapper_namespace().is_some();
         let is_a_method = !avoid_this
             && matches!(
                 details.kind,
                 CppFunctionKind::Method
                     | CppFunctionKind::ConstMethod
+                    | CppFunctionKind::VolatileMethod
                     | CppFunctionKind::Constructor
             );
         let name = match force_name {
@@ -439,12 +811,60 @@ impl<'a> CppCodeGenerator<'a> {
                 }
             })
             .next();
+        // Whether there's a flag to say if a returned optional had a value
+        let optional_return_flag = details
+            .argument_conversion
+            .iter()
+            .enumerate()
+            .find(|(_, conv)| conv.is_optional_return_flag())
+            .map(|(counter, _)| get_arg_name(counter));
+        // Likewise, whether there's somewhere to put an error from a returned
+        // variant
+        let result_variant_error = details
+            .argument_conversion
+            .iter()
+            .enumerate()
+            .find(|(_, conv)| conv.is_result_variant_error())
+            .map(|(counter, _)| get_arg_name(counter));
+        // Or somewhere to copy the elements of a returned tuple
+        let tuple_array = details
+            .argument_conversion
+            .iter()
+            .enumerate()
+            .find(|(_, conv)| conv.is_tuple_array())
+            .map(|(counter, _)| get_arg_name(counter));
+        // Or the places to move the elements of a returned pair
+        let pair_elements: Vec<_> = details
+            .argument_conversion
+            .iter()
+            .enumerate()
+            .filter(|(_, conv)| conv.is_pair_element())
+            .map(|(counter, conv)| (conv, get_arg_name(counter)))
+            .collect();
         // Arguments to underlying function call
         let arg_list: Result<Vec<_>, _> = details
             .argument_conversion
             .iter()
             .enumerate()
             .map(|(counter, conv)| match conversion_direction {
+                // The flag saying whether the optional was filled in is
+                // always the following parameter.
+                ConversionDirection::RustCallsCpp if conv.is_optional_out_param_conversion() => {
+                    conv.optional_out_param_conversion(
+                        &get_arg_name(counter),
+                        &get_arg_name(counter + 1),
+                        &self.original_name_map,
+                    )
+                    .map(Some)
+                }
+                // Likewise, a callback's context.
+                ConversionDirection::RustCallsCpp if conv.is_callback_conversion() => conv
+                    .callback_conversion(
+                        &get_arg_name(counter),
+                        &get_arg_name(counter + 1),
+                        &self.original_name_map,
+                    )
+                    .map(Some),
                 ConversionDirection::RustCallsCpp => {
                     conv.cpp_conversion(&get_arg_name(counter), &self.original_name_map, false)
                 }
@@ -472,6 +892,73 @@ impl<'a> CppCodeGenerator<'a> {
             .payload
         {
             CppFunctionBody::Cast => (arg_list, "".to_string(), false),
+            CppFunctionBody::ConversionOperator => (
+                format!("static_cast<{ret_type}>({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::LogicalNot => (
+                format!("[](const auto& value) -> bool {{ return !value; }}({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::SpaceshipCall => (
+                format!("autocxx_compare({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::PartialSpaceshipCall => (
+                format!("autocxx_partial_compare({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::EqualityCall => (
+                format!("[](const auto& a, const auto& b) -> bool {{ return a == b; }}({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            // `operator<<` is called unqualified so that argument-dependent
+            // lookup finds it in the namespace of the type.
+            CppFunctionBody::StreamInsertion => (
+                format!("[](const auto& value) {{ std::ostringstream stream; stream << value; return std::make_unique<std::string>(stream.str()); }}({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::CompoundAssign(operator) => (
+                format!("[](auto& lhs, const auto& rhs) {{ lhs {operator} rhs; }}({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            // An out-of-range index gives a null pointer, rather than being
+            // passed to `operator[]`, so Rust can tell.
+            CppFunctionBody::SubscriptCall => (
+                format!("[](const auto& container, auto index) -> {ret_type} {{ static_assert(std::is_lvalue_reference<decltype(container[index])>::value, \"operator[] must return a reference\"); if constexpr (std::is_signed<decltype(index)>::value) {{ if (index < 0) {{ return nullptr; }} }} if (static_cast<size_t>(index) >= container.size()) {{ return nullptr; }} return &container[index]; }}({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::Dereference { mutable } => {
+                let pointer = if *mutable { "auto& p" } else { "const auto& p" };
+                (
+                    format!("[]({pointer}) -> decltype(auto) {{ static_assert(std::is_lvalue_reference<decltype(*p)>::value, \"operator* must return a reference\"); return *p; }}({arg_list})"),
+                    "".to_string(),
+                    false,
+                )
+            }
+            CppFunctionBody::MoveOutOfVector => (
+                format!("[](auto& v, size_t i) -> {ret_type} {{ if (i >= v.size()) {{ return nullptr; }} return std::make_unique<{ret_type}::element_type>(std::move(v[i])); }}({arg_list})"),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::SizeOf(ty, _) => (
+                format!("sizeof({})", self.namespaced_name(ty)),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::AlignOf(ty, _) => (
+                format!("alignof({})", self.namespaced_name(ty)),
+                "".to_string(),
+                false,
+            ),
             CppFunctionBody::PlacementNew(ns, id) => {
                 let ty_id = QualifiedName::new(ns, id.clone());
                 let ty_id = self.namespaced_name(&ty_id);
@@ -512,11 +999,25 @@ impl<'a> CppCodeGenerator<'a> {
                 (destructor_call, "".to_string(), false)
             }
             CppFunctionBody::FunctionCall(ns, id) => match receiver {
+                Some(receiver) if matches!(details.kind, CppFunctionKind::VolatileMethod) => (
+                    // Adding volatile this way retains any const qualifier
+                    // which the receiver already has.
+                    format!("static_cast<std::add_volatile_t<std::remove_reference_t<decltype({receiver})>>&>({receiver}).{id}({arg_list})"),
+                    "".to_string(),
+                    false,
+                ),
                 Some(receiver) => (
                     format!("{receiver}.{id}({arg_list})"),
                     "".to_string(),
                     false,
                 ),
+                // The parentheses stop argument-dependent lookup finding a
+                // function of the same name in some other namespace.
+                None if in_wrapper_namespace => (
+                    format!("({id})({arg_list})"),
+                    "".to_string(),
+                    false,
+                ),
                 None => {
                     let underlying_function_call = ns
                         .into_iter()
@@ -530,6 +1031,11 @@ impl<'a> CppCodeGenerator<'a> {
                     )
                 }
             },
+            CppFunctionBody::TemplateCall(function, ty) => (
+                format!("{}<{ty}>({arg_list})", self.namespaced_name(function)),
+                "".to_string(),
+                false,
+            ),
             CppFunctionBody::StaticMethodCall(ns, ty_id, fn_id) => {
                 let underlying_function_call = ns
                     .into_iter()
@@ -542,6 +1048,14 @@ impl<'a> CppCodeGenerator<'a> {
                     false,
                 )
             }
+            CppFunctionBody::StaticMemberAccess(ns, ty_id, member_id) => (
+                ns.into_iter()
+                    .cloned()
+                    .chain([ty_id.to_string(), member_id.to_string()].iter().cloned())
+                    .join("::"),
+                "".to_string(),
+                false,
+            ),
             CppFunctionBody::ConstructSuperclass(_) => ("".to_string(), arg_list, false),
             CppFunctionBody::AllocUninitialized(ty) => {
                 let namespaced_ty = self.namespaced_name(ty);
@@ -560,7 +1074,33 @@ impl<'a> CppCodeGenerator<'a> {
         if let Some(ret) = &details.return_conversion {
             let call_itself = match conversion_direction {
                 ConversionDirection::RustCallsCpp => {
-                    ret.cpp_conversion(&underlying_function_call, &self.original_name_map, true)?
+                    match (&optional_return_flag, &result_variant_error, &tuple_array) {
+                        (has_value, None, None) if ret.is_pair_tuple_conversion() => {
+                            Some(ret.pair_tuple_return_conversion(
+                                &underlying_function_call,
+                                &pair_elements,
+                                has_value.as_deref(),
+                            ))
+                        }
+                        (Some(has_value), _, _) => Some(ret.optional_return_conversion(
+                            &underlying_function_call,
+                            has_value,
+                            &self.original_name_map,
+                        )?),
+                        (None, Some(error), _) => Some(ret.result_variant_return_conversion(
+                            &underlying_function_call,
+                            error,
+                            &self.original_name_map,
+                        )?),
+                        (None, None, Some(array)) => Some(
+                            ret.tuple_array_return_conversion(&underlying_function_call, array),
+                        ),
+                        (None, None, None) => ret.cpp_conversion(
+                            &underlying_function_call,
+                            &self.original_name_map,
+                            true,
+                        )?,
+                    }
                 }
                 ConversionDirection::CppCallsCpp => Some(underlying_function_call),
                 ConversionDirection::CppCallsRust => ret.inverse().cpp_conversion(
@@ -578,12 +1118,23 @@ impl<'a> CppCodeGenerator<'a> {
                     let tyname = self.original_name_map.type_to_cpp(ret.cxxbridge_type())?;
                     format!("new({placement_param}) {tyname}({call_itself})")
                 }
+                // The elements have already been copied out.
+                None if ret.is_tuple_array_conversion() || ret.is_pair_tuple_conversion() => {
+                    call_itself
+                }
                 None => format!("return {call_itself}"),
             };
         };
         if !underlying_function_call.is_empty() {
             underlying_function_call = format!("{underlying_function_call};");
         }
+        // cxx reports anything derived from std::exception as an Err, but
+        // other exceptions would escape to Rust, so replace them first.
+        let catches_exceptions = details.catches_exceptions
+            && matches!(conversion_direction, ConversionDirection::RustCallsCpp);
+        if catches_exceptions {
+            underlying_function_call = format!("try {{ {underlying_function_call} }} catch (const std::exception&) {{ throw; }} catch (...) {{ throw std::runtime_error(\"unknown C++ exception\"); }}");
+        }
         let field_assignments =
             if let CppFunctionBody::ConstructSuperclass(superclass_name) = &details.payload {
                 let superclass_assignments = if field_assignments.is_empty() {
@@ -595,7 +1146,19 @@ impl<'a> CppCodeGenerator<'a> {
             } else {
                 "".into()
             };
-        let definition_after_sig = format!("{field_assignments} {{ {underlying_function_call} }}",);
+        // Rust relies on bindgen's idea of the layout, so make sure the C++
+        // compiler agrees.
+        let layout_assertion = match &details.payload {
+            CppFunctionBody::SizeOf(ty, Some(expected)) => {
+                self.layout_assertion("sizeof", ty, *expected)
+            }
+            CppFunctionBody::AlignOf(ty, Some(expected)) => {
+                self.layout_assertion("alignof", ty, *expected)
+            }
+            _ => "".to_string(),
+        };
+        let definition_after_sig =
+            format!("{field_assignments} {{ {layout_assertion}{underlying_function_call} }}",);
         let (declaration, definition) = if requires_rust_declarations {
             (
                 Some(format!("{declaration};")),
@@ -608,10 +1171,185 @@ impl<'a> CppCodeGenerator<'a> {
             )
         };
         let mut headers = vec![Header::System("memory")];
+        if matches!(details.kind, CppFunctionKind::VolatileMethod) {
+            headers.push(Header::System("type_traits"));
+        }
+        if catches_exceptions {
+            headers.push(Header::System("exception"));
+            headers.push(Header::System("stdexcept"));
+        }
+        if matches!(
+            details.payload,
+            CppFunctionBody::SubscriptCall | CppFunctionBody::Dereference { .. }
+        ) {
+            headers.push(Header::System("type_traits"));
+        }
+        if matches!(details.payload, CppFunctionBody::SubscriptCall) {
+            headers.push(Header::System("cstddef"));
+        }
+        if matches!(
+            details.payload,
+            CppFunctionBody::SpaceshipCall | CppFunctionBody::PartialSpaceshipCall
+        ) {
+            headers.push(Header::System("type_traits"));
+            headers.push(Header::System("utility"));
+            headers.push(Header::ComparePrelude);
+        }
+        if matches!(details.payload, CppFunctionBody::MoveOutOfVector) {
+            headers.push(Header::System("cstddef"));
+            headers.push(Header::System("utility"));
+        }
+        if matches!(details.payload, CppFunctionBody::StreamInsertion) {
+            headers.push(Header::System("sstream"));
+            headers.push(Header::System("string"));
+        }
         if need_allocators {
             headers.push(Header::System("stddef.h"));
             headers.push(Header::NewDeletePrelude);
         }
+        if details
+            .return_conversion
+            .iter()
+            .chain(details.argument_conversion.iter())
+            .any(|conv| conv.is_wstring_conversion())
+        {
+            headers.push(Header::System("string"));
+            headers.push(Header::CxxH);
+            headers.push(Header::WStringPrelude);
+        }
+        if details
+            .return_conversion
+            .iter()
+            .chain(details.argument_conversion.iter())
+            .any(|conv| conv.is_u16string_conversion())
+        {
+            headers.push(Header::System("string"));
+            headers.push(Header::System("cstdint"));
+            headers.push(Header::CxxH);
+        }
+        if details
+            .return_conversion
+            .iter()
+            .chain(details.argument_conversion.iter())
+            .any(|conv| conv.is_string_view_conversion())
+        {
+            headers.push(Header::System("string_view"));
+            headers.push(Header::CxxH);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.makes_string_view())
+        {
+            headers.push(Header::System("string_view"));
+            headers.push(Header::CxxH);
+            headers.push(Header::StringViewPrelude);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_optional_string_view_conversion())
+        {
+            headers.push(Header::System("optional"));
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_slice_conversion())
+        {
+            headers.push(Header::System("cstdint"));
+            headers.push(Header::System("type_traits"));
+            headers.push(Header::CxxH);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_span_conversion())
+        {
+            headers.push(Header::System("span"));
+            headers.push(Header::CxxH);
+            headers.push(Header::SpanPrelude);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_string_callback_conversion())
+        {
+            headers.push(Header::System("string"));
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_char_pointer_param_conversion())
+        {
+            headers.push(Header::System("string"));
+            headers.push(Header::CxxH);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_callback_conversion())
+        {
+            headers.push(Header::System("cstddef"));
+            headers.push(Header::CxxH);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_fallible_callback_conversion())
+        {
+            headers.push(Header::System("stdexcept"));
+            headers.push(Header::System("string"));
+        }
+        if details
+            .return_conversion
+            .iter()
+            .any(|conv| conv.is_result_variant_conversion())
+        {
+            headers.push(Header::System("memory"));
+            headers.push(Header::System("utility"));
+            headers.push(Header::System("variant"));
+        }
+        if details
+            .return_conversion
+            .iter()
+            .any(|conv| conv.is_tuple_array_conversion())
+        {
+            headers.push(Header::System("cstddef"));
+            headers.push(Header::System("tuple"));
+        }
+        if details
+            .return_conversion
+            .iter()
+            .any(|conv| conv.is_pair_tuple_conversion())
+        {
+            headers.push(Header::System("utility"));
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_pair_string_element())
+        {
+            headers.push(Header::System("new"));
+            headers.push(Header::CxxH);
+        }
+        if details
+            .return_conversion
+            .iter()
+            .any(|conv| conv.is_duration_count_conversion())
+        {
+            headers.push(Header::System("chrono"));
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_optional_out_param_conversion())
+        {
+            headers.push(Header::System("new"));
+            headers.push(Header::System("optional"));
+            headers.push(Header::System("utility"));
+            headers.push(Header::OptionalOutPrelude);
+        }
         Ok(ExtraCpp {
             declaration,
             definition,
@@ -620,6 +1358,11 @@ impl<'a> CppCodeGenerator<'a> {
         })
     }
 
+    fn layout_assertion(&self, operator: &str, ty: &QualifiedName, expected: usize) -> String {
+        let ty = self.namespaced_name(ty);
+        format!("static_assert({operator}({ty}) == {expected}, \"{operator}({ty}) differs from the layout autocxx gave Rust\"); ")
+    }
+
     fn namespaced_name(&self, name: &QualifiedName) -> String {
         self.original_name_map.map(name)
     }
@@ -701,6 +1444,18 @@ impl<'a> CppCodeGenerator<'a> {
                 )),
                 ..Default::default()
         });
+        if self.config.is_dyn_subclass(&subclass.id().to_string()) {
+            // C++ deleting the object through a superclass pointer only drops
+            // the boxed Rust object if that reaches our destructor. bindgen
+            // doesn't say whether a destructor is virtual, so the C++
+            // compiler checks.
+            let super_cpp_name = superclass.to_cpp_name();
+            self.additional_functions.push(ExtraCpp {
+                declaration: Some(format!("static_assert(std::has_virtual_destructor<{super_cpp_name}>::value, \"{super_cpp_name} is listed in dyn_subclass!, so it needs a virtual destructor\");")),
+                headers: vec![Header::System("type_traits")],
+                ..Default::default()
+            });
+        }
         // And now constructors
         let mut constructor_decls: Vec<String> = Vec::new();
         for constructor in constructors {
@@ -739,3 +1494,128 @@ impl<'a> CppCodeGenerator<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use autocxx_parser::IncludeCppConfig;
+    use syn::parse_quote;
+
+    use super::{CppCodeGenerator, CppNameMap, ExtraCpp, HashSet, Header};
+    use crate::{
+        conversion::{
+            analysis::fun::FnPhase,
+            api::{Api, ApiName},
+            apivec::ApiVec,
+        },
+        types::{make_ident, Namespace},
+        CppCodegenOptions,
+    };
+
+    fn generate_header(header_lists: &[&[Header]]) -> Vec<u8> {
+        generate_header_with_config(header_lists, parse_quote! {})
+    }
+
+    fn generate_header_with_config(
+        header_lists: &[&[Header]],
+        config: IncludeCppConfig,
+    ) -> Vec<u8> {
+        let cpp_codegen_options = CppCodegenOptions::default();
+        let gen = CppCodeGenerator {
+            additional_functions: header_lists
+                .iter()
+                .map(|headers| ExtraCpp {
+                    declaration: Some("void f();".into()),
+                    headers: headers.to_vec(),
+                    ..Default::default()
+                })
+                .collect(),
+            inclusions: String::new(),
+            original_name_map: CppNameMap::new_from_apis(&ApiVec::<FnPhase>::new()),
+            config: &config,
+            cpp_codegen_options: &cpp_codegen_options,
+            cxxgen_header_name: "cxxgen.h",
+            map_helpers: HashSet::new(),
+        };
+        gen.generate().unwrap().header
+    }
+
+    #[test]
+    fn test_header_order_is_deterministic() {
+        let string_then_cxx: &[Header] = &[
+            Header::System("string"),
+            Header::CxxH,
+            Header::WStringPrelude,
+        ];
+        let memory: &[Header] = &[Header::System("utility"), Header::System("memory")];
+        let first = generate_header(&[string_then_cxx, memory]);
+        assert_eq!(first, generate_header(&[string_then_cxx, memory]));
+        assert_eq!(first, generate_header(&[memory, string_then_cxx]));
+        let first = String::from_utf8(first).unwrap();
+        let includes: Vec<_> = first
+            .lines()
+            .filter(|line| line.starts_with("#include"))
+            .collect();
+        assert_eq!(
+            includes,
+            [
+                "#include <memory>",
+                "#include <string>",
+                "#include <utility>",
+                "#include \"cxx.h\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repeated_needs_are_emitted_once() {
+        let config = parse_quote! {};
+        let cpp_codegen_options = CppCodegenOptions::default();
+        let api = |name: &str| ApiName::new(&Namespace::new(), make_ident(name));
+        let apis: Vec<Api<FnPhase>> = vec![
+            Api::StringConstructor {
+                name: api("make_string"),
+            },
+            Api::WStringConstructor {
+                name: api("make_wstring"),
+            },
+            Api::StringConstructor {
+                name: api("make_string"),
+            },
+        ];
+        let mut gen = CppCodeGenerator {
+            additional_functions: Vec::new(),
+            inclusions: String::new(),
+            original_name_map: CppNameMap::new_from_apis(&ApiVec::<FnPhase>::new()),
+            config: &config,
+            cpp_codegen_options: &cpp_codegen_options,
+            cxxgen_header_name: "cxxgen.h",
+            map_helpers: HashSet::new(),
+        };
+        gen.add_needs(apis.iter()).unwrap();
+        let header = String::from_utf8(gen.generate().unwrap().header).unwrap();
+        let make_string = format!("{}(::rust::Str str)", config.get_makestring_name());
+        let make_wstring = format!("{}(::rust::Str str)", config.get_makewstring_name());
+        assert_eq!(header.matches(&make_string).count(), 1);
+        assert_eq!(header.matches(&make_wstring).count(), 1);
+        assert!(header.find(&make_string) < header.find(&make_wstring));
+    }
+
+    #[test]
+    fn test_cpp_prelude_precedes_declarations() {
+        let header = generate_header_with_config(
+            &[&[Header::System("memory")]],
+            parse_quote! {
+                cpp_prelude!("#define AUTOCXX_TEST_PRELUDE 1")
+                cpp_prelude!("namespace prelude_ns {}")
+            },
+        );
+        let header = String::from_utf8(header).unwrap();
+        let define = header.find("#define AUTOCXX_TEST_PRELUDE 1\n").unwrap();
+        let namespace = header.find("namespace prelude_ns {}\n").unwrap();
+        let include = header.find("#include <memory>").unwrap();
+        let declaration = header.find("void f();").unwrap();
+        assert!(include < define);
+        assert!(define < namespace);
+        assert!(namespace < declaration);
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Support for `std::optional<T>&` out parameters. The C++ function is
+/// given an empty `std::optional<T>` belonging to a temporary, which lives
+/// until the end of the full expression containing the call. Once the
+/// call is complete, the temporary's destructor moves any value into
+/// uninitialized storage provided by Rust, and records that it did so.
+pub(super) static OPTIONAL_OUT_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_OPTIONAL_OUT_PRELUDE
+    #define AUTOCXX_OPTIONAL_OUT_PRELUDE
+    // Mechanics to pass back std::optional out parameters
+    template <typename T> class autocxx_optional_out {
+    public:
+      autocxx_optional_out(T* value, bool& has_value) : value(value), has_value(has_value) {}
+      ~autocxx_optional_out() {
+        if (optional) {
+          new (value) T(std::move(*optional));
+          has_value = true;
+        }
+      }
+      std::optional<T>& get() { return optional; }
+    private:
+      T* value;
+      bool& has_value;
+      std::optional<T> optional;
+    };
+    #endif // AUTOCXX_OPTIONAL_OUT_PRELUDE
+"};
@@ -0,0 +1,30 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Support for `std_span!` parameters. The C++ function is given a span of
+/// the slice's elements, so it has the slice's length. A span with a
+/// static extent would have to have exactly the length in its type, which
+/// nothing checks, so the conversion refuses to compile for one.
+pub(super) static SPAN_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_SPAN_PRELUDE
+    #define AUTOCXX_SPAN_PRELUDE
+    // Mechanics to pass a rust::Slice as a std::span with a dynamic extent
+    template <typename T> class autocxx_dynamic_span {
+    public:
+      autocxx_dynamic_span(T* data, std::size_t size) : span(data, size) {}
+      template <typename U, std::size_t N> operator std::span<U, N>() const {
+        static_assert(N == std::dynamic_extent, \"std_span! only supports a std::span with a dynamic extent\");
+        return span;
+      }
+    private:
+      std::span<T> span;
+    };
+    #endif // AUTOCXX_SPAN_PRELUDE
+"};
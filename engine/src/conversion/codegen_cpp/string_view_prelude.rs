@@ -0,0 +1,24 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Support for `std::string_view` parameters. Unlike `make_string`, which
+/// copies a `rust::Str` into a new heap `std::string`, this just views the
+/// Rust characters. The view is only valid while the `rust::Str` is, which
+/// for a parameter is until the call returns: the Rust caller's borrow of
+/// the `&str` lasts that long, and no longer.
+pub(super) static STRING_VIEW_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_STRING_VIEW_PRELUDE
+    #define AUTOCXX_STRING_VIEW_PRELUDE
+    // Mechanics to pass a rust::Str as a std::string_view, without copying
+    inline std::string_view autocxx_make_string_view(::rust::Str str) {
+      return std::string_view(str.data(), str.size());
+    }
+    #endif // AUTOCXX_STRING_VIEW_PRELUDE
+"};
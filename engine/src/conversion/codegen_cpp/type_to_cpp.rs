@@ -14,7 +14,7 @@ use indexmap::map::IndexMap as HashMap;
 use itertools::Itertools;
 use quote::ToTokens;
 use std::iter::once;
-use syn::{Token, Type};
+use syn::{Expr, ExprLit, Lit, Token, Type, TypeArray, TypeSlice};
 
 /// Map from QualifiedName to original C++ name. Original C++ name does not
 /// include the namespace; this can be assumed to be the same as the namespace
@@ -107,6 +107,11 @@ impl CppNameMap {
             }
             Type::Reference(typr) => match &*typr.elem {
                 Type::Path(typ) if typ.path.is_ident("str") => Ok("rust::Str".into()),
+                Type::Slice(TypeSlice { elem, .. }) => Ok(format!(
+                    "rust::Slice<{}{}>",
+                    get_mut_string(&typr.mutability),
+                    self.type_to_cpp(elem)?
+                )),
                 _ => Ok(format!(
                     "{}{}&",
                     get_mut_string(&typr.mutability),
@@ -118,6 +123,19 @@ impl CppNameMap {
                 get_mut_string(&typp.mutability),
                 self.type_to_cpp(typp.elem.as_ref())?
             )),
+            // cxx passes arrays as `std::array`s.
+            Type::Array(TypeArray {
+                elem,
+                len:
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(len), ..
+                    }),
+                ..
+            }) => Ok(format!(
+                "std::array<{}, {}>",
+                self.type_to_cpp(elem)?,
+                len.base10_digits()
+            )),
             Type::Array(_)
             | Type::BareFn(_)
             | Type::Group(_)
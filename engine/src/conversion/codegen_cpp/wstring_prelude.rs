@@ -0,0 +1,94 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Transcoding between `std::wstring` and UTF-8.
+/// The encoding of a `std::wstring` depends on the width of `wchar_t`:
+/// it's 16 bits on Windows, where strings are UTF-16, and 32 bits on
+/// most other platforms, where they're UTF-32. Both are handled here.
+/// Text coming from C++ may not be well-formed, so unpaired surrogates and
+/// out-of-range code points are replaced with U+FFFD. Text coming from
+/// Rust is always valid UTF-8 so needs no such checks.
+pub(super) static WSTRING_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_WSTRING_PRELUDE
+    #define AUTOCXX_WSTRING_PRELUDE
+    // Mechanics to convert std::wstring to and from UTF-8
+    inline void autocxx_push_utf8(std::string &out, char32_t c) {
+      if (c < 0x80) {
+        out.push_back(static_cast<char>(c));
+      } else if (c < 0x800) {
+        out.push_back(static_cast<char>(0xC0 | (c >> 6)));
+        out.push_back(static_cast<char>(0x80 | (c & 0x3F)));
+      } else if (c < 0x10000) {
+        out.push_back(static_cast<char>(0xE0 | (c >> 12)));
+        out.push_back(static_cast<char>(0x80 | ((c >> 6) & 0x3F)));
+        out.push_back(static_cast<char>(0x80 | (c & 0x3F)));
+      } else {
+        out.push_back(static_cast<char>(0xF0 | (c >> 18)));
+        out.push_back(static_cast<char>(0x80 | ((c >> 12) & 0x3F)));
+        out.push_back(static_cast<char>(0x80 | ((c >> 6) & 0x3F)));
+        out.push_back(static_cast<char>(0x80 | (c & 0x3F)));
+      }
+    }
+    inline std::string autocxx_wstring_to_utf8(const std::wstring &in) {
+      std::string out;
+      out.reserve(in.size());
+      for (std::size_t i = 0; i < in.size(); i++) {
+        char32_t c = static_cast<char32_t>(in[i]);
+        if (sizeof(wchar_t) == 2 && c >= 0xD800 && c < 0xDC00 && i + 1 < in.size()) {
+          char32_t low = static_cast<char32_t>(in[i + 1]);
+          if (low >= 0xDC00 && low < 0xE000) {
+            c = 0x10000 + ((c - 0xD800) << 10) + (low - 0xDC00);
+            i++;
+          }
+        }
+        if ((c >= 0xD800 && c < 0xE000) || c > 0x10FFFF) {
+          c = 0xFFFD;
+        }
+        autocxx_push_utf8(out, c);
+      }
+      return out;
+    }
+    inline std::wstring autocxx_utf8_to_wstring(const char *data, std::size_t len) {
+      std::wstring out;
+      out.reserve(len);
+      std::size_t i = 0;
+      while (i < len) {
+        unsigned char lead = static_cast<unsigned char>(data[i]);
+        char32_t c;
+        std::size_t extra;
+        if (lead < 0x80) {
+          c = lead;
+          extra = 0;
+        } else if (lead < 0xE0) {
+          c = lead & 0x1F;
+          extra = 1;
+        } else if (lead < 0xF0) {
+          c = lead & 0x0F;
+          extra = 2;
+        } else {
+          c = lead & 0x07;
+          extra = 3;
+        }
+        for (std::size_t j = 1; j <= extra; j++) {
+          c = (c << 6) | (static_cast<unsigned char>(data[i + j]) & 0x3F);
+        }
+        i += extra + 1;
+        if (sizeof(wchar_t) == 2 && c >= 0x10000) {
+          c -= 0x10000;
+          out.push_back(static_cast<wchar_t>(0xD800 + (c >> 10)));
+          out.push_back(static_cast<wchar_t>(0xDC00 + (c & 0x3FF)));
+        } else {
+          out.push_back(static_cast<wchar_t>(c));
+        }
+      }
+      return out;
+    }
+    #endif // AUTOCXX_WSTRING_PRELUDE
+"};
@@ -0,0 +1,178 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for `bitmask_enum!`. A C++ enum used as a set of flags may
+//! hold any combination of its enumerators, which a Rust `enum` can't
+//! represent, so we instead generate a transparent wrapper around the
+//! underlying integer in the style of the `bitflags` crate. It has the
+//! same layout as the C++ enum, so it can be passed to and from C++ by
+//! value like any other enum.
+
+use quote::quote;
+use syn::{parse_quote, Attribute, Item, ItemEnum, Meta, Type};
+
+/// Returns the wrapper type to use in place of `item`, followed by the
+/// items which implement it.
+pub(super) fn generate_bitmask_enum(item: ItemEnum) -> (Item, Vec<Item>) {
+    let id = &item.ident;
+    let repr = underlying_type(&item.attrs);
+    let doc_attrs = item.attrs.iter().filter(|attr| attr.path().is_ident("doc"));
+    let consts = item.variants.iter().map(|v| {
+        let variant_id = &v.ident;
+        let attrs = &v.attrs;
+        let value = &v
+            .discriminant
+            .as_ref()
+            .expect("bindgen gives all discriminants")
+            .1;
+        quote! {
+            #(#attrs)*
+            pub const #variant_id: Self = Self(#value);
+        }
+    });
+    let all_bits = item.variants.iter().map(|v| &v.ident);
+    let wrapper = Item::Struct(parse_quote! {
+        #(#doc_attrs)*
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Hash, PartialEq, Eq, Default)]
+        pub struct #id(pub #repr);
+    });
+    let items = vec![
+        parse_quote! {
+            #[allow(non_upper_case_globals)]
+            impl #id {
+                #(#consts)*
+
+                /// No flags set.
+                pub const fn empty() -> Self {
+                    Self(0)
+                }
+
+                /// Every flag which is one of the C++ enumerators.
+                pub const fn all() -> Self {
+                    Self(0 #(| Self::#all_bits.0)*)
+                }
+
+                /// The underlying integer, including any unknown bits.
+                pub const fn bits(&self) -> #repr {
+                    self.0
+                }
+
+                /// Returns `None` if any bits don't correspond to a C++
+                /// enumerator.
+                pub const fn from_bits(bits: #repr) -> ::core::option::Option<Self> {
+                    if bits & !Self::all().0 == 0 {
+                        ::core::option::Option::Some(Self(bits))
+                    } else {
+                        ::core::option::Option::None
+                    }
+                }
+
+                /// Discards any bits which don't correspond to a C++
+                /// enumerator.
+                pub const fn from_bits_truncate(bits: #repr) -> Self {
+                    Self(bits & Self::all().0)
+                }
+
+                /// Keeps all the bits, even those which don't correspond to
+                /// a C++ enumerator.
+                pub const fn from_bits_retain(bits: #repr) -> Self {
+                    Self(bits)
+                }
+
+                pub const fn is_empty(&self) -> bool {
+                    self.0 == 0
+                }
+
+                /// Whether all the flags in `other` are set.
+                pub const fn contains(&self, other: Self) -> bool {
+                    self.0 & other.0 == other.0
+                }
+
+                /// Whether any of the flags in `other` are set.
+                pub const fn intersects(&self, other: Self) -> bool {
+                    self.0 & other.0 != 0
+                }
+
+                pub fn insert(&mut self, other: Self) {
+                    self.0 |= other.0;
+                }
+
+                pub fn remove(&mut self, other: Self) {
+                    self.0 &= !other.0;
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::ops::BitOr for #id {
+                type Output = Self;
+                fn bitor(self, other: Self) -> Self {
+                    Self(self.0 | other.0)
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::ops::BitOrAssign for #id {
+                fn bitor_assign(&mut self, other: Self) {
+                    self.0 |= other.0;
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::ops::BitAnd for #id {
+                type Output = Self;
+                fn bitand(self, other: Self) -> Self {
+                    Self(self.0 & other.0)
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::ops::BitAndAssign for #id {
+                fn bitand_assign(&mut self, other: Self) {
+                    self.0 &= other.0;
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::ops::BitXor for #id {
+                type Output = Self;
+                fn bitxor(self, other: Self) -> Self {
+                    Self(self.0 ^ other.0)
+                }
+            }
+        },
+        // As with the `bitflags` crate, this only flips known flags.
+        parse_quote! {
+            impl ::core::ops::Not for #id {
+                type Output = Self;
+                fn not(self) -> Self {
+                    Self::from_bits_truncate(!self.0)
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::fmt::Debug for #id {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    ::core::write!(f, "{}({:#x})", stringify!(#id), self.0)
+                }
+            }
+        },
+    ];
+    (wrapper, items)
+}
+
+/// bindgen tells us the enum's underlying type with a `#[repr]`.
+pub(super) fn underlying_type(attrs: &[Attribute]) -> Type {
+    attrs
+        .iter()
+        .find_map(|attr| match &attr.meta {
+            Meta::List(list) if list.path.is_ident("repr") => list.parse_args().ok(),
+            _ => None,
+        })
+        .unwrap_or_else(|| parse_quote! { u32 })
+}
@@ -23,6 +23,7 @@ use syn::{
 use super::{
     function_wrapper_rs::RustParamConversion,
     maybe_unsafes_to_tokens,
+    tag_dispatch::generate_tag_for_overload,
     unqualify::{unqualify_params, unqualify_ret_type},
     ImplBlockDetails, ImplBlockKey, MaybeUnsafeStmt, RsCodegenResult, TraitImplBlockDetails, Use,
 };
@@ -85,6 +86,25 @@ impl UnsafetyNeeded {
     }
 }
 
+/// bindgen doesn't tell us about `[[gnu::pure]]` or `[[gnu::const]]`, so we
+/// only mention a function's lack of side effects if the user listed it in
+/// `pure_functions!` or `const_functions!`.
+fn purity_note(config: &IncludeCppConfig, qualified_cpp_name: &str) -> Option<&'static str> {
+    if config.is_const_function(qualified_cpp_name) {
+        Some(
+            "This function is listed in `const_functions!`: like a C++ `[[gnu::const]]` \
+            function, it has no side effects, and its result depends only upon its arguments.",
+        )
+    } else if config.is_pure_function(qualified_cpp_name) {
+        Some(
+            "This function is listed in `pure_functions!`: like a C++ `[[gnu::pure]]` \
+            function, it has no side effects, though its result may depend upon global state.",
+        )
+    } else {
+        None
+    }
+}
+
 pub(super) fn gen_function(
     ns: &Namespace,
     fun: FuncToConvert,
@@ -100,12 +120,31 @@ pub(super) fn gen_function(
     let rust_name = &analysis.rust_name;
     let ret_type = analysis.ret_type;
     let ret_conversion = analysis.ret_conversion;
+    let catches_exceptions = analysis.catches_exceptions;
+    let borrows_from_receiver = analysis.borrows_from_receiver;
     let param_details = analysis.param_details;
     let wrapper_function_needed = analysis.cpp_wrapper.is_some();
+    let wrapper_namespace = analysis
+        .cpp_wrapper
+        .as_ref()
+        .and_then(|cpp_wrapper| cpp_wrapper.wrapper_namespace())
+        .cloned();
     let params = analysis.params;
     let vis = analysis.vis;
     let kind = analysis.kind;
-    let doc_attrs = minisynize_vec(fun.doc_attrs);
+    let mut doc_attrs: Vec<Attribute> = minisynize_vec(fun.doc_attrs);
+    let qualified_cpp_name = match kind {
+        FnKind::Method { ref impl_for, .. } | FnKind::TraitMethod { ref impl_for, .. } => {
+            format!("{}::{}", impl_for.to_cpp_name(), cpp_call_name)
+        }
+        FnKind::Function => QualifiedName::new(ns, make_ident(&cpp_call_name)).to_cpp_name(),
+    };
+    if let Some(purity_note) = purity_note(config, &qualified_cpp_name) {
+        if !doc_attrs.is_empty() {
+            doc_attrs.push(parse_quote! { #[doc = ""] });
+        }
+        doc_attrs.push(parse_quote! { #[doc = #purity_note] });
+    }
 
     let mut cpp_name_attr = Vec::new();
     let mut impl_entry = None;
@@ -126,6 +165,8 @@ pub(super) fn gen_function(
         ret_type: &ret_type,
         ret_conversion: &ret_conversion,
         reference_wrappers: config.unsafe_policy.requires_cpprefs(),
+        catches_exceptions,
+        borrows_from_receiver,
     };
     // In rare occasions, we might need to give an explicit lifetime.
     let (lifetime_tokens, params, ret_type) = add_explicit_lifetime_if_necessary(
@@ -133,6 +174,7 @@ pub(super) fn gen_function(
         params,
         Cow::Borrowed(&ret_type),
         non_pod_types,
+        borrows_from_receiver,
     );
 
     if analysis.rust_wrapper_needed {
@@ -178,6 +220,37 @@ pub(super) fn gen_function(
             _ => Some(Use::UsedFromCxxBridge),
         },
     };
+    let mut materializations: Vec<Use> = materialization.into_iter().collect();
+    if matches!(kind, FnKind::Function)
+        && config
+            .is_tag_dispatched(&QualifiedName::new(ns, make_ident(&cpp_call_name)).to_cpp_name())
+    {
+        // Tag dispatch calls whichever Rust function users would otherwise
+        // call directly, so we need that function's signature.
+        let tag = if analysis.rust_wrapper_needed {
+            let (wrapper_lifetime_tokens, wrapper_params, wrapper_ret_type, _) =
+                fn_generator.common_parts(false, &None, None);
+            (wrapper_lifetime_tokens.is_none()
+                && analysis.requires_unsafe.wrapper_token().is_none())
+            .then(|| {
+                generate_tag_for_overload(
+                    &cpp_call_name,
+                    rust_name,
+                    &wrapper_params,
+                    &wrapper_ret_type,
+                )
+            })
+            .flatten()
+        } else {
+            (lifetime_tokens.is_none() && analysis.requires_unsafe.bridge_token().is_none())
+                .then(|| generate_tag_for_overload(&cpp_call_name, rust_name, &params, &ret_type))
+                .flatten()
+        };
+        if let Some((mut tag_items, tag_materialization)) = tag {
+            bindgen_mod_items.append(&mut tag_items);
+            materializations.push(tag_materialization);
+        }
+    }
     if cxxbridge_name != cpp_call_name && !wrapper_function_needed {
         cpp_name_attr = Attribute::parse_outer
             .parse2(quote!(
@@ -196,17 +269,29 @@ pub(super) fn gen_function(
     // which the user has declared.
     let params = unqualify_params(params);
     let ret_type = unqualify_ret_type(ret_type.into_owned());
+    // cxx turns a C++ exception into the `Err` of a `Result`.
+    let ret_type: ReturnType = match ret_type {
+        _ if !catches_exceptions => ret_type,
+        ReturnType::Default => parse_quote! { -> Result<()> },
+        ReturnType::Type(_, ty) => parse_quote! { -> Result<#ty> },
+    };
     // And we need to make an attribute for the namespace that the function
-    // itself is in.
-    let namespace_attr = if ns.is_empty() || wrapper_function_needed {
-        Vec::new()
+    // itself is in, or that its C++ wrapper is in, if it has one.
+    let namespace = if wrapper_function_needed {
+        wrapper_namespace
     } else {
-        let namespace_string = ns.to_string();
-        Attribute::parse_outer
-            .parse2(quote!(
-                #[namespace = #namespace_string]
-            ))
-            .unwrap()
+        Some(ns.clone()).filter(|ns| !ns.is_empty())
+    };
+    let namespace_attr = match namespace {
+        None => Vec::new(),
+        Some(namespace) => {
+            let namespace_string = namespace.to_string();
+            Attribute::parse_outer
+                .parse2(quote!(
+                    #[namespace = #namespace_string]
+                ))
+                .unwrap()
+        }
     };
     // At last, actually generate the cxx::bridge entry.
     let bridge_unsafety = analysis.requires_unsafe.bridge_token();
@@ -221,7 +306,7 @@ pub(super) fn gen_function(
         bindgen_mod_items,
         impl_entry,
         trait_impl_entry,
-        materializations: materialization.into_iter().collect(),
+        materializations,
         ..Default::default()
     }
 }
@@ -239,6 +324,8 @@ struct FnGenerator<'a> {
     doc_attrs: &'a Vec<Attribute>,
     non_pod_types: &'a HashSet<QualifiedName>,
     reference_wrappers: bool,
+    catches_exceptions: bool,
+    borrows_from_receiver: bool,
 }
 
 impl<'a> FnGenerator<'a> {
@@ -262,6 +349,7 @@ impl<'a> FnGenerator<'a> {
             .unwrap_or_else(|| Cow::Borrowed(self.ret_type));
         let mut any_conversion_requires_unsafe = false;
         let mut variable_counter = 0usize;
+        let mut out_params = Vec::new();
         for pd in self.param_details {
             let wrapper_arg_name: syn::Pat = if pd.self_type.is_some() && !avoid_self {
                 parse_quote!(self)
@@ -298,8 +386,25 @@ impl<'a> FnGenerator<'a> {
                     });
                     arg_list.push(pd.name.to_token_stream());
                 }
+                RustParamConversion::OutParam {
+                    local_variable,
+                    conversion,
+                    result,
+                } => {
+                    local_variables.push(MaybeUnsafeStmt::new(local_variable));
+                    arg_list.push(conversion);
+                    out_params.extend(result);
+                }
             }
         }
+        if !out_params.is_empty() {
+            let out_types = out_params.iter().map(|(_, ty)| ty);
+            ret_type = Cow::Owned(if out_params.len() == 1 {
+                parse_quote! { -> #(#out_types)* }
+            } else {
+                parse_quote! { -> ( #(#out_types),* ) }
+            });
+        }
         if let Some(parameter_reordering) = &parameter_reordering {
             wrapper_params = Self::reorder_parameters(wrapper_params, parameter_reordering);
         }
@@ -308,12 +413,26 @@ impl<'a> FnGenerator<'a> {
             wrapper_params,
             ret_type,
             self.non_pod_types,
+            self.borrows_from_receiver,
         );
 
         let cxxbridge_name = self.cxxbridge_name;
-        let call_body = MaybeUnsafeStmt::maybe_unsafe(
+        // A `catch_exceptions!` function passes on any error straight away,
+        // so the rest of the wrapper only ever sees the value.
+        let call = if self.catches_exceptions {
+            quote! {
+                cxxbridge::#cxxbridge_name ( #(#arg_list),* )?
+            }
+        } else {
             quote! {
                 cxxbridge::#cxxbridge_name ( #(#arg_list),* )
+            }
+        };
+        let call_body = MaybeUnsafeStmt::maybe_unsafe(
+            if out_params.is_empty() {
+                call
+            } else {
+                quote! { #call; }
             },
             any_conversion_requires_unsafe || matches!(self.unsafety, UnsafetyNeeded::JustBridge),
         );
@@ -377,9 +496,32 @@ impl<'a> FnGenerator<'a> {
         } else {
             let mut call_stmts = local_variables;
             call_stmts.push(call_body);
+            if !out_params.is_empty() {
+                let out_results = out_params.iter().map(|(result, _)| result);
+                call_stmts.push(MaybeUnsafeStmt::new(if out_params.len() == 1 {
+                    quote! { #(#out_results)* }
+                } else {
+                    quote! { ( #(#out_results),* ) }
+                }));
+            }
             call_stmts
         };
-        let call_body = maybe_unsafes_to_tokens(call_stmts, context_is_unsafe);
+        let mut call_body = maybe_unsafes_to_tokens(call_stmts, context_is_unsafe);
+        let ret_type = if self.catches_exceptions {
+            call_body = quote! {
+                ::core::result::Result::Ok({ #call_body })
+            };
+            Cow::Owned(match ret_type.as_ref() {
+                ReturnType::Default => parse_quote! {
+                    -> ::core::result::Result<(), ::cxx::Exception>
+                },
+                ReturnType::Type(_, ty) => parse_quote! {
+                    -> ::core::result::Result<#ty, ::cxx::Exception>
+                },
+            })
+        } else {
+            ret_type
+        };
         (lifetime_tokens, wrapper_params, ret_type, call_body)
     }
 
@@ -7,13 +7,19 @@
 // except according to those terms.
 
 use proc_macro2::TokenStream;
-use syn::{Expr, Type, TypePtr};
+use syn::{Expr, ReturnType, Type, TypeBareFn, TypePtr, TypeReference};
 
 use crate::{
-    conversion::analysis::fun::function_wrapper::{RustConversionType, TypeConversionPolicy},
+    conversion::{
+        analysis::fun::function_wrapper::{
+            CallbackKind, PairShape, RustConversionType, TypeConversionPolicy,
+        },
+        type_helpers::extract_cxx_vector_reference_item,
+    },
     types::make_ident,
 };
-use quote::quote;
+use autocxx_parser::{Utf16Policy, Utf8Policy};
+use quote::{quote, ToTokens};
 use syn::parse_quote;
 
 use super::MaybeUnsafeStmt;
@@ -29,6 +35,67 @@ pub(super) enum RustParamConversion {
     ReturnValue {
         ty: Type,
     },
+    /// A parameter which the wrapper creates in `local_variable` and passes
+    /// to C++ to fill in. If there's a `result`, that expression and its
+    /// type are then returned to the wrapper's caller.
+    OutParam {
+        local_variable: TokenStream,
+        conversion: TokenStream,
+        result: Option<(TokenStream, Type)>,
+    },
+}
+
+impl RustParamConversion {
+    /// A `UniquePtr` out parameter, named `var`, from which `result` of
+    /// type `ty` is returned.
+    fn null_unique_ptr_out_param(var: Expr, result: TokenStream, ty: Type) -> Self {
+        RustParamConversion::OutParam {
+            // Each output starts off null, so that whichever outputs
+            // the C++ does manage to create are still returned, and
+            // dropped, even if it fails to create the others.
+            local_variable: quote! {
+                let mut #var = cxx::UniquePtr::null();
+            },
+            conversion: quote! { &mut #var },
+            result: Some((result, ty)),
+        }
+    }
+}
+
+/// The tuple made from the initialized out parameters for the elements of
+/// a `std::pair`, and its type.
+fn pair_shape_to_tuple(shape: &PairShape) -> (TokenStream, Type) {
+    match shape {
+        PairShape::Element(var, ty) => (
+            quote! {
+                // Safety: C++ has initialized this element.
+                unsafe { #var.assume_init() }
+            },
+            ty.as_ref().clone().into(),
+        ),
+        PairShape::UniquePtr(var, ty) => (quote! { #var }, ty.as_ref().clone().into()),
+        PairShape::Pair(first, second) => {
+            let (first, first_ty) = pair_shape_to_tuple(first);
+            let (second, second_ty) = pair_shape_to_tuple(second);
+            (
+                quote! { (#first, #second) },
+                parse_quote! { (#first_ty, #second_ty) },
+            )
+        }
+        PairShape::Optional(has_value, pair) => {
+            let (pair, pair_ty) = pair_shape_to_tuple(pair);
+            (
+                quote! {
+                    if #has_value {
+                        ::core::option::Option::Some(#pair)
+                    } else {
+                        ::core::option::Option::None
+                    }
+                },
+                parse_quote! { ::core::option::Option<#pair_ty> },
+            )
+        }
+    }
 }
 
 impl TypeConversionPolicy {
@@ -46,11 +113,14 @@ impl TypeConversionPolicy {
                 conversion: quote! ( #var .into_cpp() ),
                 conversion_requires_unsafe: false,
             },
-            RustConversionType::ToBoxedUpHolder(ref sub) => {
+            RustConversionType::ToBoxedUpHolder {
+                subclass: ref sub,
+                is_dyn,
+            } => {
                 let holder_type = sub.holder();
-                let id = sub.id();
+                let rust_type = sub.rust_type_path(is_dyn);
                 let ty = parse_quote! { autocxx::subclass::CppSubclassRustPeerHolder<
-                    super::super::super:: #id>
+                    #rust_type>
                 };
                 RustParamConversion::Param {
                     ty,
@@ -164,6 +234,114 @@ impl TypeConversionPolicy {
                 };
                 RustParamConversion::ReturnValue { ty }
             }
+            RustConversionType::FromUniquePtrOutParamToReturnValue => {
+                let ty = match self.cxxbridge_type() {
+                    Type::Reference(TypeReference { elem, .. }) => *(*elem).clone(),
+                    _ => panic!("Not a reference"),
+                };
+                RustParamConversion::null_unique_ptr_out_param(var.clone(), quote! { #var }, ty)
+            }
+            RustConversionType::FromStringCallbackToReturnValue(ref policy) => match policy {
+                None => RustParamConversion::null_unique_ptr_out_param(
+                    var.clone(),
+                    quote! { #var },
+                    parse_quote! { cxx::UniquePtr<cxx::CxxString> },
+                ),
+                Some(policy) => {
+                    let (ty, conversion) = cxx_string_to_string(policy, quote! { s });
+                    RustParamConversion::null_unique_ptr_out_param(
+                        var.clone(),
+                        quote! {
+                            #var.as_ref().map(|s| #conversion)
+                        },
+                        parse_quote! { ::core::option::Option<#ty> },
+                    )
+                }
+            },
+            RustConversionType::FromOptionalOutParamToReturnValue { ref has_value } => {
+                let ty = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => elem.as_ref(),
+                    _ => panic!("Not a pointer"),
+                };
+                RustParamConversion::OutParam {
+                    local_variable: quote! {
+                        let mut #var = ::core::mem::MaybeUninit::<#ty>::uninit();
+                    },
+                    conversion: quote! { #var.as_mut_ptr() },
+                    result: Some((
+                        quote! {
+                            if #has_value {
+                                // Safety: C++ has initialized the value.
+                                ::core::option::Option::Some(unsafe { #var.assume_init() })
+                            } else {
+                                ::core::option::Option::None
+                            }
+                        },
+                        parse_quote! { ::core::option::Option<#ty> },
+                    )),
+                }
+            }
+            RustConversionType::FromTupleArrayOutParamToReturnValue(len) => {
+                let ty = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => elem.as_ref(),
+                    _ => panic!("Not a pointer"),
+                };
+                RustParamConversion::OutParam {
+                    local_variable: quote! {
+                        let mut #var = ::core::mem::MaybeUninit::<[#ty; #len]>::uninit();
+                    },
+                    conversion: quote! { #var.as_mut_ptr().cast::<#ty>() },
+                    result: Some((
+                        quote! {
+                            // Safety: C++ has initialized every element.
+                            unsafe { #var.assume_init() }
+                        },
+                        parse_quote! { [#ty; #len] },
+                    )),
+                }
+            }
+            RustConversionType::FromPairElementOutParamToReturnValue(ref shape) => {
+                let (local_variable, conversion) = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => (
+                        quote! {
+                            let mut #var = ::core::mem::MaybeUninit::<#elem>::uninit();
+                        },
+                        quote! { #var.as_mut_ptr() },
+                    ),
+                    Type::Reference(_) => (
+                        quote! {
+                            let mut #var = cxx::UniquePtr::null();
+                        },
+                        quote! { &mut #var },
+                    ),
+                    _ => panic!("Not a pointer or reference"),
+                };
+                RustParamConversion::OutParam {
+                    local_variable,
+                    conversion,
+                    result: shape.as_ref().map(pair_shape_to_tuple),
+                }
+            }
+            RustConversionType::FromOptionToSlice => RustParamConversion::Param {
+                ty: parse_quote! { ::core::option::Option<&str> },
+                local_variables: Vec::new(),
+                conversion: quote! { #var.as_slice() },
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::ResultErrorOutParam => RustParamConversion::OutParam {
+                local_variable: quote! {
+                    let mut #var = cxx::UniquePtr::null();
+                },
+                conversion: quote! { &mut #var },
+                result: None,
+            },
+            RustConversionType::OptionalOutParamFlag => RustParamConversion::OutParam {
+                local_variable: quote! {
+                    let mut #var = false;
+                },
+                conversion: quote! { &mut #var },
+                result: None,
+            },
             RustConversionType::FromPointerToReferenceWrapper => {
                 let (is_mut, ty) = match self.cxxbridge_type() {
                     Type::Ptr(TypePtr {
@@ -213,6 +391,616 @@ impl TypeConversionPolicy {
                     conversion_requires_unsafe: false,
                 }
             }
+            RustConversionType::FromCxxStringToString(policy) => {
+                let (ty, conversion) = cxx_string_to_string(&policy, quote! { #var });
+                RustParamConversion::Param {
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion,
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromUtf16ToString(policy) => {
+                let (ty, conversion) = match policy {
+                    Utf16Policy::Lossy => (
+                        parse_quote! { ::std::string::String },
+                        quote! { ::std::string::String::from_utf16_lossy(&#var) },
+                    ),
+                    Utf16Policy::Checked => (
+                        parse_quote! {
+                            ::core::result::Result<
+                                ::std::string::String,
+                                ::std::string::FromUtf16Error,
+                            >
+                        },
+                        quote! { ::std::string::String::from_utf16(&#var) },
+                    ),
+                };
+                RustParamConversion::Param {
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion,
+                    conversion_requires_unsafe: false,
+                }
+            }
+            // The code units are kept in a local variable so that they
+            // live until the C++ has finished with them.
+            RustConversionType::FromStrToUtf16 => RustParamConversion::Param {
+                ty: parse_quote! { &str },
+                local_variables: vec![MaybeUnsafeStmt::new(quote! {
+                    let #var = #var.encode_utf16().collect::<::std::vec::Vec<u16>>();
+                })],
+                conversion: quote! { &#var },
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromFloatRejectingNan => {
+                let message = format!("{} must not be NaN", var.to_token_stream());
+                RustParamConversion::Param {
+                    ty: self.converted_rust_type(),
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            debug_assert!(!#var.is_nan(), #message);
+                            #var
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromIntegerRejectingUnknownEnumValues {
+                ref enum_ty,
+                ref variants,
+            } => {
+                let ty = self.converted_rust_type();
+                let name = match &***enum_ty {
+                    Type::Path(tp) => tp.path.segments.last().unwrap().ident.to_string(),
+                    _ => panic!("Enum should be a path"),
+                };
+                let message = format!(
+                    "{} isn't the value of any variant of {name}",
+                    var.to_token_stream()
+                );
+                RustParamConversion::Param {
+                    conversion: quote! {
+                        {
+                            debug_assert!(
+                                [#(<#enum_ty>::#variants as #ty),*].contains(&#var),
+                                #message
+                            );
+                            #var
+                        }
+                    },
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromBitmaskRejectingUnknownBits => {
+                let ty = self.converted_rust_type();
+                let name = match &ty {
+                    Type::Path(tp) => tp.path.segments.last().unwrap().ident.to_string(),
+                    _ => panic!("Bitmask enum should be a path"),
+                };
+                let message =
+                    format!("C++ returned a {name} with bits which aren't any of its flags");
+                RustParamConversion::Param {
+                    conversion: quote! {
+                        <#ty>::from_bits(#var.bits()).expect(#message)
+                    },
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromCppVectorToSlice => {
+                let elem = extract_cxx_vector_reference_item(self.cxxbridge_type())
+                    .expect("Not a vector reference");
+                RustParamConversion::Param {
+                    ty: parse_quote! { &[#elem] },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        #var.as_slice()
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromBytesToStr => RustParamConversion::Param {
+                ty: parse_quote! {
+                    ::core::result::Result<&str, ::core::str::Utf8Error>
+                },
+                local_variables: Vec::new(),
+                conversion: quote! {
+                    ::core::str::from_utf8(#var)
+                },
+                conversion_requires_unsafe: false,
+            },
+            // The call is made outside the unsafe block, so that its own
+            // unsafety, if any, isn't nested within ours.
+            RustConversionType::FromCharPointerToCStr => RustParamConversion::Param {
+                ty: parse_quote! { &::std::ffi::CStr },
+                local_variables: Vec::new(),
+                conversion: quote! {
+                    {
+                        let ptr = #var;
+                        assert!(!ptr.is_null(), "C++ returned a null string");
+                        // Safety: the function was listed in cstr_returns!,
+                        // which promises that the null-terminated string
+                        // outlives the reference it borrows from.
+                        unsafe { ::std::ffi::CStr::from_ptr(ptr) }
+                    }
+                },
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromCStrToCharPointer => RustParamConversion::Param {
+                ty: parse_quote! { &::core::ffi::CStr },
+                local_variables: Vec::new(),
+                conversion: quote! { #var.as_ptr() },
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromIntrusivePtrToPtr => {
+                let ty = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => elem.as_ref(),
+                    _ => panic!("Not a pointer"),
+                };
+                RustParamConversion::Param {
+                    ty: parse_quote! { autocxx::IntrusivePtr<#ty> },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        autocxx::IntrusivePtr::into_raw(#var)
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromPtrToIntrusivePtr => {
+                let ty = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => elem.as_ref(),
+                    _ => panic!("Not a pointer"),
+                };
+                RustParamConversion::Param {
+                    ty: parse_quote! { autocxx::IntrusivePtr<#ty> },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            let ptr = #var;
+                            // Safety: the C++ wrapper detached this pointer
+                            // from its intrusive pointer, so we now own the
+                            // reference which that held.
+                            unsafe { autocxx::IntrusivePtr::from_raw(ptr) }
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromBitmaskParamRejectingUnknownBits => {
+                let ty = self.converted_rust_type();
+                let name = match &ty {
+                    Type::Path(tp) => tp.path.segments.last().unwrap().ident.to_string(),
+                    _ => panic!("Bitmask enum should be a path"),
+                };
+                let message = format!(
+                    "{} is a {name} with bits which aren't any of its flags",
+                    var.to_token_stream()
+                );
+                RustParamConversion::Param {
+                    conversion: quote! {
+                        <#ty>::from_bits(#var.bits()).expect(#message)
+                    },
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion_requires_unsafe: false,
+                }
+            }
+            // The buffer was reborrowed for the call, so we can still ask
+            // for its length afterwards.
+            RustConversionType::FromBytesWrittenToResult { ref buffer } => {
+                RustParamConversion::Param {
+                    ty: parse_quote! {
+                        ::core::result::Result<usize, autocxx::BufferTooSmall>
+                    },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            let written = #var;
+                            if written <= #buffer.len() {
+                                ::core::result::Result::Ok(written)
+                            } else {
+                                ::core::result::Result::Err(
+                                    autocxx::BufferTooSmall { required: written }
+                                )
+                            }
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromStringMapToHashMap {
+                ref snapshot,
+                ref value,
+            } => RustParamConversion::Param {
+                ty: parse_quote! {
+                    ::std::collections::HashMap<::std::string::String, #value>
+                },
+                local_variables: Vec::new(),
+                conversion: quote! {
+                    {
+                        let map = #var;
+                        let mut keys = ::std::vec::Vec::new();
+                        let mut values = ::std::vec::Vec::new();
+                        cxxbridge::#snapshot(map.as_ref().unwrap(), &mut keys, &mut values);
+                        keys.into_iter()
+                            .zip(values)
+                            .collect::<::std::collections::HashMap<_, _>>()
+                    }
+                },
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromStringVectorToVec(policy) => {
+                let (element_ty, conversion) = cxx_string_to_string(&policy, quote! { s });
+                // Under the checked policy, the first invalid element makes
+                // the whole thing an error, rather than each element being
+                // a `Result` of its own.
+                let ty: Type = match policy {
+                    Utf8Policy::Checked => parse_quote! {
+                        ::core::result::Result<
+                            ::std::vec::Vec<::std::string::String>,
+                            ::core::str::Utf8Error,
+                        >
+                    },
+                    _ => parse_quote! { ::std::vec::Vec<#element_ty> },
+                };
+                RustParamConversion::Param {
+                    ty: ty.clone(),
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        #var.as_ref().unwrap().iter().map(|s| #conversion).collect::<#ty>()
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromByteArrayToSlice(len) => {
+                let is_mut = matches!(
+                    self.cxxbridge_type(),
+                    Type::Reference(TypeReference {
+                        mutability: Some(_),
+                        ..
+                    })
+                );
+                let (ty, conversion) = if is_mut {
+                    (
+                        parse_quote! { &mut [u8; #len] },
+                        quote! { #var.as_mut_slice() },
+                    )
+                } else {
+                    (parse_quote! { &[u8; #len] }, quote! { #var.as_slice() })
+                };
+                RustParamConversion::Param {
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion,
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromNullableUniquePtrToOption => {
+                let ty = self.cxxbridge_type();
+                RustParamConversion::Param {
+                    ty: parse_quote! { ::core::option::Option<#ty> },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            let ptr = #var;
+                            if ptr.is_null() {
+                                ::core::option::Option::None
+                            } else {
+                                ::core::option::Option::Some(ptr)
+                            }
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromSentinelToOption(sentinel) => {
+                let ty = self.cxxbridge_type();
+                let sentinel = proc_macro2::Literal::i128_unsuffixed(sentinel);
+                RustParamConversion::Param {
+                    ty: parse_quote! { ::core::option::Option<#ty> },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            let value = #var;
+                            if value == #sentinel {
+                                ::core::option::Option::None
+                            } else {
+                                ::core::option::Option::Some(value)
+                            }
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            // The pointer is taken before we enter the unsafe block, so
+            // that we don't nest one unsafe block inside another if the call
+            // itself needs unsafe.
+            RustConversionType::FromPtrToOptionalReference => {
+                let ty = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => elem.as_ref(),
+                    _ => panic!("Not a pointer"),
+                };
+                RustParamConversion::Param {
+                    ty: parse_quote! { ::core::option::Option<&#ty> },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            let ptr = #var;
+                            unsafe { ptr.as_ref() }
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromIteratorToMap {
+                ref new,
+                ref insert,
+                ref key,
+                ref value,
+            } => {
+                let map_var_name = make_ident(format!("map{}", *counter));
+                *counter += 1;
+                RustParamConversion::Param {
+                    ty: parse_quote! {
+                        impl ::core::iter::IntoIterator<Item = (#key, #value)>
+                    },
+                    local_variables: vec![
+                        MaybeUnsafeStmt::new(quote! {
+                            let mut #map_var_name = cxxbridge::#new();
+                        }),
+                        MaybeUnsafeStmt::new(quote! {
+                            for (key, value) in #var {
+                                cxxbridge::#insert(#map_var_name.pin_mut(), key, value);
+                            }
+                        }),
+                    ],
+                    conversion: quote! {
+                        #map_var_name.as_ref().unwrap()
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromCppOptionalToOption { ref has_value } => {
+                let ty = self.cxxbridge_type().clone();
+                RustParamConversion::Param {
+                    ty: parse_quote! { ::core::option::Option<#ty> },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            let value = #var;
+                            if #has_value {
+                                ::core::option::Option::Some(value)
+                            } else {
+                                ::core::option::Option::None
+                            }
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromUniquePtrToResult {
+                ref error,
+                ref error_ty,
+            } => {
+                let ty = self.cxxbridge_type().clone();
+                RustParamConversion::Param {
+                    ty: parse_quote! {
+                        ::core::result::Result<#ty, #error_ty>
+                    },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            let value = #var;
+                            if #error.is_null() {
+                                ::core::result::Result::Ok(value)
+                            } else {
+                                ::core::result::Result::Err(#error)
+                            }
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromClosureToCallbackFn(ref kind) => {
+                let error_params = if self.is_fallible_callback_conversion() {
+                    2
+                } else {
+                    0
+                };
+                let (inputs, output) = match self.cxxbridge_type() {
+                    Type::BareFn(TypeBareFn { inputs, output, .. }) => (
+                        inputs
+                            .iter()
+                            .skip(1)
+                            .take(inputs.len() - 1 - error_params)
+                            .map(|arg| &arg.ty)
+                            .collect::<Vec<_>>(),
+                        output,
+                    ),
+                    _ => panic!("Not a function pointer"),
+                };
+                let args = (0..inputs.len())
+                    .map(|i| make_ident(format!("arg{i}")))
+                    .collect::<Vec<_>>();
+                // The closure which Rust passes, what the trampoline calling
+                // it takes after its own arguments, and how it calls it.
+                let (closure, error_args, error_types, call) = match kind {
+                    // C++ only asks whether one element is less than
+                    // another. It requires the answers to form a strict weak
+                    // ordering, which they do if they come from a consistent
+                    // `Ordering`.
+                    CallbackKind::Comparator => (
+                        quote! {
+                            ::core::ops::FnMut(#(#inputs),*) -> ::core::cmp::Ordering
+                        },
+                        quote! {},
+                        quote! {},
+                        quote! {
+                            callback(#(#args),*) == ::core::cmp::Ordering::Less
+                        },
+                    ),
+                    // C++ may ask for zero bytes, but still expects a unique
+                    // pointer, which Rust allocators needn't give for a
+                    // zero-sized `Layout`. So we ask for at least one byte.
+                    // An alignment which isn't a power of two is a failed
+                    // allocation, as it would be for C++'s
+                    // `std::aligned_alloc`.
+                    CallbackKind::Allocator => {
+                        let message = format!(
+                            "{} returned memory which isn't aligned as requested",
+                            var.to_token_stream()
+                        );
+                        (
+                            quote! {
+                                ::core::ops::FnMut(::core::alloc::Layout) -> *mut u8
+                            },
+                            quote! {},
+                            quote! {},
+                            quote! {
+                                let layout = match ::core::alloc::Layout::from_size_align(arg0.max(1), arg1) {
+                                    ::core::result::Result::Ok(layout) => layout,
+                                    ::core::result::Result::Err(_) => return 0,
+                                };
+                                let ptr = callback(layout) as usize;
+                                debug_assert_eq!(ptr % layout.align(), 0, #message);
+                                ptr
+                            },
+                        )
+                    }
+                    // A fallible closure returns a `Result`, and the
+                    // trampoline tells C++ about any error, returning a
+                    // default value which C++ ignores.
+                    CallbackKind::Closure { fallible: true } => {
+                        let ok_type = match output {
+                            ReturnType::Default => quote! { () },
+                            ReturnType::Type(_, ty) => quote! { #ty },
+                        };
+                        (
+                            quote! {
+                                ::core::ops::FnMut(#(#inputs),*) -> ::core::result::Result<#ok_type, ::std::boxed::Box<dyn ::std::error::Error>>
+                            },
+                            quote! { , failed: &mut bool, message: &mut ::std::string::String },
+                            quote! { , &mut bool, &mut ::std::string::String },
+                            quote! {
+                                match callback(#(#args),*) {
+                                    Ok(result) => result,
+                                    Err(err) => {
+                                        *failed = true;
+                                        *message = err.to_string();
+                                        ::core::default::Default::default()
+                                    }
+                                }
+                            },
+                        )
+                    }
+                    CallbackKind::Closure { fallible: false } => (
+                        quote! { ::core::ops::FnMut(#(#inputs),*) #output },
+                        quote! {},
+                        quote! {},
+                        quote! { callback(#(#args),*) },
+                    ),
+                };
+                RustParamConversion::Param {
+                    ty: parse_quote! { impl #closure },
+                    local_variables: vec![
+                        MaybeUnsafeStmt::new(quote! {
+                            fn callback_trampoline<F>(context: usize #(, #args: #inputs)* #error_args) #output
+                            where
+                                F: #closure,
+                            {
+                                // Safety: the context is the closure, which
+                                // outlives the call to C++, and the caller
+                                // promised that C++ doesn't call the
+                                // callback after that.
+                                let callback = unsafe { &mut *(context as *mut F) };
+                                #call
+                            }
+                        }),
+                        MaybeUnsafeStmt::new(quote! {
+                            fn callback_fn<F>(callback: &mut F) -> (fn(usize #(, #inputs)* #error_types) #output, usize)
+                            where
+                                F: #closure,
+                            {
+                                (callback_trampoline::<F>, callback as *mut F as usize)
+                            }
+                        }),
+                        MaybeUnsafeStmt::new(quote! {
+                            let mut #var = #var;
+                        }),
+                    ],
+                    conversion: quote! { #var },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::CallbackContext { ref callback } => RustParamConversion::OutParam {
+                local_variable: quote! {
+                    let (#callback, #var) = callback_fn(&mut #callback);
+                },
+                conversion: quote! { #var },
+                result: None,
+            },
+            RustConversionType::FromI8ToOptionalBool => RustParamConversion::Param {
+                ty: parse_quote! { ::core::option::Option<bool> },
+                local_variables: Vec::new(),
+                conversion: quote! {
+                    match #var {
+                        0 => ::core::option::Option::Some(false),
+                        1 => ::core::option::Option::Some(true),
+                        _ => ::core::option::Option::None,
+                    }
+                },
+                conversion_requires_unsafe: false,
+            },
+            // A negative, infinite or NaN count of seconds, or one too large
+            // for a `Duration`, is an error rather than a panic.
+            RustConversionType::FromSecondsToDuration => RustParamConversion::Param {
+                ty: parse_quote! {
+                    ::core::result::Result<::std::time::Duration, ::std::time::TryFromFloatSecsError>
+                },
+                local_variables: Vec::new(),
+                conversion: quote! {
+                    ::std::time::Duration::try_from_secs_f64(#var)
+                },
+                conversion_requires_unsafe: false,
+            },
         }
     }
 }
+
+/// How a `CxxString` from C++ becomes a Rust string under each
+/// [`Utf8Policy`].
+fn cxx_string_to_string(policy: &Utf8Policy, var: TokenStream) -> (Type, TokenStream) {
+    match policy {
+        Utf8Policy::Checked => (
+            parse_quote! {
+                ::core::result::Result<::std::string::String, ::core::str::Utf8Error>
+            },
+            quote! {
+                (#var).to_str().map(|s| s.to_owned())
+            },
+        ),
+        Utf8Policy::Lossy => (
+            parse_quote! { ::std::string::String },
+            quote! {
+                (#var).to_string_lossy().into_owned()
+            },
+        ),
+        // The bytes are copied before we enter the unsafe block, so
+        // that we don't nest one unsafe block inside another if the
+        // call itself needs unsafe.
+        Utf8Policy::AssumeValid => (
+            parse_quote! { ::std::string::String },
+            quote! {
+                {
+                    let bytes = (#var).as_bytes().to_vec();
+                    unsafe { ::std::string::String::from_utf8_unchecked(bytes) }
+                }
+            },
+        ),
+    }
+}
@@ -16,7 +16,7 @@ use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 use std::borrow::Cow;
 use syn::{
-    parse_quote, punctuated::Punctuated, token::Comma, FnArg, GenericArgument, PatType, Path,
+    parse_quote, punctuated::Punctuated, token::Comma, FnArg, GenericArgument, Pat, PatType, Path,
     PathSegment, ReturnType, Type, TypePath, TypeReference,
 };
 
@@ -31,11 +31,17 @@ use syn::{
 ///    built-in type
 /// 3) Any parameter is any form of reference, and we're returning an `impl New`
 ///    3a) an 'impl ValueParam' counts as a reference.
+/// 4) where a `borrow_from_receiver!` method returns a reference, which
+///    borrows from the receiver alone, whatever other references it takes.
+///
+/// In case 4, unless case 2 or 3 also applies, only the receiver shares the
+/// lifetime of the return value.
 pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     param_details: &[ArgumentAnalysis],
     mut params: Punctuated<FnArg, Comma>,
     ret_type: Cow<'r, ReturnType>,
     non_pod_types: &HashSet<QualifiedName>,
+    borrows_from_receiver: bool,
 ) -> (
     Option<TokenStream>,
     Punctuated<FnArg, Comma>,
@@ -58,9 +64,14 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     let ret_type_pod = return_type_is_pod_or_known_type_reference(&ret_type, non_pod_types);
     let returning_impl_with_a_reference_param = return_type_is_impl && any_param_is_reference;
     let hits_1024_bug = non_pod_ref_param && ret_type_pod;
-    if !(has_mutable_receiver || hits_1024_bug || returning_impl_with_a_reference_param) {
+    if !(has_mutable_receiver
+        || hits_1024_bug
+        || returning_impl_with_a_reference_param
+        || borrows_from_receiver)
+    {
         return (None, params, ret_type);
     }
+    let only_receiver = borrows_from_receiver && !return_type_is_impl && !hits_1024_bug;
     let new_return_type = match ret_type.as_ref() {
         ReturnType::Type(rarrow, boxed_type) => match boxed_type.as_ref() {
             Type::Reference(rtr) => {
@@ -91,9 +102,12 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     match new_return_type {
         None => (None, params, ret_type),
         Some(new_return_type) => {
-            for FnArg::Typed(PatType { ty, .. }) | FnArg::Receiver(syn::Receiver { ty, .. }) in
-                params.iter_mut()
-            {
+            for param in params.iter_mut() {
+                if only_receiver && !is_receiver(param) {
+                    continue;
+                }
+                let (FnArg::Typed(PatType { ty, .. }) | FnArg::Receiver(syn::Receiver { ty, .. })) =
+                    param;
                 match ty.as_mut() {
                     Type::Path(TypePath {
                         path: Path { segments, .. },
@@ -110,6 +124,17 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     }
 }
 
+/// Whether this is the receiver, either of a method or of the free function
+/// in the bridge which wraps it.
+fn is_receiver(param: &FnArg) -> bool {
+    match param {
+        FnArg::Receiver(_) => true,
+        FnArg::Typed(PatType { pat, .. }) => {
+            matches!(pat.as_ref(), Pat::Ident(pi) if pi.ident == "self" || pi.ident == "autocxx_gen_this")
+        }
+    }
+}
+
 fn reference_parameter_is_non_pod_reference(
     params: &Punctuated<FnArg, Comma>,
     non_pod_types: &HashSet<QualifiedName>,
@@ -140,6 +165,7 @@ fn return_type_is_pod_or_known_type_reference(
                     let qn = QualifiedName::from_type_path(typ);
                     !non_pod_types.contains(&qn)
                 }
+                Type::Slice(_) => true,
                 _ => false,
             },
             _ => false,
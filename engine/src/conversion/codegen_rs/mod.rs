@@ -6,18 +6,23 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod bitmask_enum;
 mod fun_codegen;
 mod function_wrapper_rs;
 mod impl_item_creator;
 mod lifetime;
 mod namespace_organizer;
 mod non_pod_struct;
+mod tag_dispatch;
 pub(crate) mod unqualify;
 
 use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 
-use autocxx_parser::{ExternCppType, IncludeCppConfig, RustFun, UnsafePolicy};
+use autocxx_parser::{
+    CompoundAssign, DerefTarget, ExternCppType, IncludeCppConfig, IncludedHeader, RustFun,
+    Subscript, UnsafePolicy,
+};
 
 use itertools::Itertools;
 use proc_macro2::{Span, TokenStream};
@@ -30,11 +35,12 @@ use syn::{
 use crate::{
     conversion::codegen_rs::{
         non_pod_struct::{make_non_pod, new_non_pod_struct},
-        unqualify::{unqualify_params, unqualify_ret_type},
+        unqualify::{unqualify_params, unqualify_ret_type, unqualify_type_path},
     },
     minisyn::minisynize_punctuated,
     types::{make_ident, Namespace, QualifiedName},
 };
+use bitmask_enum::{generate_bitmask_enum, underlying_type};
 use impl_item_creator::create_impl_items;
 
 use self::{
@@ -44,8 +50,15 @@ use self::{
 
 use super::{
     analysis::{
-        fun::{FnPhase, PodAndDepAnalysis, ReceiverMutability},
+        comparisons::{COMPARE_METHOD_NAME, EQ_METHOD_NAME, PARTIAL_COMPARE_METHOD_NAME},
+        compound_assign::{compound_assign_method_name, compound_assign_rhs_by_value},
+        deref::{DEREF_METHOD_NAME, DEREF_MUT_METHOD_NAME},
+        display::TO_STRING_METHOD_NAME,
+        enum_display::DISPLAY_NAME_METHOD_NAME,
+        fun::{find_map_helpers, FnKind, FnPhase, PodAndDepAnalysis, ReceiverMutability},
+        intrusive_ptr::{ADD_REF_METHOD_NAME, RELEASE_METHOD_NAME},
         pod::PodAnalysis,
+        subscript::INDEX_METHOD_NAME,
     },
     api::{AnalysisPhase, Api, SubclassName, TypeKind, TypedefKind},
     convert_error::ErrorContextType,
@@ -55,6 +68,10 @@ use super::{
     api::{Layout, Provenance, RustSubclassFnDetails, SuperclassMethod, TraitImplSignature},
     apivec::ApiVec,
     codegen_cpp::type_to_cpp::CppNameMap,
+    type_helpers::{
+        extract_map_key_and_value, extract_string_map_value, extract_unique_ptr_vector_item,
+        extract_unordered_map_key_and_value, is_std_string, map_snapshot_value_type,
+    },
 };
 use super::{convert_error::ErrorContext, ConvertErrorFromCpp};
 use quote::quote;
@@ -92,6 +109,66 @@ enum Use {
     Custom(Box<Item>),
 }
 
+/// The same as [get_string_items], for `std::wstring`. The conversion from
+/// UTF-8 can't fail, since Rust strings are always valid UTF-8. Each code
+/// point becomes one `wchar_t`, or a surrogate pair where `wchar_t` is 16
+/// bits wide.
+fn get_wstring_items(to_string_name: &Ident) -> Vec<Item> {
+    [
+        Item::Impl(parse_quote! {
+            impl CxxWString {
+                /// Transcodes this string to UTF-8. Any sequence which isn't
+                /// valid UTF-16 or UTF-32, as appropriate for the platform,
+                /// becomes U+FFFD REPLACEMENT CHARACTER.
+                pub fn to_string_lossy(&self) -> String {
+                    cxxbridge::#to_string_name(self)
+                }
+            }
+        }),
+        Item::Trait(parse_quote! {
+            pub trait ToCppWString {
+                fn into_cpp_wstring(self) -> cxx::UniquePtr<CxxWString>;
+            }
+        }),
+        Item::Impl(parse_quote! {
+            impl ToCppWString for &str {
+                fn into_cpp_wstring(self) -> cxx::UniquePtr<CxxWString> {
+                    make_wstring(self)
+                }
+            }
+        }),
+        Item::Impl(parse_quote! {
+            impl ToCppWString for String {
+                fn into_cpp_wstring(self) -> cxx::UniquePtr<CxxWString> {
+                    make_wstring(&self)
+                }
+            }
+        }),
+        Item::Impl(parse_quote! {
+            impl ToCppWString for &String {
+                fn into_cpp_wstring(self) -> cxx::UniquePtr<CxxWString> {
+                    make_wstring(self)
+                }
+            }
+        }),
+        Item::Impl(parse_quote! {
+            impl ToCppWString for ::std::borrow::Cow<'_, str> {
+                fn into_cpp_wstring(self) -> cxx::UniquePtr<CxxWString> {
+                    make_wstring(&self)
+                }
+            }
+        }),
+        Item::Impl(parse_quote! {
+            impl ToCppWString for cxx::UniquePtr<CxxWString> {
+                fn into_cpp_wstring(self) -> cxx::UniquePtr<CxxWString> {
+                    self
+                }
+            }
+        }),
+    ]
+    .to_vec()
+}
+
 fn get_string_items() -> Vec<Item> {
     [
         Item::Trait(parse_quote! {
@@ -123,6 +200,23 @@ fn get_string_items() -> Vec<Item> {
                 }
             }
         }),
+        // A borrowed or owned string alike has to be copied into a fresh
+        // std::string. A std::string_view parameter takes a &str instead,
+        // which a &Cow<str> of either variant derefs to without copying.
+        Item::Impl(parse_quote! {
+            impl ToCppString for ::std::borrow::Cow<'_, str> {
+                fn into_cpp(self) -> cxx::UniquePtr<cxx::CxxString> {
+                    make_string(&self)
+                }
+            }
+        }),
+        Item::Impl(parse_quote! {
+            impl ToCppString for &::std::borrow::Cow<'_, str> {
+                fn into_cpp(self) -> cxx::UniquePtr<cxx::CxxString> {
+                    make_string(self)
+                }
+            }
+        }),
         Item::Impl(parse_quote! {
             impl ToCppString for cxx::UniquePtr<cxx::CxxString> {
                 fn into_cpp(self) -> cxx::UniquePtr<cxx::CxxString> {
@@ -139,7 +233,7 @@ fn get_string_items() -> Vec<Item> {
 /// existing lumps of code within the Api structures.
 pub(crate) struct RsCodeGenerator<'a> {
     unsafe_policy: &'a UnsafePolicy,
-    include_list: &'a [String],
+    include_list: &'a [IncludedHeader],
     bindgen_mod: ItemMod,
     original_name_map: CppNameMap,
     config: &'a IncludeCppConfig,
@@ -151,7 +245,7 @@ impl<'a> RsCodeGenerator<'a> {
     pub(crate) fn generate_rs_code(
         all_apis: ApiVec<FnPhase>,
         unsafe_policy: &'a UnsafePolicy,
-        include_list: &'a [String],
+        include_list: &'a [IncludedHeader],
         bindgen_mod: ItemMod,
         config: &'a IncludeCppConfig,
         header_name: Option<String>,
@@ -175,6 +269,8 @@ impl<'a> RsCodeGenerator<'a> {
         let subclasses_with_a_single_trivial_constructor =
             find_trivially_constructed_subclasses(&all_apis);
         let non_pod_types = find_non_pod_types(&all_apis);
+        let tag_dispatchers = self.find_tag_dispatchers(&all_apis);
+        let map_helpers = find_map_helpers(&all_apis);
         // Now let's generate the Rust code.
         let (rs_codegen_results_and_namespaces, additional_cpp_needs): (Vec<_>, Vec<_>) = all_apis
             .into_iter()
@@ -186,10 +282,20 @@ impl<'a> RsCodeGenerator<'a> {
                     &methods_by_superclass,
                     &subclasses_with_a_single_trivial_constructor,
                     &non_pod_types,
+                    &map_helpers,
                 );
                 ((name, gen), more_cpp_needed)
             })
             .unzip();
+        // Each tag-dispatched overload set also needs a single function which
+        // brings its overloads together.
+        let rs_codegen_results_and_namespaces: Vec<_> = rs_codegen_results_and_namespaces
+            .into_iter()
+            .chain(tag_dispatchers.into_iter().map(|name| {
+                let dispatcher = tag_dispatch::generate_dispatcher(name.get_final_item());
+                (name, dispatcher)
+            }))
+            .collect();
         // First, the hierarchy of mods containing lots of 'use' statements
         // which is the final API exposed as 'ffi'.
         let mut use_statements =
@@ -270,6 +376,30 @@ impl<'a> RsCodeGenerator<'a> {
         all_items
     }
 
+    /// Finds the overload sets for which `tag_dispatch!` was requested and at
+    /// least one overload survived, each of which needs a single dispatching
+    /// function.
+    fn find_tag_dispatchers(&self, apis: &ApiVec<FnPhase>) -> HashSet<QualifiedName> {
+        apis.iter()
+            .filter_map(|api| match api {
+                Api::Function { name, analysis, .. }
+                    if matches!(analysis.kind, FnKind::Function)
+                        && analysis.ignore_reason.is_ok()
+                        && analysis.externally_callable =>
+                {
+                    let cpp_name = QualifiedName::new(
+                        name.name.get_namespace(),
+                        make_ident(api.effective_cpp_name()),
+                    );
+                    self.config
+                        .is_tag_dispatched(&cpp_name.to_cpp_name())
+                        .then_some(cpp_name)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     fn accumulate_superclass_methods(
         &self,
         apis: &ApiVec<FnPhase>,
@@ -301,7 +431,10 @@ impl<'a> RsCodeGenerator<'a> {
 
     fn build_include_foreign_items(&self, has_additional_cpp_needs: bool) -> Vec<ForeignItem> {
         let extra_inclusion = if has_additional_cpp_needs {
-            Some(self.header_name.clone().unwrap())
+            Some(IncludedHeader {
+                path: self.header_name.clone().unwrap(),
+                system: false,
+            })
         } else {
             None
         };
@@ -470,6 +603,7 @@ impl<'a> RsCodeGenerator<'a> {
         associated_methods: &HashMap<QualifiedName, Vec<SuperclassMethod>>,
         subclasses_with_a_single_trivial_constructor: &HashSet<QualifiedName>,
         non_pod_types: &HashSet<QualifiedName>,
+        map_helpers: &HashSet<String>,
     ) -> RsCodegenResult {
         let name = api.name().clone();
         let id = name.get_final_ident();
@@ -488,6 +622,46 @@ impl<'a> RsCodeGenerator<'a> {
                     ..Default::default()
                 }
             }
+            Api::WStringConstructor { .. } => {
+                let make_wstring_name = make_ident(self.config.get_makewstring_name());
+                let to_string_name = make_ident(self.config.get_wstring_to_string_name());
+                let alias = self.config.get_wstring_alias_name();
+                RsCodegenResult {
+                    extern_c_mod_items: vec![
+                        ForeignItem::Verbatim(quote! {
+                            /// A C++ `std::wstring`.
+                            #[cxx_name = #alias]
+                            type CxxWString;
+                        }),
+                        ForeignItem::Fn(parse_quote!(
+                            fn #make_wstring_name(str_: &str) -> UniquePtr<CxxWString>;
+                        )),
+                        ForeignItem::Fn(parse_quote!(
+                            fn #to_string_name(str_: &CxxWString) -> String;
+                        )),
+                    ],
+                    global_items: get_wstring_items(&to_string_name),
+                    materializations: vec![
+                        Use::UsedFromCxxBridgeWithAlias(make_ident("make_wstring").into()),
+                        Use::Custom(Box::new(parse_quote! {
+                            pub use cxxbridge::CxxWString;
+                        })),
+                    ],
+                    ..Default::default()
+                }
+            }
+            Api::ContainerIterator {
+                container,
+                item,
+                mutable,
+                ..
+            } => self.generate_container_iterator(id.into(), &container, &item, mutable),
+            Api::ConstantAccessor {
+                enum_type,
+                underlying_type,
+                variants,
+                ..
+            } => self.generate_constant_accessor(&name, &enum_type, &underlying_type, &variants),
             Api::Function { fun, analysis, .. } => gen_function(
                 name.get_namespace(),
                 *fun,
@@ -524,43 +698,192 @@ impl<'a> RsCodeGenerator<'a> {
             } => {
                 let doc_attrs = get_doc_attrs(&details.item.attrs);
                 let layout = details.layout.clone();
-                self.generate_type(
+                let mut result = self.generate_type(
                     &name,
-                    id,
+                    id.clone(),
                     kind,
                     constructors.move_constructor,
                     constructors.destructor,
                     || Some((Item::Struct(details.item.into()), doc_attrs)),
                     associated_methods,
-                    layout,
+                    layout.clone(),
                     is_generic,
-                )
+                );
+                if let Some(layout) =
+                    layout.filter(|_| self.config.is_layout_type(&name.to_cpp_name()))
+                {
+                    Self::add_layout_consts(&mut result, &id, &layout);
+                }
+                if self.config.is_ordered(&name.to_cpp_name()) {
+                    Self::add_ord_impls(&mut result, id.clone().into());
+                }
+                if self.config.is_partial_ord(&name.to_cpp_name()) {
+                    Self::add_partial_ord_impl(&mut result, id.clone().into());
+                }
+                if self.config.is_partial_eq(&name.to_cpp_name()) {
+                    Self::add_partial_eq_impl(&mut result, id.clone().into());
+                }
+                if self.config.is_display(&name.to_cpp_name()) {
+                    Self::add_display_impl(&mut result, id.clone().into());
+                }
+                for ca in self
+                    .config
+                    .compound_assigns
+                    .iter()
+                    .filter(|ca| ca.ty == name.to_cpp_name())
+                {
+                    Self::add_compound_assign_impl(
+                        &mut result,
+                        id.clone().into(),
+                        ca,
+                        matches!(kind, TypeKind::Pod),
+                    );
+                }
+                for subscript in self
+                    .config
+                    .subscripts
+                    .iter()
+                    .filter(|s| s.ty == name.to_cpp_name())
+                {
+                    Self::add_index_impl(&mut result, id.clone().into(), subscript);
+                }
+                for deref in self
+                    .config
+                    .deref_targets
+                    .iter()
+                    .filter(|d| d.ty == name.to_cpp_name())
+                {
+                    let target_is_pod =
+                        !non_pod_types.contains(&QualifiedName::new_from_cpp_name(&deref.target));
+                    Self::add_deref_impls(
+                        &mut result,
+                        id.clone().into(),
+                        deref,
+                        matches!(kind, TypeKind::Pod) && target_is_pod,
+                    );
+                }
+                if self.config.get_intrusive_ptr(&name.to_cpp_name()).is_some() {
+                    Self::add_intrusive_ptr_target_impl(&mut result, id.clone().into());
+                }
+                result
             }
             Api::Enum { item, .. } => {
                 let doc_attrs = get_doc_attrs(&item.attrs);
-                self.generate_type(
+                let is_bitmask = self.config.is_bitmask_enum(&name.to_cpp_name());
+                let repr = underlying_type(&item.attrs);
+                // The value as a number, for `Display` to fall back on. Enums
+                // are `Clone` but not `Copy`, so we mustn't move out of `*self`.
+                let value_expr: Expr = if is_bitmask {
+                    parse_quote! { self.0 }
+                } else {
+                    parse_quote! { self.clone() as #repr }
+                };
+                let variants: Vec<Ident> = item
+                    .variants
+                    .iter()
+                    .map(|variant| variant.ident.clone())
+                    .collect();
+                let (item, bitmask_items) = if is_bitmask {
+                    generate_bitmask_enum(item.into())
+                } else {
+                    (Item::Enum(item.into()), Vec::new())
+                };
+                let mut result = self.generate_type(
                     &name,
-                    id,
+                    id.clone(),
                     TypeKind::Pod,
                     true,
                     true,
-                    || Some((Item::Enum(item.into()), doc_attrs)),
+                    || Some((item, doc_attrs)),
                     associated_methods,
                     None,
                     false,
-                )
+                );
+                if !is_bitmask {
+                    Self::add_enum_conversions(&mut result, id.clone().into(), &repr, &variants);
+                }
+                result.bindgen_mod_items.extend(bitmask_items);
+                if self
+                    .config
+                    .get_enum_display_function(&name.to_cpp_name())
+                    .is_some()
+                {
+                    Self::add_enum_display_impl(&mut result, id.into(), &value_expr);
+                }
+                result
+            }
+            Api::ConcreteType { rs_definition, .. } => {
+                let mut result = self.generate_type(
+                    &name,
+                    id.clone(),
+                    TypeKind::Abstract,
+                    false, // assume for now that these types can't be kept in a Vector
+                    true,  // assume for now that these types can be put in a smart pointer
+                    || None,
+                    associated_methods,
+                    None,
+                    false,
+                );
+                if let Some(item) = rs_definition.as_ref().and_then(|ty| match &***ty {
+                    Type::Path(tp) => extract_unique_ptr_vector_item(tp),
+                    _ => None,
+                }) {
+                    self.add_unique_ptr_vector_accessors(&mut result, id.clone().into(), item);
+                }
+                if let Some(value) = rs_definition
+                    .as_ref()
+                    .and_then(|ty| match &***ty {
+                        Type::Path(tp) => extract_string_map_value(tp),
+                        _ => None,
+                    })
+                    .and_then(map_snapshot_value_type)
+                    .filter(|_| {
+                        map_helpers.contains(&self.config.get_map_snapshot_name(&id.to_string()))
+                    })
+                {
+                    let snapshot = make_ident(self.config.get_map_snapshot_name(&id.to_string()));
+                    result.extern_c_mod_items.push(parse_quote! {
+                        fn #snapshot(m: &#id, keys: &mut Vec<String>, values: &mut Vec<#value>);
+                    });
+                }
+                if let Some((key, value)) = rs_definition
+                    .as_ref()
+                    .and_then(|ty| match &***ty {
+                        Type::Path(tp) => extract_map_key_and_value(tp)
+                            .or_else(|| extract_unordered_map_key_and_value(tp)),
+                        _ => None,
+                    })
+                    .and_then(|(key, value)| {
+                        Some((
+                            map_snapshot_value_type(key)?,
+                            map_snapshot_value_type(value)?,
+                        ))
+                    })
+                    .filter(|_| {
+                        map_helpers
+                            .contains(&self.config.get_map_builder_name(&id.to_string(), "new"))
+                    })
+                {
+                    let [new_fn, insert_fn] = ["new", "insert"].map(|builder| {
+                        make_ident(self.config.get_map_builder_name(&id.to_string(), builder))
+                    });
+                    result.extern_c_mod_items.extend([
+                        parse_quote! {
+                            fn #new_fn() -> UniquePtr<#id>;
+                        },
+                        parse_quote! {
+                            fn #insert_fn(m: Pin<&mut #id>, key: #key, value: #value);
+                        },
+                    ]);
+                }
+                if let Some((key, value)) = rs_definition.as_ref().and_then(|ty| match &***ty {
+                    Type::Path(tp) => extract_unordered_map_key_and_value(tp),
+                    _ => None,
+                }) {
+                    self.add_unordered_map_accessors(&mut result, id.clone().into(), key, value);
+                }
+                result
             }
-            Api::ConcreteType { .. } => self.generate_type(
-                &name,
-                id,
-                TypeKind::Abstract,
-                false, // assume for now that these types can't be kept in a Vector
-                true,  // assume for now that these types can be put in a smart pointer
-                || None,
-                associated_methods,
-                None,
-                false,
-            ),
             Api::ForwardDeclaration { .. } | Api::OpaqueTypedef { .. } => self.generate_type(
                 &name,
                 id,
@@ -658,6 +981,8 @@ impl<'a> RsCodeGenerator<'a> {
         let super_path = superclass.to_type_path();
         let super_cxxxbridge_id = superclass.get_final_ident();
         let id = sub.id();
+        let is_dyn = self.config.is_dyn_subclass(&id.to_string());
+        let rust_type = sub.rust_type_path(is_dyn);
         let holder = sub.holder();
         let full_cpp = sub.cpp();
         let cpp_path = full_cpp.to_type_path();
@@ -667,12 +992,15 @@ impl<'a> RsCodeGenerator<'a> {
             pub use bindgen::root::#holder;
         });
         let relinquish_ownership_call = sub.cpp_remove_ownership();
+        let mut materializations = vec![Use::Custom(Box::new(parse_quote! {
+            pub use cxxbridge::#cpp_id;
+        }))];
         let mut bindgen_mod_items = vec![
             parse_quote! {
                 pub use cxxbridge::#cpp_id;
             },
             parse_quote! {
-                pub struct #holder(pub autocxx::subclass::CppSubclassRustPeerHolder<super::super::super::#id>);
+                pub struct #holder(pub autocxx::subclass::CppSubclassRustPeerHolder<#rust_type>);
             },
             parse_quote! {
                 impl autocxx::subclass::CppSubclassCppPeer for #cpp_id {
@@ -718,7 +1046,7 @@ impl<'a> RsCodeGenerator<'a> {
             if !methods_impls.is_empty() {
                 bindgen_mod_items.push(parse_quote! {
                     #[allow(non_snake_case)]
-                    impl #supers for super::super::super::#id {
+                    impl #supers for #rust_type {
                         #(#methods_impls)*
                     }
                 });
@@ -726,7 +1054,7 @@ impl<'a> RsCodeGenerator<'a> {
         }
         if generate_peer_constructor {
             bindgen_mod_items.push(parse_quote! {
-                impl autocxx::subclass::CppPeerConstructor<#cpp_id> for super::super::super::#id {
+                impl autocxx::subclass::CppPeerConstructor<#cpp_id> for #rust_type {
                     fn make_peer(&mut self, peer_holder: autocxx::subclass::CppSubclassRustPeerHolder<Self>) -> cxx::UniquePtr<#cpp_path> {
                         use autocxx::moveit::Emplace;
                         cxx::UniquePtr::emplace(#cpp_id :: new(peer_holder))
@@ -735,6 +1063,13 @@ impl<'a> RsCodeGenerator<'a> {
             })
         };
 
+        if is_dyn {
+            bindgen_mod_items.extend(Self::generate_dyn_subclass_struct(
+                &sub, superclass, methods,
+            ));
+            materializations.push(Use::SpecificNameFromBindgen(id.clone().into()));
+        }
+
         // Once for each superclass, in future...
         let as_id = make_ident(format!("As_{super_name}"));
         extern_c_mod_items.push(parse_quote! {
@@ -749,7 +1084,7 @@ impl<'a> RsCodeGenerator<'a> {
             fn #as_unique_ptr_id(u: UniquePtr<#cpp_id>) -> UniquePtr<#super_cxxxbridge_id>;
         });
         bindgen_mod_items.push(parse_quote! {
-            impl AsRef<#super_path> for super::super::super::#id {
+            impl AsRef<#super_path> for #rust_type {
                 fn as_ref(&self) -> &cxxbridge::#super_cxxxbridge_id {
                     use autocxx::subclass::CppSubclass;
                     self.peer().#as_id()
@@ -758,7 +1093,7 @@ impl<'a> RsCodeGenerator<'a> {
         });
         // TODO it would be nice to impl AsMut here but pin prevents us
         bindgen_mod_items.push(parse_quote! {
-            impl super::super::super::#id {
+            impl #rust_type {
                 pub fn pin_mut(&mut self) -> ::core::pin::Pin<&mut cxxbridge::#super_cxxxbridge_id> {
                     use autocxx::subclass::CppSubclass;
                     self.peer_mut().#as_mut_id()
@@ -767,7 +1102,7 @@ impl<'a> RsCodeGenerator<'a> {
         });
         let rs_as_unique_ptr_id = make_ident(format!("as_{super_name}_unique_ptr"));
         bindgen_mod_items.push(parse_quote! {
-            impl super::super::super::#id {
+            impl #rust_type {
                 pub fn #rs_as_unique_ptr_id(u: cxx::UniquePtr<#cpp_id>) -> cxx::UniquePtr<cxxbridge::#super_cxxxbridge_id> {
                     cxxbridge::#as_unique_ptr_id(u)
                 }
@@ -787,9 +1122,7 @@ impl<'a> RsCodeGenerator<'a> {
             // That's the reason for the 'false' and 'true'
             bridge_items: create_impl_items(&cpp_id, false, true, self.config),
             bindgen_mod_items,
-            materializations: vec![Use::Custom(Box::new(parse_quote! {
-                pub use cxxbridge::#cpp_id;
-            }))],
+            materializations,
             global_items,
             extern_rust_mod_items: vec![
                 parse_quote! {
@@ -803,6 +1136,92 @@ impl<'a> RsCodeGenerator<'a> {
         }
     }
 
+    /// For `dyn_subclass!`, generates the Rust struct which the user would
+    /// otherwise have written for a subclass. It owns a boxed implementation
+    /// of the superclass's methods trait, to which it forwards each pure
+    /// virtual method; other virtual methods keep calling the superclass.
+    fn generate_dyn_subclass_struct(
+        sub: &SubclassName,
+        superclass: &QualifiedName,
+        methods: Option<&Vec<SuperclassMethod>>,
+    ) -> Vec<Item> {
+        let id = sub.id();
+        let cpp_id = sub.cpp().get_final_ident();
+        let super_name = superclass.get_final_item();
+        let super_cxxbridge_id = superclass.get_final_ident();
+        let methods_trait = SubclassName::get_methods_trait_name(superclass).to_type_path();
+        let rs_as_unique_ptr_id = make_ident(format!("as_{super_name}_unique_ptr"));
+        let forwarders = methods
+            .into_iter()
+            .flatten()
+            .filter(|m| m.is_pure_virtual)
+            .map(|m| {
+                let method_name = &m.name;
+                let mut params = m.params.clone();
+                *(params.iter_mut().next().unwrap()) = match m.receiver_mutability {
+                    ReceiverMutability::Const => parse_quote!(&self),
+                    ReceiverMutability::Mutable => parse_quote!(&mut self),
+                };
+                let param_names = m.param_names.iter().skip(1);
+                let ret = &m.ret_type;
+                let unsafe_token = m.requires_unsafe.wrapper_token();
+                quote! {
+                    #unsafe_token fn #method_name(#params) #ret {
+                        self.inner.#method_name(#(#param_names),*)
+                    }
+                }
+            });
+        let struct_doc = format!(
+            "A C++ `{super_name}` whose pure virtual methods are implemented by a boxed \
+            [`{}`].",
+            SubclassName::get_methods_trait_name(superclass).get_final_item()
+        );
+        let new_doc = format!(
+            "Creates a C++ `{super_name}` which forwards calls to `inner`. C++ owns the \
+            result: once C++ deletes it, `inner` is dropped too. `{super_name}` must \
+            therefore have a virtual destructor."
+        );
+        vec![
+            parse_quote! {
+                #[doc = #struct_doc]
+                pub struct #id {
+                    cpp_peer: autocxx::subclass::CppSubclassCppPeerHolder<#cpp_id>,
+                    inner: ::std::boxed::Box<dyn #methods_trait>,
+                }
+            },
+            parse_quote! {
+                impl autocxx::subclass::CppSubclass<#cpp_id> for #id {
+                    fn peer_holder(&self) -> &autocxx::subclass::CppSubclassCppPeerHolder<#cpp_id> {
+                        &self.cpp_peer
+                    }
+                    fn peer_holder_mut(&mut self) -> &mut autocxx::subclass::CppSubclassCppPeerHolder<#cpp_id> {
+                        &mut self.cpp_peer
+                    }
+                }
+            },
+            parse_quote! {
+                #[allow(non_snake_case)]
+                impl #methods_trait for #id {
+                    #(#forwarders)*
+                }
+            },
+            parse_quote! {
+                impl #id {
+                    #[doc = #new_doc]
+                    pub fn new_from_box(
+                        inner: ::std::boxed::Box<dyn #methods_trait>,
+                    ) -> cxx::UniquePtr<cxxbridge::#super_cxxbridge_id> {
+                        use autocxx::subclass::CppSubclass;
+                        Self::#rs_as_unique_ptr_id(Self::new_cpp_owned(Self {
+                            cpp_peer: Default::default(),
+                            inner,
+                        }))
+                    }
+                }
+            },
+        ]
+    }
+
     fn generate_subclass_fn(
         api_name: Ident,
         details: RustSubclassFnDetails,
@@ -1135,6 +1554,660 @@ impl<'a> RsCodeGenerator<'a> {
         })
     }
 
+    /// Adds constants giving the size and alignment of a type listed in
+    /// `expose_layout!`. These come from bindgen's idea of the layout,
+    /// which the C++ generated for the type's `cpp_size` and `cpp_align`
+    /// methods checks is right.
+    fn add_layout_consts(result: &mut RsCodegenResult, id: &Ident, layout: &Layout) {
+        let size = layout.size;
+        let align = layout.align;
+        result.bindgen_mod_items.push(parse_quote! {
+            impl #id {
+                /// The size in bytes of this type, according to C++ `sizeof`.
+                pub const CPP_SIZE: usize = #size;
+                /// The alignment in bytes of this type, according to C++
+                /// `alignof`.
+                pub const CPP_ALIGN: usize = #align;
+            }
+        });
+    }
+
+    /// Implements `Ord` and its supertraits for a type listed in `ord!`,
+    /// all in terms of the synthesized three-way comparison method so that
+    /// they can't disagree with one another.
+    fn add_ord_impls(result: &mut RsCodegenResult, id: Ident) {
+        let compare = make_ident(COMPARE_METHOD_NAME);
+        result.bindgen_mod_items.extend([
+            parse_quote! {
+                impl ::core::cmp::Ord for #id {
+                    fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                        // Safety: both objects are valid for the duration
+                        // of the call.
+                        #[allow(unused_unsafe)]
+                        let result = unsafe { self.#compare(other) };
+                        result.cmp(&0)
+                    }
+                }
+            },
+            parse_quote! {
+                impl ::core::cmp::PartialOrd for #id {
+                    fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+                        Some(self.cmp(other))
+                    }
+                }
+            },
+            parse_quote! {
+                impl ::core::cmp::PartialEq for #id {
+                    fn eq(&self, other: &Self) -> bool {
+                        self.cmp(other) == ::core::cmp::Ordering::Equal
+                    }
+                }
+            },
+            parse_quote! {
+                impl ::core::cmp::Eq for #id {}
+            },
+        ]);
+    }
+
+    /// Implements `Display` for an enum listed in `enum_display!`, using the
+    /// synthesized method which calls the C++ function naming each value.
+    /// That may well return null for a value it doesn't know, such as a
+    /// combination of flags, in which case the number is shown instead.
+    fn add_enum_display_impl(result: &mut RsCodegenResult, id: Ident, value_expr: &Expr) {
+        let display_name = make_ident(DISPLAY_NAME_METHOD_NAME);
+        result.bindgen_mod_items.push(parse_quote! {
+            impl ::core::fmt::Display for #id {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    let name = Self::#display_name(self.clone());
+                    if name.is_null() {
+                        write!(f, "{}", #value_expr)
+                    } else {
+                        // Safety: a name given by C++ must be null-terminated, and
+                        // mustn't be freed or changed, as for a string literal.
+                        let name = unsafe { ::core::ffi::CStr::from_ptr(name) };
+                        f.write_str(&name.to_string_lossy())
+                    }
+                }
+            }
+        });
+    }
+
+    /// Converts an enum to its underlying integer type, as a C++ cast would,
+    /// and back again, which fails for a number which isn't the value of any
+    /// of its variants.
+    fn add_enum_conversions(
+        result: &mut RsCodegenResult,
+        id: Ident,
+        repr: &Type,
+        variants: &[Ident],
+    ) {
+        result.bindgen_mod_items.extend([
+            parse_quote! {
+                impl ::core::convert::From<#id> for #repr {
+                    fn from(value: #id) -> Self {
+                        value as #repr
+                    }
+                }
+            },
+            parse_quote! {
+                impl ::core::convert::TryFrom<#repr> for #id {
+                    type Error = autocxx::InvalidEnumValue<#repr>;
+
+                    fn try_from(value: #repr) -> ::core::result::Result<Self, Self::Error> {
+                        #(
+                            if value == #id::#variants as #repr {
+                                return ::core::result::Result::Ok(#id::#variants);
+                            }
+                        )*
+                        ::core::result::Result::Err(autocxx::InvalidEnumValue { value })
+                    }
+                }
+            },
+        ]);
+    }
+
+    /// Implements `IntrusivePtrTarget` for a type listed in `intrusive_ptr!`,
+    /// using the synthesized methods which adjust its reference count, so
+    /// that it can be held in an `IntrusivePtr`.
+    fn add_intrusive_ptr_target_impl(result: &mut RsCodegenResult, id: Ident) {
+        let add_ref = make_ident(ADD_REF_METHOD_NAME);
+        let release = make_ident(RELEASE_METHOD_NAME);
+        result.bindgen_mod_items.push(parse_quote! {
+            unsafe impl autocxx::IntrusivePtrTarget for #id {
+                unsafe fn add_ref(ptr: *mut Self) {
+                    unsafe { Self::#add_ref(ptr) }
+                }
+
+                unsafe fn release(ptr: *mut Self) {
+                    unsafe { Self::#release(ptr) }
+                }
+            }
+        });
+    }
+
+    /// Implements `PartialOrd` for a type listed in `partial_ord!`, using the
+    /// synthesized three-way comparison method, which may also find two
+    /// objects unordered. Its `PartialEq` comes from `operator==`, as for
+    /// `partial_eq!`.
+    fn add_partial_ord_impl(result: &mut RsCodegenResult, id: Ident) {
+        let compare = make_ident(PARTIAL_COMPARE_METHOD_NAME);
+        result.bindgen_mod_items.push(parse_quote! {
+            impl ::core::cmp::PartialOrd for #id {
+                fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+                    // Safety: both objects are valid for the duration
+                    // of the call.
+                    #[allow(unused_unsafe)]
+                    let result = unsafe { self.#compare(other) };
+                    match result {
+                        -1 => Some(::core::cmp::Ordering::Less),
+                        0 => Some(::core::cmp::Ordering::Equal),
+                        1 => Some(::core::cmp::Ordering::Greater),
+                        _ => None,
+                    }
+                }
+            }
+        });
+    }
+
+    /// Implements `PartialEq` for a type listed in `partial_eq!`, using the
+    /// synthesized method which calls its `operator==`. `ne` is left as
+    /// the default, which is the negation of `eq`, so a C++ `operator!=`
+    /// is neither needed nor used.
+    fn add_partial_eq_impl(result: &mut RsCodegenResult, id: Ident) {
+        let eq = make_ident(EQ_METHOD_NAME);
+        result.bindgen_mod_items.push(parse_quote! {
+            impl ::core::cmp::PartialEq for #id {
+                fn eq(&self, other: &Self) -> bool {
+                    // Safety: both objects are valid for the duration
+                    // of the call.
+                    #[allow(unused_unsafe)]
+                    unsafe { self.#eq(other) }
+                }
+            }
+        });
+    }
+
+    /// Implements `Index` for a type listed in `subscript!`, which panics
+    /// if the index is out of bounds, like Rust's own containers. There's
+    /// deliberately no `IndexMut`, since only the `const` `operator[]` is
+    /// called.
+    fn add_index_impl(result: &mut RsCodegenResult, id: Ident, subscript: &Subscript) {
+        let method = make_ident(INDEX_METHOD_NAME);
+        let index_ty = QualifiedName::new_from_cpp_name(&subscript.index).to_type_path();
+        let output_ty = QualifiedName::new_from_cpp_name(&subscript.output).to_type_path();
+        result.bindgen_mod_items.push(parse_quote! {
+            impl ::core::ops::Index<#index_ty> for #id {
+                type Output = #output_ty;
+                fn index(&self, index: #index_ty) -> &Self::Output {
+                    // Safety: the object is valid for the duration of the
+                    // call, and the element for as long as it's borrowed.
+                    #[allow(unused_unsafe)]
+                    let element = unsafe { self.#method(index) };
+                    element.expect("index out of bounds")
+                }
+            }
+        });
+    }
+
+    /// Implements `Display`, and so `ToString`, for a type listed in
+    /// `display!`, using the synthesized method which calls its
+    /// `operator<<`. Any invalid UTF-8 in the output is replaced.
+    fn add_display_impl(result: &mut RsCodegenResult, id: Ident) {
+        let to_string = make_ident(TO_STRING_METHOD_NAME);
+        result.bindgen_mod_items.push(parse_quote! {
+            impl ::core::fmt::Display for #id {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    // Safety: the object is valid for the duration of the
+                    // call.
+                    #[allow(unused_unsafe)]
+                    let formatted = unsafe { self.#to_string() };
+                    ::core::fmt::Display::fmt(&formatted, f)
+                }
+            }
+        });
+    }
+
+    /// Implements `Deref` for a type listed in `deref!`, and `DerefMut` if
+    /// it's listed with `mut`. Rust only ever has a `Pin<&mut T>` to a
+    /// non-POD type, so `DerefMut` is only implemented if both the type and
+    /// its target are POD types (or the target a number), to which Rust can
+    /// have a `&mut`. For others, only the method is available. `operator*`
+    /// isn't checked, so these rely on the promise made by listing the type
+    /// in `deref!`: that it can always be dereferenced.
+    fn add_deref_impls(
+        result: &mut RsCodegenResult,
+        id: Ident,
+        deref: &DerefTarget,
+        both_pod: bool,
+    ) {
+        let method = make_ident(DEREF_METHOD_NAME);
+        let target_ty = QualifiedName::new_from_cpp_name(&deref.target).to_type_path();
+        result.bindgen_mod_items.push(parse_quote! {
+            impl ::core::ops::Deref for #id {
+                type Target = #target_ty;
+                fn deref(&self) -> &Self::Target {
+                    // Safety: the object is valid for the duration of the
+                    // call, and `deref!` promises that it can always be
+                    // dereferenced, to a target which lives as long.
+                    #[allow(unused_unsafe)]
+                    unsafe { self.#method() }
+                }
+            }
+        });
+        if deref.mutable && both_pod {
+            let method = make_ident(DEREF_MUT_METHOD_NAME);
+            result.bindgen_mod_items.push(parse_quote! {
+                impl ::core::ops::DerefMut for #id {
+                    fn deref_mut(&mut self) -> &mut Self::Target {
+                        // Safety: the object is valid for the duration of
+                        // the call, and `deref!` promises that it can
+                        // always be dereferenced, to a target which lives
+                        // as long.
+                        #[allow(unused_unsafe)]
+                        unsafe { ::core::pin::Pin::new(self).#method().get_mut() }
+                    }
+                }
+            });
+        }
+    }
+
+    /// Implements the Rust trait for a `compound_assign!` operator. A POD
+    /// type can implement it directly. Otherwise, Rust only ever has a
+    /// `Pin<&mut T>`, so that's what implements it.
+    fn add_compound_assign_impl(
+        result: &mut RsCodegenResult,
+        id: Ident,
+        ca: &CompoundAssign,
+        is_pod: bool,
+    ) {
+        let (trait_name, trait_method) = ca.rust_trait();
+        let trait_name = make_ident(trait_name);
+        let trait_method = make_ident(trait_method);
+        let method = make_ident(compound_assign_method_name(ca));
+        let rhs_path = QualifiedName::new_from_cpp_name(&ca.rhs).to_type_path();
+        let by_value = compound_assign_rhs_by_value(ca);
+        let rhs_ty: Type = if by_value {
+            parse_quote! { #rhs_path }
+        } else {
+            parse_quote! { &'b #rhs_path }
+        };
+        let rhs_lifetime = (!by_value).then(|| quote! { 'b });
+        if is_pod {
+            result.bindgen_mod_items.push(parse_quote! {
+                impl<#rhs_lifetime> ::core::ops::#trait_name<#rhs_ty> for #id {
+                    fn #trait_method(&mut self, rhs: #rhs_ty) {
+                        // Safety: both objects are valid for the duration
+                        // of the call.
+                        #[allow(unused_unsafe)]
+                        unsafe { ::core::pin::Pin::new(self).#method(rhs) }
+                    }
+                }
+            });
+        } else {
+            result.bindgen_mod_items.push(parse_quote! {
+                impl<'a, #rhs_lifetime> ::core::ops::#trait_name<#rhs_ty>
+                    for ::core::pin::Pin<&'a mut #id>
+                {
+                    fn #trait_method(&mut self, rhs: #rhs_ty) {
+                        // Safety: both objects are valid for the duration
+                        // of the call.
+                        #[allow(unused_unsafe)]
+                        unsafe { self.as_mut().#method(rhs) }
+                    }
+                }
+            });
+        }
+    }
+
+    /// Provides access to the elements of a `std::vector` of
+    /// `std::unique_ptr`s, including moving them out.
+    fn add_unique_ptr_vector_accessors(
+        &self,
+        result: &mut RsCodegenResult,
+        id: Ident,
+        item: &Type,
+    ) {
+        let vector_name = id.to_string();
+        let [len_fn, get_fn, take_fn] = ["len", "get", "take"].map(|accessor| {
+            make_ident(
+                self.config
+                    .get_unique_ptr_vector_accessor_name(&vector_name, accessor),
+            )
+        });
+        let item_path = match item {
+            Type::Path(tp) => QualifiedName::from_type_path(tp).to_type_path(),
+            _ => panic!("Vector item should be a path"),
+        };
+        let bridge_item_path = unqualify_type_path(item_path.clone());
+        result.extern_c_mod_items.extend([
+            parse_quote! {
+                fn #len_fn(v: &#id) -> usize;
+            },
+            parse_quote! {
+                unsafe fn #get_fn(v: &#id, i: usize) -> *const #bridge_item_path;
+            },
+            parse_quote! {
+                unsafe fn #take_fn(v: Pin<&mut #id>, i: usize) -> UniquePtr<#bridge_item_path>;
+            },
+        ]);
+        result.bindgen_mod_items.push(parse_quote! {
+            impl #id {
+                /// The number of elements, including any which have been taken.
+                pub fn len(&self) -> usize {
+                    cxxbridge::#len_fn(self)
+                }
+
+                /// Whether there are no elements at all.
+                pub fn is_empty(&self) -> bool {
+                    self.len() == 0
+                }
+
+                /// Borrows the element at `index`, or returns `None` if
+                /// there's no such element or it's null - for example
+                /// because it has already been taken.
+                pub fn get(&self, index: usize) -> Option<&#item_path> {
+                    if index >= self.len() {
+                        return None;
+                    }
+                    // Safety: the index is in bounds, and the C++ returns
+                    // either null or a pointer to an object owned by this
+                    // vector, which we borrow.
+                    unsafe { cxxbridge::#get_fn(self, index).as_ref() }
+                }
+
+                /// Moves the element at `index` out of the vector, leaving a
+                /// null `UniquePtr` in its place. Taking the same element
+                /// again therefore gives a null `UniquePtr`.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `index` is out of bounds.
+                pub fn take(
+                    self: ::core::pin::Pin<&mut Self>,
+                    index: usize,
+                ) -> cxx::UniquePtr<#item_path> {
+                    let len = self.len();
+                    assert!(
+                        index < len,
+                        "index {} out of bounds for a vector of length {}",
+                        index,
+                        len
+                    );
+                    // Safety: the index is in bounds.
+                    unsafe { cxxbridge::#take_fn(self, index) }
+                }
+            }
+        });
+    }
+
+    /// For a `std::unordered_map<K, V>` whose keys and values can be held
+    /// by value in Rust, adds methods to look up keys and copy out entries,
+    /// bucket by bucket.
+    fn add_unordered_map_accessors(
+        &self,
+        result: &mut RsCodegenResult,
+        id: Ident,
+        key: &Type,
+        value: &Type,
+    ) {
+        let (Some(key_type), Some(value_type)) =
+            (map_snapshot_value_type(key), map_snapshot_value_type(value))
+        else {
+            return;
+        };
+        let lookup_type: Type = if is_std_string(key) {
+            parse_quote! { &str }
+        } else {
+            key_type.clone()
+        };
+        let map_name = id.to_string();
+        let [len_fn, get_fn, bucket_count_fn, bucket_fn] = ["len", "get", "bucket_count", "bucket"]
+            .map(|accessor| {
+                make_ident(
+                    self.config
+                        .get_unordered_map_accessor_name(&map_name, accessor),
+                )
+            });
+        result.extern_c_mod_items.extend([
+            parse_quote! {
+                fn #len_fn(m: &#id) -> usize;
+            },
+            parse_quote! {
+                fn #get_fn(m: &#id, key: #lookup_type, out: &mut #value_type) -> bool;
+            },
+            parse_quote! {
+                fn #bucket_count_fn(m: &#id) -> usize;
+            },
+            parse_quote! {
+                fn #bucket_fn(m: &#id, n: usize, keys: &mut Vec<#key_type>, values: &mut Vec<#value_type>);
+            },
+        ]);
+        result.bindgen_mod_items.push(parse_quote! {
+            impl #id {
+                /// The number of entries.
+                pub fn len(&self) -> usize {
+                    cxxbridge::#len_fn(self)
+                }
+
+                /// Whether there are no entries at all.
+                pub fn is_empty(&self) -> bool {
+                    self.len() == 0
+                }
+
+                /// Looks up `key` using `find`, returning a copy of its value,
+                /// or `None` if it's not present.
+                pub fn get(&self, key: #lookup_type) -> Option<#value_type> {
+                    let mut value = Default::default();
+                    cxxbridge::#get_fn(self, key, &mut value).then_some(value)
+                }
+
+                /// The number of buckets, as given by `bucket_count`.
+                pub fn bucket_count(&self) -> usize {
+                    cxxbridge::#bucket_count_fn(self)
+                }
+
+                /// Copies out the entries in bucket `n`, or returns `None` if
+                /// there's no such bucket. The order of the entries within a
+                /// bucket is unspecified.
+                pub fn bucket(&self, n: usize) -> Option<Vec<(#key_type, #value_type)>> {
+                    if n >= self.bucket_count() {
+                        return None;
+                    }
+                    let mut keys = Vec::new();
+                    let mut values = Vec::new();
+                    cxxbridge::#bucket_fn(self, n, &mut keys, &mut values);
+                    Some(keys.into_iter().zip(values).collect())
+                }
+
+                /// Copies out every entry, one bucket at a time. As with any
+                /// `std::unordered_map`, the order is unspecified, and may
+                /// change whenever the map is rehashed.
+                pub fn entries(&self) -> impl Iterator<Item = (#key_type, #value_type)> + '_ {
+                    (0..self.bucket_count()).flat_map(move |n| self.bucket(n).unwrap_or_default())
+                }
+            }
+        });
+    }
+
+    /// Generates an iterator over a C++ container, plus the container
+    /// method which creates it: `iter`, yielding shared references by way
+    /// of the container's `const_iterator`, or for a `mutable` iterator,
+    /// `iter_mut`, yielding pinned mutable references.
+    fn generate_container_iterator(
+        &self,
+        id: Ident,
+        container: &QualifiedName,
+        item: &QualifiedName,
+        mutable: bool,
+    ) -> RsCodegenResult {
+        let state_name = self.config.get_iterator_state_name(&id.to_string());
+        let state = make_ident(&state_name);
+        let new_fn = make_ident(format!("{state_name}_new"));
+        let next_fn = make_ident(format!("{state_name}_next"));
+        let next_back_fn = make_ident(format!("{state_name}_next_back"));
+        let container_id = container.get_final_ident();
+        let container_path = container.to_type_path();
+        let item_path = item.to_type_path();
+        let bridge_item_path = unqualify_type_path(item_path.clone());
+        let (method, items, receiver, bridge_container, ptr_kind, item_ty, item_from_ptr) =
+            if mutable {
+                (
+                    make_ident("iter_mut"),
+                    "mutable references to the items",
+                    quote! { self: ::core::pin::Pin<&mut Self> },
+                    quote! { Pin<&mut #container_id> },
+                    quote! { mut },
+                    quote! { ::core::pin::Pin<&'a mut #item_path> },
+                    quote! { .as_mut().map(|item| ::core::pin::Pin::new_unchecked(item)) },
+                )
+            } else {
+                (
+                    make_ident("iter"),
+                    "the items",
+                    quote! { &self },
+                    quote! { &#container_id },
+                    quote! { const },
+                    quote! { &'a #item_path },
+                    quote! { .as_ref() },
+                )
+            };
+        let mut_token = mutable.then(|| quote! { mut });
+        let doc = format!(
+            "Iterator over {items} of a [`{container_id}`]. \
+            Created by [`{container_id}::{method}`]."
+        );
+        let method_doc = format!("Iterate over {items} in this container, from either end.");
+        RsCodegenResult {
+            extern_c_mod_items: vec![
+                ForeignItem::Verbatim(quote! {
+                    type #state;
+                }),
+                parse_quote! {
+                    fn #new_fn(container: #bridge_container) -> UniquePtr<#state>;
+                },
+                parse_quote! {
+                    unsafe fn #next_fn(state: Pin<&mut #state>) -> *#ptr_kind #bridge_item_path;
+                },
+                parse_quote! {
+                    unsafe fn #next_back_fn(state: Pin<&mut #state>) -> *#ptr_kind #bridge_item_path;
+                },
+            ],
+            bindgen_mod_items: vec![
+                parse_quote! {
+                    #[doc = #doc]
+                    pub struct #id<'a> {
+                        state: cxx::UniquePtr<cxxbridge::#state>,
+                        container: ::core::marker::PhantomData<&'a #mut_token #container_path>,
+                    }
+                },
+                parse_quote! {
+                    impl<'a> Iterator for #id<'a> {
+                        type Item = #item_ty;
+                        fn next(&mut self) -> Option<Self::Item> {
+                            // Safety: the C++ side only returns null or a pointer
+                            // into the container, which we borrow for 'a. Each
+                            // item is returned at most once.
+                            unsafe { cxxbridge::#next_fn(self.state.pin_mut()) #item_from_ptr }
+                        }
+                    }
+                },
+                parse_quote! {
+                    impl<'a> DoubleEndedIterator for #id<'a> {
+                        fn next_back(&mut self) -> Option<Self::Item> {
+                            // Safety: as for next.
+                            unsafe { cxxbridge::#next_back_fn(self.state.pin_mut()) #item_from_ptr }
+                        }
+                    }
+                },
+                parse_quote! {
+                    impl<'a> ::core::iter::FusedIterator for #id<'a> {}
+                },
+            ],
+            impl_entry: Some(Box::new(ImplBlockDetails {
+                item: parse_quote! {
+                    #[doc = #method_doc]
+                    pub fn #method(#receiver) -> #id<'_> {
+                        #id {
+                            state: cxxbridge::#new_fn(self),
+                            container: ::core::marker::PhantomData,
+                        }
+                    }
+                },
+                ty: ImplBlockKey {
+                    ty: parse_quote! { #container_id },
+                    lifetime: None,
+                },
+            })),
+            materializations: vec![Use::UsedFromBindgen],
+            ..Default::default()
+        }
+    }
+
+    fn generate_constant_accessor(
+        &self,
+        name: &QualifiedName,
+        enum_type: &QualifiedName,
+        underlying_type: &Ident,
+        variants: &[crate::minisyn::Ident],
+    ) -> RsCodegenResult {
+        let accessor = make_ident(
+            self.config
+                .get_constant_accessor_name(&name.to_cpp_name().replace("::", "_")),
+        );
+        let id = name.get_final_ident();
+        let enum_path = enum_type.to_type_path();
+        let doc = format!("The value of the C++ constant `{}`.", name.to_cpp_name());
+        let panic_message = format!(
+            "C++ constant {} has value {{}}, which isn't a variant of {}",
+            name.to_cpp_name(),
+            enum_type.to_cpp_name()
+        );
+        let enum_cpp_name = enum_type.to_cpp_name();
+        let body = if self.config.rejects_unknown_bits(&enum_cpp_name) {
+            quote! {
+                match #enum_path::from_bits(value) {
+                    Some(flags) => flags,
+                    None => panic!(#panic_message, value),
+                }
+            }
+        } else if self.config.is_bitmask_enum(&enum_cpp_name) {
+            quote! {
+                #enum_path::from_bits_retain(value)
+            }
+        } else {
+            let checks = variants.iter().map(|variant| {
+                quote! {
+                    if value == #enum_path::#variant as #underlying_type {
+                        return #enum_path::#variant;
+                    }
+                }
+            });
+            quote! {
+                #(#checks)*
+                panic!(#panic_message, value)
+            }
+        };
+        RsCodegenResult {
+            extern_c_mod_items: vec![parse_quote! {
+                fn #accessor() -> #underlying_type;
+            }],
+            bindgen_mod_items: vec![parse_quote! {
+                #[doc = #doc]
+                ///
+                /// # Panics
+                ///
+                /// If the C++ constant doesn't hold one of the enum's variants.
+                #[allow(non_snake_case)]
+                pub fn #id() -> #enum_path {
+                    let value = cxxbridge::#accessor();
+                    #body
+                }
+            }],
+            materializations: vec![Use::UsedFromBindgen],
+            ..Default::default()
+        }
+    }
+
     fn generate_extern_type_impl(&self, type_kind: TypeKind, tyname: &QualifiedName) -> Vec<Item> {
         let tynamestring = self.original_name_map.map(tyname);
         let fulltypath = tyname.get_bindgen_path_idents();
@@ -0,0 +1,166 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for `tag_dispatch!`, which exposes a whole C++ overload set
+//! through a single Rust function. Each overload gets a unit struct
+//! "tag" type and an implementation of a per-function trait, keyed on
+//! the overload's argument tuple. The single function then just forwards
+//! to whichever implementation the tag selects.
+
+use proc_macro2::Span;
+use syn::{
+    parse_quote, punctuated::Punctuated, token::Comma, visit_mut::VisitMut, FnArg, GenericParam,
+    Item, Lifetime, ReturnType, Type, TypeReference,
+};
+
+use super::{RsCodegenResult, Use};
+use crate::types::make_ident;
+
+fn tag_ident(name: &str, overload: usize) -> crate::minisyn::Ident {
+    make_ident(format!("{name}_tag{overload}"))
+}
+
+fn trait_ident(name: &str) -> crate::minisyn::Ident {
+    make_ident(format!("{name}_overload"))
+}
+
+fn dispatch_ident(name: &str) -> crate::minisyn::Ident {
+    make_ident(format!("{name}_dispatch"))
+}
+
+/// Works out which overload this is from the suffix the overload tracker
+/// added to its Rust name: none for the first, then 1, 2 and so on.
+fn overload_index(name: &str, rust_name: &str) -> Option<usize> {
+    match rust_name.strip_prefix(name)? {
+        "" => Some(0),
+        suffix => suffix.parse().ok(),
+    }
+}
+
+/// Gives each elided lifetime in a parameter type its own name, so that
+/// the same type can be spelled in both the trait `impl` header and the
+/// method signature.
+#[derive(Default)]
+struct LifetimeNamer {
+    lifetimes: Vec<Lifetime>,
+}
+
+impl VisitMut for LifetimeNamer {
+    fn visit_type_reference_mut(&mut self, tyr: &mut TypeReference) {
+        if tyr.lifetime.is_none() {
+            let lifetime = Lifetime::new(&format!("'p{}", self.lifetimes.len()), Span::call_site());
+            self.lifetimes.push(lifetime.clone());
+            tyr.lifetime = Some(lifetime);
+        }
+        syn::visit_mut::visit_type_reference_mut(self, tyr);
+    }
+}
+
+/// Generates the tag type for one overload, plus the trait implementation
+/// which calls it. `params` and `ret_type` must be those of the Rust
+/// function named `rust_name` which callers would otherwise use directly.
+/// Returns `None` if this overload can't take part in tag dispatch.
+pub(super) fn generate_tag_for_overload(
+    name: &str,
+    rust_name: &str,
+    params: &Punctuated<FnArg, Comma>,
+    ret_type: &ReturnType,
+) -> Option<(Vec<Item>, Use)> {
+    let overload = overload_index(name, rust_name)?;
+    let output: Type = match ret_type {
+        ReturnType::Default => parse_quote! { () },
+        // The trait's associated `Output` type can't borrow from the
+        // arguments, nor can it be an `impl New`.
+        ReturnType::Type(_, ty) if matches!(**ty, Type::Reference(_) | Type::ImplTrait(_)) => {
+            return None
+        }
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    };
+    let mut namer = LifetimeNamer::default();
+    let mut generic_types: Vec<GenericParam> = Vec::new();
+    let mut arg_types = Vec::new();
+    for param in params {
+        let mut ty = match param {
+            FnArg::Typed(pt) => (*pt.ty).clone(),
+            FnArg::Receiver(_) => return None,
+        };
+        namer.visit_type_mut(&mut ty);
+        if let Type::ImplTrait(tyit) = ty {
+            let generic = make_ident(format!("P{}", generic_types.len()));
+            let bounds = tyit.bounds;
+            generic_types.push(parse_quote! { #generic: #bounds });
+            ty = parse_quote! { #generic };
+        }
+        arg_types.push(ty);
+    }
+    let arg_names: Vec<_> = (0..arg_types.len())
+        .map(|n| make_ident(format!("arg{n}")))
+        .collect();
+    let lifetimes = namer.lifetimes;
+    let tag = tag_ident(name, overload);
+    let trait_id = trait_ident(name);
+    let rust_id = make_ident(rust_name);
+    let tag_doc = format!(
+        "Selects [`{rust_name}`] when passed to [`{}`].",
+        dispatch_ident(name)
+    );
+    let items = vec![
+        Item::Struct(parse_quote! {
+            #[doc = #tag_doc]
+            #[allow(non_camel_case_types)]
+            #[derive(Clone, Copy, Debug)]
+            pub struct #tag;
+        }),
+        Item::Impl(parse_quote! {
+            impl<#(#lifetimes,)* #(#generic_types,)*> #trait_id<( #(#arg_types,)* )> for #tag {
+                type Output = #output;
+                fn call(self, ( #(#arg_names,)* ): ( #(#arg_types,)* )) -> #output {
+                    #rust_id( #(#arg_names),* )
+                }
+            }
+        }),
+    ];
+    Some((items, Use::SpecificNameFromBindgen(tag.into())))
+}
+
+/// Generates the trait implemented by each overload's tag, and the single
+/// function through which all the overloads can be called.
+pub(super) fn generate_dispatcher(name: &str) -> RsCodegenResult {
+    let trait_id = trait_ident(name);
+    let dispatch_id = dispatch_ident(name);
+    let trait_doc = format!(
+        "Implemented by the tag types for each overload of `{name}` which can be called \
+        through [`{dispatch_id}`]."
+    );
+    let dispatch_doc = format!(
+        "Calls the overload of `{name}` selected by `tag`, passing it the tuple of `args`."
+    );
+    RsCodegenResult {
+        bindgen_mod_items: vec![
+            Item::Trait(parse_quote! {
+                #[doc = #trait_doc]
+                #[allow(non_camel_case_types)]
+                pub trait #trait_id<Args> {
+                    type Output;
+                    fn call(self, args: Args) -> Self::Output;
+                }
+            }),
+            Item::Fn(parse_quote! {
+                #[doc = #dispatch_doc]
+                pub fn #dispatch_id<Tag: #trait_id<Args>, Args>(tag: Tag, args: Args) -> Tag::Output {
+                    tag.call(args)
+                }
+            }),
+        ],
+        materializations: vec![
+            Use::SpecificNameFromBindgen(trait_id.into()),
+            Use::SpecificNameFromBindgen(dispatch_id.into()),
+        ],
+        ..Default::default()
+    }
+}
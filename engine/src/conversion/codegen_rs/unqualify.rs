@@ -13,7 +13,7 @@ use syn::{
 
 /// Mod to handle stripping paths off the front of types.
 
-fn unqualify_type_path(typ: TypePath) -> TypePath {
+pub(crate) fn unqualify_type_path(typ: TypePath) -> TypePath {
     // If we've still got more than one
     // path segment then this is referring to a type within
     // C++ namespaces. Strip them off for now, until cxx supports
@@ -69,6 +69,12 @@ pub enum ConvertErrorFromCpp {
     UnknownType(String),
     #[error("Encountered mutable static data, not yet supported: {0}")]
     StaticData(String),
+    #[error("Encountered static data which isn't a constant of enum type, not yet supported: {0}")]
+    NonEnumStaticConstant(String),
+    #[error(
+        "Encountered a static data member {0} which isn't a number or bool, not yet supported"
+    )]
+    NonPrimitiveStaticMember(String),
     #[error("Encountered typedef to itself - this is a known bindgen bug: {0}")]
     InfinitelyRecursiveTypedef(QualifiedName),
     #[error("Unexpected 'use' statement encountered: {}", .0.as_ref().map(|s| s.as_str()).unwrap_or("<unknown>"))]
@@ -137,8 +143,54 @@ pub enum ConvertErrorFromCpp {
     InvalidTypeForCppPtr(QualifiedName),
     #[error("A C++ std::vector was found containing some type that cxx can't accommodate as a vector element ({})", .0.to_cpp_name())]
     InvalidTypeForCppVector(QualifiedName),
-    #[error("Variadic functions are not supported by cxx or autocxx.")]
-    Variadic,
+    #[error("This function is listed in result_variant!, but doesn't return a std::variant of two types, one of which is {0}.")]
+    NotAResultVariant(String),
+    #[error("This function is listed in tuple_array!, but doesn't return a std::tuple whose elements are all the same built-in type.")]
+    NotAHomogeneousTuple,
+    #[error("This function is listed in pair_tuple!, but doesn't return a std::pair, or a std::optional of one, whose elements are built-in types, std::strings, std::unique_ptrs or further such pairs.")]
+    NotATrivialPair,
+    #[error("This function is listed in std_array!, but the parameter or return value isn't passed by value, or the array's elements aren't a built-in type.")]
+    NotAStdArray,
+    #[error("This function is listed in std_array! with a zero-length std::array, which cxx can't pass.")]
+    ZeroLengthStdArray,
+    #[error("This function is listed in std_span!, but the parameter isn't passed by value, or the span's elements aren't a number or a POD type.")]
+    NotAStdSpan,
+    #[error("This function is listed in std_span!, but has no parameter called {0}.")]
+    NoSuchStdSpanParam(String),
+    #[error("This function is listed in iterator_ranges!, but {0} and {1} aren't adjacent parameters which are pointers to the same number or POD type.")]
+    NotAnIteratorRange(String, String),
+    #[error("This function is listed in nanoseconds_count! or seconds_count!, but doesn't return a std::chrono::duration.")]
+    NotADuration,
+    #[error("This function is listed in closure_callback!, but its callback uses the type {0}. Only numbers and bool are supported.")]
+    UnsupportedClosureCallbackType(String),
+    #[error("A std::optional<{0}> can't be returned, since only numbers and POD types can simply be copied out of a std::optional into Rust.")]
+    NonTrivialOptional(String),
+    #[error("A std::optional<{0}> can't be returned, since {0} isn't a POD type. It can be returned if {0} is listed in generate_pod! rather than generate!.")]
+    OptionalOfNonPod(QualifiedName),
+    #[error("A std::optional<{0}> can't be returned, since {0} has no copy constructor, so can't be copied out of the std::optional into Rust.")]
+    OptionalOfUncopyable(QualifiedName),
+    #[error("This function is listed in sentinel_none!, but its sentinel {0} is out of range for its return type, {1}.")]
+    SentinelOutOfRange(i128, String),
+    #[error("A std::optional of a pointer can't be returned. Return a pointer which may be null instead.")]
+    OptionalOfPointer,
+    #[error("{0} is listed in vector_slices!, but doesn't return a const reference to a std::vector. A std::vector returned by value would be destroyed before Rust could look at the slice.")]
+    NotAVectorReference(String),
+    #[error(
+        "A std::vector can only be returned as a slice if its elements are numbers or POD types."
+    )]
+    NonTrivialVectorSlice,
+    #[error("This function is listed in catch_exceptions!, but constructs a C++ object in place, which can't also report an exception. Return the object in a std::unique_ptr instead.")]
+    CatchExceptionsWithPlacementReturn,
+    #[error(
+        "This type is listed in probe_traits!, and the C++ compiler says it doesn't satisfy {0}."
+    )]
+    FailedTraitProbe(&'static str),
+    #[error("The type {0} listed in template_returns! is neither a built-in type nor the name of a C++ type, so we can't work out the Rust type for it. Name it with a typedef instead.")]
+    UnnameableTemplateReturnType(String),
+    #[error("Function {0} is variadic (it takes `...`), which is not supported by cxx or autocxx, so no binding has been generated for it. Consider writing a C++ wrapper with a fixed parameter list and binding that instead.")]
+    Variadic(String),
+    #[error("This function returns a {0} by value, but {0} can be neither moved nor copied, so there's no way to get the returned object out of C++. Return it in a std::unique_ptr instead.")]
+    ReturnsImmovableType(QualifiedName),
     #[error("A type had a template inside a std::vector, which is not supported.")]
     GenericsWithinVector,
     #[error("This typedef takes generic parameters, not yet supported by autocxx.")]
@@ -115,6 +115,31 @@ pub(crate) fn convert_apis<FF, SF, EF, TF, A, B>(
             Api::StringConstructor { name } => {
                 Ok(Box::new(std::iter::once(Api::StringConstructor { name })))
             }
+            Api::WStringConstructor { name } => {
+                Ok(Box::new(std::iter::once(Api::WStringConstructor { name })))
+            }
+            Api::ContainerIterator {
+                name,
+                container,
+                item,
+                mutable,
+            } => Ok(Box::new(std::iter::once(Api::ContainerIterator {
+                name,
+                container,
+                item,
+                mutable,
+            }))),
+            Api::ConstantAccessor {
+                name,
+                enum_type,
+                underlying_type,
+                variants,
+            } => Ok(Box::new(std::iter::once(Api::ConstantAccessor {
+                name,
+                enum_type,
+                underlying_type,
+                variants,
+            }))),
             Api::Const { name, const_item } => {
                 Ok(Box::new(std::iter::once(Api::Const { name, const_item })))
             }
@@ -20,8 +20,9 @@ mod parse;
 mod type_helpers;
 mod utilities;
 
-use analysis::fun::FnAnalyzer;
-use autocxx_parser::IncludeCppConfig;
+use analysis::fun::{confirm_vector_slices_are_references, FnAnalyzer};
+pub(crate) use analysis::trait_probes::{trait_probe_header, TRAIT_PROBE_NAMESPACE};
+use autocxx_parser::{IncludeCppConfig, IncludedHeader};
 pub(crate) use codegen_cpp::CppCodeGenerator;
 pub(crate) use convert_error::ConvertError;
 use convert_error::ConvertErrorFromCpp;
@@ -36,12 +37,26 @@ use self::{
         allocators::create_alloc_and_frees,
         casts::add_casts,
         check_names,
+        comparisons::add_comparisons,
+        compound_assign::add_compound_assigns,
         constructor_deps::decorate_types_with_constructor_deps,
+        default_args::add_default_arg_overloads,
+        deref::add_derefs,
+        display::add_displays,
+        enum_display::add_enum_displays,
         gc::filter_apis_by_following_edges_from_allowlist,
+        intrusive_ptr::add_intrusive_ptrs,
+        layout::add_layout_accessors,
+        move_out_of_vector::add_moves_out_of_vectors,
+        operator_bool::add_operator_bools,
+        operator_not::add_operator_nots,
         pod::analyze_pod_apis,
         remove_ignored::filter_apis_by_ignored_dependents,
         replace_hopeless_typedef_targets,
+        subscript::add_subscripts,
         tdef::convert_typedef_targets,
+        template_returns::add_template_return_instances,
+        trait_probes::take_trait_probe_results,
     },
     api::AnalysisPhase,
     apivec::ApiVec,
@@ -64,7 +79,7 @@ const LOG_APIS: bool = true;
 /// if the bindgen output is not as expected. It may be in future that
 /// we need to be a bit more graceful, but for now, that's OK.
 pub(crate) struct BridgeConverter<'a> {
-    include_list: &'a [String],
+    include_list: &'a [IncludedHeader],
     config: &'a IncludeCppConfig,
 }
 
@@ -76,7 +91,7 @@ pub(crate) struct CodegenResults {
 }
 
 impl<'a> BridgeConverter<'a> {
-    pub fn new(include_list: &'a [String], config: &'a IncludeCppConfig) -> Self {
+    pub fn new(include_list: &'a [IncludedHeader], config: &'a IncludeCppConfig) -> Self {
         Self {
             include_list,
             config,
@@ -118,6 +133,9 @@ impl<'a> BridgeConverter<'a> {
                 let parser = ParseBindgen::new(self.config);
                 let apis = parser.parse_items(items_to_process, source_file_contents)?;
                 Self::dump_apis("parsing", &apis);
+                // Take out the answers to any questions we asked the C++
+                // compiler about type traits.
+                let (apis, trait_probes) = take_trait_probe_results(self.config, apis);
                 // Inside parse_results, we now have a list of APIs.
                 // We now enter various analysis phases.
                 // Next, convert any typedefs.
@@ -135,7 +153,20 @@ impl<'a> BridgeConverter<'a> {
                     analyze_pod_apis(apis, self.config).map_err(ConvertError::Cpp)?;
                 Self::dump_apis("pod analysis", &analyzed_apis);
                 let analyzed_apis = replace_hopeless_typedef_targets(self.config, analyzed_apis);
+                let analyzed_apis = add_default_arg_overloads(self.config, analyzed_apis);
+                let analyzed_apis = add_template_return_instances(self.config, analyzed_apis);
                 let analyzed_apis = add_casts(analyzed_apis);
+                let analyzed_apis = add_operator_bools(self.config, analyzed_apis);
+                let analyzed_apis = add_operator_nots(self.config, analyzed_apis);
+                let analyzed_apis = add_comparisons(self.config, analyzed_apis);
+                let analyzed_apis = add_displays(self.config, analyzed_apis);
+                let analyzed_apis = add_compound_assigns(self.config, analyzed_apis);
+                let analyzed_apis = add_subscripts(self.config, analyzed_apis);
+                let analyzed_apis = add_derefs(self.config, analyzed_apis);
+                let analyzed_apis = add_moves_out_of_vectors(self.config, analyzed_apis);
+                let analyzed_apis = add_layout_accessors(self.config, analyzed_apis);
+                let analyzed_apis = add_enum_displays(self.config, analyzed_apis);
+                let analyzed_apis = add_intrusive_ptrs(self.config, analyzed_apis);
                 let analyzed_apis = create_alloc_and_frees(analyzed_apis);
                 // Next, figure out how we materialize different functions.
                 // Some will be simple entries in the cxx::bridge module; others will
@@ -147,8 +178,10 @@ impl<'a> BridgeConverter<'a> {
                     analyzed_apis,
                     &unsafe_policy,
                     self.config,
+                    &trait_probes,
                     codegen_options.force_wrapper_gen,
                 );
+                confirm_vector_slices_are_references(&analyzed_apis).map_err(ConvertError::Cpp)?;
                 // If any of those functions turned out to be pure virtual, don't attempt
                 // to generate UniquePtr implementations for the type, since it can't
                 // be instantiated.
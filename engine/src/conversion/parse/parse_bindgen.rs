@@ -11,13 +11,18 @@ use indexmap::set::IndexSet as HashSet;
 
 use crate::{
     conversion::{
-        api::{Api, ApiName, NullPhase, StructDetails, SubclassName, TypedefKind, UnanalyzedApi},
+        analysis::fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, NullPhase, Provenance,
+            References, StructDetails, SubclassName, TypedefKind, UnanalyzedApi, Virtualness,
+        },
         apivec::ApiVec,
         convert_error::LocatedConvertErrorFromRust,
+        type_helpers::is_integer,
         ConvertError, ConvertErrorFromCpp,
     },
-    types::Namespace,
     types::QualifiedName,
+    types::{make_ident, Namespace},
 };
 use crate::{
     conversion::{
@@ -27,7 +32,7 @@ use crate::{
     types::validate_ident_ok_for_cxx,
 };
 use autocxx_parser::{IncludeCppConfig, RustPath};
-use syn::{parse_quote, Fields, Ident, Item, Type, TypePath, UseTree};
+use syn::{parse_quote, punctuated::Punctuated, Fields, Ident, Item, Type, TypePath, UseTree};
 
 use super::{
     super::utilities::generate_utilities, bindgen_semantic_attributes::BindgenSemanticAttributes,
@@ -39,6 +44,97 @@ use super::parse_foreign_mod::ParseForeignMod;
 pub(crate) struct ParseBindgen<'a> {
     config: &'a IncludeCppConfig,
     apis: ApiVec<NullPhase>,
+    static_constants: Vec<(ApiName, TypePath, Option<String>)>,
+}
+
+/// Whether a static data member is of a type we can read through an
+/// accessor without any conversion.
+fn is_primitive_static_member(ty: &Type) -> bool {
+    is_integer(ty)
+        || matches!(ty, Type::Path(tp) if ["f32", "f64", "bool"].iter().any(|name| tp.path.is_ident(name)))
+}
+
+/// The scope of a variable, innermost last, from its mangled link name.
+/// Only names nested within some scope are understood, which is all we
+/// need in order to spot static data members.
+fn demangle_scope(link_name: &str) -> Option<Vec<String>> {
+    let link_name = link_name.trim_start_matches('\u{1}');
+    if let Some(mut rest) = link_name
+        .strip_prefix("_ZN")
+        .or_else(|| link_name.strip_prefix("__ZN"))
+    {
+        // Itanium: each name is prefixed by its length, ending with 'E'.
+        let mut scope = Vec::new();
+        while !rest.starts_with('E') {
+            let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+            let (len, tail) = rest.split_at(digits);
+            let len: usize = len.parse().ok()?;
+            scope.push(tail.get(..len)?.to_string());
+            rest = &tail[len..];
+        }
+        (scope.len() > 1).then_some(scope)
+    } else {
+        // MSVC: the names are innermost first, separated by '@'.
+        let (names, _) = link_name.strip_prefix('?')?.split_once("@@")?;
+        let scope: Vec<_> = names.split('@').rev().map(str::to_string).collect();
+        (scope.len() > 1).then_some(scope)
+    }
+}
+
+/// Makes a static method of `class` which returns the value of its static
+/// data member `member`, which bindgen called `name`.
+fn static_member_accessor(
+    name: &ApiName,
+    class: &QualifiedName,
+    member: String,
+    ty: Type,
+) -> UnanalyzedApi {
+    let accessor_name = QualifiedName::new(
+        name.name.get_namespace(),
+        make_ident(format!(
+            "{}_AutocxxStaticMember",
+            name.name.get_final_item()
+        )),
+    );
+    let member = make_ident(member);
+    let doc = format!(
+        "The value of the C++ static data member `{}::{member}`.",
+        class.to_cpp_name()
+    );
+    let doc_attr: syn::Attribute = parse_quote! { #[doc = #doc] };
+    UnanalyzedApi::Function {
+        name: ApiName::new_from_qualified_name(accessor_name),
+        fun: Box::new(FuncToConvert {
+            ident: member.clone(),
+            doc_attrs: vec![doc_attr.into()],
+            inputs: Punctuated::new(),
+            output: parse_quote! {
+                -> #ty
+            },
+            vis: parse_quote! { pub },
+            virtualness: Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: None,
+            self_ty: Some(class.clone()),
+            synthesized_this_type: None,
+            add_to_trait: None,
+            synthetic_cpp: Some((
+                CppFunctionBody::StaticMemberAccess(
+                    class.get_namespace().clone(),
+                    class.get_final_ident(),
+                    member,
+                ),
+                CppFunctionKind::Function,
+            )),
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+        }),
+        analysis: (),
+    }
 }
 
 fn api_name(ns: &Namespace, id: Ident, attrs: &BindgenSemanticAttributes) -> ApiName {
@@ -67,6 +163,7 @@ impl<'a> ParseBindgen<'a> {
         ParseBindgen {
             config,
             apis: ApiVec::new(),
+            static_constants: Vec::new(),
         }
     }
 
@@ -85,6 +182,8 @@ impl<'a> ParseBindgen<'a> {
             .map_err(ConvertError::Rust)?;
         let root_ns = Namespace::new();
         self.parse_mod_items(items, root_ns);
+        self.resolve_static_members();
+        self.resolve_enum_constants();
         self.confirm_all_generate_directives_obeyed()
             .map_err(ConvertError::Cpp)?;
         self.replace_extern_cpp_types();
@@ -135,6 +234,29 @@ impl<'a> ParseBindgen<'a> {
                     }
                 }),
         );
+        self.apis.extend(
+            self.config
+                .iterables
+                .iter()
+                .flat_map(|it| {
+                    let mutabilities: &[bool] = if it.mutable { &[false, true] } else { &[false] };
+                    mutabilities.iter().map(move |mutable| (it, *mutable))
+                })
+                .map(|(it, mutable)| {
+                    let container = QualifiedName::new_from_cpp_name(&it.container);
+                    let suffix = if mutable { "IterMut" } else { "Iter" };
+                    let name = QualifiedName::new(
+                        container.get_namespace(),
+                        make_ident(format!("{}{suffix}", container.get_final_item())),
+                    );
+                    Api::ContainerIterator {
+                        name: ApiName::new_from_qualified_name(name),
+                        container,
+                        item: QualifiedName::new_from_cpp_name(&it.item),
+                        mutable,
+                    }
+                }),
+        );
         Ok(())
     }
 
@@ -165,6 +287,110 @@ impl<'a> ParseBindgen<'a> {
         self.apis.extend(replacements.into_iter().map(|(_, v)| v));
     }
 
+    /// bindgen names each static data member of a class `{class}_{member}`,
+    /// just as it might name a namespace-level variable, so the name alone
+    /// can't tell us which is which. If bindgen can work out the value of
+    /// such a member, it gives it to us as a constant with no record of
+    /// where it was declared: those stay as plain constants. Otherwise it
+    /// gives it to us as static data whose link name is mangled with the
+    /// class in which it's declared. So long as such a member is a number
+    /// or a `bool`, we read it through an accessor which becomes a static
+    /// method of the class. Anything else would need converting, so we
+    /// don't support it.
+    fn resolve_static_members(&mut self) {
+        let classes: HashMap<_, _> = self
+            .apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Struct { name, .. } => Some((name.qualified_cpp_name(), name.name.clone())),
+                _ => None,
+            })
+            .collect();
+        let mut accessors = Vec::new();
+        let mut static_constants = Vec::new();
+        for (name, typ, link_name) in std::mem::take(&mut self.static_constants) {
+            let found = link_name
+                .as_deref()
+                .and_then(demangle_scope)
+                .and_then(|mut scope| {
+                    let member = scope.pop()?;
+                    let class = classes.get(&scope.join("::"))?;
+                    Some((class, member))
+                });
+            let (class, member) = match found {
+                Some(found) => found,
+                None => {
+                    static_constants.push((name, typ, link_name));
+                    continue;
+                }
+            };
+            let typ = Type::Path(typ);
+            if is_primitive_static_member(&typ) {
+                accessors.push(static_member_accessor(&name, class, member, typ));
+            } else {
+                let id = name.name.get_final_ident();
+                accessors.push(Api::IgnoredItem {
+                    err: ConvertErrorFromCpp::NonPrimitiveStaticMember(format!(
+                        "{}::{member}",
+                        class.to_cpp_name()
+                    )),
+                    ctx: Some(ErrorContext::new_for_item(id)),
+                    name,
+                });
+            }
+        }
+        self.static_constants = static_constants;
+        self.apis.extend(accessors.into_iter());
+    }
+
+    /// bindgen gives us C++ global constants as static data. We can read
+    /// those through an accessor, but only if they're of enum type, which
+    /// we can't know until we've seen all the enums.
+    fn resolve_enum_constants(&mut self) {
+        let enums: HashMap<_, _> = self
+            .apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Enum { name, item } => Some((&name.name, item)),
+                _ => None,
+            })
+            .collect();
+        let constants: Vec<_> = std::mem::take(&mut self.static_constants)
+            .into_iter()
+            .map(|(name, typ, _)| {
+                let enum_type = QualifiedName::from_type_path(&typ);
+                let underlying_type = enums.get(&enum_type).and_then(|item| {
+                    item.attrs
+                        .iter()
+                        .filter(|attr| attr.path().is_ident("repr"))
+                        .find_map(|attr| attr.parse_args::<Ident>().ok())
+                        .map(|underlying_type| (underlying_type, item))
+                });
+                match underlying_type {
+                    Some((underlying_type, item)) => Api::ConstantAccessor {
+                        name,
+                        enum_type,
+                        underlying_type: underlying_type.into(),
+                        variants: item
+                            .variants
+                            .iter()
+                            .map(|variant| variant.ident.clone().into())
+                            .collect(),
+                    },
+                    None => {
+                        let id = name.name.get_final_ident();
+                        Api::IgnoredItem {
+                            err: ConvertErrorFromCpp::NonEnumStaticConstant(id.to_string()),
+                            ctx: Some(ErrorContext::new_for_item(id)),
+                            name,
+                        }
+                    }
+                }
+            })
+            .collect();
+        self.apis.extend(constants.into_iter());
+    }
+
     fn find_items_in_root(items: Vec<Item>) -> Result<Vec<Item>, ConvertErrorFromCpp> {
         for item in items {
             match item {
@@ -196,7 +422,8 @@ impl<'a> ParseBindgen<'a> {
             });
         }
         self.apis.append(&mut more_apis);
-        mod_converter.finished(&mut self.apis);
+        let mut static_constants = mod_converter.finished(&mut self.apis);
+        self.static_constants.append(&mut static_constants);
     }
 
     fn parse_item(
@@ -420,3 +647,22 @@ impl<'a> ParseBindgen<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::demangle_scope;
+
+    #[test]
+    fn test_demangle_scope() {
+        assert_eq!(
+            demangle_scope("\u{1}_ZN2ns6Widget5LIMITE").unwrap(),
+            ["ns", "Widget", "LIMIT"]
+        );
+        assert_eq!(
+            demangle_scope("?LIMIT@Widget@ns@@2IB").unwrap(),
+            ["ns", "Widget", "LIMIT"]
+        );
+        assert!(demangle_scope("Widget_LIMIT").is_none());
+        assert!(demangle_scope("_ZN6WidgetE").is_none());
+    }
+}
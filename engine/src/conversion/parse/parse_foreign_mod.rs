@@ -21,7 +21,10 @@ use crate::{
     types::{Namespace, QualifiedName},
 };
 use std::collections::HashMap;
-use syn::{Block, Expr, ExprCall, ForeignItem, Ident, ImplItem, ItemImpl, Stmt, Type};
+use syn::{
+    Block, Expr, ExprCall, ExprLit, ForeignItem, ForeignItemStatic, Ident, ImplItem, ItemImpl, Lit,
+    Meta, MetaNameValue, StaticMutability, Stmt, Type, TypePath,
+};
 
 use super::bindgen_semantic_attributes::BindgenSemanticAttributes;
 
@@ -41,6 +44,10 @@ pub(crate) struct ParseForeignMod {
     // function name to type name.
     method_receivers: HashMap<Ident, QualifiedName>,
     ignored_apis: ApiVec<NullPhase>,
+    // Immutable static data, with its link name if bindgen gave one,
+    // which may turn out to be constants of enum type, or static data
+    // members of a class, once we've seen all the enums and classes.
+    static_constants: Vec<(ApiName, TypePath, Option<String>)>,
 }
 
 impl ParseForeignMod {
@@ -50,6 +57,7 @@ impl ParseForeignMod {
             funcs_to_convert: Vec::new(),
             method_receivers: HashMap::new(),
             ignored_apis: ApiVec::new(),
+            static_constants: Vec::new(),
         }
     }
 
@@ -93,6 +101,43 @@ impl ParseForeignMod {
                 });
                 Ok(())
             }
+            ForeignItem::Static(ForeignItemStatic {
+                ident,
+                mutability: StaticMutability::None,
+                ty,
+                attrs,
+                ..
+            }) if matches!(*ty, Type::Path(_)) => {
+                let annotations = BindgenSemanticAttributes::new(&attrs);
+                let typ = match *ty {
+                    Type::Path(typ) => typ,
+                    _ => unreachable!(),
+                };
+                let link_name = attrs
+                    .iter()
+                    .filter(|attr| attr.path().is_ident("link_name"))
+                    .find_map(|attr| match &attr.meta {
+                        Meta::NameValue(MetaNameValue {
+                            value:
+                                Expr::Lit(ExprLit {
+                                    lit: Lit::Str(link_name),
+                                    ..
+                                }),
+                            ..
+                        }) => Some(link_name.value()),
+                        _ => None,
+                    });
+                self.static_constants.push((
+                    ApiName::new_with_cpp_name(
+                        &self.ns,
+                        ident.into(),
+                        annotations.get_original_name(),
+                    ),
+                    typ,
+                    link_name,
+                ));
+                Ok(())
+            }
             ForeignItem::Static(item) => Err(ConvertErrorWithContext(
                 ConvertErrorFromCpp::StaticData(item.ident.to_string()),
                 Some(ErrorContext::new_for_item(item.ident.into())),
@@ -127,8 +172,12 @@ impl ParseForeignMod {
 
     /// Indicate that all foreign mods and all impl blocks have been
     /// fed into us, and we should process that information to generate
-    /// the resulting APIs.
-    pub(crate) fn finished(mut self, apis: &mut ApiVec<NullPhase>) {
+    /// the resulting APIs. Returns any possible constants, which can't be
+    /// turned into APIs until all enums and classes are known.
+    pub(crate) fn finished(
+        mut self,
+        apis: &mut ApiVec<NullPhase>,
+    ) -> Vec<(ApiName, TypePath, Option<String>)> {
         apis.append(&mut self.ignored_apis);
         while !self.funcs_to_convert.is_empty() {
             let mut fun = self.funcs_to_convert.remove(0);
@@ -143,6 +192,7 @@ impl ParseForeignMod {
                 analysis: (),
             })
         }
+        self.static_constants
     }
 }
 
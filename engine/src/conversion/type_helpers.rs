@@ -7,8 +7,8 @@
 // except according to those terms.
 
 use syn::{
-    AngleBracketedGenericArguments, GenericArgument, PathArguments, PathSegment, Type, TypePath,
-    TypeReference,
+    parse_quote, AngleBracketedGenericArguments, Expr, ExprLit, GenericArgument, Lit,
+    PathArguments, PathSegment, Type, TypeArray, TypePath, TypePtr, TypeReference,
 };
 
 /// Looks in a `core::pin::Pin<&mut Something>` and returns the `Something`
@@ -56,3 +56,402 @@ fn is_pin(tp: &TypePath) -> bool {
     }
     true
 }
+
+/// Looks in a `std::optional<Something>` as emitted by bindgen and
+/// returns the `Something` if it's found.
+pub(crate) fn extract_std_optional_type(ty: &Type) -> Option<&Type> {
+    let tp = match ty {
+        Type::Path(tp) => tp,
+        _ => return None,
+    };
+    // Skip bindgen's `root` and any inline namespaces such as libc++'s `__1`.
+    let names: Vec<_> = tp
+        .path
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .filter(|name| name != "root" && !name.starts_with("__"))
+        .collect();
+    if names != ["std", "optional"] {
+        return None;
+    }
+    match tp.path.segments.last() {
+        Some(PathSegment {
+            arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+            ..
+        }) if args.len() == 1 => match args.first() {
+            Some(GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether this path names `expected`, ignoring bindgen's `root` and any
+/// inline namespaces such as `__cxx11`.
+fn path_is<const N: usize>(tp: &TypePath, expected: [&str; N]) -> bool {
+    tp.path
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .filter(|name| name != "root" && !name.starts_with("__"))
+        .eq(expected)
+}
+
+fn is_std_type(ty: &Type, name: &str) -> bool {
+    matches!(ty, Type::Path(tp) if path_is(tp, ["std", name]))
+}
+
+/// Returns the type arguments of a template instantiation named `expected`.
+fn type_args<'a>(tp: &'a TypePath, expected: [&str; 2]) -> Option<Vec<&'a Type>> {
+    match tp.path.segments.last() {
+        Some(PathSegment {
+            arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+            ..
+        }) if path_is(tp, expected) => Some(
+            args.iter()
+                .filter_map(|arg| match arg {
+                    GenericArgument::Type(inner) => Some(inner),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Whether this is a `std::string` as emitted by bindgen.
+pub(crate) fn is_std_string(ty: &Type) -> bool {
+    is_std_type(ty, "string")
+}
+
+/// Whether this is a `std::vector<Something>` as emitted by bindgen.
+pub(crate) fn is_std_vector(ty: &Type) -> bool {
+    is_std_type(ty, "vector")
+}
+
+/// Whether this is a `std::wstring` as emitted by bindgen.
+pub(crate) fn is_std_wstring(ty: &Type) -> bool {
+    is_std_type(ty, "wstring")
+}
+
+/// Whether this is a `std::u16string` as emitted by bindgen.
+pub(crate) fn is_std_u16string(ty: &Type) -> bool {
+    is_std_type(ty, "u16string")
+}
+
+/// Whether this is a `std::string_view` as emitted by bindgen.
+pub(crate) fn is_std_string_view(ty: &Type) -> bool {
+    is_std_type(ty, "string_view")
+}
+
+/// Whether this is a `std::chrono::duration`, or the
+/// `std::chrono::nanoseconds` alias of one, as emitted by bindgen.
+pub(crate) fn is_std_chrono_duration(ty: &Type) -> bool {
+    matches!(ty, Type::Path(tp)
+        if path_is(tp, ["std", "chrono", "duration"])
+            || path_is(tp, ["std", "chrono", "nanoseconds"]))
+}
+
+/// Whether this is a `std::optional<std::string_view>` as emitted by
+/// bindgen.
+pub(crate) fn is_std_optional_string_view(ty: &Type) -> bool {
+    extract_std_optional_type(ty).is_some_and(is_std_string_view)
+}
+
+/// If this is an array of `uint8_t`, as emitted by bindgen, returns its
+/// length.
+pub(crate) fn extract_byte_array_len(ty: &Type) -> Option<usize> {
+    match ty {
+        Type::Array(TypeArray {
+            elem,
+            len: Expr::Lit(ExprLit {
+                lit: Lit::Int(len), ..
+            }),
+            ..
+        }) if matches!(elem.as_ref(), Type::Path(tp)
+            if tp.path.is_ident("u8")
+                || tp.path.segments.last().is_some_and(|seg| seg.ident == "c_uchar")) =>
+        {
+            len.base10_parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// Looks in a `&cxx::CxxVector<Something>`, as produced by our type
+/// conversion, and returns the `Something`.
+pub(crate) fn extract_cxx_vector_reference_item(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(TypeReference {
+            mutability: None,
+            elem,
+            ..
+        }) => match elem.as_ref() {
+            Type::Path(tp) => type_args(tp, ["cxx", "CxxVector"])?.first().copied(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Looks in a `cxx::CxxVector<std::unique_ptr<Something>>`, as made into a
+/// concrete type by our type conversion, and returns the `Something`.
+pub(crate) fn extract_unique_ptr_vector_item(tp: &TypePath) -> Option<&Type> {
+    match type_args(tp, ["cxx", "CxxVector"])?.first()? {
+        Type::Path(inner) => type_args(inner, ["std", "unique_ptr"])?.first().copied(),
+        _ => None,
+    }
+}
+
+/// Looks in a `std::reference_wrapper<Something>`, as emitted by bindgen,
+/// and returns the `Something`.
+pub(crate) fn extract_reference_wrapper_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Path(tp) => type_args(tp, ["std", "reference_wrapper"])?
+            .first()
+            .copied(),
+        _ => None,
+    }
+}
+
+/// Looks in a `std::variant<First, Second>`, as emitted by bindgen, and
+/// returns the `First` and `Second`. Variants of any other number of types
+/// aren't recognized.
+pub(crate) fn extract_variant_alternatives(ty: &Type) -> Option<(&Type, &Type)> {
+    match ty {
+        Type::Path(tp) => match type_args(tp, ["std", "variant"])?.as_slice() {
+            [first, second] => Some((first, second)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the element types of a `std::tuple`, as emitted by bindgen.
+pub(crate) fn extract_tuple_elements(ty: &Type) -> Option<Vec<&Type>> {
+    match ty {
+        Type::Path(tp) => type_args(tp, ["std", "tuple"]),
+        _ => None,
+    }
+}
+
+/// Returns the two element types of a `std::pair`, as emitted by bindgen.
+pub(crate) fn extract_pair_elements(ty: &Type) -> Option<(&Type, &Type)> {
+    match ty {
+        Type::Path(tp) => match type_args(tp, ["std", "pair"]).as_deref() {
+            Some([first, second]) => Some((first, second)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether this is a `*const c_char`, as produced by our type conversion.
+pub(crate) fn is_const_char_pointer(ty: &Type) -> bool {
+    matches!(ty, Type::Ptr(TypePtr { mutability: None, elem, .. })
+        if matches!(elem.as_ref(), Type::Path(tp) if path_is(tp, ["std", "os", "raw", "c_char"])))
+}
+
+/// Whether this is a `*const c_char` or a `*mut c_char`, as produced by our
+/// type conversion.
+pub(crate) fn is_char_pointer(ty: &Type) -> bool {
+    matches!(ty, Type::Ptr(TypePtr { elem, .. })
+        if matches!(elem.as_ref(), Type::Path(tp) if path_is(tp, ["std", "os", "raw", "c_char"])))
+}
+
+/// Whether this is a `&cxx::CxxString`, as produced by our type conversion.
+pub(crate) fn is_cxx_string_reference(ty: &Type) -> bool {
+    matches!(ty, Type::Reference(TypeReference { mutability: None, elem, .. })
+        if matches!(elem.as_ref(), Type::Path(tp) if path_is(tp, ["cxx", "CxxString"])))
+}
+
+/// Whether this is a `cxx::CxxVector<cxx::CxxString>`, as produced by our
+/// type conversion.
+pub(crate) fn is_cxx_string_vector(ty: &Type) -> bool {
+    match ty {
+        Type::Path(tp) => matches!(
+            type_args(tp, ["cxx", "CxxVector"]).as_deref(),
+            Some([Type::Path(inner)]) if path_is(inner, ["cxx", "CxxString"])
+        ),
+        _ => false,
+    }
+}
+
+/// Whether this is a `cxx::UniquePtr<cxx::CxxVector<Something>>`, as
+/// produced by our type conversion.
+pub(crate) fn is_unique_ptr_vector(ty: &Type) -> bool {
+    match ty {
+        Type::Path(tp) => matches!(
+            type_args(tp, ["cxx", "UniquePtr"]).as_deref(),
+            Some([Type::Path(inner)]) if type_args(inner, ["cxx", "CxxVector"]).is_some()
+        ),
+        _ => false,
+    }
+}
+
+/// Looks in a `std::map<Key, Value>`, as emitted by bindgen, and returns
+/// the `Key` and `Value`.
+pub(crate) fn extract_map_key_and_value(tp: &TypePath) -> Option<(&Type, &Type)> {
+    match type_args(tp, ["std", "map"])?.as_slice() {
+        [key, value, ..] => Some((key, value)),
+        _ => None,
+    }
+}
+
+/// Looks in a `std::unordered_map<Key, Value>`, as emitted by bindgen, and
+/// returns the `Key` and `Value`.
+pub(crate) fn extract_unordered_map_key_and_value(tp: &TypePath) -> Option<(&Type, &Type)> {
+    match type_args(tp, ["std", "unordered_map"])?.as_slice() {
+        [key, value, ..] => Some((key, value)),
+        _ => None,
+    }
+}
+
+/// Looks in an instantiation of the intrusive pointer template `pointer`,
+/// such as `boost::intrusive_ptr<Something>`, as emitted by bindgen, and
+/// returns the `Something`.
+pub(crate) fn extract_intrusive_ptr_pointee<'a>(
+    tp: &'a TypePath,
+    pointer: &str,
+) -> Option<&'a Type> {
+    match tp.path.segments.last()? {
+        PathSegment {
+            arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+            ..
+        } if path_is_cpp_name(tp, pointer) => match args.iter().collect::<Vec<_>>().as_slice() {
+            [GenericArgument::Type(pointee)] => Some(pointee),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether this path, ignoring `root`, inline namespaces and any template
+/// arguments, names the C++ `cpp_name`, such as `boost::intrusive_ptr`.
+fn path_is_cpp_name(tp: &TypePath, cpp_name: &str) -> bool {
+    tp.path
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .filter(|name| name != "root" && !name.starts_with("__"))
+        .eq(cpp_name.split("::").map(str::to_string))
+}
+
+/// Looks in a `std::map<std::string, Something>`, as emitted by bindgen,
+/// and returns the `Something`.
+pub(crate) fn extract_string_map_value(tp: &TypePath) -> Option<&Type> {
+    match extract_map_key_and_value(tp)? {
+        (key, value) if is_std_string(key) => Some(value),
+        _ => None,
+    }
+}
+
+/// If the values of a `std::map<std::string, Something>` can be copied
+/// into a Rust `HashMap`, returns the Rust type they become there.
+/// That's possible for fixed-size numbers, `bool` and `std::string`.
+pub(crate) fn map_snapshot_value_type(value: &Type) -> Option<Type> {
+    match value {
+        _ if is_std_string(value) => Some(parse_quote! { String }),
+        Type::Path(tp)
+            if [
+                "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64", "bool",
+            ]
+            .iter()
+            .any(|name| tp.path.is_ident(name)) =>
+        {
+            Some(value.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Whether this is a Rust integer type, such as `i64` or `c_int`, as
+/// produced by our type conversion.
+pub(crate) fn is_integer(ty: &Type) -> bool {
+    integer_range(ty).is_some()
+}
+
+/// The smallest and largest values of a Rust integer type, such as `i64`
+/// or `c_int`, as produced by our type conversion.
+pub(crate) fn integer_range(ty: &Type) -> Option<(i128, i128)> {
+    use std::os::raw::*;
+    let Type::Path(tp) = ty else {
+        return None;
+    };
+    Some(match tp.path.segments.last()?.ident.to_string().as_str() {
+        "u8" => (u8::MIN as i128, u8::MAX as i128),
+        "u16" => (u16::MIN as i128, u16::MAX as i128),
+        "u32" => (u32::MIN as i128, u32::MAX as i128),
+        "u64" => (u64::MIN as i128, u64::MAX as i128),
+        "usize" => (usize::MIN as i128, usize::MAX as i128),
+        "i8" => (i8::MIN as i128, i8::MAX as i128),
+        "i16" => (i16::MIN as i128, i16::MAX as i128),
+        "i32" => (i32::MIN as i128, i32::MAX as i128),
+        "i64" => (i64::MIN as i128, i64::MAX as i128),
+        "isize" => (isize::MIN as i128, isize::MAX as i128),
+        "c_char" => (c_char::MIN as i128, c_char::MAX as i128),
+        "c_schar" => (c_schar::MIN as i128, c_schar::MAX as i128),
+        "c_uchar" => (c_uchar::MIN as i128, c_uchar::MAX as i128),
+        "c_short" => (c_short::MIN as i128, c_short::MAX as i128),
+        "c_ushort" => (c_ushort::MIN as i128, c_ushort::MAX as i128),
+        "c_int" => (c_int::MIN as i128, c_int::MAX as i128),
+        "c_uint" => (c_uint::MIN as i128, c_uint::MAX as i128),
+        "c_long" => (c_long::MIN as i128, c_long::MAX as i128),
+        "c_ulong" => (c_ulong::MIN as i128, c_ulong::MAX as i128),
+        "c_longlong" => (c_longlong::MIN as i128, c_longlong::MAX as i128),
+        "c_ulonglong" => (c_ulonglong::MIN as i128, c_ulonglong::MAX as i128),
+        _ => return None,
+    })
+}
+
+/// Whether this is a `cxx::UniquePtr<Something>`, as produced by our
+/// type conversion.
+pub(crate) fn is_unique_ptr(ty: &Type) -> bool {
+    match ty {
+        Type::Path(tp) => tp
+            .path
+            .segments
+            .iter()
+            .map(|seg| seg.ident.to_string())
+            .eq(["cxx", "UniquePtr"]),
+        _ => false,
+    }
+}
+
+/// Looks in a `cxx::UniquePtr<Something>`, as produced by our type
+/// conversion, and returns the `Something`.
+pub(crate) fn extract_unique_ptr_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Path(tp) => type_args(tp, ["cxx", "UniquePtr"])?.first().copied(),
+        _ => None,
+    }
+}
+
+/// Looks in a `cxx::SharedPtr<Something>`, as produced by our type
+/// conversion, and returns the `Something` if it's found.
+pub(crate) fn extract_shared_ptr_type(ty: &Type) -> Option<&Type> {
+    let tp = match ty {
+        Type::Path(tp) => tp,
+        _ => return None,
+    };
+    let names: Vec<_> = tp
+        .path
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .collect();
+    if names != ["cxx", "SharedPtr"] {
+        return None;
+    }
+    match tp.path.segments.last() {
+        Some(PathSegment {
+            arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+            ..
+        }) if args.len() == 1 => match args.first() {
+            Some(GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -27,4 +27,7 @@ pub(crate) fn generate_utilities(apis: &mut ApiVec<NullPhase>, config: &IncludeC
     apis.push(UnanalyzedApi::StringConstructor {
         name: ApiName::new(&Namespace::new(), make_ident(config.get_makestring_name())),
     });
+    apis.push(UnanalyzedApi::WStringConstructor {
+        name: ApiName::new(&Namespace::new(), make_ident(config.get_makewstring_name())),
+    });
 }
@@ -325,6 +325,22 @@ impl TypeDatabase {
             .unwrap_or(true)
     }
 
+    /// Whether this is a built-in type such as a number, which Rust and C++
+    /// both hold and pass by value.
+    pub(crate) fn is_by_value_built_in(&self, ty: &QualifiedName) -> bool {
+        self.get(ty)
+            .map(|td| {
+                matches!(
+                    td.behavior,
+                    Behavior::CByValue
+                        | Behavior::CByValueVecSafe
+                        | Behavior::CVariableLengthByValue
+                        | Behavior::CChar16
+                )
+            })
+            .unwrap_or(false)
+    }
+
     pub(crate) fn conflicts_with_built_in_type(&self, ty: &QualifiedName) -> bool {
         self.get(ty).is_some()
     }
@@ -29,7 +29,7 @@ mod builder;
 
 use autocxx_bindgen::BindgenError;
 use autocxx_parser::{IncludeCppConfig, UnsafePolicy};
-use conversion::BridgeConverter;
+use conversion::{trait_probe_header, BridgeConverter, TRAIT_PROBE_NAMESPACE};
 use miette::{SourceOffset, SourceSpan};
 use parse_callbacks::AutocxxParseCallbacks;
 use parse_file::CppBuildable;
@@ -320,7 +320,7 @@ impl IncludeCppEngine {
             self.config
                 .inclusions
                 .iter()
-                .map(|path| format!("#include \"{path}\"\n")),
+                .map(|inc| format!("{}\n", inc.include_stmt())),
             "",
         )
     }
@@ -365,6 +365,9 @@ impl IncludeCppEngine {
                     .allowlist_function(&a)
                     .allowlist_var(&a);
             }
+            if !self.config.trait_probe_types().is_empty() {
+                builder = builder.allowlist_var(format!("{TRAIT_PROBE_NAMESPACE}::.*"));
+            }
         }
 
         log::info!(
@@ -449,7 +452,13 @@ impl IncludeCppEngine {
         }
         let header_contents = self.build_header();
         self.dump_header_if_so_configured(&header_contents, &inc_dirs, extra_clang_args);
-        let header_and_prelude = format!("{}\n\n{}", known_types().get_prelude(), header_contents);
+        // The trait probes are only for bindgen, not for the C++ we generate.
+        let header_and_prelude = format!(
+            "{}\n\n{}\n{}",
+            known_types().get_prelude(),
+            header_contents,
+            trait_probe_header(&self.config)
+        );
         log::info!("Header and prelude for bindgen:\n{}", header_and_prelude);
         builder = builder.header_contents("example.hpp", &header_and_prelude);
 
@@ -262,436 +262,621 @@ fn test_give_string_plain() {
 }
 
 #[test]
-fn test_cycle_string_up() {
+fn test_give_string_rust_checked() {
     let cxx = indoc! {"
-        std::unique_ptr<std::string> give_str_up() {
-            return std::make_unique<std::string>(\"Bob\");
+        std::string give_str() {
+            return std::string(\"Bob\");
         }
-        uint32_t take_str_up(std::unique_ptr<std::string> a) {
-            return a->length();
+        std::string give_bad_str() {
+            return std::string(\"B\\xff\" \"b\");
         }
     "};
     let hdr = indoc! {"
-        #include <memory>
         #include <string>
-        #include <cstdint>
-        std::unique_ptr<std::string> give_str_up();
-        uint32_t take_str_up(std::unique_ptr<std::string> a);
+        std::string give_str();
+        std::string give_bad_str();
     "};
     let rs = quote! {
-        let s = ffi::give_str_up();
-        assert_eq!(ffi::take_str_up(s), 3);
+        assert_eq!(ffi::give_str().unwrap(), "Bob");
+        assert!(ffi::give_bad_str().is_err());
     };
-    run_test(cxx, hdr, rs, &["give_str_up", "take_str_up"], &[]);
+    run_test_ex(
+        cxx,
+        hdr,
+        rs,
+        directives_from_lists(
+            &["give_str", "give_bad_str"],
+            &[],
+            Some(quote! { rust_string_returns!() }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_cycle_string() {
+fn test_give_string_rust_lossy() {
     let cxx = indoc! {"
-        std::string give_str() {
-            return std::string(\"Bob\");
-        }
-        uint32_t take_str(std::string a) {
-            return a.length();
+        std::string give_bad_str() {
+            return std::string(\"B\\xff\" \"b\");
         }
     "};
     let hdr = indoc! {"
         #include <string>
-        #include <cstdint>
-        std::string give_str();
-        uint32_t take_str(std::string a);
+        std::string give_bad_str();
     "};
     let rs = quote! {
-        let s = ffi::give_str();
-        assert_eq!(ffi::take_str(s), 3);
+        assert_eq!(ffi::give_bad_str(), "B\u{FFFD}b");
     };
-    let generate = &["give_str", "take_str"];
-    run_test(cxx, hdr, rs, generate, &[]);
+    run_test_ex(
+        cxx,
+        hdr,
+        rs,
+        directives_from_lists(
+            &["give_bad_str"],
+            &[],
+            Some(quote! { rust_string_returns!(lossy) }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_cycle_string_by_ref() {
+fn test_give_string_rust_assume_valid() {
     let cxx = indoc! {"
-        std::unique_ptr<std::string> give_str() {
-            return std::make_unique<std::string>(\"Bob\");
-        }
-        uint32_t take_str(const std::string& a) {
-            return a.length();
+        std::string give_str() {
+            return std::string(\"Bob\");
         }
     "};
     let hdr = indoc! {"
         #include <string>
-        #include <memory>
-        #include <cstdint>
-        std::unique_ptr<std::string> give_str();
-        uint32_t take_str(const std::string& a);
+        std::string give_str();
     "};
     let rs = quote! {
-        let s = ffi::give_str();
-        assert_eq!(ffi::take_str(s.as_ref().unwrap()), 3);
+        let s: String = ffi::give_str();
+        assert_eq!(s, "Bob");
     };
-    let generate = &["give_str", "take_str"];
-    run_test(cxx, hdr, rs, generate, &[]);
+    run_test_ex(
+        cxx,
+        hdr,
+        rs,
+        directives_from_lists(
+            &["give_str"],
+            &[],
+            Some(quote! { rust_string_returns!(assume_valid) }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_cycle_string_by_mut_ref() {
+fn test_give_optional_bool() {
     let cxx = indoc! {"
-        std::unique_ptr<std::string> give_str() {
-            return std::make_unique<std::string>(\"Bob\");
-        }
-        uint32_t take_str(std::string& a) {
-            return a.length();
+        std::optional<bool> give_optional_bool(uint32_t a) {
+            switch (a) {
+                case 0: return std::nullopt;
+                case 1: return false;
+                default: return true;
+            }
         }
     "};
     let hdr = indoc! {"
-        #include <string>
-        #include <memory>
         #include <cstdint>
-        std::unique_ptr<std::string> give_str();
-        uint32_t take_str(std::string& a);
+        #include <optional>
+        std::optional<bool> give_optional_bool(uint32_t a);
     "};
     let rs = quote! {
-        let mut s = ffi::give_str();
-        assert_eq!(ffi::take_str(s.as_mut().unwrap()), 3);
+        assert_eq!(ffi::give_optional_bool(0), None);
+        assert_eq!(ffi::give_optional_bool(1), Some(false));
+        assert_eq!(ffi::give_optional_bool(2), Some(true));
     };
-    let generate = &["give_str", "take_str"];
-    run_test(cxx, hdr, rs, generate, &[]);
+    run_test_ex(
+        cxx,
+        hdr,
+        rs,
+        directives_from_lists(&["give_optional_bool"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_give_pod_by_value() {
+fn test_give_optional_double() {
     let cxx = indoc! {"
-        Bob give_bob() {
-            Bob a;
-            a.a = 3;
-            a.b = 4;
-            return a;
+        std::optional<double> give_optional_double(uint32_t a) {
+            switch (a) {
+                case 0: return std::nullopt;
+                case 1: return 2.5;
+                default: return std::numeric_limits<double>::quiet_NaN();
+            }
+        }
+        std::optional<float> give_optional_float(bool present) {
+            if (present) {
+                return 0.0f;
+            }
+            return std::nullopt;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-        };
-        Bob give_bob();
+        #include <limits>
+        #include <optional>
+        std::optional<double> give_optional_double(uint32_t a);
+        std::optional<float> give_optional_float(bool present);
     "};
     let rs = quote! {
-        assert_eq!(ffi::give_bob().b, 4);
+        assert_eq!(ffi::give_optional_double(0), None);
+        assert_eq!(ffi::give_optional_double(1), Some(2.5));
+        assert!(ffi::give_optional_double(2).unwrap().is_nan());
+        assert_eq!(ffi::give_optional_float(true), Some(0.0));
+        assert_eq!(ffi::give_optional_float(false), None);
     };
-    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
+    run_test_ex(
+        cxx,
+        hdr,
+        rs,
+        directives_from_lists(&["give_optional_double", "give_optional_float"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_give_pod_class_by_value() {
-    let cxx = indoc! {"
-        Bob give_bob() {
-            Bob a;
-            a.a = 3;
-            a.b = 4;
-            return a;
-        }
-    "};
+fn test_give_optional_value() {
     let hdr = indoc! {"
         #include <cstdint>
-        class Bob {
+        #include <optional>
+        #include <string>
+        inline std::optional<int32_t> maybe_value(bool present) {
+            if (present) {
+                return 0;
+            }
+            return std::nullopt;
+        }
+        struct Point {
+            int32_t x;
+            int32_t y;
+        };
+        inline std::optional<Point> maybe_point(int32_t x) {
+            if (x < 0) {
+                return std::nullopt;
+            }
+            return Point { x, 2 };
+        }
+        inline std::optional<std::string> maybe_name() {
+            return std::nullopt;
+        }
+        class Widget {
         public:
-            uint32_t a;
-            uint32_t b;
+            Widget() {}
+            int32_t id;
         };
-        Bob give_bob();
+        inline std::optional<Widget> maybe_widget() {
+            return std::nullopt;
+        }
+        inline std::optional<int32_t*> maybe_pointer() {
+            return std::nullopt;
+        }
     "};
     let rs = quote! {
-        assert_eq!(ffi::give_bob().b, 4);
+        assert_eq!(ffi::maybe_value(true), Some(0));
+        assert_eq!(ffi::maybe_value(false), None);
+        let point = ffi::maybe_point(1).unwrap();
+        assert_eq!((point.x, point.y), (1, 2));
+        assert!(ffi::maybe_point(-1).is_none());
     };
-    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &[
+                "maybe_value",
+                "maybe_point",
+                "maybe_name",
+                "Widget",
+                "maybe_widget",
+                "maybe_pointer",
+            ],
+            &["Point"],
+            None,
+        ),
+        make_cpp17_adder(),
+        Some(make_string_finder(vec![
+            "since only numbers and POD types can simply be copied".to_string(),
+            "since Widget isn't a POD type".to_string(),
+            "A std::optional of a pointer can't be returned".to_string(),
+        ])),
+        None,
+    );
 }
 
 #[test]
-fn test_give_pod_by_up() {
-    let cxx = indoc! {"
-        std::unique_ptr<Bob> give_bob() {
-            auto a = std::make_unique<Bob>();
-            a->a = 3;
-            a->b = 4;
-            return a;
-        }
-    "};
+fn test_give_optional_vector() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-        };
-        std::unique_ptr<Bob> give_bob();
+        #include <optional>
+        #include <vector>
+        inline std::optional<std::vector<int32_t>> maybe_values(int32_t count) {
+            if (count < 0) {
+                return std::nullopt;
+            }
+            return std::vector<int32_t>(count, 7);
+        }
     "};
     let rs = quote! {
-        assert_eq!(ffi::give_bob().as_ref().unwrap().b, 4);
+        let values = ffi::maybe_values(3).unwrap();
+        assert_eq!(values.iter().copied().collect::<Vec<_>>(), [7, 7, 7]);
+        let empty = ffi::maybe_values(0).unwrap();
+        assert!(empty.is_empty());
+        assert!(ffi::maybe_values(-1).is_none());
     };
-    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["maybe_values"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_take_pod_by_value() {
-    let cxx = indoc! {"
-        uint32_t take_bob(Bob a) {
-            return a.a;
-        }
-    "};
+fn test_sentinel_none() {
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-        };
-        uint32_t take_bob(Bob a);
+        inline int64_t find_index(int64_t value) {
+            return value < 10 ? value - 1 : -1;
+        }
+        inline uint32_t first_free_slot(bool full) {
+            return full ? 0xffffffff : 3;
+        }
+        inline int32_t count_unchanged(int32_t value) {
+            return value;
+        }
+        inline uint8_t level() {
+            return 255;
+        }
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(ffi::take_bob(a), 12);
+        assert_eq!(ffi::find_index(3), Some(2));
+        assert_eq!(ffi::find_index(42), None);
+        assert_eq!(ffi::first_free_slot(false), Some(3));
+        assert_eq!(ffi::first_free_slot(true), None);
+        assert_eq!(ffi::count_unchanged(-1), -1);
     };
-    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["find_index", "first_free_slot", "count_unchanged", "level"],
+            &[],
+            Some(quote! {
+                sentinel_none!("find_index", -1)
+                sentinel_none!("first_free_slot", 4294967295)
+                sentinel_none!("level", 256)
+            }),
+        ),
+        None,
+        Some(make_string_finder(vec![
+            "its sentinel 256 is out of range".to_string()
+        ])),
+        None,
+    );
 }
 
 #[test]
-fn test_negative_take_as_pod_with_destructor() {
-    let cxx = indoc! {"
-        uint32_t take_bob(Bob a) {
-            return a.a;
-        }
-    "};
+fn test_give_optional_reference() {
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-            inline ~Bob() {}
+        #include <functional>
+        #include <optional>
+        class Widget {
+        public:
+            Widget(uint32_t id0) : id(id0) {}
+            uint32_t id;
         };
-        uint32_t take_bob(Bob a);
+        class Registry {
+        public:
+            Registry() : first(1), second(2) {}
+            std::optional<std::reference_wrapper<const Widget>> find(uint32_t id) const {
+                if (id == first.id) {
+                    return std::cref(first);
+                }
+                if (id == second.id) {
+                    return std::cref(second);
+                }
+                return std::nullopt;
+            }
+            Widget first;
+            Widget second;
+        };
+        inline std::optional<std::reference_wrapper<Widget>> pick(Registry& r, bool second) {
+            if (second) {
+                return std::ref(r.second);
+            }
+            return std::nullopt;
+        }
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(ffi::take_bob(a), 12);
+        let mut registry = ffi::Registry::new().within_unique_ptr();
+        assert_eq!(registry.find(1).map(|w| w.id), Some(1));
+        assert_eq!(registry.find(2).map(|w| w.id), Some(2));
+        assert!(registry.find(3).is_none());
+        assert_eq!(ffi::pick(registry.pin_mut(), true).map(|w| w.id), Some(2));
+        assert!(ffi::pick(registry.pin_mut(), false).is_none());
     };
-    run_test_expect_fail(cxx, hdr, rs, &["take_bob"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["Registry", "Widget", "pick"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_negative_take_as_pod_with_move_constructor() {
-    let cxx = indoc! {"
-        uint32_t take_bob(Bob a) {
-            return a.a;
-        }
-    "};
+fn test_iterable() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <type_traits>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-            inline Bob(Bob&& other_bob) {}
+        #include <list>
+        class Numbers {
+        public:
+            Numbers() {}
+            void push(uint32_t n) { items.push_back(n); }
+            std::list<uint32_t>::const_iterator begin() const { return items.begin(); }
+            std::list<uint32_t>::const_iterator end() const { return items.end(); }
+        private:
+            std::list<uint32_t> items;
         };
-        uint32_t take_bob(Bob a);
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(ffi::take_bob(a), 12);
+        let mut numbers = ffi::Numbers::new().within_unique_ptr();
+        assert_eq!(numbers.iter().next(), None);
+        numbers.pin_mut().push(1);
+        numbers.pin_mut().push(2);
+        numbers.pin_mut().push(3);
+        assert_eq!(numbers.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(numbers.iter().rev().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+        let mut it = numbers.iter();
+        assert_eq!(it.next(), Some(&1));
+        assert_eq!(it.next_back(), Some(&3));
+        assert_eq!(it.next(), Some(&2));
+        assert_eq!(it.next_back(), None);
+        assert_eq!(it.next(), None);
     };
-    run_test_expect_fail(cxx, hdr, rs, &["take_bob"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Numbers"],
+            &[],
+            Some(quote! { iterable!("Numbers", "uint32_t") }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
-#[ignore] // https://github.com/google/autocxx/issues/1252
 #[test]
-fn test_take_as_pod_with_is_relocatable() {
-    let cxx = indoc! {"
-        uint32_t take_bob(Bob a) {
-            return a.a;
-        }
-    "};
+fn test_iterable_mutable() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <type_traits>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-            inline Bob() {}
-            inline ~Bob() {}
-            inline Bob(Bob&& other_bob) { a = other_bob.a; b = other_bob.b; }
-            using IsRelocatable = std::true_type;
+        #include <vector>
+        class Counter {
+        public:
+            Counter(uint32_t n) : count(n) {}
+            uint32_t count;
+        };
+        class Counters {
+        public:
+            Counters() {}
+            void push(uint32_t n) { items.push_back(Counter(n)); }
+            std::vector<Counter>::const_iterator begin() const { return items.begin(); }
+            std::vector<Counter>::const_iterator end() const { return items.end(); }
+            std::vector<Counter>::iterator begin() { return items.begin(); }
+            std::vector<Counter>::iterator end() { return items.end(); }
+        private:
+            std::vector<Counter> items;
         };
-        uint32_t take_bob(Bob a);
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(ffi::take_bob(a), 12);
+        let mut counters = ffi::Counters::new().within_unique_ptr();
+        counters.pin_mut().push(1);
+        counters.pin_mut().push(2);
+        counters.pin_mut().push(3);
+        for mut counter in counters.pin_mut().iter_mut() {
+            counter.count *= 10;
+        }
+        if let Some(mut last) = counters.pin_mut().iter_mut().next_back() {
+            last.count += 1;
+        }
+        let counts: Vec<u32> = counters.iter().map(|c| c.count).collect();
+        assert_eq!(counts, vec![10, 20, 31]);
     };
-    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Counters"],
+            &["Counter"],
+            Some(quote! { iterable!("Counters", "Counter", mutable) }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_take_pod_by_ref() {
+fn test_cycle_string_up() {
     let cxx = indoc! {"
-        uint32_t take_bob(const Bob& a) {
-            return a.a;
+        std::unique_ptr<std::string> give_str_up() {
+            return std::make_unique<std::string>(\"Bob\");
+        }
+        uint32_t take_str_up(std::unique_ptr<std::string> a) {
+            return a->length();
         }
     "};
     let hdr = indoc! {"
+        #include <memory>
+        #include <string>
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-        };
-        uint32_t take_bob(const Bob& a);
+        std::unique_ptr<std::string> give_str_up();
+        uint32_t take_str_up(std::unique_ptr<std::string> a);
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(ffi::take_bob(&a), 12);
+        let s = ffi::give_str_up();
+        assert_eq!(ffi::take_str_up(s), 3);
     };
-    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
+    run_test(cxx, hdr, rs, &["give_str_up", "take_str_up"], &[]);
 }
 
 #[test]
-fn test_take_pod_by_ref_and_ptr() {
+fn test_cycle_string() {
     let cxx = indoc! {"
-        uint32_t take_bob_ref(const Bob& a) {
-            return a.a;
+        std::string give_str() {
+            return std::string(\"Bob\");
         }
-        uint32_t take_bob_ptr(const Bob* a) {
-            return a->a;
+        uint32_t take_str(std::string a) {
+            return a.length();
         }
     "};
     let hdr = indoc! {"
+        #include <string>
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-        };
-        uint32_t take_bob_ref(const Bob& a);
-        uint32_t take_bob_ptr(const Bob* a);
+        std::string give_str();
+        uint32_t take_str(std::string a);
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(ffi::take_bob_ref(&a), 12);
+        let s = ffi::give_str();
+        assert_eq!(ffi::take_str(s), 3);
     };
-    run_test(cxx, hdr, rs, &["take_bob_ref", "take_bob_ptr"], &["Bob"]);
+    let generate = &["give_str", "take_str"];
+    run_test(cxx, hdr, rs, generate, &[]);
 }
 
 #[test]
-fn test_return_pod_by_ref_and_ptr() {
-    let hdr = indoc! {"
-        #include <cstdint>
-        struct B {
-            uint32_t a;
-        };
-        struct A {
-            B b;
-        };
-        inline const B& return_b_ref(const A& a) {
-            return a.b;
+fn test_cycle_string_by_ref() {
+    let cxx = indoc! {"
+        std::unique_ptr<std::string> give_str() {
+            return std::make_unique<std::string>(\"Bob\");
         }
-        inline const B* return_b_ptr(const A& a) {
-            return &a.b;
+        uint32_t take_str(const std::string& a) {
+            return a.length();
         }
     "};
+    let hdr = indoc! {"
+        #include <string>
+        #include <memory>
+        #include <cstdint>
+        std::unique_ptr<std::string> give_str();
+        uint32_t take_str(const std::string& a);
+    "};
     let rs = quote! {
-        let a = ffi::A { b: ffi::B { a: 3 } };
-        assert_eq!(ffi::return_b_ref(&a).a, 3);
-        let b_ptr = ffi::return_b_ptr(&a);
-        assert_eq!(unsafe { b_ptr.as_ref() }.unwrap().a, 3);
+        let s = ffi::give_str();
+        assert_eq!(ffi::take_str(s.as_ref().unwrap()), 3);
     };
-    run_test("", hdr, rs, &["return_b_ref", "return_b_ptr"], &["A", "B"]);
+    let generate = &["give_str", "take_str"];
+    run_test(cxx, hdr, rs, generate, &[]);
 }
 
 #[test]
-fn test_take_pod_by_mut_ref() {
+fn test_cycle_string_by_mut_ref() {
     let cxx = indoc! {"
-        uint32_t take_bob(Bob& a) {
-            a.b = 14;
-            return a.a;
+        std::unique_ptr<std::string> give_str() {
+            return std::make_unique<std::string>(\"Bob\");
+        }
+        uint32_t take_str(std::string& a) {
+            return a.length();
         }
     "};
     let hdr = indoc! {"
+        #include <string>
+        #include <memory>
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-        };
-        uint32_t take_bob(Bob& a);
+        std::unique_ptr<std::string> give_str();
+        uint32_t take_str(std::string& a);
     "};
     let rs = quote! {
-        let mut a = Box::pin(ffi::Bob { a: 12, b: 13 });
-        assert_eq!(ffi::take_bob(a.as_mut()), 12);
-        assert_eq!(a.b, 14);
+        let mut s = ffi::give_str();
+        assert_eq!(ffi::take_str(s.as_mut().unwrap()), 3);
     };
-    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
+    let generate = &["give_str", "take_str"];
+    run_test(cxx, hdr, rs, generate, &[]);
 }
 
 #[test]
-fn test_take_nested_pod_by_value() {
+fn test_give_pod_by_value() {
     let cxx = indoc! {"
-        uint32_t take_bob(Bob a) {
-            return a.a;
+        Bob give_bob() {
+            Bob a;
+            a.a = 3;
+            a.b = 4;
+            return a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Phil {
-            uint32_t d;
-        };
         struct Bob {
             uint32_t a;
             uint32_t b;
-            Phil c;
         };
-        uint32_t take_bob(Bob a);
+        Bob give_bob();
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13, c: ffi::Phil { d: 4 } };
-        assert_eq!(ffi::take_bob(a), 12);
+        assert_eq!(ffi::give_bob().b, 4);
     };
-    // Should be no need to allowlist Phil below
-    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
+    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_take_nonpod_by_value() {
+fn test_give_pod_class_by_value() {
     let cxx = indoc! {"
-        Bob::Bob(uint32_t a0, uint32_t b0)
-           : a(a0), b(b0) {}
-        uint32_t take_bob(Bob a) {
-            return a.a;
+        Bob give_bob() {
+            Bob a;
+            a.a = 3;
+            a.b = 4;
+            return a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        struct Bob {
-            Bob(uint32_t a, uint32_t b);
+        class Bob {
+        public:
             uint32_t a;
             uint32_t b;
-            std::string reason_why_this_is_nonpod;
         };
-        uint32_t take_bob(Bob a);
+        Bob give_bob();
     "};
     let rs = quote! {
-        let a = ffi::Bob::new(12, 13).within_unique_ptr();
-        assert_eq!(ffi::take_bob(a), 12);
+        assert_eq!(ffi::give_bob().b, 4);
     };
-    run_test(cxx, hdr, rs, &["take_bob", "Bob"], &[]);
+    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_take_nonpod_by_ref() {
+fn test_give_pod_by_up() {
     let cxx = indoc! {"
-        uint32_t take_bob(const Bob& a) {
-            return a.a;
-        }
-        std::unique_ptr<Bob> make_bob(uint32_t a) {
-            auto b = std::make_unique<Bob>();
-            b->a = a;
-            return b;
+        std::unique_ptr<Bob> give_bob() {
+            auto a = std::make_unique<Bob>();
+            a->a = 3;
+            a->b = 4;
+            return a;
         }
     "};
     let hdr = indoc! {"
@@ -699,27 +884,32 @@ fn test_take_nonpod_by_ref() {
         #include <memory>
         struct Bob {
             uint32_t a;
+            uint32_t b;
         };
-        std::unique_ptr<Bob> make_bob(uint32_t a);
-        uint32_t take_bob(const Bob& a);
+        std::unique_ptr<Bob> give_bob();
     "};
     let rs = quote! {
-        let a = ffi::make_bob(12);
-        assert_eq!(ffi::take_bob(&a), 12);
+        assert_eq!(ffi::give_bob().as_ref().unwrap().b, 4);
     };
-    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
+    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_take_nonpod_by_up() {
+fn test_give_up_with_custom_deleter() {
     let cxx = indoc! {"
-        uint32_t take_bob(std::unique_ptr<Bob> a) {
-            return a->a;
+        uint32_t deletions = 0;
+        void BobDeleter::operator()(Bob* bob) const {
+            deletions++;
+            delete bob;
         }
-        std::unique_ptr<Bob> make_bob(uint32_t a) {
-            auto b = std::make_unique<Bob>();
-            b->a = a;
-            return b;
+        std::unique_ptr<Bob, BobDeleter> give_bob() {
+            return std::unique_ptr<Bob, BobDeleter>(new Bob { 3, 4 });
+        }
+        uint32_t get_b(const std::unique_ptr<Bob, BobDeleter>& bob) {
+            return bob->b;
+        }
+        uint32_t get_deletions() {
+            return deletions;
         }
     "};
     let hdr = indoc! {"
@@ -727,468 +917,711 @@ fn test_take_nonpod_by_up() {
         #include <memory>
         struct Bob {
             uint32_t a;
+            uint32_t b;
         };
-
-        struct NOP { inline void take_bob(); };
-        std::unique_ptr<Bob> make_bob(uint32_t a);
-        uint32_t take_bob(std::unique_ptr<Bob> a);
+        struct BobDeleter {
+            void operator()(Bob* bob) const;
+        };
+        std::unique_ptr<Bob, BobDeleter> give_bob();
+        uint32_t get_b(const std::unique_ptr<Bob, BobDeleter>& bob);
+        uint32_t get_deletions();
     "};
+    // The unique_ptr is opaque to Rust, but can be passed back to C++.
     let rs = quote! {
-        let a = ffi::make_bob(12);
-        assert_eq!(ffi::take_bob(a), 12);
+        let bob = ffi::give_bob();
+        assert_eq!(ffi::get_b(bob.as_ref().unwrap()), 4);
+        assert_eq!(ffi::get_deletions(), 0);
+        drop(bob);
+        assert_eq!(ffi::get_deletions(), 1);
     };
-    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob", "NOP"], &[]);
-}
-
+    run_test(
+        cxx,
+        hdr,
+        rs,
+        &["give_bob", "get_b", "get_deletions"],
+        &["Bob"],
+    );
+}
+
 #[test]
-fn test_take_nonpod_by_ptr_simple() {
+fn test_take_pod_by_value() {
     let cxx = indoc! {"
-        uint32_t take_bob(const Bob* a) {
-            return a->a;
-        }
-        std::unique_ptr<Bob> make_bob(uint32_t a) {
-            auto b = std::make_unique<Bob>();
-            b->a = a;
-            return b;
+        uint32_t take_bob(Bob a) {
+            return a.a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         struct Bob {
             uint32_t a;
+            uint32_t b;
         };
-        std::unique_ptr<Bob> make_bob(uint32_t a);
-        uint32_t take_bob(const Bob* a);
+        uint32_t take_bob(Bob a);
     "};
     let rs = quote! {
-        let a = ffi::make_bob(12);
-        let a_ptr = a.into_raw();
-        assert_eq!(unsafe { ffi::take_bob(a_ptr) }, 12);
-        unsafe { cxx::UniquePtr::from_raw(a_ptr) }; // so we drop
+        let a = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(ffi::take_bob(a), 12);
     };
-    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
+    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_take_nonpod_by_ptr_in_method() {
+fn test_negative_take_as_pod_with_destructor() {
+    let cxx = indoc! {"
+        uint32_t take_bob(Bob a) {
+            return a.a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         struct Bob {
             uint32_t a;
+            uint32_t b;
+            inline ~Bob() {}
         };
-        #include <cstdint>
-        class A {
-        public:
-            A() {};
-            uint32_t take_bob(const Bob* a) const {
-                return a->a;
-            }
-            std::unique_ptr<Bob> make_bob(uint32_t a) const {
-                auto b = std::make_unique<Bob>();
-                b->a = a;
-                return b;
-            }
-            uint16_t a;
-        };
-
+        uint32_t take_bob(Bob a);
     "};
     let rs = quote! {
-        let a = ffi::A::new().within_unique_ptr();
-        let b = a.as_ref().unwrap().make_bob(12);
-        let b_ptr = b.into_raw();
-        assert_eq!(unsafe { a.as_ref().unwrap().take_bob(b_ptr) }, 12);
-        unsafe { cxx::UniquePtr::from_raw(b_ptr) }; // so we drop
+        let a = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(ffi::take_bob(a), 12);
     };
-    run_test("", hdr, rs, &["A", "Bob"], &[]);
+    run_test_expect_fail(cxx, hdr, rs, &["take_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_take_nonpod_by_ptr_in_wrapped_method() {
+fn test_negative_take_as_pod_with_move_constructor() {
+    let cxx = indoc! {"
+        uint32_t take_bob(Bob a) {
+            return a.a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        struct C {
-            C() {}
-            uint32_t a;
-        };
+        #include <type_traits>
         struct Bob {
             uint32_t a;
+            uint32_t b;
+            inline Bob(Bob&& other_bob) {}
         };
-        class A {
-        public:
-            A() {};
-            uint32_t take_bob(const Bob* a, C) const {
-                return a->a;
-            }
-            std::unique_ptr<Bob> make_bob(uint32_t a) const {
-                auto b = std::make_unique<Bob>();
-                b->a = a;
-                return b;
-            }
-            uint16_t a;
-        };
-
+        uint32_t take_bob(Bob a);
     "};
     let rs = quote! {
-        let a = ffi::A::new().within_unique_ptr();
-        let c = ffi::C::new().within_unique_ptr();
-        let b = a.as_ref().unwrap().make_bob(12);
-        let b_ptr = b.into_raw();
-        assert_eq!(unsafe { a.as_ref().unwrap().take_bob(b_ptr, c) }, 12);
-        unsafe { cxx::UniquePtr::from_raw(b_ptr) }; // so we drop
+        let a = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(ffi::take_bob(a), 12);
     };
-    run_test("", hdr, rs, &["A", "Bob", "C"], &[]);
+    run_test_expect_fail(cxx, hdr, rs, &["take_bob"], &["Bob"]);
 }
 
-fn run_char_test(builder_modifier: Option<BuilderModifier>) {
+#[ignore] // https://github.com/google/autocxx/issues/1252
+#[test]
+fn test_take_as_pod_with_is_relocatable() {
+    let cxx = indoc! {"
+        uint32_t take_bob(Bob a) {
+            return a.a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        struct C {
-            C() { test = \"hi\"; }
+        #include <type_traits>
+        struct Bob {
             uint32_t a;
-            const char* test;
-        };
-        class A {
-        public:
-            A() {};
-            uint32_t take_char(const char* a, C) const {
-                return a[0];
-            }
-            const char* make_char(C extra) const {
-                return extra.test;
-            }
-            uint16_t a;
+            uint32_t b;
+            inline Bob() {}
+            inline ~Bob() {}
+            inline Bob(Bob&& other_bob) { a = other_bob.a; b = other_bob.b; }
+            using IsRelocatable = std::true_type;
         };
-
+        uint32_t take_bob(Bob a);
     "};
     let rs = quote! {
-        let a = ffi::A::new().within_unique_ptr();
-        let c1 = ffi::C::new().within_unique_ptr();
-        let c2 = ffi::C::new().within_unique_ptr();
-        let ch = a.as_ref().unwrap().make_char(c1);
-        assert_eq!(unsafe { ch.as_ref()}.unwrap(), &104i8);
-        assert_eq!(unsafe { a.as_ref().unwrap().take_char(ch, c2) }, 104);
+        let a = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(ffi::take_bob(a), 12);
     };
-    run_test_ex(
-        "",
-        hdr,
-        rs,
-        directives_from_lists(&["A", "C"], &[], None),
-        builder_modifier,
-        None,
-        None,
-    );
-}
-
-#[test]
-fn test_take_char_by_ptr_in_wrapped_method() {
-    run_char_test(None)
-}
-
-#[test]
-fn test_take_char_by_ptr_in_wrapped_method_with_unsigned_chars() {
-    run_char_test(make_clang_arg_adder(&["-funsigned-char"]))
+    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_take_nonpod_by_mut_ref() {
+fn test_take_pod_by_ref() {
     let cxx = indoc! {"
-        uint32_t take_bob(Bob& a) {
+        uint32_t take_bob(const Bob& a) {
             return a.a;
         }
-        std::unique_ptr<Bob> make_bob(uint32_t a) {
-            auto b = std::make_unique<Bob>();
-            b->a = a;
-            return b;
-        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         struct Bob {
             uint32_t a;
+            uint32_t b;
         };
-        std::unique_ptr<Bob> make_bob(uint32_t a);
-        uint32_t take_bob(Bob& a);
+        uint32_t take_bob(const Bob& a);
     "};
     let rs = quote! {
-        let mut a = ffi::make_bob(12);
-        assert_eq!(ffi::take_bob(a.pin_mut()), 12);
+        let a = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(ffi::take_bob(&a), 12);
     };
-    // TODO confirm that the object really was mutated by C++ in this
-    // and similar tests.
-    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
+    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_return_nonpod_by_value() {
+fn test_take_pod_by_ref_and_ptr() {
     let cxx = indoc! {"
-        Bob::Bob(uint32_t a0, uint32_t b0)
-           : a(a0), b(b0) {}
-        Bob give_bob(uint32_t a) {
-            Bob c(a, 44);
-            return c;
+        uint32_t take_bob_ref(const Bob& a) {
+            return a.a;
         }
-        uint32_t take_bob(std::unique_ptr<Bob> a) {
+        uint32_t take_bob_ptr(const Bob* a) {
             return a->a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         struct Bob {
-            Bob(uint32_t a, uint32_t b);
             uint32_t a;
             uint32_t b;
         };
-        Bob give_bob(uint32_t a);
-        uint32_t take_bob(std::unique_ptr<Bob> a);
+        uint32_t take_bob_ref(const Bob& a);
+        uint32_t take_bob_ptr(const Bob* a);
     "};
     let rs = quote! {
-        let a = ffi::give_bob(13).within_unique_ptr();
-        assert_eq!(ffi::take_bob(a), 13);
+        let a = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(ffi::take_bob_ref(&a), 12);
     };
-    run_test(cxx, hdr, rs, &["take_bob", "give_bob", "Bob"], &[]);
+    run_test(cxx, hdr, rs, &["take_bob_ref", "take_bob_ptr"], &["Bob"]);
 }
 
 #[test]
-fn test_get_str_by_up() {
-    let cxx = indoc! {"
-    std::unique_ptr<std::string> get_str() {
-            return std::make_unique<std::string>(\"hello\");
-        }
-    "};
+fn test_const_pointers_as_references() {
     let hdr = indoc! {"
-        #include <string>
-        #include <memory>
-        std::unique_ptr<std::string> get_str();
+        #include <cstdint>
+        #include <cstring>
+        struct Bob {
+            uint32_t a;
+            uint32_t b;
+        };
+        class Thing {
+        public:
+            Thing() : value(3) {}
+            uint32_t value;
+            uint32_t add(const Bob* const bob) const { return value + bob->a; }
+        };
+        inline uint32_t sum(const Bob* const first, const Thing* const second) {
+            return first->a + first->b + second->value;
+        }
+        // The wrapper must pick the pointer overload, not this one.
+        inline uint32_t pick(const Bob&) { return 1; }
+        inline uint32_t pick(const Bob* const) { return 2; }
+        // A `const char*` is a string, so stays a pointer unless it's
+        // also listed in `str_params!`.
+        inline uint32_t add_length(const Bob* const bob, const char* text) {
+            return bob->a + std::strlen(text);
+        }
+        inline uint32_t add_str_length(const Bob* const bob, const char* text) {
+            return bob->a + std::strlen(text);
+        }
     "};
     let rs = quote! {
-        assert_eq!(ffi::get_str().as_ref().unwrap(), "hello");
+        let bob = ffi::Bob { a: 10, b: 20 };
+        let thing = ffi::Thing::new().within_unique_ptr();
+        assert_eq!(ffi::sum(&bob, &thing), 33);
+        assert_eq!(thing.add(&bob), 13);
+        assert_eq!(ffi::pick(&bob), 1);
+        assert_eq!(ffi::pick1(&bob), 2);
+        let text: *const ::std::os::raw::c_char = b"abc\0".as_ptr().cast();
+        assert_eq!(unsafe { ffi::add_length(&bob, text) }, 13);
+        assert_eq!(ffi::add_str_length(&bob, "abcd"), 14);
     };
-    run_test(cxx, hdr, rs, &["get_str"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["sum", "pick", "add_length", "add_str_length", "Thing"],
+            &["Bob"],
+            Some(quote! {
+                const_pointers_as_references!("sum")
+                const_pointers_as_references!("pick")
+                const_pointers_as_references!("add_length")
+                const_pointers_as_references!("add_str_length")
+                str_params!("add_str_length")
+                const_pointers_as_references!("Thing::add")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_get_str_by_value() {
-    let cxx = indoc! {"
-        std::string get_str() {
-            return \"hello\";
+fn test_byte_slices() {
+    let hdr = indoc! {"
+        #include <cstddef>
+        #include <cstdint>
+        #include <cstring>
+        inline uint32_t checksum(const void* data, size_t len) {
+            if (len != 0 && data == nullptr) {
+                return 0xffffffff;
+            }
+            uint32_t sum = 0;
+            const uint8_t* bytes = static_cast<const uint8_t*>(data);
+            for (size_t i = 0; i < len; i++) {
+                sum += bytes[i];
+            }
+            return sum;
         }
-    "};
-    let hdr = indoc! {"
-        #include <string>
-        std::string get_str();
+        inline bool is_null(const void* data, size_t) {
+            return data == nullptr;
+        }
+        class Filler {
+        public:
+            Filler() : value(7) {}
+            uint8_t value;
+            // Only the first pointer is followed by a length.
+            size_t fill(void* out, size_t len, void* context) const {
+                std::memset(out, value, len);
+                return context == nullptr ? len : 0;
+            }
+        };
     "};
     let rs = quote! {
-        assert_eq!(ffi::get_str().as_ref().unwrap(), "hello");
+        assert_eq!(ffi::checksum(&[1, 2, 3, 250]), 256);
+        assert_eq!(ffi::checksum(&[]), 0);
+        assert!(ffi::is_null(&[]));
+        assert!(!ffi::is_null(&[0]));
+        let filler = ffi::Filler::new().within_unique_ptr();
+        let mut buf = [0u8; 4];
+        assert_eq!(
+            unsafe { filler.fill(&mut buf, std::ptr::null_mut()) },
+            4
+        );
+        assert_eq!(buf, [7, 7, 7, 7]);
     };
-    run_test(cxx, hdr, rs, &["get_str"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["checksum", "is_null", "Filler"],
+            &[],
+            Some(quote! {
+                byte_slices!("checksum")
+                byte_slices!("is_null")
+                byte_slices!("Filler::fill")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_cycle_nonpod_with_str_by_ref() {
-    let cxx = indoc! {"
-        uint32_t take_bob(const Bob& a) {
-            return a.a;
-        }
-        std::unique_ptr<Bob> make_bob() {
-            auto a = std::make_unique<Bob>();
-            a->a = 32;
-            a->b = \"hello\";
-            return a;
-        }
-    "};
+fn test_catch_exceptions() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
         #include <memory>
-        struct Bob {
-            uint32_t a;
-            std::string b;
-        };
-        uint32_t take_bob(const Bob& a);
-        std::unique_ptr<Bob> make_bob();
+        #include <stdexcept>
+        #include <string>
+        inline uint32_t parse_digit(char c) {
+            if (c < '0' || c > '9') {
+                throw std::invalid_argument(std::string(\"not a digit: \") + c);
+            }
+            return c - '0';
+        }
+        inline void fail_oddly() {
+            throw 42;
+        }
+        inline std::unique_ptr<std::string> make_greeting(bool fail) {
+            if (fail) {
+                throw std::runtime_error(\"no greeting\");
+            }
+            return std::make_unique<std::string>(\"hello\");
+        }
     "};
     let rs = quote! {
-        let a = ffi::make_bob();
-        assert_eq!(ffi::take_bob(a.as_ref().unwrap()), 32);
+        assert_eq!(ffi::parse_digit(b'7' as std::os::raw::c_char).unwrap(), 7);
+        let e = ffi::parse_digit(b'x' as std::os::raw::c_char).unwrap_err();
+        assert_eq!(e.what(), "not a digit: x");
+        let e = ffi::fail_oddly().unwrap_err();
+        assert_eq!(e.what(), "unknown C++ exception");
+        assert_eq!(ffi::make_greeting(false).unwrap().to_str().unwrap(), "hello");
+        assert_eq!(ffi::make_greeting(true).unwrap_err().what(), "no greeting");
     };
-    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["parse_digit", "fail_oddly", "make_greeting"],
+            &[],
+            Some(quote! {
+                catch_exceptions!("parse_digit")
+                catch_exceptions!("fail_oddly")
+                catch_exceptions!("make_greeting")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_make_up() {
-    let cxx = indoc! {"
-        Bob::Bob() : a(3) {
-        }
-        uint32_t take_bob(const Bob& a) {
-            return a.a;
-        }
-    "};
+fn test_iterator_ranges() {
     let hdr = indoc! {"
         #include <cstdint>
-        class Bob {
-        public:
-            Bob();
-            uint32_t a;
-        };
-        uint32_t take_bob(const Bob& a);
+        #include <numeric>
+        #include <algorithm>
+        struct Point {
+            int32_t x;
+            int32_t y;
+        };
+        inline int32_t sum(const int32_t* begin, const int32_t* end) {
+            return std::accumulate(begin, end, 0);
+        }
+        inline void sort_values(int32_t* begin, int32_t* end) {
+            std::sort(begin, end);
+        }
+        inline int32_t sum_x(const Point* begin, const Point* end) {
+            int32_t total = 0;
+            for (const Point* p = begin; p != end; p++) {
+                total += p->x;
+            }
+            return total;
+        }
+        // Only the last two pointers are a range.
+        inline int32_t scaled_sum(const int32_t* scale, const int32_t* first, const int32_t* last) {
+            return *scale * std::accumulate(first, last, 0);
+        }
+        // Not a range: the pointers are to different types.
+        inline int32_t mixed(const int32_t* a, const int16_t* b) {
+            return *a + *b;
+        }
     "};
     let rs = quote! {
-        let a = ffi::Bob::new().within_unique_ptr(); // TODO test with all sorts of arguments.
-        assert_eq!(ffi::take_bob(a.as_ref().unwrap()), 3);
+        assert_eq!(ffi::sum(&[1, 2, 3, 4]), 10);
+        assert_eq!(ffi::sum(&[]), 0);
+        let mut values = [3, 1, 2];
+        ffi::sort_values(&mut values);
+        assert_eq!(values, [1, 2, 3]);
+        let points = [ffi::Point { x: 1, y: 2 }, ffi::Point { x: 3, y: 4 }];
+        assert_eq!(ffi::sum_x(&points), 4);
+        assert_eq!(unsafe { ffi::scaled_sum(&2, &[1, 2, 3]) }, 12);
     };
-    run_test(cxx, hdr, rs, &["Bob", "take_bob"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["sum", "sort_values", "sum_x", "scaled_sum", "mixed"],
+            &["Point"],
+            Some(quote! {
+                iterator_ranges!("sum", begin = end)
+                iterator_ranges!("sort_values", begin = end)
+                iterator_ranges!("sum_x", begin = end)
+                iterator_ranges!("scaled_sum", first = last)
+                iterator_ranges!("mixed", a = b)
+            }),
+        ),
+        None,
+        Some(make_string_finder(vec![
+            "listed in iterator_ranges!, but a and b".to_string(),
+        ])),
+        None,
+    );
 }
 
 #[test]
-fn test_make_up_with_args() {
-    let cxx = indoc! {"
-        Bob::Bob(uint32_t a0, uint32_t b0)
-           : a(a0), b(b0) {}
-        uint32_t take_bob(const Bob& a) {
-            return a.a;
-        }
-    "};
+fn test_span_params() {
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            Bob(uint32_t a, uint32_t b);
-            uint32_t a;
-            uint32_t b;
+        #include <span>
+        #include <string>
+        struct Point {
+            int32_t x;
+            int32_t y;
         };
-        uint32_t take_bob(const Bob& a);
+        inline void fill(std::span<int32_t> out) {
+            for (size_t i = 0; i < out.size(); i++) {
+                out[i] = static_cast<int32_t>(i * 10);
+            }
+        }
+        inline int32_t sum(std::span<const int32_t> values) {
+            int32_t total = 0;
+            for (int32_t v : values) {
+                total += v;
+            }
+            return total;
+        }
+        inline size_t count(std::span<int32_t> values) { return values.size(); }
+        inline void move_right(std::span<Point> points, int32_t by) {
+            for (Point& p : points) {
+                p.x += by;
+            }
+        }
+        inline size_t count_strings(std::span<std::string> strings) { return strings.size(); }
+        inline size_t count_misspelled(std::span<int32_t> values) { return values.size(); }
     "};
     let rs = quote! {
-        let a = ffi::Bob::new(12, 13).within_unique_ptr();
-        assert_eq!(ffi::take_bob(a.as_ref().unwrap()), 12);
+        let mut values = [1, 1, 1];
+        ffi::fill(&mut values);
+        assert_eq!(values, [0, 10, 20]);
+        assert_eq!(ffi::sum(&values), 30);
+        assert_eq!(ffi::sum(&values[1..]), 30);
+        assert_eq!(ffi::count(&mut [1, 2]), 2);
+        assert_eq!(ffi::count(&mut []), 0);
+        let mut points = [ffi::Point { x: 1, y: 2 }, ffi::Point { x: 3, y: 4 }];
+        ffi::move_right(&mut points, 5);
+        assert_eq!(points[0].x, 6);
+        assert_eq!(points[1].x, 8);
+        assert_eq!(points[1].y, 4);
     };
-    run_test(cxx, hdr, rs, &["take_bob", "Bob"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &[
+                "fill",
+                "sum",
+                "count",
+                "move_right",
+                "count_strings",
+                "count_misspelled",
+            ],
+            &["Point"],
+            Some(quote! {
+                std_span!("fill", out = "int32_t")
+                std_span!("sum", values = "const int32_t")
+                std_span!("count", values = "int32_t")
+                std_span!("move_right", points = "Point")
+                std_span!("count_strings", strings = "std::string")
+                std_span!("count_misspelled", vals = "int32_t")
+            }),
+        ),
+        make_clang_arg_adder(&["-std=c++20"]),
+        Some(make_string_finder(vec![
+            "listed in std_span!, but the parameter".to_string(),
+            "listed in std_span!, but has no parameter called vals".to_string(),
+        ])),
+        None,
+    );
 }
 
 #[test]
-#[ignore] // because we don't support unique_ptrs to primitives
-fn test_make_up_int() {
-    let cxx = indoc! {"
-        Bob::Bob(uint32_t a) : b(a) {
-        }
-    "};
+fn test_span_params_static_extent() {
     let hdr = indoc! {"
         #include <cstdint>
-        class Bob {
-        public:
-            Bob(uint32_t a);
-            uint32_t b;
-        };
+        #include <span>
+        inline int32_t sum_three(std::span<const int32_t, 3> values) {
+            return values[0] + values[1] + values[2];
+        }
     "};
     let rs = quote! {
-        let a = ffi::Bob::new(3).within_unique_ptr();
-        assert_eq!(a.as_ref().unwrap().b, 3);
+        assert_eq!(ffi::sum_three(&[1, 2]), 3);
     };
-    run_test(cxx, hdr, rs, &["Bob"], &[]);
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["sum_three"],
+            &[],
+            Some(quote! {
+                std_span!("sum_three", values = "const int32_t")
+            }),
+        ),
+        make_clang_arg_adder(&["-std=c++20"]),
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_enum_with_funcs() {
-    let cxx = indoc! {"
-        Bob give_bob() {
-            return Bob::BOB_VALUE_2;
-        }
-    "};
+fn test_slices() {
     let hdr = indoc! {"
+        #include <cstddef>
         #include <cstdint>
-        enum Bob {
-            BOB_VALUE_1,
-            BOB_VALUE_2,
-        };
-        Bob give_bob();
+        struct Point {
+            int32_t x;
+            int32_t y;
+        };
+        inline double sum(const double* values, size_t count) {
+            double total = 0;
+            for (size_t i = 0; i < count; i++) {
+                total += values[i];
+            }
+            return total;
+        }
+        inline bool is_null(const double* values, size_t) {
+            return values == nullptr;
+        }
+        inline void scale(double* values, size_t count, double factor) {
+            for (size_t i = 0; i < count; i++) {
+                values[i] *= factor;
+            }
+        }
+        inline int32_t total_x(const Point* points, size_t count) {
+            int32_t total = 0;
+            for (size_t i = 0; i < count; i++) {
+                total += points[i].x;
+            }
+            return total;
+        }
     "};
     let rs = quote! {
-        let a = ffi::Bob::BOB_VALUE_2;
-        let b = ffi::give_bob();
-        assert!(a == b);
+        let values: &[f64] = &[1.5, 2.5, 4.0];
+        assert_eq!(ffi::sum(values), 8.0);
+        assert_eq!(ffi::sum(&[]), 0.0);
+        assert!(ffi::is_null(&[]));
+        assert!(!ffi::is_null(&[0.0]));
+        let mut values = [1.0, 2.0];
+        ffi::scale(&mut values, 3.0);
+        assert_eq!(values, [3.0, 6.0]);
+        let points = [ffi::Point { x: 1, y: 2 }, ffi::Point { x: 3, y: 4 }];
+        assert_eq!(ffi::total_x(&points), 4);
     };
-    run_test(cxx, hdr, rs, &["Bob", "give_bob"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["sum", "is_null", "scale", "total_x"],
+            &["Point"],
+            Some(quote! {
+                slices!("sum")
+                slices!("is_null")
+                slices!("scale")
+                slices!("total_x")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_re_export() {
-    let cxx = indoc! {"
-        Bob give_bob() {
-            return Bob::BOB_VALUE_2;
-        }
-    "};
+fn test_output_buffers() {
     let hdr = indoc! {"
+        #include <cstddef>
         #include <cstdint>
-        enum Bob {
-            BOB_VALUE_1,
-            BOB_VALUE_2,
+        #include <cstring>
+        struct Input {
+            uint8_t value;
+            size_t count;
+        };
+        // Returns the number of bytes written, or the number needed if
+        // there isn't room.
+        inline size_t encode(const Input& in, uint8_t* out, size_t cap) {
+            if (cap < in.count) {
+                return in.count;
+            }
+            std::memset(out, in.value, in.count);
+            return in.count;
+        }
+        class Encoder {
+        public:
+            Encoder() : prefix(9) {}
+            uint8_t prefix;
+            size_t encode(void* out, size_t cap) const {
+                if (cap < 1) {
+                    return 1;
+                }
+                static_cast<uint8_t*>(out)[0] = prefix;
+                return 1;
+            }
         };
-        Bob give_bob();
     "};
     let rs = quote! {
-        let a = ffi::Bob::BOB_VALUE_2;
-        let b = ffi::give_bob();
-        assert!(a == b);
+        let input = ffi::Input { value: 5, count: 3 };
+        let mut buf = [0u8; 4];
+        assert_eq!(ffi::encode(&input, &mut buf), Ok(3));
+        assert_eq!(buf, [5, 5, 5, 0]);
+        let mut small = [0u8; 2];
+        assert_eq!(
+            ffi::encode(&input, &mut small),
+            Err(autocxx::BufferTooSmall { required: 3 })
+        );
+        assert_eq!(small, [0, 0]);
+        assert_eq!(
+            ffi::encode(&input, &mut []),
+            Err(autocxx::BufferTooSmall { required: 3 })
+        );
+        let encoder = ffi::Encoder::new().within_unique_ptr();
+        let mut one = [0u8; 1];
+        assert_eq!(encoder.encode(&mut one), Ok(1));
+        assert_eq!(one, [9]);
     };
     run_test_ex(
-        cxx,
+        "",
         hdr,
         rs,
-        directives_from_lists(&["Bob", "give_bob"], &[], None),
+        directives_from_lists(
+            &["encode", "Encoder"],
+            &["Input"],
+            Some(quote! {
+                output_buffers!("encode")
+                output_buffers!("Encoder::encode")
+            }),
+        ),
+        None,
         None,
         None,
-        Some(quote! { pub use ffi::Bob; }),
     );
 }
 
 #[test]
-fn test_enum_no_funcs() {
-    let cxx = indoc! {"
-    "};
+fn test_return_pod_by_ref_and_ptr() {
     let hdr = indoc! {"
-        enum Bob {
-            BOB_VALUE_1,
-            BOB_VALUE_2,
+        #include <cstdint>
+        struct B {
+            uint32_t a;
+        };
+        struct A {
+            B b;
         };
+        inline const B& return_b_ref(const A& a) {
+            return a.b;
+        }
+        inline const B* return_b_ptr(const A& a) {
+            return &a.b;
+        }
     "};
     let rs = quote! {
-        let a = ffi::Bob::BOB_VALUE_1;
-        let b = ffi::Bob::BOB_VALUE_2;
-        assert!(a != b);
+        let a = ffi::A { b: ffi::B { a: 3 } };
+        assert_eq!(ffi::return_b_ref(&a).a, 3);
+        let b_ptr = ffi::return_b_ptr(&a);
+        assert_eq!(unsafe { b_ptr.as_ref() }.unwrap().a, 3);
     };
-    run_test(cxx, hdr, rs, &["Bob"], &[]);
+    run_test("", hdr, rs, &["return_b_ref", "return_b_ptr"], &["A", "B"]);
 }
 
 #[test]
-fn test_enum_with_funcs_as_pod() {
+fn test_take_pod_by_mut_ref() {
     let cxx = indoc! {"
-        Bob give_bob() {
-            return Bob::BOB_VALUE_2;
+        uint32_t take_bob(Bob& a) {
+            a.b = 14;
+            return a.a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        enum Bob {
-            BOB_VALUE_1,
-            BOB_VALUE_2,
+        struct Bob {
+            uint32_t a;
+            uint32_t b;
         };
-        Bob give_bob();
+        uint32_t take_bob(Bob& a);
     "};
     let rs = quote! {
-        let a = ffi::Bob::BOB_VALUE_2;
-        let b = ffi::give_bob();
-        assert!(a == b);
+        let mut a = Box::pin(ffi::Bob { a: 12, b: 13 });
+        assert_eq!(ffi::take_bob(a.as_mut()), 12);
+        assert_eq!(a.b, 14);
     };
-    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
+    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
 }
 
-#[test] // works, but causes compile warnings
-fn test_take_pod_class_by_value() {
+#[test]
+fn test_take_nested_pod_by_value() {
     let cxx = indoc! {"
         uint32_t take_bob(Bob a) {
             return a.a;
@@ -1196,163 +1629,266 @@ fn test_take_pod_class_by_value() {
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        class Bob {
-        public:
+        struct Phil {
+            uint32_t d;
+        };
+        struct Bob {
             uint32_t a;
             uint32_t b;
+            Phil c;
         };
         uint32_t take_bob(Bob a);
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13 };
+        let a = ffi::Bob { a: 12, b: 13, c: ffi::Phil { d: 4 } };
         assert_eq!(ffi::take_bob(a), 12);
     };
+    // Should be no need to allowlist Phil below
     run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_pod_method() {
+fn test_take_nonpod_by_value() {
     let cxx = indoc! {"
-        uint32_t Bob::get_bob() const {
-            return a;
+        Bob::Bob(uint32_t a0, uint32_t b0)
+           : a(a0), b(b0) {}
+        uint32_t take_bob(Bob a) {
+            return a.a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
+        #include <string>
         struct Bob {
-        public:
+            Bob(uint32_t a, uint32_t b);
             uint32_t a;
             uint32_t b;
-            uint32_t get_bob() const;
+            std::string reason_why_this_is_nonpod;
         };
+        uint32_t take_bob(Bob a);
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(a.get_bob(), 12);
+        let a = ffi::Bob::new(12, 13).within_unique_ptr();
+        assert_eq!(ffi::take_bob(a), 12);
     };
-    run_test(cxx, hdr, rs, &[], &["Bob"]);
+    run_test(cxx, hdr, rs, &["take_bob", "Bob"], &[]);
 }
 
 #[test]
-#[ignore] // https://github.com/google/autocxx/issues/723
-fn test_constructors_for_specialized_types() {
-    // bindgen sometimes makes such opaque types as type Bob = u32[2];
+fn test_take_nonpod_by_ref() {
+    let cxx = indoc! {"
+        uint32_t take_bob(const Bob& a) {
+            return a.a;
+        }
+        std::unique_ptr<Bob> make_bob(uint32_t a) {
+            auto b = std::make_unique<Bob>();
+            b->a = a;
+            return b;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        template<typename T>
-        class A {
-            uint32_t foo() { return 12; };
-        private:
-            T a[2];
+        #include <memory>
+        struct Bob {
+            uint32_t a;
         };
-
-        typedef A<uint32_t> B;
-        typedef B C;
+        std::unique_ptr<Bob> make_bob(uint32_t a);
+        uint32_t take_bob(const Bob& a);
     "};
     let rs = quote! {
-        let a = ffi::C::new().within_unique_ptr();
-        assert_eq!(a.foo(), 12);
+        let a = ffi::make_bob(12);
+        assert_eq!(ffi::take_bob(&a), 12);
     };
-    run_test("", hdr, rs, &["C"], &[]);
+    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
 }
 
 #[test]
-fn test_pod_mut_method() {
+fn test_take_nonpod_by_up() {
     let cxx = indoc! {"
-        uint32_t Bob::get_bob() {
-            return a;
+        uint32_t take_bob(std::unique_ptr<Bob> a) {
+            return a->a;
+        }
+        std::unique_ptr<Bob> make_bob(uint32_t a) {
+            auto b = std::make_unique<Bob>();
+            b->a = a;
+            return b;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
+        #include <memory>
         struct Bob {
-        public:
             uint32_t a;
-            uint32_t b;
-            uint32_t get_bob();
         };
+
+        struct NOP { inline void take_bob(); };
+        std::unique_ptr<Bob> make_bob(uint32_t a);
+        uint32_t take_bob(std::unique_ptr<Bob> a);
     "};
     let rs = quote! {
-        let mut a = Box::pin(ffi::Bob { a: 12, b: 13 });
-        assert_eq!(a.as_mut().get_bob(), 12);
+        let a = ffi::make_bob(12);
+        assert_eq!(ffi::take_bob(a), 12);
     };
-    run_test(cxx, hdr, rs, &[], &["Bob"]);
+    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob", "NOP"], &[]);
 }
 
 #[test]
-fn test_define_int() {
+fn test_take_nonpod_by_ptr_simple() {
     let cxx = indoc! {"
+        uint32_t take_bob(const Bob* a) {
+            return a->a;
+        }
+        std::unique_ptr<Bob> make_bob(uint32_t a) {
+            auto b = std::make_unique<Bob>();
+            b->a = a;
+            return b;
+        }
     "};
     let hdr = indoc! {"
-        #define BOB 3
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        std::unique_ptr<Bob> make_bob(uint32_t a);
+        uint32_t take_bob(const Bob* a);
     "};
     let rs = quote! {
-        assert_eq!(ffi::BOB, 3);
+        let a = ffi::make_bob(12);
+        let a_ptr = a.into_raw();
+        assert_eq!(unsafe { ffi::take_bob(a_ptr) }, 12);
+        unsafe { cxx::UniquePtr::from_raw(a_ptr) }; // so we drop
     };
-    run_test(cxx, hdr, rs, &["BOB"], &[]);
+    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
 }
 
 #[test]
-fn test_define_str() {
-    let cxx = indoc! {"
-    "};
+fn test_take_nonpod_by_ptr_in_method() {
     let hdr = indoc! {"
-        #define BOB \"foo\"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        #include <cstdint>
+        class A {
+        public:
+            A() {};
+            uint32_t take_bob(const Bob* a) const {
+                return a->a;
+            }
+            std::unique_ptr<Bob> make_bob(uint32_t a) const {
+                auto b = std::make_unique<Bob>();
+                b->a = a;
+                return b;
+            }
+            uint16_t a;
+        };
+
     "};
     let rs = quote! {
-        assert_eq!(core::str::from_utf8(ffi::BOB).unwrap().trim_end_matches(char::from(0)), "foo");
+        let a = ffi::A::new().within_unique_ptr();
+        let b = a.as_ref().unwrap().make_bob(12);
+        let b_ptr = b.into_raw();
+        assert_eq!(unsafe { a.as_ref().unwrap().take_bob(b_ptr) }, 12);
+        unsafe { cxx::UniquePtr::from_raw(b_ptr) }; // so we drop
     };
-    run_test(cxx, hdr, rs, &["BOB"], &[]);
+    run_test("", hdr, rs, &["A", "Bob"], &[]);
 }
 
 #[test]
-fn test_i32_const() {
-    let cxx = indoc! {"
-    "};
+fn test_take_nonpod_by_ptr_in_wrapped_method() {
     let hdr = indoc! {"
         #include <cstdint>
-        const uint32_t BOB = 3;
+        #include <memory>
+        struct C {
+            C() {}
+            uint32_t a;
+        };
+        struct Bob {
+            uint32_t a;
+        };
+        class A {
+        public:
+            A() {};
+            uint32_t take_bob(const Bob* a, C) const {
+                return a->a;
+            }
+            std::unique_ptr<Bob> make_bob(uint32_t a) const {
+                auto b = std::make_unique<Bob>();
+                b->a = a;
+                return b;
+            }
+            uint16_t a;
+        };
+
     "};
     let rs = quote! {
-        assert_eq!(ffi::BOB, 3);
+        let a = ffi::A::new().within_unique_ptr();
+        let c = ffi::C::new().within_unique_ptr();
+        let b = a.as_ref().unwrap().make_bob(12);
+        let b_ptr = b.into_raw();
+        assert_eq!(unsafe { a.as_ref().unwrap().take_bob(b_ptr, c) }, 12);
+        unsafe { cxx::UniquePtr::from_raw(b_ptr) }; // so we drop
     };
-    run_test(cxx, hdr, rs, &["BOB"], &[]);
+    run_test("", hdr, rs, &["A", "Bob", "C"], &[]);
 }
 
-#[test]
-fn test_negative_rs_nonsense() {
-    // Really just testing the test infrastructure.
-    let cxx = indoc! {"
-    "};
+fn run_char_test(builder_modifier: Option<BuilderModifier>) {
     let hdr = indoc! {"
         #include <cstdint>
-        const uint32_t BOB = 3;
+        #include <memory>
+        struct C {
+            C() { test = \"hi\"; }
+            uint32_t a;
+            const char* test;
+        };
+        class A {
+        public:
+            A() {};
+            uint32_t take_char(const char* a, C) const {
+                return a[0];
+            }
+            const char* make_char(C extra) const {
+                return extra.test;
+            }
+            uint16_t a;
+        };
+
     "};
     let rs = quote! {
-        foo bar
+        let a = ffi::A::new().within_unique_ptr();
+        let c1 = ffi::C::new().within_unique_ptr();
+        let c2 = ffi::C::new().within_unique_ptr();
+        let ch = a.as_ref().unwrap().make_char(c1);
+        assert_eq!(unsafe { ch.as_ref()}.unwrap(), &104i8);
+        assert_eq!(unsafe { a.as_ref().unwrap().take_char(ch, c2) }, 104);
     };
-    run_test_expect_fail(cxx, hdr, rs, &["BOB"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["A", "C"], &[], None),
+        builder_modifier,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_negative_cpp_nonsense() {
-    // Really just testing the test infrastructure.
-    let cxx = indoc! {"
-    "};
-    let hdr = indoc! {"
-        #include <cstdint>
-        const uint32_t BOB = CAT;
-    "};
-    let rs = quote! {
-        assert_eq!(ffi::BOB, 3);
-    };
-    run_test_expect_fail(cxx, hdr, rs, &["BOB"], &[]);
+fn test_take_char_by_ptr_in_wrapped_method() {
+    run_char_test(None)
 }
 
 #[test]
-fn test_negative_make_nonpod() {
+fn test_take_char_by_ptr_in_wrapped_method_with_unsigned_chars() {
+    run_char_test(make_clang_arg_adder(&["-funsigned-char"]))
+}
+
+#[test]
+fn test_take_nonpod_by_mut_ref() {
     let cxx = indoc! {"
-        uint32_t take_bob(const Bob& a) {
+        uint32_t take_bob(Bob& a) {
             return a.a;
         }
         std::unique_ptr<Bob> make_bob(uint32_t a) {
@@ -1368,3391 +1904,6891 @@ fn test_negative_make_nonpod() {
             uint32_t a;
         };
         std::unique_ptr<Bob> make_bob(uint32_t a);
-        uint32_t take_bob(const Bob& a);
+        uint32_t take_bob(Bob& a);
     "};
     let rs = quote! {
-        ffi::Bob {};
-    };
-    let rs2 = quote! {
-        ffi::Bob { a: 12 };
-    };
-    let rs3 = quote! {
-        ffi::Bob { do_not_attempt_to_allocate_nonpod_types: [] };
+        let mut a = ffi::make_bob(12);
+        assert_eq!(ffi::take_bob(a.pin_mut()), 12);
     };
-    run_test_expect_fail(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
-    run_test_expect_fail(cxx, hdr, rs2, &["take_bob", "Bob", "make_bob"], &[]);
-    run_test_expect_fail(cxx, hdr, rs3, &["take_bob", "Bob", "make_bob"], &[]);
+    // TODO confirm that the object really was mutated by C++ in this
+    // and similar tests.
+    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
 }
 
 #[test]
-fn test_method_pass_pod_by_value() {
-    let cxx = indoc! {"
-        uint32_t Bob::get_bob(Anna) const {
-            return a;
-        }
-    "};
+fn test_mutate_opaque_by_mut_ref() {
     let hdr = indoc! {"
         #include <cstdint>
-        struct Anna {
-            uint32_t a;
-        };
-        struct Bob {
+        #include <string>
+        class Counter {
         public:
-            uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(Anna a) const;
-        };
-    "};
-    let rs = quote! {
-        let a = ffi::Anna { a: 14 };
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(a), 12);
-    };
-    run_test(cxx, hdr, rs, &[], &["Bob", "Anna"]);
-}
-
-fn perform_asan_doom_test(into_raw: TokenStream, box_type: TokenStream) {
-    if std::env::var_os("AUTOCXX_ASAN").is_none() {
-        return;
-    }
-    // Testing that we get an asan fail when it's enabled.
-    // Really just testing our CI is working to spot ASAN mistakes.
-    let hdr = indoc! {"
-        #include <cstddef>
-        struct A {
-            int a;
+            Counter() : count(0) {}
+            ~Counter() {}
+            uint32_t get() const { return count; }
+            uint32_t count;
         };
-        inline size_t how_big_is_a() {
-            return sizeof(A);
+        inline void bump(Counter& c) {
+            c.count++;
+        }
+        // Returning a std::string needs a C++ wrapper, which must pass
+        // the reference straight through.
+        inline std::string bump_and_describe(Counter& c) {
+            c.count++;
+            return std::to_string(c.count);
         }
     "};
     let rs = quote! {
-        let a = #box_type::emplace(ffi::A::new());
-        unsafe {
-            let a_raw = #into_raw;
-            // Intentional memory unsafety. Don't @ me.
-            let a_offset_into_doom = a_raw.offset(ffi::how_big_is_a().try_into().unwrap());
-            a_offset_into_doom.write_bytes(0x69, 1);
-            #box_type::from_raw(a_raw); // to delete. If we haven't yet crashed.
-        }
+        let mut c = ffi::Counter::new().within_box();
+        ffi::bump(c.as_mut());
+        ffi::bump(c.as_mut());
+        assert_eq!(c.get(), 2);
+        assert_eq!(ffi::bump_and_describe(c.as_mut()).to_str().unwrap(), "3");
+        assert_eq!(c.get(), 3);
     };
-    run_test_expect_fail("", hdr, rs, &["A", "how_big_is_a"], &[]);
-}
-
-#[test]
-fn test_asan_working_as_expected_for_cpp_allocations() {
-    perform_asan_doom_test(quote! { a.into_raw() }, quote! { UniquePtr })
-}
-
-#[test]
-fn test_asan_working_as_expected_for_rust_allocations() {
-    perform_asan_doom_test(
-        quote! { Box::into_raw(core::pin::Pin::into_inner_unchecked(a)) },
-        quote! { Box },
-    )
+    run_test("", hdr, rs, &["Counter", "bump", "bump_and_describe"], &[]);
 }
 
 #[test]
-fn test_inline_method() {
+fn test_return_nonpod_by_value() {
+    let cxx = indoc! {"
+        Bob::Bob(uint32_t a0, uint32_t b0)
+           : a(a0), b(b0) {}
+        Bob give_bob(uint32_t a) {
+            Bob c(a, 44);
+            return c;
+        }
+        uint32_t take_bob(std::unique_ptr<Bob> a) {
+            return a->a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Anna {
-            uint32_t a;
-        };
+        #include <memory>
         struct Bob {
-        public:
+            Bob(uint32_t a, uint32_t b);
             uint32_t a;
             uint32_t b;
-            uint32_t get_bob(Anna) const {
-                return a;
-            }
         };
+        Bob give_bob(uint32_t a);
+        uint32_t take_bob(std::unique_ptr<Bob> a);
     "};
     let rs = quote! {
-        let a = ffi::Anna { a: 14 };
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(a), 12);
+        let a = ffi::give_bob(13).within_unique_ptr();
+        assert_eq!(ffi::take_bob(a), 13);
     };
-    run_test("", hdr, rs, &[], &["Bob", "Anna"]);
+    run_test(cxx, hdr, rs, &["take_bob", "give_bob", "Bob"], &[]);
 }
 
 #[test]
-fn test_method_pass_pod_by_reference() {
+fn test_get_str_by_up() {
     let cxx = indoc! {"
-        uint32_t Bob::get_bob(const Anna&) const {
-            return a;
+    std::unique_ptr<std::string> get_str() {
+            return std::make_unique<std::string>(\"hello\");
         }
     "};
     let hdr = indoc! {"
-        #include <cstdint>
-        struct Anna {
-            uint32_t a;
-        };
-        struct Bob {
-        public:
-            uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(const Anna& a) const;
-        };
+        #include <string>
+        #include <memory>
+        std::unique_ptr<std::string> get_str();
     "};
     let rs = quote! {
-        let a = ffi::Anna { a: 14 };
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(&a), 12);
+        assert_eq!(ffi::get_str().as_ref().unwrap(), "hello");
     };
-    run_test(cxx, hdr, rs, &[], &["Bob", "Anna"]);
+    run_test(cxx, hdr, rs, &["get_str"], &[]);
 }
 
 #[test]
-fn test_method_pass_pod_by_mut_reference() {
+fn test_get_str_by_value() {
     let cxx = indoc! {"
-        uint32_t Bob::get_bob(Anna&) const {
-            return a;
+        std::string get_str() {
+            return \"hello\";
         }
     "};
     let hdr = indoc! {"
-        #include <cstdint>
-        struct Anna {
-            uint32_t a;
-        };
-        struct Bob {
-        public:
-            uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(Anna& a) const;
-        };
+        #include <string>
+        std::string get_str();
     "};
     let rs = quote! {
-        let mut a = Box::pin(ffi::Anna { a: 14 });
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(a.as_mut()), 12);
+        assert_eq!(ffi::get_str().as_ref().unwrap(), "hello");
     };
-    run_test(cxx, hdr, rs, &[], &["Bob", "Anna"]);
+    run_test(cxx, hdr, rs, &["get_str"], &[]);
 }
 
 #[test]
-fn test_method_pass_pod_by_up() {
+fn test_cycle_nonpod_with_str_by_ref() {
     let cxx = indoc! {"
-        uint32_t Bob::get_bob(std::unique_ptr<Anna>) const {
+        uint32_t take_bob(const Bob& a) {
+            return a.a;
+        }
+        std::unique_ptr<Bob> make_bob() {
+            auto a = std::make_unique<Bob>();
+            a->a = 32;
+            a->b = \"hello\";
             return a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
+        #include <string>
         #include <memory>
-        struct Anna {
-            uint32_t a;
-        };
         struct Bob {
-        public:
             uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(std::unique_ptr<Anna> z) const;
+            std::string b;
         };
+        uint32_t take_bob(const Bob& a);
+        std::unique_ptr<Bob> make_bob();
     "};
     let rs = quote! {
-        let a = ffi::Anna { a: 14 };
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(cxx::UniquePtr::new(a)), 12);
+        let a = ffi::make_bob();
+        assert_eq!(ffi::take_bob(a.as_ref().unwrap()), 32);
     };
-    run_test(cxx, hdr, rs, &[], &["Bob", "Anna"]);
+    run_test(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
 }
 
 #[test]
-fn test_method_pass_nonpod_by_value() {
+fn test_make_up() {
     let cxx = indoc! {"
-        uint32_t Bob::get_bob(Anna) const {
-            return a;
+        Bob::Bob() : a(3) {
         }
-        Anna give_anna() {
-            Anna a;
-            a.a = 10;
-            return a;
+        uint32_t take_bob(const Bob& a) {
+            return a.a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        struct Anna {
-            uint32_t a;
-            std::string b;
-        };
-        Anna give_anna();
-        struct Bob {
+        class Bob {
         public:
+            Bob();
             uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(Anna a) const;
         };
+        uint32_t take_bob(const Bob& a);
     "};
     let rs = quote! {
-        let a = ffi::give_anna().within_box();
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(a), 12);
+        let a = ffi::Bob::new().within_unique_ptr(); // TODO test with all sorts of arguments.
+        assert_eq!(ffi::take_bob(a.as_ref().unwrap()), 3);
     };
-    run_test(cxx, hdr, rs, &["Anna", "give_anna"], &["Bob"]);
+    run_test(cxx, hdr, rs, &["Bob", "take_bob"], &[]);
 }
 
 #[test]
-fn test_pass_two_nonpod_by_value() {
-    let cxx = indoc! {"
-        void take_a(A, A) {
-        }
-    "};
+fn test_unique_ptr_out_params() {
     let hdr = indoc! {"
-        #include <string>
-        struct A {
-            std::string b;
+        #include <cstdint>
+        #include <memory>
+        class Bob {
+        public:
+            Bob(uint32_t a0) : a(a0) {}
+            uint32_t a;
         };
-        void take_a(A, A);
+        class Fred {
+        public:
+            Fred(uint32_t b0) : b(b0) {}
+            uint32_t b;
+        };
+        inline void make_pair(uint32_t n, std::unique_ptr<Bob>& bob, std::unique_ptr<Fred>& fred) {
+            bob = std::make_unique<Bob>(n);
+            if (n < 10) {
+                fred = std::make_unique<Fred>(n * 2);
+            }
+        }
     "};
     let rs = quote! {
-        let a = ffi::A::new().within_unique_ptr();
-        let a2 = ffi::A::new().within_unique_ptr();
-        ffi::take_a(a, a2);
+        let (bob, fred) = ffi::make_pair(3);
+        assert_eq!(bob.as_ref().unwrap().a, 3);
+        assert_eq!(fred.as_ref().unwrap().b, 6);
+        // If one output isn't created, the other is still passed back.
+        let (bob, fred) = ffi::make_pair(20);
+        assert_eq!(bob.as_ref().unwrap().a, 20);
+        assert!(fred.is_null());
     };
-    run_test(cxx, hdr, rs, &["A", "take_a"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["make_pair", "Bob", "Fred"],
+            &[],
+            Some(quote! { out_params!("make_pair") }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_issue_931() {
-    let cxx = "";
+fn test_optional_out_params() {
     let hdr = indoc! {"
-    namespace a {
-        struct __cow_string {
-          __cow_string();
-        };
-        class b {
-        public:
-          __cow_string c;
-        };
-        class j {
-        public:
-          b d;
-        };
-        template <typename> class e;
-        } // namespace a
-        template <typename> struct f {};
-        namespace llvm {
-        template <class> class g {
-          union {
-            f<a::j> h;
-          };
-        };
-        class MemoryBuffer {
-        public:
-          g<a::e<MemoryBuffer>> i;
-        };
-        } // namespace llvm
+        #include <cstdint>
+        #include <optional>
+        struct Point {
+            int32_t x;
+            int32_t y;
+        };
+        inline void try_compute(int in, std::optional<int>& out, std::optional<Point>& point) {
+            if (in > 0) {
+                out = in * 2;
+            }
+            if (in > 10) {
+                point = Point { in, -in };
+            }
+        }
     "};
-    let rs = quote! {};
-    run_test(cxx, hdr, rs, &["llvm::MemoryBuffer"], &[]);
-}
-
-#[test]
-fn test_issue_936() {
-    let cxx = "";
-    let hdr = indoc! {"
-    struct a;
-    class B {
-    public:
-        B(a &, bool);
+    let rs = quote! {
+        let (out, point) = ffi::try_compute(c_int(3));
+        assert_eq!(out, Some(c_int(6)));
+        assert!(point.is_none());
+        let (out, point) = ffi::try_compute(c_int(11));
+        assert_eq!(out, Some(c_int(22)));
+        let point = point.unwrap();
+        assert_eq!((point.x, point.y), (11, -11));
+        let (out, point) = ffi::try_compute(c_int(-1));
+        assert_eq!(out, None);
+        assert!(point.is_none());
     };
-    "};
-    let rs = quote! {};
-    run_test(cxx, hdr, rs, &["B"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["try_compute"],
+            &["Point"],
+            Some(quote! { out_params!("try_compute") }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_method_pass_nonpod_by_value_with_up() {
-    // Checks that existing UniquePtr params are not wrecked
-    // by the conversion we do here.
-    let cxx = indoc! {"
-        uint32_t Bob::get_bob(Anna, std::unique_ptr<Anna>) const {
-            return a;
-        }
-        Anna give_anna() {
-            Anna a;
-            a.a = 10;
-            return a;
-        }
-    "};
+fn test_string_callback() {
     let hdr = indoc! {"
         #include <cstdint>
+        #include <functional>
         #include <string>
-        #include <memory>
-        struct Anna {
-            uint32_t a;
-            std::string b;
-        };
-        Anna give_anna();
-        struct Bob {
-        public:
-            uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(Anna a, std::unique_ptr<Anna>) const;
-        };
+        inline void lookup_name(uint32_t calls, std::function<void(const std::string&)> on_name) {
+            for (uint32_t i = 0; i < calls; i++) {
+                on_name(std::string(\"name\") + std::to_string(i));
+            }
+        }
     "};
     let rs = quote! {
-        let a = ffi::give_anna().within_unique_ptr();
-        let a2 = ffi::give_anna().within_unique_ptr();
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(a, a2), 12);
+        assert!(unsafe { ffi::lookup_name(0) }.is_null());
+        assert_eq!(unsafe { ffi::lookup_name(1) }.to_str().unwrap(), "name0");
+        // The last string passed to the callback is the one returned.
+        assert_eq!(unsafe { ffi::lookup_name(2) }.to_str().unwrap(), "name1");
     };
-    run_test(cxx, hdr, rs, &["Anna", "give_anna"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["lookup_name"],
+            &[],
+            Some(quote! { string_callback!("lookup_name") }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_issue_940() {
-    let cxx = "";
+fn test_string_callback_rust_string() {
     let hdr = indoc! {"
-    template <class> class b;
-    template <class = void> struct c;
-    struct identity;
-    template <class, class, class e, class> class f {
-    using g = e;
-    g h;
-    };
-    template <class i, class k = c<>, class l = b<i>>
-    using j = f<i, identity, k, l>;
-    class n;
-    class RenderFrameHost {
-    public:
-    virtual void o(const j<n> &);
-    virtual ~RenderFrameHost() {}
-    };
+        #include <functional>
+        #include <string>
+        namespace names {
+        inline void lookup_name(bool found, std::function<void(std::string)> on_name) {
+            if (found) {
+                on_name(\"Bob\");
+            }
+        }
+        }
     "};
-    let rs = quote! {};
+    let rs = quote! {
+        assert_eq!(
+            unsafe { ffi::names::lookup_name(true) },
+            Some("Bob".to_string())
+        );
+        assert_eq!(unsafe { ffi::names::lookup_name(false) }, None);
+    };
     run_test_ex(
-        cxx,
+        "",
         hdr,
         rs,
-        directives_from_lists(&["RenderFrameHost"], &[], None),
-        make_cpp17_adder(),
+        directives_from_lists(
+            &["names::lookup_name"],
+            &[],
+            Some(quote! {
+                string_callback!("names::lookup_name")
+                rust_string_returns!(lossy)
+            }),
+        ),
+        None,
         None,
         None,
     );
 }
 
 #[test]
-fn test_method_pass_nonpod_by_reference() {
-    let cxx = indoc! {"
-        uint32_t Bob::get_bob(const Anna&) const {
-            return a;
-        }
-        Anna give_anna() {
-            Anna a;
-            a.a = 10;
-            return a;
-        }
-    "};
+fn test_special_floats_passed_through() {
     let hdr = indoc! {"
-        #include <cstdint>
-        #include <string>
-        struct Anna {
-            uint32_t a;
-            std::string b;
-        };
-        Anna give_anna();
-        struct Bob {
-        public:
-            uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(const Anna& a) const;
-        };
+        #include <cmath>
+        inline bool is_nan(double a) { return std::isnan(a); }
+        inline bool is_positive_infinity(float a) { return std::isinf(a) && a > 0; }
+        inline double identity(double a) { return a; }
     "};
     let rs = quote! {
-        let a = ffi::give_anna().within_box();
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(a.as_ref().get_ref()), 12);
+        assert!(ffi::is_nan(f64::NAN));
+        assert!(!ffi::is_nan(f64::INFINITY));
+        assert!(ffi::is_positive_infinity(f32::INFINITY));
+        assert!(!ffi::is_positive_infinity(f32::NEG_INFINITY));
+        assert!(ffi::identity(f64::NAN).is_nan());
+        assert_eq!(ffi::identity(f64::NEG_INFINITY), f64::NEG_INFINITY);
+        assert_eq!(ffi::identity(-0.0).to_bits(), (-0.0f64).to_bits());
     };
-    run_test(cxx, hdr, rs, &["Anna", "give_anna"], &["Bob"]);
+    run_test(
+        "",
+        hdr,
+        rs,
+        &["is_nan", "is_positive_infinity", "identity"],
+        &[],
+    );
 }
 
 #[test]
-fn test_method_pass_nonpod_by_mut_reference() {
-    let cxx = indoc! {"
-        uint32_t Bob::get_bob(Anna&) const {
-            return a;
-        }
-        Anna give_anna() {
-            Anna a;
-            a.a = 10;
-            return a;
-        }
-    "};
+fn test_integer_enum_params() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        struct Anna {
-            uint32_t a;
-            std::string b;
-        };
-        Anna give_anna();
-        struct Bob {
+        enum class Mode : uint32_t { Off = 0, On = 1, Auto = 4 };
+        inline uint32_t mode_value(Mode m) { return static_cast<uint32_t>(m); }
+        class Device {
         public:
-            uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(Anna& a) const;
+            Device() : mode(Mode::Off) {}
+            void set_mode(Mode m, uint32_t) { mode = m; }
+            bool is_auto() const { return mode == Mode::Auto; }
+        private:
+            Mode mode;
         };
     "};
     let rs = quote! {
-        let mut a = ffi::give_anna().within_unique_ptr();
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(a.as_mut().unwrap()), 12);
+        assert_eq!(ffi::mode_value(4), 4);
+        let mut device = ffi::Device::new().within_unique_ptr();
+        device.pin_mut().set_mode(4, 0);
+        assert!(device.is_auto());
+        let bad = std::panic::catch_unwind(|| ffi::mode_value(2));
+        assert_eq!(bad.is_err(), cfg!(debug_assertions));
     };
-    run_test(cxx, hdr, rs, &["Anna", "give_anna"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["mode_value", "Device", "Mode"],
+            &[],
+            Some(quote! {
+                integer_enum_params!("mode_value")
+                integer_enum_params!("Device::set_mode")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_method_pass_nonpod_by_up() {
-    let cxx = indoc! {"
-        uint32_t Bob::get_bob(std::unique_ptr<Anna>) const {
-            return a;
-        }
-        Anna give_anna() {
-            Anna a;
-            a.a = 10;
-            return a;
-        }
-    "};
+fn test_reject_nan() {
     let hdr = indoc! {"
+        #include <cmath>
         #include <cstdint>
-        #include <memory>
-        #include <string>
-        struct Anna {
-            uint32_t a;
-            std::string b;
-        };
-        Anna give_anna();
-        struct Bob {
+        // Must not be passed NaN.
+        inline double checked_sqrt(double a) { return std::sqrt(a); }
+        class Solver {
         public:
-            uint32_t a;
-            uint32_t b;
-            uint32_t get_bob(std::unique_ptr<Anna> z) const;
+            Solver() : tolerance(0.0) {}
+            // Must not be passed NaN.
+            void set_tolerance(float t, uint32_t) { tolerance = t; }
+            float tolerance;
         };
     "};
     let rs = quote! {
-        let a = ffi::give_anna().within_unique_ptr();
-        let b = ffi::Bob { a: 12, b: 13 };
-        assert_eq!(b.get_bob(a), 12);
+        assert_eq!(ffi::checked_sqrt(4.0), 2.0);
+        assert_eq!(ffi::checked_sqrt(f64::INFINITY), f64::INFINITY);
+        let mut solver = ffi::Solver::new().within_unique_ptr();
+        solver.pin_mut().set_tolerance(f32::INFINITY, 1);
+        assert_eq!(solver.tolerance, f32::INFINITY);
+        let nan_sqrt = std::panic::catch_unwind(|| ffi::checked_sqrt(f64::NAN));
+        assert_eq!(nan_sqrt.is_err(), cfg!(debug_assertions));
     };
-    run_test(cxx, hdr, rs, &["give_anna"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["checked_sqrt", "Solver"],
+            &[],
+            Some(quote! {
+                reject_nan!("checked_sqrt")
+                reject_nan!("Solver::set_tolerance")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
-
 #[test]
-fn test_method_return_nonpod_by_value() {
-    let cxx = indoc! {"
-        Anna Bob::get_anna() const {
-            Anna a;
-            a.a = 12;
-            return a;
-        }
-    "};
+fn test_operator_bool() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        struct Anna {
-            uint32_t a;
-            std::string b;
-        };
-        struct Bob {
+        class Handle {
         public:
-            uint32_t a;
-            uint32_t b;
-            Anna get_anna() const;
+            Handle(uint32_t fd0) : fd(fd0) {}
+            explicit operator bool() const { return fd != 0; }
+        private:
+            uint32_t fd;
+        };
+        struct Flag {
+            bool set;
+            explicit operator bool() const { return set; }
         };
     "};
     let rs = quote! {
-        let b = ffi::Bob { a: 12, b: 13 };
-        let a = b.get_anna().within_unique_ptr();
-        assert!(!a.is_null());
+        let valid = ffi::Handle::new(3).within_unique_ptr();
+        assert!(valid.is_valid());
+        let invalid = ffi::Handle::new(0).within_unique_ptr();
+        assert!(!invalid.is_valid());
+        assert!(ffi::Flag { set: true }.as_bool());
+        assert!(!ffi::Flag { set: false }.as_bool());
     };
-    run_test(cxx, hdr, rs, &["Anna"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Handle"],
+            &["Flag"],
+            Some(quote! {
+                operator_bool!("Handle", is_valid)
+                operator_bool!("Flag")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_pass_string_by_value() {
-    let cxx = indoc! {"
-        uint32_t measure_string(std::string z) {
-            return z.length();
-        }
-        std::unique_ptr<std::string> get_msg() {
-            return std::make_unique<std::string>(\"hello\");
-        }
-    "};
+fn test_operator_not() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        #include <memory>
-        uint32_t measure_string(std::string a);
-        std::unique_ptr<std::string> get_msg();
+        class Stream {
+        public:
+            Stream(uint32_t errors0) : errors(errors0) {}
+            bool operator!() const { return errors != 0; }
+        private:
+            uint32_t errors;
+        };
+        struct Mask {
+            uint32_t bits;
+            bool operator!() const { return bits == 0; }
+        };
     "};
     let rs = quote! {
-        let a = ffi::get_msg();
-        let c = ffi::measure_string(a);
-        assert_eq!(c, 5);
+        let good = ffi::Stream::new(0).within_unique_ptr();
+        assert!(!good.failed());
+        let bad = ffi::Stream::new(2).within_unique_ptr();
+        assert!(bad.failed());
+        assert!(ffi::Mask { bits: 0 }.logical_not());
+        assert!(!ffi::Mask { bits: 4 }.logical_not());
     };
-    run_test(cxx, hdr, rs, &["measure_string", "get_msg"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Stream"],
+            &["Mask"],
+            Some(quote! {
+                operator_not!("Stream", failed)
+                operator_not!("Mask")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_return_string_by_value() {
-    let cxx = indoc! {"
-        std::string get_msg() {
-            return \"hello\";
-        }
-    "};
+fn test_bitmask_enum() {
     let hdr = indoc! {"
-        #include <string>
-        std::string get_msg();
+        #include <cstdint>
+        enum Permissions : uint32_t {
+            Read = 1,
+            Write = 2,
+            Execute = 4,
+        };
+        enum Mode : uint8_t {
+            Fast = 1,
+            Safe = 2,
+        };
+        inline Permissions read_write() { return static_cast<Permissions>(Read | Write); }
+        inline Permissions with_unknown_bits() { return static_cast<Permissions>(Read | 0x100); }
+        inline uint32_t count_permissions(Permissions p) {
+            return ((p & Read) ? 1 : 0) + ((p & Write) ? 1 : 0) + ((p & Execute) ? 1 : 0);
+        }
+        inline Mode all_modes() { return static_cast<Mode>(Fast | Safe); }
+        inline Mode unknown_mode() { return static_cast<Mode>(0x80); }
     "};
     let rs = quote! {
-        let a = ffi::get_msg();
-        assert!(a.as_ref().unwrap() == "hello");
+        use ffi::Permissions;
+        let rw = ffi::read_write();
+        assert_eq!(rw, Permissions::Read | Permissions::Write);
+        assert!(rw.contains(Permissions::Write));
+        assert!(!rw.contains(Permissions::Execute));
+        assert_eq!(ffi::count_permissions(rw | Permissions::Execute), 3);
+        assert_eq!(ffi::count_permissions(Permissions::empty()), 0);
+        let unknown = ffi::with_unknown_bits();
+        assert_eq!(unknown.bits(), 0x101);
+        assert_eq!(Permissions::from_bits(unknown.bits()), None);
+        assert_eq!(Permissions::from_bits_truncate(unknown.bits()), Permissions::Read);
+        assert_eq!(Permissions::all().bits(), 7);
+        assert_eq!(ffi::all_modes(), ffi::Mode::Fast | ffi::Mode::Safe);
+        assert!(std::panic::catch_unwind(|| ffi::unknown_mode()).is_err());
     };
-    run_test(cxx, hdr, rs, &["get_msg"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &[
+                "read_write",
+                "with_unknown_bits",
+                "count_permissions",
+                "all_modes",
+                "unknown_mode",
+            ],
+            &[],
+            Some(quote! {
+                bitmask_enum!("Permissions")
+                bitmask_enum!("Mode", reject_unknown_bits)
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
-fn test_method_pass_string_by_value() {
-    let cxx = indoc! {"
-        uint32_t Bob::measure_string(std::string z) const {
-            return z.length();
-        }
-        std::unique_ptr<std::string> get_msg() {
-            return std::make_unique<std::string>(\"hello\");
+fn test_enum_display() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        enum Color {
+            Red,
+            Green,
+            Blue,
+        };
+        enum Permissions : uint32_t {
+            Read = 1,
+            Write = 2,
+        };
+        namespace names {
+            inline const char* toString(Color c) {
+                switch (c) {
+                    case Red: return \"red\";
+                    case Green: return \"green\";
+                    default: return nullptr;
+                }
+            }
+            inline const char* toString(Permissions p) {
+                switch (p) {
+                    case Read: return \"read\";
+                    case Write: return \"write\";
+                    default: return nullptr;
+                }
+            }
         }
     "};
+    let rs = quote! {
+        use ffi::{Color, Permissions};
+        assert_eq!(Color::Red.to_string(), "red");
+        assert_eq!(format!("{}", Color::Green), "green");
+        assert_eq!(Color::Blue.to_string(), "2");
+        assert!(Color::cpp_display_name(Color::Blue).is_null());
+        assert_eq!(Permissions::Write.to_string(), "write");
+        assert_eq!((Permissions::Read | Permissions::Write).to_string(), "3");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Color", "Permissions"],
+            &[],
+            Some(quote! {
+                bitmask_enum!("Permissions")
+                enum_display!("Color", "names::toString")
+                enum_display!("Permissions", "names::toString")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_bitmask_params() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        #include <memory>
-        struct Bob {
-        public:
-            uint32_t a;
-            uint32_t b;
-            uint32_t measure_string(std::string a) const;
+        enum Permissions : uint32_t {
+            Read = 1,
+            Write = 2,
+            Execute = 4,
         };
-        std::unique_ptr<std::string> get_msg();
+        enum Mode : uint8_t {
+            Fast = 1,
+            Safe = 2,
+        };
+        inline uint32_t count_flags(uint32_t flags, uint8_t mode, uint32_t unrelated) {
+            return ((flags & Read) ? 1 : 0) + ((flags & Write) ? 1 : 0) + ((flags & Execute) ? 1 : 0)
+                + ((mode & Fast) ? 1 : 0) + ((mode & Safe) ? 1 : 0) + unrelated;
+        }
+        inline bool is_safe(Mode mode) { return (mode & Safe) != 0; }
     "};
     let rs = quote! {
-        let a = ffi::get_msg();
-        let b = ffi::Bob { a: 12, b: 13 };
-        let c = b.measure_string(a);
-        assert_eq!(c, 5);
+        use ffi::{Mode, Permissions};
+        assert_eq!(ffi::count_flags(Permissions::Read | Permissions::Write, Mode::Fast, 10), 13);
+        assert_eq!(ffi::count_flags(Permissions::from_bits_retain(0x100), Mode::empty(), 0), 0);
+        assert!(std::panic::catch_unwind(|| {
+            ffi::count_flags(Permissions::empty(), Mode::from_bits_retain(0x80), 0)
+        })
+        .is_err());
+        assert!(ffi::is_safe(Mode::Safe));
+        assert!(std::panic::catch_unwind(|| ffi::is_safe(Mode::from_bits_retain(0x80))).is_err());
     };
-    run_test(cxx, hdr, rs, &["Bob", "get_msg"], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["count_flags", "is_safe", "Permissions", "Mode"],
+            &[],
+            Some(quote! {
+                bitmask_enum!("Permissions")
+                bitmask_enum!("Mode", reject_unknown_bits)
+                bitmask_params!("count_flags", flags = "Permissions", mode = "Mode")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_method_return_string_by_value() {
-    let cxx = indoc! {"
-        std::string Bob::get_msg() const {
-            return \"hello\";
-        }
-    "};
+fn test_ord() {
     let hdr = indoc! {"
         #include <cstdint>
         #include <string>
-        struct Bob {
+        struct Version {
+            uint32_t major;
+            uint32_t minor;
+            bool operator<(const Version& other) const {
+                return major < other.major || (major == other.major && minor < other.minor);
+            }
+        };
+        class Name {
         public:
-            uint32_t a;
-            uint32_t b;
-            std::string get_msg() const;
+            Name(const std::string& s0) : s(s0) {}
+            bool operator<(const Name& other) const { return s < other.s; }
+        private:
+            std::string s;
         };
     "};
     let rs = quote! {
-        let b = ffi::Bob { a: 12, b: 13 };
-        let a = b.get_msg();
-        assert!(a.as_ref().unwrap() == "hello");
+        use std::collections::BTreeMap;
+        let v = |major, minor| ffi::Version { major, minor };
+        assert!(v(1, 2) < v(1, 3));
+        assert!(v(2, 0) > v(1, 3));
+        assert_eq!(v(1, 2), v(1, 2));
+        assert_eq!(v(1, 2).cpp_compare(&v(1, 2)), 0);
+        let mut versions = BTreeMap::new();
+        versions.insert(v(2, 0), "two");
+        versions.insert(v(1, 0), "one");
+        versions.insert(v(1, 0), "uno");
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions.values().copied().collect::<Vec<_>>(), ["uno", "two"]);
+        let mut names = BTreeMap::new();
+        for (i, s) in ["b", "a", "c", "a"].iter().enumerate() {
+            names.insert(ffi::Name::new(*s).within_unique_ptr(), i);
+        }
+        assert_eq!(names.values().copied().collect::<Vec<_>>(), [3, 0, 2]);
     };
-    run_test(cxx, hdr, rs, &[], &["Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Name"],
+            &["Version"],
+            Some(quote! {
+                ord!("Version")
+                ord!("Name")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_pass_rust_string_by_ref() {
-    let cxx = indoc! {"
-        uint32_t measure_string(const rust::String& z) {
-            return std::string(z).length();
-        }
-    "};
+fn test_partial_ord() {
     let hdr = indoc! {"
-        #include <cstdint>
-        #include <cxx.h>
-        uint32_t measure_string(const rust::String& z);
+        struct Reading {
+            double value;
+            bool operator<(const Reading& other) const { return value < other.value; }
+            bool operator==(const Reading& other) const { return value == other.value; }
+        };
     "};
     let rs = quote! {
-        let c = ffi::measure_string(&"hello".to_string());
-        assert_eq!(c, 5);
+        let r = |value| ffi::Reading { value };
+        let mut readings = vec![r(2.5), r(-1.0), r(0.5)];
+        readings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            readings.iter().map(|r| r.value).collect::<Vec<_>>(),
+            [-1.0, 0.5, 2.5]
+        );
+        assert!(r(1.0) <= r(1.0));
+        assert_eq!(r(1.0).partial_cmp(&r(f64::NAN)), None);
+        assert_eq!(r(1.0).cpp_partial_compare(&r(f64::NAN)), 2);
+        assert!(r(f64::NAN) != r(f64::NAN));
     };
-    run_test(cxx, hdr, rs, &["measure_string"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &[],
+            &["Reading"],
+            Some(quote! {
+                partial_ord!("Reading")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_pass_rust_string_by_value() {
-    let cxx = indoc! {"
-        uint32_t measure_string(rust::String z) {
-            return std::string(z).length();
-        }
-    "};
+fn test_ord_spaceship() {
     let hdr = indoc! {"
+        #include <compare>
         #include <cstdint>
-        #include <cxx.h>
-        uint32_t measure_string(rust::String z);
+        #include <string>
+        class Name {
+        public:
+            Name(const std::string& s0) : s(s0) {}
+            std::strong_ordering operator<=>(const Name& other) const {
+                return s.compare(other.s) <=> 0;
+            }
+            // Deliberately inconsistent, to show that it isn't used.
+            bool operator<(const Name&) const { return false; }
+        private:
+            std::string s;
+        };
     "};
     let rs = quote! {
-        let c = ffi::measure_string("hello".into());
-        assert_eq!(c, 5);
+        let mut names: Vec<_> = ["b", "c", "a"]
+            .iter()
+            .map(|s| ffi::Name::new(*s).within_unique_ptr())
+            .collect();
+        names.sort();
+        assert!(names[0] == ffi::Name::new("a").within_unique_ptr());
+        assert!(names[2] == ffi::Name::new("c").within_unique_ptr());
     };
-    run_test(cxx, hdr, rs, &["measure_string"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Name"],
+            &[],
+            Some(quote! {
+                ord!("Name")
+            }),
+        ),
+        make_clang_arg_adder(&["-std=c++20"]),
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_pass_rust_str() {
-    // passing by value is the only legal option
-    let cxx = indoc! {"
-        uint32_t measure_string(rust::Str z) {
-            return std::string(z).length();
-        }
-    "};
+fn test_partial_eq() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <cxx.h>
-        uint32_t measure_string(rust::Str z);
+        #include <string>
+        struct Point {
+            int32_t x;
+            int32_t y;
+        };
+        inline bool operator==(const Point& a, const Point& b) {
+            return a.x == b.x && a.y == b.y;
+        }
+        class Label {
+        public:
+            Label(const std::string& s0) : s(s0) {}
+            bool operator==(const Label& other) const { return s == other.s; }
+        private:
+            std::string s;
+        };
     "};
     let rs = quote! {
-        let c = ffi::measure_string("hello");
-        assert_eq!(c, 5);
+        let p = |x, y| ffi::Point { x, y };
+        assert_eq!(p(1, 2), p(1, 2));
+        assert_ne!(p(1, 2), p(2, 1));
+        assert!(p(3, 4).cpp_eq(&p(3, 4)));
+        let a = ffi::Label::new("a").within_unique_ptr();
+        let b = ffi::Label::new("b").within_unique_ptr();
+        let a2 = ffi::Label::new("a").within_unique_ptr();
+        assert!(a == a2);
+        assert!(a != b);
     };
-    run_test(cxx, hdr, rs, &["measure_string"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Label"],
+            &["Point"],
+            Some(quote! {
+                partial_eq!("Point")
+                partial_eq!("Label")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_multiple_classes_with_methods() {
+fn test_display() {
     let hdr = indoc! {"
         #include <cstdint>
-
-        struct TrivialStruct {
-            uint32_t val = 0;
-
-            uint32_t get() const;
-            uint32_t inc();
+        #include <ostream>
+        #include <string>
+        namespace maths {
+        struct Fraction {
+            int32_t numerator;
+            int32_t denominator;
         };
-        TrivialStruct make_trivial_struct();
-
-        class TrivialClass {
-          public:
-            uint32_t get() const;
-            uint32_t inc();
-
-          private:
-            uint32_t val_ = 1;
+        inline std::ostream& operator<<(std::ostream& os, const Fraction& f) {
+            return os << f.numerator << '/' << f.denominator;
+        }
+        }
+        class Tag {
+        public:
+            Tag(const std::string& name0) : name(name0) {}
+            friend std::ostream& operator<<(std::ostream& os, const Tag& t) {
+                return os << '<' << t.name << '>';
+            }
+        private:
+            std::string name;
         };
-        TrivialClass make_trivial_class();
-
-        struct OpaqueStruct {
-            // ~OpaqueStruct();
-            uint32_t val = 2;
+    "};
+    let rs = quote! {
+        let f = ffi::maths::Fraction { numerator: 3, denominator: 4 };
+        assert_eq!(format!("{}", f), "3/4");
+        assert_eq!(f.to_string(), "3/4");
+        assert_eq!(f.cpp_to_string().to_str().unwrap(), "3/4");
+        let t = ffi::Tag::new("b").within_unique_ptr();
+        assert_eq!(t.to_string(), "<b>");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Tag"],
+            &["maths::Fraction"],
+            Some(quote! {
+                display!("maths::Fraction")
+                display!("Tag")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
 
-            uint32_t get() const;
-            uint32_t inc();
+#[test]
+fn test_compound_assign() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Vec2 {
+            double x;
+            double y;
+            Vec2& operator+=(const Vec2& other) {
+                x += other.x;
+                y += other.y;
+                return *this;
+            }
+            Vec2& operator*=(double factor) {
+                x *= factor;
+                y *= factor;
+                return *this;
+            }
         };
-        OpaqueStruct make_opaque_struct();
-
-        class OpaqueClass {
-          public:
-            // ~OpaqueClass();
-            uint32_t get() const;
-            uint32_t inc();
-
-          private:
-            uint32_t val_ = 3;
+        class Counter {
+        public:
+            Counter(uint32_t count) : count(count) {}
+            uint32_t get() const { return count; }
+            Counter& operator+=(const Counter& other) {
+                count += other.count;
+                return *this;
+            }
+            Counter& operator<<=(uint32_t shift) {
+                count <<= shift;
+                return *this;
+            }
+        private:
+            uint32_t count;
         };
-        OpaqueClass make_opaque_class();
-    "};
-    let cxx = indoc! {"
-        TrivialStruct make_trivial_struct() { return {}; }
-        TrivialClass make_trivial_class() { return {}; }
-        OpaqueStruct make_opaque_struct() { return {}; }
-        OpaqueClass make_opaque_class() { return {}; }
-
-        uint32_t TrivialStruct::get() const { return val;}
-        uint32_t TrivialClass::get() const { return val_; }
-        uint32_t OpaqueStruct::get() const { return val;}
-        uint32_t OpaqueClass::get() const { return val_; }
-
-        uint32_t TrivialStruct::inc() { return ++val; }
-        uint32_t TrivialClass::inc() { return ++val_; }
-        uint32_t OpaqueStruct::inc() { return ++val; }
-        uint32_t OpaqueClass::inc() { return ++val_; }
     "};
     let rs = quote! {
-        use ffi::*;
-
-        let mut ts = Box::pin(make_trivial_struct());
-        assert_eq!(ts.get(), 0);
-        assert_eq!(ts.as_mut().inc(), 1);
-        assert_eq!(ts.as_mut().inc(), 2);
-
-        let mut tc = Box::pin(make_trivial_class());
-        assert_eq!(tc.get(), 1);
-        assert_eq!(tc.as_mut().inc(), 2);
-        assert_eq!(tc.as_mut().inc(), 3);
-
-        let mut os= make_opaque_struct().within_unique_ptr();
-        assert_eq!(os.get(), 2);
-        assert_eq!(os.pin_mut().inc(), 3);
-        assert_eq!(os.pin_mut().inc(), 4);
-
-        let mut oc = make_opaque_class().within_unique_ptr();
-        assert_eq!(oc.get(), 3);
-        assert_eq!(oc.pin_mut().inc(), 4);
-        assert_eq!(oc.pin_mut().inc(), 5);
+        let mut v = ffi::Vec2 { x: 1.0, y: 2.0 };
+        v += &ffi::Vec2 { x: 3.0, y: 4.0 };
+        v *= 2.0;
+        assert_eq!((v.x, v.y), (8.0, 12.0));
+        let mut total = ffi::Counter::new(1).within_unique_ptr();
+        let step = ffi::Counter::new(5).within_unique_ptr();
+        let mut pinned = total.pin_mut();
+        pinned += &*step;
+        pinned <<= 2;
+        pinned.as_mut().cpp_shl_assign(1);
+        assert_eq!(total.get(), 48);
     };
-    run_test(
-        cxx,
+    run_test_ex(
+        "",
         hdr,
         rs,
-        &[
-            "make_trivial_struct",
-            "make_trivial_class",
-            "make_opaque_struct",
-            "make_opaque_class",
-            "OpaqueStruct",
-            "OpaqueClass",
-        ],
-        &["TrivialStruct", "TrivialClass"],
+        directives_from_lists(
+            &["Counter"],
+            &["Vec2"],
+            Some(quote! {
+                compound_assign!("Vec2", "+=")
+                compound_assign!("Vec2", "*=", "double")
+                compound_assign!("Counter", "+=")
+                compound_assign!("Counter", "<<=", "uint32_t")
+            }),
+        ),
+        None,
+        None,
+        None,
     );
 }
 
 #[test]
-fn test_ns_return_struct() {
-    let cxx = indoc! {"
-        A::B::Bob give_bob() {
-            A::B::Bob a;
-            a.a = 3;
-            a.b = 4;
-            return a;
-        }
-    "};
+fn test_subscript() {
     let hdr = indoc! {"
+        #include <cstddef>
         #include <cstdint>
-        namespace A {
-            namespace B {
-                struct Bob {
-                    uint32_t a;
-                    uint32_t b;
-                };
-            }
-        }
-        A::B::Bob give_bob();
+        struct Cell {
+            int32_t value;
+        };
+        class Grid {
+        public:
+            Grid() : values{0.0, 0.25, 0.5} {}
+            const double& operator[](size_t i) const { return values[i]; }
+            size_t size() const { return 3; }
+        private:
+            double values[3];
+        };
+        class Row {
+        public:
+            Row() : cells{{1}, {2}} {}
+            const Cell& operator[](int32_t i) const { return cells[i]; }
+            Cell& operator[](int32_t i) { return cells[i]; }
+            size_t size() const { return 2; }
+        private:
+            Cell cells[2];
+        };
     "};
     let rs = quote! {
-        assert_eq!(ffi::give_bob().b, 4);
+        let grid = ffi::Grid::new().within_unique_ptr();
+        assert_eq!(grid[2], 0.5);
+        assert_eq!(grid.cpp_index(1), Some(&0.25));
+        assert_eq!(grid.cpp_index(3), None);
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| grid[3])).is_err());
+        let row = ffi::Row::new().within_unique_ptr();
+        assert_eq!(row[1].value, 2);
+        assert!(row.cpp_index(-1).is_none());
+        assert!(row.cpp_index(2).is_none());
     };
-    run_test(cxx, hdr, rs, &["give_bob"], &["A::B::Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Grid", "Row"],
+            &["Cell"],
+            Some(quote! {
+                subscript!("Grid", "size_t", "double")
+                subscript!("Row", "int32_t", "Cell")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_ns_take_struct() {
-    let cxx = indoc! {"
-    uint32_t take_bob(A::B::Bob a) {
-        return a.a;
-    }
+fn test_subscript_is_not_mutable() {
+    let hdr = indoc! {"
+        #include <cstddef>
+        class Grid {
+        public:
+            Grid() : values{0.0, 0.25, 0.5} {}
+            const double& operator[](size_t i) const { return values[i]; }
+            double& operator[](size_t i) { return values[i]; }
+            size_t size() const { return 3; }
+        private:
+            double values[3];
+        };
     "};
+    // If `Grid`, or `Pin<&mut Grid>`, implemented `IndexMut`, both
+    // implementations of `NotIndexMut` would apply to it, so the type of
+    // `check` would be ambiguous and this wouldn't compile.
+    let rs = quote! {
+        trait NotIndexMut<A> {
+            fn check() {}
+        }
+        impl<T: ?Sized> NotIndexMut<()> for T {}
+        impl<T: ?Sized + std::ops::IndexMut<usize>> NotIndexMut<u8> for T {}
+        fn implements_index<T: std::ops::Index<usize, Output = f64>>() {}
+        implements_index::<ffi::Grid>();
+        <ffi::Grid as NotIndexMut<_>>::check();
+        <std::pin::Pin<&mut ffi::Grid> as NotIndexMut<_>>::check();
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Grid"],
+            &[],
+            Some(quote! {
+                subscript!("Grid", "size_t", "double")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_deref() {
     let hdr = indoc! {"
         #include <cstdint>
-        namespace A {
-            namespace B {
-                struct Bob {
-                    uint32_t a;
-                    uint32_t b;
-                };
-            }
+        class Widget {
+        public:
+            Widget(uint32_t id) : id(id) {}
+            uint32_t get_id() const { return id; }
+        private:
+            uint32_t id;
+        };
+        class WidgetHandle {
+        public:
+            WidgetHandle() : widget(7) {}
+            const Widget& operator*() const { return widget; }
+            const Widget* operator->() const { return &widget; }
+        private:
+            Widget widget;
+        };
+        struct Counter {
+            int32_t count;
+        };
+        struct CounterRef {
+            Counter* counter;
+            const Counter& operator*() const { return *counter; }
+            Counter& operator*() { return *counter; }
+        };
+        inline CounterRef make_counter_ref(Counter& counter) {
+            return CounterRef { &counter };
+        }
+        struct WidgetRef {
+            Widget* widget;
+            const Widget& operator*() const { return *widget; }
+            Widget& operator*() { return *widget; }
+        };
+        inline WidgetRef make_widget_ref(Widget& widget) {
+            return WidgetRef { &widget };
         }
-        uint32_t take_bob(A::B::Bob a);
     "};
     let rs = quote! {
-        let a = ffi::A::B::Bob { a: 12, b: 13 };
-        assert_eq!(ffi::take_bob(a), 12);
+        let handle = ffi::WidgetHandle::new().within_unique_ptr();
+        assert_eq!(handle.get_id(), 7);
+        assert_eq!(handle.cpp_deref().get_id(), 7);
+        let mut counter = ffi::Counter { count: 1 };
+        let mut counter_ref = ffi::make_counter_ref(std::pin::Pin::new(&mut counter));
+        counter_ref.count += 2;
+        assert_eq!(counter_ref.count, 3);
+        assert_eq!(counter.count, 3);
+        // `Widget` isn't a POD type, so `WidgetRef` can't implement
+        // `DerefMut`, but the method is still there.
+        let mut widget = ffi::Widget::new(9).within_unique_ptr();
+        let mut widget_ref = ffi::make_widget_ref(widget.pin_mut());
+        assert_eq!(widget_ref.get_id(), 9);
+        let widget: std::pin::Pin<&mut ffi::Widget> =
+            std::pin::Pin::new(&mut widget_ref).cpp_deref_mut();
+        assert_eq!(widget.get_id(), 9);
     };
-    run_test(cxx, hdr, rs, &["take_bob"], &["A::B::Bob"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &[
+                "Widget",
+                "WidgetHandle",
+                "make_counter_ref",
+                "make_widget_ref",
+            ],
+            &["Counter", "CounterRef", "WidgetRef"],
+            Some(quote! {
+                deref!("WidgetHandle", "Widget")
+                deref!("CounterRef", "Counter", mut)
+                deref!("WidgetRef", "Widget", mut)
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_ns_func() {
+fn test_make_up_with_args() {
     let cxx = indoc! {"
-        using namespace C;
-        A::B::Bob C::give_bob() {
-            A::B::Bob a;
-            a.a = 3;
-            a.b = 4;
-            return a;
+        Bob::Bob(uint32_t a0, uint32_t b0)
+           : a(a0), b(b0) {}
+        uint32_t take_bob(const Bob& a) {
+            return a.a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        namespace A {
-            namespace B {
-                struct Bob {
-                    uint32_t a;
-                    uint32_t b;
-                };
-            }
-        }
-        namespace C {
-            ::A::B::Bob give_bob();
-        }
+        struct Bob {
+            Bob(uint32_t a, uint32_t b);
+            uint32_t a;
+            uint32_t b;
+        };
+        uint32_t take_bob(const Bob& a);
     "};
     let rs = quote! {
-        assert_eq!(ffi::C::give_bob().b, 4);
+        let a = ffi::Bob::new(12, 13).within_unique_ptr();
+        assert_eq!(ffi::take_bob(a.as_ref().unwrap()), 12);
     };
-    run_test(cxx, hdr, rs, &["C::give_bob"], &["A::B::Bob"]);
+    run_test(cxx, hdr, rs, &["take_bob", "Bob"], &[]);
 }
 
 #[test]
-fn test_overload_constructors() {
+#[ignore] // because we don't support unique_ptrs to primitives
+fn test_make_up_int() {
     let cxx = indoc! {"
-        Bob::Bob() {}
-        Bob::Bob(uint32_t _a) :a(_a) {}
+        Bob::Bob(uint32_t a) : b(a) {
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        struct Bob {
-            Bob();
+        class Bob {
+        public:
             Bob(uint32_t a);
-            uint32_t a;
             uint32_t b;
         };
     "};
     let rs = quote! {
-        ffi::Bob::new().within_unique_ptr();
-        ffi::Bob::new1(32).within_unique_ptr();
+        let a = ffi::Bob::new(3).within_unique_ptr();
+        assert_eq!(a.as_ref().unwrap().b, 3);
     };
     run_test(cxx, hdr, rs, &["Bob"], &[]);
 }
 
 #[test]
-fn test_overload_functions() {
+fn test_enum_with_funcs() {
     let cxx = indoc! {"
-        void daft(uint32_t) {}
-        void daft(uint8_t) {}
-        void daft(std::string) {}
-        void daft(Fred) {}
-        void daft(Norma) {}
+        Bob give_bob() {
+            return Bob::BOB_VALUE_2;
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        struct Fred {
-            uint32_t a;
-        };
-        struct Norma {
-            Norma() {}
-            uint32_t a;
+        enum Bob {
+            BOB_VALUE_1,
+            BOB_VALUE_2,
         };
-        void daft(uint32_t);
-        void daft(uint8_t);
-        void daft(std::string);
-        void daft(Fred);
-        void daft(Norma);
+        Bob give_bob();
     "};
     let rs = quote! {
-        use ffi::ToCppString;
-        ffi::daft(32);
-        ffi::daft1(8);
-        ffi::daft2("hello".into_cpp());
-        let b = ffi::Fred { a: 3 };
-        ffi::daft3(b);
-        let c = ffi::Norma::new().within_unique_ptr();
-        ffi::daft4(c);
+        let a = ffi::Bob::BOB_VALUE_2;
+        let b = ffi::give_bob();
+        assert!(a == b);
     };
-    run_test(
-        cxx,
-        hdr,
-        rs,
-        &["Norma", "daft", "daft1", "daft2", "daft3", "daft4"],
-        &["Fred"],
-    );
+    run_test(cxx, hdr, rs, &["Bob", "give_bob"], &[]);
 }
 
 #[test]
-#[ignore] // At present, bindgen generates two separate 'daft1'
-          // functions here, and there's not much we can do about that.
-fn test_overload_numeric_functions() {
-    // Because bindgen deals with conflicting overloaded functions by
-    // appending a numeric suffix, let's see if we can cope.
+fn test_re_export() {
     let cxx = indoc! {"
-        void daft1(uint32_t) {}
-        void daft2(uint8_t) {}
-        void daft(std::string) {}
-        void daft(Fred) {}
-        void daft(Norma) {}
+        Bob give_bob() {
+            return Bob::BOB_VALUE_2;
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        struct Fred {
-            uint32_t a;
-        };
-        struct Norma {
-            uint32_t a;
+        enum Bob {
+            BOB_VALUE_1,
+            BOB_VALUE_2,
         };
-        void daft1(uint32_t a);
-        void daft2(uint8_t a);
-        void daft(std::string a);
-        void daft(Fred a);
-        void daft(Norma a);
+        Bob give_bob();
     "};
     let rs = quote! {
-        use ffi::ToCppString;
-        ffi::daft(32);
-        ffi::daft1(8);
-        ffi::daft2("hello".into_cpp());
-        let b = ffi::Fred { a: 3 };
-        ffi::daft3(b);
-        let c = ffi::Norma::new().within_unique_ptr();
-        ffi::daft4(c);
+        let a = ffi::Bob::BOB_VALUE_2;
+        let b = ffi::give_bob();
+        assert!(a == b);
     };
-    run_test(
+    run_test_ex(
         cxx,
         hdr,
         rs,
-        &["Norma", "daft", "daft1", "daft2", "daft3", "daft4"],
-        &["Fred"],
+        directives_from_lists(&["Bob", "give_bob"], &[], None),
+        None,
+        None,
+        Some(quote! { pub use ffi::Bob; }),
     );
 }
 
 #[test]
-fn test_overload_methods() {
+fn test_enum_no_funcs() {
     let cxx = indoc! {"
-        void Bob::daft(uint32_t) const {}
-        void Bob::daft(uint8_t) const {}
-        void Bob::daft(std::string) const {}
-        void Bob::daft(Fred) const {}
-        void Bob::daft(Norma) const {}
     "};
     let hdr = indoc! {"
-        #include <cstdint>
-        #include <string>
-        struct Fred {
-            uint32_t a;
-        };
-        struct Norma {
-            Norma() {}
-            uint32_t a;
-        };
-        struct Bob {
-            uint32_t a;
-            void daft(uint32_t) const;
-            void daft(uint8_t) const;
-            void daft(std::string) const;
-            void daft(Fred) const;
-            void daft(Norma) const;
+        enum Bob {
+            BOB_VALUE_1,
+            BOB_VALUE_2,
         };
     "};
     let rs = quote! {
-        use ffi::ToCppString;
-        let a = ffi::Bob { a: 12 };
-        a.daft(32);
-        a.daft1(8);
-        a.daft2("hello".into_cpp());
-        let b = ffi::Fred { a: 3 };
-        a.daft3(b);
-        let c = ffi::Norma::new().within_unique_ptr();
-        a.daft4(c);
+        let a = ffi::Bob::BOB_VALUE_1;
+        let b = ffi::Bob::BOB_VALUE_2;
+        assert!(a != b);
     };
-    run_test(cxx, hdr, rs, &["Norma"], &["Fred", "Bob"]);
+    run_test(cxx, hdr, rs, &["Bob"], &[]);
 }
 
 #[test]
-fn test_ns_constructor() {
+fn test_enum_with_funcs_as_pod() {
     let cxx = indoc! {"
-        A::Bob::Bob() {}
+        Bob give_bob() {
+            return Bob::BOB_VALUE_2;
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        namespace A {
-            struct Bob {
-                Bob();
-                uint32_t a;
-                uint32_t b;
-            };
-        }
+        enum Bob {
+            BOB_VALUE_1,
+            BOB_VALUE_2,
+        };
+        Bob give_bob();
     "};
     let rs = quote! {
-        ffi::A::Bob::new().within_unique_ptr();
+        let a = ffi::Bob::BOB_VALUE_2;
+        let b = ffi::give_bob();
+        assert!(a == b);
     };
-    run_test(cxx, hdr, rs, &["A::Bob"], &[]);
+    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_ns_up_direct() {
-    let cxx = indoc! {"
-        std::unique_ptr<A::Bob> A::get_bob() {
-            A::Bob b;
-            b.a = 2;
-            b.b = 3;
-            return std::make_unique<A::Bob>(b);
-        }
-        uint32_t give_bob(A::Bob bob) {
-            return bob.a;
-        }
-    "};
+fn test_enum_constant() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         namespace A {
-            struct Bob {
-                uint32_t a;
-                uint32_t b;
+            enum class Color : uint8_t {
+                Red,
+                Green,
+                Blue,
             };
-            std::unique_ptr<Bob> get_bob();
+            constexpr Color DEFAULT_COLOR = Color::Green;
+            constexpr Color BOGUS_COLOR = static_cast<Color>(42);
         }
-        uint32_t give_bob(A::Bob bob);
     "};
     let rs = quote! {
-        assert_eq!(ffi::give_bob(ffi::A::get_bob()), 2);
+        assert!(ffi::A::DEFAULT_COLOR() == ffi::A::Color::Green);
+        assert!(std::panic::catch_unwind(|| ffi::A::BOGUS_COLOR()).is_err());
     };
-    run_test(cxx, hdr, rs, &["give_bob", "A::get_bob"], &[]);
+    run_test("", hdr, rs, &["A::DEFAULT_COLOR", "A::BOGUS_COLOR"], &[]);
 }
 
 #[test]
-fn test_ns_up_wrappers() {
-    let cxx = indoc! {"
-        A::Bob get_bob() {
-            A::Bob b;
-            b.a = 2;
-            b.b = 3;
-            return b;
-        }
-        uint32_t give_bob(A::Bob bob) {
-            return bob.a;
-        }
-    "};
+fn test_enum_conversions() {
     let hdr = indoc! {"
         #include <cstdint>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-                uint32_t b;
-            };
-        }
-        A::Bob get_bob();
-        uint32_t give_bob(A::Bob bob);
+        enum class Color : uint8_t {
+            Red = 1,
+            Green = 4,
+            Blue = 200,
+            Crimson = Red,
+        };
+        enum class Level {
+            Low = -1,
+            High = 1,
+        };
+        inline uint8_t color_value(Color c) { return static_cast<uint8_t>(c); }
     "};
     let rs = quote! {
-        assert_eq!(ffi::give_bob(as_new(ffi::get_bob())), 2);
+        use std::convert::TryFrom;
+        assert_eq!(u8::from(ffi::Color::Blue), 200);
+        assert_eq!(
+            u8::from(ffi::Color::Green),
+            ffi::color_value(ffi::Color::Green)
+        );
+        assert!(ffi::Color::try_from(4u8) == Ok(ffi::Color::Green));
+        // `Crimson` shares its value with `Red`, which comes first.
+        assert!(ffi::Color::try_from(1u8) == Ok(ffi::Color::Red));
+        assert_eq!(
+            ffi::Color::try_from(3u8).err(),
+            Some(autocxx::InvalidEnumValue { value: 3u8 })
+        );
+        // Without a declared underlying type, it's an `int`.
+        assert_eq!(i32::from(ffi::Level::Low), -1);
+        assert!(ffi::Level::try_from(1i32) == Ok(ffi::Level::High));
+        assert!(ffi::Level::try_from(0i32).is_err());
     };
-    run_test(cxx, hdr, rs, &["give_bob", "get_bob"], &[]);
+    run_test("", hdr, rs, &["Color", "Level", "color_value"], &[]);
 }
 
 #[test]
-fn test_ns_up_wrappers_in_up() {
+fn test_static_members() {
     let cxx = indoc! {"
-        A::Bob A::get_bob() {
-            A::Bob b;
-            b.a = 2;
-            b.b = 3;
-            return b;
-        }
-        uint32_t give_bob(A::Bob bob) {
-            return bob.a;
-        }
+        const uint32_t Widget::LIMIT = 7;
+        const double Widget::RATIO = 1.5;
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-                uint32_t b;
-            };
-            Bob get_bob();
-        }
-        uint32_t give_bob(A::Bob bob);
+        struct Point {
+            int32_t x;
+            int32_t y;
+        };
+        class Widget {
+        public:
+            static constexpr int32_t MAX = 100;
+            static const uint32_t LIMIT;
+            static const double RATIO;
+            static constexpr Point ORIGIN = Point { 0, 0 };
+            int32_t get() const { return 3; }
+        };
     "};
     let rs = quote! {
-        assert_eq!(ffi::give_bob(as_new(ffi::A::get_bob())), 2);
+        // bindgen works out the value of `MAX` itself, so it stays as a
+        // constant.
+        assert_eq!(ffi::Widget_MAX, 100);
+        assert_eq!(ffi::Widget::LIMIT(), 7);
+        assert_eq!(ffi::Widget::RATIO(), 1.5);
+        // `ORIGIN` isn't a number, so it's ignored without getting in the
+        // way of the rest of the class.
+        assert_eq!(ffi::Widget::new().within_unique_ptr().get(), 3);
     };
-    run_test(cxx, hdr, rs, &["give_bob", "A::get_bob"], &[]);
+    run_test(cxx, hdr, rs, &["Widget", "Widget_MAX"], &[]);
 }
 
 #[test]
-fn test_return_reference() {
+fn test_static_member_lookalikes() {
+    // These are named just as bindgen would name static data members of
+    // `Widget`, but they aren't, so mustn't become accessors on `Widget`.
     let cxx = indoc! {"
-        const Bob& give_bob(const Bob& input_bob) {
-            return input_bob;
-        }
+        const uint32_t Widget_LIMIT = 7;
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
+        class Widget {
+        public:
+            int32_t get() const { return 3; }
         };
-        const Bob& give_bob(const Bob& input_bob);
+        constexpr int32_t Widget_COUNT = 100;
+        extern const uint32_t Widget_LIMIT;
     "};
     let rs = quote! {
-        let b = ffi::Bob { a: 3, b: 4 };
-        assert_eq!(ffi::give_bob(&b).b, 4);
+        assert_eq!(ffi::Widget_COUNT, 100);
+        assert_eq!(ffi::Widget::new().within_unique_ptr().get(), 3);
     };
-    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
+    run_test_ex(
+        cxx,
+        hdr,
+        rs,
+        quote! {
+            generate_all!()
+        },
+        None,
+        None,
+        None,
+    );
 }
 
-#[test]
-fn test_return_reference_non_pod() {
+#[test] // works, but causes compile warnings
+fn test_take_pod_class_by_value() {
     let cxx = indoc! {"
-        const Bob& give_bob(const Bob& input_bob) {
-            return input_bob;
+        uint32_t take_bob(Bob a) {
+            return a.a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
+        class Bob {
+        public:
             uint32_t a;
             uint32_t b;
         };
-        namespace A {
-            void give_bob(); // force wrapper generation
-        }
-        const Bob& give_bob(const Bob& input_bob);
+        uint32_t take_bob(Bob a);
     "};
-    let rs = quote! {};
-    run_test(cxx, hdr, rs, &["give_bob", "Bob", "A::give_bob"], &[]);
-}
-
+    let rs = quote! {
+        let a = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(ffi::take_bob(a), 12);
+    };
+    run_test(cxx, hdr, rs, &["take_bob"], &["Bob"]);
+}
+
 #[test]
-fn test_return_reference_non_pod_string() {
+fn test_pod_method() {
     let cxx = indoc! {"
-        const std::string& give_bob(const Bob& input_bob) {
-            return input_bob.a;
+        uint32_t Bob::get_bob() const {
+            return a;
         }
     "};
     let hdr = indoc! {"
-        #include <string>
+        #include <cstdint>
         struct Bob {
-            std::string a;
+        public:
+            uint32_t a;
+            uint32_t b;
+            uint32_t get_bob() const;
         };
-       // namespace A {
-       //     void give_bob(); // force wrapper generation
-       // }
-        const std::string& give_bob(const Bob& input_bob);
     "};
-    let rs = quote! {};
-    run_test(cxx, hdr, rs, &["give_bob", "Bob"], &[]);
+    let rs = quote! {
+        let a = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(a.get_bob(), 12);
+    };
+    run_test(cxx, hdr, rs, &[], &["Bob"]);
 }
 
 #[test]
-fn test_member_return_reference() {
+#[ignore] // https://github.com/google/autocxx/issues/723
+fn test_constructors_for_specialized_types() {
+    // bindgen sometimes makes such opaque types as type Bob = u32[2];
     let hdr = indoc! {"
-        #include <string>
+        #include <cstdint>
+        template<typename T>
         class A {
-        public:
-            virtual const std::string& get_str() { return a; }
-            virtual ~A() {}
-            std::string a;
+            uint32_t foo() { return 12; };
+        private:
+            T a[2];
         };
+
+        typedef A<uint32_t> B;
+        typedef B C;
     "};
     let rs = quote! {
-        let mut b = ffi::A::new().within_unique_ptr();
-        b.pin_mut().get_str();
+        let a = ffi::C::new().within_unique_ptr();
+        assert_eq!(a.foo(), 12);
     };
-    run_test("", hdr, rs, &["A"], &[]);
+    run_test("", hdr, rs, &["C"], &[]);
 }
 
 #[test]
-fn test_destructor() {
-    let hdr = indoc! {"
-        struct WithDtor {
-            ~WithDtor();
-        };
-        WithDtor make_with_dtor();
-    "};
+fn test_pod_mut_method() {
     let cxx = indoc! {"
-        WithDtor::~WithDtor() {}
-        WithDtor make_with_dtor() {
-            return {};
+        uint32_t Bob::get_bob() {
+            return a;
         }
     "};
-    let rs = quote! {
-        use ffi::*;
-        let with_dtor: cxx::UniquePtr<WithDtor> = make_with_dtor().within_unique_ptr();
-        drop(with_dtor);
-    };
-    run_test(cxx, hdr, rs, &["WithDtor", "make_with_dtor"], &[]);
-}
-
-#[test]
-fn test_nested_with_destructor() {
-    // Regression test, naming the destructor in the generated C++ is a bit tricky.
     let hdr = indoc! {"
-        struct A {
-            struct B {
-                B() = default;
-                ~B() = default;
-            };
+        #include <cstdint>
+        struct Bob {
+        public:
+            uint32_t a;
+            uint32_t b;
+            uint32_t get_bob();
         };
     "};
     let rs = quote! {
-        ffi::A_B::new().within_unique_ptr();
+        let mut a = Box::pin(ffi::Bob { a: 12, b: 13 });
+        assert_eq!(a.as_mut().get_bob(), 12);
     };
-    run_test("", hdr, rs, &["A", "A_B"], &[]);
+    run_test(cxx, hdr, rs, &[], &["Bob"]);
 }
 
-// Even without a `safety!`, we still need to generate a safe `fn drop`.
 #[test]
-fn test_destructor_no_safety() {
-    let hdr = indoc! {"
-        struct WithDtor {
-            ~WithDtor();
-        };
-    "};
+fn test_define_int() {
     let cxx = indoc! {"
-        WithDtor::~WithDtor() {}
     "};
-    let hexathorpe = Token![#](Span::call_site());
-    let unexpanded_rust = quote! {
-        use autocxx::prelude::*;
-
-        include_cpp!(
-            #hexathorpe include "input.h"
-            generate!("WithDtor")
-        );
-
-        fn main() {}
-    };
-
-    do_run_test_manual(cxx, hdr, unexpanded_rust, None, None).unwrap();
-}
-
-#[test]
-fn test_static_func() {
     let hdr = indoc! {"
-        #include <cstdint>
-        struct WithStaticMethod {
-            static uint32_t call();
-        };
-    "};
-    let cxx = indoc! {"
-        uint32_t WithStaticMethod::call() {
-            return 42;
-        }
+        #define BOB 3
     "};
     let rs = quote! {
-        assert_eq!(ffi::WithStaticMethod::call(), 42);
+        assert_eq!(ffi::BOB, 3);
     };
-    run_test(cxx, hdr, rs, &["WithStaticMethod"], &[]);
+    run_test(cxx, hdr, rs, &["BOB"], &[]);
 }
 
 #[test]
-fn test_static_func_wrapper() {
+fn test_define_str() {
+    let cxx = indoc! {"
+    "};
     let hdr = indoc! {"
-        #include <cstdint>
-        #include <string>
-        struct A {
-            std::string a;
-            static A CreateA(std::string a, std::string) {
-                A c;
-                c.a = a;
-                return c;
-            }
-        };
+        #define BOB \"foo\"
     "};
     let rs = quote! {
-        use ffi::ToCppString;
-        ffi::A::CreateA("a".into_cpp(), "b".into_cpp());
+        assert_eq!(core::str::from_utf8(ffi::BOB).unwrap().trim_end_matches(char::from(0)), "foo");
     };
-    run_test("", hdr, rs, &["A"], &[]);
+    run_test(cxx, hdr, rs, &["BOB"], &[]);
 }
 
 #[test]
-fn test_give_pod_typedef_by_value() {
+fn test_i32_const() {
     let cxx = indoc! {"
-        Horace give_bob() {
-            Horace a;
-            a.a = 3;
-            a.b = 4;
-            return a;
-        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-        };
-        using Horace = Bob;
-        Horace give_bob();
+        const uint32_t BOB = 3;
     "};
     let rs = quote! {
-        assert_eq!(ffi::give_bob().b, 4);
+        assert_eq!(ffi::BOB, 3);
     };
-    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
+    run_test(cxx, hdr, rs, &["BOB"], &[]);
 }
 
-#[ignore] // because we need to put some aliases in the output ffi mod.
 #[test]
-fn test_use_pod_typedef() {
+fn test_negative_rs_nonsense() {
+    // Really just testing the test infrastructure.
     let cxx = indoc! {"
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-            uint32_t b;
-        };
-        using Horace = Bob;
+        const uint32_t BOB = 3;
     "};
     let rs = quote! {
-        let h = Horace { a: 3, b: 4 };
-        assert_eq!(h.b, 4);
+        foo bar
     };
-    run_test(cxx, hdr, rs, &[], &["Bob"]);
+    run_test_expect_fail(cxx, hdr, rs, &["BOB"], &[]);
 }
 
 #[test]
-fn test_typedef_to_ns() {
+fn test_negative_cpp_nonsense() {
+    // Really just testing the test infrastructure.
+    let cxx = indoc! {"
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        namespace A {
-            template<typename T>
-            struct C {
-                T* t;
-            };
-            typedef C<char> B;
-        }
+        const uint32_t BOB = CAT;
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["A::B"], &[]);
+    let rs = quote! {
+        assert_eq!(ffi::BOB, 3);
+    };
+    run_test_expect_fail(cxx, hdr, rs, &["BOB"], &[]);
 }
 
-#[ignore] // we don't yet allow typedefs to be listed in allow_pod
 #[test]
-fn test_use_pod_typedef_with_allowpod() {
+fn test_negative_make_nonpod() {
     let cxx = indoc! {"
+        uint32_t take_bob(const Bob& a) {
+            return a.a;
+        }
+        std::unique_ptr<Bob> make_bob(uint32_t a) {
+            auto b = std::make_unique<Bob>();
+            b->a = a;
+            return b;
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
+        #include <memory>
         struct Bob {
             uint32_t a;
-            uint32_t b;
         };
-        using Horace = Bob;
+        std::unique_ptr<Bob> make_bob(uint32_t a);
+        uint32_t take_bob(const Bob& a);
     "};
     let rs = quote! {
-        let h = Horace { a: 3, b: 4 };
-        assert_eq!(h.b, 4);
+        ffi::Bob {};
     };
-    run_test(cxx, hdr, rs, &[], &["Horace"]);
+    let rs2 = quote! {
+        ffi::Bob { a: 12 };
+    };
+    let rs3 = quote! {
+        ffi::Bob { do_not_attempt_to_allocate_nonpod_types: [] };
+    };
+    run_test_expect_fail(cxx, hdr, rs, &["take_bob", "Bob", "make_bob"], &[]);
+    run_test_expect_fail(cxx, hdr, rs2, &["take_bob", "Bob", "make_bob"], &[]);
+    run_test_expect_fail(cxx, hdr, rs3, &["take_bob", "Bob", "make_bob"], &[]);
 }
 
 #[test]
-fn test_give_nonpod_typedef_by_value() {
+fn test_method_pass_pod_by_value() {
     let cxx = indoc! {"
-        Horace give_bob() {
-            Horace a;
-            a.a = 3;
-            a.b = 4;
+        uint32_t Bob::get_bob(Anna) const {
             return a;
         }
     "};
     let hdr = indoc! {"
         #include <cstdint>
+        struct Anna {
+            uint32_t a;
+        };
         struct Bob {
+        public:
             uint32_t a;
             uint32_t b;
+            uint32_t get_bob(Anna a) const;
         };
-        using Horace = Bob;
-        Horace give_bob();
-        inline uint32_t take_horace(const Horace& horace) { return horace.b; }
     "};
     let rs = quote! {
-        assert_eq!(ffi::take_horace(&moveit!(ffi::give_bob())), 4);
+        let a = ffi::Anna { a: 14 };
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(a), 12);
     };
-    run_test(cxx, hdr, rs, &["give_bob", "take_horace"], &[]);
+    run_test(cxx, hdr, rs, &[], &["Bob", "Anna"]);
 }
 
-#[test]
-fn test_conflicting_static_functions() {
-    let cxx = indoc! {"
-        Bob Bob::create() { Bob a; return a; }
-        Fred Fred::create() { Fred b; return b; }
+fn perform_asan_doom_test(into_raw: TokenStream, box_type: TokenStream) {
+    if std::env::var_os("AUTOCXX_ASAN").is_none() {
+        return;
+    }
+    // Testing that we get an asan fail when it's enabled.
+    // Really just testing our CI is working to spot ASAN mistakes.
+    let hdr = indoc! {"
+        #include <cstddef>
+        struct A {
+            int a;
+        };
+        inline size_t how_big_is_a() {
+            return sizeof(A);
+        }
     "};
+    let rs = quote! {
+        let a = #box_type::emplace(ffi::A::new());
+        unsafe {
+            let a_raw = #into_raw;
+            // Intentional memory unsafety. Don't @ me.
+            let a_offset_into_doom = a_raw.offset(ffi::how_big_is_a().try_into().unwrap());
+            a_offset_into_doom.write_bytes(0x69, 1);
+            #box_type::from_raw(a_raw); // to delete. If we haven't yet crashed.
+        }
+    };
+    run_test_expect_fail("", hdr, rs, &["A", "how_big_is_a"], &[]);
+}
+
+#[test]
+fn test_asan_working_as_expected_for_cpp_allocations() {
+    perform_asan_doom_test(quote! { a.into_raw() }, quote! { UniquePtr })
+}
+
+#[test]
+fn test_asan_working_as_expected_for_rust_allocations() {
+    perform_asan_doom_test(
+        quote! { Box::into_raw(core::pin::Pin::into_inner_unchecked(a)) },
+        quote! { Box },
+    )
+}
+
+#[test]
+fn test_inline_method() {
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            Bob() : a(0) {}
+        struct Anna {
             uint32_t a;
-            static Bob create();
         };
-        struct Fred {
-            Fred() : b(0) {}
+        struct Bob {
+        public:
+            uint32_t a;
             uint32_t b;
-            static Fred create();
+            uint32_t get_bob(Anna) const {
+                return a;
+            }
         };
     "};
     let rs = quote! {
-        ffi::Bob::create();
-        ffi::Fred::create();
+        let a = ffi::Anna { a: 14 };
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(a), 12);
     };
-    run_test(cxx, hdr, rs, &[], &["Bob", "Fred"]);
+    run_test("", hdr, rs, &[], &["Bob", "Anna"]);
 }
 
 #[test]
-fn test_conflicting_ns_up_functions() {
+fn test_method_pass_pod_by_reference() {
     let cxx = indoc! {"
-        uint32_t A::create(C) { return 3; }
-        uint32_t B::create(C) { return 4; }
+        uint32_t Bob::get_bob(const Anna&) const {
+            return a;
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct C {
-            C() {}
+        struct Anna {
             uint32_t a;
         };
-        namespace A {
-            uint32_t create(C c);
-        };
-        namespace B {
-            uint32_t create(C c);
+        struct Bob {
+        public:
+            uint32_t a;
+            uint32_t b;
+            uint32_t get_bob(const Anna& a) const;
         };
     "};
     let rs = quote! {
-        let c = ffi::C::new().within_unique_ptr();
-        let c2 = ffi::C::new().within_unique_ptr();
-        assert_eq!(ffi::A::create(c), 3);
-        assert_eq!(ffi::B::create(c2), 4);
+        let a = ffi::Anna { a: 14 };
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(&a), 12);
     };
-    run_test(cxx, hdr, rs, &["A::create", "B::create", "C"], &[]);
+    run_test(cxx, hdr, rs, &[], &["Bob", "Anna"]);
 }
 
 #[test]
-fn test_conflicting_methods() {
+fn test_method_pass_pod_by_mut_reference() {
     let cxx = indoc! {"
-        uint32_t Bob::get() const { return a; }
-        uint32_t Fred::get() const { return b; }
+        uint32_t Bob::get_bob(Anna&) const {
+            return a;
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
+        struct Anna {
             uint32_t a;
-            uint32_t get() const;
         };
-        struct Fred {
+        struct Bob {
+        public:
+            uint32_t a;
             uint32_t b;
-            uint32_t get() const;
+            uint32_t get_bob(Anna& a) const;
         };
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 10 };
-        let b = ffi::Fred { b: 20 };
-        assert_eq!(a.get(), 10);
-        assert_eq!(b.get(), 20);
+        let mut a = Box::pin(ffi::Anna { a: 14 });
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(a.as_mut()), 12);
     };
-    run_test(cxx, hdr, rs, &[], &["Bob", "Fred"]);
+    run_test(cxx, hdr, rs, &[], &["Bob", "Anna"]);
 }
 
 #[test]
-// There's a bindgen bug here. bindgen generates
-// functions called 'get' and 'get1' but then generates impl
-// blocks which call 'get' and 'get'. By luck, we currently
-// should not be broken by this, but at some point we should take
-// the time to create a minimal bindgen test case and submit it
-// as a bindgen bug.
-fn test_conflicting_up_wrapper_methods_not_in_ns() {
-    // Ensures the two names 'get' do not conflict in the flat
-    // cxx::bridge mod namespace.
+fn test_method_pass_pod_by_up() {
     let cxx = indoc! {"
-        Bob::Bob() : a(\"hello\") {}
-        Fred::Fred() : b(\"goodbye\") {}
-        std::string Bob::get() const { return a; }
-        std::string Fred::get() const { return b; }
+        uint32_t Bob::get_bob(std::unique_ptr<Anna>) const {
+            return a;
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        struct Bob {
-            Bob();
-            std::string a;
-            std::string get() const;
+        #include <memory>
+        struct Anna {
+            uint32_t a;
         };
-        struct Fred {
-            Fred();
-            std::string b;
-            std::string get() const;
+        struct Bob {
+        public:
+            uint32_t a;
+            uint32_t b;
+            uint32_t get_bob(std::unique_ptr<Anna> z) const;
         };
     "};
     let rs = quote! {
-        let a = ffi::Bob::new().within_unique_ptr();
-        let b = ffi::Fred::new().within_unique_ptr();
-        assert_eq!(a.get().as_ref().unwrap().to_str().unwrap(), "hello");
-        assert_eq!(b.get().as_ref().unwrap().to_str().unwrap(), "goodbye");
+        let a = ffi::Anna { a: 14 };
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(cxx::UniquePtr::new(a)), 12);
     };
-    run_test(cxx, hdr, rs, &["Bob", "Fred"], &[]);
+    run_test(cxx, hdr, rs, &[], &["Bob", "Anna"]);
 }
 
 #[test]
-fn test_conflicting_methods_in_ns() {
+fn test_method_pass_nonpod_by_value() {
     let cxx = indoc! {"
-        uint32_t A::Bob::get() const { return a; }
-        uint32_t B::Fred::get() const { return b; }
+        uint32_t Bob::get_bob(Anna) const {
+            return a;
+        }
+        Anna give_anna() {
+            Anna a;
+            a.a = 10;
+            return a;
+        }
     "};
     let hdr = indoc! {"
         #include <cstdint>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-                uint32_t get() const;
-            };
-        }
-        namespace B {
-            struct Fred {
-                uint32_t b;
-                uint32_t get() const;
-            };
-        }
+        #include <string>
+        struct Anna {
+            uint32_t a;
+            std::string b;
+        };
+        Anna give_anna();
+        struct Bob {
+        public:
+            uint32_t a;
+            uint32_t b;
+            uint32_t get_bob(Anna a) const;
+        };
     "};
     let rs = quote! {
-        let a = ffi::A::Bob { a: 10 };
-        let b = ffi::B::Fred { b: 20 };
-        assert_eq!(a.get(), 10);
-        assert_eq!(b.get(), 20);
+        let a = ffi::give_anna().within_box();
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(a), 12);
     };
-    run_test(cxx, hdr, rs, &[], &["A::Bob", "B::Fred"]);
+    run_test(cxx, hdr, rs, &["Anna", "give_anna"], &["Bob"]);
 }
 
 #[test]
-fn test_conflicting_up_wrapper_methods_in_ns() {
+fn test_pass_two_nonpod_by_value() {
     let cxx = indoc! {"
-        A::Bob::Bob() : a(\"hello\") {}
-        B::Fred::Fred() : b(\"goodbye\") {}
-        std::string A::Bob::get() const { return a; }
-        std::string B::Fred::get() const { return b; }
+        void take_a(A, A) {
+        }
     "};
     let hdr = indoc! {"
-        #include <cstdint>
         #include <string>
-        namespace A {
-            struct Bob {
-                Bob();
-                std::string a;
-                std::string get() const;
-            };
-        }
-        namespace B {
-            struct Fred {
-                Fred();
-                std::string b;
-                std::string get() const;
-            };
-        }
+        struct A {
+            std::string b;
+        };
+        void take_a(A, A);
     "};
     let rs = quote! {
-        let a = ffi::A::Bob::new().within_unique_ptr();
-        let b = ffi::B::Fred::new().within_unique_ptr();
-        assert_eq!(a.get().as_ref().unwrap().to_str().unwrap(), "hello");
-        assert_eq!(b.get().as_ref().unwrap().to_str().unwrap(), "goodbye");
+        let a = ffi::A::new().within_unique_ptr();
+        let a2 = ffi::A::new().within_unique_ptr();
+        ffi::take_a(a, a2);
     };
-    run_test(cxx, hdr, rs, &["A::Bob", "B::Fred"], &[]);
+    run_test(cxx, hdr, rs, &["A", "take_a"], &[]);
 }
 
 #[test]
-fn test_ns_struct_pod_request() {
+fn test_issue_931() {
+    let cxx = "";
     let hdr = indoc! {"
-        #include <cstdint>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-            };
-        }
-    "};
-    let rs = quote! {
-        ffi::A::Bob { a: 12 };
-    };
-    run_test("", hdr, rs, &[], &["A::Bob"]);
+    namespace a {
+        struct __cow_string {
+          __cow_string();
+        };
+        class b {
+        public:
+          __cow_string c;
+        };
+        class j {
+        public:
+          b d;
+        };
+        template <typename> class e;
+        } // namespace a
+        template <typename> struct f {};
+        namespace llvm {
+        template <class> class g {
+          union {
+            f<a::j> h;
+          };
+        };
+        class MemoryBuffer {
+        public:
+          g<a::e<MemoryBuffer>> i;
+        };
+        } // namespace llvm
+    "};
+    let rs = quote! {};
+    run_test(cxx, hdr, rs, &["llvm::MemoryBuffer"], &[]);
 }
 
 #[test]
-fn test_conflicting_ns_funcs() {
-    let cxx = indoc! {"
-        uint32_t A::get() { return 10; }
-        uint32_t B::get() { return 20; }
-    "};
+fn test_issue_936() {
+    let cxx = "";
     let hdr = indoc! {"
-        #include <cstdint>
-        namespace A {
-            uint32_t get();
-        }
-        namespace B {
-            uint32_t get();
-        }
-    "};
-    let rs = quote! {
-        assert_eq!(ffi::A::get(), 10);
-        assert_eq!(ffi::B::get(), 20);
+    struct a;
+    class B {
+    public:
+        B(a &, bool);
     };
-    run_test(cxx, hdr, rs, &["A::get", "B::get"], &[]);
+    "};
+    let rs = quote! {};
+    run_test(cxx, hdr, rs, &["B"], &[]);
 }
 
-#[ignore]
-// because currently we feed a flat namespace to cxx
-// This would be relatively easy to enable now that we have the facility
-// to add aliases to the 'use' statements we generate, plus
-// bridge_name_tracker to pick a unique name. TODO.
 #[test]
-fn test_conflicting_ns_structs() {
-    let hdr = indoc! {"
-        #include <cstdint>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-            };
+fn test_method_pass_nonpod_by_value_with_up() {
+    // Checks that existing UniquePtr params are not wrecked
+    // by the conversion we do here.
+    let cxx = indoc! {"
+        uint32_t Bob::get_bob(Anna, std::unique_ptr<Anna>) const {
+            return a;
         }
-        namespace B {
-            struct Bob {
-                uint32_t a;
-            };
+        Anna give_anna() {
+            Anna a;
+            a.a = 10;
+            return a;
         }
     "};
-    let rs = quote! {
-        ffi::A::Bob { a: 12 };
-        ffi::B::Bob { b: 12 };
-    };
-    run_test("", hdr, rs, &[], &["A::Bob", "B::Bob"]);
-}
-
-#[test]
-fn test_make_string() {
     let hdr = indoc! {"
         #include <cstdint>
+        #include <string>
+        #include <memory>
+        struct Anna {
+            uint32_t a;
+            std::string b;
+        };
+        Anna give_anna();
         struct Bob {
+        public:
             uint32_t a;
+            uint32_t b;
+            uint32_t get_bob(Anna a, std::unique_ptr<Anna>) const;
         };
     "};
     let rs = quote! {
-        use ffi::ToCppString;
-        let a = "hello".into_cpp();
-        assert_eq!(a.to_str().unwrap(), "hello");
+        let a = ffi::give_anna().within_unique_ptr();
+        let a2 = ffi::give_anna().within_unique_ptr();
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(a, a2), 12);
     };
-    run_test("", hdr, rs, &["Bob"], &[]);
+    run_test(cxx, hdr, rs, &["Anna", "give_anna"], &["Bob"]);
 }
 
 #[test]
-fn test_string_make_unique() {
+fn test_issue_940() {
+    let cxx = "";
     let hdr = indoc! {"
-        #include <string>
-        inline void take_string(const std::string*) {};
-    "};
-    let rs = quote! {
-        let s = ffi::make_string("");
-        unsafe { ffi::take_string(s.as_ref().unwrap()) };
+    template <class> class b;
+    template <class = void> struct c;
+    struct identity;
+    template <class, class, class e, class> class f {
+    using g = e;
+    g h;
     };
-    run_test("", hdr, rs, &["take_string"], &[]);
-}
-
-#[test]
-fn test_string_constant() {
-    let hdr = indoc! {"
-        #include <cstdint>
-        const char* STRING = \"Foo\";
-    "};
-    let rs = quote! {
-        let a = core::str::from_utf8(ffi::STRING).unwrap().trim_end_matches(char::from(0));
-        assert_eq!(a, "Foo");
+    template <class i, class k = c<>, class l = b<i>>
+    using j = f<i, identity, k, l>;
+    class n;
+    class RenderFrameHost {
+    public:
+    virtual void o(const j<n> &);
+    virtual ~RenderFrameHost() {}
     };
-    run_test("", hdr, rs, &["STRING"], &[]);
-}
-
-#[test]
-fn test_string_let_cxx_string() {
-    let hdr = indoc! {"
-        #include <string>
-        inline void take_string(const std::string&) {};
     "};
-    let rs = quote! {
-        autocxx::cxx::let_cxx_string!(s = "hello");
-        ffi::take_string(&s);
-    };
-    run_test("", hdr, rs, &["take_string"], &[]);
+    let rs = quote! {};
+    run_test_ex(
+        cxx,
+        hdr,
+        rs,
+        directives_from_lists(&["RenderFrameHost"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_pod_constant_harmless_inside_type() {
-    // Check that the presence of this constant doesn't break anything.
+fn test_method_pass_nonpod_by_reference() {
+    let cxx = indoc! {"
+        uint32_t Bob::get_bob(const Anna&) const {
+            return a;
+        }
+        Anna give_anna() {
+            Anna a;
+            a.a = 10;
+            return a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-        };
+        #include <string>
         struct Anna {
             uint32_t a;
-            const Bob BOB = Bob { 10 };
+            std::string b;
         };
-    "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &[], &["Anna"]);
-}
-
-#[test]
-#[ignore] // https://github.com/google/autocxx/issues/93
-fn test_pod_constant() {
-    let hdr = indoc! {"
-        #include <cstdint>
+        Anna give_anna();
         struct Bob {
+        public:
             uint32_t a;
+            uint32_t b;
+            uint32_t get_bob(const Anna& a) const;
         };
-        const Bob BOB = Bob { 10 };
     "};
     let rs = quote! {
-        let a = &ffi::BOB;
-        assert_eq!(a.a, 10);
+        let a = ffi::give_anna().within_box();
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(a.as_ref().get_ref()), 12);
     };
-    run_test("", hdr, rs, &["BOB"], &["Bob"]);
+    run_test(cxx, hdr, rs, &["Anna", "give_anna"], &["Bob"]);
 }
 
 #[test]
-fn test_pod_static_harmless_inside_type() {
-    // Check that the presence of this constant doesn't break anything.
-    // Remove this test when the following one is enabled.
+fn test_method_pass_nonpod_by_mut_reference() {
+    let cxx = indoc! {"
+        uint32_t Bob::get_bob(Anna&) const {
+            return a;
+        }
+        Anna give_anna() {
+            Anna a;
+            a.a = 10;
+            return a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        struct Bob {
-            uint32_t a;
-        };
+        #include <string>
         struct Anna {
             uint32_t a;
-            static Bob BOB;
+            std::string b;
         };
-        Bob Anna::BOB = Bob { 10 };
-    "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &[], &["Anna"]);
-}
-
-#[test]
-#[ignore] // https://github.com/google/autocxx/issues/93
-fn test_pod_static() {
-    let hdr = indoc! {"
-        #include <cstdint>
+        Anna give_anna();
         struct Bob {
+        public:
             uint32_t a;
+            uint32_t b;
+            uint32_t get_bob(Anna& a) const;
         };
-        static Bob BOB = Bob { 10 };
     "};
     let rs = quote! {
-        let a = &ffi::BOB;
-        assert_eq!(a.a, 10);
+        let mut a = ffi::give_anna().within_unique_ptr();
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(a.as_mut().unwrap()), 12);
     };
-    run_test("", hdr, rs, &["BOB"], &["Bob"]);
+    run_test(cxx, hdr, rs, &["Anna", "give_anna"], &["Bob"]);
 }
 
 #[test]
-#[ignore] // this probably requires code generation on the C++
-          // side. It's not at all clear how best to handle this.
-fn test_non_pod_constant() {
+fn test_method_pass_nonpod_by_up() {
+    let cxx = indoc! {"
+        uint32_t Bob::get_bob(std::unique_ptr<Anna>) const {
+            return a;
+        }
+        Anna give_anna() {
+            Anna a;
+            a.a = 10;
+            return a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
+        #include <memory>
         #include <string>
+        struct Anna {
+            uint32_t a;
+            std::string b;
+        };
+        Anna give_anna();
         struct Bob {
-            std::string a;
-            std::string get() { return a };
+        public:
+            uint32_t a;
+            uint32_t b;
+            uint32_t get_bob(std::unique_ptr<Anna> z) const;
         };
-        const Bob BOB = Bob { \"hello\" };
     "};
     let rs = quote! {
-        let a = ffi::BOB;
-        // following line assumes that 'a' is a &Bob
-        // but who knows how we'll really do this.
-        assert_eq!(a.get().as_ref().unwrap().to_str().unwrap(), "hello");
+        let a = ffi::give_anna().within_unique_ptr();
+        let b = ffi::Bob { a: 12, b: 13 };
+        assert_eq!(b.get_bob(a), 12);
     };
-    run_test("", hdr, rs, &["BOB"], &[]);
+    run_test(cxx, hdr, rs, &["give_anna"], &["Bob"]);
 }
 
 #[test]
-fn test_templated_typedef() {
+fn test_method_return_nonpod_by_value() {
+    let cxx = indoc! {"
+        Anna Bob::get_anna() const {
+            Anna a;
+            a.a = 12;
+            return a;
+        }
+    "};
     let hdr = indoc! {"
-        #include <string>
         #include <cstdint>
-
-        template <typename STRING_TYPE> class BasicStringPiece {
-        public:
-            const STRING_TYPE* ptr_;
-            size_t length_;
+        #include <string>
+        struct Anna {
+            uint32_t a;
+            std::string b;
         };
-        typedef BasicStringPiece<uint8_t> StringPiece;
-
-        struct Origin {
-            Origin() {}
-            StringPiece host;
+        struct Bob {
+        public:
+            uint32_t a;
+            uint32_t b;
+            Anna get_anna() const;
         };
     "};
     let rs = quote! {
-        ffi::Origin::new().within_unique_ptr();
+        let b = ffi::Bob { a: 12, b: 13 };
+        let a = b.get_anna().within_unique_ptr();
+        assert!(!a.is_null());
     };
-    run_test("", hdr, rs, &["Origin"], &[]);
+    run_test(cxx, hdr, rs, &["Anna"], &["Bob"]);
 }
 
 #[test]
-fn test_struct_templated_typedef() {
+fn test_pass_string_by_value() {
+    let cxx = indoc! {"
+        uint32_t measure_string(std::string z) {
+            return z.length();
+        }
+        std::unique_ptr<std::string> get_msg() {
+            return std::make_unique<std::string>(\"hello\");
+        }
+    "};
     let hdr = indoc! {"
-        #include <string>
         #include <cstdint>
-
-        struct Concrete {
-            uint8_t a;
-        };
-        template <typename STRING_TYPE> class BasicStringPiece {
-        public:
-            const STRING_TYPE* ptr_;
-            size_t length_;
-        };
-        typedef BasicStringPiece<Concrete> StringPiece;
-
-        struct Origin {
-            Origin() {}
-            StringPiece host;
-        };
+        #include <string>
+        #include <memory>
+        uint32_t measure_string(std::string a);
+        std::unique_ptr<std::string> get_msg();
     "};
     let rs = quote! {
-        ffi::Origin::new().within_unique_ptr();
+        let a = ffi::get_msg();
+        let c = ffi::measure_string(a);
+        assert_eq!(c, 5);
     };
-    run_test("", hdr, rs, &["Origin"], &[]);
+    run_test(cxx, hdr, rs, &["measure_string", "get_msg"], &[]);
 }
 
 #[test]
-fn test_enum_typedef() {
+fn test_return_string_by_value() {
+    let cxx = indoc! {"
+        std::string get_msg() {
+            return \"hello\";
+        }
+    "};
     let hdr = indoc! {"
-        enum ConstraintSolverParameters_TrailCompression : int {
-            ConstraintSolverParameters_TrailCompression_NO_COMPRESSION = 0,
-            ConstraintSolverParameters_TrailCompression_COMPRESS_WITH_ZLIB = 1
-        };
-        typedef ConstraintSolverParameters_TrailCompression TrailCompression;
+        #include <string>
+        std::string get_msg();
     "};
     let rs = quote! {
-        let _ = ffi::TrailCompression::ConstraintSolverParameters_TrailCompression_NO_COMPRESSION;
+        let a = ffi::get_msg();
+        assert!(a.as_ref().unwrap() == "hello");
     };
-    run_test("", hdr, rs, &["TrailCompression"], &[]);
+    run_test(cxx, hdr, rs, &["get_msg"], &[]);
 }
 
 #[test]
-#[ignore] // https://github.com/google/autocxx/issues/264
-fn test_conflicting_usings() {
+#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
+fn test_method_pass_string_by_value() {
+    let cxx = indoc! {"
+        uint32_t Bob::measure_string(std::string z) const {
+            return z.length();
+        }
+        std::unique_ptr<std::string> get_msg() {
+            return std::make_unique<std::string>(\"hello\");
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <cstddef>
-        typedef size_t diff;
-        struct A {
-            using diff = diff;
-            diff a;
-        };
-        struct B {
-            using diff = diff;
-            diff a;
+        #include <string>
+        #include <memory>
+        struct Bob {
+        public:
+            uint32_t a;
+            uint32_t b;
+            uint32_t measure_string(std::string a) const;
         };
+        std::unique_ptr<std::string> get_msg();
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &[], &["A", "B"]);
+    let rs = quote! {
+        let a = ffi::get_msg();
+        let b = ffi::Bob { a: 12, b: 13 };
+        let c = b.measure_string(a);
+        assert_eq!(c, 5);
+    };
+    run_test(cxx, hdr, rs, &["Bob", "get_msg"], &["Bob"]);
 }
 
 #[test]
-fn test_conflicting_usings_with_self_declaration1() {
+fn test_method_return_string_by_value() {
+    let cxx = indoc! {"
+        std::string Bob::get_msg() const {
+            return \"hello\";
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <cstddef>
-        struct common_params {
-            using difference_type = ptrdiff_t;
-        };
-        template <typename Params>
-        class btree_node {
-            public:
-            using difference_type = typename Params::difference_type;
-            Params params;
-        };
-        template <typename Tree>
-        class btree_container {
-            public:
-            using difference_type = typename Tree::difference_type;
-            void clear() {}
-            Tree b;
+        #include <string>
+        struct Bob {
+        public:
             uint32_t a;
+            uint32_t b;
+            std::string get_msg() const;
         };
-        typedef btree_container<btree_node<common_params>> my_tree;
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["my_tree"], &[]);
+    let rs = quote! {
+        let b = ffi::Bob { a: 12, b: 13 };
+        let a = b.get_msg();
+        assert!(a.as_ref().unwrap() == "hello");
+    };
+    run_test(cxx, hdr, rs, &[], &["Bob"]);
 }
 
 #[test]
-#[ignore] // https://github.com/google/autocxx/issues/106
-fn test_string_templated_typedef() {
+fn test_pass_rust_string_by_ref() {
+    let cxx = indoc! {"
+        uint32_t measure_string(const rust::String& z) {
+            return std::string(z).length();
+        }
+    "};
     let hdr = indoc! {"
-        #include <string>
         #include <cstdint>
-
-        template <typename STRING_TYPE> class BasicStringPiece {
-        public:
-            const STRING_TYPE* ptr_;
-            size_t length_;
-        };
-        typedef BasicStringPiece<std::string> StringPiece;
-
-        struct Origin {
-            Origin() {}
-            StringPiece host;
-        };
+        #include <cxx.h>
+        uint32_t measure_string(const rust::String& z);
     "};
     let rs = quote! {
-        ffi::Origin::new().within_unique_ptr();
+        let c = ffi::measure_string(&"hello".to_string());
+        assert_eq!(c, 5);
     };
-    run_test("", hdr, rs, &["Origin"], &[]);
+    run_test(cxx, hdr, rs, &["measure_string"], &[]);
 }
 
 #[test]
-fn test_associated_type_problem() {
-    // Regression test for a potential bindgen bug
+fn test_pass_rust_string_by_value() {
+    let cxx = indoc! {"
+        uint32_t measure_string(rust::String z) {
+            return std::string(z).length();
+        }
+    "};
     let hdr = indoc! {"
-        namespace a {
-        template <typename> class b {};
-        } // namespace a
-        class bl {
-        public:
-          a::b<bl> bm;
-        };
-        struct B {
-            int a;
-        };
+        #include <cstdint>
+        #include <cxx.h>
+        uint32_t measure_string(rust::String z);
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["B"], &[]);
+    let rs = quote! {
+        let c = ffi::measure_string("hello".into());
+        assert_eq!(c, 5);
+    };
+    run_test(cxx, hdr, rs, &["measure_string"], &[]);
 }
 
 #[test]
-fn test_two_type_constructors() {
-    // https://github.com/google/autocxx/issues/877
+fn test_pass_rust_str() {
+    // passing by value is the only legal option
+    let cxx = indoc! {"
+        uint32_t measure_string(rust::Str z) {
+            return std::string(z).length();
+        }
+    "};
     let hdr = indoc! {"
-        struct A {
-            int a;
-        };
-        struct B {
-            int B;
-        };
+        #include <cstdint>
+        #include <cxx.h>
+        uint32_t measure_string(rust::Str z);
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["A", "B"], &[]);
+    let rs = quote! {
+        let c = ffi::measure_string("hello");
+        assert_eq!(c, 5);
+    };
+    run_test(cxx, hdr, rs, &["measure_string"], &[]);
 }
 
-#[ignore] // https://github.com/rust-lang/rust-bindgen/issues/1924
 #[test]
-fn test_associated_type_templated_typedef_in_struct() {
+fn test_multiple_classes_with_methods() {
     let hdr = indoc! {"
-        #include <string>
         #include <cstdint>
 
-        template <typename STRING_TYPE> class BasicStringPiece {
-        public:
-            typedef size_t size_type;
-            typedef typename STRING_TYPE::value_type value_type;
-            const value_type* ptr_;
-            size_type length_;
+        struct TrivialStruct {
+            uint32_t val = 0;
+
+            uint32_t get() const;
+            uint32_t inc();
         };
+        TrivialStruct make_trivial_struct();
 
-        typedef BasicStringPiece<std::string> StringPiece;
+        class TrivialClass {
+          public:
+            uint32_t get() const;
+            uint32_t inc();
 
-        struct Origin {
-            // void SetHost(StringPiece host);
-            StringPiece host;
+          private:
+            uint32_t val_ = 1;
         };
-    "};
-    let rs = quote! {
-        ffi::Origin::new().within_unique_ptr();
-    };
-    run_test("", hdr, rs, &["Origin"], &[]);
-}
+        TrivialClass make_trivial_class();
 
-#[test]
-fn test_associated_type_templated_typedef() {
-    let hdr = indoc! {"
-        #include <string>
-        #include <cstdint>
+        struct OpaqueStruct {
+            // ~OpaqueStruct();
+            uint32_t val = 2;
 
-        template <typename STRING_TYPE> class BasicStringPiece {
-        public:
-            typedef size_t size_type;
-            typedef typename STRING_TYPE::value_type value_type;
-            const value_type* ptr_;
-            size_type length_;
+            uint32_t get() const;
+            uint32_t inc();
         };
+        OpaqueStruct make_opaque_struct();
 
-        typedef BasicStringPiece<std::string> StringPiece;
+        class OpaqueClass {
+          public:
+            // ~OpaqueClass();
+            uint32_t get() const;
+            uint32_t inc();
 
-        struct Container {
-            Container() {}
-            const StringPiece& get_string_piece() const { return sp; }
-            StringPiece sp;
+          private:
+            uint32_t val_ = 3;
         };
+        OpaqueClass make_opaque_class();
+    "};
+    let cxx = indoc! {"
+        TrivialStruct make_trivial_struct() { return {}; }
+        TrivialClass make_trivial_class() { return {}; }
+        OpaqueStruct make_opaque_struct() { return {}; }
+        OpaqueClass make_opaque_class() { return {}; }
 
-        inline void take_string_piece(const StringPiece&) {}
+        uint32_t TrivialStruct::get() const { return val;}
+        uint32_t TrivialClass::get() const { return val_; }
+        uint32_t OpaqueStruct::get() const { return val;}
+        uint32_t OpaqueClass::get() const { return val_; }
+
+        uint32_t TrivialStruct::inc() { return ++val; }
+        uint32_t TrivialClass::inc() { return ++val_; }
+        uint32_t OpaqueStruct::inc() { return ++val; }
+        uint32_t OpaqueClass::inc() { return ++val_; }
     "};
     let rs = quote! {
-        let sp = ffi::Container::new().within_box();
-        ffi::take_string_piece(sp.get_string_piece());
-    };
-    run_test("", hdr, rs, &["take_string_piece", "Container"], &[]);
-}
+        use ffi::*;
 
-#[test]
-fn test_associated_type_templated_typedef_by_value_regular() {
-    let hdr = indoc! {"
-        #include <string>
-        #include <cstdint>
+        let mut ts = Box::pin(make_trivial_struct());
+        assert_eq!(ts.get(), 0);
+        assert_eq!(ts.as_mut().inc(), 1);
+        assert_eq!(ts.as_mut().inc(), 2);
 
-        template <typename STRING_TYPE> class BasicStringPiece {
-        public:
-            BasicStringPiece() : ptr_(nullptr), length_(0) {}
-            typedef size_t size_type;
-            typedef typename STRING_TYPE::value_type value_type;
-            const value_type* ptr_;
-            size_type length_;
-        };
+        let mut tc = Box::pin(make_trivial_class());
+        assert_eq!(tc.get(), 1);
+        assert_eq!(tc.as_mut().inc(), 2);
+        assert_eq!(tc.as_mut().inc(), 3);
 
-        typedef BasicStringPiece<std::string> StringPiece;
+        let mut os= make_opaque_struct().within_unique_ptr();
+        assert_eq!(os.get(), 2);
+        assert_eq!(os.pin_mut().inc(), 3);
+        assert_eq!(os.pin_mut().inc(), 4);
 
-        inline StringPiece give_string_piece() {
-            StringPiece s;
-            return s;
-        }
-        inline void take_string_piece(StringPiece) {}
-    "};
-    let rs = quote! {
-        let sp = ffi::give_string_piece();
-        ffi::take_string_piece(sp);
+        let mut oc = make_opaque_class().within_unique_ptr();
+        assert_eq!(oc.get(), 3);
+        assert_eq!(oc.pin_mut().inc(), 4);
+        assert_eq!(oc.pin_mut().inc(), 5);
     };
-    run_test_ex(
-        "",
+    run_test(
+        cxx,
         hdr,
         rs,
-        quote! {
-            generate!("take_string_piece")
-            generate!("give_string_piece")
-            instantiable!("StringPiece")
-        },
-        None,
-        None,
-        None,
+        &[
+            "make_trivial_struct",
+            "make_trivial_class",
+            "make_opaque_struct",
+            "make_opaque_class",
+            "OpaqueStruct",
+            "OpaqueClass",
+        ],
+        &["TrivialStruct", "TrivialClass"],
     );
 }
 
 #[test]
-fn test_associated_type_templated_typedef_by_value_forward_declaration() {
+fn test_ns_return_struct() {
+    let cxx = indoc! {"
+        A::B::Bob give_bob() {
+            A::B::Bob a;
+            a.a = 3;
+            a.b = 4;
+            return a;
+        }
+    "};
     let hdr = indoc! {"
-        #include <string>
         #include <cstdint>
-
-        template <typename STRING_TYPE> class BasicStringPiece;
-
-        typedef BasicStringPiece<std::string> StringPiece;
-
-        struct Container {
-            StringPiece give_string_piece() const;
-            void take_string_piece(StringPiece string_piece) const;
-            const StringPiece& get_string_piece() const;
-            uint32_t b;
-        };
-
-        inline void take_string_piece_by_ref(const StringPiece&) {}
-    "};
-    let cpp = indoc! {"
-        template <typename STRING_TYPE> class BasicStringPiece {
-        public:
-            BasicStringPiece() : ptr_(nullptr), length_(0) {}
-            typedef size_t size_type;
-            typedef typename STRING_TYPE::value_type value_type;
-            const value_type* ptr_;
-            size_type length_;
-        };
-
-        StringPiece Container::give_string_piece() const {
-            StringPiece s;
-            return s;
-        }
-        void Container::take_string_piece(StringPiece) const {}
-
-        StringPiece a;
-
-        const StringPiece& Container::get_string_piece() const {
-            return a;
+        namespace A {
+            namespace B {
+                struct Bob {
+                    uint32_t a;
+                    uint32_t b;
+                };
+            }
         }
+        A::B::Bob give_bob();
     "};
-    // As this template is forward declared we shouldn't be able to pass it by
-    // value, but we still want to be able to use it by reference.
     let rs = quote! {
-        let cont = ffi::Container::new().within_box();
-        ffi::take_string_piece_by_ref(cont.as_ref().get_string_piece());
+        assert_eq!(ffi::give_bob().b, 4);
     };
-    run_test(
-        cpp,
-        hdr,
-        rs,
-        &["take_string_piece_by_ref", "Container"],
-        &[],
-    );
+    run_test(cxx, hdr, rs, &["give_bob"], &["A::B::Bob"]);
 }
 
 #[test]
-fn test_remove_cv_t_pathological() {
-    let hdr = indoc! {"
-        template <class _Ty>
-        struct remove_cv {
-            using type = _Ty;
-            template <template <class> class _Fn>
-            using _Apply = _Fn<_Ty>;
-        };
-
-        template <class _Ty>
-        struct remove_cv<const _Ty> {
-            using type = _Ty;
-            template <template <class> class _Fn>
-            using _Apply = const _Fn<_Ty>;
-        };
-
-        template <class _Ty>
-        struct remove_cv<volatile _Ty> {
-            using type = _Ty;
-            template <template <class> class _Fn>
-            using _Apply = volatile _Fn<_Ty>;
-        };
-
-        template <class _Ty>
-        struct remove_cv<const volatile _Ty> {
-            using type = _Ty;
-            template <template <class> class _Fn>
-            using _Apply = const volatile _Fn<_Ty>;
-        };
-
-        template <class _Ty>
-        using remove_cv_t = typename remove_cv<_Ty>::type;
-
-        template <class _Ty>
-        struct remove_reference {
-            using type                 = _Ty;
-            using _Const_thru_ref_type = const _Ty;
-        };
-
-        template <class _Ty>
-        struct remove_reference<_Ty&> {
-            using type                 = _Ty;
-            using _Const_thru_ref_type = const _Ty&;
-        };
-
-        template <class _Ty>
-        struct remove_reference<_Ty&&> {
-            using type                 = _Ty;
-            using _Const_thru_ref_type = const _Ty&&;
-        };
-
-        template <class _Ty>
-        using remove_reference_t = typename remove_reference<_Ty>::type;
-
-        template <class _Ty>
-        using _Remove_cvref_t = remove_cv_t<remove_reference_t<_Ty>>;
-    "};
-    run_generate_all_test(hdr);
-}
-
-#[test]
-fn test_foreign_ns_func_arg_pod() {
+fn test_ns_take_struct() {
+    let cxx = indoc! {"
+    uint32_t take_bob(A::B::Bob a) {
+        return a.a;
+    }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         namespace A {
-            struct Bob {
-                uint32_t a;
-            };
-        }
-        namespace B {
-            inline uint32_t daft(A::Bob a) { return a.a; }
+            namespace B {
+                struct Bob {
+                    uint32_t a;
+                    uint32_t b;
+                };
+            }
         }
+        uint32_t take_bob(A::B::Bob a);
     "};
     let rs = quote! {
-        let a = ffi::A::Bob { a: 12 };
-        assert_eq!(ffi::B::daft(a), 12);
+        let a = ffi::A::B::Bob { a: 12, b: 13 };
+        assert_eq!(ffi::take_bob(a), 12);
     };
-    run_test("", hdr, rs, &["B::daft"], &["A::Bob"]);
+    run_test(cxx, hdr, rs, &["take_bob"], &["A::B::Bob"]);
 }
 
 #[test]
-fn test_foreign_ns_func_arg_nonpod() {
+fn test_ns_func() {
+    let cxx = indoc! {"
+        using namespace C;
+        A::B::Bob C::give_bob() {
+            A::B::Bob a;
+            a.a = 3;
+            a.b = 4;
+            return a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         namespace A {
-            struct Bob {
-                uint32_t a;
-                Bob(uint32_t _a) :a(_a) {}
-            };
+            namespace B {
+                struct Bob {
+                    uint32_t a;
+                    uint32_t b;
+                };
+            }
         }
-        namespace B {
-            inline uint32_t daft(A::Bob a) { return a.a; }
+        namespace C {
+            ::A::B::Bob give_bob();
         }
     "};
     let rs = quote! {
-        let a = ffi::A::Bob::new(12).within_unique_ptr();
-        assert_eq!(ffi::B::daft(a), 12);
+        assert_eq!(ffi::C::give_bob().b, 4);
     };
-    run_test("", hdr, rs, &["B::daft", "A::Bob"], &[]);
+    run_test(cxx, hdr, rs, &["C::give_bob"], &["A::B::Bob"]);
 }
 
 #[test]
-fn test_foreign_ns_meth_arg_pod() {
+fn test_overload_constructors() {
+    let cxx = indoc! {"
+        Bob::Bob() {}
+        Bob::Bob(uint32_t _a) :a(_a) {}
+    "};
     let hdr = indoc! {"
         #include <cstdint>
         #include <memory>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-            };
-        }
-        namespace B {
-            struct C {
-                uint32_t a;
-                uint32_t daft(A::Bob a) const { return a.a; }
-            };
-        }
+        struct Bob {
+            Bob();
+            Bob(uint32_t a);
+            uint32_t a;
+            uint32_t b;
+        };
     "};
     let rs = quote! {
-        let a = ffi::A::Bob { a: 12 };
-        let b = ffi::B::C { a: 12 };
-        assert_eq!(b.daft(a), 12);
+        ffi::Bob::new().within_unique_ptr();
+        ffi::Bob::new1(32).within_unique_ptr();
     };
-    run_test("", hdr, rs, &[], &["A::Bob", "B::C"]);
+    run_test(cxx, hdr, rs, &["Bob"], &[]);
 }
 
 #[test]
-fn test_foreign_ns_meth_arg_nonpod() {
+fn test_overload_functions() {
+    let cxx = indoc! {"
+        void daft(uint32_t) {}
+        void daft(uint8_t) {}
+        void daft(std::string) {}
+        void daft(Fred) {}
+        void daft(Norma) {}
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-                Bob(uint32_t _a) :a(_a) {}
-            };
-        }
-        namespace B {
-            struct C {
-                uint32_t a;
-                uint32_t daft(A::Bob a) const { return a.a; }
-            };
-        }
+        #include <string>
+        struct Fred {
+            uint32_t a;
+        };
+        struct Norma {
+            Norma() {}
+            uint32_t a;
+        };
+        void daft(uint32_t);
+        void daft(uint8_t);
+        void daft(std::string);
+        void daft(Fred);
+        void daft(Norma);
     "};
     let rs = quote! {
-        let a = ffi::A::Bob::new(12).within_unique_ptr();
-        let b = ffi::B::C { a: 12 };
-        assert_eq!(b.daft(a), 12);
+        use ffi::ToCppString;
+        ffi::daft(32);
+        ffi::daft1(8);
+        ffi::daft2("hello".into_cpp());
+        let b = ffi::Fred { a: 3 };
+        ffi::daft3(b);
+        let c = ffi::Norma::new().within_unique_ptr();
+        ffi::daft4(c);
     };
-    run_test("", hdr, rs, &["A::Bob"], &["B::C"]);
+    run_test(
+        cxx,
+        hdr,
+        rs,
+        &["Norma", "daft", "daft1", "daft2", "daft3", "daft4"],
+        &["Fred"],
+    );
 }
 
 #[test]
-fn test_foreign_ns_cons_arg_pod() {
+#[ignore] // At present, bindgen generates two separate 'daft1'
+          // functions here, and there's not much we can do about that.
+fn test_overload_numeric_functions() {
+    // Because bindgen deals with conflicting overloaded functions by
+    // appending a numeric suffix, let's see if we can cope.
+    let cxx = indoc! {"
+        void daft1(uint32_t) {}
+        void daft2(uint8_t) {}
+        void daft(std::string) {}
+        void daft(Fred) {}
+        void daft(Norma) {}
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-            };
-        }
-        namespace B {
-            struct C {
-                uint32_t a;
-                C(const A::Bob& input) : a(input.a) {}
-            };
-        }
+        #include <string>
+        struct Fred {
+            uint32_t a;
+        };
+        struct Norma {
+            uint32_t a;
+        };
+        void daft1(uint32_t a);
+        void daft2(uint8_t a);
+        void daft(std::string a);
+        void daft(Fred a);
+        void daft(Norma a);
     "};
     let rs = quote! {
-        let a = ffi::A::Bob { a: 12 };
-        let b = ffi::B::C::new(&a).within_unique_ptr();
-        assert_eq!(b.as_ref().unwrap().a, 12);
+        use ffi::ToCppString;
+        ffi::daft(32);
+        ffi::daft1(8);
+        ffi::daft2("hello".into_cpp());
+        let b = ffi::Fred { a: 3 };
+        ffi::daft3(b);
+        let c = ffi::Norma::new().within_unique_ptr();
+        ffi::daft4(c);
     };
-    run_test("", hdr, rs, &[], &["B::C", "A::Bob"]);
+    run_test(
+        cxx,
+        hdr,
+        rs,
+        &["Norma", "daft", "daft1", "daft2", "daft3", "daft4"],
+        &["Fred"],
+    );
 }
 
 #[test]
-fn test_foreign_ns_cons_arg_nonpod() {
+fn test_overload_tag_dispatch() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        namespace A {
-            struct Bob {
-                Bob(uint32_t _a) :a(_a) {}
-                uint32_t a;
-            };
-        }
-        namespace B {
-            struct C {
-                uint32_t a;
-                C(const A::Bob& input) : a(input.a) {}
-            };
-        }
+        #include <string>
+        struct Fred {
+            uint32_t a;
+        };
+        inline uint32_t describe(uint32_t a) { return a + 1; }
+        inline uint32_t describe(std::string a) { return a.size(); }
+        inline uint32_t describe(uint32_t a, uint32_t b) { return a * b; }
+        inline uint32_t describe(const Fred& a) { return a.a + 100; }
     "};
     let rs = quote! {
-        let a = ffi::A::Bob::new(12).within_unique_ptr();
-        let b = ffi::B::C::new(&a).within_unique_ptr();
-        assert_eq!(b.as_ref().unwrap().a, 12);
+        assert_eq!(ffi::describe_dispatch(ffi::describe_tag0, (3,)), 4);
+        assert_eq!(ffi::describe_dispatch(ffi::describe_tag1, ("hello",)), 5);
+        assert_eq!(ffi::describe_dispatch(ffi::describe_tag2, (3, 4)), 12);
+        let fred = ffi::Fred { a: 3 };
+        assert_eq!(ffi::describe_dispatch(ffi::describe_tag3, (&fred,)), 103);
+        // The individual overloads are still available too.
+        assert_eq!(ffi::describe2(3, 4), 12);
     };
-    run_test("", hdr, rs, &["A::Bob"], &["B::C"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["describe"],
+            &["Fred"],
+            Some(quote! { tag_dispatch!("describe") }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_foreign_ns_func_ret_pod() {
+fn test_overload_methods() {
+    let cxx = indoc! {"
+        void Bob::daft(uint32_t) const {}
+        void Bob::daft(uint8_t) const {}
+        void Bob::daft(std::string) const {}
+        void Bob::daft(Fred) const {}
+        void Bob::daft(Norma) const {}
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-            };
-        }
-        namespace B {
-            inline A::Bob daft() { A::Bob bob; bob.a = 12; return bob; }
-        }
+        #include <string>
+        struct Fred {
+            uint32_t a;
+        };
+        struct Norma {
+            Norma() {}
+            uint32_t a;
+        };
+        struct Bob {
+            uint32_t a;
+            void daft(uint32_t) const;
+            void daft(uint8_t) const;
+            void daft(std::string) const;
+            void daft(Fred) const;
+            void daft(Norma) const;
+        };
     "};
     let rs = quote! {
-        assert_eq!(ffi::B::daft().a, 12);
+        use ffi::ToCppString;
+        let a = ffi::Bob { a: 12 };
+        a.daft(32);
+        a.daft1(8);
+        a.daft2("hello".into_cpp());
+        let b = ffi::Fred { a: 3 };
+        a.daft3(b);
+        let c = ffi::Norma::new().within_unique_ptr();
+        a.daft4(c);
     };
-    run_test("", hdr, rs, &["B::daft"], &["A::Bob"]);
+    run_test(cxx, hdr, rs, &["Norma"], &["Fred", "Bob"]);
 }
 
 #[test]
-fn test_foreign_ns_func_ret_nonpod() {
+fn test_volatile_methods() {
+    // Without volatile_methods!, cxx would take the address of each method
+    // as a pointer to a non-volatile member function, which doesn't compile.
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        namespace A {
-            struct Bob {
-                uint32_t a;
-            };
-        }
-        namespace B {
-            inline A::Bob daft() { A::Bob bob; bob.a = 12; return bob; }
-        }
+        class Register {
+        public:
+            Register() : value(42) {}
+            uint32_t read() volatile { return value; }
+            uint32_t peek() const volatile { return value; }
+        private:
+            uint32_t value;
+        };
     "};
     let rs = quote! {
-        ffi::B::daft().within_box().as_ref();
+        let mut r = ffi::Register::new().within_unique_ptr();
+        assert_eq!(r.pin_mut().read(), 42);
+        assert_eq!(r.peek(), 42);
     };
-    run_test("", hdr, rs, &["B::daft", "A::Bob"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Register")
+            volatile_methods!("Register::read")
+            volatile_methods!("Register::peek")
+        },
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_foreign_ns_meth_ret_pod() {
+fn test_ns_constructor() {
+    let cxx = indoc! {"
+        A::Bob::Bob() {}
+    "};
     let hdr = indoc! {"
         #include <cstdint>
         #include <memory>
         namespace A {
             struct Bob {
+                Bob();
                 uint32_t a;
-            };
-        }
-        namespace B {
-            struct C {
-                uint32_t a;
-                A::Bob daft() const { A::Bob bob; bob.a = 12; return bob; }
+                uint32_t b;
             };
         }
     "};
     let rs = quote! {
-        let b = ffi::B::C { a: 12 };
-        assert_eq!(b.daft().a, 12);
+        ffi::A::Bob::new().within_unique_ptr();
     };
-    run_test("", hdr, rs, &[], &["A::Bob", "B::C"]);
+    run_test(cxx, hdr, rs, &["A::Bob"], &[]);
 }
 
 #[test]
-fn test_foreign_ns_meth_ret_nonpod() {
+fn test_ns_up_direct() {
+    let cxx = indoc! {"
+        std::unique_ptr<A::Bob> A::get_bob() {
+            A::Bob b;
+            b.a = 2;
+            b.b = 3;
+            return std::make_unique<A::Bob>(b);
+        }
+        uint32_t give_bob(A::Bob bob) {
+            return bob.a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
         #include <memory>
         namespace A {
             struct Bob {
                 uint32_t a;
+                uint32_t b;
             };
+            std::unique_ptr<Bob> get_bob();
         }
-        namespace B {
-            struct C {
-                uint32_t a;
-                A::Bob daft() const { A::Bob bob; bob.a = 12; return bob; }
-            };
-        }
+        uint32_t give_bob(A::Bob bob);
     "};
     let rs = quote! {
-        let b = ffi::B::C { a: 14 };
-        b.daft().within_unique_ptr().as_ref().unwrap();
+        assert_eq!(ffi::give_bob(ffi::A::get_bob()), 2);
     };
-    run_test("", hdr, rs, &["A::Bob"], &["B::C"]);
+    run_test(cxx, hdr, rs, &["give_bob", "A::get_bob"], &[]);
 }
 
 #[test]
-fn test_root_ns_func_arg_pod() {
+fn test_ns_up_wrappers() {
+    let cxx = indoc! {"
+        A::Bob get_bob() {
+            A::Bob b;
+            b.a = 2;
+            b.b = 3;
+            return b;
+        }
+        uint32_t give_bob(A::Bob bob) {
+            return bob.a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        struct Bob {
-            uint32_t a;
-        };
-        namespace B {
-            inline uint32_t daft(Bob a) { return a.a; }
+        namespace A {
+            struct Bob {
+                uint32_t a;
+                uint32_t b;
+            };
         }
+        A::Bob get_bob();
+        uint32_t give_bob(A::Bob bob);
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12 };
-        assert_eq!(ffi::B::daft(a), 12);
+        assert_eq!(ffi::give_bob(as_new(ffi::get_bob())), 2);
     };
-    run_test("", hdr, rs, &["B::daft"], &["Bob"]);
+    run_test(cxx, hdr, rs, &["give_bob", "get_bob"], &[]);
 }
 
 #[test]
-fn test_root_ns_func_arg_nonpod() {
+fn test_ns_up_wrappers_in_up() {
+    let cxx = indoc! {"
+        A::Bob A::get_bob() {
+            A::Bob b;
+            b.a = 2;
+            b.b = 3;
+            return b;
+        }
+        uint32_t give_bob(A::Bob bob) {
+            return bob.a;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        struct Bob {
-            uint32_t a;
-            Bob(uint32_t _a) :a(_a) {}
-        };
-        namespace B {
-            inline uint32_t daft(Bob a) { return a.a; }
+        namespace A {
+            struct Bob {
+                uint32_t a;
+                uint32_t b;
+            };
+            Bob get_bob();
         }
+        uint32_t give_bob(A::Bob bob);
     "};
     let rs = quote! {
-        let a = ffi::Bob::new(12).within_unique_ptr();
-        assert_eq!(ffi::B::daft(a), 12);
+        assert_eq!(ffi::give_bob(as_new(ffi::A::get_bob())), 2);
     };
-    run_test("", hdr, rs, &["B::daft", "Bob"], &[]);
+    run_test(cxx, hdr, rs, &["give_bob", "A::get_bob"], &[]);
 }
 
 #[test]
-fn test_root_ns_meth_arg_pod() {
+fn test_ns_wrappers_for_same_named_functions() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        struct Bob {
-            uint32_t a;
-        };
-        namespace B {
-            struct C {
-                uint32_t a;
-                uint32_t daft(Bob a) const { return a.a; }
+        #include <string>
+        namespace a {
+            struct Counter {
+                uint32_t count;
             };
+            inline std::string calc(Counter c) {
+                return std::string(c.count, 'a');
+            }
+        }
+        namespace b {
+            // Argument-dependent lookup would find this for a call to
+            // plain `calc` with an `a::Counter`.
+            inline std::string calc(a::Counter c) {
+                return std::string(c.count, 'b');
+            }
+            inline std::string calc() {
+                return \"b\";
+            }
         }
     "};
     let rs = quote! {
-        let a = ffi::Bob { a: 12 };
-        let b = ffi::B::C { a: 12 };
-        assert_eq!(b.daft(a), 12);
+        assert_eq!(ffi::a::calc(ffi::a::Counter { count: 2 }).to_str().unwrap(), "aa");
+        assert_eq!(ffi::b::calc(ffi::a::Counter { count: 2 }).to_str().unwrap(), "bb");
+        assert_eq!(ffi::b::calc1().to_str().unwrap(), "b");
     };
-    run_test("", hdr, rs, &[], &["Bob", "B::C"]);
+    run_test("", hdr, rs, &["a::calc", "b::calc"], &["a::Counter"]);
 }
 
 #[test]
-fn test_root_ns_meth_arg_nonpod() {
+fn test_return_reference() {
+    let cxx = indoc! {"
+        const Bob& give_bob(const Bob& input_bob) {
+            return input_bob;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         struct Bob {
             uint32_t a;
-            Bob(uint32_t _a) :a(_a) {}
+            uint32_t b;
         };
-        namespace B {
-            struct C {
-                uint32_t a;
-                uint32_t daft(Bob a) const { return a.a; }
-            };
-        }
+        const Bob& give_bob(const Bob& input_bob);
     "};
     let rs = quote! {
-        let a = ffi::Bob::new(12).within_unique_ptr();
-        let b = ffi::B::C { a: 12 };
-        assert_eq!(b.daft(a), 12);
+        let b = ffi::Bob { a: 3, b: 4 };
+        assert_eq!(ffi::give_bob(&b).b, 4);
     };
-    run_test("", hdr, rs, &["Bob"], &["B::C"]);
+    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
 }
 
 #[test]
-fn test_root_ns_cons_arg_pod() {
+fn test_return_reference_non_pod() {
+    let cxx = indoc! {"
+        const Bob& give_bob(const Bob& input_bob) {
+            return input_bob;
+        }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
         struct Bob {
             uint32_t a;
+            uint32_t b;
         };
-        namespace B {
-            struct C {
-                uint32_t a;
-                C(const Bob& input) : a(input.a) {}
-            };
+        namespace A {
+            void give_bob(); // force wrapper generation
         }
+        const Bob& give_bob(const Bob& input_bob);
     "};
-    let rs = quote! {
-        let a = ffi::Bob { a: 12 };
-        let b = ffi::B::C::new(&a).within_unique_ptr();
-        assert_eq!(b.as_ref().unwrap().a, 12);
-    };
-    run_test("", hdr, rs, &[], &["B::C", "Bob"]);
+    let rs = quote! {};
+    run_test(cxx, hdr, rs, &["give_bob", "Bob", "A::give_bob"], &[]);
 }
 
 #[test]
-fn test_root_ns_cons_arg_nonpod() {
-    let hdr = indoc! {"
-        #include <cstdint>
-        #include <memory>
-        struct Bob {
-            Bob(uint32_t _a) :a(_a) {}
-            uint32_t a;
-        };
-        namespace B {
-            struct C {
-                uint32_t a;
-                C(const Bob& input) : a(input.a) {}
-            };
+fn test_return_reference_non_pod_string() {
+    let cxx = indoc! {"
+        const std::string& give_bob(const Bob& input_bob) {
+            return input_bob.a;
         }
     "};
-    let rs = quote! {
-        let a = ffi::Bob::new(12).within_unique_ptr();
-        let b = ffi::B::C::new(&a).within_unique_ptr();
-        assert_eq!(b.as_ref().unwrap().a, 12);
-    };
-    run_test("", hdr, rs, &["Bob"], &["B::C"]);
-}
-
-#[test]
-fn test_root_ns_func_ret_pod() {
     let hdr = indoc! {"
-        #include <cstdint>
-        #include <memory>
+        #include <string>
         struct Bob {
-            uint32_t a;
+            std::string a;
         };
-        namespace B {
-            inline Bob daft() { Bob bob; bob.a = 12; return bob; }
-        }
+       // namespace A {
+       //     void give_bob(); // force wrapper generation
+       // }
+        const std::string& give_bob(const Bob& input_bob);
     "};
-    let rs = quote! {
-        assert_eq!(ffi::B::daft().a, 12);
-    };
-    run_test("", hdr, rs, &["B::daft"], &["Bob"]);
+    let rs = quote! {};
+    run_test(cxx, hdr, rs, &["give_bob", "Bob"], &[]);
 }
 
 #[test]
-fn test_root_ns_func_ret_nonpod() {
+fn test_member_return_reference() {
     let hdr = indoc! {"
-        #include <cstdint>
-        #include <memory>
-        struct Bob {
-            uint32_t a;
+        #include <string>
+        class A {
+        public:
+            virtual const std::string& get_str() { return a; }
+            virtual ~A() {}
+            std::string a;
         };
-        namespace B {
-            inline Bob daft() { Bob bob; bob.a = 12; return bob; }
-        }
     "};
     let rs = quote! {
-        ffi::B::daft().within_unique_ptr().as_ref().unwrap();
+        let mut b = ffi::A::new().within_unique_ptr();
+        b.pin_mut().get_str();
     };
-    run_test("", hdr, rs, &["B::daft", "Bob"], &[]);
+    run_test("", hdr, rs, &["A"], &[]);
 }
 
 #[test]
-fn test_root_ns_meth_ret_pod() {
+fn test_member_return_reference_chain() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <memory>
-        struct Bob {
-            uint32_t a;
+        #include <string>
+        class Config {
+        public:
+            Config() : level(0) {}
+            void set_level(uint32_t l) { level = l; }
+            uint32_t get_level() const { return level; }
+        private:
+            uint32_t level;
+        };
+        class Key {
+        public:
+            Key() : index(1) {}
+            uint32_t index;
+        };
+        class App {
+        public:
+            Config& settings() { return configs[0]; }
+            const Config& settings_const() const { return configs[0]; }
+            Config& settings_for(const Key& key) { return configs[key.index]; }
+            const Config& settings_for_const(const Key& key) const { return configs[key.index]; }
+            const Key& pick(const Key& key) const { return key; }
+        private:
+            Config configs[2];
         };
-        namespace B {
-            struct C {
-                uint32_t a;
-                Bob daft() const { Bob bob; bob.a = 12; return bob; }
-            };
-        }
     "};
     let rs = quote! {
-        let b = ffi::B::C { a: 12 };
-        assert_eq!(b.daft().a, 12);
-    };
-    run_test("", hdr, rs, &[], &["Bob", "B::C"]);
+        let mut app = ffi::App::new().within_unique_ptr();
+        app.pin_mut().settings().set_level(3);
+        assert_eq!(app.settings_const().get_level(), 3);
+        let config = {
+            let key = ffi::Key::new().within_unique_ptr();
+            app.pin_mut().settings_for(&key).set_level(4);
+            // The returned reference borrows only from the app, so can
+            // outlive the key.
+            app.settings_for_const(&key)
+        };
+        assert_eq!(config.get_level(), 4);
+    };
+    // `pick` returns its argument, not part of the app; unlisted, its
+    // lifetime is still ambiguous, so it's ignored.
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["App", "Config", "Key"],
+            &[],
+            Some(quote! {
+                borrow_from_receiver!("App::settings_for")
+                borrow_from_receiver!("App::settings_for_const")
+            }),
+        ),
+        None,
+        Some(make_string_finder(vec![
+            "the lifetime of the output reference cannot be deduced".to_string(),
+        ])),
+        None,
+    );
 }
 
 #[test]
-fn test_root_ns_meth_ret_nonpod() {
+fn test_destructor() {
     let hdr = indoc! {"
-        #include <cstdint>
-        #include <memory>
-        struct Bob {
-            uint32_t a;
+        struct WithDtor {
+            ~WithDtor();
         };
-        namespace B {
-            struct C {
-                uint32_t a;
-                Bob daft() const { Bob bob; bob.a = 12; return bob; }
-            };
+        WithDtor make_with_dtor();
+    "};
+    let cxx = indoc! {"
+        WithDtor::~WithDtor() {}
+        WithDtor make_with_dtor() {
+            return {};
         }
     "};
     let rs = quote! {
-        let b = ffi::B::C { a: 12 };
-        b.daft().within_unique_ptr().as_ref().unwrap();
+        use ffi::*;
+        let with_dtor: cxx::UniquePtr<WithDtor> = make_with_dtor().within_unique_ptr();
+        drop(with_dtor);
     };
-    run_test("", hdr, rs, &["Bob"], &["B::C"]);
+    run_test(cxx, hdr, rs, &["WithDtor", "make_with_dtor"], &[]);
 }
 
 #[test]
-fn test_forward_declaration() {
+fn test_destructor_side_effects_via_unique_ptr() {
+    // The destructor is defined in a different translation unit from the
+    // generated code, and must still run when each `UniquePtr` is dropped.
     let hdr = indoc! {"
         #include <cstdint>
         #include <memory>
-        struct A;
-        struct B {
-            B() : a(0) {}
-            uint32_t a;
-            void daft(const A&) const {}
-            static B daft3(const A&) { B b; return b; }
-            A daft4();
-            std::unique_ptr<A> daft5();
-            const std::unique_ptr<A>& daft6();
+        class Tracker {
+        public:
+            Tracker(uint32_t id0);
+            ~Tracker();
+            uint32_t id;
         };
-        A* get_a();
-        void delete_a(A*);
+        Tracker make_tracker(uint32_t id);
+        std::unique_ptr<Tracker> make_unique_tracker(uint32_t id);
+        uint32_t destroyed_count();
+        uint32_t last_destroyed();
     "};
-    let cpp = indoc! {"
-        struct A {
-            A() : a(0) {}
-            uint32_t a;
-        };
-        A* get_a() {
-            return new A();
-        }
-        void delete_a(A* a) {
-            delete a;
-        }
-        A B::daft4() {
-            A a;
-            return a;
+    let cxx = indoc! {"
+        static uint32_t destroyed = 0;
+        static uint32_t last = 0;
+        Tracker::Tracker(uint32_t id0) : id(id0) {}
+        Tracker::~Tracker() {
+            destroyed++;
+            last = id;
         }
-        std::unique_ptr<A> B::daft5() {
-            return std::make_unique<A>();
+        Tracker make_tracker(uint32_t id) {
+            return Tracker(id);
         }
-        std::unique_ptr<A> fixed;
-        const std::unique_ptr<A>& B::daft6() {
-            return fixed;
+        std::unique_ptr<Tracker> make_unique_tracker(uint32_t id) {
+            return std::make_unique<Tracker>(id);
         }
+        uint32_t destroyed_count() { return destroyed; }
+        uint32_t last_destroyed() { return last; }
     "};
     let rs = quote! {
-        let b = ffi::B::new().within_unique_ptr();
-        let a = ffi::get_a();
-        b.daft(unsafe { a.as_ref().unwrap() });
-        unsafe { ffi::delete_a(a) };
+        let start = ffi::destroyed_count();
+        let a = ffi::Tracker::new(1).within_unique_ptr();
+        let b = ffi::make_tracker(2).within_unique_ptr();
+        let c = ffi::make_unique_tracker(3);
+        // Constructing these may have destroyed temporaries, so count from here.
+        let constructed = ffi::destroyed_count();
+        assert!(constructed >= start);
+        drop(b);
+        assert_eq!(ffi::destroyed_count(), constructed + 1);
+        assert_eq!(ffi::last_destroyed(), 2);
+        drop(c);
+        assert_eq!(ffi::destroyed_count(), constructed + 2);
+        assert_eq!(ffi::last_destroyed(), 3);
+        // Nothing is destroyed twice, nor when a null UniquePtr is dropped.
+        drop(cxx::UniquePtr::<ffi::Tracker>::null());
+        assert_eq!(ffi::destroyed_count(), constructed + 2);
+        drop(a);
+        assert_eq!(ffi::destroyed_count(), constructed + 3);
+        assert_eq!(ffi::last_destroyed(), 1);
     };
-    run_test(cpp, hdr, rs, &["B", "get_a", "delete_a"], &[]);
+    run_test(
+        cxx,
+        hdr,
+        rs,
+        &[
+            "Tracker",
+            "make_tracker",
+            "make_unique_tracker",
+            "destroyed_count",
+            "last_destroyed",
+        ],
+        &[],
+    );
 }
 
 #[test]
-fn test_ulong() {
+fn test_nested_with_destructor() {
+    // Regression test, naming the destructor in the generated C++ is a bit tricky.
     let hdr = indoc! {"
-    inline unsigned long daft(unsigned long a) { return a; }
+        struct A {
+            struct B {
+                B() = default;
+                ~B() = default;
+            };
+        };
     "};
     let rs = quote! {
-        assert_eq!(ffi::daft(autocxx::c_ulong(34)), autocxx::c_ulong(34));
+        ffi::A_B::new().within_unique_ptr();
     };
-    run_test("", hdr, rs, &["daft"], &[]);
+    run_test("", hdr, rs, &["A", "A_B"], &[]);
 }
 
-#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
-#[cfg_attr(skip_windows_msvc_failing_tests, ignore)]
+// Even without a `safety!`, we still need to generate a safe `fn drop`.
 #[test]
-fn test_typedef_to_ulong() {
+fn test_destructor_no_safety() {
     let hdr = indoc! {"
-        typedef unsigned long fiddly;
-        inline fiddly daft(fiddly a) { return a; }
+        struct WithDtor {
+            ~WithDtor();
+        };
     "};
-    let rs = quote! {
-        assert_eq!(ffi::daft(autocxx::c_ulong(34)), autocxx::c_ulong(34));
+    let cxx = indoc! {"
+        WithDtor::~WithDtor() {}
+    "};
+    let hexathorpe = Token![#](Span::call_site());
+    let unexpanded_rust = quote! {
+        use autocxx::prelude::*;
+
+        include_cpp!(
+            #hexathorpe include "input.h"
+            generate!("WithDtor")
+        );
+
+        fn main() {}
     };
-    run_test("", hdr, rs, &["daft"], &[]);
+
+    do_run_test_manual(cxx, hdr, unexpanded_rust, None, None).unwrap();
 }
 
 #[test]
-fn test_generate_typedef_to_ulong() {
+fn test_static_func() {
     let hdr = indoc! {"
         #include <cstdint>
-        typedef uint32_t fish_t;
+        struct WithStaticMethod {
+            static uint32_t call();
+        };
+    "};
+    let cxx = indoc! {"
+        uint32_t WithStaticMethod::call() {
+            return 42;
+        }
     "};
     let rs = quote! {
-        let _: ffi::fish_t;
+        assert_eq!(ffi::WithStaticMethod::call(), 42);
     };
-    run_test("", hdr, rs, &[], &["fish_t"]);
+    run_test(cxx, hdr, rs, &["WithStaticMethod"], &[]);
 }
 
 #[test]
-fn test_ulong_method() {
+fn test_static_func_wrapper() {
     let hdr = indoc! {"
-    class A {
-        public:
-        A() {};
-        unsigned long daft(unsigned long a) const { return a; }
-    };
+        #include <cstdint>
+        #include <string>
+        struct A {
+            std::string a;
+            static A CreateA(std::string a, std::string) {
+                A c;
+                c.a = a;
+                return c;
+            }
+        };
     "};
     let rs = quote! {
-        let a = ffi::A::new().within_unique_ptr();
-        assert_eq!(a.as_ref().unwrap().daft(autocxx::c_ulong(34)), autocxx::c_ulong(34));
+        use ffi::ToCppString;
+        ffi::A::CreateA("a".into_cpp(), "b".into_cpp());
     };
     run_test("", hdr, rs, &["A"], &[]);
 }
 
 #[test]
-fn test_ulong_wrapped_method() {
-    let hdr = indoc! {"
-    #include <cstdint>
-    struct B {
-        B() {};
-        uint32_t a;
-    };
-    class A {
-        public:
-        A() {};
-        unsigned long daft(unsigned long a, B) const { return a; }
-    };
+fn test_give_pod_typedef_by_value() {
+    let cxx = indoc! {"
+        Horace give_bob() {
+            Horace a;
+            a.a = 3;
+            a.b = 4;
+            return a;
+        }
     "};
-    let rs = quote! {
-        let b = ffi::B::new().within_unique_ptr();
-        let a = ffi::A::new().within_unique_ptr();
-        assert_eq!(a.as_ref().unwrap().daft(autocxx::c_ulong(34), b), autocxx::c_ulong(34));
-    };
-    run_test("", hdr, rs, &["A", "B"], &[]);
-}
-
-#[test]
-fn test_reserved_name() {
     let hdr = indoc! {"
         #include <cstdint>
-        inline uint32_t async(uint32_t a) { return a; }
+        struct Bob {
+            uint32_t a;
+            uint32_t b;
+        };
+        using Horace = Bob;
+        Horace give_bob();
     "};
     let rs = quote! {
-        assert_eq!(ffi::async_(34), 34);
+        assert_eq!(ffi::give_bob().b, 4);
     };
-    run_test("", hdr, rs, &["async_"], &[]);
+    run_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
 }
 
-#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
-#[cfg_attr(skip_windows_msvc_failing_tests, ignore)]
+#[ignore] // because we need to put some aliases in the output ffi mod.
 #[test]
-fn test_nested_type() {
-    // Test that we can import APIs that use nested types.
-    // As a regression test, we also test that the nested type `A::B` doesn't conflict with the
-    // top-level type `B`. This used to cause compile errors.
+fn test_use_pod_typedef() {
+    let cxx = indoc! {"
+    "};
     let hdr = indoc! {"
-        struct A {
-            A() {}
-            struct B {
-                B() {}
-            };
-            enum C {};
-            using D = int;
-        };
-        struct B {
-            B() {}
-            void method_on_top_level_type() const {}
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+            uint32_t b;
         };
-        void take_A_B(A::B);
-        void take_A_C(A::C);
-        void take_A_D(A::D);
+        using Horace = Bob;
     "};
     let rs = quote! {
-        let _ = ffi::A::new().within_unique_ptr();
-        let b = ffi::B::new().within_unique_ptr();
-        b.as_ref().unwrap().method_on_top_level_type();
+        let h = Horace { a: 3, b: 4 };
+        assert_eq!(h.b, 4);
     };
-    run_test("", hdr, rs, &["A", "B", "take_A_B", "take_A_C"], &[]);
+    run_test(cxx, hdr, rs, &[], &["Bob"]);
 }
 
 #[test]
-fn test_nested_type_in_namespace() {
-    // Test that we can import APIs that use nested types in a namespace.
-    // We can't make this part of the previous test as autocxx drops the
-    // namespace, so `A::B` and `N::A::B` would be imported as the same
-    // type.
+fn test_typedef_to_ns() {
     let hdr = indoc! {"
-        namespace N {
-            struct A {
-                A() {}
-                struct B {
-                    B() {}
-                };
+        #include <cstdint>
+        namespace A {
+            template<typename T>
+            struct C {
+                T* t;
             };
-        };
-        void take_A_B(N::A::B);
+            typedef C<char> B;
+        }
     "};
     let rs = quote! {};
-    run_test("", hdr, rs, &["take_A_B"], &[]);
+    run_test("", hdr, rs, &["A::B"], &[]);
 }
 
+#[ignore] // we don't yet allow typedefs to be listed in allow_pod
 #[test]
-fn test_nested_enum_in_namespace() {
-    let hdr = indoc! {"
-        namespace N {
-            struct A {
-                A() {}
-                enum B {
-                    C,
-                    D,
-                };
-            };
-        };
-        void take_A_B(N::A::B);
+fn test_use_pod_typedef_with_allowpod() {
+    let cxx = indoc! {"
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["take_A_B"], &[]);
-}
-
-#[test]
-fn test_abstract_nested_type() {
     let hdr = indoc! {"
-        namespace N {
-            class A {
-            public:
-                A() {}
-                class B {
-                private:
-                    B() {}
-                public:
-                    virtual ~B() {}
-                    virtual void Foo() = 0;
-                };
-            };
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+            uint32_t b;
         };
-        void take_A_B(const N::A::B&);
+        using Horace = Bob;
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["take_A_B", "N::A_B"], &[]);
+    let rs = quote! {
+        let h = Horace { a: 3, b: 4 };
+        assert_eq!(h.b, 4);
+    };
+    run_test(cxx, hdr, rs, &[], &["Horace"]);
 }
 
 #[test]
-fn test_nested_unnamed_enum() {
-    let hdr = indoc! {"
-        namespace N {
-            struct A {
-                enum {
-                    LOW_VAL = 1,
-                    HIGH_VAL = 1000,
-                };
-            };
+fn test_give_nonpod_typedef_by_value() {
+    let cxx = indoc! {"
+        Horace give_bob() {
+            Horace a;
+            a.a = 3;
+            a.b = 4;
+            return a;
         }
     "};
-    run_test_ex(
-        "",
-        hdr,
-        quote! {},
-        quote! { generate_ns!("N")},
-        None,
-        None,
-        None,
-    );
-}
-
-#[test]
-fn test_nested_type_constructor() {
     let hdr = indoc! {"
-        #include <string>
-        class A {
-        public:
-            class B {
-            public:
-                B(const std::string&) {}
-                int b;
-            };
-            int a;
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+            uint32_t b;
         };
+        using Horace = Bob;
+        Horace give_bob();
+        inline uint32_t take_horace(const Horace& horace) { return horace.b; }
     "};
     let rs = quote! {
-        ffi::A_B::new(&ffi::make_string("Hello")).within_unique_ptr();
+        assert_eq!(ffi::take_horace(&moveit!(ffi::give_bob())), 4);
     };
-    run_test("", hdr, rs, &["A_B"], &[]);
+    run_test(cxx, hdr, rs, &["give_bob", "take_horace"], &[]);
 }
 
 #[test]
-fn test_generic_type() {
+fn test_conflicting_static_functions() {
+    let cxx = indoc! {"
+        Bob Bob::create() { Bob a; return a; }
+        Fred Fred::create() { Fred b; return b; }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <string>
-        template<typename TY>
-        struct Container {
-            Container(TY a_) : a(a_) {}
-            TY a;
+        struct Bob {
+            Bob() : a(0) {}
+            uint32_t a;
+            static Bob create();
         };
-        struct Secondary {
-            Secondary() {}
-            void take_a(const Container<char>) const {}
-            void take_b(const Container<uint16_t>) const {}
-            uint16_t take_c(std::string a) const { return 10 + a.size(); }
+        struct Fred {
+            Fred() : b(0) {}
+            uint32_t b;
+            static Fred create();
         };
     "};
     let rs = quote! {
-        use ffi::ToCppString;
-        let item = ffi::Secondary::new().within_unique_ptr();
-        assert_eq!(item.take_c("hello".into_cpp()), 15)
+        ffi::Bob::create();
+        ffi::Fred::create();
     };
-    run_test("", hdr, rs, &["Secondary"], &[]);
+    run_test(cxx, hdr, rs, &[], &["Bob", "Fred"]);
 }
 
 #[test]
-fn test_cycle_generic_type() {
+fn test_conflicting_ns_up_functions() {
+    let cxx = indoc! {"
+        uint32_t A::create(C) { return 3; }
+        uint32_t B::create(C) { return 4; }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        template<typename TY>
-        struct Container {
-            Container(TY a_) : a(a_) {}
-            TY a;
+        struct C {
+            C() {}
+            uint32_t a;
+        };
+        namespace A {
+            uint32_t create(C c);
+        };
+        namespace B {
+            uint32_t create(C c);
         };
-        inline Container<char> make_thingy() {
-            Container<char> a('a');
-            return a;
-        }
-        typedef Container<char> Concrete;
-        inline uint32_t take_thingy(Concrete a) {
-            return a.a;
-        }
     "};
     let rs = quote! {
-        assert_eq!(ffi::take_thingy(ffi::make_thingy()), 'a' as u32)
+        let c = ffi::C::new().within_unique_ptr();
+        let c2 = ffi::C::new().within_unique_ptr();
+        assert_eq!(ffi::A::create(c), 3);
+        assert_eq!(ffi::B::create(c2), 4);
     };
-    run_test("", hdr, rs, &["take_thingy", "make_thingy"], &[]);
+    run_test(cxx, hdr, rs, &["A::create", "B::create", "C"], &[]);
 }
 
 #[test]
-fn test_virtual_fns() {
+fn test_conflicting_methods() {
+    let cxx = indoc! {"
+        uint32_t Bob::get() const { return a; }
+        uint32_t Fred::get() const { return b; }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        class A {
-        public:
-            A(uint32_t num) : b(num) {}
-            virtual uint32_t foo(uint32_t a) { return a+1; };
-            virtual ~A() {}
-            uint32_t b;
+        struct Bob {
+            uint32_t a;
+            uint32_t get() const;
         };
-        class B: public A {
-        public:
-            B() : A(3), c(4) {}
-            virtual uint32_t foo(uint32_t a) { return a+2; };
-            uint32_t c;
+        struct Fred {
+            uint32_t b;
+            uint32_t get() const;
         };
     "};
     let rs = quote! {
-        let mut a = ffi::A::new(12).within_unique_ptr();
-        assert_eq!(a.pin_mut().foo(2), 3);
-        let mut b = ffi::B::new().within_unique_ptr();
-        assert_eq!(b.pin_mut().foo(2), 4);
+        let a = ffi::Bob { a: 10 };
+        let b = ffi::Fred { b: 20 };
+        assert_eq!(a.get(), 10);
+        assert_eq!(b.get(), 20);
     };
-    run_test("", hdr, rs, &["A", "B"], &[]);
+    run_test(cxx, hdr, rs, &[], &["Bob", "Fred"]);
 }
 
 #[test]
-fn test_const_virtual_fns() {
+// There's a bindgen bug here. bindgen generates
+// functions called 'get' and 'get1' but then generates impl
+// blocks which call 'get' and 'get'. By luck, we currently
+// should not be broken by this, but at some point we should take
+// the time to create a minimal bindgen test case and submit it
+// as a bindgen bug.
+fn test_conflicting_up_wrapper_methods_not_in_ns() {
+    // Ensures the two names 'get' do not conflict in the flat
+    // cxx::bridge mod namespace.
+    let cxx = indoc! {"
+        Bob::Bob() : a(\"hello\") {}
+        Fred::Fred() : b(\"goodbye\") {}
+        std::string Bob::get() const { return a; }
+        std::string Fred::get() const { return b; }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        class A {
-        public:
-            A(uint32_t num) : b(num) {}
-            virtual uint32_t foo(uint32_t a) const { return a+1; };
-            virtual ~A() {}
-            uint32_t b;
+        #include <string>
+        struct Bob {
+            Bob();
+            std::string a;
+            std::string get() const;
         };
-        class B: public A {
-        public:
-            B() : A(3), c(4) {}
-            virtual uint32_t foo(uint32_t a) const { return a+2; };
-            uint32_t c;
+        struct Fred {
+            Fred();
+            std::string b;
+            std::string get() const;
         };
     "};
     let rs = quote! {
-        let a = ffi::A::new(12).within_unique_ptr();
-        assert_eq!(a.foo(2), 3);
-        let b = ffi::B::new().within_unique_ptr();
-        assert_eq!(b.foo(2), 4);
+        let a = ffi::Bob::new().within_unique_ptr();
+        let b = ffi::Fred::new().within_unique_ptr();
+        assert_eq!(a.get().as_ref().unwrap().to_str().unwrap(), "hello");
+        assert_eq!(b.get().as_ref().unwrap().to_str().unwrap(), "goodbye");
     };
-    run_test("", hdr, rs, &["A", "B"], &[]);
+    run_test(cxx, hdr, rs, &["Bob", "Fred"], &[]);
 }
 
 #[test]
-#[ignore] // https://github.com/google/autocxx/issues/197
-fn test_virtual_fns_inheritance() {
+fn test_conflicting_methods_in_ns() {
+    let cxx = indoc! {"
+        uint32_t A::Bob::get() const { return a; }
+        uint32_t B::Fred::get() const { return b; }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        class A {
-        public:
-            A(uint32_t num) : b(num) {}
-            virtual uint32_t foo(uint32_t a) { return a+1; };
-            virtual ~A() {}
-            uint32_t b;
-        };
-        class B: public A {
-        public:
-            B() : A(3), c(4) {}
-            uint32_t c;
-        };
+        namespace A {
+            struct Bob {
+                uint32_t a;
+                uint32_t get() const;
+            };
+        }
+        namespace B {
+            struct Fred {
+                uint32_t b;
+                uint32_t get() const;
+            };
+        }
     "};
     let rs = quote! {
-        let mut b = ffi::B::new().within_unique_ptr();
-        assert_eq!(b.pin_mut().foo(2), 3);
+        let a = ffi::A::Bob { a: 10 };
+        let b = ffi::B::Fred { b: 20 };
+        assert_eq!(a.get(), 10);
+        assert_eq!(b.get(), 20);
     };
-    run_test("", hdr, rs, &["B"], &[]);
+    run_test(cxx, hdr, rs, &[], &["A::Bob", "B::Fred"]);
 }
 
 #[test]
-fn test_vector_cycle_up() {
+fn test_conflicting_up_wrapper_methods_in_ns() {
+    let cxx = indoc! {"
+        A::Bob::Bob() : a(\"hello\") {}
+        B::Fred::Fred() : b(\"goodbye\") {}
+        std::string A::Bob::get() const { return a; }
+        std::string B::Fred::get() const { return b; }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <vector>
-        #include <memory>
-        struct A {
-            uint32_t a;
-        };
-        inline uint32_t take_vec(std::unique_ptr<std::vector<A>> many_as) {
-            return many_as->size();
+        #include <string>
+        namespace A {
+            struct Bob {
+                Bob();
+                std::string a;
+                std::string get() const;
+            };
         }
-        inline std::unique_ptr<std::vector<A>> get_vec() {
-            auto items = std::make_unique<std::vector<A>>();
-            items->push_back(A { 3 });
-            items->push_back(A { 4 });
-            return items;
+        namespace B {
+            struct Fred {
+                Fred();
+                std::string b;
+                std::string get() const;
+            };
         }
     "};
     let rs = quote! {
-        let v = ffi::get_vec();
-        assert_eq!(v.as_ref().unwrap().is_empty(), false);
-        assert_eq!(ffi::take_vec(v), 2);
+        let a = ffi::A::Bob::new().within_unique_ptr();
+        let b = ffi::B::Fred::new().within_unique_ptr();
+        assert_eq!(a.get().as_ref().unwrap().to_str().unwrap(), "hello");
+        assert_eq!(b.get().as_ref().unwrap().to_str().unwrap(), "goodbye");
     };
-    run_test("", hdr, rs, &["take_vec", "get_vec"], &[]);
+    run_test(cxx, hdr, rs, &["A::Bob", "B::Fred"], &[]);
 }
 
 #[test]
-fn test_vector_cycle_bare() {
+fn test_ns_struct_pod_request() {
     let hdr = indoc! {"
         #include <cstdint>
-        #include <vector>
-        struct A {
-            uint32_t a;
-        };
-        inline uint32_t take_vec(std::vector<A> many_as) {
-            return many_as.size();
-        }
-        inline std::vector<A> get_vec() {
-            std::vector<A> items;
-            items.push_back(A { 3 });
-            items.push_back(A { 4 });
-            return items;
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
         }
     "};
     let rs = quote! {
-        assert_eq!(ffi::take_vec(ffi::get_vec()), 2);
+        ffi::A::Bob { a: 12 };
     };
-    run_test("", hdr, rs, &["take_vec", "get_vec"], &[]);
+    run_test("", hdr, rs, &[], &["A::Bob"]);
 }
 
 #[test]
-fn test_cycle_up_of_vec() {
+fn test_conflicting_ns_funcs() {
+    let cxx = indoc! {"
+        uint32_t A::get() { return 10; }
+        uint32_t B::get() { return 20; }
+    "};
     let hdr = indoc! {"
         #include <cstdint>
-        #include <vector>
-        #include <memory>
-        struct A {
-            uint32_t a;
-        };
-        inline std::unique_ptr<std::vector<A>> take_vec(std::unique_ptr<std::vector<A>> a) {
-            return a;
+        namespace A {
+            uint32_t get();
         }
-        inline std::unique_ptr<std::vector<A>> get_vec() {
-            std::unique_ptr<std::vector<A>> items = std::make_unique<std::vector<A>>();
-            items->push_back(A { 3 });
-            items->push_back(A { 4 });
-            return items;
+        namespace B {
+            uint32_t get();
         }
     "};
     let rs = quote! {
-        ffi::take_vec(ffi::get_vec());
+        assert_eq!(ffi::A::get(), 10);
+        assert_eq!(ffi::B::get(), 20);
     };
-    run_test("", hdr, rs, &["take_vec", "get_vec"], &[]);
+    run_test(cxx, hdr, rs, &["A::get", "B::get"], &[]);
 }
 
+#[ignore]
+// because currently we feed a flat namespace to cxx
+// This would be relatively easy to enable now that we have the facility
+// to add aliases to the 'use' statements we generate, plus
+// bridge_name_tracker to pick a unique name. TODO.
 #[test]
-fn test_typedef_to_std() {
+fn test_conflicting_ns_structs() {
     let hdr = indoc! {"
-        #include <string>
         #include <cstdint>
-        typedef std::string my_string;
-        inline uint32_t take_str(my_string a) {
-            return a.size();
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
+        }
+        namespace B {
+            struct Bob {
+                uint32_t a;
+            };
         }
     "};
     let rs = quote! {
-        use ffi::ToCppString;
-        assert_eq!(ffi::take_str("hello".into_cpp()), 5);
+        ffi::A::Bob { a: 12 };
+        ffi::B::Bob { b: 12 };
     };
-    run_test("", hdr, rs, &["take_str"], &[]);
+    run_test("", hdr, rs, &[], &["A::Bob", "B::Bob"]);
 }
 
 #[test]
-fn test_typedef_to_up_in_fn_call() {
+fn test_make_string() {
     let hdr = indoc! {"
-        #include <string>
-        #include <memory>
-        typedef std::unique_ptr<std::string> my_string;
-        inline uint32_t take_str(my_string a) {
-            return a->size();
-        }
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+        };
     "};
     let rs = quote! {
         use ffi::ToCppString;
-        assert_eq!(ffi::take_str("hello".into_cpp()), 5);
+        let a = "hello".into_cpp();
+        assert_eq!(a.to_str().unwrap(), "hello");
     };
-    run_test("", hdr, rs, &["take_str"], &[]);
+    run_test("", hdr, rs, &["Bob"], &[]);
 }
 
 #[test]
-fn test_typedef_in_pod_struct() {
+fn test_string_make_unique() {
     let hdr = indoc! {"
         #include <string>
+        inline void take_string(const std::string*) {};
+    "};
+    let rs = quote! {
+        let s = ffi::make_string("");
+        unsafe { ffi::take_string(s.as_ref().unwrap()) };
+    };
+    run_test("", hdr, rs, &["take_string"], &[]);
+}
+
+#[test]
+fn test_string_constant() {
+    let hdr = indoc! {"
         #include <cstdint>
-        typedef uint32_t my_int;
+        const char* STRING = \"Foo\";
+    "};
+    let rs = quote! {
+        let a = core::str::from_utf8(ffi::STRING).unwrap().trim_end_matches(char::from(0));
+        assert_eq!(a, "Foo");
+    };
+    run_test("", hdr, rs, &["STRING"], &[]);
+}
+
+#[test]
+fn test_string_let_cxx_string() {
+    let hdr = indoc! {"
+        #include <string>
+        inline void take_string(const std::string&) {};
+    "};
+    let rs = quote! {
+        autocxx::cxx::let_cxx_string!(s = "hello");
+        ffi::take_string(&s);
+    };
+    run_test("", hdr, rs, &["take_string"], &[]);
+}
+
+#[test]
+fn test_pod_constant_harmless_inside_type() {
+    // Check that the presence of this constant doesn't break anything.
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+        };
+        struct Anna {
+            uint32_t a;
+            const Bob BOB = Bob { 10 };
+        };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &[], &["Anna"]);
+}
+
+#[test]
+#[ignore] // https://github.com/google/autocxx/issues/93
+fn test_pod_constant() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+        };
+        const Bob BOB = Bob { 10 };
+    "};
+    let rs = quote! {
+        let a = &ffi::BOB;
+        assert_eq!(a.a, 10);
+    };
+    run_test("", hdr, rs, &["BOB"], &["Bob"]);
+}
+
+#[test]
+fn test_pod_static_harmless_inside_type() {
+    // Check that the presence of this constant doesn't break anything.
+    // Remove this test when the following one is enabled.
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+        };
+        struct Anna {
+            uint32_t a;
+            static Bob BOB;
+        };
+        Bob Anna::BOB = Bob { 10 };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &[], &["Anna"]);
+}
+
+#[test]
+#[ignore] // https://github.com/google/autocxx/issues/93
+fn test_pod_static() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+        };
+        static Bob BOB = Bob { 10 };
+    "};
+    let rs = quote! {
+        let a = &ffi::BOB;
+        assert_eq!(a.a, 10);
+    };
+    run_test("", hdr, rs, &["BOB"], &["Bob"]);
+}
+
+#[test]
+#[ignore] // this probably requires code generation on the C++
+          // side. It's not at all clear how best to handle this.
+fn test_non_pod_constant() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        struct Bob {
+            std::string a;
+            std::string get() { return a };
+        };
+        const Bob BOB = Bob { \"hello\" };
+    "};
+    let rs = quote! {
+        let a = ffi::BOB;
+        // following line assumes that 'a' is a &Bob
+        // but who knows how we'll really do this.
+        assert_eq!(a.get().as_ref().unwrap().to_str().unwrap(), "hello");
+    };
+    run_test("", hdr, rs, &["BOB"], &[]);
+}
+
+#[test]
+fn test_templated_typedef() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+
+        template <typename STRING_TYPE> class BasicStringPiece {
+        public:
+            const STRING_TYPE* ptr_;
+            size_t length_;
+        };
+        typedef BasicStringPiece<uint8_t> StringPiece;
+
+        struct Origin {
+            Origin() {}
+            StringPiece host;
+        };
+    "};
+    let rs = quote! {
+        ffi::Origin::new().within_unique_ptr();
+    };
+    run_test("", hdr, rs, &["Origin"], &[]);
+}
+
+#[test]
+fn test_struct_templated_typedef() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+
+        struct Concrete {
+            uint8_t a;
+        };
+        template <typename STRING_TYPE> class BasicStringPiece {
+        public:
+            const STRING_TYPE* ptr_;
+            size_t length_;
+        };
+        typedef BasicStringPiece<Concrete> StringPiece;
+
+        struct Origin {
+            Origin() {}
+            StringPiece host;
+        };
+    "};
+    let rs = quote! {
+        ffi::Origin::new().within_unique_ptr();
+    };
+    run_test("", hdr, rs, &["Origin"], &[]);
+}
+
+#[test]
+fn test_enum_typedef() {
+    let hdr = indoc! {"
+        enum ConstraintSolverParameters_TrailCompression : int {
+            ConstraintSolverParameters_TrailCompression_NO_COMPRESSION = 0,
+            ConstraintSolverParameters_TrailCompression_COMPRESS_WITH_ZLIB = 1
+        };
+        typedef ConstraintSolverParameters_TrailCompression TrailCompression;
+    "};
+    let rs = quote! {
+        let _ = ffi::TrailCompression::ConstraintSolverParameters_TrailCompression_NO_COMPRESSION;
+    };
+    run_test("", hdr, rs, &["TrailCompression"], &[]);
+}
+
+#[test]
+#[ignore] // https://github.com/google/autocxx/issues/264
+fn test_conflicting_usings() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <cstddef>
+        typedef size_t diff;
         struct A {
-            my_int a;
+            using diff = diff;
+            diff a;
+        };
+        struct B {
+            using diff = diff;
+            diff a;
+        };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &[], &["A", "B"]);
+}
+
+#[test]
+fn test_conflicting_usings_with_self_declaration1() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <cstddef>
+        struct common_params {
+            using difference_type = ptrdiff_t;
+        };
+        template <typename Params>
+        class btree_node {
+            public:
+            using difference_type = typename Params::difference_type;
+            Params params;
+        };
+        template <typename Tree>
+        class btree_container {
+            public:
+            using difference_type = typename Tree::difference_type;
+            void clear() {}
+            Tree b;
+            uint32_t a;
+        };
+        typedef btree_container<btree_node<common_params>> my_tree;
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["my_tree"], &[]);
+}
+
+#[test]
+#[ignore] // https://github.com/google/autocxx/issues/106
+fn test_string_templated_typedef() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+
+        template <typename STRING_TYPE> class BasicStringPiece {
+        public:
+            const STRING_TYPE* ptr_;
+            size_t length_;
+        };
+        typedef BasicStringPiece<std::string> StringPiece;
+
+        struct Origin {
+            Origin() {}
+            StringPiece host;
+        };
+    "};
+    let rs = quote! {
+        ffi::Origin::new().within_unique_ptr();
+    };
+    run_test("", hdr, rs, &["Origin"], &[]);
+}
+
+#[test]
+fn test_associated_type_problem() {
+    // Regression test for a potential bindgen bug
+    let hdr = indoc! {"
+        namespace a {
+        template <typename> class b {};
+        } // namespace a
+        class bl {
+        public:
+          a::b<bl> bm;
+        };
+        struct B {
+            int a;
+        };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["B"], &[]);
+}
+
+#[test]
+fn test_two_type_constructors() {
+    // https://github.com/google/autocxx/issues/877
+    let hdr = indoc! {"
+        struct A {
+            int a;
+        };
+        struct B {
+            int B;
+        };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["A", "B"], &[]);
+}
+
+#[ignore] // https://github.com/rust-lang/rust-bindgen/issues/1924
+#[test]
+fn test_associated_type_templated_typedef_in_struct() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+
+        template <typename STRING_TYPE> class BasicStringPiece {
+        public:
+            typedef size_t size_type;
+            typedef typename STRING_TYPE::value_type value_type;
+            const value_type* ptr_;
+            size_type length_;
+        };
+
+        typedef BasicStringPiece<std::string> StringPiece;
+
+        struct Origin {
+            // void SetHost(StringPiece host);
+            StringPiece host;
+        };
+    "};
+    let rs = quote! {
+        ffi::Origin::new().within_unique_ptr();
+    };
+    run_test("", hdr, rs, &["Origin"], &[]);
+}
+
+#[test]
+fn test_associated_type_templated_typedef() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+
+        template <typename STRING_TYPE> class BasicStringPiece {
+        public:
+            typedef size_t size_type;
+            typedef typename STRING_TYPE::value_type value_type;
+            const value_type* ptr_;
+            size_type length_;
+        };
+
+        typedef BasicStringPiece<std::string> StringPiece;
+
+        struct Container {
+            Container() {}
+            const StringPiece& get_string_piece() const { return sp; }
+            StringPiece sp;
+        };
+
+        inline void take_string_piece(const StringPiece&) {}
+    "};
+    let rs = quote! {
+        let sp = ffi::Container::new().within_box();
+        ffi::take_string_piece(sp.get_string_piece());
+    };
+    run_test("", hdr, rs, &["take_string_piece", "Container"], &[]);
+}
+
+#[test]
+fn test_associated_type_templated_typedef_by_value_regular() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+
+        template <typename STRING_TYPE> class BasicStringPiece {
+        public:
+            BasicStringPiece() : ptr_(nullptr), length_(0) {}
+            typedef size_t size_type;
+            typedef typename STRING_TYPE::value_type value_type;
+            const value_type* ptr_;
+            size_type length_;
+        };
+
+        typedef BasicStringPiece<std::string> StringPiece;
+
+        inline StringPiece give_string_piece() {
+            StringPiece s;
+            return s;
+        }
+        inline void take_string_piece(StringPiece) {}
+    "};
+    let rs = quote! {
+        let sp = ffi::give_string_piece();
+        ffi::take_string_piece(sp);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("take_string_piece")
+            generate!("give_string_piece")
+            instantiable!("StringPiece")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_associated_type_templated_typedef_by_value_forward_declaration() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+
+        template <typename STRING_TYPE> class BasicStringPiece;
+
+        typedef BasicStringPiece<std::string> StringPiece;
+
+        struct Container {
+            StringPiece give_string_piece() const;
+            void take_string_piece(StringPiece string_piece) const;
+            const StringPiece& get_string_piece() const;
+            uint32_t b;
+        };
+
+        inline void take_string_piece_by_ref(const StringPiece&) {}
+    "};
+    let cpp = indoc! {"
+        template <typename STRING_TYPE> class BasicStringPiece {
+        public:
+            BasicStringPiece() : ptr_(nullptr), length_(0) {}
+            typedef size_t size_type;
+            typedef typename STRING_TYPE::value_type value_type;
+            const value_type* ptr_;
+            size_type length_;
+        };
+
+        StringPiece Container::give_string_piece() const {
+            StringPiece s;
+            return s;
+        }
+        void Container::take_string_piece(StringPiece) const {}
+
+        StringPiece a;
+
+        const StringPiece& Container::get_string_piece() const {
+            return a;
+        }
+    "};
+    // As this template is forward declared we shouldn't be able to pass it by
+    // value, but we still want to be able to use it by reference.
+    let rs = quote! {
+        let cont = ffi::Container::new().within_box();
+        ffi::take_string_piece_by_ref(cont.as_ref().get_string_piece());
+    };
+    run_test(
+        cpp,
+        hdr,
+        rs,
+        &["take_string_piece_by_ref", "Container"],
+        &[],
+    );
+}
+
+#[test]
+fn test_remove_cv_t_pathological() {
+    let hdr = indoc! {"
+        template <class _Ty>
+        struct remove_cv {
+            using type = _Ty;
+            template <template <class> class _Fn>
+            using _Apply = _Fn<_Ty>;
+        };
+
+        template <class _Ty>
+        struct remove_cv<const _Ty> {
+            using type = _Ty;
+            template <template <class> class _Fn>
+            using _Apply = const _Fn<_Ty>;
+        };
+
+        template <class _Ty>
+        struct remove_cv<volatile _Ty> {
+            using type = _Ty;
+            template <template <class> class _Fn>
+            using _Apply = volatile _Fn<_Ty>;
+        };
+
+        template <class _Ty>
+        struct remove_cv<const volatile _Ty> {
+            using type = _Ty;
+            template <template <class> class _Fn>
+            using _Apply = const volatile _Fn<_Ty>;
+        };
+
+        template <class _Ty>
+        using remove_cv_t = typename remove_cv<_Ty>::type;
+
+        template <class _Ty>
+        struct remove_reference {
+            using type                 = _Ty;
+            using _Const_thru_ref_type = const _Ty;
+        };
+
+        template <class _Ty>
+        struct remove_reference<_Ty&> {
+            using type                 = _Ty;
+            using _Const_thru_ref_type = const _Ty&;
+        };
+
+        template <class _Ty>
+        struct remove_reference<_Ty&&> {
+            using type                 = _Ty;
+            using _Const_thru_ref_type = const _Ty&&;
+        };
+
+        template <class _Ty>
+        using remove_reference_t = typename remove_reference<_Ty>::type;
+
+        template <class _Ty>
+        using _Remove_cvref_t = remove_cv_t<remove_reference_t<_Ty>>;
+    "};
+    run_generate_all_test(hdr);
+}
+
+#[test]
+fn test_foreign_ns_func_arg_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
+        }
+        namespace B {
+            inline uint32_t daft(A::Bob a) { return a.a; }
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::A::Bob { a: 12 };
+        assert_eq!(ffi::B::daft(a), 12);
+    };
+    run_test("", hdr, rs, &["B::daft"], &["A::Bob"]);
+}
+
+#[test]
+fn test_foreign_ns_func_arg_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+                Bob(uint32_t _a) :a(_a) {}
+            };
+        }
+        namespace B {
+            inline uint32_t daft(A::Bob a) { return a.a; }
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::A::Bob::new(12).within_unique_ptr();
+        assert_eq!(ffi::B::daft(a), 12);
+    };
+    run_test("", hdr, rs, &["B::daft", "A::Bob"], &[]);
+}
+
+#[test]
+fn test_foreign_ns_meth_arg_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
+        }
+        namespace B {
+            struct C {
+                uint32_t a;
+                uint32_t daft(A::Bob a) const { return a.a; }
+            };
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::A::Bob { a: 12 };
+        let b = ffi::B::C { a: 12 };
+        assert_eq!(b.daft(a), 12);
+    };
+    run_test("", hdr, rs, &[], &["A::Bob", "B::C"]);
+}
+
+#[test]
+fn test_foreign_ns_meth_arg_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+                Bob(uint32_t _a) :a(_a) {}
+            };
+        }
+        namespace B {
+            struct C {
+                uint32_t a;
+                uint32_t daft(A::Bob a) const { return a.a; }
+            };
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::A::Bob::new(12).within_unique_ptr();
+        let b = ffi::B::C { a: 12 };
+        assert_eq!(b.daft(a), 12);
+    };
+    run_test("", hdr, rs, &["A::Bob"], &["B::C"]);
+}
+
+#[test]
+fn test_foreign_ns_cons_arg_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
+        }
+        namespace B {
+            struct C {
+                uint32_t a;
+                C(const A::Bob& input) : a(input.a) {}
+            };
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::A::Bob { a: 12 };
+        let b = ffi::B::C::new(&a).within_unique_ptr();
+        assert_eq!(b.as_ref().unwrap().a, 12);
+    };
+    run_test("", hdr, rs, &[], &["B::C", "A::Bob"]);
+}
+
+#[test]
+fn test_foreign_ns_cons_arg_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                Bob(uint32_t _a) :a(_a) {}
+                uint32_t a;
+            };
+        }
+        namespace B {
+            struct C {
+                uint32_t a;
+                C(const A::Bob& input) : a(input.a) {}
+            };
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::A::Bob::new(12).within_unique_ptr();
+        let b = ffi::B::C::new(&a).within_unique_ptr();
+        assert_eq!(b.as_ref().unwrap().a, 12);
+    };
+    run_test("", hdr, rs, &["A::Bob"], &["B::C"]);
+}
+
+#[test]
+fn test_foreign_ns_func_ret_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
+        }
+        namespace B {
+            inline A::Bob daft() { A::Bob bob; bob.a = 12; return bob; }
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::B::daft().a, 12);
+    };
+    run_test("", hdr, rs, &["B::daft"], &["A::Bob"]);
+}
+
+#[test]
+fn test_foreign_ns_func_ret_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
+        }
+        namespace B {
+            inline A::Bob daft() { A::Bob bob; bob.a = 12; return bob; }
+        }
+    "};
+    let rs = quote! {
+        ffi::B::daft().within_box().as_ref();
+    };
+    run_test("", hdr, rs, &["B::daft", "A::Bob"], &[]);
+}
+
+#[test]
+fn test_foreign_ns_meth_ret_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
+        }
+        namespace B {
+            struct C {
+                uint32_t a;
+                A::Bob daft() const { A::Bob bob; bob.a = 12; return bob; }
+            };
+        }
+    "};
+    let rs = quote! {
+        let b = ffi::B::C { a: 12 };
+        assert_eq!(b.daft().a, 12);
+    };
+    run_test("", hdr, rs, &[], &["A::Bob", "B::C"]);
+}
+
+#[test]
+fn test_foreign_ns_meth_ret_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace A {
+            struct Bob {
+                uint32_t a;
+            };
+        }
+        namespace B {
+            struct C {
+                uint32_t a;
+                A::Bob daft() const { A::Bob bob; bob.a = 12; return bob; }
+            };
+        }
+    "};
+    let rs = quote! {
+        let b = ffi::B::C { a: 14 };
+        b.daft().within_unique_ptr().as_ref().unwrap();
+    };
+    run_test("", hdr, rs, &["A::Bob"], &["B::C"]);
+}
+
+#[test]
+fn test_root_ns_func_arg_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        namespace B {
+            inline uint32_t daft(Bob a) { return a.a; }
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::Bob { a: 12 };
+        assert_eq!(ffi::B::daft(a), 12);
+    };
+    run_test("", hdr, rs, &["B::daft"], &["Bob"]);
+}
+
+#[test]
+fn test_root_ns_func_arg_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+            Bob(uint32_t _a) :a(_a) {}
+        };
+        namespace B {
+            inline uint32_t daft(Bob a) { return a.a; }
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::Bob::new(12).within_unique_ptr();
+        assert_eq!(ffi::B::daft(a), 12);
+    };
+    run_test("", hdr, rs, &["B::daft", "Bob"], &[]);
+}
+
+#[test]
+fn test_root_ns_meth_arg_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        namespace B {
+            struct C {
+                uint32_t a;
+                uint32_t daft(Bob a) const { return a.a; }
+            };
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::Bob { a: 12 };
+        let b = ffi::B::C { a: 12 };
+        assert_eq!(b.daft(a), 12);
+    };
+    run_test("", hdr, rs, &[], &["Bob", "B::C"]);
+}
+
+#[test]
+fn test_root_ns_meth_arg_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+            Bob(uint32_t _a) :a(_a) {}
+        };
+        namespace B {
+            struct C {
+                uint32_t a;
+                uint32_t daft(Bob a) const { return a.a; }
+            };
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::Bob::new(12).within_unique_ptr();
+        let b = ffi::B::C { a: 12 };
+        assert_eq!(b.daft(a), 12);
+    };
+    run_test("", hdr, rs, &["Bob"], &["B::C"]);
+}
+
+#[test]
+fn test_root_ns_cons_arg_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        namespace B {
+            struct C {
+                uint32_t a;
+                C(const Bob& input) : a(input.a) {}
+            };
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::Bob { a: 12 };
+        let b = ffi::B::C::new(&a).within_unique_ptr();
+        assert_eq!(b.as_ref().unwrap().a, 12);
+    };
+    run_test("", hdr, rs, &[], &["B::C", "Bob"]);
+}
+
+#[test]
+fn test_root_ns_cons_arg_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            Bob(uint32_t _a) :a(_a) {}
+            uint32_t a;
+        };
+        namespace B {
+            struct C {
+                uint32_t a;
+                C(const Bob& input) : a(input.a) {}
+            };
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::Bob::new(12).within_unique_ptr();
+        let b = ffi::B::C::new(&a).within_unique_ptr();
+        assert_eq!(b.as_ref().unwrap().a, 12);
+    };
+    run_test("", hdr, rs, &["Bob"], &["B::C"]);
+}
+
+#[test]
+fn test_root_ns_func_ret_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        namespace B {
+            inline Bob daft() { Bob bob; bob.a = 12; return bob; }
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::B::daft().a, 12);
+    };
+    run_test("", hdr, rs, &["B::daft"], &["Bob"]);
+}
+
+#[test]
+fn test_root_ns_func_ret_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        namespace B {
+            inline Bob daft() { Bob bob; bob.a = 12; return bob; }
+        }
+    "};
+    let rs = quote! {
+        ffi::B::daft().within_unique_ptr().as_ref().unwrap();
+    };
+    run_test("", hdr, rs, &["B::daft", "Bob"], &[]);
+}
+
+#[test]
+fn test_root_ns_meth_ret_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        namespace B {
+            struct C {
+                uint32_t a;
+                Bob daft() const { Bob bob; bob.a = 12; return bob; }
+            };
+        }
+    "};
+    let rs = quote! {
+        let b = ffi::B::C { a: 12 };
+        assert_eq!(b.daft().a, 12);
+    };
+    run_test("", hdr, rs, &[], &["Bob", "B::C"]);
+}
+
+#[test]
+fn test_root_ns_meth_ret_nonpod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Bob {
+            uint32_t a;
+        };
+        namespace B {
+            struct C {
+                uint32_t a;
+                Bob daft() const { Bob bob; bob.a = 12; return bob; }
+            };
+        }
+    "};
+    let rs = quote! {
+        let b = ffi::B::C { a: 12 };
+        b.daft().within_unique_ptr().as_ref().unwrap();
+    };
+    run_test("", hdr, rs, &["Bob"], &["B::C"]);
+}
+
+#[test]
+fn test_forward_declaration() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct A;
+        struct B {
+            B() : a(0) {}
+            uint32_t a;
+            void daft(const A&) const {}
+            static B daft3(const A&) { B b; return b; }
+            A daft4();
+            std::unique_ptr<A> daft5();
+            const std::unique_ptr<A>& daft6();
+        };
+        A* get_a();
+        void delete_a(A*);
+    "};
+    let cpp = indoc! {"
+        struct A {
+            A() : a(0) {}
+            uint32_t a;
+        };
+        A* get_a() {
+            return new A();
+        }
+        void delete_a(A* a) {
+            delete a;
+        }
+        A B::daft4() {
+            A a;
+            return a;
+        }
+        std::unique_ptr<A> B::daft5() {
+            return std::make_unique<A>();
+        }
+        std::unique_ptr<A> fixed;
+        const std::unique_ptr<A>& B::daft6() {
+            return fixed;
+        }
+    "};
+    let rs = quote! {
+        let b = ffi::B::new().within_unique_ptr();
+        let a = ffi::get_a();
+        b.daft(unsafe { a.as_ref().unwrap() });
+        unsafe { ffi::delete_a(a) };
+    };
+    run_test(cpp, hdr, rs, &["B", "get_a", "delete_a"], &[]);
+}
+
+#[test]
+fn test_ulong() {
+    let hdr = indoc! {"
+    inline unsigned long daft(unsigned long a) { return a; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::daft(autocxx::c_ulong(34)), autocxx::c_ulong(34));
+    };
+    run_test("", hdr, rs, &["daft"], &[]);
+}
+
+#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
+#[cfg_attr(skip_windows_msvc_failing_tests, ignore)]
+#[test]
+fn test_typedef_to_ulong() {
+    let hdr = indoc! {"
+        typedef unsigned long fiddly;
+        inline fiddly daft(fiddly a) { return a; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::daft(autocxx::c_ulong(34)), autocxx::c_ulong(34));
+    };
+    run_test("", hdr, rs, &["daft"], &[]);
+}
+
+#[test]
+fn test_generate_typedef_to_ulong() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        typedef uint32_t fish_t;
+    "};
+    let rs = quote! {
+        let _: ffi::fish_t;
+    };
+    run_test("", hdr, rs, &[], &["fish_t"]);
+}
+
+#[test]
+fn test_ulong_method() {
+    let hdr = indoc! {"
+    class A {
+        public:
+        A() {};
+        unsigned long daft(unsigned long a) const { return a; }
+    };
+    "};
+    let rs = quote! {
+        let a = ffi::A::new().within_unique_ptr();
+        assert_eq!(a.as_ref().unwrap().daft(autocxx::c_ulong(34)), autocxx::c_ulong(34));
+    };
+    run_test("", hdr, rs, &["A"], &[]);
+}
+
+#[test]
+fn test_ulong_wrapped_method() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    struct B {
+        B() {};
+        uint32_t a;
+    };
+    class A {
+        public:
+        A() {};
+        unsigned long daft(unsigned long a, B) const { return a; }
+    };
+    "};
+    let rs = quote! {
+        let b = ffi::B::new().within_unique_ptr();
+        let a = ffi::A::new().within_unique_ptr();
+        assert_eq!(a.as_ref().unwrap().daft(autocxx::c_ulong(34), b), autocxx::c_ulong(34));
+    };
+    run_test("", hdr, rs, &["A", "B"], &[]);
+}
+
+#[test]
+fn test_reserved_name() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t async(uint32_t a) { return a; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::async_(34), 34);
+    };
+    run_test("", hdr, rs, &["async_"], &[]);
+}
+
+#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
+#[cfg_attr(skip_windows_msvc_failing_tests, ignore)]
+#[test]
+fn test_nested_type() {
+    // Test that we can import APIs that use nested types.
+    // As a regression test, we also test that the nested type `A::B` doesn't conflict with the
+    // top-level type `B`. This used to cause compile errors.
+    let hdr = indoc! {"
+        struct A {
+            A() {}
+            struct B {
+                B() {}
+            };
+            enum C {};
+            using D = int;
+        };
+        struct B {
+            B() {}
+            void method_on_top_level_type() const {}
+        };
+        void take_A_B(A::B);
+        void take_A_C(A::C);
+        void take_A_D(A::D);
+    "};
+    let rs = quote! {
+        let _ = ffi::A::new().within_unique_ptr();
+        let b = ffi::B::new().within_unique_ptr();
+        b.as_ref().unwrap().method_on_top_level_type();
+    };
+    run_test("", hdr, rs, &["A", "B", "take_A_B", "take_A_C"], &[]);
+}
+
+#[test]
+fn test_nested_type_in_namespace() {
+    // Test that we can import APIs that use nested types in a namespace.
+    // We can't make this part of the previous test as autocxx drops the
+    // namespace, so `A::B` and `N::A::B` would be imported as the same
+    // type.
+    let hdr = indoc! {"
+        namespace N {
+            struct A {
+                A() {}
+                struct B {
+                    B() {}
+                };
+            };
+        };
+        void take_A_B(N::A::B);
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["take_A_B"], &[]);
+}
+
+#[test]
+fn test_nested_enum_in_namespace() {
+    let hdr = indoc! {"
+        namespace N {
+            struct A {
+                A() {}
+                enum B {
+                    C,
+                    D,
+                };
+            };
+        };
+        void take_A_B(N::A::B);
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["take_A_B"], &[]);
+}
+
+#[test]
+fn test_abstract_nested_type() {
+    let hdr = indoc! {"
+        namespace N {
+            class A {
+            public:
+                A() {}
+                class B {
+                private:
+                    B() {}
+                public:
+                    virtual ~B() {}
+                    virtual void Foo() = 0;
+                };
+            };
+        };
+        void take_A_B(const N::A::B&);
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["take_A_B", "N::A_B"], &[]);
+}
+
+#[test]
+fn test_nested_unnamed_enum() {
+    let hdr = indoc! {"
+        namespace N {
+            struct A {
+                enum {
+                    LOW_VAL = 1,
+                    HIGH_VAL = 1000,
+                };
+            };
+        }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! { generate_ns!("N")},
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_nested_type_constructor() {
+    let hdr = indoc! {"
+        #include <string>
+        class A {
+        public:
+            class B {
+            public:
+                B(const std::string&) {}
+                int b;
+            };
+            int a;
+        };
+    "};
+    let rs = quote! {
+        ffi::A_B::new(&ffi::make_string("Hello")).within_unique_ptr();
+    };
+    run_test("", hdr, rs, &["A_B"], &[]);
+}
+
+#[test]
+fn test_generic_type() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        template<typename TY>
+        struct Container {
+            Container(TY a_) : a(a_) {}
+            TY a;
+        };
+        struct Secondary {
+            Secondary() {}
+            void take_a(const Container<char>) const {}
+            void take_b(const Container<uint16_t>) const {}
+            uint16_t take_c(std::string a) const { return 10 + a.size(); }
+        };
+    "};
+    let rs = quote! {
+        use ffi::ToCppString;
+        let item = ffi::Secondary::new().within_unique_ptr();
+        assert_eq!(item.take_c("hello".into_cpp()), 15)
+    };
+    run_test("", hdr, rs, &["Secondary"], &[]);
+}
+
+#[test]
+fn test_cycle_generic_type() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        template<typename TY>
+        struct Container {
+            Container(TY a_) : a(a_) {}
+            TY a;
+        };
+        inline Container<char> make_thingy() {
+            Container<char> a('a');
+            return a;
+        }
+        typedef Container<char> Concrete;
+        inline uint32_t take_thingy(Concrete a) {
+            return a.a;
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::take_thingy(ffi::make_thingy()), 'a' as u32)
+    };
+    run_test("", hdr, rs, &["take_thingy", "make_thingy"], &[]);
+}
+
+#[test]
+fn test_virtual_fns() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        class A {
+        public:
+            A(uint32_t num) : b(num) {}
+            virtual uint32_t foo(uint32_t a) { return a+1; };
+            virtual ~A() {}
+            uint32_t b;
+        };
+        class B: public A {
+        public:
+            B() : A(3), c(4) {}
+            virtual uint32_t foo(uint32_t a) { return a+2; };
+            uint32_t c;
+        };
+    "};
+    let rs = quote! {
+        let mut a = ffi::A::new(12).within_unique_ptr();
+        assert_eq!(a.pin_mut().foo(2), 3);
+        let mut b = ffi::B::new().within_unique_ptr();
+        assert_eq!(b.pin_mut().foo(2), 4);
+    };
+    run_test("", hdr, rs, &["A", "B"], &[]);
+}
+
+#[test]
+fn test_const_virtual_fns() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        class A {
+        public:
+            A(uint32_t num) : b(num) {}
+            virtual uint32_t foo(uint32_t a) const { return a+1; };
+            virtual ~A() {}
+            uint32_t b;
+        };
+        class B: public A {
+        public:
+            B() : A(3), c(4) {}
+            virtual uint32_t foo(uint32_t a) const { return a+2; };
+            uint32_t c;
+        };
+    "};
+    let rs = quote! {
+        let a = ffi::A::new(12).within_unique_ptr();
+        assert_eq!(a.foo(2), 3);
+        let b = ffi::B::new().within_unique_ptr();
+        assert_eq!(b.foo(2), 4);
+    };
+    run_test("", hdr, rs, &["A", "B"], &[]);
+}
+
+#[test]
+#[ignore] // https://github.com/google/autocxx/issues/197
+fn test_virtual_fns_inheritance() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        class A {
+        public:
+            A(uint32_t num) : b(num) {}
+            virtual uint32_t foo(uint32_t a) { return a+1; };
+            virtual ~A() {}
+            uint32_t b;
+        };
+        class B: public A {
+        public:
+            B() : A(3), c(4) {}
+            uint32_t c;
+        };
+    "};
+    let rs = quote! {
+        let mut b = ffi::B::new().within_unique_ptr();
+        assert_eq!(b.pin_mut().foo(2), 3);
+    };
+    run_test("", hdr, rs, &["B"], &[]);
+}
+
+#[test]
+fn test_vector_cycle_up() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <vector>
+        #include <memory>
+        struct A {
+            uint32_t a;
+        };
+        inline uint32_t take_vec(std::unique_ptr<std::vector<A>> many_as) {
+            return many_as->size();
+        }
+        inline std::unique_ptr<std::vector<A>> get_vec() {
+            auto items = std::make_unique<std::vector<A>>();
+            items->push_back(A { 3 });
+            items->push_back(A { 4 });
+            return items;
+        }
+    "};
+    let rs = quote! {
+        let v = ffi::get_vec();
+        assert_eq!(v.as_ref().unwrap().is_empty(), false);
+        assert_eq!(ffi::take_vec(v), 2);
+    };
+    run_test("", hdr, rs, &["take_vec", "get_vec"], &[]);
+}
+
+#[test]
+fn test_vector_cycle_bare() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <vector>
+        struct A {
+            uint32_t a;
+        };
+        inline uint32_t take_vec(std::vector<A> many_as) {
+            return many_as.size();
+        }
+        inline std::vector<A> get_vec() {
+            std::vector<A> items;
+            items.push_back(A { 3 });
+            items.push_back(A { 4 });
+            return items;
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::take_vec(ffi::get_vec()), 2);
+    };
+    run_test("", hdr, rs, &["take_vec", "get_vec"], &[]);
+}
+
+#[test]
+fn test_cycle_up_of_vec() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <vector>
+        #include <memory>
+        struct A {
+            uint32_t a;
+        };
+        inline std::unique_ptr<std::vector<A>> take_vec(std::unique_ptr<std::vector<A>> a) {
+            return a;
+        }
+        inline std::unique_ptr<std::vector<A>> get_vec() {
+            std::unique_ptr<std::vector<A>> items = std::make_unique<std::vector<A>>();
+            items->push_back(A { 3 });
+            items->push_back(A { 4 });
+            return items;
+        }
+    "};
+    let rs = quote! {
+        ffi::take_vec(ffi::get_vec());
+    };
+    run_test("", hdr, rs, &["take_vec", "get_vec"], &[]);
+}
+
+#[test]
+fn test_typedef_to_std() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+        typedef std::string my_string;
+        inline uint32_t take_str(my_string a) {
+            return a.size();
+        }
+    "};
+    let rs = quote! {
+        use ffi::ToCppString;
+        assert_eq!(ffi::take_str("hello".into_cpp()), 5);
+    };
+    run_test("", hdr, rs, &["take_str"], &[]);
+}
+
+#[test]
+fn test_take_string_from_cow() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+        inline uint32_t take_str(std::string a) {
+            return a.size();
+        }
+    "};
+    let rs = quote! {
+        use std::borrow::Cow;
+        let borrowed: Cow<str> = Cow::Borrowed("hello");
+        let owned: Cow<str> = Cow::Owned("hello world".to_string());
+        assert_eq!(ffi::take_str(&borrowed), 5);
+        assert_eq!(ffi::take_str(&owned), 11);
+        assert_eq!(ffi::take_str(borrowed), 5);
+        assert_eq!(ffi::take_str(owned), 11);
+    };
+    run_test("", hdr, rs, &["take_str"], &[]);
+}
+
+#[test]
+fn test_take_string_view_from_cow() {
+    let hdr = indoc! {"
+        #include <string_view>
+        #include <cstdint>
+        inline uint32_t take_view(std::string_view a) {
+            return a.size();
+        }
+    "};
+    let rs = quote! {
+        use std::borrow::Cow;
+        let borrowed: Cow<str> = Cow::Borrowed("hello");
+        let owned: Cow<str> = Cow::Owned("hello world".to_string());
+        assert_eq!(ffi::take_view(&borrowed), 5);
+        assert_eq!(ffi::take_view(&owned), 11);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["take_view"], &[], None),
+        make_cpp17_adder(),
+        Some(Box::new(CppMatcher::new(
+            &["autocxx_make_string_view("],
+            &["std::make_unique<std::string>"],
+        ))),
+        None,
+    );
+}
+
+#[test]
+fn test_wstring() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+        inline uint32_t take_wstring(std::wstring a) {
+            return a.size();
+        }
+        inline uint32_t take_wstring_ref(const std::wstring& a) {
+            return a.size();
+        }
+        inline std::wstring give_wstring() {
+            return L\"h\\u00e9llo \\U0001F980\";
+        }
+        inline std::wstring echo_wstring(const std::wstring& a) {
+            return a;
+        }
+        inline std::wstring give_invalid_wstring() {
+            std::wstring s(L\"a\");
+            s.push_back(static_cast<wchar_t>(0xD800));
+            s.push_back(L'b');
+            return s;
+        }
+    "};
+    let rs = quote! {
+        // A character outside the BMP is a surrogate pair on Windows
+        // but a single code unit elsewhere.
+        let wide_crab_len = if cfg!(windows) { 2 } else { 1 };
+        assert_eq!(ffi::take_wstring("héllo"), 5);
+        assert_eq!(ffi::take_wstring_ref("🦀"), wide_crab_len);
+        assert_eq!(ffi::give_wstring(), "héllo 🦀");
+        assert_eq!(ffi::echo_wstring("naïve 🦀"), "naïve 🦀");
+        assert_eq!(ffi::give_invalid_wstring(), "a\u{FFFD}b");
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &[
+            "take_wstring",
+            "take_wstring_ref",
+            "give_wstring",
+            "echo_wstring",
+            "give_invalid_wstring",
+        ],
+        &[],
+    );
+}
+
+#[test]
+fn test_make_wstring() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t do_nothing() { return 0; }
+    "};
+    let rs = quote! {
+        use ffi::ToCppWString;
+        let original = "naïve 🦀";
+        assert_eq!(ffi::make_wstring(original).to_string_lossy(), original);
+        assert_eq!(ffi::make_wstring("").to_string_lossy(), "");
+        assert_eq!(String::from("wide").into_cpp_wstring().to_string_lossy(), "wide");
+        let ws: cxx::UniquePtr<ffi::CxxWString> = "héllo".into_cpp_wstring();
+        assert_eq!(ws.into_cpp_wstring().to_string_lossy(), "héllo");
+    };
+    run_test("", hdr, rs, &["do_nothing"], &[]);
+}
+
+#[test]
+fn test_cstr_returns() {
+    let hdr = indoc! {"
+        #include <string>
+        class Widget {
+        public:
+            Widget() : label_(\"wide\") {}
+            const char* name() const { return \"widget\"; }
+            const std::string& label() const { return label_; }
+            const char* raw_name() const { return \"raw\"; }
+        private:
+            std::string label_;
+        };
+        inline const char* describe(const Widget& w) { return w.name(); }
+    "};
+    let rs = quote! {
+        use std::ffi::CStr;
+        let w = ffi::Widget::new().within_unique_ptr();
+        let w = w.as_ref().unwrap();
+        let name: &CStr = w.name();
+        assert_eq!(name.to_str().unwrap(), "widget");
+        assert_eq!(w.label().to_bytes(), b"wide");
+        assert_eq!(ffi::describe(w), name);
+        let raw: *const std::os::raw::c_char = w.raw_name();
+        assert_eq!(unsafe { CStr::from_ptr(raw) }.to_str().unwrap(), "raw");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Widget", "describe"],
+            &[],
+            Some(quote! {
+                cstr_returns!("Widget::name")
+                cstr_returns!("Widget::label")
+                cstr_returns!("describe")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_nullable_returns() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        class Node {
+        public:
+            Node(uint32_t id, const Node* parent) : id_(id), parent_(parent) {}
+            uint32_t get_id() const { return id_; }
+            const Node* parent() const { return parent_; }
+            const Node* raw_parent() const { return parent_; }
+        private:
+            uint32_t id_;
+            const Node* parent_;
+        };
+        inline const Node* parent_of(const Node& n) { return n.parent(); }
+    "};
+    let rs = quote! {
+        let root = unsafe { ffi::Node::new(1, std::ptr::null()) }.within_unique_ptr();
+        let root = root.as_ref().unwrap();
+        let child = unsafe { ffi::Node::new(2, root) }.within_unique_ptr();
+        let child = child.as_ref().unwrap();
+        assert!(root.parent().is_none());
+        let parent: Option<&ffi::Node> = child.parent();
+        assert_eq!(parent.unwrap().get_id(), 1);
+        assert_eq!(ffi::parent_of(child).unwrap().get_id(), 1);
+        assert!(ffi::parent_of(root).is_none());
+        let raw: *const ffi::Node = child.raw_parent();
+        assert_eq!(unsafe { raw.as_ref() }.unwrap().get_id(), 1);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Node", "parent_of"],
+            &[],
+            Some(quote! {
+                nullable_returns!("Node::parent")
+                nullable_returns!("parent_of")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_nullable_unique_ptr_returns() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        #include <string>
+        class Plugin {
+        public:
+            Plugin(uint32_t version) : version_(version) {}
+            uint32_t version() const { return version_; }
+        private:
+            uint32_t version_;
+        };
+        class PluginRegistry {
+        public:
+            std::unique_ptr<Plugin> create(const std::string& name) const {
+                if (name == \"gzip\") {
+                    return std::make_unique<Plugin>(2);
+                }
+                return nullptr;
+            }
+        };
+        inline std::unique_ptr<Plugin> create_plugin(const std::string& name) {
+            return PluginRegistry().create(name);
+        }
+        inline std::unique_ptr<Plugin> make_plugin() {
+            return std::make_unique<Plugin>(1);
+        }
+    "};
+    let rs = quote! {
+        let registry = ffi::PluginRegistry::new().within_unique_ptr();
+        let plugin: Option<cxx::UniquePtr<ffi::Plugin>> = registry.create("gzip");
+        assert_eq!(plugin.unwrap().version(), 2);
+        assert!(registry.create("zstd").is_none());
+        assert_eq!(ffi::create_plugin("gzip").unwrap().version(), 2);
+        assert!(ffi::create_plugin("zstd").is_none());
+        let plugin: cxx::UniquePtr<ffi::Plugin> = ffi::make_plugin();
+        assert_eq!(plugin.version(), 1);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["PluginRegistry", "create_plugin", "make_plugin"],
+            &[],
+            Some(quote! {
+                nullable_returns!("PluginRegistry::create")
+                nullable_returns!("create_plugin")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_cstr_string_returns() {
+    let hdr = indoc! {"
+        inline const char* version() { return \"1.2.3\"; }
+        inline const char* missing() { return nullptr; }
+        inline char* scratch() {
+            static char buffer[] = \"scratch\";
+            return buffer;
+        }
+        inline const char* not_utf8() { return \"\\xff\"; }
+        class Build {
+        public:
+            const char* tag() const { return \"release\"; }
+        };
+    "};
+    let rs = quote! {
+        let version: String = ffi::version().unwrap();
+        assert_eq!(version, "1.2.3");
+        assert_eq!(ffi::missing().unwrap(), "");
+        assert_eq!(ffi::scratch().unwrap(), "scratch");
+        assert!(ffi::not_utf8().is_err());
+        let build = ffi::Build::new().within_unique_ptr();
+        assert_eq!(build.tag().unwrap(), "release");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["version", "missing", "scratch", "not_utf8", "Build"],
+            &[],
+            Some(quote! {
+                cstr_string_returns!("version")
+                cstr_string_returns!("missing")
+                cstr_string_returns!("scratch")
+                cstr_string_returns!("not_utf8")
+                cstr_string_returns!("Build::tag")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_str_params() {
+    let hdr = indoc! {"
+        #include <cstddef>
+        #include <cstring>
+        inline size_t measure(const char* text) { return std::strlen(text); }
+        inline bool starts_with(const char* text, const char* prefix) {
+            return std::strncmp(text, prefix, std::strlen(prefix)) == 0;
+        }
+        class Ruler {
+        public:
+            size_t measure_twice(const char* text) const { return 2 * std::strlen(text); }
+        };
+    "};
+    let rs = quote! {
+        let length: usize = ffi::measure("hello");
+        assert_eq!(length, 5);
+        assert_eq!(ffi::measure(""), 0);
+        // Longer than any small string buffer.
+        assert_eq!(ffi::measure(&"x".repeat(1000)), 1000);
+        assert_eq!(ffi::measure("naïve"), 6);
+        // C++ only sees as far as a null character.
+        assert_eq!(ffi::measure("ab\0cd"), 2);
+        assert!(ffi::starts_with("autocxx", "auto"));
+        assert!(!ffi::starts_with("autocxx", "cxx"));
+        let ruler = ffi::Ruler::new().within_unique_ptr();
+        assert_eq!(ruler.measure_twice("abc"), 6);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["measure", "starts_with", "Ruler"],
+            &[],
+            Some(quote! {
+                str_params!("measure")
+                str_params!("starts_with")
+                str_params!("Ruler::measure_twice")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_cstr_params() {
+    let hdr = indoc! {"
+        #include <cstddef>
+        #include <cstring>
+        inline size_t measure(const char* text) { return std::strlen(text); }
+        class Ruler {
+        public:
+            size_t measure_twice(const char* text) const { return 2 * std::strlen(text); }
+        };
+    "};
+    let rs = quote! {
+        use std::ffi::{CStr, CString};
+        let hello = CStr::from_bytes_with_nul(b"hello\0").unwrap();
+        let length: usize = ffi::measure(hello);
+        assert_eq!(length, 5);
+        assert_eq!(ffi::measure(&CString::default()), 0);
+        let long = CString::new("x".repeat(1000)).unwrap();
+        assert_eq!(ffi::measure(&long), 1000);
+        let ruler = ffi::Ruler::new().within_unique_ptr();
+        assert_eq!(ruler.measure_twice(hello), 10);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["measure", "Ruler"],
+            &[],
+            Some(quote! {
+                cstr_params!("measure")
+                cstr_params!("Ruler::measure_twice")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_u16string() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+        inline uint32_t take_u16string(std::u16string a) {
+            return a.size();
+        }
+        inline uint32_t take_u16string_ref(const std::u16string& a) {
+            return a.size();
+        }
+        inline std::u16string give_u16string() {
+            return u\"h\\u00e9llo \\U0001F980\";
+        }
+        inline std::u16string echo_u16string(const std::u16string& a) {
+            return a;
+        }
+        inline std::u16string give_invalid_u16string() {
+            std::u16string s(u\"a\");
+            s.push_back(static_cast<char16_t>(0xD800));
+            s.push_back(u'b');
+            return s;
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::take_u16string("héllo"), 5);
+        // A character outside the BMP is a surrogate pair.
+        assert_eq!(ffi::take_u16string_ref("🦀"), 2);
+        assert_eq!(ffi::give_u16string(), "héllo 🦀");
+        assert_eq!(ffi::echo_u16string("naïve 🦀"), "naïve 🦀");
+        assert_eq!(ffi::give_invalid_u16string(), "a\u{FFFD}b");
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &[
+            "take_u16string",
+            "take_u16string_ref",
+            "give_u16string",
+            "echo_u16string",
+            "give_invalid_u16string",
+        ],
+        &[],
+    );
+}
+
+#[test]
+fn test_u16string_checked() {
+    let hdr = indoc! {"
+        #include <string>
+        inline std::u16string give_u16string() {
+            return u\"\\U0001F980\";
+        }
+        inline std::u16string give_invalid_u16string() {
+            std::u16string s(u\"a\");
+            s.push_back(static_cast<char16_t>(0xDC00));
+            return s;
+        }
+    "};
+    let rs = quote! {
+        let valid: Result<String, std::string::FromUtf16Error> = ffi::give_u16string();
+        assert_eq!(valid.unwrap(), "🦀");
+        assert!(ffi::give_invalid_u16string().is_err());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["give_u16string", "give_invalid_u16string"],
+            &[],
+            Some(quote! { u16string_returns!(checked) }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_vector_reference_accessor() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        #include <vector>
+        class Inventory {
+        public:
+            Inventory() : counts_{3, 1, 4}, names_{\"bolt\", \"nut\"} {}
+            const std::vector<int32_t>& counts() const { return counts_; }
+            const std::vector<std::string>& names() const { return names_; }
+            const std::vector<int32_t>& counts_for(const std::string& name) const {
+                return counts_;
+            }
+            const int32_t* counts_data() const { return counts_.data(); }
+        private:
+            std::vector<int32_t> counts_;
+            std::vector<std::string> names_;
+        };
+    "};
+    let rs = quote! {
+        let inventory = ffi::Inventory::new().within_unique_ptr();
+        let inventory = inventory.as_ref().unwrap();
+        let counts: &cxx::CxxVector<i32> = inventory.counts();
+        assert_eq!(counts.iter().copied().collect::<Vec<_>>(), vec![3, 1, 4]);
+        // The very vector inside the object, rather than a copy.
+        assert_eq!(counts.as_slice().as_ptr(), inventory.counts_data());
+        assert!(std::ptr::eq(counts, inventory.counts()));
+        let names: &cxx::CxxVector<cxx::CxxString> = inventory.names();
+        assert_eq!(names.get(1).unwrap().to_str().unwrap(), "nut");
+        cxx::let_cxx_string!(bolt = "bolt");
+        assert!(std::ptr::eq(inventory.counts_for(&bolt), counts));
+    };
+    run_test("", hdr, rs, &["Inventory"], &[]);
+}
+
+#[test]
+fn test_vector_slices() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <vector>
+        struct Point {
+            int32_t x;
+            int32_t y;
+        };
+        class Series {
+        public:
+            Series() : values_{1, 2, 3, 4}, points_{{1, 2}, {3, 4}} {}
+            const std::vector<int32_t>& values() const { return values_; }
+            const std::vector<Point>& points() const { return points_; }
+        private:
+            std::vector<int32_t> values_;
+            std::vector<Point> points_;
+        };
+    "};
+    let rs = quote! {
+        let series = ffi::Series::new().within_unique_ptr();
+        let series = series.as_ref().unwrap();
+        let values: &[i32] = series.values();
+        assert_eq!(values.iter().sum::<i32>(), 10);
+        let points: &[ffi::Point] = series.points();
+        assert_eq!(points.iter().map(|p| p.x + p.y).sum::<i32>(), 10);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Series"],
+            &["Point"],
+            Some(quote! {
+                vector_slices!("Series::values")
+                vector_slices!("Series::points")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_vector_slices_by_value_fails() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <vector>
+        class Series {
+        public:
+            Series() : values_{1, 2, 3, 4} {}
+            std::vector<int32_t> copy_values() const { return values_; }
+        private:
+            std::vector<int32_t> values_;
+        };
+    "};
+    let rs = quote! {};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Series"],
+            &[],
+            Some(quote! {
+                vector_slices!("Series::copy_values")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_string_view() {
+    let hdr = indoc! {"
+        #include <string_view>
+        #include <cstdint>
+        inline uint32_t take_string_view(std::string_view a) {
+            return a.size();
+        }
+        inline std::string_view trim(std::string_view a) {
+            auto start = a.find_first_not_of(' ');
+            if (start == std::string_view::npos) {
+                return a.substr(a.size());
+            }
+            return a.substr(start, a.find_last_not_of(' ') - start + 1);
+        }
+        class Name {
+        public:
+            Name() : name(\"Fred\") {}
+            std::string_view get() const { return name; }
+            // Half of the two-byte encoding of \"é\".
+            std::string_view truncated() const { return std::string_view(\"caf\\xc3\", 4); }
+        private:
+            std::string_view name;
+        };
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::take_string_view("héllo"), 6);
+        let owned = String::from("  spaced out  ");
+        let trimmed = ffi::trim(&owned).unwrap();
+        assert_eq!(trimmed, "spaced out");
+        // The result points into the original string.
+        assert_eq!(trimmed.as_ptr(), owned[2..].as_ptr());
+        assert_eq!(ffi::trim("   ").unwrap(), "");
+        let name = ffi::Name::new().within_unique_ptr();
+        assert_eq!(name.get().unwrap(), "Fred");
+        assert!(name.truncated().is_err());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["take_string_view", "trim", "Name"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_string_view_param_does_not_allocate() {
+    let hdr = indoc! {"
+        #include <string_view>
+        #include <cstddef>
+        inline size_t measure(std::string_view a) {
+            return a.size();
+        }
+        inline size_t measure_ref(const std::string_view& a) {
+            return a.size();
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::measure("héllo"), 6);
+        assert_eq!(ffi::measure(""), 0);
+        let owned = String::from("some text");
+        assert_eq!(ffi::measure_ref(&owned), 9);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["measure", "measure_ref"], &[], None),
+        make_cpp17_adder(),
+        Some(Box::new(CppMatcher::new(
+            &["#include <string_view>", "autocxx_make_string_view("],
+            &["std::make_unique<std::string>"],
+        ))),
+        None,
+    );
+}
+
+#[test]
+fn test_string_view_copied_if_it_may_dangle() {
+    let hdr = indoc! {"
+        #include <string_view>
+        inline std::string_view greeting() {
+            return \"hello\";
+        }
+        inline std::string_view longer(std::string_view a, std::string_view b) {
+            return a.size() >= b.size() ? a : b;
+        }
+        inline std::string_view truncated() {
+            return std::string_view(\"caf\\xc3\", 4);
+        }
+    "};
+    let rs = quote! {
+        let greeting: String = ffi::greeting();
+        assert_eq!(greeting, "hello");
+        let a = String::from("short");
+        let longest: String = ffi::longer(&a, "much longer");
+        assert_eq!(longest, "much longer");
+        assert_eq!(ffi::truncated(), "caf\u{FFFD}");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["greeting", "longer", "truncated"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_optional_string_view_param() {
+    let hdr = indoc! {"
+        #include <string_view>
+        #include <optional>
+        #include <cstdint>
+        inline int32_t length_or_minus_one(std::optional<std::string_view> a) {
+            return a ? a->size() : -1;
+        }
+        inline std::string_view or_default(const std::optional<std::string_view>& a) {
+            return a.value_or(\"\");
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::length_or_minus_one(Some("héllo")), 6);
+        assert_eq!(ffi::length_or_minus_one(Some("")), 0);
+        assert_eq!(ffi::length_or_minus_one(None), -1);
+        let owned = String::from("borrowed");
+        let viewed = ffi::or_default(Some(&owned)).unwrap();
+        // The result points into the original string.
+        assert_eq!(viewed.as_ptr(), owned.as_ptr());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["length_or_minus_one", "or_default"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_typedef_to_up_in_fn_call() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <memory>
+        typedef std::unique_ptr<std::string> my_string;
+        inline uint32_t take_str(my_string a) {
+            return a->size();
+        }
+    "};
+    let rs = quote! {
+        use ffi::ToCppString;
+        assert_eq!(ffi::take_str("hello".into_cpp()), 5);
+    };
+    run_test("", hdr, rs, &["take_str"], &[]);
+}
+
+#[test]
+fn test_typedef_in_pod_struct() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+        typedef uint32_t my_int;
+        struct A {
+            my_int a;
+        };
+        inline uint32_t take_a(A a) {
+            return a.a;
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::A {
+            a: 32,
+        };
+        assert_eq!(ffi::take_a(a), 32);
+    };
+    run_test("", hdr, rs, &["take_a"], &["A"]);
+}
+
+#[test]
+fn test_cint_in_pod_struct() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+        struct A {
+            int a;
+        };
+        inline uint32_t take_a(A a) {
+            return a.a;
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::A {
+            a: 32,
+        };
+        assert_eq!(ffi::take_a(a), 32);
+    };
+    run_test("", hdr, rs, &["take_a"], &["A"]);
+}
+
+#[test]
+fn test_string_in_struct() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <memory>
+        struct A {
+            std::string a;
+        };
+        inline A make_a(std::string b) {
+            A bob;
+            bob.a = b;
+            return bob;
+        }
+        inline uint32_t take_a(A a) {
+            return a.a.size();
+        }
+    "};
+    let rs = quote! {
+        use ffi::ToCppString;
+        assert_eq!(ffi::take_a(as_new(ffi::make_a("hello".into_cpp()))), 5);
+    };
+    run_test("", hdr, rs, &["make_a", "take_a"], &[]);
+}
+
+#[test]
+#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
+fn test_up_in_struct() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <memory>
+        struct A {
+            std::unique_ptr<std::string> a;
+        };
+        inline A make_a(std::string b) {
+            A bob;
+            bob.a = std::make_unique<std::string>(b);
+            return bob;
+        }
+        inline uint32_t take_a(A a) {
+            return a.a->size();
+        }
+    "};
+    let rs = quote! {
+        use ffi::ToCppString;
+        assert_eq!(ffi::take_a(as_new(ffi::make_a("hello".into_cpp()))), 5);
+    };
+    run_test("", hdr, rs, &["make_a", "take_a"], &[]);
+}
+
+#[test]
+fn test_typedef_to_std_in_struct() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+        typedef std::string my_string;
+        struct A {
+            my_string a;
+        };
+        inline A make_a(std::string b) {
+            A bob;
+            bob.a = b;
+            return bob;
+        }
+        inline uint32_t take_a(A a) {
+            return a.a.size();
+        }
+    "};
+    let rs = quote! {
+        use ffi::ToCppString;
+        assert_eq!(ffi::take_a(as_new(ffi::make_a("hello".into_cpp()))), 5);
+    };
+    run_test("", hdr, rs, &["make_a", "take_a"], &[]);
+}
+
+#[test]
+#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
+fn test_typedef_to_up_in_struct() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <memory>
+        typedef std::unique_ptr<std::string> my_string;
+        struct A {
+            my_string a;
+        };
+        inline A make_a(std::string b) {
+            A bob;
+            bob.a = std::make_unique<std::string>(b);
+            return bob;
+        }
+        inline uint32_t take_a(A a) {
+            return a.a->size();
+        }
+    "};
+    let rs = quote! {
+        use ffi::ToCppString;
+        assert_eq!(ffi::take_a(as_new(ffi::make_a("hello".into_cpp()))), 5);
+    };
+    run_test("", hdr, rs, &["make_a", "take_a"], &[]);
+}
+
+#[test]
+fn test_float() {
+    let hdr = indoc! {"
+    inline float daft(float a) { return a; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::daft(34.0f32), 34.0f32);
+    };
+    run_test("", hdr, rs, &["daft"], &[]);
+}
+
+#[test]
+fn test_double() {
+    let hdr = indoc! {"
+    inline double daft(double a) { return a; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::daft(34.0f64), 34.0f64);
+    };
+    run_test("", hdr, rs, &["daft"], &[]);
+}
+
+#[test]
+fn test_issues_217_222() {
+    let hdr = indoc! {"
+    #include <string>
+    #include <cstdint>
+    #include <cstddef>
+
+    template <typename STRING_TYPE> class BasicStringPiece {
+        public:
+         typedef size_t size_type;
+         typedef typename STRING_TYPE::traits_type traits_type;
+         typedef typename STRING_TYPE::value_type value_type;
+         typedef const value_type* pointer;
+         typedef const value_type& reference;
+         typedef const value_type& const_reference;
+         typedef ptrdiff_t difference_type;
+         typedef const value_type* const_iterator;
+         typedef std::reverse_iterator<const_iterator> const_reverse_iterator;
+         static const size_type npos;
+    };
+
+    template<typename CHAR>
+    class Replacements {
+     public:
+      Replacements() {
+      }
+      void SetScheme(const CHAR*) {
+      }
+      uint16_t a;
+    };
+
+    struct Component {
+        uint16_t a;
+    };
+
+    template <typename STR>
+    class StringPieceReplacements : public Replacements<typename STR::value_type> {
+        private:
+         using CharT = typename STR::value_type;
+         using StringPieceT = BasicStringPiece<STR>;
+         using ParentT = Replacements<CharT>;
+         using SetterFun = void (ParentT::*)(const CharT*, const Component&);
+         void SetImpl(SetterFun, StringPieceT) {
+        }
+        public:
+        void SetSchemeStr(const CharT* str) { SetImpl(&ParentT::SetScheme, str); }
+    };
+
+    class GURL {
+        public:
+        typedef StringPieceReplacements<std::string> UrlReplacements;
+        GURL() {}
+        GURL ReplaceComponents(const Replacements<char>&) const {
+            return GURL();
+        }
+        uint16_t a;
+    };
+    "};
+    let rs = quote! {
+        ffi::GURL::new().within_unique_ptr();
+    };
+    // The block! directives here are to avoid running into
+    // https://github.com/rust-lang/rust-bindgen/pull/1975
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! { generate!("GURL") block!("StringPiece") block!("Replacements") },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+#[ignore] // https://github.com/rust-lang/rust-bindgen/pull/1975, https://github.com/google/autocxx/issues/106
+fn test_dependent_qualified_type() {
+    let hdr = indoc! {"
+    #include <stddef.h>
+    struct MyString {
+        typedef char value_type;
+    };
+    template<typename T> struct MyStringView {
+        typedef typename T::value_type view_value_type;
+        const view_value_type* start;
+        size_t length;
+    };
+    const char* HELLO = \"hello\";
+    inline MyStringView<MyString> make_string_view() {
+        MyStringView<MyString> r;
+        r.start = HELLO;
+        r.length = 2;
+        return r;
+    }
+    inline size_t take_string_view(const MyStringView<MyString>& bit) {
+        return bit.length;
+    }
+    "};
+    let rs = quote! {
+        let sv = ffi::make_string_view();
+        assert_eq!(ffi::take_string_view(sv.as_ref().unwrap()), 2);
+    };
+    run_test("", hdr, rs, &["take_string_view", "make_string_view"], &[]);
+}
+
+#[test]
+fn test_simple_dependent_qualified_type() {
+    // bindgen seems to cope with this case just fine
+    let hdr = indoc! {"
+    #include <stddef.h>
+    #include <stdint.h>
+    struct MyString {
+        typedef char value_type;
+    };
+    template<typename T> struct MyStringView {
+        typedef typename T::value_type view_value_type;
+        const view_value_type* start;
+        size_t length;
+    };
+    typedef MyStringView<MyString>::view_value_type MyChar;
+    inline MyChar make_char() {
+        return 'a';
+    }
+    inline uint32_t take_char(MyChar c) {
+        return static_cast<unsigned char>(c);
+    }
+    "};
+    let rs = quote! {
+        let c = ffi::make_char();
+        assert_eq!(ffi::take_char(c), 97);
+    };
+    run_test("", hdr, rs, &["make_char", "take_char"], &[]);
+}
+
+#[test]
+fn test_ignore_dependent_qualified_type() {
+    let hdr = indoc! {"
+    #include <stddef.h>
+    struct MyString {
+        typedef char value_type;
+    };
+    template<typename T> struct MyStringView {
+        typedef typename T::value_type view_value_type;
+        const view_value_type* start;
+        size_t length;
+    };
+    MyStringView<MyString> make_string_view();
+    struct B {
+        B() {}
+        inline size_t take_string_view(const MyStringView<MyString> bit) {
+            return bit.length;
+        }
+    };
+    "};
+    let cpp = indoc! {"
+    const char* HELLO = \"hello\";
+    MyStringView<MyString> make_string_view() {
+        MyStringView<MyString> r;
+        r.start = HELLO;
+        r.length = 2;
+        return r;
+    }
+    "};
+    let rs = quote! {
+        ffi::B::new().within_unique_ptr();
+    };
+    run_test(cpp, hdr, rs, &["B"], &[]);
+}
+
+#[test]
+fn test_ignore_dependent_qualified_type_reference() {
+    let hdr = indoc! {"
+    #include <stddef.h>
+    struct MyString {
+        typedef char value_type;
+    };
+    template<typename T> struct MyStringView {
+        typedef typename T::value_type view_value_type;
+        const view_value_type* start;
+        size_t length;
+    };
+    MyStringView<MyString> make_string_view();
+    struct B {
+        B() {}
+        inline size_t take_string_view(const MyStringView<MyString>& bit) {
+            return bit.length;
+        }
+    };
+    "};
+    let cpp = indoc! {"
+    const char* HELLO = \"hello\";
+    MyStringView<MyString> make_string_view() {
+        MyStringView<MyString> r;
+        r.start = HELLO;
+        r.length = 2;
+        return r;
+    }
+    "};
+    let rs = quote! {
+        ffi::B::new().within_unique_ptr();
+    };
+    run_test(cpp, hdr, rs, &["B"], &[]);
+}
+
+#[test]
+fn test_specialization() {
+    let hdr = indoc! {"
+    #include <stddef.h>
+    #include <stdint.h>
+    #include <string>
+    #include <type_traits>
+
+    template <typename T, bool = std::is_trivially_destructible<T>::value>
+    struct OptionalStorageBase {
+        T value_;
+    };
+
+    template <typename T,
+    bool = std::is_trivially_copy_constructible<T>::value,
+    bool = std::is_trivially_move_constructible<T>::value>
+    struct OptionalStorage : OptionalStorageBase<T> {};
+
+    template <typename T>
+    struct OptionalStorage<T,
+                       true /* trivially copy constructible */,
+                       false /* trivially move constructible */>
+    : OptionalStorageBase<T> {
+    };
+
+    template <typename T>
+    struct OptionalStorage<T,
+                       false /* trivially copy constructible */,
+                       true /* trivially move constructible */>
+    : OptionalStorageBase<T> {
+    };
+
+    template <typename T>
+    struct OptionalStorage<T,
+                       true /* trivially copy constructible */,
+                       true /* trivially move constructible */>
+    : OptionalStorageBase<T> {
+    };
+
+    template <typename T>
+    class OptionalBase {
+    private:
+        OptionalStorage<T> storage_;
+    };
+
+    template <typename T>
+    class Optional : public OptionalBase<T> {
+
+    };
+
+    struct B {
+        B() {}
+        void take_optional(Optional<std::string>) {}
+        uint32_t a;
+    };
+    "};
+    let rs = quote! {
+        ffi::B::new().within_unique_ptr();
+    };
+    run_test("", hdr, rs, &["B"], &[]);
+}
+
+#[test]
+fn test_private_constructor_make_unique() {
+    let hdr = indoc! {"
+    #include <stdint.h>
+    struct A {
+    private:
+        A() {};
+    public:
+        uint32_t a;
+    };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["A"], &[]);
+}
+
+#[test]
+#[ignore] // https://github.com/google/autocxx/issues/266
+fn test_take_array() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    uint32_t take_array(const uint32_t a[4]) {
+        return a[0] + a[2];
+    }
+    "};
+    let rs = quote! {
+        let c: [u32; 4usize] = [ 10, 20, 30, 40 ];
+        let c = c as *const [_];
+        assert_eq!(ffi::take_array(&c), 40);
+    };
+    run_test("", hdr, rs, &["take_array"], &[]);
+}
+
+#[test]
+fn test_take_array_in_struct() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    struct data {
+        char a[4];
+    };
+    uint32_t take_array(const data a) {
+        return a.a[0] + a.a[2];
+    }
+    "};
+    let rs = quote! {
+        let c = ffi::data { a: [ 10, 20, 30, 40 ] };
+        assert_eq!(ffi::take_array(c), 40);
+    };
+    run_test("", hdr, rs, &["take_array"], &["data"]);
+}
+
+#[test]
+fn test_take_struct_built_array_in_function() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    struct data {
+        char a[4];
+    };
+    uint32_t take_array(char a[4]) {
+        return a[0] + a[2];
+    }
+    "};
+    let rs = quote! {
+        let mut c = ffi::data { a: [ 10, 20, 30, 40 ] };
+        unsafe {
+            assert_eq!(ffi::take_array(c.a.as_mut_ptr()), 40);
+        }
+    };
+    run_test("", hdr, rs, &["take_array"], &["data"]);
+}
+
+#[test]
+fn test_take_array_in_function() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    uint32_t take_array(char a[4]) {
+        return a[0] + a[2];
+    }
+    "};
+    let rs = quote! {
+        let mut a: [i8; 4] = [ 10, 20, 30, 40 ];
+        unsafe {
+            assert_eq!(ffi::take_array(a.as_mut_ptr()), 40);
+        }
+    };
+    run_test("", hdr, rs, &["take_array"], &[]);
+}
+
+#[test]
+fn test_byte_array_references() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    struct Header {
+        uint8_t magic[4];
+        uint32_t version;
+    };
+    inline uint32_t checksum(const uint8_t (&bytes)[4]) {
+        return bytes[0] | (bytes[1] << 8) | (bytes[2] << 16) | (uint32_t(bytes[3]) << 24);
+    }
+    inline void fill(uint8_t (&bytes)[4], uint8_t start) {
+        for (uint8_t i = 0; i < 4; i++) {
+            bytes[i] = start + i;
+        }
+    }
+    inline uint8_t first_magic(const Header& h) {
+        return h.magic[0];
+    }
+    inline void set_magic(Header& h) {
+        h.magic[0] = 0x7f;
+        h.magic[1] = 'E';
+        h.magic[2] = 'L';
+        h.magic[3] = 'F';
+    }
+    "};
+    let rs = quote! {
+        let bytes: [u8; 4] = [0x01, 0x02, 0x03, 0x04];
+        // Byte order is preserved, so this matches a little-endian read
+        // whatever the platform's endianness.
+        assert_eq!(ffi::checksum(&bytes), 0x04030201);
+        assert_eq!(ffi::checksum(&bytes), u32::from_le_bytes(bytes));
+        let mut out = [0u8; 4];
+        ffi::fill(&mut out, 10);
+        assert_eq!(out, [10, 11, 12, 13]);
+        let mut header = Box::pin(ffi::Header { magic: [1, 2, 3, 4], version: 1 });
+        assert_eq!(ffi::first_magic(&header), 1);
+        ffi::set_magic(header.as_mut());
+        assert_eq!(&header.magic, b"\x7fELF");
+        assert_eq!(ffi::checksum(&header.magic), u32::from_le_bytes(header.magic));
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &["checksum", "fill", "first_magic", "set_magic"],
+        &["Header"],
+    );
+}
+
+#[test]
+fn test_union_ignored() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    union A {
+        uint32_t a;
+        float b;
+    };
+    struct B {
+        B() :a(1) {}
+        uint32_t take_union(A) const {
+            return 3;
+        }
+        uint32_t get_a() const { return 2; }
+        uint32_t a;
+    };
+    "};
+    let rs = quote! {
+        let b = ffi::B::new().within_unique_ptr();
+        assert_eq!(b.get_a(), 2);
+    };
+    run_test("", hdr, rs, &["B"], &[]);
+}
+
+#[test]
+fn test_union_nonpod() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    union A {
+        uint32_t a;
+        float b;
+    };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["A"], &[]);
+}
+
+#[test]
+fn test_union_pod() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    union A {
+        uint32_t a;
+        float b;
+    };
+    "};
+    let rs = quote! {};
+    run_test_expect_fail("", hdr, rs, &[], &["A"]);
+}
+
+#[test]
+fn test_type_aliased_anonymous_union_ignored() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    namespace test {
+        typedef union {
+        int a;
+        } Union;
         };
-        inline uint32_t take_a(A a) {
-            return a.a;
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["test::Union"], &[]);
+}
+
+#[test]
+fn test_type_aliased_anonymous_struct_ignored() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    namespace test {
+        typedef struct {
+            int a;
+        } Struct;
+    };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["test::Struct"], &[]);
+}
+
+#[test]
+fn test_type_aliased_anonymous_nested_struct_ignored() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    namespace test {
+        struct Outer {
+            typedef struct {
+                int a;
+            } Struct;
+            int b;
+        };
+    };
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["test::Outer_Struct"], &[]);
+}
+
+#[ignore] // https://github.com/google/autocxx/issues/1251
+#[test]
+fn test_double_underscores_ignored() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    struct __FOO {
+        uint32_t a;
+    };
+    struct B {
+        B() :a(1) {}
+        uint32_t take_foo(__FOO) const {
+            return 3;
+        }
+        void do__something() const { }
+        uint32_t get_a() const { return 2; }
+        uint32_t a;
+    };
+
+    struct __default { __default() = default; };
+    struct __destructor { ~__destructor() = default; };
+    struct __copy { __copy(const __copy&) = default; };
+    struct __copy_operator { __copy_operator &operator=(const __copy_operator&) = default; };
+    struct __move { __move(__move&&) = default; };
+    struct __move_operator { __move_operator &operator=(const __move_operator&) = default; };
+    "};
+    let rs = quote! {
+        let b = ffi::B::new().within_unique_ptr();
+        assert_eq!(b.get_a(), 2);
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &[
+            "B",
+            "__default",
+            "__destructor",
+            "__copy",
+            "__copy_operator",
+            "__move",
+            "__move_operator",
+        ],
+        &[],
+    );
+}
+
+// This test fails on Windows gnu but not on Windows msvc
+#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
+#[test]
+fn test_double_underscore_typedef_ignored() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    typedef int __int32_t;
+    typedef __int32_t __darwin_pid_t;
+    typedef __darwin_pid_t pid_t;
+    struct B {
+        B() :a(1) {}
+        uint32_t take_foo(pid_t) const {
+            return 3;
         }
+        uint32_t get_a() const { return 2; }
+        uint32_t a;
+    };
     "};
     let rs = quote! {
-        let a = ffi::A {
-            a: 32,
+        let b = ffi::B::new().within_unique_ptr();
+        assert_eq!(b.get_a(), 2);
+    };
+    run_test("", hdr, rs, &["B"], &[]);
+}
+
+#[test]
+fn test_double_underscores_fn_namespace() {
+    let hdr = indoc! {"
+    namespace __B {
+        inline void a() {}
+    };
+    "};
+    run_generate_all_test(hdr);
+}
+
+#[test]
+fn test_typedef_to_ptr_is_marked_unsafe() {
+    let hdr = indoc! {"
+    struct _xlocalefoo; /* forward reference */
+    typedef struct _xlocalefoo * locale_tfoo;
+    extern \"C\" {
+        locale_tfoo duplocalefoo(locale_tfoo);
+    }
+    "};
+    let rs = quote! {};
+    run_test("", hdr, rs, &["duplocalefoo"], &[]);
+}
+
+#[test]
+fn test_issue_264() {
+    let hdr = indoc! {"
+    namespace a {
+        typedef int b;
+        //inline namespace c {}
+        template <typename> class aa;
+        inline namespace c {
+        template <typename d, typename = d, typename = aa<d>> class e;
+        }
+        typedef e<char> f;
+        template <typename g, typename, template <typename> typename> struct h {
+          using i = g;
         };
-        assert_eq!(ffi::take_a(a), 32);
+        template <typename g, template <typename> class k> using j = h<g, void, k>;
+        template <typename g, template <typename> class k>
+        using m = typename j<g, k>::i;
+        template <typename> struct l { typedef b ab; };
+        template <typename p> class aa {
+        public:
+          typedef p n;
+        };
+        struct r {
+          template <typename p> using o = typename p::c;
+        };
+        template <typename ad> struct u : r {
+          typedef typename ad::n n;
+          using ae = m<n, o>;
+          template <typename af, typename> struct v { using i = typename l<f>::ab; };
+          using ab = typename v<ad, ae>::i;
+        };
+        } // namespace a
+        namespace q {
+        template <typename ad> struct w : a::u<ad> {};
+        } // namespace q
+        namespace a {
+        inline namespace c {
+        template <typename, typename, typename ad> class e {
+          typedef q::w<ad> s;
+        public:
+          typedef typename s::ab ab;
+        };
+        } // namespace c
+        } // namespace a
+        namespace ag {
+        namespace ah {
+        typedef a::f::ab t;
+        class ai {
+        public:
+          t aj;
+        };
+        class al;
+        namespace am {
+        class an {
+        public:
+          void ao(ai);
+        };
+        } // namespace am
+        class ap {
+        public:
+          al aq();
+        };
+        class ar {
+        public:
+          am::an as;
+        };
+        class al {
+        public:
+          ar at;
+        };
+        struct au {
+          ap av;
+        };
+        } // namespace ah
+        } // namespace ag
+        namespace operations_research {
+        class aw {
+        public:
+          ag::ah::au ax;
+        };
+        class Solver {
+        public:
+          aw ay;
+        };
+        } // namespace operations_research
+    "};
+    let rs = quote! {};
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["operations_research::Solver"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_unexpected_use() {
+    // https://github.com/google/autocxx/issues/303
+    let hdr = indoc! {"
+        typedef int a;
+        namespace b {
+        namespace c {
+        enum d : a;
+        }
+        } // namespace b
+        namespace {
+        using d = b::c::d;
+        }
+        namespace content {
+        class RenderFrameHost {
+        public:
+            RenderFrameHost() {}
+        d e;
+        };
+        } // namespace content
+        "};
+    let rs = quote! {
+        let _ = ffi::content::RenderFrameHost::new().within_unique_ptr();
     };
-    run_test("", hdr, rs, &["take_a"], &["A"]);
+    run_test("", hdr, rs, &["content::RenderFrameHost"], &[]);
 }
 
 #[test]
-fn test_cint_in_pod_struct() {
+fn test_get_pure_virtual() {
     let hdr = indoc! {"
-        #include <string>
         #include <cstdint>
-        struct A {
-            int a;
+        class A {
+        public:
+            virtual ~A() {}
+            virtual uint32_t get_val() const = 0;
         };
-        inline uint32_t take_a(A a) {
-            return a.a;
-        }
+        class B : public A {
+        public:
+            virtual uint32_t get_val() const { return 3; }
+        };
+        const B b;
+        inline const A* get_a() { return &b; };
     "};
     let rs = quote! {
-        let a = ffi::A {
-            a: 32,
-        };
-        assert_eq!(ffi::take_a(a), 32);
+        let a = ffi::get_a();
+        let a_ref = unsafe { a.as_ref() }.unwrap();
+        assert_eq!(a_ref.get_val(), 3);
     };
-    run_test("", hdr, rs, &["take_a"], &["A"]);
+    run_test("", hdr, rs, &["A", "get_a"], &[]);
 }
 
 #[test]
-fn test_string_in_struct() {
+fn test_abstract_class_no_make_unique() {
+    // We shouldn't generate a new().within_unique_ptr() for abstract classes.
+    // The test is successful if the bindings compile, i.e. if autocxx doesn't
+    // attempt to instantiate the class.
     let hdr = indoc! {"
-        #include <string>
-        #include <memory>
-        struct A {
-            std::string a;
+        class A {
+        public:
+            A() {}
+            virtual ~A() {}
+            virtual void foo() const = 0;
         };
-        inline A make_a(std::string b) {
-            A bob;
-            bob.a = b;
-            return bob;
-        }
-        inline uint32_t take_a(A a) {
-            return a.a.size();
-        }
     "};
-    let rs = quote! {
-        use ffi::ToCppString;
-        assert_eq!(ffi::take_a(as_new(ffi::make_a("hello".into_cpp()))), 5);
-    };
-    run_test("", hdr, rs, &["make_a", "take_a"], &[]);
+    let rs = quote! {};
+    run_test("", hdr, rs, &["A"], &[]);
 }
 
 #[test]
-#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
-fn test_up_in_struct() {
+fn test_derived_abstract_class_no_make_unique() {
     let hdr = indoc! {"
-        #include <string>
-        #include <memory>
-        struct A {
-            std::unique_ptr<std::string> a;
+        class A {
+        public:
+            A();
+            virtual ~A() {}
+            virtual void foo() const = 0;
+        };
+
+        class B : public A {
+        public:
+            B();
         };
-        inline A make_a(std::string b) {
-            A bob;
-            bob.a = std::make_unique<std::string>(b);
-            return bob;
-        }
-        inline uint32_t take_a(A a) {
-            return a.a->size();
-        }
     "};
-    let rs = quote! {
-        use ffi::ToCppString;
-        assert_eq!(ffi::take_a(as_new(ffi::make_a("hello".into_cpp()))), 5);
-    };
-    run_test("", hdr, rs, &["make_a", "take_a"], &[]);
+    let rs = quote! {};
+    run_test("", hdr, rs, &["A", "B"], &[]);
 }
 
 #[test]
-fn test_typedef_to_std_in_struct() {
+fn test_recursive_derived_abstract_class_no_make_unique() {
     let hdr = indoc! {"
-        #include <string>
-        #include <cstdint>
-        typedef std::string my_string;
-        struct A {
-            my_string a;
+        class A {
+        public:
+            A() {}
+            virtual ~A() {}
+            virtual void foo() const = 0;
+        };
+
+        class B : public A {
+        public:
+            B() {};
+        };
+
+        class C : public B {
+        public:
+            C() {};
         };
-        inline A make_a(std::string b) {
-            A bob;
-            bob.a = b;
-            return bob;
-        }
-        inline uint32_t take_a(A a) {
-            return a.a.size();
-        }
     "};
-    let rs = quote! {
-        use ffi::ToCppString;
-        assert_eq!(ffi::take_a(as_new(ffi::make_a("hello".into_cpp()))), 5);
-    };
-    run_test("", hdr, rs, &["make_a", "take_a"], &[]);
+    let rs = quote! {};
+    run_test("", hdr, rs, &["A", "B", "C"], &[]);
 }
 
 #[test]
-#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
-fn test_typedef_to_up_in_struct() {
+fn test_derived_abstract_class_with_no_allowlisting_no_make_unique() {
     let hdr = indoc! {"
-        #include <string>
-        #include <memory>
-        typedef std::unique_ptr<std::string> my_string;
-        struct A {
-            my_string a;
+        class A {
+        public:
+            A();
+            virtual ~A() {}
+            virtual void foo() const = 0;
+        };
+
+        class B : public A {
+        public:
+            B();
         };
-        inline A make_a(std::string b) {
-            A bob;
-            bob.a = std::make_unique<std::string>(b);
-            return bob;
-        }
-        inline uint32_t take_a(A a) {
-            return a.a->size();
-        }
     "};
-    let rs = quote! {
-        use ffi::ToCppString;
-        assert_eq!(ffi::take_a(as_new(ffi::make_a("hello".into_cpp()))), 5);
-    };
-    run_test("", hdr, rs, &["make_a", "take_a"], &[]);
+    let rs = quote! {};
+    run_test("", hdr, rs, &["B"], &[]);
 }
 
 #[test]
-fn test_float() {
+fn test_vector_of_pointers() {
+    // Just ensures the troublesome API is ignored
     let hdr = indoc! {"
-    inline float daft(float a) { return a; }
+        #include <vector>
+        namespace operations_research {
+        class a;
+        class Solver {
+        public:
+          struct b c(std::vector<a *>);
+        };
+        class a {};
+        } // namespace operations_research
     "};
-    let rs = quote! {
-        assert_eq!(ffi::daft(34.0f32), 34.0f32);
-    };
-    run_test("", hdr, rs, &["daft"], &[]);
+    let rs = quote! {};
+    run_test("", hdr, rs, &["operations_research::Solver"], &[]);
 }
 
 #[test]
-fn test_double() {
+fn test_return_vector_of_unique_ptrs() {
     let hdr = indoc! {"
-    inline double daft(double a) { return a; }
+        #include <cstdint>
+        #include <memory>
+        #include <vector>
+        class Widget {
+        public:
+            Widget(uint32_t id0) : id(id0) {}
+            uint32_t get_id() const { return id; }
+        private:
+            uint32_t id;
+        };
+        inline std::vector<std::unique_ptr<Widget>> make_widgets() {
+            std::vector<std::unique_ptr<Widget>> widgets;
+            widgets.push_back(std::make_unique<Widget>(1));
+            widgets.push_back(std::make_unique<Widget>(2));
+            widgets.push_back(std::make_unique<Widget>(3));
+            return widgets;
+        }
     "};
     let rs = quote! {
-        assert_eq!(ffi::daft(34.0f64), 34.0f64);
+        let mut widgets = ffi::make_widgets().within_unique_ptr();
+        assert_eq!(widgets.len(), 3);
+        assert!(!widgets.is_empty());
+        assert_eq!(widgets.get(1).unwrap().get_id(), 2);
+        let taken = widgets.pin_mut().take(1);
+        assert_eq!(taken.get_id(), 2);
+        // The vector no longer owns the element...
+        assert!(widgets.get(1).is_none());
+        assert!(widgets.pin_mut().take(1).is_null());
+        // ...but still owns the others.
+        assert_eq!(widgets.len(), 3);
+        assert_eq!(widgets.get(0).unwrap().get_id(), 1);
+        assert_eq!(widgets.get(2).unwrap().get_id(), 3);
+        assert!(widgets.get(3).is_none());
+        // The taken element outlives the vector.
+        drop(widgets);
+        assert_eq!(taken.get_id(), 2);
+    };
+    run_test("", hdr, rs, &["Widget", "make_widgets"], &[]);
+}
+
+#[test]
+fn test_string_vec() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <vector>
+        inline std::vector<std::string> list_files() {
+            return {\"a.txt\", \"b.txt\"};
+        }
+        inline std::vector<std::string> list_bad_files() {
+            return {\"ok\", \"bad\\xff\"};
+        }
+        inline std::vector<std::string> list_replaced_files() {
+            return {\"ok\", \"bad\\xff\"};
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::list_files().unwrap(), vec!["a.txt", "b.txt"]);
+        assert!(ffi::list_bad_files().is_err());
+        assert_eq!(ffi::list_replaced_files(), vec!["ok", "bad\u{fffd}"]);
     };
-    run_test("", hdr, rs, &["daft"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["list_files", "list_bad_files", "list_replaced_files"],
+            &[],
+            Some(quote! {
+                string_vec!("list_files")
+                string_vec!("list_bad_files", checked)
+                string_vec!("list_replaced_files", lossy)
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_issues_217_222() {
+fn test_map_snapshot() {
     let hdr = indoc! {"
-    #include <string>
-    #include <cstdint>
-    #include <cstddef>
-
-    template <typename STRING_TYPE> class BasicStringPiece {
-        public:
-         typedef size_t size_type;
-         typedef typename STRING_TYPE::traits_type traits_type;
-         typedef typename STRING_TYPE::value_type value_type;
-         typedef const value_type* pointer;
-         typedef const value_type& reference;
-         typedef const value_type& const_reference;
-         typedef ptrdiff_t difference_type;
-         typedef const value_type* const_iterator;
-         typedef std::reverse_iterator<const_iterator> const_reverse_iterator;
-         static const size_type npos;
-    };
-
-    template<typename CHAR>
-    class Replacements {
-     public:
-      Replacements() {
-      }
-      void SetScheme(const CHAR*) {
-      }
-      uint16_t a;
-    };
-
-    struct Component {
-        uint16_t a;
-    };
-
-    template <typename STR>
-    class StringPieceReplacements : public Replacements<typename STR::value_type> {
-        private:
-         using CharT = typename STR::value_type;
-         using StringPieceT = BasicStringPiece<STR>;
-         using ParentT = Replacements<CharT>;
-         using SetterFun = void (ParentT::*)(const CharT*, const Component&);
-         void SetImpl(SetterFun, StringPieceT) {
-        }
-        public:
-        void SetSchemeStr(const CharT* str) { SetImpl(&ParentT::SetScheme, str); }
-    };
-
-    class GURL {
+        #include <cstdint>
+        #include <map>
+        #include <string>
+        class Opaque {
         public:
-        typedef StringPieceReplacements<std::string> UrlReplacements;
-        GURL() {}
-        GURL ReplaceComponents(const Replacements<char>&) const {
-            return GURL();
+            Opaque() {}
+            ~Opaque() {}
+        };
+        inline std::map<std::string, uint32_t> get_limits() {
+            return {{\"files\", 10}, {\"threads\", 4}};
         }
-        uint16_t a;
-    };
+        inline std::map<std::string, std::string> get_names() {
+            return {{\"a\", \"alpha\"}, {\"b\", \"beta\"}};
+        }
+        inline std::map<std::string, Opaque> get_opaques() {
+            return std::map<std::string, Opaque>();
+        }
+        inline void take_opaques(const std::map<std::string, Opaque>&) {}
     "};
     let rs = quote! {
-        ffi::GURL::new().within_unique_ptr();
+        let limits: ::std::collections::HashMap<String, u32> = ffi::get_limits();
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits["files"], 10);
+        assert_eq!(limits["threads"], 4);
+        let names = ffi::get_names();
+        assert_eq!(names["a"], "alpha");
+        assert_eq!(names["b"], "beta");
+        // Opaque values can't be copied, so we get the map itself.
+        let opaques = ffi::get_opaques();
+        ffi::take_opaques(&opaques);
     };
-    // The block! directives here are to avoid running into
-    // https://github.com/rust-lang/rust-bindgen/pull/1975
     run_test_ex(
         "",
         hdr,
         rs,
-        quote! { generate!("GURL") block!("StringPiece") block!("Replacements") },
+        directives_from_lists(
+            &["get_limits", "get_names", "get_opaques", "take_opaques"],
+            &[],
+            Some(quote! {
+                map_snapshot!("get_limits")
+                map_snapshot!("get_names")
+                map_snapshot!("get_opaques")
+            }),
+        ),
         None,
         None,
         None,
@@ -4760,717 +8796,1346 @@ fn test_issues_217_222() {
 }
 
 #[test]
-#[ignore] // https://github.com/rust-lang/rust-bindgen/pull/1975, https://github.com/google/autocxx/issues/106
-fn test_dependent_qualified_type() {
+fn test_map_snapshot_not_listed() {
     let hdr = indoc! {"
-    #include <stddef.h>
-    struct MyString {
-        typedef char value_type;
-    };
-    template<typename T> struct MyStringView {
-        typedef typename T::value_type view_value_type;
-        const view_value_type* start;
-        size_t length;
-    };
-    const char* HELLO = \"hello\";
-    inline MyStringView<MyString> make_string_view() {
-        MyStringView<MyString> r;
-        r.start = HELLO;
-        r.length = 2;
-        return r;
-    }
-    inline size_t take_string_view(const MyStringView<MyString>& bit) {
-        return bit.length;
-    }
+        #include <cstdint>
+        #include <map>
+        #include <string>
+        inline std::map<std::string, uint32_t> get_limits() {
+            return {{\"files\", 10}};
+        }
     "};
+    // Without map_snapshot!, the map stays opaque, and nothing is generated
+    // to copy it out.
     let rs = quote! {
-        let sv = ffi::make_string_view();
-        assert_eq!(ffi::take_string_view(sv.as_ref().unwrap()), 2);
+        let _limits = ffi::get_limits();
     };
-    run_test("", hdr, rs, &["take_string_view", "make_string_view"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["get_limits"], &[], None),
+        None,
+        Some(Box::new(CppMatcher::new(&[], &["_snapshot("]))),
+        None,
+    );
 }
 
 #[test]
-fn test_simple_dependent_qualified_type() {
-    // bindgen seems to cope with this case just fine
+fn test_default_args() {
     let hdr = indoc! {"
-    #include <stddef.h>
-    #include <stdint.h>
-    struct MyString {
-        typedef char value_type;
-    };
-    template<typename T> struct MyStringView {
-        typedef typename T::value_type view_value_type;
-        const view_value_type* start;
-        size_t length;
-    };
-    typedef MyStringView<MyString>::view_value_type MyChar;
-    inline MyChar make_char() {
-        return 'a';
-    }
-    inline uint32_t take_char(MyChar c) {
-        return static_cast<unsigned char>(c);
-    }
+        #include <cstdint>
+        namespace defaults {
+            inline uint32_t compute_default() {
+                static uint32_t calls = 0;
+                return 100 + calls++;
+            }
+        }
+        namespace defaults {
+            inline uint32_t add(uint32_t x, uint32_t y = compute_default(), uint32_t z = 3) {
+                return x + y + z;
+            }
+        }
+        class Counter {
+        public:
+            Counter() : total(0) {}
+            uint32_t bump(uint32_t by = sizeof(uint64_t)) { total += by; return total; }
+        private:
+            uint32_t total;
+        };
     "};
     let rs = quote! {
-        let c = ffi::make_char();
-        assert_eq!(ffi::take_char(c), 97);
+        assert_eq!(ffi::defaults::add(1, 2, 3), 6);
+        assert_eq!(ffi::defaults::add1(1, 2), 6);
+        // The default is evaluated afresh for each call.
+        assert_eq!(ffi::defaults::add2(1), 104);
+        assert_eq!(ffi::defaults::add2(1), 105);
+        let mut counter = ffi::Counter::new().within_unique_ptr();
+        assert_eq!(counter.pin_mut().bump(1), 1);
+        assert_eq!(counter.pin_mut().bump1(), 9);
     };
-    run_test("", hdr, rs, &["make_char", "take_char"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["defaults::add", "Counter"],
+            &[],
+            Some(quote! {
+                default_args!("defaults::add", 2)
+                default_args!("Counter::bump", 1)
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_ignore_dependent_qualified_type() {
+fn test_default_args_with_params() {
     let hdr = indoc! {"
-    #include <stddef.h>
-    struct MyString {
-        typedef char value_type;
-    };
-    template<typename T> struct MyStringView {
-        typedef typename T::value_type view_value_type;
-        const view_value_type* start;
-        size_t length;
-    };
-    MyStringView<MyString> make_string_view();
-    struct B {
-        B() {}
-        inline size_t take_string_view(const MyStringView<MyString> bit) {
-            return bit.length;
+        #include <cstdint>
+        inline uint32_t scale(uint32_t x, uint32_t factor = 2, bool round_up = false) {
+            return x * factor + (round_up ? 1 : 0);
         }
-    };
-    "};
-    let cpp = indoc! {"
-    const char* HELLO = \"hello\";
-    MyStringView<MyString> make_string_view() {
-        MyStringView<MyString> r;
-        r.start = HELLO;
-        r.length = 2;
-        return r;
-    }
+        class Cache {
+        public:
+            Cache() : refreshes(0), forced(0) {}
+            void refresh(bool force = false) { refreshes++; if (force) { forced++; } }
+            uint32_t get_refreshes() const { return refreshes; }
+            uint32_t get_forced() const { return forced; }
+        private:
+            uint32_t refreshes;
+            uint32_t forced;
+        };
     "};
     let rs = quote! {
-        ffi::B::new().within_unique_ptr();
+        assert_eq!(ffi::scale(3), 6);
+        assert_eq!(ffi::scale_with_factor(3, 4), 12);
+        assert_eq!(ffi::scale_with_round_up(3, 4, true), 13);
+        let mut cache = ffi::Cache::new().within_unique_ptr();
+        cache.pin_mut().refresh();
+        cache.pin_mut().refresh_with_force(true);
+        assert_eq!(cache.get_refreshes(), 2);
+        assert_eq!(cache.get_forced(), 1);
     };
-    run_test(cpp, hdr, rs, &["B"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["scale"],
+            &["Cache"],
+            Some(quote! {
+                default_args!("scale", 2, with_params)
+                default_args!("Cache::refresh", 1, with_params)
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_ignore_dependent_qualified_type_reference() {
+fn test_default_args_constexpr() {
     let hdr = indoc! {"
-    #include <stddef.h>
-    struct MyString {
-        typedef char value_type;
-    };
-    template<typename T> struct MyStringView {
-        typedef typename T::value_type view_value_type;
-        const view_value_type* start;
-        size_t length;
-    };
-    MyStringView<MyString> make_string_view();
-    struct B {
-        B() {}
-        inline size_t take_string_view(const MyStringView<MyString>& bit) {
-            return bit.length;
+        #include <cstdint>
+        namespace canvas {
+            constexpr int32_t kOrigin = 7;
+            inline int32_t draw(int32_t x, int32_t y = kOrigin * 2) {
+                return x * 100 + y;
+            }
         }
-    };
-    "};
-    let cpp = indoc! {"
-    const char* HELLO = \"hello\";
-    MyStringView<MyString> make_string_view() {
-        MyStringView<MyString> r;
-        r.start = HELLO;
-        r.length = 2;
-        return r;
-    }
+        class Pen {
+        public:
+            static constexpr int32_t kWidth = 3;
+            int32_t stroke(int32_t length, int32_t width = kWidth) const {
+                return length * width;
+            }
+        };
     "};
     let rs = quote! {
-        ffi::B::new().within_unique_ptr();
+        assert_eq!(ffi::canvas::draw(5, 10), 510);
+        assert_eq!(ffi::canvas::draw1(5), 514);
+        let pen = ffi::Pen::new().within_unique_ptr();
+        assert_eq!(pen.stroke(2, 5), 10);
+        // `kWidth` is found in the scope of the class, as it would be for
+        // a call from C++.
+        assert_eq!(pen.stroke1(2), 6);
     };
-    run_test(cpp, hdr, rs, &["B"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["canvas::draw", "Pen"],
+            &[],
+            Some(quote! {
+                default_args!("canvas::draw", 1)
+                default_args!("Pen::stroke", 1)
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_specialization() {
+fn test_comparator() {
     let hdr = indoc! {"
-    #include <stddef.h>
-    #include <stdint.h>
-    #include <string>
-    #include <type_traits>
-
-    template <typename T, bool = std::is_trivially_destructible<T>::value>
-    struct OptionalStorageBase {
-        T value_;
-    };
-
-    template <typename T,
-    bool = std::is_trivially_copy_constructible<T>::value,
-    bool = std::is_trivially_move_constructible<T>::value>
-    struct OptionalStorage : OptionalStorageBase<T> {};
-
-    template <typename T>
-    struct OptionalStorage<T,
-                       true /* trivially copy constructible */,
-                       false /* trivially move constructible */>
-    : OptionalStorageBase<T> {
-    };
-
-    template <typename T>
-    struct OptionalStorage<T,
-                       false /* trivially copy constructible */,
-                       true /* trivially move constructible */>
-    : OptionalStorageBase<T> {
-    };
-
-    template <typename T>
-    struct OptionalStorage<T,
-                       true /* trivially copy constructible */,
-                       true /* trivially move constructible */>
-    : OptionalStorageBase<T> {
-    };
-
-    template <typename T>
-    class OptionalBase {
-    private:
-        OptionalStorage<T> storage_;
-    };
-
-    template <typename T>
-    class Optional : public OptionalBase<T> {
-
+        #include <algorithm>
+        #include <cstdint>
+        #include <functional>
+        #include <vector>
+        struct Point {
+            int32_t x;
+            int32_t y;
+        };
+        inline int32_t smallest(int32_t a, int32_t b, int32_t c,
+                                std::function<bool(const int32_t&, const int32_t&)> less) {
+            return std::min({a, b, c}, less);
+        }
+        inline int32_t sorted_xs(Point a, Point b, Point c,
+                                 std::function<bool(const Point&, const Point&)> less) {
+            std::vector<Point> points{a, b, c};
+            std::sort(points.begin(), points.end(), less);
+            return points[0].x * 100 + points[1].x * 10 + points[2].x;
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(unsafe { ffi::smallest(3, 1, 2, |a, b| a.cmp(b)) }, 1);
+        assert_eq!(unsafe { ffi::smallest(3, 1, 2, |a, b| b.cmp(a)) }, 3);
+        let mut comparisons = 0;
+        let p = |x, y| ffi::Point { x, y };
+        assert_eq!(
+            unsafe {
+                ffi::sorted_xs(p(1, 9), p(2, 7), p(3, 8), |a, b| {
+                    comparisons += 1;
+                    a.y.cmp(&b.y)
+                })
+            },
+            231
+        );
+        assert!(comparisons > 0);
     };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["smallest", "sorted_xs"],
+            &["Point"],
+            Some(quote! {
+                comparator!("smallest", "int32_t")
+                comparator!("sorted_xs", "Point")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
 
-    struct B {
-        B() {}
-        void take_optional(Optional<std::string>) {}
-        uint32_t a;
-    };
+#[test]
+fn test_move_into_box() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        inline uint32_t& moves() { static uint32_t count = 0; return count; }
+        inline uint32_t& destructions() { static uint32_t count = 0; return count; }
+        inline uint32_t move_count() { return moves(); }
+        inline uint32_t destruction_count() { return destructions(); }
+        struct Tracked {
+            Tracked(uint32_t v) : value(v) {}
+            Tracked(const Tracked&) = delete;
+            Tracked(Tracked&& other) : value(other.value) {
+                other.value = 0;
+                ++moves();
+            }
+            ~Tracked() { ++destructions(); }
+            uint32_t get() const { return value; }
+            uint32_t value;
+        };
+        inline std::unique_ptr<Tracked> make_tracked(uint32_t v) {
+            return std::make_unique<Tracked>(v);
+        }
     "};
     let rs = quote! {
-        ffi::B::new().within_unique_ptr();
+        let obj = ffi::make_tracked(42);
+        assert_eq!(ffi::move_count(), 0);
+        let boxed = obj.move_into_box();
+        assert_eq!(ffi::move_count(), 1);
+        assert_eq!(ffi::destruction_count(), 1);
+        assert_eq!(boxed.get(), 42);
+        drop(boxed);
+        assert_eq!(ffi::move_count(), 1);
+        assert_eq!(ffi::destruction_count(), 2);
     };
-    run_test("", hdr, rs, &["B"], &[]);
+    run_test(
+        "",
+        hdr,
+        rs,
+        &["Tracked", "make_tracked", "move_count", "destruction_count"],
+        &[],
+    );
 }
 
 #[test]
-fn test_private_constructor_make_unique() {
+fn test_with_pin_mut() {
     let hdr = indoc! {"
-    #include <stdint.h>
-    struct A {
-    private:
-        A() {};
-    public:
-        uint32_t a;
-    };
+        #include <cstdint>
+        #include <memory>
+        class Counter {
+        public:
+            Counter() : self_(this) {}
+            Counter(const Counter&) = delete;
+            void add(uint32_t n) { total_ += n; }
+            uint32_t total() const { return total_; }
+            bool unmoved() const { return self_ == this; }
+        private:
+            uint32_t total_ = 0;
+            const Counter* self_;
+        };
+        inline std::unique_ptr<Counter> make_counter() {
+            return std::make_unique<Counter>();
+        }
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["A"], &[]);
+    let rs = quote! {
+        let total = ffi::make_counter().with_pin_mut(|mut c| {
+            c.as_mut().add(2);
+            c.as_mut().add(3);
+            assert!(c.unmoved());
+            c.total()
+        });
+        assert_eq!(total, 5);
+        let mut counter = ffi::make_counter();
+        counter.with_pin_mut(|c| c.add(7));
+        assert_eq!(counter.total(), 7);
+        assert!(counter.unmoved());
+    };
+    run_test("", hdr, rs, &["Counter", "make_counter"], &[]);
 }
 
 #[test]
-#[ignore] // https://github.com/google/autocxx/issues/266
-fn test_take_array() {
+fn test_allocator_callback() {
     let hdr = indoc! {"
-    #include <cstdint>
-    uint32_t take_array(const uint32_t a[4]) {
-        return a[0] + a[2];
-    }
+        #include <cstddef>
+        #include <cstdint>
+        #include <cstring>
+        #include <functional>
+        inline uint32_t fill_buffer(size_t len, uint8_t value,
+                                    std::function<void*(size_t, size_t)> allocate) {
+            auto* bytes = static_cast<uint8_t*>(allocate(len * sizeof(uint32_t), alignof(uint32_t)));
+            if (bytes == nullptr) {
+                return 0;
+            }
+            std::memset(bytes, value, len * sizeof(uint32_t));
+            return reinterpret_cast<uint32_t*>(bytes)[len - 1];
+        }
+        inline bool allocate_nothing(std::function<void*(size_t)> allocate) {
+            return allocate(0) != nullptr;
+        }
     "};
     let rs = quote! {
-        let c: [u32; 4usize] = [ 10, 20, 30, 40 ];
-        let c = c as *const [_];
-        assert_eq!(ffi::take_array(&c), 40);
+        let mut layouts = Vec::new();
+        let mut storage = Vec::new();
+        let value = unsafe {
+            ffi::fill_buffer(4, 1, |layout| {
+                layouts.push(layout);
+                storage.resize(layout.size() / 4, 0u32);
+                storage.as_mut_ptr() as *mut u8
+            })
+        };
+        assert_eq!(value, 0x01010101);
+        assert_eq!(layouts.len(), 1);
+        assert_eq!(layouts[0].size(), 16);
+        assert_eq!(layouts[0].align(), 4);
+        assert_eq!(
+            unsafe { ffi::fill_buffer(4, 1, |_| ::core::ptr::null_mut()) },
+            0
+        );
+        let mut allocated = None;
+        assert!(unsafe {
+            ffi::allocate_nothing(|layout| {
+                let ptr = ::std::alloc::alloc(layout);
+                allocated = Some((layout, ptr));
+                ptr
+            })
+        });
+        let (layout, ptr) = allocated.unwrap();
+        assert_eq!(layout.size(), 1);
+        assert!(layout.align() >= ::core::mem::align_of::<u64>());
+        unsafe { ::std::alloc::dealloc(ptr, layout) };
     };
-    run_test("", hdr, rs, &["take_array"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["fill_buffer", "allocate_nothing"],
+            &[],
+            Some(quote! {
+                allocator_callback!("fill_buffer")
+                allocator_callback!("allocate_nothing")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_take_array_in_struct() {
+fn test_closure_callback() {
     let hdr = indoc! {"
-    #include <cstdint>
-    struct data {
-        char a[4];
-    };
-    uint32_t take_array(const data a) {
-        return a.a[0] + a.a[2];
-    }
+        #include <cstdint>
+        #include <functional>
+        inline uint32_t run_job(uint32_t steps, std::function<void(uint32_t, uint32_t)> progress) {
+            for (uint32_t i = 1; i <= steps; ++i) {
+                progress(i, steps);
+            }
+            return steps;
+        }
+        inline double sum_mapped(uint32_t count, std::function<double(uint32_t, bool)> map) {
+            double total = 0;
+            for (uint32_t i = 0; i < count; ++i) {
+                total += map(i, i % 2 == 0);
+            }
+            return total;
+        }
     "};
+    // Safety: none of these functions keeps its callback.
     let rs = quote! {
-        let c = ffi::data { a: [ 10, 20, 30, 40 ] };
-        assert_eq!(ffi::take_array(c), 40);
+        let mut reports = Vec::new();
+        assert_eq!(unsafe { ffi::run_job(3, |done, total| reports.push((done, total))) }, 3);
+        assert_eq!(reports, [(1, 3), (2, 3), (3, 3)]);
+        let mut calls = 0;
+        assert_eq!(unsafe { ffi::run_job(0, |_, _| calls += 1) }, 0);
+        assert_eq!(calls, 0);
+        let total = unsafe { ffi::sum_mapped(4, |i, even| if even { f64::from(i) } else { 0.5 }) };
+        assert_eq!(total, 3.0);
     };
-    run_test("", hdr, rs, &["take_array"], &["data"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["run_job", "sum_mapped"],
+            &[],
+            Some(quote! {
+                closure_callback!("run_job", "void(uint32_t, uint32_t)")
+                closure_callback!("sum_mapped", "double(uint32_t, bool)")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_take_struct_built_array_in_function() {
+fn test_closure_callback_fallible() {
     let hdr = indoc! {"
-    #include <cstdint>
-    struct data {
-        char a[4];
-    };
-    uint32_t take_array(char a[4]) {
-        return a[0] + a[2];
-    }
+        #include <cstdint>
+        #include <functional>
+        #include <stdexcept>
+        #include <string>
+        inline std::string fetch_all(uint32_t count, std::function<uint32_t(uint32_t)> fetch) {
+            uint32_t total = 0;
+            try {
+                for (uint32_t i = 0; i < count; ++i) {
+                    total += fetch(i);
+                }
+            } catch (const std::runtime_error& e) {
+                return std::string(\"failed: \") + e.what();
+            }
+            return std::to_string(total);
+        }
+        inline bool visit_all(uint32_t count, std::function<void(uint32_t)> visit) {
+            try {
+                for (uint32_t i = 0; i < count; ++i) {
+                    visit(i);
+                }
+            } catch (const std::runtime_error&) {
+                return false;
+            }
+            return true;
+        }
     "};
+    // Safety: neither of these functions keeps its callback.
     let rs = quote! {
-        let mut c = ffi::data { a: [ 10, 20, 30, 40 ] };
-        unsafe {
-            assert_eq!(ffi::take_array(c.a.as_mut_ptr()), 40);
-        }
+        let doubled = unsafe { ffi::fetch_all(3, |i| Ok(i * 2)) };
+        assert_eq!(doubled.to_str().unwrap(), "6");
+        let failed =
+            unsafe { ffi::fetch_all(3, |i| if i == 1 { Err("no item 1".into()) } else { Ok(i) }) };
+        assert_eq!(failed.to_str().unwrap(), "failed: no item 1");
+        let mut visited = Vec::new();
+        assert!(unsafe {
+            ffi::visit_all(3, |i| {
+                visited.push(i);
+                Ok(())
+            })
+        });
+        assert!(!unsafe {
+            ffi::visit_all(3, |i| {
+                visited.push(i);
+                Err(format!("stopped at {i}").into())
+            })
+        });
+        assert_eq!(visited, [0, 1, 2, 0]);
     };
-    run_test("", hdr, rs, &["take_array"], &["data"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["fetch_all", "visit_all"],
+            &[],
+            Some(quote! {
+                closure_callback!("fetch_all", "uint32_t(uint32_t)", fallible)
+                closure_callback!("visit_all", "void(uint32_t)", fallible)
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_take_array_in_function() {
+fn test_cpp_prelude() {
     let hdr = indoc! {"
-    #include <cstdint>
-    uint32_t take_array(char a[4]) {
-        return a[0] + a[2];
-    }
+        #include <string>
+        inline std::string greet() { return \"hello\"; }
     "};
     let rs = quote! {
-        let mut a: [i8; 4] = [ 10, 20, 30, 40 ];
-        unsafe {
-            assert_eq!(ffi::take_array(a.as_mut_ptr()), 40);
-        }
+        assert_eq!(ffi::greet().to_str().unwrap(), "hello");
     };
-    run_test("", hdr, rs, &["take_array"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["greet"],
+            &[],
+            Some(quote! { cpp_prelude!("#define AUTOCXX_PRELUDE_VALUE 42") }),
+        ),
+        None,
+        Some(Box::new(CppMatcher::new(
+            &["#define AUTOCXX_PRELUDE_VALUE 42"],
+            &[],
+        ))),
+        None,
+    );
 }
 
 #[test]
-fn test_union_ignored() {
+fn test_result_variant() {
     let hdr = indoc! {"
-    #include <cstdint>
-    union A {
-        uint32_t a;
-        float b;
-    };
-    struct B {
-        B() :a(1) {}
-        uint32_t take_union(A) const {
-            return 3;
+        #include <cstdint>
+        #include <string>
+        #include <variant>
+        namespace errors {
+        class ParseError {
+        public:
+            ParseError(uint32_t line) : line(line) {}
+            uint32_t get_line() const { return line; }
+        private:
+            uint32_t line;
+        };
+        }
+        class Config {
+        public:
+            Config(std::string name) : name(name) {}
+            const std::string& get_name() const { return name; }
+        private:
+            std::string name;
+        };
+        inline std::variant<errors::ParseError, Config> parse(bool ok) {
+            if (ok) {
+                return Config(\"fred\");
+            }
+            return errors::ParseError(3);
+        }
+        // The error needn't be the first alternative.
+        inline std::variant<Config, errors::ParseError> parse_reversed(bool ok) {
+            if (ok) {
+                return Config(\"bob\");
+            }
+            return errors::ParseError(4);
         }
-        uint32_t get_a() const { return 2; }
-        uint32_t a;
-    };
     "};
     let rs = quote! {
-        let b = ffi::B::new().within_unique_ptr();
-        assert_eq!(b.get_a(), 2);
+        let config = ffi::parse(true).ok().unwrap();
+        assert_eq!(config.get_name().to_str().unwrap(), "fred");
+        let error = ffi::parse(false).err().unwrap();
+        assert_eq!(error.get_line(), 3);
+        let config = ffi::parse_reversed(true).ok().unwrap();
+        assert_eq!(config.get_name().to_str().unwrap(), "bob");
+        let error = ffi::parse_reversed(false).err().unwrap();
+        assert_eq!(error.get_line(), 4);
     };
-    run_test("", hdr, rs, &["B"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["parse", "parse_reversed", "Config", "errors::ParseError"],
+            &[],
+            Some(quote! {
+                result_variant!("parse", "errors::ParseError")
+                result_variant!("parse_reversed", "errors::ParseError")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_union_nonpod() {
+fn test_probe_traits() {
+    // The defaulted copy constructor is in fact deleted, because of the
+    // `unique_ptr`, so must not be used for `CopyNew`.
     let hdr = indoc! {"
-    #include <cstdint>
-    union A {
-        uint32_t a;
-        float b;
-    };
+        #include <cstdint>
+        #include <memory>
+        class Holder {
+        public:
+            Holder() : value(std::make_unique<uint32_t>(4)) {}
+            Holder(const Holder&) = default;
+            Holder(Holder&&) = default;
+            uint32_t get() const { return *value; }
+        private:
+            std::unique_ptr<uint32_t> value;
+        };
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["A"], &[]);
-}
-
-#[test]
-fn test_union_pod() {
-    let hdr = indoc! {"
-    #include <cstdint>
-    union A {
-        uint32_t a;
-        float b;
+    let rs = quote! {
+        fn assert_move_new<T: autocxx::moveit::MoveNew>() {}
+        assert_move_new::<ffi::Holder>();
+        let holder = ffi::Holder::new().within_unique_ptr();
+        assert_eq!(holder.get(), 4);
     };
-    "};
-    let rs = quote! {};
-    run_test_expect_fail("", hdr, rs, &[], &["A"]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["Holder"], &[], Some(quote! { probe_traits!("Holder") })),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_type_aliased_anonymous_union_ignored() {
+fn test_tuple_array() {
     let hdr = indoc! {"
-    #include <cstdint>
-    namespace test {
-        typedef union {
-        int a;
-        } Union;
+        #include <cstdint>
+        #include <tuple>
+        inline std::tuple<int32_t, int32_t, int32_t> get_coords() {
+            return std::make_tuple(1, 2, 3);
+        }
+        class Color {
+        public:
+            std::tuple<double, double> get_range() const { return std::make_tuple(0.5, 1.5); }
         };
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["test::Union"], &[]);
+    let rs = quote! {
+        assert_eq!(ffi::get_coords(), [1, 2, 3]);
+        let color = ffi::Color::new().within_unique_ptr();
+        assert_eq!(color.get_range(), [0.5, 1.5]);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["get_coords", "Color"],
+            &[],
+            Some(quote! {
+                tuple_array!("get_coords")
+                tuple_array!("Color::get_range")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_type_aliased_anonymous_struct_ignored() {
+fn test_pair_tuple() {
     let hdr = indoc! {"
-    #include <cstdint>
-    namespace test {
-        typedef struct {
-            int a;
-        } Struct;
-    };
+        #include <cstdint>
+        #include <string>
+        #include <utility>
+        #include <vector>
+        inline std::pair<int32_t, bool> divmod(int32_t a, int32_t b) {
+            return std::make_pair(a / b, a % b == 0);
+        }
+        inline std::pair<std::pair<int32_t, int32_t>, double> span() {
+            return std::make_pair(std::make_pair(2, 7), 0.5);
+        }
+        inline std::pair<std::string, int32_t> named() {
+            return std::make_pair(std::string(\"seven\"), 7);
+        }
+        inline std::pair<std::string, std::string> entry() {
+            return std::make_pair(std::string(\"key\"), std::string(\"va\\xffue\"));
+        }
+        inline std::pair<std::vector<int32_t>, int32_t> listed() {
+            return std::make_pair(std::vector<int32_t>{1, 2}, 2);
+        }
+        class Range {
+        public:
+            std::pair<uint64_t, uint64_t> bounds() const { return std::make_pair(3, 9); }
+        };
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["test::Struct"], &[]);
+    let rs = quote! {
+        assert_eq!(ffi::divmod(7, 2), (3, false));
+        assert_eq!(ffi::divmod(9, 3), (3, true));
+        assert_eq!(ffi::span(), ((2, 7), 0.5));
+        assert_eq!(ffi::named(), ("seven".to_string(), 7));
+        let (key, value): (String, String) = ffi::entry();
+        assert_eq!(key, "key");
+        assert_eq!(value, "va\u{FFFD}ue");
+        let range = ffi::Range::new().within_unique_ptr();
+        assert_eq!(range.bounds(), (3, 9));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["divmod", "span", "named", "entry", "listed", "Range"],
+            &[],
+            Some(quote! {
+                pair_tuple!("divmod")
+                pair_tuple!("span")
+                pair_tuple!("named")
+                pair_tuple!("entry")
+                pair_tuple!("listed")
+                pair_tuple!("Range::bounds")
+            }),
+        ),
+        None,
+        Some(make_string_finder(vec![
+            "pair_tuple!, but doesn't return a std::pair".to_string(),
+        ])),
+        None,
+    );
 }
 
 #[test]
-fn test_type_aliased_anonymous_nested_struct_ignored() {
+fn test_pair_tuple_optional() {
     let hdr = indoc! {"
-    #include <cstdint>
-    namespace test {
-        struct Outer {
-            typedef struct {
-                int a;
-            } Struct;
-            int b;
+        #include <cstdint>
+        #include <memory>
+        #include <optional>
+        #include <string>
+        #include <utility>
+        inline std::optional<std::pair<int32_t, bool>> find(int32_t key) {
+            if (key < 0) {
+                return std::nullopt;
+            }
+            return std::make_pair(key * 2, key % 2 == 0);
+        }
+        struct Widget {
+            int32_t id;
         };
-    };
+        inline std::pair<std::unique_ptr<Widget>, int32_t> make_widget(int32_t id) {
+            return std::make_pair(std::make_unique<Widget>(Widget{id}), id + 1);
+        }
+        inline std::optional<std::pair<std::unique_ptr<Widget>, std::pair<std::string, double>>>
+        maybe_widget(bool present) {
+            if (!present) {
+                return std::nullopt;
+            }
+            return std::make_pair(std::make_unique<Widget>(Widget{4}), std::make_pair(std::string(\"four\"), 0.5));
+        }
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["test::Outer_Struct"], &[]);
+    let rs = quote! {
+        assert_eq!(ffi::find(3), Some((6, false)));
+        assert_eq!(ffi::find(4), Some((8, true)));
+        assert_eq!(ffi::find(-1), None);
+        let (widget, next): (cxx::UniquePtr<ffi::Widget>, i32) = ffi::make_widget(7);
+        assert_eq!(widget.id, 7);
+        assert_eq!(next, 8);
+        let (widget, (name, ratio)) = ffi::maybe_widget(true).unwrap();
+        assert_eq!(widget.id, 4);
+        assert_eq!(name, "four");
+        assert_eq!(ratio, 0.5);
+        assert!(ffi::maybe_widget(false).is_none());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["find", "make_widget", "maybe_widget"],
+            &["Widget"],
+            Some(quote! {
+                pair_tuple!("find")
+                pair_tuple!("make_widget")
+                pair_tuple!("maybe_widget")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
-#[ignore] // https://github.com/google/autocxx/issues/1251
 #[test]
-fn test_double_underscores_ignored() {
+fn test_std_array() {
     let hdr = indoc! {"
-    #include <cstdint>
-    struct __FOO {
-        uint32_t a;
-    };
-    struct B {
-        B() :a(1) {}
-        uint32_t take_foo(__FOO) const {
-            return 3;
+        #include <array>
+        #include <cstdint>
+        inline std::array<int32_t, 4> reverse(std::array<int32_t, 4> values) {
+            return {values[3], values[2], values[1], values[0]};
         }
-        void do__something() const { }
-        uint32_t get_a() const { return 2; }
-        uint32_t a;
-    };
-
-    struct __default { __default() = default; };
-    struct __destructor { ~__destructor() = default; };
-    struct __copy { __copy(const __copy&) = default; };
-    struct __copy_operator { __copy_operator &operator=(const __copy_operator&) = default; };
-    struct __move { __move(__move&&) = default; };
-    struct __move_operator { __move_operator &operator=(const __move_operator&) = default; };
+        inline uint32_t checksum(std::array<uint8_t, 4096> data) {
+            uint32_t sum = 0;
+            for (auto byte : data) {
+                sum += byte;
+            }
+            return sum;
+        }
+        inline void ignore(std::array<int32_t, 0> nothing) {}
+        class Grid {
+        public:
+            std::array<double, 2> origin() const { return {1.5, -2.0}; }
+        };
     "};
     let rs = quote! {
-        let b = ffi::B::new().within_unique_ptr();
-        assert_eq!(b.get_a(), 2);
+        assert_eq!(ffi::reverse([1, 2, 3, 4]), [4, 3, 2, 1]);
+        assert_eq!(ffi::checksum([1; 4096]), 4096);
+        let grid = ffi::Grid::new().within_unique_ptr();
+        assert_eq!(grid.origin(), [1.5, -2.0]);
     };
-    run_test(
+    run_test_ex(
         "",
         hdr,
         rs,
-        &[
-            "B",
-            "__default",
-            "__destructor",
-            "__copy",
-            "__copy_operator",
-            "__move",
-            "__move_operator",
-        ],
-        &[],
+        directives_from_lists(
+            &["reverse", "checksum", "ignore", "Grid"],
+            &[],
+            Some(quote! {
+                std_array!("reverse", values = ["int32_t"; 4], return = ["int32_t"; 4])
+                std_array!("checksum", data = ["uint8_t"; 4096])
+                std_array!("ignore", nothing = ["int32_t"; 0])
+                std_array!("Grid::origin", return = ["double"; 2])
+            }),
+        ),
+        None,
+        Some(make_string_finder(vec![
+            "zero-length std::array".to_string()
+        ])),
+        None,
     );
 }
 
-// This test fails on Windows gnu but not on Windows msvc
-#[cfg_attr(skip_windows_gnu_failing_tests, ignore)]
 #[test]
-fn test_double_underscore_typedef_ignored() {
+fn test_template_returns() {
     let hdr = indoc! {"
-    #include <cstdint>
-    typedef int __int32_t;
-    typedef __int32_t __darwin_pid_t;
-    typedef __darwin_pid_t pid_t;
-    struct B {
-        B() :a(1) {}
-        uint32_t take_foo(pid_t) const {
-            return 3;
+        #include <cstdint>
+        #include <string>
+        namespace config {
+        struct Widget {
+            uint32_t size;
+        };
+        template<class T> T get();
+        template<> inline int32_t get<int32_t>() { return 42; }
+        template<> inline double get<double>() { return 0.5; }
+        template<> inline std::string get<std::string>() { return \"hello\"; }
+        template<> inline Widget get<Widget>() { return Widget { 3 }; }
         }
-        uint32_t get_a() const { return 2; }
-        uint32_t a;
-    };
     "};
     let rs = quote! {
-        let b = ffi::B::new().within_unique_ptr();
-        assert_eq!(b.get_a(), 2);
+        assert_eq!(ffi::config::get_int32_t(), 42);
+        assert_eq!(ffi::config::get_double(), 0.5);
+        assert_eq!(ffi::config::get_std_string().to_str().unwrap(), "hello");
+        assert_eq!(ffi::config::get_config_Widget().size, 3);
     };
-    run_test("", hdr, rs, &["B"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &[],
+            &["config::Widget"],
+            Some(quote! {
+                template_returns!("config::get", "int32_t", "double", "std::string", "config::Widget")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_double_underscores_fn_namespace() {
+fn test_template_returns_unnameable_type() {
     let hdr = indoc! {"
-    namespace __B {
-        inline void a() {}
-    };
+        template<class T> T make() { return T(); }
     "};
-    run_generate_all_test(hdr);
+    let rs = quote! {};
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            template_returns!("make", "int*")
+        },
+        None,
+        Some(make_error_finder("make_int")),
+        None,
+    );
 }
 
 #[test]
-fn test_typedef_to_ptr_is_marked_unsafe() {
+fn test_nanoseconds_count() {
     let hdr = indoc! {"
-    struct _xlocalefoo; /* forward reference */
-    typedef struct _xlocalefoo * locale_tfoo;
-    extern \"C\" {
-        locale_tfoo duplocalefoo(locale_tfoo);
-    }
+        #include <chrono>
+        #include <cstdint>
+        inline std::chrono::nanoseconds elapsed() {
+            return std::chrono::nanoseconds(1500);
+        }
+        class Timer {
+        public:
+            std::chrono::nanoseconds remaining() const { return std::chrono::seconds(-2); }
+        };
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["duplocalefoo"], &[]);
+    let rs = quote! {
+        assert_eq!(ffi::elapsed(), 1500i64);
+        let timer = ffi::Timer::new().within_unique_ptr();
+        assert_eq!(timer.remaining(), -2_000_000_000i64);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["elapsed", "Timer"],
+            &[],
+            Some(quote! {
+                nanoseconds_count!("elapsed")
+                nanoseconds_count!("Timer::remaining")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_issue_264() {
+fn test_seconds_count() {
     let hdr = indoc! {"
-    namespace a {
-        typedef int b;
-        //inline namespace c {}
-        template <typename> class aa;
-        inline namespace c {
-        template <typename d, typename = d, typename = aa<d>> class e;
+        #include <chrono>
+        #include <limits>
+        inline std::chrono::duration<double> exposure() {
+            return std::chrono::duration<double>(0.125);
+        }
+        inline std::chrono::milliseconds timeout() {
+            return std::chrono::milliseconds(1500);
+        }
+        inline std::chrono::duration<double> age_of_universe() {
+            return std::chrono::duration<double>(4.35e17);
+        }
+        inline std::chrono::duration<double> frame_time() {
+            return std::chrono::duration<double>(1.0 / 60.0);
+        }
+        inline std::chrono::duration<double> forever() {
+            return std::chrono::duration<double>(std::numeric_limits<double>::max());
+        }
+        inline std::chrono::duration<double> ago() {
+            return std::chrono::duration<double>(-2.5);
         }
-        typedef e<char> f;
-        template <typename g, typename, template <typename> typename> struct h {
-          using i = g;
-        };
-        template <typename g, template <typename> class k> using j = h<g, void, k>;
-        template <typename g, template <typename> class k>
-        using m = typename j<g, k>::i;
-        template <typename> struct l { typedef b ab; };
-        template <typename p> class aa {
-        public:
-          typedef p n;
-        };
-        struct r {
-          template <typename p> using o = typename p::c;
-        };
-        template <typename ad> struct u : r {
-          typedef typename ad::n n;
-          using ae = m<n, o>;
-          template <typename af, typename> struct v { using i = typename l<f>::ab; };
-          using ab = typename v<ad, ae>::i;
-        };
-        } // namespace a
-        namespace q {
-        template <typename ad> struct w : a::u<ad> {};
-        } // namespace q
-        namespace a {
-        inline namespace c {
-        template <typename, typename, typename ad> class e {
-          typedef q::w<ad> s;
-        public:
-          typedef typename s::ab ab;
-        };
-        } // namespace c
-        } // namespace a
-        namespace ag {
-        namespace ah {
-        typedef a::f::ab t;
-        class ai {
-        public:
-          t aj;
-        };
-        class al;
-        namespace am {
-        class an {
-        public:
-          void ao(ai);
-        };
-        } // namespace am
-        class ap {
-        public:
-          al aq();
-        };
-        class ar {
-        public:
-          am::an as;
-        };
-        class al {
-        public:
-          ar at;
-        };
-        struct au {
-          ap av;
-        };
-        } // namespace ah
-        } // namespace ag
-        namespace operations_research {
-        class aw {
-        public:
-          ag::ah::au ax;
-        };
-        class Solver {
-        public:
-          aw ay;
-        };
-        } // namespace operations_research
     "};
-    let rs = quote! {};
+    let rs = quote! {
+        assert_eq!(ffi::exposure(), 0.125);
+        assert_eq!(ffi::timeout(), 1.5);
+        assert_eq!(ffi::age_of_universe(), 4.35e17);
+        assert_eq!(
+            ffi::frame_time().unwrap(),
+            std::time::Duration::from_secs_f64(1.0 / 60.0)
+        );
+        assert!(ffi::forever().is_err());
+        assert!(ffi::ago().is_err());
+    };
     run_test_ex(
         "",
         hdr,
         rs,
-        directives_from_lists(&["operations_research::Solver"], &[], None),
-        make_cpp17_adder(),
+        directives_from_lists(
+            &[
+                "exposure",
+                "timeout",
+                "age_of_universe",
+                "frame_time",
+                "forever",
+                "ago",
+            ],
+            &[],
+            Some(quote! {
+                seconds_count!("exposure")
+                seconds_count!("timeout", f64)
+                seconds_count!("age_of_universe")
+                seconds_count!("frame_time", duration)
+                seconds_count!("forever", duration)
+                seconds_count!("ago", duration)
+            }),
+        ),
+        None,
         None,
         None,
     );
 }
 
 #[test]
-fn test_unexpected_use() {
-    // https://github.com/google/autocxx/issues/303
+fn test_return_move_only_value() {
     let hdr = indoc! {"
-        typedef int a;
-        namespace b {
-        namespace c {
-        enum d : a;
-        }
-        } // namespace b
-        namespace {
-        using d = b::c::d;
-        }
-        namespace content {
-        class RenderFrameHost {
+        #include <cstdint>
+        #include <memory>
+        class Buffer {
         public:
-            RenderFrameHost() {}
-        d e;
+            Buffer(uint32_t size) : data_(new uint8_t[size]()), size_(size) {}
+            Buffer(Buffer&&) = default;
+            Buffer(const Buffer&) = delete;
+            uint32_t size() const { return data_ ? size_ : 0; }
+        private:
+            std::unique_ptr<uint8_t[]> data_;
+            uint32_t size_;
         };
-        } // namespace content
-        "};
+        inline Buffer make_buffer(uint32_t size) { return Buffer(size); }
+    "};
     let rs = quote! {
-        let _ = ffi::content::RenderFrameHost::new().within_unique_ptr();
+        moveit! {
+            let buffer = ffi::make_buffer(16);
+        }
+        assert_eq!(buffer.size(), 16);
+        let boxed = ffi::make_buffer(4).within_box();
+        assert_eq!(boxed.size(), 4);
     };
-    run_test("", hdr, rs, &["content::RenderFrameHost"], &[]);
+    run_test("", hdr, rs, &["Buffer", "make_buffer"], &[]);
 }
 
 #[test]
-fn test_get_pure_virtual() {
+fn test_return_immovable_value() {
     let hdr = indoc! {"
         #include <cstdint>
-        class A {
-        public:
-            virtual ~A() {}
-            virtual uint32_t get_val() const = 0;
-        };
-        class B : public A {
+        #include <memory>
+        class Pinned {
         public:
-            virtual uint32_t get_val() const { return 3; }
+            Pinned(uint32_t value) : value_(value) {}
+            Pinned(Pinned&&) = delete;
+            uint32_t get() const { return value_; }
+        private:
+            uint32_t value_;
         };
-        const B b;
-        inline const A* get_a() { return &b; };
+        Pinned make_pinned(uint32_t value);
+        inline std::unique_ptr<Pinned> make_pinned_ptr(uint32_t value) {
+            return std::make_unique<Pinned>(value);
+        }
     "};
     let rs = quote! {
-        let a = ffi::get_a();
-        let a_ref = unsafe { a.as_ref() }.unwrap();
-        assert_eq!(a_ref.get_val(), 3);
+        assert_eq!(ffi::make_pinned_ptr(3).get(), 3);
     };
-    run_test("", hdr, rs, &["A", "get_a"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["Pinned", "make_pinned", "make_pinned_ptr"], &[], None),
+        None,
+        Some(make_error_finder("make_pinned")),
+        None,
+    );
 }
 
 #[test]
-fn test_abstract_class_no_make_unique() {
-    // We shouldn't generate a new().within_unique_ptr() for abstract classes.
-    // The test is successful if the bindings compile, i.e. if autocxx doesn't
-    // attempt to instantiate the class.
+fn test_move_out_of_vector() {
     let hdr = indoc! {"
-        class A {
+        #include <cstdint>
+        #include <memory>
+        #include <vector>
+        class Token {
         public:
-            A() {}
-            virtual ~A() {}
-            virtual void foo() const = 0;
+            Token(uint32_t value) : value(std::make_unique<uint32_t>(value)) {}
+            Token(Token&&) = default;
+            Token(const Token&) = delete;
+            uint32_t get() const { return value ? *value : 0; }
+        private:
+            std::unique_ptr<uint32_t> value;
         };
+        inline std::unique_ptr<std::vector<Token>> make_tokens() {
+            auto tokens = std::make_unique<std::vector<Token>>();
+            tokens->emplace_back(1);
+            tokens->emplace_back(2);
+            return tokens;
+        }
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["A"], &[]);
+    let rs = quote! {
+        let mut tokens = ffi::make_tokens();
+        let t = ffi::Token::take_from_vector(tokens.pin_mut(), 1);
+        assert_eq!(t.get(), 2);
+        // The moved-from element is still in the vector.
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens.get(1).unwrap().get(), 0);
+        assert_eq!(tokens.get(0).unwrap().get(), 1);
+        assert!(ffi::Token::take_from_vector(tokens.pin_mut(), 2).is_null());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Token", "make_tokens"],
+            &[],
+            Some(quote! {
+                move_out_of_vector!("Token")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_derived_abstract_class_no_make_unique() {
+fn test_expose_layout() {
     let hdr = indoc! {"
-        class A {
-        public:
-            A();
-            virtual ~A() {}
-            virtual void foo() const = 0;
+        #include <cstdint>
+        #include <string>
+        struct alignas(16) Aligned {
+            uint8_t a;
         };
-
-        class B : public A {
+        class Named {
         public:
-            B();
+            std::string name;
+            uint32_t id;
         };
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["A", "B"], &[]);
+    let rs = quote! {
+        const ALIGNED_STORAGE: [u8; ffi::Aligned::CPP_SIZE] = [0; ffi::Aligned::CPP_SIZE];
+        assert_eq!(ALIGNED_STORAGE.len(), 16);
+        assert_eq!(ffi::Aligned::CPP_ALIGN, 16);
+        assert_eq!(ffi::Aligned::cpp_size(), 16);
+        assert_eq!(ffi::Aligned::cpp_align(), 16);
+        assert_eq!(ffi::Named::CPP_SIZE, ffi::Named::cpp_size());
+        assert_eq!(ffi::Named::CPP_ALIGN, ffi::Named::cpp_align());
+        assert_eq!(ffi::Named::CPP_SIZE, std::mem::size_of::<ffi::Named>());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["Aligned", "Named"],
+            &[],
+            Some(quote! {
+                expose_layout!("Aligned")
+                expose_layout!("Named")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_recursive_derived_abstract_class_no_make_unique() {
+fn test_null_vector() {
     let hdr = indoc! {"
-        class A {
-        public:
-            A() {}
-            virtual ~A() {}
-            virtual void foo() const = 0;
-        };
+        #include <cstdint>
+        #include <memory>
+        #include <vector>
+        inline std::unique_ptr<std::vector<uint32_t>> find_items(bool found) {
+            if (!found) {
+                return nullptr;
+            }
+            return std::make_unique<std::vector<uint32_t>>(std::vector<uint32_t>{1, 2, 3});
+        }
+        inline std::unique_ptr<std::vector<uint32_t>> find_items_or_none(bool found) {
+            return find_items(found);
+        }
+        inline std::unique_ptr<std::vector<uint32_t>> find_items_or_empty(bool found) {
+            return find_items(found);
+        }
+    "};
+    let rs = quote! {
+        // Without the directive, we just get the UniquePtr.
+        assert!(ffi::find_items(false).is_null());
+        assert_eq!(ffi::find_items(true).len(), 3);
+        assert!(ffi::find_items_or_none(false).is_none());
+        let items = ffi::find_items_or_none(true).unwrap();
+        assert_eq!(items.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        let items = ffi::find_items_or_empty(false);
+        assert!(!items.is_null());
+        assert!(items.is_empty());
+        assert_eq!(ffi::find_items_or_empty(true).len(), 3);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["find_items", "find_items_or_none", "find_items_or_empty"],
+            &[],
+            Some(quote! {
+                null_vector!("find_items_or_none", none)
+                null_vector!("find_items_or_empty", empty)
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
 
-        class B : public A {
-        public:
-            B() {};
-        };
+#[test]
+fn test_map_from_iter() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <map>
+        #include <string>
+        inline uint32_t total(const std::map<std::string, uint32_t>& limits) {
+            uint32_t total = 0;
+            for (const auto& entry : limits) {
+                total += entry.second;
+            }
+            return total;
+        }
+        inline uint32_t lookup(const std::map<uint32_t, std::string>& names, uint32_t key) {
+            auto it = names.find(key);
+            return it == names.end() ? 0 : it->second.size();
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::total(::std::iter::empty()), 0);
+        assert_eq!(
+            ffi::total([("files".to_string(), 10u32), ("threads".to_string(), 4)]),
+            14
+        );
+        assert_eq!(
+            ffi::total((1..=3u32).map(|n| (n.to_string(), n))),
+            6
+        );
+        // As with std::map::insert, the first value for each key is kept.
+        assert_eq!(
+            ffi::lookup(
+                vec![(1u32, "one".to_string()), (1, "uno".to_string()), (2, "two".to_string())],
+                1
+            ),
+            3
+        );
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["total", "lookup"],
+            &[],
+            Some(quote! {
+                map_from_iter!("total")
+                map_from_iter!("lookup")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
 
-        class C : public B {
-        public:
-            C() {};
-        };
+#[test]
+fn test_map_from_iter_not_listed() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <map>
+        #include <string>
+        inline uint32_t count(const std::map<uint32_t, uint32_t>& m) {
+            return m.size();
+        }
     "};
+    // Without map_from_iter!, the function takes the opaque map, and
+    // nothing is generated to build one.
     let rs = quote! {};
-    run_test("", hdr, rs, &["A", "B", "C"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["count"], &[], None),
+        None,
+        Some(Box::new(CppMatcher::new(&[], &["_insert("]))),
+        None,
+    );
 }
 
 #[test]
-fn test_derived_abstract_class_with_no_allowlisting_no_make_unique() {
+fn test_map_from_iter_unordered() {
     let hdr = indoc! {"
-        class A {
-        public:
-            A();
-            virtual ~A() {}
-            virtual void foo() const = 0;
-        };
-
-        class B : public A {
-        public:
-            B();
-        };
+        #include <cstdint>
+        #include <string>
+        #include <unordered_map>
+        inline uint32_t total_length(const std::unordered_map<std::string, std::string>& names) {
+            uint32_t total = 0;
+            for (const auto& entry : names) {
+                total += entry.first.size() + entry.second.size();
+            }
+            return total;
+        }
+        inline bool has_key(const std::unordered_map<int32_t, bool>& flags, int32_t key) {
+            return flags.count(key) != 0;
+        }
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["B"], &[]);
+    let rs = quote! {
+        use std::collections::HashMap;
+        let mut names = HashMap::new();
+        names.insert("a".to_string(), "bc".to_string());
+        names.insert("def".to_string(), String::new());
+        assert_eq!(ffi::total_length(names), 6);
+        assert_eq!(ffi::total_length(HashMap::new()), 0);
+        let flags: HashMap<i32, bool> = [(1, true), (2, false)].into_iter().collect();
+        assert!(ffi::has_key(flags.clone(), 2));
+        assert!(!ffi::has_key(flags, 3));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["total_length", "has_key"],
+            &[],
+            Some(quote! {
+                map_from_iter!("total_length")
+                map_from_iter!("has_key")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
 }
 
 #[test]
-fn test_vector_of_pointers() {
-    // Just ensures the troublesome API is ignored
+fn test_unordered_map_accessors() {
     let hdr = indoc! {"
-        #include <vector>
-        namespace operations_research {
-        class a;
-        class Solver {
-        public:
-          struct b c(std::vector<a *>);
-        };
-        class a {};
-        } // namespace operations_research
+        #include <cstdint>
+        #include <string>
+        #include <unordered_map>
+        inline std::unordered_map<std::string, uint32_t> get_limits() {
+            return {{\"files\", 10}, {\"depth\", 3}};
+        }
+        inline std::unordered_map<int32_t, std::string> get_names() {
+            return {{1, \"one\"}, {2, \"two\"}, {3, \"three\"}};
+        }
     "};
-    let rs = quote! {};
-    run_test("", hdr, rs, &["operations_research::Solver"], &[]);
+    let rs = quote! {
+        let limits = ffi::get_limits();
+        assert_eq!(limits.len(), 2);
+        assert!(!limits.is_empty());
+        assert_eq!(limits.get("files"), Some(10));
+        assert_eq!(limits.get("depth"), Some(3));
+        assert_eq!(limits.get("width"), None);
+        let buckets = limits.bucket_count();
+        assert!(buckets > 0);
+        assert!(limits.bucket(buckets).is_none());
+        let in_buckets: usize = (0..buckets).map(|n| limits.bucket(n).unwrap().len()).sum();
+        assert_eq!(in_buckets, 2);
+        let mut entries: Vec<_> = limits.entries().collect();
+        entries.sort();
+        assert_eq!(entries, [("depth".to_string(), 3), ("files".to_string(), 10)]);
+        let names = ffi::get_names();
+        assert_eq!(names.get(2).as_deref(), Some("two"));
+        assert_eq!(names.get(4), None);
+        let mut keys: Vec<i32> = names.entries().map(|(key, _)| key).collect();
+        keys.sort();
+        assert_eq!(keys, [1, 2, 3]);
+    };
+    run_test("", hdr, rs, &["get_limits", "get_names"], &[]);
 }
 
 #[test]
@@ -6160,12 +10825,54 @@ fn test_doc_passthru() {
         "",
         hdr,
         rs,
-        directives_from_lists(&["A", "get_a"], &["B"], None),
+        directives_from_lists(&["A", "get_a"], &["B"], None),
+        None,
+        Some(make_string_finder(
+            ["Giraffes", "Elephants", "Rhinos"]
+                .map(|s| s.to_string())
+                .to_vec(),
+        )),
+        None,
+    );
+}
+
+#[test]
+fn test_pure_and_const_functions() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Shape {
+            uint32_t width;
+            uint32_t height;
+            [[gnu::pure]] uint32_t area() const { return width * height; }
+        };
+        [[gnu::const]] inline uint32_t square(uint32_t x) { return x * x; }
+        inline uint32_t next_id() { static uint32_t id = 0; return ++id; }
+    "};
+    let rs = quote! {
+        let s = ffi::Shape { width: 3, height: 4 };
+        assert_eq!(s.area(), 12);
+        assert_eq!(ffi::square(5), 25);
+        assert_eq!(ffi::next_id(), 1);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("square")
+            generate!("next_id")
+            generate_pod!("Shape")
+            pure_functions!("Shape::area")
+            const_functions!("square")
+        },
         None,
         Some(make_string_finder(
-            ["Giraffes", "Elephants", "Rhinos"]
-                .map(|s| s.to_string())
-                .to_vec(),
+            [
+                "listed in `pure_functions!`",
+                "listed in `const_functions!`",
+            ]
+            .map(|s| s.to_string())
+            .to_vec(),
         )),
         None,
     );
@@ -6655,6 +11362,39 @@ fn test_std_thing() {
     run_generate_all_test(hdr);
 }
 
+#[test]
+fn test_include_angle_brackets() {
+    let hdr = indoc! {"
+        #include <string>
+        inline std::string get_name() {
+            return \"Bob\";
+        }
+    "};
+    let hexathorpe = Token![#](Span::call_site());
+    let rs = quote! {
+        use autocxx::prelude::*;
+        include_cpp! {
+            #hexathorpe include <input.h>
+            safety!(unsafe_ffi)
+            generate!("get_name")
+        }
+        fn main() {
+            assert_eq!(ffi::get_name().to_str().unwrap(), "Bob");
+        }
+    };
+    do_run_test_manual(
+        "",
+        hdr,
+        rs,
+        None,
+        Some(Box::new(CppMatcher::new(
+            &["#include <input.h>"],
+            &["#include \"input.h\""],
+        ))),
+    )
+    .unwrap();
+}
+
 #[test]
 fn test_two_mods() {
     let hdr = indoc! {"
@@ -7073,12 +11813,209 @@ fn test_shared_ptr() {
 }
 
 #[test]
-#[ignore] // https://github.com/google/autocxx/issues/799
+fn test_weak_ptr_lock() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Cache {
+            uint32_t size;
+        };
+        inline std::shared_ptr<Cache> make_cache() {
+            return std::make_shared<Cache>(Cache { 16 });
+        }
+        inline std::weak_ptr<Cache> observe(const std::shared_ptr<Cache>& cache) {
+            return cache;
+        }
+        inline uint32_t use_count(const std::shared_ptr<Cache>& cache) {
+            return static_cast<uint32_t>(cache.use_count());
+        }
+    "};
+    let rs = quote! {
+        let cache = ffi::make_cache();
+        let observer = ffi::observe(&cache);
+        {
+            let locked = observer.lock().unwrap();
+            assert_eq!(locked.as_ref().unwrap().size, 16);
+            assert_eq!(ffi::use_count(&cache), 2);
+        }
+        assert_eq!(ffi::use_count(&cache), 1);
+        let observer2 = observer.clone();
+        drop(cache);
+        assert!(observer.lock().is_none());
+        assert!(observer2.lock().is_none());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["make_cache", "observe", "use_count"], &["Cache"], None),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_intrusive_ptr() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <utility>
+        namespace boost {
+        template <typename T> class intrusive_ptr {
+        public:
+            intrusive_ptr() : p(nullptr) {}
+            intrusive_ptr(T* p, bool add_ref = true) : p(p) {
+                if (p && add_ref) {
+                    intrusive_ptr_add_ref(p);
+                }
+            }
+            intrusive_ptr(const intrusive_ptr& other) : intrusive_ptr(other.p) {}
+            intrusive_ptr& operator=(intrusive_ptr other) {
+                std::swap(p, other.p);
+                return *this;
+            }
+            ~intrusive_ptr() {
+                if (p) {
+                    intrusive_ptr_release(p);
+                }
+            }
+            T* get() const { return p; }
+            T* detach() {
+                T* result = p;
+                p = nullptr;
+                return result;
+            }
+        private:
+            T* p;
+        };
+        }
+        inline uint32_t& live_documents() {
+            static uint32_t count = 0;
+            return count;
+        }
+        class Document {
+        public:
+            explicit Document(uint32_t id) : id(id) { ++live_documents(); }
+            ~Document() { --live_documents(); }
+            uint32_t get_id() const { return id; }
+            uint32_t get_refs() const { return refs; }
+            uint32_t refs = 0;
+        private:
+            uint32_t id;
+        };
+        inline void intrusive_ptr_add_ref(Document* doc) { ++doc->refs; }
+        inline void intrusive_ptr_release(Document* doc) {
+            if (--doc->refs == 0) {
+                delete doc;
+            }
+        }
+        inline boost::intrusive_ptr<Document> open_document(uint32_t id) {
+            return boost::intrusive_ptr<Document>(new Document(id));
+        }
+        inline boost::intrusive_ptr<Document> no_document() { return {}; }
+        inline uint32_t document_id(boost::intrusive_ptr<Document> doc) {
+            return doc.get()->get_id();
+        }
+        inline uint32_t document_refs(const boost::intrusive_ptr<Document>& doc) {
+            return doc.get()->get_refs();
+        }
+        inline uint32_t live_document_count() { return live_documents(); }
+    "};
+    let rs = quote! {
+        let doc = ffi::open_document(7);
+        assert_eq!(doc.get_id(), 7);
+        assert_eq!(doc.get_refs(), 1);
+        let copy = doc.clone();
+        assert_eq!(doc.get_refs(), 2);
+        // C++ adopts the reference each parameter gives up.
+        assert_eq!(ffi::document_id(copy), 7);
+        assert_eq!(doc.get_refs(), 1);
+        assert_eq!(ffi::document_refs(doc.clone()), 2);
+        assert_eq!(doc.get_refs(), 1);
+        assert_eq!(ffi::live_document_count(), 1);
+        drop(doc);
+        assert_eq!(ffi::live_document_count(), 0);
+        let none = ffi::no_document();
+        assert!(none.is_null());
+        assert!(none.as_ref().is_none());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &[
+                "Document",
+                "open_document",
+                "no_document",
+                "document_id",
+                "document_refs",
+                "live_document_count",
+            ],
+            &[],
+            Some(quote! {
+                intrusive_ptr!("Document")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_shared_ptr_shares_ownership() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct Widget {
+            uint32_t id;
+        };
+        inline std::shared_ptr<Widget>& widget_storage() {
+            static std::shared_ptr<Widget> widget = std::make_shared<Widget>(Widget { 42 });
+            return widget;
+        }
+        inline std::shared_ptr<Widget> get_widget() {
+            return widget_storage();
+        }
+        inline uint32_t widget_use_count() {
+            return static_cast<uint32_t>(widget_storage().use_count());
+        }
+        inline std::shared_ptr<Widget> get_no_widget() {
+            return nullptr;
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::get_widget();
+        let b = ffi::get_widget();
+        assert_eq!(ffi::widget_use_count(), 3);
+        assert!(std::ptr::eq(a.as_ref().unwrap(), b.as_ref().unwrap()));
+        assert_eq!(a.as_ref().unwrap().id, 42);
+        let c = b.clone();
+        assert_eq!(ffi::widget_use_count(), 4);
+        drop(a);
+        drop(b);
+        drop(c);
+        assert_eq!(ffi::widget_use_count(), 1);
+        let none = ffi::get_no_widget();
+        assert!(none.is_null());
+        assert!(none.as_ref().is_none());
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &["get_widget", "widget_use_count", "get_no_widget"],
+        &["Widget"],
+    );
+}
+
+#[test]
 fn test_shared_ptr_const() {
     let hdr = indoc! {"
         #include <memory>
         inline std::shared_ptr<const int> make_shared_int() {
-            return std::make_shared<const int>(3);
+            return std::make_shared<int>(3);
         }
         inline int take_shared_int(std::shared_ptr<const int> a) {
             return *a;
@@ -7088,7 +12025,117 @@ fn test_shared_ptr_const() {
         let a = ffi::make_shared_int();
         assert_eq!(ffi::take_shared_int(a.clone()), autocxx::c_int(3));
     };
-    run_test("", hdr, rs, &["make_shared_int", "take_shared_int"], &[]);
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["make_shared_int", "take_shared_int"],
+            &[],
+            Some(quote! {
+                mutable_shared_ptr_returns!("make_shared_int")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_shared_ptr_const_struct() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        class A {
+        public:
+            A(uint32_t a) : a(a) {}
+            uint32_t get() const { return a; }
+            void set(uint32_t new_a) { a = new_a; }
+        private:
+            uint32_t a;
+        };
+        inline std::shared_ptr<const A> make_shared_a() {
+            return std::make_shared<A>(3);
+        }
+        inline void set_shared_a(std::shared_ptr<A> a) {
+            a->set(4);
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::make_shared_a();
+        assert_eq!(a.get(), 3);
+        ffi::set_shared_a(a.clone());
+        assert_eq!(a.get(), 4);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(
+            &["make_shared_a", "set_shared_a", "A"],
+            &[],
+            Some(quote! {
+                mutable_shared_ptr_returns!("make_shared_a")
+            }),
+        ),
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_shared_ptr_const_return_not_listed() {
+    // Without `mutable_shared_ptr_returns!`, nothing casts away the
+    // constness, so the generated C++ can't compile.
+    let hdr = indoc! {"
+        #include <memory>
+        inline std::shared_ptr<const int> make_shared_int() {
+            return std::make_shared<const int>(3);
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::make_shared_int();
+        assert_eq!(*a, autocxx::c_int(3));
+    };
+    run_test_expect_fail("", hdr, rs, &["make_shared_int"], &[]);
+}
+
+#[test]
+fn test_shared_ptr_polymorphic_factory() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        class Shape {
+        public:
+            virtual ~Shape() {}
+            virtual uint32_t area() const = 0;
+            virtual uint32_t sides() const { return 0; }
+        };
+        class Square : public Shape {
+        public:
+            Square(uint32_t side) : side(side) {}
+            uint32_t area() const override { return side * side; }
+            uint32_t sides() const override { return 4; }
+        private:
+            uint32_t side;
+        };
+        inline std::shared_ptr<Shape> make_shape() {
+            return std::make_shared<Square>(3);
+        }
+        inline uint32_t area_in_cpp(std::shared_ptr<Shape> shape) {
+            return shape->area();
+        }
+    "};
+    let rs = quote! {
+        let shape = ffi::make_shape();
+        let as_ref = shape.as_ref().unwrap();
+        assert_eq!(as_ref.area(), 9);
+        assert_eq!(as_ref.sides(), 4);
+        assert_eq!(ffi::area_in_cpp(shape.clone()), 9);
+    };
+    run_test("", hdr, rs, &["make_shape", "area_in_cpp", "Shape"], &[]);
 }
 
 #[test]
@@ -7646,6 +12693,113 @@ fn test_pv_subclass_mut() {
     );
 }
 
+#[test]
+fn test_dyn_subclass() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <memory>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual uint32_t notify(uint32_t value) = 0;
+        virtual ~Observer() {}
+    };
+    inline uint32_t notify_twice(Observer& o) {
+        return o.notify(1) + o.notify(2);
+    }
+    inline void drop_observer(std::unique_ptr<Observer> o) {}
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let drops = std::rc::Rc::new(std::cell::Cell::new(0));
+            let mut observer = ffi::BoxedObserver::new_from_box(Box::new(Counter {
+                total: 10,
+                drops: drops.clone(),
+            }));
+            assert_eq!(ffi::notify_twice(observer.pin_mut()), 24);
+            assert_eq!(drops.get(), 0);
+            ffi::drop_observer(observer);
+            assert_eq!(drops.get(), 1);
+        },
+        quote! {
+            generate!("notify_twice")
+            generate!("drop_observer")
+            dyn_subclass!("Observer", BoxedObserver)
+        },
+        None,
+        None,
+        Some(quote! {
+            use ffi::Observer_methods;
+            struct Counter {
+                total: u32,
+                drops: std::rc::Rc<std::cell::Cell<u32>>,
+            }
+            impl Observer_methods for Counter {
+                fn notify(&mut self, value: u32) -> u32 {
+                    self.total += value;
+                    self.total
+                }
+            }
+            impl Drop for Counter {
+                fn drop(&mut self) {
+                    self.drops.set(self.drops.get() + 1);
+                }
+            }
+        }),
+    );
+}
+
+/// Without a virtual destructor, C++ deleting a `dyn_subclass!` object
+/// through a pointer to its superclass would leak the boxed Rust object.
+/// The generated C++ static_asserts that there is one, whether the class
+/// declares no destructor at all or a non-virtual one.
+fn run_dyn_subclass_without_virtual_destructor(destructor: &str) {
+    let hdr = format!(
+        "
+    #include <cstdint>
+
+    class Observer {{
+    public:
+        Observer() {{}}
+        virtual uint32_t notify(uint32_t value) = 0;
+        {destructor}
+    }};
+    "
+    );
+    run_test_expect_fail_ex(
+        "",
+        &hdr,
+        quote! {},
+        quote! {
+            dyn_subclass!("Observer", BoxedObserver)
+        },
+        None,
+        None,
+        Some(quote! {
+            use ffi::Observer_methods;
+            struct Counter;
+            impl Observer_methods for Counter {
+                fn notify(&mut self, value: u32) -> u32 {
+                    value
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_dyn_subclass_without_destructor() {
+    run_dyn_subclass_without_virtual_destructor("");
+}
+
+#[test]
+fn test_dyn_subclass_with_non_virtual_destructor() {
+    run_dyn_subclass_without_virtual_destructor("~Observer() {}");
+}
+
 #[test]
 fn test_pv_subclass_const() {
     let hdr = indoc! {"
@@ -11567,6 +16721,36 @@ fn test_variadic() {
     run_test("", hdr, quote! {}, &["SomeClass"], &[]);
 }
 
+#[test]
+fn test_error_generated_for_variadic_function() {
+    let hdr = indoc! {"
+        #include <cstdarg>
+        inline int sum_ints(int count, ...) {
+            va_list args;
+            va_start(args, count);
+            int total = 0;
+            for (int i = 0; i < count; i++) {
+                total += va_arg(args, int);
+            }
+            va_end(args);
+            return total;
+        }
+        inline int add(int a, int b) { return a + b; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::add(c_int(2), c_int(3)), c_int(5));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["sum_ints", "add"], &[], None),
+        None,
+        Some(make_error_finder("sum_ints")),
+        None,
+    );
+}
+
 #[test]
 fn test_typedef_to_enum() {
     let hdr = indoc! {"
@@ -90,6 +90,103 @@ impl UnsafePolicy {
     }
 }
 
+/// How to deal with C++ strings which may not be valid UTF-8, when
+/// `std::string` return values are converted into a Rust `String`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Default)]
+pub enum Utf8Policy {
+    /// Return a `Result<String, Utf8Error>`.
+    #[default]
+    Checked,
+    /// Replace any invalid sequences with U+FFFD.
+    Lossy,
+    /// Assume the C++ code only ever returns valid UTF-8.
+    AssumeValid,
+}
+
+impl Parse for Utf8Policy {
+    fn parse(input: ParseStream) -> ParseResult<Self> {
+        let r = match input.parse::<Option<syn::Ident>>()? {
+            Some(id) => {
+                if id == "checked" {
+                    Ok(Utf8Policy::Checked)
+                } else if id == "lossy" {
+                    Ok(Utf8Policy::Lossy)
+                } else if id == "assume_valid" {
+                    Ok(Utf8Policy::AssumeValid)
+                } else {
+                    Err(syn::Error::new(
+                        id.span(),
+                        "expected checked, lossy or assume_valid",
+                    ))
+                }
+            }
+            None => Ok(Utf8Policy::Checked),
+        };
+        if !input.is_empty() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "unexpected tokens within UTF-8 policy",
+            ));
+        }
+        r
+    }
+}
+
+impl ToTokens for Utf8Policy {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        match self {
+            Utf8Policy::Checked => tokens.extend(quote! { checked }),
+            Utf8Policy::Lossy => tokens.extend(quote! { lossy }),
+            Utf8Policy::AssumeValid => tokens.extend(quote! { assume_valid }),
+        }
+    }
+}
+
+/// How to deal with C++ `std::u16string`s which may not be valid UTF-16,
+/// such as those containing unpaired surrogates, when they're converted
+/// into a Rust `String`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Default)]
+pub enum Utf16Policy {
+    /// Replace any unpaired surrogates with U+FFFD.
+    #[default]
+    Lossy,
+    /// Return a `Result<String, FromUtf16Error>`.
+    Checked,
+}
+
+impl Parse for Utf16Policy {
+    fn parse(input: ParseStream) -> ParseResult<Self> {
+        let r = match input.parse::<Option<syn::Ident>>()? {
+            Some(id) => {
+                if id == "lossy" {
+                    Ok(Utf16Policy::Lossy)
+                } else if id == "checked" {
+                    Ok(Utf16Policy::Checked)
+                } else {
+                    Err(syn::Error::new(id.span(), "expected lossy or checked"))
+                }
+            }
+            None => Ok(Utf16Policy::Lossy),
+        };
+        if !input.is_empty() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "unexpected tokens within UTF-16 policy",
+            ));
+        }
+        r
+    }
+}
+
+impl ToTokens for Utf16Policy {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        match self {
+            Utf16Policy::Lossy => tokens.extend(quote! { lossy }),
+            Utf16Policy::Checked => tokens.extend(quote! { checked }),
+        }
+    }
+}
+
 /// An entry in the allowlist.
 #[derive(Hash, Debug)]
 pub enum AllowlistEntry {
@@ -161,6 +258,331 @@ pub struct Subclass {
     pub subclass: Ident,
 }
 
+/// A C++ container type for which we should generate a Rust iterator,
+/// and the type of item that iterator yields. If `mutable`, there's also
+/// an iterator over mutable items, using the container's non-const
+/// `begin()` and `end()`.
+#[derive(Debug, Clone, Hash)]
+pub struct Iterable {
+    pub container: String,
+    pub item: String,
+    pub mutable: bool,
+}
+
+/// A C++ type with an `operator bool()` which should be exposed
+/// as a Rust method, and the name of that method.
+#[derive(Debug, Clone, Hash)]
+pub struct OperatorBool {
+    pub ty: String,
+    pub method: Ident,
+}
+
+/// A C++ type with an `operator!()` which should be exposed as a Rust
+/// method, and the name of that method.
+#[derive(Debug, Clone, Hash)]
+pub struct OperatorNot {
+    pub ty: String,
+    pub method: Ident,
+}
+
+/// The C++ compound assignment operators which `compound_assign!` accepts,
+/// each with the Rust trait which it implements and that trait's method.
+pub const COMPOUND_ASSIGNMENT_OPERATORS: [(&str, &str, &str); 10] = [
+    ("+=", "AddAssign", "add_assign"),
+    ("-=", "SubAssign", "sub_assign"),
+    ("*=", "MulAssign", "mul_assign"),
+    ("/=", "DivAssign", "div_assign"),
+    ("%=", "RemAssign", "rem_assign"),
+    ("&=", "BitAndAssign", "bitand_assign"),
+    ("|=", "BitOrAssign", "bitor_assign"),
+    ("^=", "BitXorAssign", "bitxor_assign"),
+    ("<<=", "ShlAssign", "shl_assign"),
+    (">>=", "ShrAssign", "shr_assign"),
+];
+
+/// A C++ type with a compound assignment operator, such as `operator+=`,
+/// which should implement the corresponding Rust trait. `rhs` is the C++
+/// type of the right hand side.
+#[derive(Debug, Clone, Hash)]
+pub struct CompoundAssign {
+    pub ty: String,
+    pub operator: String,
+    pub rhs: String,
+}
+
+impl CompoundAssign {
+    /// The Rust trait implemented using this operator, and its method.
+    pub fn rust_trait(&self) -> (&'static str, &'static str) {
+        COMPOUND_ASSIGNMENT_OPERATORS
+            .iter()
+            .find(|(operator, _, _)| *operator == self.operator)
+            .map(|(_, tr, method)| (*tr, *method))
+            .expect("operator was checked when parsing")
+    }
+}
+
+/// A C++ type with a `const` `operator[]`, which should implement Rust's
+/// `Index` trait. `index` is the C++ type of the subscript, and `output`
+/// the type of the element to which `operator[]` returns a reference.
+#[derive(Debug, Clone, Hash)]
+pub struct Subscript {
+    pub ty: String,
+    pub index: String,
+    pub output: String,
+}
+
+/// A C++ type, usually a smart pointer, with an `operator*` which should
+/// implement Rust's `Deref` trait. `target` is the C++ type to which it
+/// returns a reference, and if `mutable` the non-`const` `operator*`
+/// should implement `DerefMut` too.
+#[derive(Debug, Clone, Hash)]
+pub struct DerefTarget {
+    pub ty: String,
+    pub target: String,
+    pub mutable: bool,
+}
+
+/// A C++ enum whose values are combinations of flags, and whether
+/// values returned from C++ may contain bits which aren't one of
+/// its enumerators.
+#[derive(Debug, Clone, Hash)]
+pub struct BitmaskEnum {
+    pub ty: String,
+    pub reject_unknown_bits: bool,
+}
+
+/// A C++ enum which implements Rust's `Display` using `function`, which
+/// returns the name of a value as a `const char*`.
+#[derive(Debug, Clone, Hash)]
+pub struct EnumDisplay {
+    pub ty: String,
+    pub function: String,
+}
+
+/// A header named by an `#include` directive. It's `#include`d in quotes,
+/// like `#include "foo.h"`, unless it was written with angle brackets, like
+/// `#include <mylib/foo.h>`, in which case it's `system` and is `#include`d
+/// with them too, so C++ only looks for it on the include path.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct IncludedHeader {
+    pub path: String,
+    pub system: bool,
+}
+
+impl IncludedHeader {
+    /// The C++ `#include` statement for this header.
+    pub fn include_stmt(&self) -> String {
+        if self.system {
+            format!("#include <{}>", self.path)
+        } else {
+            format!("#include \"{}\"", self.path)
+        }
+    }
+}
+
+/// The header as it's written after `#include`, and also as cxx's
+/// `include!` expects it.
+impl ToTokens for IncludedHeader {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        if self.system {
+            let path: proc_macro2::TokenStream = self
+                .path
+                .parse()
+                .expect("header path was originally parsed from tokens");
+            tokens.extend(quote! { < #path > })
+        } else {
+            self.path.to_tokens(tokens)
+        }
+    }
+}
+
+/// A C++ type `ty` which keeps its own reference count, so is held by an
+/// intrusive smart pointer such as `boost::intrusive_ptr`. Rust holds it
+/// in an `autocxx::IntrusivePtr` wherever C++ uses the `pointer` template,
+/// calling `add_ref` and `release` to adjust the count.
+#[derive(Debug, Clone, Hash)]
+pub struct IntrusivePtrType {
+    pub ty: String,
+    pub pointer: String,
+    pub add_ref: String,
+    pub release: String,
+}
+
+/// An integer parameter of a C++ function which is really a set of flags
+/// from the `bitmask_enum!` `ty`, so which Rust passes as one.
+#[derive(Debug, Clone, Hash)]
+pub struct BitmaskParam {
+    pub function: String,
+    pub param: String,
+    pub ty: String,
+}
+
+/// A by-value `std::array` parameter, or the return value if `param` is
+/// `return`, of a C++ function, which Rust passes as a `[T; len]` of the
+/// built-in `element_type`.
+#[derive(Debug, Clone, Hash)]
+pub struct StdArray {
+    pub function: String,
+    pub param: String,
+    pub element_type: String,
+    pub len: usize,
+}
+
+/// A `std::span` parameter of a C++ function, which Rust passes as a
+/// `&mut [T]` of `element_type`, or a `&[T]` if the span's elements are
+/// `const`.
+#[derive(Debug, Clone, Hash)]
+pub struct StdSpan {
+    pub function: String,
+    pub param: String,
+    pub element_type: String,
+    pub is_const: bool,
+}
+
+/// A pair of adjacent pointer parameters of a C++ function giving the
+/// beginning and end of a range, which Rust passes as a single slice.
+#[derive(Debug, Clone, Hash)]
+pub struct IteratorRange {
+    pub function: String,
+    pub begin: String,
+    pub end: String,
+}
+
+/// A C++ function template listed in `template_returns!`, which takes no
+/// parameters, and the types with which to instantiate it.
+#[derive(Debug, Clone, Hash)]
+pub struct TemplateReturn {
+    pub function: String,
+    pub types: Vec<String>,
+}
+
+impl TemplateReturn {
+    /// The qualified name of the function which calls the template
+    /// instantiated with `ty`. That's the template's own name, followed by
+    /// the name of the type with each run of characters which can't appear
+    /// in an identifier replaced by an underscore, so `get<std::string>`
+    /// becomes `get_std_string`.
+    pub fn instance_name(&self, ty: &str) -> String {
+        let suffix = ty
+            .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .filter(|part| !part.is_empty())
+            .join("_");
+        format!("{}_{suffix}", self.function)
+    }
+
+    /// Each type, along with the qualified name of the function which
+    /// calls the template instantiated with it.
+    pub fn instances(&self) -> impl Iterator<Item = (&str, String)> + '_ {
+        self.types
+            .iter()
+            .map(|ty| (ty.as_str(), self.instance_name(ty)))
+    }
+}
+
+/// What a function listed in `null_vector!` should give Rust if it
+/// returns a null `std::unique_ptr<std::vector<T>>`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum NullVectorPolicy {
+    /// Return an `Option`, which is `None` if the pointer is null.
+    AsNone,
+    /// Return a pointer to a new, empty vector instead.
+    AsEmpty,
+}
+
+/// A C++ function returning a `std::unique_ptr<std::vector<T>>` which
+/// may be null, and what to do if it is.
+#[derive(Debug, Clone, Hash)]
+pub struct NullVector {
+    pub function: String,
+    pub policy: NullVectorPolicy,
+}
+
+/// A C++ function which returns an integer, using `sentinel` to mean that
+/// there's no value.
+#[derive(Debug, Clone, Hash)]
+pub struct NoneSentinel {
+    pub function: String,
+    pub sentinel: i128,
+}
+
+/// What a function listed in `seconds_count!` should give Rust for the
+/// `std::chrono::duration` it returns.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum SecondsCountPolicy {
+    /// Return the count of seconds as an `f64`.
+    AsF64,
+    /// Return a `Result<std::time::Duration, TryFromFloatSecsError>`,
+    /// which is an error if the duration is negative or too long.
+    AsDuration,
+}
+
+/// A C++ function returning a `std::chrono::duration` which Rust gets as a
+/// number of seconds, and what sort of number it should be.
+#[derive(Debug, Clone, Hash)]
+pub struct SecondsCount {
+    pub function: String,
+    pub policy: SecondsCountPolicy,
+}
+
+/// A C++ function returning a `std::vector<std::string>` which is copied
+/// into a Rust `Vec` of strings, and what to do with any element which
+/// isn't valid UTF-8. If there's no `policy`, that of
+/// `rust_string_returns!` applies.
+#[derive(Debug, Clone, Hash)]
+pub struct StringVec {
+    pub function: String,
+    pub policy: Option<Utf8Policy>,
+}
+
+/// How the forms of a function listed in `default_args!` are named.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Default)]
+pub enum DefaultArgNaming {
+    /// The full form keeps the function's name, and the shorter forms are
+    /// numbered like any other overloads.
+    #[default]
+    Overloads,
+    /// The shortest form keeps the function's name, and each longer form
+    /// is named after the last parameter it adds, as `name_with_param`.
+    WithParams,
+}
+
+/// A C++ function whose last `count` parameters have default arguments,
+/// so which can also be called without them.
+#[derive(Debug, Clone, Hash)]
+pub struct DefaultArgs {
+    pub function: String,
+    pub count: usize,
+    pub naming: DefaultArgNaming,
+}
+
+/// A C++ function whose final parameter is a comparator of
+/// `element_type`s, which Rust provides as a closure.
+#[derive(Debug, Clone, Hash)]
+pub struct Comparator {
+    pub function: String,
+    pub element_type: String,
+}
+
+/// A C++ function whose final parameter is a callback taking `param_types`
+/// and returning `return_type`, which Rust provides as a closure. If it's
+/// `fallible`, the closure returns a `Result`, and an error becomes a C++
+/// exception thrown by the callback.
+#[derive(Debug, Clone, Hash)]
+pub struct ClosureCallback {
+    pub function: String,
+    pub return_type: String,
+    pub param_types: Vec<String>,
+    pub fallible: bool,
+}
+
+/// A C++ function returning a `std::variant` of two types, which Rust
+/// returns as a `Result` with `error_type` as the error.
+#[derive(Debug, Clone, Hash)]
+pub struct ResultVariant {
+    pub function: String,
+    pub error_type: String,
+}
+
 #[derive(Clone, Hash)]
 pub struct RustFun {
     pub path: RustPath,
@@ -211,7 +633,7 @@ impl std::hash::Hash for ConcretesMap {
 
 #[derive(Debug, Default, Hash)]
 pub struct IncludeCppConfig {
-    pub inclusions: Vec<String>,
+    pub inclusions: Vec<IncludedHeader>,
     pub unsafe_policy: UnsafePolicy,
     pub parse_only: bool,
     pub exclude_impls: bool,
@@ -220,13 +642,78 @@ pub struct IncludeCppConfig {
     pub(crate) blocklist: Vec<String>,
     pub(crate) constructor_blocklist: Vec<String>,
     pub instantiable: Vec<String>,
+    pub(crate) pure_functions: Vec<String>,
+    pub(crate) const_functions: Vec<String>,
+    pub(crate) volatile_methods: Vec<String>,
+    pub(crate) tag_dispatched: Vec<String>,
+    pub(crate) out_param_functions: Vec<String>,
+    pub(crate) nan_rejecting_functions: Vec<String>,
+    pub(crate) integer_enum_param_functions: Vec<String>,
+    pub(crate) map_snapshot_functions: Vec<String>,
+    pub(crate) map_from_iter_functions: Vec<String>,
+    pub(crate) const_pointer_reference_functions: Vec<String>,
+    pub(crate) string_callback_functions: Vec<String>,
+    pub(crate) allocator_callback_functions: Vec<String>,
+    pub(crate) byte_slice_functions: Vec<String>,
+    pub(crate) exception_catching_functions: Vec<String>,
+    pub(crate) slice_functions: Vec<String>,
+    pub(crate) output_buffer_functions: Vec<String>,
+    pub(crate) cstr_return_functions: Vec<String>,
+    pub(crate) nullable_return_functions: Vec<String>,
+    pub(crate) mutable_shared_ptr_return_functions: Vec<String>,
+    pub(crate) cstr_string_return_functions: Vec<String>,
+    pub(crate) str_param_functions: Vec<String>,
+    pub(crate) cstr_param_functions: Vec<String>,
+    pub(crate) vector_slice_functions: Vec<String>,
+    pub(crate) receiver_borrowing_functions: Vec<String>,
+    pub(crate) tuple_array_functions: Vec<String>,
+    pub(crate) pair_tuple_functions: Vec<String>,
+    pub(crate) nanoseconds_count_functions: Vec<String>,
+    pub(crate) trait_probe_types: Vec<String>,
+    pub(crate) ordered_types: Vec<String>,
+    pub(crate) partial_eq_types: Vec<String>,
+    pub(crate) partial_ord_types: Vec<String>,
+    pub(crate) display_types: Vec<String>,
+    pub(crate) move_out_of_vector_types: Vec<String>,
+    pub(crate) layout_types: Vec<String>,
+    pub(crate) cpp_preludes: Vec<String>,
     pub(crate) exclude_utilities: bool,
     pub(crate) mod_name: Option<Ident>,
     pub rust_types: Vec<RustPath>,
     pub subclasses: Vec<Subclass>,
+    pub(crate) dyn_subclasses: Vec<Ident>,
     pub extern_rust_funs: Vec<RustFun>,
     pub concretes: ConcretesMap,
+    pub iterables: Vec<Iterable>,
+    pub operator_bools: Vec<OperatorBool>,
+    pub operator_nots: Vec<OperatorNot>,
+    pub compound_assigns: Vec<CompoundAssign>,
+    pub subscripts: Vec<Subscript>,
+    pub deref_targets: Vec<DerefTarget>,
+    pub bitmask_enums: Vec<BitmaskEnum>,
+    pub enum_displays: Vec<EnumDisplay>,
+    pub intrusive_ptrs: Vec<IntrusivePtrType>,
+    pub bitmask_params: Vec<BitmaskParam>,
+    pub std_arrays: Vec<StdArray>,
+    pub std_spans: Vec<StdSpan>,
+    pub iterator_ranges: Vec<IteratorRange>,
+    pub template_returns: Vec<TemplateReturn>,
+    pub null_vectors: Vec<NullVector>,
+    pub none_sentinels: Vec<NoneSentinel>,
+    pub seconds_counts: Vec<SecondsCount>,
+    pub string_vecs: Vec<StringVec>,
+    pub default_args: Vec<DefaultArgs>,
+    pub comparators: Vec<Comparator>,
+    pub closure_callbacks: Vec<ClosureCallback>,
+    pub result_variants: Vec<ResultVariant>,
     pub externs: ExternCppTypeMap,
+    /// If set, `std::string`s returned by value are converted into
+    /// Rust `String`s according to this policy, rather than being
+    /// returned as `UniquePtr<CxxString>`.
+    pub string_return_policy: Option<Utf8Policy>,
+    /// How `std::u16string`s returned by value are converted into Rust
+    /// `String`s.
+    pub u16string_return_policy: Utf16Policy,
 }
 
 impl Parse for IncludeCppConfig {
@@ -365,6 +852,7 @@ impl IncludeCppConfig {
             || self.is_rust_fun(cpp_name)
             || self.is_rust_type_name(cpp_name)
             || self.is_concrete_type(cpp_name)
+            || self.is_template_return_instance(cpp_name)
             || match &self.allowlist {
                 Allowlist::Unspecified(_) => panic!("Eek no allowlist yet"),
                 Allowlist::All => true,
@@ -383,6 +871,326 @@ impl IncludeCppConfig {
         self.constructor_blocklist.contains(&cpp_name.to_string())
     }
 
+    /// Whether this function was listed in `pure_functions!`.
+    pub fn is_pure_function(&self, cpp_name: &str) -> bool {
+        self.pure_functions.contains(&cpp_name.to_string())
+    }
+
+    /// Whether this function was listed in `const_functions!`.
+    pub fn is_const_function(&self, cpp_name: &str) -> bool {
+        self.const_functions.contains(&cpp_name.to_string())
+    }
+
+    /// Whether this method was listed in `volatile_methods!`.
+    pub fn has_volatile_receiver(&self, cpp_name: &str) -> bool {
+        self.volatile_methods.contains(&cpp_name.to_string())
+    }
+
+    pub fn is_tag_dispatched(&self, cpp_name: &str) -> bool {
+        self.tag_dispatched.contains(&cpp_name.to_string())
+    }
+
+    pub fn is_out_param_function(&self, cpp_name: &str) -> bool {
+        self.out_param_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn rejects_nan(&self, cpp_name: &str) -> bool {
+        self.nan_rejecting_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn takes_enums_as_integers(&self, cpp_name: &str) -> bool {
+        self.integer_enum_param_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn is_map_snapshot_function(&self, cpp_name: &str) -> bool {
+        self.map_snapshot_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn is_map_from_iter_function(&self, cpp_name: &str) -> bool {
+        self.map_from_iter_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn takes_const_pointers_as_references(&self, cpp_name: &str) -> bool {
+        self.const_pointer_reference_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn is_string_callback_function(&self, cpp_name: &str) -> bool {
+        self.string_callback_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn is_allocator_callback_function(&self, cpp_name: &str) -> bool {
+        self.allocator_callback_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn takes_byte_slices(&self, cpp_name: &str) -> bool {
+        self.byte_slice_functions.contains(&cpp_name.to_string())
+    }
+
+    /// The pairs of parameters of this function listed in
+    /// `iterator_ranges!`.
+    pub fn get_iterator_ranges<'a>(
+        &'a self,
+        cpp_name: &'a str,
+    ) -> impl Iterator<Item = &'a IteratorRange> {
+        self.iterator_ranges
+            .iter()
+            .filter(move |ir| ir.function == cpp_name)
+    }
+
+    pub fn catches_exceptions(&self, cpp_name: &str) -> bool {
+        self.exception_catching_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn takes_slices(&self, cpp_name: &str) -> bool {
+        self.slice_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn writes_output_buffers(&self, cpp_name: &str) -> bool {
+        self.output_buffer_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn returns_cstr(&self, cpp_name: &str) -> bool {
+        self.cstr_return_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn returns_nullable(&self, cpp_name: &str) -> bool {
+        self.nullable_return_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn returns_mutable_shared_ptr(&self, cpp_name: &str) -> bool {
+        self.mutable_shared_ptr_return_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn returns_cstr_as_string(&self, cpp_name: &str) -> bool {
+        self.cstr_string_return_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn takes_str_params(&self, cpp_name: &str) -> bool {
+        self.str_param_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn takes_cstr_params(&self, cpp_name: &str) -> bool {
+        self.cstr_param_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn returns_vector_slice(&self, cpp_name: &str) -> bool {
+        self.vector_slice_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn borrows_from_receiver(&self, cpp_name: &str) -> bool {
+        self.receiver_borrowing_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn returns_tuple_as_array(&self, cpp_name: &str) -> bool {
+        self.tuple_array_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn returns_pair_as_tuple(&self, cpp_name: &str) -> bool {
+        self.pair_tuple_functions.contains(&cpp_name.to_string())
+    }
+
+    pub fn returns_nanoseconds_count(&self, cpp_name: &str) -> bool {
+        self.nanoseconds_count_functions
+            .contains(&cpp_name.to_string())
+    }
+
+    /// How to return the `std::chrono::duration` which this function
+    /// returns, if it's listed in `seconds_count!`.
+    pub fn get_seconds_count_policy(&self, cpp_name: &str) -> Option<SecondsCountPolicy> {
+        self.seconds_counts
+            .iter()
+            .find(|sc| sc.function == cpp_name)
+            .map(|sc| sc.policy)
+    }
+
+    /// Whether this subclass was requested using `dyn_subclass!`, so that
+    /// autocxx generates the Rust struct itself.
+    pub fn is_dyn_subclass(&self, subclass: &str) -> bool {
+        self.dyn_subclasses.iter().any(|id| id == subclass)
+    }
+
+    pub fn is_bitmask_enum(&self, cpp_name: &str) -> bool {
+        self.bitmask_enums.iter().any(|be| be.ty == cpp_name)
+    }
+
+    pub fn rejects_unknown_bits(&self, cpp_name: &str) -> bool {
+        self.bitmask_enums
+            .iter()
+            .any(|be| be.ty == cpp_name && be.reject_unknown_bits)
+    }
+
+    /// How to hold this type with an intrusive pointer, if it's listed in
+    /// `intrusive_ptr!`.
+    pub fn get_intrusive_ptr(&self, cpp_name: &str) -> Option<&IntrusivePtrType> {
+        self.intrusive_ptrs.iter().find(|ip| ip.ty == cpp_name)
+    }
+
+    /// The C++ function which names the values of this enum, if it's listed
+    /// in `enum_display!`.
+    pub fn get_enum_display_function(&self, cpp_name: &str) -> Option<&str> {
+        self.enum_displays
+            .iter()
+            .find(|ed| ed.ty == cpp_name)
+            .map(|ed| ed.function.as_str())
+    }
+
+    /// The `bitmask_enum!` which this integer parameter of this function
+    /// should take instead, if it's listed in `bitmask_params!`.
+    pub fn get_bitmask_param_type(&self, cpp_name: &str, param: &str) -> Option<&str> {
+        self.bitmask_params
+            .iter()
+            .find(|bp| bp.function == cpp_name && bp.param == param)
+            .map(|bp| bp.ty.as_str())
+    }
+
+    /// The element type and length of the `std::array` which this
+    /// parameter of this function takes, or which it returns if `param` is
+    /// `return`, if it's listed in `std_array!`.
+    pub fn get_std_array(&self, cpp_name: &str, param: &str) -> Option<&StdArray> {
+        self.std_arrays
+            .iter()
+            .find(|sa| sa.function == cpp_name && sa.param == param)
+    }
+
+    /// The element type of the `std::span` which this parameter of this
+    /// function takes, if it's listed in `std_span!`.
+    pub fn get_std_span(&self, cpp_name: &str, param: &str) -> Option<&StdSpan> {
+        self.std_spans
+            .iter()
+            .find(|ss| ss.function == cpp_name && ss.param == param)
+    }
+
+    /// The names of the parameters of this function listed in `std_span!`.
+    pub fn get_std_span_params<'a>(&'a self, cpp_name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.std_spans
+            .iter()
+            .filter(move |ss| ss.function == cpp_name)
+            .map(|ss| ss.param.as_str())
+    }
+
+    /// How many trailing parameters of this function have default
+    /// arguments which C++ may fill in.
+    pub fn get_default_arg_count(&self, cpp_name: &str) -> usize {
+        self.default_args
+            .iter()
+            .find(|da| da.function == cpp_name)
+            .map(|da| da.count)
+            .unwrap_or_default()
+    }
+
+    /// How the forms of this function with and without its default
+    /// arguments are named.
+    pub fn get_default_arg_naming(&self, cpp_name: &str) -> DefaultArgNaming {
+        self.default_args
+            .iter()
+            .find(|da| da.function == cpp_name)
+            .map(|da| da.naming)
+            .unwrap_or_default()
+    }
+
+    /// The C++ type compared by this function's comparator parameter, if
+    /// it has one.
+    pub fn get_comparator_element_type(&self, cpp_name: &str) -> Option<&str> {
+        self.comparators
+            .iter()
+            .find(|c| c.function == cpp_name)
+            .map(|c| c.element_type.as_str())
+    }
+
+    /// The signature of this function's callback parameter, if it's listed
+    /// in `closure_callback!`.
+    pub fn get_closure_callback(&self, cpp_name: &str) -> Option<&ClosureCallback> {
+        self.closure_callbacks
+            .iter()
+            .find(|cc| cc.function == cpp_name)
+    }
+
+    /// The alternative of a returned `std::variant` which this function
+    /// uses to report errors, if it's listed in `result_variant!`.
+    pub fn get_result_variant_error_type(&self, cpp_name: &str) -> Option<&str> {
+        self.result_variants
+            .iter()
+            .find(|rv| rv.function == cpp_name)
+            .map(|rv| rv.error_type.as_str())
+    }
+
+    /// The value which means "none" when returned by this function, if
+    /// it's listed in `sentinel_none!`.
+    pub fn get_none_sentinel(&self, cpp_name: &str) -> Option<i128> {
+        self.none_sentinels
+            .iter()
+            .find(|ns| ns.function == cpp_name)
+            .map(|ns| ns.sentinel)
+    }
+
+    pub fn get_null_vector_policy(&self, cpp_name: &str) -> Option<NullVectorPolicy> {
+        self.null_vectors
+            .iter()
+            .find(|nv| nv.function == cpp_name)
+            .map(|nv| nv.policy)
+    }
+
+    /// How to treat elements which aren't valid UTF-8 in the
+    /// `std::vector<std::string>` returned by this function, if it's listed
+    /// in `string_vec!`.
+    pub fn get_string_vec_policy(&self, cpp_name: &str) -> Option<Utf8Policy> {
+        self.string_vecs
+            .iter()
+            .find(|sv| sv.function == cpp_name)
+            .map(|sv| sv.policy.or(self.string_return_policy).unwrap_or_default())
+    }
+
+    pub fn is_ordered(&self, cpp_name: &str) -> bool {
+        self.ordered_types.contains(&cpp_name.to_string())
+    }
+
+    /// Whether a type is listed in `partial_ord!`. Types which are also
+    /// listed in `ord!` get their `PartialOrd` from that instead.
+    pub fn is_partial_ord(&self, cpp_name: &str) -> bool {
+        self.partial_ord_types.contains(&cpp_name.to_string()) && !self.is_ordered(cpp_name)
+    }
+
+    /// Whether a type is listed in `partial_eq!`, or in `partial_ord!`,
+    /// which also needs `PartialEq`. Types which are also listed in `ord!`
+    /// get their `PartialEq` from that instead.
+    pub fn is_partial_eq(&self, cpp_name: &str) -> bool {
+        (self.partial_eq_types.contains(&cpp_name.to_string()) || self.is_partial_ord(cpp_name))
+            && !self.is_ordered(cpp_name)
+    }
+
+    /// Whether a type is listed in `display!`.
+    pub fn is_display(&self, cpp_name: &str) -> bool {
+        self.display_types.contains(&cpp_name.to_string())
+    }
+
+    /// The types listed in `probe_traits!`, in order.
+    pub fn trait_probe_types(&self) -> &[String] {
+        &self.trait_probe_types
+    }
+
+    /// The snippets of C++ given in `cpp_prelude!`, in order.
+    pub fn cpp_preludes(&self) -> &[String] {
+        &self.cpp_preludes
+    }
+
+    pub fn is_move_out_of_vector_type(&self, cpp_name: &str) -> bool {
+        self.move_out_of_vector_types
+            .contains(&cpp_name.to_string())
+    }
+
+    pub fn is_layout_type(&self, cpp_name: &str) -> bool {
+        self.layout_types.contains(&cpp_name.to_string())
+    }
+
     pub fn get_blocklist(&self) -> impl Iterator<Item = &String> {
         self.blocklist.iter()
     }
@@ -391,6 +1199,13 @@ impl IncludeCppConfig {
         self.concretes.0.values().any(|val| *val == cpp_name)
     }
 
+    fn is_template_return_instance(&self, cpp_name: &str) -> bool {
+        self.template_returns
+            .iter()
+            .flat_map(TemplateReturn::instances)
+            .any(|(_, name)| name == cpp_name)
+    }
+
     /// Get a hash of the contents of this `include_cpp!` block.
     pub fn get_hash(&self) -> u64 {
         let mut s = DefaultHasher::new();
@@ -409,6 +1224,54 @@ impl IncludeCppConfig {
         self.uniquify_name_per_mod("autocxx_make_string")
     }
 
+    pub fn get_makewstring_name(&self) -> String {
+        self.uniquify_name_per_mod("autocxx_make_wstring")
+    }
+
+    pub fn get_wstring_to_string_name(&self) -> String {
+        self.uniquify_name_per_mod("autocxx_wstring_to_string")
+    }
+
+    /// The name of the C++ alias for `std::wstring` by which the cxx
+    /// bridge knows it. It differs per mod so that each mod's bridge can
+    /// have its own `UniquePtr` machinery for it.
+    pub fn get_wstring_alias_name(&self) -> String {
+        self.uniquify_name_per_mod("autocxx_wstring")
+    }
+
+    /// The name of the C++ type holding the state of a generated
+    /// iterator, which is also used as a prefix for the C++ functions
+    /// manipulating that state.
+    pub fn get_iterator_state_name(&self, iterator_name: &str) -> String {
+        self.uniquify_name_per_mod(&format!("{iterator_name}_AutocxxIter"))
+    }
+
+    /// Get the name of the C++ function used to read a constant, which
+    /// is also the name by which it's known in the cxx bridge.
+    pub fn get_constant_accessor_name(&self, constant_name: &str) -> String {
+        self.uniquify_name_per_mod(&format!("{constant_name}_AutocxxConstant"))
+    }
+
+    /// Get the name of a C++ function giving access to the elements of a
+    /// `std::vector` of `std::unique_ptr`s, such as its length.
+    pub fn get_unique_ptr_vector_accessor_name(&self, vector_name: &str, accessor: &str) -> String {
+        self.uniquify_name_per_mod(&format!("{vector_name}_{accessor}"))
+    }
+
+    pub fn get_map_snapshot_name(&self, map_name: &str) -> String {
+        self.uniquify_name_per_mod(&format!("{map_name}_snapshot"))
+    }
+
+    pub fn get_map_builder_name(&self, map_name: &str, builder: &str) -> String {
+        self.uniquify_name_per_mod(&format!("{map_name}_{builder}"))
+    }
+
+    /// Get the name of a C++ function giving access to the entries of a
+    /// `std::unordered_map`, such as its number of buckets.
+    pub fn get_unordered_map_accessor_name(&self, map_name: &str, accessor: &str) -> String {
+        self.uniquify_name_per_mod(&format!("{map_name}_{accessor}"))
+    }
+
     pub fn is_rust_type(&self, id: &Ident) -> bool {
         let id_string = id.to_string();
         self.is_rust_type_name(&id_string) || self.is_subclass_holder(&id_string)
@@ -466,7 +1329,10 @@ impl IncludeCppConfig {
     /// preprocessed replacement.
     pub fn replace_included_headers(&mut self, replacement: &str) {
         self.inclusions.clear();
-        self.inclusions.push(replacement.to_string());
+        self.inclusions.push(IncludedHeader {
+            path: replacement.to_string(),
+            system: false,
+        });
     }
 }
 
@@ -496,7 +1362,8 @@ impl ToTokens for IncludeCppConfig {
 
 #[cfg(test)]
 mod parse_tests {
-    use crate::config::UnsafePolicy;
+    use crate::config::{IncludeCppConfig, UnsafePolicy, Utf16Policy, Utf8Policy};
+    use quote::quote;
     use syn::parse_quote;
     #[test]
     fn test_safety_unsafe() {
@@ -519,4 +1386,96 @@ mod parse_tests {
         let us: UnsafePolicy = parse_quote! {};
         assert_eq!(us, UnsafePolicy::AllFunctionsUnsafe)
     }
+
+    #[test]
+    fn test_utf8_policy() {
+        let p: Utf8Policy = parse_quote! {};
+        assert_eq!(p, Utf8Policy::Checked);
+        let p: Utf8Policy = parse_quote! {
+            lossy
+        };
+        assert_eq!(p, Utf8Policy::Lossy);
+        let p: Utf8Policy = parse_quote! {
+            assume_valid
+        };
+        assert_eq!(p, Utf8Policy::AssumeValid);
+    }
+
+    #[test]
+    fn test_utf16_policy() {
+        let p: Utf16Policy = parse_quote! {};
+        assert_eq!(p, Utf16Policy::Lossy);
+        let p: Utf16Policy = parse_quote! {
+            checked
+        };
+        assert_eq!(p, Utf16Policy::Checked);
+    }
+
+    #[test]
+    fn test_inclusions() {
+        // `#include` can't be written inside `parse_quote!`.
+        let config: IncludeCppConfig = syn::parse_str(
+            r#"
+                #include "foo.h"
+                #include <mylib/foo-bar.h>
+                generate!("Foo")
+            "#,
+        )
+        .unwrap();
+        let stmts: Vec<_> = config
+            .inclusions
+            .iter()
+            .map(|inc| inc.include_stmt())
+            .collect();
+        assert_eq!(stmts, ["#include \"foo.h\"", "#include <mylib/foo-bar.h>"]);
+        assert!(!config.inclusions[0].system);
+        assert!(config.inclusions[1].system);
+        assert!(syn::parse_str::<IncludeCppConfig>("#include <foo(1).h>").is_err());
+    }
+
+    #[test]
+    fn test_sentinel_range() {
+        let config: IncludeCppConfig = parse_quote! {
+            sentinel_none!("find_index", -1)
+            sentinel_none!("first_free_slot", 18446744073709551615)
+        };
+        assert_eq!(config.get_none_sentinel("find_index"), Some(-1));
+        assert!(syn::parse_str::<IncludeCppConfig>(
+            r#"sentinel_none!("find_index", 18446744073709551616)"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_operator_not_method_name() {
+        let config: IncludeCppConfig = parse_quote! {
+            operator_not!("Stream", failed)
+        };
+        assert_eq!(config.operator_nots[0].method, "failed");
+        assert!(syn::parse_str::<IncludeCppConfig>(r#"operator_not!("Stream", not)"#).is_err());
+    }
+
+    #[test]
+    fn test_template_return_names() {
+        let config: IncludeCppConfig = parse_quote! {
+            template_returns!("config::get", "int32_t", "std::string", "unsigned long")
+        };
+        let names: Vec<_> = config.template_returns[0]
+            .instances()
+            .map(|(_, name)| name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "config::get_int32_t",
+                "config::get_std_string",
+                "config::get_unsigned_long"
+            ]
+        );
+        assert!(config.is_on_allowlist("config::get_std_string"));
+        let clash = syn::parse2::<IncludeCppConfig>(quote! {
+            template_returns!("get", "a::b", "a_b")
+        });
+        assert!(clash.is_err());
+    }
 }
@@ -16,6 +16,7 @@ use proc_macro2::TokenStream;
 
 #[cfg(feature = "reproduction_case")]
 use quote::{quote, ToTokens};
+use syn::ext::IdentExt;
 use syn::parse::ParseStream;
 
 use crate::config::AllowlistErr;
@@ -24,7 +25,10 @@ use crate::config::AllowlistErr;
 use crate::config::Allowlist;
 
 use crate::directive_names::{EXTERN_RUST_FUN, EXTERN_RUST_TYPE, SUBCLASS};
-use crate::{AllowlistEntry, IncludeCppConfig};
+use crate::{
+    AllowlistEntry, DefaultArgNaming, IncludeCppConfig, IncludedHeader, NullVectorPolicy,
+    SecondsCountPolicy,
+};
 use crate::{ParseResult, RustFun, RustPath};
 
 pub(crate) struct DirectivesMap {
@@ -44,6 +48,8 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
         need_exclamation.insert("generate_ns".into(), Box::new(GenerateNs));
         need_exclamation.insert("generate_all".into(), Box::new(GenerateAll));
         need_exclamation.insert("safety".into(), Box::new(Safety));
+        need_exclamation.insert("rust_string_returns".into(), Box::new(RustStringReturns));
+        need_exclamation.insert("u16string_returns".into(), Box::new(U16StringReturns));
         need_exclamation.insert(
             "pod".into(),
             Box::new(StringList(
@@ -72,6 +78,251 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.instantiable,
             )),
         );
+        need_exclamation.insert(
+            "pure_functions".into(),
+            Box::new(StringList(
+                |config| &mut config.pure_functions,
+                |config| &config.pure_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "const_functions".into(),
+            Box::new(StringList(
+                |config| &mut config.const_functions,
+                |config| &config.const_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "volatile_methods".into(),
+            Box::new(StringList(
+                |config| &mut config.volatile_methods,
+                |config| &config.volatile_methods,
+            )),
+        );
+        need_exclamation.insert(
+            "tag_dispatch".into(),
+            Box::new(StringList(
+                |config| &mut config.tag_dispatched,
+                |config| &config.tag_dispatched,
+            )),
+        );
+        need_exclamation.insert(
+            "out_params".into(),
+            Box::new(StringList(
+                |config| &mut config.out_param_functions,
+                |config| &config.out_param_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "reject_nan".into(),
+            Box::new(StringList(
+                |config| &mut config.nan_rejecting_functions,
+                |config| &config.nan_rejecting_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "integer_enum_params".into(),
+            Box::new(StringList(
+                |config| &mut config.integer_enum_param_functions,
+                |config| &config.integer_enum_param_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "map_snapshot".into(),
+            Box::new(StringList(
+                |config| &mut config.map_snapshot_functions,
+                |config| &config.map_snapshot_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "map_from_iter".into(),
+            Box::new(StringList(
+                |config| &mut config.map_from_iter_functions,
+                |config| &config.map_from_iter_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "const_pointers_as_references".into(),
+            Box::new(StringList(
+                |config| &mut config.const_pointer_reference_functions,
+                |config| &config.const_pointer_reference_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "string_callback".into(),
+            Box::new(StringList(
+                |config| &mut config.string_callback_functions,
+                |config| &config.string_callback_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "allocator_callback".into(),
+            Box::new(StringList(
+                |config| &mut config.allocator_callback_functions,
+                |config| &config.allocator_callback_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "byte_slices".into(),
+            Box::new(StringList(
+                |config| &mut config.byte_slice_functions,
+                |config| &config.byte_slice_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "catch_exceptions".into(),
+            Box::new(StringList(
+                |config| &mut config.exception_catching_functions,
+                |config| &config.exception_catching_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "slices".into(),
+            Box::new(StringList(
+                |config| &mut config.slice_functions,
+                |config| &config.slice_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "output_buffers".into(),
+            Box::new(StringList(
+                |config| &mut config.output_buffer_functions,
+                |config| &config.output_buffer_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "cstr_returns".into(),
+            Box::new(StringList(
+                |config| &mut config.cstr_return_functions,
+                |config| &config.cstr_return_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "nullable_returns".into(),
+            Box::new(StringList(
+                |config| &mut config.nullable_return_functions,
+                |config| &config.nullable_return_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "mutable_shared_ptr_returns".into(),
+            Box::new(StringList(
+                |config| &mut config.mutable_shared_ptr_return_functions,
+                |config| &config.mutable_shared_ptr_return_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "cstr_string_returns".into(),
+            Box::new(StringList(
+                |config| &mut config.cstr_string_return_functions,
+                |config| &config.cstr_string_return_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "str_params".into(),
+            Box::new(StringList(
+                |config| &mut config.str_param_functions,
+                |config| &config.str_param_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "cstr_params".into(),
+            Box::new(StringList(
+                |config| &mut config.cstr_param_functions,
+                |config| &config.cstr_param_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "vector_slices".into(),
+            Box::new(StringList(
+                |config| &mut config.vector_slice_functions,
+                |config| &config.vector_slice_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "borrow_from_receiver".into(),
+            Box::new(StringList(
+                |config| &mut config.receiver_borrowing_functions,
+                |config| &config.receiver_borrowing_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "probe_traits".into(),
+            Box::new(StringList(
+                |config| &mut config.trait_probe_types,
+                |config| &config.trait_probe_types,
+            )),
+        );
+        need_exclamation.insert(
+            "cpp_prelude".into(),
+            Box::new(StringList(
+                |config| &mut config.cpp_preludes,
+                |config| &config.cpp_preludes,
+            )),
+        );
+        need_exclamation.insert(
+            "pair_tuple".into(),
+            Box::new(StringList(
+                |config| &mut config.pair_tuple_functions,
+                |config| &config.pair_tuple_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "tuple_array".into(),
+            Box::new(StringList(
+                |config| &mut config.tuple_array_functions,
+                |config| &config.tuple_array_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "nanoseconds_count".into(),
+            Box::new(StringList(
+                |config| &mut config.nanoseconds_count_functions,
+                |config| &config.nanoseconds_count_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "ord".into(),
+            Box::new(StringList(
+                |config| &mut config.ordered_types,
+                |config| &config.ordered_types,
+            )),
+        );
+        need_exclamation.insert(
+            "partial_ord".into(),
+            Box::new(StringList(
+                |config| &mut config.partial_ord_types,
+                |config| &config.partial_ord_types,
+            )),
+        );
+        need_exclamation.insert(
+            "partial_eq".into(),
+            Box::new(StringList(
+                |config| &mut config.partial_eq_types,
+                |config| &config.partial_eq_types,
+            )),
+        );
+        need_exclamation.insert(
+            "display".into(),
+            Box::new(StringList(
+                |config| &mut config.display_types,
+                |config| &config.display_types,
+            )),
+        );
+        need_exclamation.insert(
+            "move_out_of_vector".into(),
+            Box::new(StringList(
+                |config| &mut config.move_out_of_vector_types,
+                |config| &config.move_out_of_vector_types,
+            )),
+        );
+        need_exclamation.insert(
+            "expose_layout".into(),
+            Box::new(StringList(
+                |config| &mut config.layout_types,
+                |config| &config.layout_types,
+            )),
+        );
         need_exclamation.insert(
             "parse_only".into(),
             Box::new(BoolFlag(
@@ -95,9 +346,35 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
         );
         need_exclamation.insert("name".into(), Box::new(ModName));
         need_exclamation.insert("concrete".into(), Box::new(Concrete));
+        need_exclamation.insert("iterable".into(), Box::new(Iterable));
+        need_exclamation.insert("null_vector".into(), Box::new(NullVector));
+        need_exclamation.insert("sentinel_none".into(), Box::new(SentinelNone));
+        need_exclamation.insert("string_vec".into(), Box::new(StringVec));
+        need_exclamation.insert("seconds_count".into(), Box::new(SecondsCount));
+        need_exclamation.insert("default_args".into(), Box::new(DefaultArgs));
+        need_exclamation.insert("comparator".into(), Box::new(Comparator));
+        need_exclamation.insert("closure_callback".into(), Box::new(ClosureCallback));
+        need_exclamation.insert("result_variant".into(), Box::new(ResultVariant));
+        need_exclamation.insert("operator_bool".into(), Box::new(OperatorBool));
+        need_exclamation.insert("operator_not".into(), Box::new(OperatorNot));
+        need_exclamation.insert("compound_assign".into(), Box::new(CompoundAssign));
+        need_exclamation.insert("subscript".into(), Box::new(Subscript));
+        need_exclamation.insert("deref".into(), Box::new(Deref));
+        need_exclamation.insert("bitmask_enum".into(), Box::new(BitmaskEnum));
+        need_exclamation.insert("enum_display".into(), Box::new(EnumDisplay));
+        need_exclamation.insert("intrusive_ptr".into(), Box::new(IntrusivePtr));
+        need_exclamation.insert("bitmask_params".into(), Box::new(BitmaskParams));
+        need_exclamation.insert("std_array".into(), Box::new(StdArray));
+        need_exclamation.insert("std_span".into(), Box::new(StdSpan));
+        need_exclamation.insert("iterator_ranges".into(), Box::new(IteratorRanges));
+        need_exclamation.insert("template_returns".into(), Box::new(TemplateReturns));
         need_exclamation.insert("rust_type".into(), Box::new(RustType { output: false }));
         need_exclamation.insert(EXTERN_RUST_TYPE.into(), Box::new(RustType { output: true }));
-        need_exclamation.insert(SUBCLASS.into(), Box::new(Subclass));
+        need_exclamation.insert(SUBCLASS.into(), Box::new(Subclass { dyn_adapter: false }));
+        need_exclamation.insert(
+            "dyn_subclass".into(),
+            Box::new(Subclass { dyn_adapter: true }),
+        );
         need_exclamation.insert(EXTERN_RUST_FUN.into(), Box::new(ExternRustFun));
         need_exclamation.insert(
             "extern_cpp_type".into(),
@@ -140,8 +417,37 @@ impl Directive for Inclusion {
         config: &mut IncludeCppConfig,
         _span: &Span,
     ) -> ParseResult<()> {
-        let hdr: syn::LitStr = args.parse()?;
-        config.inclusions.push(hdr.value());
+        let hdr = if args.parse::<Option<syn::token::Lt>>()?.is_some() {
+            // Angle brackets aren't a token group, and a header path isn't
+            // Rust syntax, so take it apart just as cxx's `include!` does:
+            // names and numbers, separated by punctuation.
+            let mut path = String::new();
+            while args.parse::<Option<syn::token::Gt>>()?.is_none() {
+                match args.parse()? {
+                    proc_macro2::TokenTree::Ident(id) => path.push_str(&id.to_string()),
+                    proc_macro2::TokenTree::Literal(lit)
+                        if lit.to_string().starts_with(|c: char| c.is_ascii_digit()) =>
+                    {
+                        path.push_str(&lit.to_string())
+                    }
+                    proc_macro2::TokenTree::Punct(punct) => path.push(punct.as_char()),
+                    tt => {
+                        return Err(syn::Error::new(
+                            tt.span(),
+                            "expected a header path within #include <...>",
+                        ))
+                    }
+                }
+            }
+            IncludedHeader { path, system: true }
+        } else {
+            let hdr: syn::LitStr = args.parse()?;
+            IncludedHeader {
+                path: hdr.value(),
+                system: false,
+            }
+        };
+        config.inclusions.push(hdr);
         Ok(())
     }
 
@@ -150,7 +456,7 @@ impl Directive for Inclusion {
         &self,
         config: &'a IncludeCppConfig,
     ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
-        Box::new(config.inclusions.iter().map(|val| quote! { #val }))
+        Box::new(config.inclusions.iter().map(|inc| quote! { #inc }))
     }
 }
 
@@ -289,6 +595,58 @@ impl Directive for Safety {
     }
 }
 
+struct RustStringReturns;
+
+impl Directive for RustStringReturns {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        config.string_return_policy = Some(args.parse()?);
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        match &config.string_return_policy {
+            None => Box::new(std::iter::empty()),
+            Some(policy) => Box::new(std::iter::once(policy.to_token_stream())),
+        }
+    }
+}
+
+struct U16StringReturns;
+
+impl Directive for U16StringReturns {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        config.u16string_return_policy = args.parse()?;
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        let policy = &config.u16string_return_policy;
+        if *policy == Default::default() {
+            Box::new(std::iter::empty())
+        } else {
+            Box::new(std::iter::once(policy.to_token_stream()))
+        }
+    }
+}
+
 fn allowlist_err_to_syn_err(err: AllowlistErr, span: &Span) -> syn::Error {
     syn::Error::new(*span, format!("{err}"))
 }
@@ -415,20 +773,32 @@ impl Directive for Concrete {
     }
 }
 
-struct RustType {
-    #[allow(dead_code)]
-    output: bool,
-}
+struct Iterable;
 
-impl Directive for RustType {
+impl Directive for Iterable {
     fn parse(
         &self,
         args: ParseStream,
         config: &mut IncludeCppConfig,
         _ident_span: &Span,
     ) -> ParseResult<()> {
-        let id: Ident = args.parse()?;
-        config.rust_types.push(RustPath::new_from_ident(id));
+        let container: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let item: syn::LitStr = args.parse()?;
+        let mutable = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let kind: syn::Ident = args.parse()?;
+            if kind != "mutable" {
+                return Err(syn::Error::new(kind.span(), "expected mutable"));
+            }
+            true
+        } else {
+            false
+        };
+        config.iterables.push(crate::config::Iterable {
+            container: container.value(),
+            item: item.value(),
+            mutable,
+        });
         Ok(())
     }
 
@@ -437,29 +807,142 @@ impl Directive for RustType {
         &self,
         config: &'a IncludeCppConfig,
     ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
-        if self.output {
-            Box::new(config.rust_types.iter().map(|rp| rp.to_token_stream()))
+        Box::new(config.iterables.iter().map(|it| {
+            let container = &it.container;
+            let item = &it.item;
+            if it.mutable {
+                quote! {
+                    #container,#item,mutable
+                }
+            } else {
+                quote! {
+                    #container,#item
+                }
+            }
+        }))
+    }
+}
+
+struct OperatorBool;
+
+impl Directive for OperatorBool {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let method = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            args.parse()?
         } else {
-            Box::new(std::iter::empty())
+            syn::Ident::new("as_bool", *ident_span)
+        };
+        config.operator_bools.push(crate::config::OperatorBool {
+            ty: ty.value(),
+            method,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.operator_bools.iter().map(|ob| {
+            let ty = &ob.ty;
+            let method = &ob.method;
+            quote! {
+                #ty,#method
+            }
+        }))
+    }
+}
+
+struct OperatorNot;
+
+impl Directive for OperatorNot {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let method: syn::Ident = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            args.parse()?
+        } else {
+            syn::Ident::new("logical_not", *ident_span)
+        };
+        if method == "not" {
+            return Err(syn::Error::new(
+                method.span(),
+                "a method named `not` would be confused with `std::ops::Not::not`",
+            ));
         }
+        config.operator_nots.push(crate::config::OperatorNot {
+            ty: ty.value(),
+            method,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.operator_nots.iter().map(|on| {
+            let ty = &on.ty;
+            let method = &on.method;
+            quote! {
+                #ty,#method
+            }
+        }))
     }
 }
 
-struct Subclass;
+struct CompoundAssign;
 
-impl Directive for Subclass {
+impl Directive for CompoundAssign {
     fn parse(
         &self,
         args: ParseStream,
         config: &mut IncludeCppConfig,
         _ident_span: &Span,
     ) -> ParseResult<()> {
-        let superclass: syn::LitStr = args.parse()?;
+        let ty: syn::LitStr = args.parse()?;
         args.parse::<syn::token::Comma>()?;
-        let subclass: syn::Ident = args.parse()?;
-        config.subclasses.push(crate::config::Subclass {
-            superclass: superclass.value(),
-            subclass,
+        let operator: syn::LitStr = args.parse()?;
+        let rhs = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            args.parse::<syn::LitStr>()?.value()
+        } else {
+            ty.value()
+        };
+        if !crate::config::COMPOUND_ASSIGNMENT_OPERATORS
+            .iter()
+            .any(|(op, _, _)| *op == operator.value())
+        {
+            return Err(syn::Error::new(
+                operator.span(),
+                "expected a compound assignment operator such as \"+=\"",
+            ));
+        }
+        if config
+            .compound_assigns
+            .iter()
+            .any(|ca| ca.ty == ty.value() && ca.operator == operator.value())
+        {
+            return Err(syn::Error::new(
+                operator.span(),
+                "this operator has already been listed for this type",
+            ));
+        }
+        config.compound_assigns.push(crate::config::CompoundAssign {
+            ty: ty.value(),
+            operator: operator.value(),
+            rhs,
         });
         Ok(())
     }
@@ -469,16 +952,959 @@ impl Directive for Subclass {
         &self,
         config: &'a IncludeCppConfig,
     ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
-        Box::new(config.subclasses.iter().map(|sc| {
-            let superclass = &sc.superclass;
-            let subclass = &sc.subclass;
+        Box::new(config.compound_assigns.iter().map(|ca| {
+            let ty = &ca.ty;
+            let operator = &ca.operator;
+            let rhs = &ca.rhs;
             quote! {
-                #superclass,#subclass
+                #ty,#operator,#rhs
             }
         }))
     }
 }
 
+struct Subscript;
+
+impl Directive for Subscript {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let index: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let output: syn::LitStr = args.parse()?;
+        if config.subscripts.iter().any(|s| s.ty == ty.value()) {
+            return Err(syn::Error::new(
+                ty.span(),
+                "operator[] has already been listed for this type",
+            ));
+        }
+        config.subscripts.push(crate::config::Subscript {
+            ty: ty.value(),
+            index: index.value(),
+            output: output.value(),
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.subscripts.iter().map(|s| {
+            let ty = &s.ty;
+            let index = &s.index;
+            let output = &s.output;
+            quote! {
+                #ty,#index,#output
+            }
+        }))
+    }
+}
+
+struct Deref;
+
+impl Directive for Deref {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let target: syn::LitStr = args.parse()?;
+        let mutable = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            args.parse::<syn::token::Mut>()?;
+            true
+        } else {
+            false
+        };
+        if config.deref_targets.iter().any(|d| d.ty == ty.value()) {
+            return Err(syn::Error::new(
+                ty.span(),
+                "operator* has already been listed for this type",
+            ));
+        }
+        config.deref_targets.push(crate::config::DerefTarget {
+            ty: ty.value(),
+            target: target.value(),
+            mutable,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.deref_targets.iter().map(|d| {
+            let ty = &d.ty;
+            let target = &d.target;
+            let mutable = d.mutable.then(|| quote! { ,mut });
+            quote! {
+                #ty,#target #mutable
+            }
+        }))
+    }
+}
+
+struct DefaultArgs;
+
+impl Directive for DefaultArgs {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let count: syn::LitInt = args.parse()?;
+        let naming = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let naming: syn::Ident = args.parse()?;
+            match naming.to_string().as_str() {
+                "overloads" => DefaultArgNaming::Overloads,
+                "with_params" => DefaultArgNaming::WithParams,
+                _ => {
+                    return Err(syn::Error::new(
+                        naming.span(),
+                        "expected overloads or with_params",
+                    ))
+                }
+            }
+        } else {
+            DefaultArgNaming::Overloads
+        };
+        config.default_args.push(crate::config::DefaultArgs {
+            function: function.value(),
+            count: count.base10_parse()?,
+            naming,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.default_args.iter().map(|da| {
+            let function = &da.function;
+            let count = syn::LitInt::new(&da.count.to_string(), Span::call_site());
+            match da.naming {
+                DefaultArgNaming::Overloads => quote! {
+                    #function,#count
+                },
+                DefaultArgNaming::WithParams => quote! {
+                    #function,#count,with_params
+                },
+            }
+        }))
+    }
+}
+
+struct Comparator;
+
+impl Directive for Comparator {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let element_type: syn::LitStr = args.parse()?;
+        config.comparators.push(crate::config::Comparator {
+            function: function.value(),
+            element_type: element_type.value(),
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.comparators.iter().map(|c| {
+            let function = &c.function;
+            let element_type = &c.element_type;
+            quote! {
+                #function,#element_type
+            }
+        }))
+    }
+}
+
+struct ClosureCallback;
+
+impl Directive for ClosureCallback {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let signature: syn::LitStr = args.parse()?;
+        let value = signature.value();
+        let (return_type, params) = value
+            .trim()
+            .strip_suffix(')')
+            .and_then(|signature| signature.split_once('('))
+            .ok_or_else(|| {
+                syn::Error::new(
+                    signature.span(),
+                    "expected a C++ function signature such as \"void(uint32_t, double)\"",
+                )
+            })?;
+        let fallible = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let flag: syn::Ident = args.parse()?;
+            if flag != "fallible" {
+                return Err(syn::Error::new(flag.span(), "expected fallible"));
+            }
+            true
+        } else {
+            false
+        };
+        config
+            .closure_callbacks
+            .push(crate::config::ClosureCallback {
+                function: function.value(),
+                return_type: return_type.trim().to_string(),
+                param_types: params
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|param| !param.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                fallible,
+            });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.closure_callbacks.iter().map(|cc| {
+            let function = &cc.function;
+            let signature = format!("{}({})", cc.return_type, cc.param_types.join(", "));
+            if cc.fallible {
+                quote! {
+                    #function,#signature,fallible
+                }
+            } else {
+                quote! {
+                    #function,#signature
+                }
+            }
+        }))
+    }
+}
+
+struct ResultVariant;
+
+impl Directive for ResultVariant {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let error_type: syn::LitStr = args.parse()?;
+        config.result_variants.push(crate::config::ResultVariant {
+            function: function.value(),
+            error_type: error_type.value(),
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.result_variants.iter().map(|rv| {
+            let function = &rv.function;
+            let error_type = &rv.error_type;
+            quote! {
+                #function,#error_type
+            }
+        }))
+    }
+}
+
+struct NullVector;
+
+impl Directive for NullVector {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let policy = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let policy: syn::Ident = args.parse()?;
+            match policy.to_string().as_str() {
+                "none" => NullVectorPolicy::AsNone,
+                "empty" => NullVectorPolicy::AsEmpty,
+                _ => return Err(syn::Error::new(policy.span(), "expected none or empty")),
+            }
+        } else {
+            NullVectorPolicy::AsNone
+        };
+        config.null_vectors.push(crate::config::NullVector {
+            function: function.value(),
+            policy,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.null_vectors.iter().map(|nv| {
+            let function = &nv.function;
+            let policy = match nv.policy {
+                NullVectorPolicy::AsNone => quote! { none },
+                NullVectorPolicy::AsEmpty => quote! { empty },
+            };
+            quote! {
+                #function,#policy
+            }
+        }))
+    }
+}
+
+struct SentinelNone;
+
+impl Directive for SentinelNone {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let negative = args.parse::<Option<syn::token::Minus>>()?.is_some();
+        let sentinel: syn::LitInt = args.parse()?;
+        if !sentinel.suffix().is_empty() {
+            return Err(syn::Error::new(
+                sentinel.span(),
+                "expected an integer without a type suffix",
+            ));
+        }
+        let magnitude = sentinel.base10_parse::<i128>()?;
+        let sentinel_value = if negative { -magnitude } else { magnitude };
+        // We don't know the function's return type yet, but no C++ integer
+        // can hold a value beyond these.
+        if !(i128::from(i64::MIN)..=i128::from(u64::MAX)).contains(&sentinel_value) {
+            return Err(syn::Error::new(
+                sentinel.span(),
+                "the sentinel is out of range for any integer type",
+            ));
+        }
+        config.none_sentinels.push(crate::config::NoneSentinel {
+            function: function.value(),
+            sentinel: sentinel_value,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.none_sentinels.iter().map(|ns| {
+            let function = &ns.function;
+            let sentinel = proc_macro2::Literal::i128_unsuffixed(ns.sentinel);
+            quote! {
+                #function,#sentinel
+            }
+        }))
+    }
+}
+
+struct SecondsCount;
+
+impl Directive for SecondsCount {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let policy = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let policy: syn::Ident = args.parse()?;
+            match policy.to_string().as_str() {
+                "f64" => SecondsCountPolicy::AsF64,
+                "duration" => SecondsCountPolicy::AsDuration,
+                _ => return Err(syn::Error::new(policy.span(), "expected f64 or duration")),
+            }
+        } else {
+            SecondsCountPolicy::AsF64
+        };
+        config.seconds_counts.push(crate::config::SecondsCount {
+            function: function.value(),
+            policy,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.seconds_counts.iter().map(|sc| {
+            let function = &sc.function;
+            let policy = match sc.policy {
+                SecondsCountPolicy::AsF64 => quote! { f64 },
+                SecondsCountPolicy::AsDuration => quote! { duration },
+            };
+            quote! {
+                #function,#policy
+            }
+        }))
+    }
+}
+
+struct StringVec;
+
+impl Directive for StringVec {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let policy = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            Some(args.parse()?)
+        } else {
+            None
+        };
+        config.string_vecs.push(crate::config::StringVec {
+            function: function.value(),
+            policy,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.string_vecs.iter().map(|sv| {
+            let function = &sv.function;
+            match &sv.policy {
+                None => quote! { #function },
+                Some(policy) => quote! { #function,#policy },
+            }
+        }))
+    }
+}
+
+struct BitmaskEnum;
+
+impl Directive for BitmaskEnum {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let reject_unknown_bits = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let policy: syn::Ident = args.parse()?;
+            match policy.to_string().as_str() {
+                "reject_unknown_bits" => true,
+                "retain_unknown_bits" => false,
+                _ => {
+                    return Err(syn::Error::new(
+                        policy.span(),
+                        "expected reject_unknown_bits or retain_unknown_bits",
+                    ))
+                }
+            }
+        } else {
+            false
+        };
+        config.bitmask_enums.push(crate::config::BitmaskEnum {
+            ty: ty.value(),
+            reject_unknown_bits,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.bitmask_enums.iter().map(|be| {
+            let ty = &be.ty;
+            let policy = if be.reject_unknown_bits {
+                quote! { reject_unknown_bits }
+            } else {
+                quote! { retain_unknown_bits }
+            };
+            quote! {
+                #ty,#policy
+            }
+        }))
+    }
+}
+
+struct EnumDisplay;
+
+impl Directive for EnumDisplay {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let function: syn::LitStr = args.parse()?;
+        config.enum_displays.push(crate::config::EnumDisplay {
+            ty: ty.value(),
+            function: function.value(),
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.enum_displays.iter().map(|ed| {
+            let ty = &ed.ty;
+            let function = &ed.function;
+            quote! {
+                #ty,#function
+            }
+        }))
+    }
+}
+
+struct IntrusivePtr;
+
+impl Directive for IntrusivePtr {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let (pointer, add_ref, release) = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let pointer: syn::LitStr = args.parse()?;
+            args.parse::<syn::token::Comma>()?;
+            let add_ref: syn::LitStr = args.parse()?;
+            args.parse::<syn::token::Comma>()?;
+            let release: syn::LitStr = args.parse()?;
+            (pointer.value(), add_ref.value(), release.value())
+        } else {
+            (
+                "boost::intrusive_ptr".into(),
+                "intrusive_ptr_add_ref".into(),
+                "intrusive_ptr_release".into(),
+            )
+        };
+        config.intrusive_ptrs.push(crate::config::IntrusivePtrType {
+            ty: ty.value(),
+            pointer,
+            add_ref,
+            release,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.intrusive_ptrs.iter().map(|ip| {
+            let ty = &ip.ty;
+            let pointer = &ip.pointer;
+            let add_ref = &ip.add_ref;
+            let release = &ip.release;
+            quote! {
+                #ty,#pointer,#add_ref,#release
+            }
+        }))
+    }
+}
+
+struct BitmaskParams;
+
+impl Directive for BitmaskParams {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let mut any = false;
+        while args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let param: syn::Ident = args.parse()?;
+            args.parse::<syn::token::Eq>()?;
+            let ty: syn::LitStr = args.parse()?;
+            config.bitmask_params.push(crate::config::BitmaskParam {
+                function: function.value(),
+                param: param.to_string(),
+                ty: ty.value(),
+            });
+            any = true;
+        }
+        if !any {
+            return Err(syn::Error::new(
+                function.span(),
+                "expected at least one parameter, such as flags = \"Permissions\"",
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.bitmask_params.iter().map(|bp| {
+            let function = &bp.function;
+            let param = Ident::new(&bp.param, Span::call_site());
+            let ty = &bp.ty;
+            quote! {
+                #function,#param = #ty
+            }
+        }))
+    }
+}
+
+struct StdArray;
+
+impl Directive for StdArray {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let mut any = false;
+        while args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            // `return` is a keyword, so can't be the name of a parameter.
+            let param = syn::Ident::parse_any(args)?;
+            args.parse::<syn::token::Eq>()?;
+            let array;
+            syn::bracketed!(array in args);
+            let element_type: syn::LitStr = array.parse()?;
+            array.parse::<syn::token::Semi>()?;
+            let len: syn::LitInt = array.parse()?;
+            config.std_arrays.push(crate::config::StdArray {
+                function: function.value(),
+                param: param.to_string(),
+                element_type: element_type.value(),
+                len: len.base10_parse()?,
+            });
+            any = true;
+        }
+        if !any {
+            return Err(syn::Error::new(
+                function.span(),
+                "expected at least one parameter, such as coords = [\"int32_t\"; 4]",
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.std_arrays.iter().map(|sa| {
+            let function = &sa.function;
+            let param = Ident::new(&sa.param, Span::call_site());
+            let element_type = &sa.element_type;
+            let len = syn::LitInt::new(&sa.len.to_string(), Span::call_site());
+            quote! {
+                #function,#param = [#element_type; #len]
+            }
+        }))
+    }
+}
+
+struct StdSpan;
+
+impl Directive for StdSpan {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let mut any = false;
+        while args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let param: syn::Ident = args.parse()?;
+            args.parse::<syn::token::Eq>()?;
+            let element_type: syn::LitStr = args.parse()?;
+            let element_type = element_type.value();
+            let (element_type, is_const) = match element_type.strip_prefix("const ") {
+                Some(element_type) => (element_type.trim_start().to_string(), true),
+                None => (element_type, false),
+            };
+            config.std_spans.push(crate::config::StdSpan {
+                function: function.value(),
+                param: param.to_string(),
+                element_type,
+                is_const,
+            });
+            any = true;
+        }
+        if !any {
+            return Err(syn::Error::new(
+                function.span(),
+                "expected at least one parameter, such as out = \"int32_t\"",
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.std_spans.iter().map(|ss| {
+            let function = &ss.function;
+            let param = Ident::new(&ss.param, Span::call_site());
+            let element_type = if ss.is_const {
+                format!("const {}", ss.element_type)
+            } else {
+                ss.element_type.clone()
+            };
+            quote! {
+                #function,#param = #element_type
+            }
+        }))
+    }
+}
+
+struct IteratorRanges;
+
+impl Directive for IteratorRanges {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let mut any = false;
+        while args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let begin: syn::Ident = args.parse()?;
+            args.parse::<syn::token::Eq>()?;
+            let end: syn::Ident = args.parse()?;
+            config.iterator_ranges.push(crate::config::IteratorRange {
+                function: function.value(),
+                begin: begin.to_string(),
+                end: end.to_string(),
+            });
+            any = true;
+        }
+        if !any {
+            return Err(syn::Error::new(
+                function.span(),
+                "expected at least one pair of parameters, such as begin = end",
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.iterator_ranges.iter().map(|ir| {
+            let function = &ir.function;
+            let begin = Ident::new(&ir.begin, Span::call_site());
+            let end = Ident::new(&ir.end, Span::call_site());
+            quote! {
+                #function,#begin = #end
+            }
+        }))
+    }
+}
+
+struct TemplateReturns;
+
+impl Directive for TemplateReturns {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let mut template_return = crate::config::TemplateReturn {
+            function: function.value(),
+            types: Vec::new(),
+        };
+        while args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let ty: syn::LitStr = args.parse()?;
+            let name = template_return.instance_name(&ty.value());
+            // Distinct types can have names which differ only in characters
+            // which we replace, such as `a::b` and `a_b`.
+            let clash = config
+                .template_returns
+                .iter()
+                .chain(std::iter::once(&template_return))
+                .flat_map(crate::config::TemplateReturn::instances)
+                .find(|(_, existing)| *existing == name)
+                .map(|(existing_ty, _)| existing_ty.to_string());
+            if let Some(existing_ty) = clash {
+                return Err(syn::Error::new(
+                    ty.span(),
+                    format!(
+                        "the instantiations for {existing_ty} and {} would both be named {name}",
+                        ty.value()
+                    ),
+                ));
+            }
+            template_return.types.push(ty.value());
+        }
+        if template_return.types.is_empty() {
+            return Err(syn::Error::new(
+                function.span(),
+                "expected at least one type, such as \"int32_t\"",
+            ));
+        }
+        config.template_returns.push(template_return);
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.template_returns.iter().map(|tr| {
+            let function = &tr.function;
+            let types = &tr.types;
+            quote! {
+                #function #(,#types)*
+            }
+        }))
+    }
+}
+
+struct RustType {
+    #[allow(dead_code)]
+    output: bool,
+}
+
+impl Directive for RustType {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let id: Ident = args.parse()?;
+        config.rust_types.push(RustPath::new_from_ident(id));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        if self.output {
+            Box::new(config.rust_types.iter().map(|rp| rp.to_token_stream()))
+        } else {
+            Box::new(std::iter::empty())
+        }
+    }
+}
+
+struct Subclass {
+    /// Whether autocxx should generate the Rust struct itself, wrapping
+    /// a boxed trait object.
+    dyn_adapter: bool,
+}
+
+impl Directive for Subclass {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let superclass: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let subclass: syn::Ident = args.parse()?;
+        if self.dyn_adapter {
+            config.dyn_subclasses.push(subclass.clone());
+        }
+        config.subclasses.push(crate::config::Subclass {
+            superclass: superclass.value(),
+            subclass,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        let dyn_adapter = self.dyn_adapter;
+        Box::new(
+            config
+                .subclasses
+                .iter()
+                .filter(move |sc| config.is_dyn_subclass(&sc.subclass.to_string()) == dyn_adapter)
+                .map(|sc| {
+                    let superclass = &sc.superclass;
+                    let subclass = &sc.subclass;
+                    quote! {
+                        #superclass,#subclass
+                    }
+                }),
+        )
+    }
+}
+
 struct ExternRustFun;
 
 impl Directive for ExternRustFun {
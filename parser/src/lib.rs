@@ -16,7 +16,10 @@ mod path;
 mod subclass_attrs;
 
 pub use config::{
-    AllowlistEntry, ExternCppType, IncludeCppConfig, RustFun, Subclass, UnsafePolicy,
+    AllowlistEntry, ClosureCallback, CompoundAssign, DefaultArgNaming, DerefTarget, ExternCppType,
+    IncludeCppConfig, IncludedHeader, IntrusivePtrType, Iterable, IteratorRange, NullVectorPolicy,
+    OperatorBool, OperatorNot, RustFun, SecondsCountPolicy, StdArray, StdSpan, Subclass, Subscript,
+    TemplateReturn, UnsafePolicy, Utf16Policy, Utf8Policy,
 };
 use file_locations::FileLocationStrategy;
 pub use multi_bindings::{MultiBindings, MultiBindingsErr};
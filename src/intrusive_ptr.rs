@@ -0,0 +1,137 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::{ops::Deref, ptr};
+
+/// A C++ type which keeps count of its own references, so that it can be
+/// held by an [`IntrusivePtr`]. `autocxx` implements this for each type
+/// listed in `intrusive_ptr!`, by calling the C++ functions which adjust the
+/// count - for `boost::intrusive_ptr`, those are `intrusive_ptr_add_ref` and
+/// `intrusive_ptr_release`.
+///
+/// # Safety
+///
+/// `add_ref` must add a reference to the object, and `release` must remove
+/// one, destroying the object once there are none left.
+pub unsafe trait IntrusivePtrTarget {
+    /// Adds a reference to the object at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a live object.
+    unsafe fn add_ref(ptr: *mut Self);
+
+    /// Removes a reference from the object at `ptr`, which may destroy it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a live object, and the caller must own the
+    /// reference being removed.
+    unsafe fn release(ptr: *mut Self);
+}
+
+/// An owning pointer to a C++ object which keeps its own reference count,
+/// like a C++ `boost::intrusive_ptr`. This is what C++ functions taking or
+/// returning intrusive pointers to a type listed in `intrusive_ptr!` use
+/// instead.
+///
+/// Each `IntrusivePtr` owns one reference: cloning it adds a reference
+/// and dropping it removes one, so the object is destroyed once the last
+/// intrusive pointer to it goes away, whether that's in Rust or C++.
+/// Passing one to C++ hands over its reference, without adding another.
+///
+/// Like a [`cxx::SharedPtr`], an `IntrusivePtr` may be null, and only
+/// gives shared access to the object.
+pub struct IntrusivePtr<T: IntrusivePtrTarget> {
+    ptr: *mut T,
+}
+
+impl<T: IntrusivePtrTarget> IntrusivePtr<T> {
+    /// Makes a null pointer.
+    pub fn null() -> Self {
+        Self {
+            ptr: ptr::null_mut(),
+        }
+    }
+
+    /// Takes ownership of a reference to the object at `ptr`, which may be
+    /// null.
+    ///
+    /// # Safety
+    ///
+    /// If it's not null, `ptr` must point to a live object, and the caller
+    /// must own a reference to it which it gives up.
+    pub unsafe fn from_raw(ptr: *mut T) -> Self {
+        Self { ptr }
+    }
+
+    /// Gives up this pointer's reference to the object, returning the raw
+    /// pointer. Unless it's null, something must later release the
+    /// reference, for example by passing it to [`IntrusivePtr::from_raw`].
+    pub fn into_raw(self) -> *mut T {
+        let ptr = self.ptr;
+        std::mem::forget(self);
+        ptr
+    }
+
+    /// Whether this points to nothing.
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// Borrows the object, or returns `None` if this is null.
+    pub fn as_ref(&self) -> Option<&T> {
+        // Safety: while we hold a reference, the object stays alive.
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// The raw pointer to the object, without affecting its reference
+    /// count.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+}
+
+impl<T: IntrusivePtrTarget> Clone for IntrusivePtr<T> {
+    fn clone(&self) -> Self {
+        if !self.ptr.is_null() {
+            // Safety: while we hold a reference, the object stays alive.
+            unsafe { <T as IntrusivePtrTarget>::add_ref(self.ptr) };
+        }
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<T: IntrusivePtrTarget> Drop for IntrusivePtr<T> {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            // Safety: we own this reference, and nothing uses it after now.
+            unsafe { <T as IntrusivePtrTarget>::release(self.ptr) };
+        }
+    }
+}
+
+impl<T: IntrusivePtrTarget> Default for IntrusivePtr<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T: IntrusivePtrTarget> Deref for IntrusivePtr<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self.as_ref() {
+            Some(target) => target,
+            None => panic!(
+                "called deref on a null IntrusivePtr<{}>",
+                std::any::type_name::<T>()
+            ),
+        }
+    }
+}
@@ -16,11 +16,13 @@
 // do anything - all the magic is handled entirely by
 // autocxx_macro::include_cpp_impl.
 
+mod intrusive_ptr;
 mod reference_wrapper;
 mod rvalue_param;
 pub mod subclass;
 mod value_param;
 
+pub use intrusive_ptr::{IntrusivePtr, IntrusivePtrTarget};
 pub use reference_wrapper::{AsCppMutRef, AsCppRef, CppMutRef, CppPin, CppRef, CppUniquePtrPin};
 
 #[cfg_attr(doc, aquamarine::aquamarine)]
@@ -66,7 +68,9 @@ pub use reference_wrapper::{AsCppMutRef, AsCppRef, CppMutRef, CppPin, CppRef, Cp
 /// Within the braces of the `include_cpp!{...}` macro, you should provide
 /// a list of at least the following:
 ///
-/// * `#include "cpp_header.h"`: a header filename to parse and include
+/// * `#include "cpp_header.h"`: a header filename to parse and include. This may
+///   instead be written `#include <lib/cpp_header.h>`, like a system header,
+///   in which case it's `#include`d with angle brackets.
 /// * `generate!("type_or_function_name")`: a type or function name whose declaration
 ///   should be made available to C++. (See the section on Allowlisting, below).
 /// * Optionally, `safety!(unsafe)` - see discussion of [`safety`].
@@ -112,6 +116,12 @@ macro_rules! include_cpp {
             $($mac!($($arg)*))*
         }
     };
+    // `#include <...>` isn't a literal, so it can't be matched above.
+    ($($tt:tt)*) => {
+        $crate::include_cpp_impl! {
+            $($tt)*
+        }
+    };
 }
 
 /// Include a C++ header. A directive to be included inside
@@ -206,6 +216,43 @@ macro_rules! block_constructors {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Functions which have no side effects, though their results may depend
+/// upon global state, as if they were declared `[[gnu::pure]]` in C++.
+/// bindgen doesn't tell autocxx about such attributes, so autocxx assumes
+/// nothing about a function unless it's listed here, for example
+/// `pure_functions!("Shape::area")`. The generated Rust function's
+/// documentation then says so.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! pure_functions {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Like [`pure_functions!`], but for functions whose results depend only
+/// upon their arguments, as if they were declared `[[gnu::const]]` in C++.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! const_functions {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Methods whose receiver is `volatile`-qualified, for example
+/// `volatile_methods!("Register::read")`. bindgen doesn't tell autocxx
+/// about this qualifier, and cxx can't express it, so autocxx calls each
+/// listed method through a C++ wrapper which makes `this` `volatile`.
+/// Every overload of a listed method is called this way.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! volatile_methods {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// The name of the mod to be generated with the FFI code.
 /// The default is `ffi`.
 ///
@@ -228,6 +275,998 @@ macro_rules! concrete {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Generates a Rust iterator over a C++ container type, for example
+/// `iterable!("Container", "Contents")`. The container must have
+/// `begin()` and `end()` methods returning bidirectional iterators,
+/// and both types must already be on the allowlist by having used
+/// `generate!` or similar. The container then gains an `iter()`
+/// method returning a `ContainerIter`, which implements both
+/// [`Iterator`] and [`DoubleEndedIterator`]. This uses the `const`
+/// overloads of `begin()` and `end()`, so yields `&Contents`.
+///
+/// Add `mutable`, as in `iterable!("Container", "Contents", mutable)`,
+/// if the container also has non-const `begin()` and `end()`. It then
+/// additionally gains an `iter_mut()` method, taking `Pin<&mut Self>`,
+/// which returns a `ContainerIterMut` yielding `Pin<&mut Contents>`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! iterable {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generates a single Rust function for a set of overloaded C++
+/// functions, for example `tag_dispatch!("do_thing")`. As well as the
+/// usual `do_thing`, `do_thing1` etc., you get `do_thing_dispatch(tag, args)`
+/// where `tag` is one of the generated `do_thing_tag0`, `do_thing_tag1`
+/// etc. unit structs (numbered in the same way as the overloads) and `args`
+/// is a tuple of that overload's arguments. Overloads which are `unsafe`,
+/// or which return a reference or an `impl New`, don't get a tag.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! tag_dispatch {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns the `std::unique_ptr` out-parameters of a C++ function
+/// as its Rust return value, for example `out_params!("make_pair")`.
+/// This applies to a function returning `void` whose
+/// `std::unique_ptr<T>&` parameters are filled in by the function:
+/// those parameters disappear from the Rust signature, and the function
+/// instead returns a `UniquePtr<T>`, or a tuple of them if there's more
+/// than one. Each `UniquePtr` starts off null, so if the C++ fails to
+/// create one of the outputs, that element of the tuple is null, while
+/// any outputs it did create are still owned by (and dropped by) Rust.
+/// Likewise, `std::optional<T>&` parameters are returned as an
+/// `Option<T>`, which is `None` if the C++ leaves the optional empty.
+/// This works for any `T` which can be copied into Rust by value, such
+/// as numbers and POD types.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! out_params {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns the string which a C++ function delivers through a callback,
+/// for example `string_callback!("lookup_name")`. This applies to a
+/// function returning `void` whose final parameter is a callback, such as a
+/// `std::function<void(const std::string&)>`. That parameter disappears from
+/// the Rust signature: the C++ wrapper supplies its own callback, which
+/// keeps the string, and the function returns it as a
+/// `UniquePtr<CxxString>`. That's null if the callback is never called; if
+/// it's called more than once, the last string wins. If
+/// [`rust_string_returns`] is in use, the function instead returns an
+/// `Option` of the converted string. The C++ function mustn't keep the
+/// callback after it returns, which autocxx can't check, so the function
+/// is `unsafe`.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! string_callback {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Checks that floating point arguments to a C++ function aren't NaN,
+/// for example `reject_nan!("sqrt_checked")` or
+/// `reject_nan!("Solver::set_tolerance")` for a method.
+/// Normally `f32` and `f64` arguments, including NaN and infinities,
+/// are passed to C++ unchanged. Use this for functions whose documented
+/// preconditions rule out NaN: each such argument then gets a
+/// `debug_assert!`, so passing NaN panics in debug builds. Infinities
+/// are still passed through, and release builds do no checking at all.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! reject_nan {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Lets Rust pass the enum parameters of a C++ function as integers, for
+/// example `integer_enum_params!("set_mode")` or
+/// `integer_enum_params!("Device::set_mode")` for a method. This helps
+/// with migrating code which already treats C++ enums as integers. Each
+/// parameter of enum type then takes the enum's underlying integer type
+/// instead, and gets a `debug_assert!` that it's the value of one of the
+/// enum's variants. Release builds do no checking, and pass C++ whatever
+/// integer they're given. Enums listed in `bitmask_enum!` are unaffected.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! integer_enum_params {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Copies the `std::map<std::string, T>` returned by a C++ function into
+/// a Rust `HashMap<String, T>`, for example `map_snapshot!("get_config")`.
+/// Normally such a map is returned as a `UniquePtr` to an opaque type,
+/// which avoids copying but is awkward to use. This copies all the entries
+/// in a single call instead. `T` must be a fixed-size number, `bool` or
+/// `std::string`, which becomes a `String`; for any other `T`, the function
+/// carries on returning the opaque type.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! map_snapshot {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Says how many trailing parameters of a C++ function have default
+/// arguments, for example `default_args!("configure", 2)` or
+/// `default_args!("Widget::resize", 1)`. bindgen gives the type of each
+/// parameter but not whether it has a default, so autocxx can't tell which
+/// shorter calls C++ would accept, and normally only the full form of the
+/// function is available. This adds overloads which omit the last one, two and so on
+/// up to that many parameters, and which are named like any other
+/// overloads. Each calls the C++ function without those arguments, so the
+/// C++ compiler fills in the defaults. They can therefore be arbitrary
+/// expressions, which are evaluated on each call in the scope of the
+/// function's declaration, just as for calls from C++.
+///
+/// Alternatively, `default_args!("refresh", 1, with_params)` gives the
+/// shortest form the function's own name, and names each longer form after
+/// the last parameter it takes. For `void refresh(bool force = false)`,
+/// that's `refresh()` and `refresh_with_force(force)`. Constructors are
+/// always named like other overloads.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! default_args {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Lets Rust pass a closure as the comparator which a C++ function takes
+/// as its final parameter, for example
+/// `comparator!("sort_widgets", "Widget")`, where the second argument is
+/// the C++ type being compared. The parameter may be a
+/// `std::function<bool(const Widget&, const Widget&)>` or anything else
+/// which a lambda can be passed as. In Rust it becomes an
+/// `impl FnMut(&Widget, &Widget) -> Ordering`: C++ hears that one element
+/// is less than another when the closure returns `Ordering::Less`. C++
+/// requires comparisons to form a strict weak ordering, which any
+/// consistent `Ordering` (such as one from `Ord::cmp`) does. The C++
+/// function mustn't keep the comparator after it returns, which autocxx
+/// can't check, so the function is `unsafe`. This applies only to free
+/// functions.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! comparator {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Lets Rust pass a closure as the allocation callback which a C++
+/// function takes as its final parameter, for example
+/// `allocator_callback!("make_buffer")`. The parameter may be a
+/// `std::function<void*(size_t size, size_t alignment)>`, a
+/// `std::function<void*(size_t size)>`, or anything else which a lambda
+/// can be passed as. In Rust it becomes an
+/// `impl FnMut(Layout) -> *mut u8`, which may return null if the
+/// allocation fails. Requests for zero bytes become requests for one, since
+/// C++ still expects a unique pointer, and callbacks which aren't told the
+/// alignment ask for that of `std::max_align_t`. An alignment which isn't
+/// a power of two gives the C++ null without calling the closure. In debug
+/// builds the program aborts if the closure returns memory which isn't
+/// aligned as requested. The C++ function mustn't keep the callback after
+/// it returns, which autocxx can't check, so the function is `unsafe`.
+/// This applies only to free functions.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! allocator_callback {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns an `Option` from a C++ function which returns a special value
+/// of an integer to mean that there's none, for example
+/// `sentinel_none!("find_index", -1)`. A function returning an `int64_t`
+/// then returns an `Option<i64>`, which is `None` when the C++ returns
+/// `-1` and otherwise `Some` of whatever it returned. Each function has
+/// its own sentinel, which must be an integer literal. If the sentinel
+/// can't be returned as the function's integer type, the function isn't
+/// generated, and its documentation says why. Functions which don't return
+/// an integer are unaffected.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! sentinel_none {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Holds a C++ type which keeps its own reference count in an
+/// [`IntrusivePtr`] wherever C++ holds it in a `boost::intrusive_ptr`,
+/// for example `intrusive_ptr!("Document")`. Functions taking such a
+/// pointer, by value or by `const` reference, then take an `IntrusivePtr`,
+/// and those returning one return an `IntrusivePtr`. Cloning and dropping
+/// it call `intrusive_ptr_add_ref` and `intrusive_ptr_release`, just as
+/// `boost::intrusive_ptr` does. For another intrusive pointer template with
+/// the same interface, give its name and those of the two functions:
+/// `intrusive_ptr!("Document", "my::ref_ptr", "doc_ref", "doc_unref")`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! intrusive_ptr {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Lets Rust pass a closure as the callback which a C++ function takes as
+/// its final parameter, for example
+/// `closure_callback!("run_job", "void(uint32_t, uint32_t)")`, where the
+/// second argument is the C++ signature of the callback. The parameter may
+/// be a `std::function` of that signature or anything else which a lambda
+/// can be passed as. In Rust it becomes an `impl FnMut(u32, u32)`, which
+/// C++ calls each time it calls the callback. For now, the callback may
+/// only take and return numbers and `bool`, and return values may also be
+/// `void`. The closure only lives for the duration of the call, but
+/// nothing stops the C++ function keeping the callback after it returns,
+/// so the function is `unsafe`: calling it is a promise that it doesn't.
+/// This applies only to free functions.
+/// Add `fallible`, as in
+/// `closure_callback!("fetch", "uint32_t(uint32_t)", fallible)`, for a
+/// closure which returns a `Result<u32, Box<dyn std::error::Error>>`. If
+/// it returns an error, the callback throws a `std::runtime_error` holding
+/// the error's message, which the C++ function must catch.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! closure_callback {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Says what should happen when a C++ function returning a
+/// `std::unique_ptr<std::vector<T>>` returns null, for example
+/// `null_vector!("find_items", none)`. Such functions return a
+/// `UniquePtr<CxxVector<T>>` regardless. With `none`, which is the
+/// default, the function instead returns an `Option` of one, which is
+/// `None` if the pointer is null. With `empty`, a null pointer is
+/// replaced with a pointer to a new, empty vector, so the function still
+/// returns a `UniquePtr<CxxVector<T>>` which is never null.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! null_vector {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Lets a C++ function taking a `const std::map<K, V>&` or
+/// `const std::unordered_map<K, V>&` be called with any Rust iterator of
+/// `(K, V)` pairs instead, such as a `HashMap`, for example
+/// `map_from_iter!("set_limits")`. The generated code makes a temporary
+/// map, which lives until the call returns, and inserts each pair in turn,
+/// following `insert`: if a key turns up more than once, the first value
+/// is kept. `K` and `V` must
+/// each be a fixed-size number, `bool` or `std::string`, which is passed as
+/// a `String`; for any other types, the parameter carries on being a
+/// reference to the opaque map type.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! map_from_iter {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Accepts a Rust `&T` for each `const T*` parameter of a C++ function,
+/// for example `const_pointers_as_references!("draw")` or
+/// `const_pointers_as_references!("Canvas::draw")` for a method.
+/// This is intended for parameters declared `const T* const` which must
+/// never be null: C++ ignores the outer `const` in a function's type, so
+/// autocxx can't spot these by itself. A C++ wrapper then takes a
+/// `const T&` and passes its address to the original function, which
+/// therefore gets exactly the argument type it declares. `const char*`
+/// parameters are left as pointers, since they're usually strings: use
+/// [`str_params!`] or [`cstr_params!`] for those.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! const_pointers_as_references {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns a Rust `Result` from a C++ function which returns a
+/// `std::variant` of two types, for example
+/// `result_variant!("parse_config", "ParseError")`, where the second
+/// argument is the alternative which represents an error. A function
+/// returning `std::variant<ParseError, Config>` then returns a
+/// `Result<UniquePtr<Config>, UniquePtr<ParseError>>`: both alternatives
+/// are moved into new heap allocations, so both must be types which can
+/// go in a `UniquePtr`.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! result_variant {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns a Rust array from a C++ function which returns a `std::tuple`
+/// whose elements are all the same built-in type, for example
+/// `tuple_array!("get_coords")` or `tuple_array!("Point::get_coords")`
+/// for a method. A function returning `std::tuple<int32_t, int32_t, int32_t>`
+/// then returns an `[i32; 3]`. It's an error to list a function whose
+/// tuple has elements of different types, or of a type such as a class
+/// or a string.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! tuple_array {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns a Rust tuple from a C++ function which returns a `std::pair`,
+/// for example `pair_tuple!("divmod")` or `pair_tuple!("Range::bounds")`
+/// for a method. A function returning `std::pair<int32_t, bool>` then
+/// returns an `(i32, bool)`. Pairs may be nested, so
+/// `std::pair<std::pair<int32_t, int32_t>, bool>` becomes
+/// `((i32, i32), bool)`. Elements which are `std::string`s become
+/// `String`s, replacing any invalid UTF-8, so a
+/// `std::pair<std::string, std::string>` becomes a `(String, String)`.
+/// A `std::unique_ptr<T>` element is moved out of the pair into a
+/// `cxx::UniquePtr<T>`. It's an error to list a function whose pair has
+/// elements of another type, such as a class: they must be numbers,
+/// `bool`, strings or `std::unique_ptr`s. A function may also return a
+/// `std::optional` of such a pair, so
+/// `std::optional<std::pair<int32_t, bool>>` becomes `Option<(i32, bool)>`,
+/// which is `None` if the optional was empty.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! pair_tuple {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns the count of nanoseconds, as an `i64`, from a C++ function which
+/// returns a `std::chrono::nanoseconds`, for example
+/// `nanoseconds_count!("elapsed")` or `nanoseconds_count!("Timer::elapsed")`
+/// for a method. Other `std::chrono::duration`s are converted to nanoseconds
+/// first, by `std::chrono::duration_cast`. It's an error to list a function
+/// which returns anything else.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! nanoseconds_count {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns the count of seconds, as an `f64`, from a C++ function which
+/// returns any `std::chrono::duration`, for example
+/// `seconds_count!("elapsed")`. The duration is converted to a
+/// `std::chrono::duration<double>` by `std::chrono::duration_cast`, so
+/// any fraction of a second is kept, whether the duration counts in a
+/// floating point type or in integer milliseconds. Durations too long to
+/// count exactly in a `double` lose precision rather than overflowing.
+/// `seconds_count!("elapsed", duration)` instead returns a
+/// `Result<std::time::Duration, std::time::TryFromFloatSecsError>`, which
+/// is an error if the duration is negative, not a number, or too long for
+/// a `Duration`. The function must already be on the allowlist by having
+/// used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! seconds_count {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Asks the C++ compiler, rather than autocxx's own analysis, whether a
+/// type can be copied and moved, for example `probe_traits!("Holder")`.
+/// autocxx then only implements `CopyNew` if `std::is_copy_constructible`
+/// holds for the type, and `MoveNew` if `std::is_move_constructible` does.
+/// This helps with types whose `= default` special members are in fact
+/// deleted, which autocxx otherwise assumes exist. The type traits are
+/// evaluated while the bindings are generated, by a probe added to the
+/// header given to bindgen.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! probe_traits {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Adds a snippet of raw C++ to the header of C++ glue code which autocxx
+/// generates, for example `cpp_prelude!("#define LOG_CALLS 1")`. Each
+/// snippet is emitted verbatim, in the order given, after the `#include`s
+/// listed in [include_cpp] and before any of autocxx's own declarations.
+/// The snippets are only seen by autocxx's glue code: neither bindgen nor
+/// your own C++ sees them.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! cpp_prelude {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Accepts a Rust `&[u8]` in place of each `const void*` parameter of a
+/// C++ function which is immediately followed by a `size_t` length, for
+/// example `byte_slices!("send")` or `byte_slices!("Socket::send")` for a
+/// method. A non-`const` `void*` similarly becomes a `&mut [u8]`. The
+/// length parameter disappears from the Rust signature: the C++ wrapper
+/// passes the slice's length, and a null pointer if the slice is empty.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! byte_slices {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Accepts a Rust `&[T]` in place of a pair of adjacent `const T*`
+/// parameters of a C++ function giving the beginning and end of a range,
+/// for example `iterator_ranges!("sum", begin = end)` for
+/// `int sum(const int* begin, const int* end)`. Several pairs can be
+/// listed, separated by commas. A pair of non-`const` `T*` similarly
+/// becomes a `&mut [T]`. The C++ wrapper passes pointers to the start and
+/// end of the slice. Only pointers are recognized as iterators, since only
+/// they are sure to refer to contiguous storage, and `T` must be a number
+/// or a POD type.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! iterator_ranges {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Reports any C++ exception thrown by a function as a Rust `Err`, for
+/// example `catch_exceptions!("parse")` or `catch_exceptions!("Parser::parse")`
+/// for a method. The function then returns a
+/// `Result<T, cxx::Exception>` instead of `T`. Exceptions derived from
+/// `std::exception` keep their `what()` message; anything else is reported as
+/// "unknown C++ exception". Constructors, and functions which return a C++
+/// object by value, can't be listed here.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! catch_exceptions {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Like [byte_slices], but for arrays of numbers or POD types: accepts a
+/// Rust `&[T]` in place of each `const T*` parameter of a C++ function
+/// which is immediately followed by a `size_t` length, for example
+/// `slices!("sum")`. A non-`const` `T*` similarly becomes a `&mut [T]`. The
+/// C++ wrapper receives a `rust::Slice<const T>` and passes on its data
+/// and length, or a null pointer if the slice is empty. The function must
+/// already be on the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! slices {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Accepts a Rust `&mut [u8]` in place of each `uint8_t*` or `void*`
+/// parameter of a C++ function which is immediately followed by a `size_t`
+/// capacity, for example `output_buffers!("encode")` or
+/// `output_buffers!("Encoder::encode")` for a method. If there's exactly one
+/// such buffer and the function returns a `size_t`, that's taken to be the
+/// number of bytes written, and the function instead returns a
+/// `Result<usize, BufferTooSmall>`. A count bigger than the buffer is taken
+/// to be the size the buffer needed to be, as `snprintf` reports it, and
+/// gives a [`BufferTooSmall`] error.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! output_buffers {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns a `&CStr` from a C++ function returning a `const char*` or a
+/// `const std::string&`, for example `cstr_returns!("Widget::name")`. The
+/// `&CStr` borrows the characters in place, so its lifetime is tied to
+/// that of the function's one reference parameter, usually its receiver,
+/// exactly as if the function returned a reference. By listing a function
+/// returning `const char*`, you're promising that the characters are
+/// null-terminated, and that they live as long as that reference
+/// parameter and aren't changed meanwhile. Returning null panics. A
+/// `std::string` is always null-terminated, but any null character within
+/// it ends the `&CStr` early. The function must already be on the
+/// allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! cstr_returns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns an `Option<&T>` from a C++ function returning a `const T*`
+/// which may be null, for example `nullable_returns!("Node::parent")`. It's
+/// `None` if the pointer is null, and otherwise a reference whose lifetime
+/// is tied to that of the function's one reference parameter, usually its
+/// receiver, exactly as if the function returned a reference. By listing a
+/// function, you're promising that any object it points to lives as long
+/// as that reference parameter, and isn't changed meanwhile. This has no
+/// effect under `safety!(unsafe_references_wrapped)`.
+///
+/// A function returning a `std::unique_ptr<T>` which may be null, such as
+/// a factory looking up what to make by name, instead returns an
+/// `Option<UniquePtr<T>>`, which is `None` if the pointer is null. That
+/// works whatever the safety policy.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! nullable_returns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns a `SharedPtr<T>` from a C++ function returning a
+/// `std::shared_ptr<const T>`, for example
+/// `mutable_shared_ptr_returns!("make_config")`. cxx only knows about
+/// `std::shared_ptr<T>`, so we cast the constness away in C++. By listing
+/// a function, you're promising that the object it returns wasn't itself
+/// created `const`, because Rust may pass the `SharedPtr` on to C++
+/// functions which change the object. Without this, such functions can't
+/// be generated at all.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! mutable_shared_ptr_returns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns an owned Rust `String` from a C++ function returning a
+/// `const char*` or `char*`, for example `cstr_string_returns!("version")`.
+/// The C++ wrapper copies the null-terminated characters into a new
+/// `std::string`, so unlike [cstr_returns] nothing is borrowed, and the
+/// characters needn't outlive the call. A null pointer gives an empty
+/// string. The characters are checked to be UTF-8, but nothing else is
+/// assumed about their encoding: as for a returned `std::string`,
+/// [rust_string_returns] says what to do if they aren't, and by default
+/// the function returns a `Result<String, Utf8Error>`. The function must
+/// already be on the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! cstr_string_returns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Makes each `const char*` parameter of a C++ function take a `&str`, for
+/// example `str_params!("measure")`. The C++ wrapper copies the characters
+/// into a `std::string` for the duration of the call, so that C++ is given
+/// a null-terminated string; if the `&str` contains a null character, C++
+/// sees the string end there. You're promising that the function doesn't
+/// keep the pointer once it returns. To avoid the copy, use
+/// [`cstr_params!`] instead.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! str_params {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Makes each `const char*` parameter of a C++ function take a
+/// `&CStr`, for example `cstr_params!("measure")`. A `&CStr` is already
+/// null-terminated, so C++ is given a pointer to its characters without
+/// anything being copied or allocated. You're promising that the function
+/// doesn't keep the pointer once it returns.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! cstr_params {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Returns a `&[T]` from a C++ function returning a
+/// `const std::vector<T>&`, for example `vector_slices!("Series::values")`.
+/// The slice views the vector's own storage, without copying, and borrows
+/// from the function's one reference parameter, usually its receiver,
+/// exactly as the `&CxxVector<T>` it would otherwise return. `T` must be a
+/// number or a POD type. A function returning a `std::vector` by value
+/// can't be listed, since the vector would be destroyed before Rust could
+/// look at the slice; listing one is a build error.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! vector_slices {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Declares that the reference returned by a C++ method refers to part of
+/// the object itself, for example `borrow_from_receiver!("App::settings_for")`
+/// for `Config& settings_for(const Key& key)`. Such a method takes references
+/// besides its receiver, so its bindings otherwise fail to generate, since
+/// there's no telling which of them the result borrows from. Once listed,
+/// the returned reference borrows from the receiver alone, and so may
+/// outlive the other arguments. This is only sound if the method really does
+/// return a reference into its own object: check this before listing it.
+/// The method must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! borrow_from_receiver {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Exposes the `operator bool()` of a C++ type as a Rust method, for
+/// example `operator_bool!("Handle")`. Rust has no implicit conversions to
+/// `bool`, so instead `Handle` gains a method `as_bool(&self) -> bool`.
+/// A different method name can be given as a second argument, for example
+/// `operator_bool!("Handle", is_valid)`. The type must already be on the
+/// allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! operator_bool {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Exposes the `operator!()` of a C++ type as a Rust method, for example
+/// `operator_not!("Stream")`. This doesn't implement [`core::ops::Not`],
+/// which Rust uses for bitwise negation as well as for `bool`s, and which
+/// consumes its operand. Instead, `Stream` gains a method
+/// `logical_not(&self) -> bool`, returning the result of `!stream`
+/// converted to `bool`. A different method name can be given as a second
+/// argument, for example `operator_not!("Stream", failed)`, but it can't
+/// be `not`, which would be confused with `Not::not`. The type must already
+/// be on the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! operator_not {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implements `Display` for a C++ enum using a C++ function which names
+/// each value, for example `enum_display!("Color", "toString")` for
+/// `const char* toString(Color)`. The function may be overloaded for other
+/// types, and needn't itself be on the allowlist. It must return either
+/// null or a null-terminated string which lives forever and doesn't change,
+/// such as a string literal; any invalid UTF-8 is replaced. For values
+/// it returns null for, such as unknown combinations of flags in a
+/// [bitmask_enum], the number is shown instead. The name is also available
+/// from the static method `cpp_display_name`. The enum must already be on
+/// the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! enum_display {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Treats a C++ enum as a set of flags, for example
+/// `bitmask_enum!("Permissions")`. Rather than a Rust `enum`, which can only
+/// hold one of its variants, `Permissions` becomes a wrapper around the
+/// enum's underlying integer in the style of the `bitflags` crate, so any
+/// combination of flags can be passed to and from C++. By default, values
+/// returned from C++ keep any bits which aren't one of the enumerators;
+/// `bitmask_enum!("Permissions", reject_unknown_bits)` instead panics if
+/// C++ returns such a value, or if Rust tries to pass one to C++. The enum
+/// must already be on the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! bitmask_enum {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Makes integer parameters of a C++ function take a `bitmask_enum!`
+/// instead, for example `bitmask_params!("open_file", flags = "OpenFlags")`.
+/// Much C++ takes flags as a plain `unsigned int` even though they're the
+/// enumerators of an enum; here, Rust passes `open_file` an `OpenFlags`
+/// for its `flags` parameter, and the C++ wrapper casts it to the integer.
+/// Several parameters can be listed, separated by commas. Any other
+/// function which names `OpenFlags` as its parameter's type is unaffected.
+/// If the enum was listed with `reject_unknown_bits`, passing a value with
+/// bits which aren't any of its flags panics. The function and the enum
+/// must already be on the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! bitmask_params {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Passes `std::array`s to and from a C++ function as Rust arrays, for
+/// example `std_array!("translate", offset = ["int32_t"; 3], return = ["int32_t"; 3])`.
+/// autocxx can't see the element type and length of a `std::array` for
+/// itself, so this says that `translate`'s `offset` parameter, and its
+/// return value, are each a `std::array<int32_t, 3>`, which Rust then
+/// passes as a `[i32; 3]`. Elements must be built-in types, and each
+/// array must be passed by value. It's copied, whatever its length, but
+/// zero-length arrays aren't supported. Several parameters can be
+/// listed, separated by commas. The function must already be on the
+/// allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! std_array {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Passes a Rust slice to a C++20 `std::span` parameter, for example
+/// `std_span!("fill", out = "int32_t")`. autocxx can't see the element
+/// type of a `std::span` for itself, so this says that `fill`'s `out`
+/// parameter is a `std::span<int32_t>`, which Rust then passes as a
+/// `&mut [i32]`. Name the element type `"const int32_t"` for a
+/// `std::span<const int32_t>`, which Rust passes as a `&[i32]`. Elements
+/// must be numbers or POD types, and the span must have a dynamic extent
+/// and be passed by value. The span covers the whole slice, so always has
+/// its length. Several parameters can be listed, separated by commas. The
+/// function must already be on the allowlist by having used `generate!`
+/// or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! std_span {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Instantiates a C++ function template which takes no parameters with
+/// each of the given return types, for example
+/// `template_returns!("config::get", "int32_t", "std::string")`. bindgen
+/// can't see function templates, so this makes a function for each type,
+/// which calls `config::get<int32_t>()` and so on. Each is named after the
+/// template and the type, with any run of characters which can't appear
+/// in an identifier replaced by an underscore, so here Rust gets
+/// `config::get_int32_t` and `config::get_std_string`. Names which clash
+/// are an error. Each type must be a built-in type or one which autocxx
+/// generates, and be named by identifiers alone, so use a typedef for
+/// something like `Widget*`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! template_returns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Lets a C++ type be moved out of a `CxxVector`, for example
+/// `move_out_of_vector!("Token")`. `CxxVector` only hands out references
+/// to its elements, which is a problem for move-only types, so this gives
+/// `Token` a static method
+/// `take_from_vector(vector: Pin<&mut CxxVector<Token>>, index: usize)`.
+/// That calls `std::move(vector[index])` and returns the result in a new
+/// `UniquePtr<Token>`, which is null if `index` is out of bounds. The
+/// vector keeps its length: the element left behind is in whatever valid
+/// but moved-from state `Token`'s move constructor leaves it. The type
+/// must already be on the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! move_out_of_vector {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Tells Rust the size and alignment of a C++ type, for example
+/// `expose_layout!("Widget")`, so that Rust code can allocate storage for
+/// one. Where bindgen knows the type's layout, `Widget` gets associated
+/// constants `CPP_SIZE` and `CPP_ALIGN`, which can be used at compile
+/// time. The C++ compiler checks that they match its own `sizeof` and
+/// `alignof`. In any case, `Widget` gets static methods `cpp_size()` and
+/// `cpp_align()`, which ask the C++ at runtime. The type must already be on
+/// the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! expose_layout {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implements a Rust compound assignment trait, such as `AddAssign`, for a
+/// C++ type using the corresponding operator, for example
+/// `compound_assign!("Vec2", "+=")`. The right hand side is a `const`
+/// reference to the same type unless another C++ type is given, as in
+/// `compound_assign!("Vec2", "*=", "double")`; numbers and other built-in
+/// types are passed by value. For non-POD types the trait is implemented
+/// by `Pin<&mut T>`. The operator is also available as a method such as
+/// `cpp_add_assign`. The type must already be on the allowlist by having
+/// used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! compound_assign {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implements `Index` for a C++ type using its `const` `operator[]`, for
+/// example `subscript!("Grid", "size_t", "double")` for a type whose
+/// `operator[]` takes a `size_t` and returns a `const double&`. The second
+/// argument is the type of the index, which should be a number or other
+/// built-in type, and the third that of the element. The element is
+/// borrowed from the object, and can't be changed: `IndexMut` isn't
+/// implemented, even if there's also a non-`const` `operator[]`. The type
+/// must also have a `size()`, as standard containers do, and indexing
+/// panics unless the index is less than that, so `operator[]` is never
+/// called out of bounds. The operator is also available as the method
+/// `cpp_index`, which returns an `Option` instead. The type must already be
+/// on the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! subscript {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implements `Deref` for a C++ type, such as a smart pointer, using its
+/// `const` `operator*`, for example `deref!("WidgetHandle", "Widget")` for
+/// a type whose `operator*` returns a `const Widget&`. Rust's method calls
+/// look through `Deref`, so `operator->` isn't needed. With
+/// `deref!("WidgetHandle", "Widget", mut)`, the non-`const` `operator*` also
+/// implements `DerefMut`, so long as both types are POD types (or the
+/// target a number); otherwise, it's only available as the method
+/// `cpp_deref_mut`. The `const` `operator*` is always available as the
+/// method `cpp_deref`. `operator*` is called from safe Rust without any
+/// checks, so listing a type is a promise that every object of that type
+/// which Rust can get hold of can be dereferenced: never list a smart
+/// pointer which may be null. The type must already be on the allowlist by
+/// having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! deref {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implements `Ord`, `PartialOrd`, `Eq` and `PartialEq` for a C++ type
+/// using its `operator<`, for example `ord!("Key")`. This allows the type,
+/// or a `UniquePtr` of it, to be used as a key in a `BTreeMap`.
+/// Two objects are considered equal if neither is less than the other, so
+/// equality is always consistent with the ordering, and `operator==` is
+/// never used. The `operator<` must therefore be a strict weak ordering,
+/// as `std::map` also requires. If the C++ is compiled as C++20 or later
+/// and the type has an `operator<=>`, that's used instead, in a single
+/// call. Listing a type here asserts that its ordering is total; use
+/// [partial_ord] if it isn't. The comparison is also available as a
+/// method `cpp_compare`. The type must already be on the allowlist by
+/// having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! ord {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implements `PartialOrd` and `PartialEq` for a C++ type whose ordering
+/// may be partial, for example `partial_ord!("Reading")`. `PartialEq` uses
+/// its `operator==`, just as for [partial_eq]. `PartialOrd` uses its
+/// `operator<=>` if the C++ is compiled as C++20 or later and the type has
+/// one, or else its `operator<` and `operator==`: if neither object is
+/// less than the other and they aren't equal either, they're unordered,
+/// and `partial_cmp` returns `None`. The comparison is also available as a
+/// method `cpp_partial_compare`. Types listed in [ord] already implement
+/// `PartialOrd`. The type must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! partial_ord {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implements `PartialEq` for a C++ type using its `operator==`, for
+/// example `partial_eq!("Point")`. The operator may be a member or a free
+/// function. `!=` is always the negation of `==`, so any C++ `operator!=`
+/// isn't used. `Eq` isn't implemented, since C++ doesn't promise that
+/// `operator==` is an equivalence relation. The comparison is also
+/// available as a method `cpp_eq`. Types listed in [ord] already implement
+/// `PartialEq`, so this has no effect on them. The type must already be on
+/// the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! partial_eq {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implements `Display`, and therefore `ToString`, for a C++ type using
+/// its `operator<<`, for example `display!("Fraction")`. The object is
+/// streamed into a `std::ostringstream`, so the operator may be a friend
+/// or any other free function, including one in the type's own namespace
+/// which only argument-dependent lookup would find. Any invalid UTF-8 in the
+/// output is replaced with U+FFFD. The string is also available from a
+/// method `cpp_to_string`, as a `UniquePtr<CxxString>`. The type must
+/// already be on the allowlist by having used `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! display {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Specifies a global safety policy for functions generated
 /// from these headers. By default (without such a `safety!`
 /// directive) all such functions are marked as `unsafe` and
@@ -278,6 +1317,54 @@ macro_rules! safety {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Return C++ `std::string`s to Rust as a Rust `String`, rather than
+/// as a [`cxx::UniquePtr<cxx::CxxString>`]. C++ strings need not contain
+/// valid UTF-8, so you can choose how to handle that:
+/// `rust_string_returns!(checked)` (the default, also selected by
+/// `rust_string_returns!()`) returns a `Result<String, Utf8Error>`;
+/// `rust_string_returns!(lossy)` replaces invalid sequences with U+FFFD;
+/// `rust_string_returns!(assume_valid)` performs no checks at all. The
+/// last option is your promise that the C++ code only ever returns
+/// valid UTF-8; if it doesn't, undefined behavior will occur.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! rust_string_returns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Chooses how C++ `std::u16string`s returned to Rust are decoded into a
+/// Rust `String`. A `std::u16string` may hold unpaired surrogates, which
+/// aren't valid UTF-16. `u16string_returns!(lossy)` (the default, also
+/// selected by `u16string_returns!()`) replaces them with U+FFFD;
+/// `u16string_returns!(checked)` instead returns a
+/// `Result<String, FromUtf16Error>`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! u16string_returns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Copies the `std::vector<std::string>` returned by a C++ function into a
+/// Rust `Vec`, for example `string_vec!("list_files")`. Each element is
+/// converted as under [`rust_string_returns`], using its policy for
+/// elements which aren't valid UTF-8, unless another policy is given, for
+/// example `string_vec!("list_files", lossy)`. Under the `checked` policy
+/// the function returns a `Result<Vec<String>, Utf8Error>`, and otherwise
+/// a `Vec<String>`.
+/// The function must already be on the allowlist by having used
+/// `generate!` or similar.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! string_vec {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Whether to avoid generating [`cxx::UniquePtr`] and [`cxx::Vector`]
 /// implementations. This is primarily useful for reducing test cases and
 /// shouldn't be used in normal operation.
@@ -369,6 +1456,22 @@ macro_rules! subclass {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Allows a C++ class made up of pure virtual methods to be implemented by a
+/// Rust trait object, for example `dyn_subclass!("Observer", BoxedObserver)`.
+/// This is like [`subclass!`], except that autocxx generates the Rust struct
+/// `BoxedObserver` itself. Its `new_from_box` function takes a
+/// `Box<dyn Observer_methods>` and returns a `UniquePtr<Observer>`, which
+/// drops the boxed object when C++ deletes it. `Observer` must therefore have
+/// a virtual destructor, which the generated C++ checks with a
+/// `static_assert`, and also a default constructor.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! dyn_subclass {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Indicates that a C++ type can definitely be instantiated. This has effect
 /// only in a very specific case:
 /// * the type is a typedef to something else
@@ -502,6 +1605,38 @@ pub struct BindingGenerationFailure {
     _pinned: core::marker::PhantomData<core::marker::PhantomPinned>,
 }
 
+/// Returned by a function listed in [`output_buffers`] when the C++ said
+/// it needed to write more bytes than the buffer could hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferTooSmall {
+    /// The number of bytes the C++ said it needed.
+    pub required: usize,
+}
+
+impl std::fmt::Display for BufferTooSmall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "buffer too small: {} bytes required", self.required)
+    }
+}
+
+impl std::error::Error for BufferTooSmall {}
+
+/// Returned when converting a number to an enum with `TryFrom`, if the
+/// number isn't the value of any of the enum's enumerators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidEnumValue<T> {
+    /// The number which doesn't match any enumerator.
+    pub value: T,
+}
+
+impl<T: std::fmt::Display> std::fmt::Display for InvalidEnumValue<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} isn't the value of any enumerator", self.value)
+    }
+}
+
+impl<T: std::fmt::Debug + std::fmt::Display> std::error::Error for InvalidEnumValue<T> {}
+
 /// Tools to export Rust code to C++.
 // These are in a mod to avoid shadowing the definitions of the
 // directives above, which, being macro_rules, are unavoidably
@@ -603,8 +1738,10 @@ pub trait WithinBox {
 
 use cxx::kind::Trivial;
 use cxx::ExternType;
+use moveit::AsMove;
 use moveit::Emplace;
 use moveit::MakeCppStorage;
+use moveit::MoveNew;
 
 impl<N, T> WithinUniquePtr for N
 where
@@ -630,6 +1767,92 @@ where
     }
 }
 
+/// Provides a utility function to move a C++ object which is already
+/// owned by some pointer, such as a [`cxx::UniquePtr`], into a new [`Box`].
+/// Automatically imported by the autocxx prelude and implemented by any
+/// [`moveit::AsMove`] pointer to a type with a C++ move constructor.
+pub trait MoveIntoBox {
+    type Inner;
+    /// Move this object into a pinned box. The box's storage is allocated
+    /// first, and the object's C++ move constructor then constructs the new
+    /// object directly within it, so the object is moved exactly once. The
+    /// moved-from object is then destroyed and its storage freed. When the
+    /// box is dropped, the object's C++ destructor is run before Rust frees
+    /// the box.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a null [`cxx::UniquePtr`].
+    fn move_into_box(self) -> Pin<Box<Self::Inner>>;
+}
+
+impl<P> MoveIntoBox for P
+where
+    P: AsMove,
+    P::Target: MoveNew,
+{
+    type Inner = P::Target;
+    fn move_into_box(self) -> Pin<Box<P::Target>> {
+        // Dereferencing a null `UniquePtr` panics, which we want to happen
+        // before we move from whatever it points to.
+        let _ = &*self;
+        Box::emplace(moveit::new::mov(self))
+    }
+}
+
+/// Provides a utility function to mutate a C++ object owned by a
+/// [`cxx::UniquePtr`] for the duration of a closure, without taking it out
+/// of the pointer. Automatically imported by the autocxx prelude and
+/// implemented by any [`cxx::UniquePtr`].
+pub trait WithPinMut {
+    type Inner;
+    /// Calls `f` with a pinned mutable reference to the object, and returns
+    /// whatever `f` returns. This is the same as calling `f(self.pin_mut())`,
+    /// but can be chained straight after a function returning a
+    /// [`cxx::UniquePtr`]. The reference can't outlive the closure, and
+    /// because it's pinned, the object can't be moved out from behind it,
+    /// so any C++ pointers to the object stay valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a null [`cxx::UniquePtr`].
+    fn with_pin_mut<R>(&mut self, f: impl FnOnce(Pin<&mut Self::Inner>) -> R) -> R;
+}
+
+impl<T> WithPinMut for UniquePtr<T>
+where
+    T: UniquePtrTarget,
+{
+    type Inner = T;
+    fn with_pin_mut<R>(&mut self, f: impl FnOnce(Pin<&mut T>) -> R) -> R {
+        f(self.pin_mut())
+    }
+}
+
+/// Provides a way to get an owning pointer from a [`cxx::WeakPtr`], like
+/// C++ `std::weak_ptr::lock`. Automatically imported by the autocxx prelude
+/// and implemented by any [`cxx::WeakPtr`].
+pub trait WeakPtrLock {
+    type Inner: SharedPtrTarget;
+    /// Returns a [`cxx::SharedPtr`] sharing ownership of the object, or
+    /// `None` if the object has already been destroyed. This calls
+    /// `std::weak_ptr::lock`, so it uses the same control block as any
+    /// `std::shared_ptr`s and `std::weak_ptr`s to the object held in C++, and
+    /// the object lives at least as long as the returned pointer.
+    fn lock(&self) -> Option<SharedPtr<Self::Inner>>;
+}
+
+impl<T> WeakPtrLock for WeakPtr<T>
+where
+    T: WeakPtrTarget + SharedPtrTarget,
+{
+    type Inner = T;
+    fn lock(&self) -> Option<SharedPtr<T>> {
+        let strong = self.upgrade();
+        (!strong.is_null()).then_some(strong)
+    }
+}
+
 /// Emulates the [`WithinUniquePtr`] trait, but for trivial (plain old data) types.
 /// This allows such types to behave identically if a type is changed from
 /// `generate!` to `generate_pod!`.
@@ -670,8 +1893,8 @@ where
     }
 }
 
-use cxx::memory::UniquePtrTarget;
-use cxx::UniquePtr;
+use cxx::memory::{SharedPtrTarget, UniquePtrTarget, WeakPtrTarget};
+use cxx::{SharedPtr, UniquePtr, WeakPtr};
 use moveit::New;
 pub use rvalue_param::RValueParam;
 pub use rvalue_param::RValueParamHandler;
@@ -704,9 +1927,13 @@ pub mod prelude {
     pub use crate::CppPin;
     pub use crate::CppRef;
     pub use crate::CppUniquePtrPin;
+    pub use crate::IntrusivePtr;
+    pub use crate::MoveIntoBox;
     pub use crate::PinMut;
     pub use crate::RValueParam;
     pub use crate::ValueParam;
+    pub use crate::WeakPtrLock;
+    pub use crate::WithPinMut;
     pub use crate::WithinBox;
     pub use crate::WithinBoxTrivial;
     pub use crate::WithinUniquePtr;